@@ -2,6 +2,7 @@
 
 use crate::apu::Apu;
 use crate::bus::Bus;
+use crate::camera::CameraSensor;
 use crate::cartridge::{Cartridge, SoftwareType};
 use crate::cgb::CgbRegisters;
 use crate::dma::DmaUnit;
@@ -10,12 +11,15 @@ use crate::inputs::InputState;
 use crate::interrupts::InterruptRegisters;
 use crate::memory::Memory;
 use crate::ppu::Ppu;
+use crate::printer::{GameBoyPrinter, PrintedImage};
 use crate::serial::SerialPort;
+use crate::sgb::{SgbJoypadPort, SgbPacket};
 use crate::sm83::Sm83;
 use crate::timer::GbTimer;
 use crate::{HardwareMode, audio, ppu};
 use bincode::{Decode, Encode};
 use gb_config::{GameBoyButton, GameBoyInputs, GbAspectRatio, GbAudioResampler, GbPalette};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, Color, ColorCorrection, EmulatorConfigTrait, EmulatorTrait, InputPoller,
     RenderFrameOptions, Renderer, SaveWriter, TickEffect, TickResult,
@@ -49,6 +53,7 @@ pub struct GameBoyEmulatorConfig {
     pub force_dmg_mode: bool,
     pub force_cgb_mode: bool,
     pub pretend_to_be_gba: bool,
+    pub printer_connected: bool,
     pub aspect_ratio: GbAspectRatio,
     pub gb_palette: GbPalette,
     #[cfg_display(debug_fmt)]
@@ -94,6 +99,8 @@ pub struct GameBoyEmulator {
     apu: Apu,
     memory: Memory,
     serial_port: SerialPort,
+    link_cable_connected: bool,
+    printer: Option<GameBoyPrinter>,
     interrupt_registers: InterruptRegisters,
     cgb_registers: CgbRegisters,
     #[partial_clone(partial)]
@@ -150,14 +157,21 @@ impl GameBoyEmulator {
         jgenesis_common::rom::mirror_to_next_power_of_two(&mut rom);
         let cartridge = Cartridge::create(rom.into_boxed_slice(), initial_sram, save_writer)?;
 
+        let mut serial_port = SerialPort::new(hardware_mode);
+        let mut interrupt_registers = InterruptRegisters::default();
+        let printer = config.printer_connected.then(GameBoyPrinter::new);
+        serial_port.set_link_connected(printer.is_some(), &mut interrupt_registers);
+
         Ok(Self {
             hardware_mode,
             cpu: Sm83::new(hardware_mode, config.pretend_to_be_gba, boot_rom_present),
             ppu,
             apu: Apu::new(config, hardware_mode),
             memory,
-            serial_port: SerialPort::new(hardware_mode),
-            interrupt_registers: InterruptRegisters::default(),
+            serial_port,
+            link_cable_connected: false,
+            printer,
+            interrupt_registers,
             cgb_registers: CgbRegisters::new(),
             cartridge,
             timer: GbTimer::new(),
@@ -192,12 +206,266 @@ impl GameBoyEmulator {
     pub fn is_cgb_mode(&self) -> bool {
         self.hardware_mode == HardwareMode::Cgb
     }
+
+    /// Connect or disconnect a link cable.
+    ///
+    /// While connected, serial transfers on the internal clock do not complete until the other
+    /// Game Boy has responded to every clock pulse. Clock pulses must be delivered using
+    /// [`Self::pop_link_cable_pulse`], [`Self::receive_link_cable_pulse`], and
+    /// [`Self::receive_link_cable_response`].
+    ///
+    /// Has no effect while a Game Boy Printer is attached.
+    pub fn set_link_cable_connected(&mut self, connected: bool) {
+        self.link_cable_connected = connected;
+        self.update_serial_connection();
+    }
+
+    fn update_serial_connection(&mut self) {
+        let connected = self.link_cable_connected || self.printer.is_some();
+        self.serial_port.set_link_connected(connected, &mut self.interrupt_registers);
+    }
+
+    fn update_printer(&mut self) {
+        let Some(printer) = &mut self.printer else { return };
+
+        let link_clock = self.serial_port.link_clock();
+        while let Some(bit) = self.serial_port.pop_outgoing_pulse() {
+            let response = printer.receive_clock_pulse(bit, link_clock);
+            self.serial_port.receive_response(response, &mut self.interrupt_registers);
+        }
+    }
+
+    /// Take the oldest page printed by the Game Boy Printer, if a printer is attached and has
+    /// finished printing a page since the last call.
+    pub fn take_printed_image(&mut self) -> Option<PrintedImage> {
+        self.printer.as_mut().and_then(GameBoyPrinter::take_printed_image)
+    }
+
+    /// Update the image in front of the Game Boy Camera sensor. Does nothing if the cartridge is
+    /// not a Game Boy Camera.
+    ///
+    /// This should be called once per frame; photos use the most recent image.
+    pub fn update_camera_sensor<C: CameraSensor>(&mut self, sensor: &mut C) {
+        if let Some(image) = self.cartridge.camera_image_mut() {
+            sensor.capture(image);
+        }
+    }
+
+    /// Cycle counter for running two linked Game Boys in lockstep. This advances at 2.097152 MHz
+    /// regardless of whether the CPU is in double speed mode.
+    #[inline]
+    #[must_use]
+    pub fn link_clock(&self) -> u64 {
+        self.serial_port.link_clock()
+    }
+
+    /// Take the oldest internal clock pulse that has not yet been sent to the other Game Boy.
+    /// The returned value is the data bit sent along with the pulse.
+    pub fn pop_link_cable_pulse(&mut self) -> Option<bool> {
+        self.serial_port.pop_outgoing_pulse()
+    }
+
+    /// Receive a clock pulse and data bit from the other Game Boy. Returns the data bit to send
+    /// back in response.
+    pub fn receive_link_cable_pulse(&mut self, bit: bool) -> bool {
+        self.serial_port.receive_clock_pulse(bit, &mut self.interrupt_registers)
+    }
+
+    /// Receive the other Game Boy's response to the oldest unanswered clock pulse.
+    pub fn receive_link_cable_response(&mut self, bit: bool) {
+        self.serial_port.receive_response(bit, &mut self.interrupt_registers);
+    }
+
+    fn execute_instruction(&mut self) {
+        self.cpu.execute_instruction(&mut Bus {
+            hardware_mode: self.hardware_mode,
+            ppu: &mut self.ppu,
+            apu: &mut self.apu,
+            memory: &mut self.memory,
+            serial_port: &mut self.serial_port,
+            cartridge: &mut self.cartridge,
+            interrupt_registers: &mut self.interrupt_registers,
+            cgb_registers: &mut self.cgb_registers,
+            timer: &mut self.timer,
+            dma_unit: &mut self.dma_unit,
+            input_state: &mut self.input_state,
+        });
+
+        self.update_printer();
+
+        self.input_state.check_for_joypad_interrupt(&mut self.interrupt_registers);
+    }
+}
+
+// Super Game Boy interface, used by the SNES core's ICD2 emulation.
+//
+// In the SGB, the SNES drives the Game Boy's clock, reads the LCD output line-by-line, supplies
+// joypad inputs, and receives command packets sent through the JOYP register. Rendering, audio
+// output, and save file persistence are all handled by the SNES side.
+impl GameBoyEmulator {
+    /// Create a Game Boy that is embedded in a Super Game Boy. The SGB always runs in DMG mode
+    /// and has no separate Game Boy boot ROM.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if it cannot load the ROM (e.g. unsupported mapper).
+    pub fn create_super_game_boy<S: SaveWriter>(
+        rom: Vec<u8>,
+        save_writer: &mut S,
+    ) -> Result<Self, GameBoyLoadError> {
+        // Video settings don't matter because the SNES reads raw shades from the LCD output
+        let config = GameBoyEmulatorConfig {
+            force_dmg_mode: true,
+            force_cgb_mode: false,
+            pretend_to_be_gba: false,
+            printer_connected: false,
+            aspect_ratio: GbAspectRatio::default(),
+            gb_palette: GbPalette::default(),
+            gb_custom_palette: [(0, 0, 0); 4],
+            gbc_color_correction: ColorCorrection::default(),
+            frame_blending: false,
+            audio_resampler: GbAudioResampler::default(),
+            audio_60hz_hack: false,
+        };
+        let boot_roms = BootRoms { dmg: None, cgb: None };
+
+        let mut emulator = Self::create(rom, boot_roms, config, save_writer)?;
+        emulator.cpu = Sm83::new_super_game_boy();
+        emulator.input_state = InputState::new_sgb();
+
+        Ok(emulator)
+    }
+
+    /// Execute a single CPU instruction. Returns the number of Game Boy clock cycles elapsed.
+    pub fn sgb_step(&mut self) -> u64 {
+        let prev_link_clock = self.serial_port.link_clock();
+
+        self.execute_instruction();
+
+        if self.ppu.frame_complete() {
+            self.ppu.clear_frame_complete();
+            self.cartridge.apply_cheat_ram_writes(&mut self.memory);
+            self.cartridge.update_rtc_time();
+        }
+
+        // Link clock advances at half the CPU clock rate
+        2 * (self.serial_port.link_clock() - prev_link_clock)
+    }
+
+    /// Reset everything except the cartridge, as when the ICD2 asserts the Game Boy's reset line.
+    ///
+    /// # Panics
+    ///
+    /// Should never panic; memory initialization can only fail if given an invalid boot ROM.
+    pub fn sgb_reset(&mut self) {
+        self.cpu = Sm83::new_super_game_boy();
+        self.ppu = Ppu::new(HardwareMode::Dmg, self.cartridge.rom(), false);
+        self.memory = Memory::new(None, HardwareMode::Dmg)
+            .expect("Creating memory without a boot ROM should never fail");
+        self.serial_port = SerialPort::new(HardwareMode::Dmg);
+        self.interrupt_registers = InterruptRegisters::default();
+        self.cgb_registers = CgbRegisters::new();
+        self.timer = GbTimer::new();
+        self.dma_unit = DmaUnit::new(HardwareMode::Dmg);
+        self.input_state = InputState::new_sgb();
+        self.update_serial_connection();
+
+        // Powering off the APU resets all of its registers
+        self.apu.write_register(0xFF26, 0x00);
+    }
+
+    /// Current LCD line (LY). Lines 0-143 are visible.
+    #[inline]
+    #[must_use]
+    pub fn sgb_scanline(&self) -> u8 {
+        self.ppu.scanline()
+    }
+
+    /// Shades (0-3) of the given visible line in the most recently rendered frame.
+    #[must_use]
+    pub fn sgb_line_shades(&self, line: u8) -> &[u16] {
+        let start = usize::from(line) * ppu::SCREEN_WIDTH;
+        &self.ppu.frame_buffer()[start..start + ppu::SCREEN_WIDTH]
+    }
+
+    /// Set the state of a joypad as written to the ICD2 joypad registers: active low, bits 0-3
+    /// are Right/Left/Up/Down and bits 4-7 are A/B/Select/Start.
+    pub fn sgb_set_joypad(&mut self, player: usize, value: u8) {
+        if let Some(sgb) = self.input_state.sgb_mut() {
+            sgb.set_joypad(player, value);
+        }
+    }
+
+    /// Set the number of connected joypads (0 = 1 player, 1 = 2 players, 3 = 4 players).
+    pub fn sgb_set_player_count(&mut self, value: u8) {
+        if let Some(sgb) = self.input_state.sgb_mut() {
+            sgb.set_player_count(value);
+        }
+    }
+
+    /// Take the oldest command packet that the Game Boy has sent through JOYP, if any.
+    pub fn sgb_take_packet(&mut self) -> Option<SgbPacket> {
+        self.input_state.sgb_mut().and_then(SgbJoypadPort::take_packet)
+    }
+
+    /// Set the Game Boy's effective clock rate, which in the SGB depends on the SNES clock and the
+    /// ICD2 clock divider.
+    pub fn sgb_set_clock_frequency(&mut self, gb_clock_frequency: f64) {
+        // The APU ticks at half of the CPU clock rate
+        self.apu.override_source_frequency(gb_clock_frequency / 2.0);
+    }
+
+    /// Push all pending audio samples to the given audio output.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any error returned by the audio output.
+    pub fn sgb_drain_audio_samples<A: AudioOutput>(
+        &mut self,
+        audio_output: &mut A,
+    ) -> Result<(), A::Err> {
+        self.apu.drain_samples_into(audio_output)
+    }
+
+    #[must_use]
+    pub fn has_battery(&self) -> bool {
+        self.cartridge.has_battery()
+    }
+
+    #[must_use]
+    pub fn sram(&self) -> &[u8] {
+        self.cartridge.sram()
+    }
+
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no battery.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        self.has_battery().then(|| self.sram())
+    }
+
+    /// Returns whether SRAM has been written since the last call.
+    pub fn get_and_clear_sram_dirty(&mut self) -> bool {
+        self.cartridge.get_and_clear_sram_dirty()
+    }
+
+    /// Persist the cartridge's RTC state, if the cartridge has an RTC.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any error returned by the save writer.
+    pub fn save_rtc_state<S: SaveWriter>(&mut self, save_writer: &mut S) -> Result<(), S::Err> {
+        self.cartridge.save_rtc_state(save_writer)
+    }
 }
 
 impl EmulatorTrait for GameBoyEmulator {
     type Button = GameBoyButton;
     type Inputs = GameBoyInputs;
     type Config = GameBoyEmulatorConfig;
+
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::GameBoy;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -217,26 +485,14 @@ impl EmulatorTrait for GameBoyEmulator {
         I: InputPoller<Self::Inputs>,
         S: SaveWriter,
     {
-        self.input_state.set_inputs(*input_poller.poll());
+        let inputs = *input_poller.poll();
+        self.input_state.set_inputs(inputs);
+        self.cartridge.update_inputs(&inputs);
 
-        self.cpu.execute_instruction(&mut Bus {
-            hardware_mode: self.hardware_mode,
-            ppu: &mut self.ppu,
-            apu: &mut self.apu,
-            memory: &mut self.memory,
-            serial_port: &mut self.serial_port,
-            cartridge: &mut self.cartridge,
-            interrupt_registers: &mut self.interrupt_registers,
-            cgb_registers: &mut self.cgb_registers,
-            timer: &mut self.timer,
-            dma_unit: &mut self.dma_unit,
-            input_state: &mut self.input_state,
-        });
+        self.execute_instruction();
 
         self.apu.drain_samples_into(audio_output).map_err(GameBoyError::Audio)?;
 
-        self.input_state.check_for_joypad_interrupt(&mut self.interrupt_registers);
-
         if self.ppu.frame_complete() {
             self.ppu.clear_frame_complete();
             self.rgba_buffer.copy_from(self.ppu.frame_buffer(), self.hardware_mode, &self.config);
@@ -249,6 +505,8 @@ impl EmulatorTrait for GameBoyEmulator {
                 )
                 .map_err(GameBoyError::Rendering)?;
 
+            self.cartridge.apply_cheat_ram_writes(&mut self.memory);
+
             self.cartridge.update_rtc_time();
 
             if self.cartridge.has_battery()
@@ -285,12 +543,21 @@ impl EmulatorTrait for GameBoyEmulator {
     fn reload_config(&mut self, config: &Self::Config) {
         self.config = *config;
         self.apu.reload_config(*config);
+
+        if config.printer_connected != self.printer.is_some() {
+            self.printer = config.printer_connected.then(GameBoyPrinter::new);
+            self.update_serial_connection();
+        }
     }
 
     fn take_rom_from(&mut self, other: &mut Self) {
         self.cartridge.take_rom_from(&mut other.cartridge);
     }
 
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cartridge.set_cheats(cheats);
+    }
+
     fn soft_reset(&mut self) {
         log::warn!("The Game Boy does not support soft reset except in software");
     }
@@ -375,6 +375,10 @@ impl Apu {
         self.resampler.reload_config(&config);
     }
 
+    pub fn override_source_frequency(&mut self, apu_frequency: f64) {
+        self.resampler.override_source_frequency(apu_frequency);
+    }
+
     pub fn update_output_frequency(&mut self, output_frequency: u64) {
         self.resampler.update_output_frequency(output_frequency);
     }
@@ -65,6 +65,7 @@ pub struct GameBoyResampler {
     dc_offset_r: FirstOrderIirFilter,
     resampler: ResamplerImpl,
     output_frequency: u64,
+    source_frequency_override: Option<f64>,
 }
 
 impl GameBoyResampler {
@@ -75,6 +76,7 @@ impl GameBoyResampler {
             dc_offset_r: new_dc_offset_filter(),
             resampler: create_resampler(config, output_frequency),
             output_frequency,
+            source_frequency_override: None,
         }
     }
 
@@ -95,9 +97,18 @@ impl GameBoyResampler {
         if config.audio_resampler != self.resampler.resampler_type() {
             log::info!("Changing resampler type to {:?}", config.audio_resampler);
             self.resampler = create_resampler(config, self.output_frequency);
-        } else {
-            self.resampler.update_source_frequency(gb_source_frequency(config.audio_60hz_hack));
         }
+
+        let source_frequency = self
+            .source_frequency_override
+            .unwrap_or_else(|| gb_source_frequency(config.audio_60hz_hack));
+        self.resampler.update_source_frequency(source_frequency);
+    }
+
+    // Used when the Game Boy is not clocked by its own crystal, e.g. in the Super Game Boy
+    pub fn override_source_frequency(&mut self, apu_frequency: f64) {
+        self.source_frequency_override = Some(apu_frequency);
+        self.resampler.update_source_frequency(apu_frequency);
     }
 
     pub fn update_output_frequency(&mut self, output_frequency: u64) {
@@ -127,7 +127,7 @@ impl Bus<'_> {
         loop {
             self.timer.tick_m_cycle(self.interrupt_registers);
             self.dma_unit.oam_dma_tick_m_cycle(self.cartridge, self.memory, self.ppu);
-            self.serial_port.tick(self.interrupt_registers);
+            self.serial_port.tick(self.cgb_registers.speed, self.interrupt_registers);
 
             if self.cgb_registers.speed == CpuSpeed::Double {
                 self.cgb_registers.double_speed_odd_cycle =
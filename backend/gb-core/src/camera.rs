@@ -0,0 +1,18 @@
+//! Image input for the Game Boy Camera (Pocket Camera) cartridge
+//!
+//! The cartridge's M64282FP sensor captures 128x112 images. The frontend provides the scene in
+//! front of the sensor through [`CameraSensor`], and the cartridge runs the sensor's exposure, edge
+//! enhancement, and dithering steps on it when the game captures a photo.
+
+pub const CAMERA_IMAGE_WIDTH: usize = 128;
+pub const CAMERA_IMAGE_HEIGHT: usize = 112;
+pub const CAMERA_IMAGE_LEN: usize = CAMERA_IMAGE_WIDTH * CAMERA_IMAGE_HEIGHT;
+
+pub type CameraImage = [u8; CAMERA_IMAGE_LEN];
+
+/// A source of images for the Game Boy Camera sensor.
+pub trait CameraSensor {
+    /// Write the scene currently in front of the sensor to `image` as 8-bit grayscale pixels in
+    /// row-major order, where 0 is black and 255 is white.
+    fn capture(&mut self, image: &mut CameraImage);
+}
@@ -3,10 +3,20 @@
 mod mappers;
 
 use crate::api::GameBoyLoadError;
+use crate::camera::CameraImage;
+use crate::cartridge::mappers::camera::PocketCamera;
+use crate::cartridge::mappers::huc1::Huc1;
 use crate::cartridge::mappers::huc3::Huc3;
 use crate::cartridge::mappers::mbc3::Mbc3Rtc;
+use crate::cartridge::mappers::mbc6::{MBC6_FLASH_LEN, Mbc6};
+use crate::cartridge::mappers::mbc7::{MBC7_EEPROM_LEN, Mbc7};
+use crate::cartridge::mappers::mmm01::Mmm01;
+use crate::cartridge::mappers::tama5::{TAMA5_RAM_LEN, Tama5};
 use crate::cartridge::mappers::{Mbc1, Mbc2, Mbc3, Mbc5};
+use crate::memory::Memory;
 use bincode::{Decode, Encode};
+use gb_config::GameBoyInputs;
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::frontend::SaveWriter;
 use jgenesis_proc_macros::{FakeDecode, FakeEncode, PartialClone};
 use std::fmt::{Display, Formatter};
@@ -48,19 +58,33 @@ enum Mapper {
     Mbc2(Mbc2),
     Mbc3(Mbc3),
     Mbc5(Mbc5),
+    Mbc6(Mbc6),
+    Mbc7(Mbc7),
+    Mmm01(Mmm01),
+    Huc1(Huc1),
     Huc3(Huc3),
+    Tama5(Tama5),
+    PocketCamera(PocketCamera),
 }
 
 impl Mapper {
-    fn map_rom_address(&self, address: u16) -> u32 {
-        match self {
+    fn read_rom(&self, address: u16, rom: &[u8], sram: &[u8]) -> u8 {
+        let rom_addr = match self {
             Self::None => address.into(),
             Self::Mbc1(mbc1) => mbc1.map_rom_address(address),
             Self::Mbc2(mbc2) => mbc2.map_rom_address(address),
             Self::Mbc3(mbc3) => mbc3.map_rom_address(address),
             Self::Mbc5(mbc5) => mbc5.map_rom_address(address),
+            // MBC6 can map flash into the ROM address space
+            Self::Mbc6(mbc6) => return mbc6.read_rom(address, rom, sram),
+            Self::Mbc7(mbc7) => mbc7.map_rom_address(address),
+            Self::Mmm01(mmm01) => mmm01.map_rom_address(address),
+            Self::Huc1(huc1) => huc1.map_rom_address(address),
             Self::Huc3(huc3) => huc3.map_rom_address(address),
-        }
+            Self::Tama5(tama5) => tama5.map_rom_address(address),
+            Self::PocketCamera(camera) => camera.map_rom_address(address),
+        };
+        rom[rom_addr as usize]
     }
 
     fn read_ram(&self, address: u16, sram: &[u8]) -> u8 {
@@ -70,7 +94,13 @@ impl Mapper {
             Self::Mbc2(mbc2) => mbc2.read_ram(address),
             Self::Mbc3(mbc3) => mbc3.read_ram(address, sram),
             Self::Mbc5(mbc5) => mbc5.read_ram(address, sram),
+            Self::Mbc6(mbc6) => mbc6.read_ram(address, sram),
+            Self::Mbc7(mbc7) => mbc7.read_ram(address),
+            Self::Mmm01(mmm01) => mmm01.read_ram(address, sram),
+            Self::Huc1(huc1) => huc1.read_ram(address, sram),
             Self::Huc3(huc3) => huc3.read_ram(address, sram),
+            Self::Tama5(tama5) => tama5.read_ram(address),
+            Self::PocketCamera(camera) => camera.read_ram(address, sram),
         }
     }
 
@@ -85,19 +115,33 @@ impl Mapper {
             Self::Mbc2(mbc2) => mbc2.write_ram(address, value),
             Self::Mbc3(mbc3) => mbc3.write_ram(address, value, sram),
             Self::Mbc5(mbc5) => mbc5.write_ram(address, value, sram),
+            Self::Mbc6(mbc6) => mbc6.write_ram(address, value, sram),
+            Self::Mbc7(mbc7) => mbc7.write_ram(address, value, sram),
+            Self::Mmm01(mmm01) => mmm01.write_ram(address, value, sram),
+            Self::Huc1(huc1) => huc1.write_ram(address, value, sram),
             Self::Huc3(huc3) => huc3.write_ram(address, value, sram),
+            Self::Tama5(tama5) => tama5.write_ram(address, value, sram),
+            Self::PocketCamera(camera) => camera.write_ram(address, value, sram),
         }
     }
 
-    fn write_rom_address(&mut self, address: u16, value: u8) {
+    // Returns whether SRAM was modified, which is only possible with MBC6 flash
+    fn write_rom_address(&mut self, address: u16, value: u8, sram: &mut [u8]) -> bool {
         match self {
-            Self::None => {}
+            Self::None | Self::Tama5(..) => {}
             Self::Mbc1(mbc1) => mbc1.write_rom_address(address, value),
             Self::Mbc2(mbc2) => mbc2.write_rom_address(address, value),
             Self::Mbc3(mbc3) => mbc3.write_rom_address(address, value),
             Self::Mbc5(mbc5) => mbc5.write_rom_address(address, value),
+            Self::Mbc6(mbc6) => return mbc6.write_rom_address(address, value, sram),
+            Self::Mbc7(mbc7) => mbc7.write_rom_address(address, value),
+            Self::Mmm01(mmm01) => mmm01.write_rom_address(address, value),
+            Self::Huc1(huc1) => huc1.write_rom_address(address, value),
             Self::Huc3(huc3) => huc3.write_rom_address(address, value),
+            Self::PocketCamera(camera) => camera.write_rom_address(address, value),
         }
+
+        false
     }
 
     fn mapper_type(&self) -> &'static str {
@@ -107,7 +151,13 @@ impl Mapper {
             Self::Mbc2(..) => "MBC2",
             Self::Mbc3(..) => "MBC3",
             Self::Mbc5(..) => "MBC5",
+            Self::Mbc6(..) => "MBC6",
+            Self::Mbc7(..) => "MBC7",
+            Self::Mmm01(..) => "MMM01",
+            Self::Huc1(..) => "HuC-1",
             Self::Huc3(..) => "HuC-3",
+            Self::Tama5(..) => "TAMA5",
+            Self::PocketCamera(..) => "Pocket Camera",
         }
     }
 }
@@ -141,6 +191,22 @@ impl Display for SoftwareType {
     }
 }
 
+// MMM01 multicarts boot into a menu in the last 32KB of ROM, and the cartridge header at the start
+// of ROM belongs to the first game. Check the menu's header first, requiring that it contains the
+// same logo as the first header to avoid false positives
+fn header_address(rom: &[u8]) -> usize {
+    match rom.len().checked_sub(0x8000) {
+        Some(menu_addr)
+            if menu_addr != 0
+                && (0x0B..=0x0D).contains(&rom[menu_addr + 0x0147])
+                && rom[menu_addr + 0x0104..menu_addr + 0x0134] == rom[0x0104..0x0134] =>
+        {
+            menu_addr
+        }
+        _ => 0,
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode, PartialClone)]
 pub struct Cartridge {
     #[partial_clone(default)]
@@ -149,6 +215,8 @@ pub struct Cartridge {
     mapper: Mapper,
     has_battery: bool,
     sram_dirty: bool,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl Cartridge {
@@ -157,28 +225,37 @@ impl Cartridge {
         initial_sram: Option<Vec<u8>>,
         save_writer: &mut S,
     ) -> Result<Self, GameBoyLoadError> {
-        // Cartridge type is always at $0147 in ROM
-        let mapper_byte = rom[0x0147];
-        let is_mbc2 = mapper_byte == 0x05 || mapper_byte == 0x06;
+        let header_addr = header_address(&rom);
+
+        // Cartridge type is always at $0147 in the header
+        let mapper_byte = rom[header_addr + 0x0147];
 
-        let sram_len = if is_mbc2 {
+        let sram_len = match mapper_byte {
             // MBC2 has a fixed 512x4 bits of RAM
-            mappers::MBC2_RAM_LEN
-        } else {
-            // The byte at $0149 in the ROM header indicates SRAM size
-            let sram_len_byte = rom[0x0149];
-            match sram_len_byte {
-                0x00 => 0,
-                0x01 => {
-                    // $01 (2KB) is an unofficial value used by some homebrew/bootlegs
-                    log::warn!("Cartridge header reports 2KB of SRAM ($01); this is unusual");
-                    2 * 1024
-                }
-                0x02 => 8 * 1024,
-                0x03 => 32 * 1024,
-                0x04 => 128 * 1024,
-                0x05 => 64 * 1024,
-                _ => return Err(GameBoyLoadError::InvalidSramByte(sram_len_byte)),
+            0x05 | 0x06 => mappers::MBC2_RAM_LEN,
+            // MBC7 has an EEPROM instead of RAM
+            0x22 => MBC7_EEPROM_LEN,
+            // TAMA5 RAM is inside the TAMA6 microcontroller
+            0xFD => TAMA5_RAM_LEN,
+            _ => {
+                // The byte at $0149 in the ROM header indicates SRAM size
+                let sram_len_byte = rom[header_addr + 0x0149];
+                let ram_len = match sram_len_byte {
+                    0x00 => 0,
+                    0x01 => {
+                        // $01 (2KB) is an unofficial value used by some homebrew/bootlegs
+                        log::warn!("Cartridge header reports 2KB of SRAM ($01); this is unusual");
+                        2 * 1024
+                    }
+                    0x02 => 8 * 1024,
+                    0x03 => 32 * 1024,
+                    0x04 => 128 * 1024,
+                    0x05 => 64 * 1024,
+                    _ => return Err(GameBoyLoadError::InvalidSramByte(sram_len_byte)),
+                };
+
+                // MBC6 flash is stored after RAM
+                if mapper_byte == 0x20 { ram_len + MBC6_FLASH_LEN } else { ram_len }
             }
         };
 
@@ -220,11 +297,28 @@ impl Cartridge {
 
                 (mapper, has_battery)
             }
+            0x0B..=0x0D => {
+                let mapper = Mapper::Mmm01(Mmm01::new(rom.len() as u32, sram_len as u32));
+                let has_battery = mapper_byte == 0x0D;
+
+                (mapper, has_battery)
+            }
+            0x20 => {
+                let ram_len = sram_len - MBC6_FLASH_LEN;
+                (Mapper::Mbc6(Mbc6::new(rom.len() as u32, ram_len as u32)), true)
+            }
+            0x22 => (Mapper::Mbc7(Mbc7::new(rom.len() as u32)), true),
+            0xFC => {
+                let camera = PocketCamera::new(rom.len() as u32, sram_len as u32);
+                (Mapper::PocketCamera(camera), true)
+            }
+            0xFD => (Mapper::Tama5(Tama5::new(rom.len() as u32)), true),
             0xFE => {
                 let rtc = save_writer.load_serialized("rtc").ok();
                 let mapper = Mapper::Huc3(Huc3::new(rom.len() as u32, sram_len as u32, rtc));
                 (mapper, true)
             }
+            0xFF => (Mapper::Huc1(Huc1::new(rom.len() as u32, sram_len as u32)), true),
             _ => return Err(GameBoyLoadError::UnsupportedMapperByte(mapper_byte)),
         };
 
@@ -236,16 +330,19 @@ impl Cartridge {
             mapper,
             has_battery,
             sram_dirty: true,
+            cheats: CheatSet::default(),
         })
     }
 
     pub fn read_rom(&self, address: u16) -> u8 {
-        let rom_addr = self.mapper.map_rom_address(address);
-        self.rom[rom_addr as usize]
+        let value = self.mapper.read_rom(address, &self.rom, &self.sram);
+        self.cheats.patch_rom_u8(address.into(), value)
     }
 
     pub fn write_rom(&mut self, address: u16, value: u8) {
-        self.mapper.write_rom_address(address, value);
+        if self.mapper.write_rom_address(address, value, &mut self.sram) {
+            self.sram_dirty = true;
+        }
     }
 
     pub fn read_ram(&self, address: u16) -> u8 {
@@ -257,12 +354,48 @@ impl Cartridge {
         self.sram_dirty = true;
     }
 
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
     pub fn take_rom(&mut self) -> Vec<u8> {
         mem::take(&mut self.rom.0).into_vec()
     }
 
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.rom = mem::take(&mut other.rom);
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    pub fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    // Cheat RAM writes should be applied once per frame. SRAM is only marked dirty if a write
+    // changes its contents, so that a cheat does not cause the save file to be rewritten every frame
+    pub fn apply_cheat_ram_writes(&mut self, memory: &mut Memory) {
+        for &RamWrite { address, value, bank } in self.cheats.ram_writes() {
+            let address = address as u16;
+            match (address, bank) {
+                (0xA000..=0xBFFF, Some(bank)) => {
+                    let sram_addr = (usize::from(bank) << 13) | usize::from(address & 0x1FFF);
+                    if let Some(sram_value) = self.sram.get_mut(sram_addr)
+                        && *sram_value != value
+                    {
+                        *sram_value = value;
+                        self.sram_dirty = true;
+                    }
+                }
+                (0xA000..=0xBFFF, None) if self.mapper.read_ram(address, &self.sram) != value => {
+                    self.mapper.write_ram(address, value, &mut self.sram);
+                    self.sram_dirty = true;
+                }
+                (0xD000..=0xDFFF, Some(bank)) => memory.write_main_ram_bank(bank, address, value),
+                (0xC000..=0xFDFF, _) => memory.write_main_ram(address, value),
+                (0xFF80..=0xFFFE, _) => memory.write_hram(address, value),
+                _ => {}
+            }
+        }
     }
 
     pub fn has_battery(&self) -> bool {
@@ -298,9 +431,72 @@ impl Cartridge {
         Ok(())
     }
 
+    pub fn update_inputs(&mut self, inputs: &GameBoyInputs) {
+        if let Mapper::Mbc7(mbc7) = &mut self.mapper {
+            mbc7.update_tilt(inputs);
+        }
+    }
+
+    pub fn camera_image_mut(&mut self) -> Option<&mut CameraImage> {
+        match &mut self.mapper {
+            Mapper::PocketCamera(camera) => Some(camera.image_mut()),
+            _ => None,
+        }
+    }
+
     pub fn tick_cpu(&mut self) {
-        if let Mapper::Huc3(huc3) = &mut self.mapper {
-            huc3.tick_cpu();
+        match &mut self.mapper {
+            Mapper::Huc3(huc3) => huc3.tick_cpu(),
+            Mapper::PocketCamera(camera) => self.sram_dirty |= camera.tick_cpu(&mut self.sram),
+            _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGO: [u8; 0x30] = [0xCE; 0x30];
+
+    fn test_rom(len: usize, mapper_byte: u8) -> Vec<u8> {
+        let mut rom = vec![0; len];
+        rom[0x0104..0x0134].copy_from_slice(&LOGO);
+        rom[0x0147] = mapper_byte;
+        rom
+    }
+
+    fn set_menu_header(rom: &mut [u8], mapper_byte: u8, logo: [u8; 0x30]) {
+        let menu_addr = rom.len() - 0x8000;
+        rom[menu_addr + 0x0104..menu_addr + 0x0134].copy_from_slice(&logo);
+        rom[menu_addr + 0x0147] = mapper_byte;
+    }
+
+    #[test]
+    fn mmm01_header_in_menu() {
+        let mut rom = test_rom(0x100000, 0x01);
+        set_menu_header(&mut rom, 0x0D, LOGO);
+        assert_eq!(header_address(&rom), 0xF8000);
+    }
+
+    #[test]
+    fn mmm01_header_requires_matching_logo() {
+        let mut rom = test_rom(0x100000, 0x01);
+        set_menu_header(&mut rom, 0x0D, [0; 0x30]);
+        assert_eq!(header_address(&rom), 0);
+    }
+
+    #[test]
+    fn mmm01_header_requires_mmm01_mapper_byte() {
+        let mut rom = test_rom(0x100000, 0x01);
+        set_menu_header(&mut rom, 0x1B, LOGO);
+        assert_eq!(header_address(&rom), 0);
+    }
+
+    #[test]
+    fn header_at_start_of_32kb_rom() {
+        // The last 32KB of a 32KB ROM is the first header, so it is never treated as a menu
+        let rom = test_rom(0x8000, 0x0B);
+        assert_eq!(header_address(&rom), 0);
+    }
+}
@@ -1,5 +1,11 @@
+pub mod camera;
+pub mod huc1;
 pub mod huc3;
 pub mod mbc3;
+pub mod mbc6;
+pub mod mbc7;
+pub mod mmm01;
+pub mod tama5;
 
 use crate::cartridge::HasBasicRamMapping;
 use crate::cartridge::mappers::mbc3::Mbc3Rtc;
@@ -345,7 +351,7 @@ impl HasBasicRamMapping for Mbc5 {
     }
 }
 
-// MBC2 / MBC3 / MBC5
+// MBC2 / MBC3 / MBC5 / MBC7 / HuC-1 / HuC-3 / TAMA5 / Pocket Camera
 fn basic_map_rom_address(
     address: u16,
     rom_bank: u32,
@@ -362,7 +368,7 @@ fn basic_map_rom_address(
     }
 }
 
-// MBC3 / MBC5
+// MBC3 / MBC5 / HuC-1 / HuC-3 / Pocket Camera
 fn basic_map_ram_address(
     ram_enabled: bool,
     address: u16,
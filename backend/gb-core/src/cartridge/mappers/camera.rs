@@ -0,0 +1,413 @@
+//! Game Boy Camera (Pocket Camera) mapper
+//!
+//! Setting bit 4 of the RAM bank register maps the M64282FP sensor registers to $A000-$BFFF
+//! instead of RAM. Captured photos are written to RAM bank 0 at $A100 as 16x14 tiles.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/Gameboy_Camera.html>
+
+use crate::camera::{CAMERA_IMAGE_HEIGHT, CAMERA_IMAGE_LEN, CAMERA_IMAGE_WIDTH, CameraImage};
+use crate::cartridge::mappers::{basic_map_ram_address, basic_map_rom_address};
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+const NUM_REGISTERS: usize = 0x36;
+
+// $A006-$A035: 4x4 matrix of 3 thresholds each
+const DITHER_MATRIX_START: usize = 0x06;
+
+const IMAGE_RAM_ADDR: usize = 0x0100;
+
+// Edge enhancement ratios in units of 1/4: 0.5, 0.75, 1, 1.25, 2, 3, 4, 5
+const EDGE_RATIO_QUARTERS: [i32; 8] = [2, 3, 4, 5, 8, 12, 16, 20];
+
+// Exposure time register value that passes through the source image unchanged
+const NEUTRAL_EXPOSURE: u32 = 0x1000;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct PocketCamera {
+    rom_bank: u8,
+    rom_addr_mask: u32,
+    ram_bank: u8,
+    ram_addr_mask: u32,
+    ram_write_enabled: bool,
+    registers_mapped: bool,
+    registers: [u8; NUM_REGISTERS],
+    capture_cycles_remaining: u32,
+    image: Box<CameraImage>,
+}
+
+impl PocketCamera {
+    pub fn new(rom_len: u32, ram_len: u32) -> Self {
+        Self {
+            rom_bank: 1,
+            rom_addr_mask: rom_len - 1,
+            ram_bank: 0,
+            ram_addr_mask: ram_len.saturating_sub(1),
+            ram_write_enabled: false,
+            registers_mapped: false,
+            registers: [0; NUM_REGISTERS],
+            capture_cycles_remaining: 0,
+            // Flat gray until the frontend provides an image
+            image: vec![0x80; CAMERA_IMAGE_LEN].into_boxed_slice().try_into().unwrap(),
+        }
+    }
+
+    pub fn map_rom_address(&self, address: u16) -> u32 {
+        basic_map_rom_address(address, self.rom_bank.into(), true, self.rom_addr_mask)
+    }
+
+    pub fn write_rom_address(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                // RAM can always be read, but can only be written while enabled
+                self.ram_write_enabled = value & 0x0F == 0x0A;
+            }
+            0x2000..=0x3FFF => {
+                // ROM bank
+                self.rom_bank = value & 0x3F;
+            }
+            0x4000..=0x5FFF => {
+                // RAM bank / camera registers select
+                self.registers_mapped = value.bit(4);
+                self.ram_bank = value & 0x0F;
+            }
+            0x6000..=0x7FFF => {}
+            0x8000..=0xFFFF => panic!("Invalid ROM address: {address:04X}"),
+        }
+    }
+
+    pub fn image_mut(&mut self) -> &mut CameraImage {
+        &mut self.image
+    }
+
+    pub fn read_ram(&self, address: u16, sram: &[u8]) -> u8 {
+        if self.registers_mapped {
+            // All registers except $A000 are write-only
+            return if address & 0x7F == 0 {
+                (self.registers[0] & 0x06) | u8::from(self.capture_cycles_remaining != 0)
+            } else {
+                0x00
+            };
+        }
+
+        basic_map_ram_address(true, address, self.ram_bank.into(), self.ram_addr_mask)
+            .map_or(0xFF, |ram_addr| sram[ram_addr as usize])
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, sram: &mut [u8]) {
+        if self.registers_mapped {
+            self.write_register((address & 0x7F) as usize, value);
+            return;
+        }
+
+        if !self.ram_write_enabled {
+            return;
+        }
+
+        if let Some(ram_addr) =
+            basic_map_ram_address(true, address, self.ram_bank.into(), self.ram_addr_mask)
+        {
+            sram[ram_addr as usize] = value;
+        }
+    }
+
+    fn write_register(&mut self, register: usize, value: u8) {
+        if register >= NUM_REGISTERS {
+            return;
+        }
+
+        log::trace!("Camera register write: {register:02X} {value:02X}");
+
+        if register != 0 {
+            self.registers[register] = value;
+            return;
+        }
+
+        self.registers[0] = value & 0x07;
+        if value.bit(0) && self.capture_cycles_remaining == 0 {
+            self.capture_cycles_remaining = self.capture_cycles();
+            log::debug!("Camera capture started; {} cycles", self.capture_cycles_remaining);
+        } else if !value.bit(0) {
+            // Clearing bit 0 aborts an in-progress capture
+            self.capture_cycles_remaining = 0;
+        }
+    }
+
+    fn exposure(&self) -> u32 {
+        u16::from_be_bytes([self.registers[2], self.registers[3]]).into()
+    }
+
+    // Capture duration in M-cycles, from pandocs
+    fn capture_cycles(&self) -> u32 {
+        let n_bit_cycles = if self.registers[1].bit(7) { 0 } else { 512 };
+        32446 + n_bit_cycles + 16 * self.exposure()
+    }
+
+    // Returns whether SRAM was modified
+    pub fn tick_cpu(&mut self, sram: &mut [u8]) -> bool {
+        if self.capture_cycles_remaining == 0 {
+            return false;
+        }
+
+        self.capture_cycles_remaining -= 1;
+        if self.capture_cycles_remaining != 0 {
+            return false;
+        }
+
+        self.registers[0] &= !1;
+        self.process_image(sram);
+        true
+    }
+
+    fn exposed_pixel(&self, x: i32, y: i32) -> i32 {
+        let x = x.clamp(0, CAMERA_IMAGE_WIDTH as i32 - 1) as usize;
+        let y = y.clamp(0, CAMERA_IMAGE_HEIGHT as i32 - 1) as usize;
+        let pixel = u32::from(self.image[y * CAMERA_IMAGE_WIDTH + x]);
+
+        let exposed = (pixel * self.exposure() / NEUTRAL_EXPOSURE).min(255) as i32;
+        if self.registers[4].bit(3) { 255 - exposed } else { exposed }
+    }
+
+    fn process_image(&self, sram: &mut [u8]) {
+        let Some(image_ram) = sram.get_mut(IMAGE_RAM_ADDR..IMAGE_RAM_ADDR + CAMERA_IMAGE_LEN / 4)
+        else {
+            return;
+        };
+        image_ram.fill(0);
+
+        let edge_mode = self.registers[1] >> 5;
+        let edge_ratio = EDGE_RATIO_QUARTERS[usize::from((self.registers[4] >> 4) & 0x07)];
+
+        for y in 0..CAMERA_IMAGE_HEIGHT {
+            for x in 0..CAMERA_IMAGE_WIDTH {
+                let (xi, yi) = (x as i32, y as i32);
+                let pixel = self.exposed_pixel(xi, yi);
+
+                // Bit 7 is N (exclusive edge mode) and bits 5-6 are VH (edge directions)
+                let edge = match edge_mode {
+                    0b111 => {
+                        4 * pixel
+                            - self.exposed_pixel(xi - 1, yi)
+                            - self.exposed_pixel(xi + 1, yi)
+                            - self.exposed_pixel(xi, yi - 1)
+                            - self.exposed_pixel(xi, yi + 1)
+                    }
+                    0b001 | 0b101 => {
+                        2 * pixel - self.exposed_pixel(xi - 1, yi) - self.exposed_pixel(xi + 1, yi)
+                    }
+                    0b010 | 0b110 => {
+                        2 * pixel - self.exposed_pixel(xi, yi - 1) - self.exposed_pixel(xi, yi + 1)
+                    }
+                    _ => 0,
+                };
+                let value = (pixel + edge * edge_ratio / 4).clamp(0, 255);
+
+                let matrix_idx = DITHER_MATRIX_START + 3 * ((x & 3) + 4 * (y & 3));
+                let thresholds = &self.registers[matrix_idx..matrix_idx + 3];
+                // Color is 3 (darkest) below the first threshold and 0 above all thresholds
+                let color = thresholds
+                    .iter()
+                    .position(|&threshold| value < threshold.into())
+                    .map_or(0, |i| 3 - i as u8);
+
+                let tile = (y / 8) * (CAMERA_IMAGE_WIDTH / 8) + x / 8;
+                let byte_idx = 16 * tile + 2 * (y % 8);
+                let bit = 7 - (x % 8);
+                image_ram[byte_idx] |= (color & 1) << bit;
+                image_ram[byte_idx + 1] |= (color >> 1) << bit;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_LEN: u32 = 1024 * 1024;
+    const RAM_LEN: u32 = 128 * 1024;
+
+    struct TestCamera {
+        camera: PocketCamera,
+        sram: Vec<u8>,
+    }
+
+    impl TestCamera {
+        fn new() -> Self {
+            let mut camera = PocketCamera::new(ROM_LEN, RAM_LEN);
+            camera.write_rom_address(0x4000, 0x10);
+
+            let mut camera = Self { camera, sram: vec![0; RAM_LEN as usize] };
+
+            // Neutral exposure
+            camera.write_register(0x02, 0x10);
+            camera.write_register(0x03, 0x00);
+
+            camera
+        }
+
+        fn write_register(&mut self, register: u16, value: u8) {
+            self.camera.write_ram(0xA000 | register, value, &mut self.sram);
+        }
+
+        fn set_dither_thresholds(&mut self, x: usize, y: usize, thresholds: [u8; 3]) {
+            let register = DITHER_MATRIX_START + 3 * (x + 4 * y);
+            for (i, threshold) in thresholds.into_iter().enumerate() {
+                self.write_register((register + i) as u16, threshold);
+            }
+        }
+
+        fn set_all_dither_thresholds(&mut self, thresholds: [u8; 3]) {
+            for y in 0..4 {
+                for x in 0..4 {
+                    self.set_dither_thresholds(x, y, thresholds);
+                }
+            }
+        }
+
+        fn capture(&mut self) {
+            self.write_register(0x00, 0x01);
+
+            let mut cycles = 0;
+            while !self.camera.tick_cpu(&mut self.sram) {
+                cycles += 1;
+                assert!(cycles < 1_000_000, "Capture never completed");
+            }
+        }
+
+        fn pixel_color(&self, x: usize, y: usize) -> u8 {
+            let tile = (y / 8) * (CAMERA_IMAGE_WIDTH / 8) + x / 8;
+            let byte_idx = IMAGE_RAM_ADDR + 16 * tile + 2 * (y % 8);
+            let bit = 7 - (x % 8) as u8;
+            u8::from(self.sram[byte_idx].bit(bit))
+                | (u8::from(self.sram[byte_idx + 1].bit(bit)) << 1)
+        }
+    }
+
+    #[test]
+    fn capture_status() {
+        let mut camera = TestCamera::new();
+        camera.write_register(0x01, 0x80);
+        camera.write_register(0x02, 0x00);
+        camera.write_register(0x03, 0x10);
+
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x00);
+
+        camera.write_register(0x00, 0x07);
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x07);
+
+        // Registers other than $A000 are write-only
+        assert_eq!(camera.camera.read_ram(0xA001, &camera.sram), 0x00);
+
+        // Capture takes 32446 + 16 * exposure cycles when the N bit is set
+        for _ in 0..32446 + 16 * 0x10 - 1 {
+            assert!(!camera.camera.tick_cpu(&mut camera.sram));
+        }
+        assert!(camera.camera.tick_cpu(&mut camera.sram));
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x06);
+    }
+
+    #[test]
+    fn capture_abort() {
+        let mut camera = TestCamera::new();
+
+        camera.write_register(0x00, 0x01);
+        camera.write_register(0x00, 0x00);
+
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x00);
+        assert!(!camera.camera.tick_cpu(&mut camera.sram));
+    }
+
+    #[test]
+    fn ram_write_enable() {
+        let mut camera = TestCamera::new();
+        camera.camera.write_rom_address(0x4000, 0x01);
+
+        camera.camera.write_ram(0xA000, 0x12, &mut camera.sram);
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x00);
+
+        camera.camera.write_rom_address(0x0000, 0x0A);
+        camera.camera.write_ram(0xA000, 0x12, &mut camera.sram);
+        assert_eq!(camera.sram[0x2000], 0x12);
+        assert_eq!(camera.camera.read_ram(0xA000, &camera.sram), 0x12);
+    }
+
+    #[test]
+    fn dither_thresholds() {
+        let mut camera = TestCamera::new();
+        camera.set_all_dither_thresholds([0x40, 0x90, 0xC0]);
+
+        // Source image is flat $80 gray, which is between the first and second thresholds
+        camera.capture();
+
+        assert!(
+            (0..CAMERA_IMAGE_HEIGHT)
+                .all(|y| { (0..CAMERA_IMAGE_WIDTH).all(|x| camera.pixel_color(x, y) == 2) })
+        );
+    }
+
+    #[test]
+    fn dither_matrix_position() {
+        let mut camera = TestCamera::new();
+        camera.set_all_dither_thresholds([0x00; 3]);
+        camera.set_dither_thresholds(1, 2, [0xFF; 3]);
+
+        camera.capture();
+
+        // Matrix repeats every 4 pixels in both directions
+        for (x, y) in [(1, 2), (5, 2), (1, 6), (125, 110)] {
+            assert_eq!(camera.pixel_color(x, y), 3, "({x}, {y})");
+        }
+        for (x, y) in [(0, 0), (2, 2), (1, 3), (4, 2)] {
+            assert_eq!(camera.pixel_color(x, y), 0, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn exposure_and_invert() {
+        let mut camera = TestCamera::new();
+        camera.set_all_dither_thresholds([0x7F, 0x80, 0x81]);
+
+        // $80 -> inverted $7F
+        camera.write_register(0x04, 0x08);
+        camera.capture();
+        assert_eq!(camera.pixel_color(0, 0), 2);
+
+        // Half exposure: $80 -> $40 -> inverted $BF
+        camera.write_register(0x02, 0x08);
+        camera.capture();
+        assert_eq!(camera.pixel_color(0, 0), 0);
+
+        // Half exposure without inversion
+        camera.write_register(0x04, 0x00);
+        camera.capture();
+        assert_eq!(camera.pixel_color(0, 0), 3);
+    }
+
+    #[test]
+    fn horizontal_edge_enhancement() {
+        let mut camera = TestCamera::new();
+        camera.set_all_dither_thresholds([0x01, 0x80, 0xFF]);
+
+        // Left half dark, right half bright
+        for (i, pixel) in camera.camera.image_mut().iter_mut().enumerate() {
+            *pixel = if i % CAMERA_IMAGE_WIDTH < 64 { 0x40 } else { 0xC0 };
+        }
+
+        // Horizontal edge enhancement with a ratio of 1
+        camera.write_register(0x01, 0x20);
+        camera.write_register(0x04, 0x20);
+        camera.capture();
+
+        let colors: Vec<_> = (62..66).map(|x| camera.pixel_color(x, 50)).collect();
+        assert_eq!(colors, [2, 3, 0, 1]);
+
+        // Without edge enhancement, only the source brightness matters
+        camera.write_register(0x01, 0x00);
+        camera.capture();
+
+        let colors: Vec<_> = (62..66).map(|x| camera.pixel_color(x, 50)).collect();
+        assert_eq!(colors, [2, 2, 1, 1]);
+    }
+}
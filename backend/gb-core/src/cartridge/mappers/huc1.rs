@@ -0,0 +1,153 @@
+//! HuC-1 mapper, used by a handful of Hudson games with an infrared port such as Pokemon Card GB
+//!
+//! There is no second device to communicate with over IR, but like on real hardware the receiver
+//! sees light from the cartridge's own LED.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/HuC1.html>
+
+use crate::cartridge::mappers::{basic_map_ram_address, basic_map_rom_address};
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Huc1 {
+    rom_bank: u8,
+    rom_addr_mask: u32,
+    ram_bank: u8,
+    ram_addr_mask: u32,
+    ir_mapped: bool,
+    ir_led_on: bool,
+}
+
+impl Huc1 {
+    pub fn new(rom_len: u32, ram_len: u32) -> Self {
+        Self {
+            rom_bank: 1,
+            rom_addr_mask: rom_len - 1,
+            ram_bank: 0,
+            ram_addr_mask: ram_len.saturating_sub(1),
+            ir_mapped: false,
+            ir_led_on: false,
+        }
+    }
+
+    pub fn map_rom_address(&self, address: u16) -> u32 {
+        basic_map_rom_address(address, self.rom_bank.into(), false, self.rom_addr_mask)
+    }
+
+    pub fn write_rom_address(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                // $E maps the IR port to $A000-$BFFF, any other value maps RAM
+                // Unlike MBC1, RAM does not need to be explicitly enabled
+                self.ir_mapped = value & 0x0F == 0x0E;
+                log::trace!("HuC-1 IR mapped: {}", self.ir_mapped);
+            }
+            0x2000..=0x3FFF => {
+                // ROM bank
+                self.rom_bank = value & 0x3F;
+            }
+            0x4000..=0x5FFF => {
+                // RAM bank
+                self.ram_bank = value & 0x03;
+            }
+            0x6000..=0x7FFF => {
+                // No known functionality; ignore
+            }
+            0x8000..=0xFFFF => panic!("Invalid ROM address: {address:04X}"),
+        }
+    }
+
+    pub fn read_ram(&self, address: u16, sram: &[u8]) -> u8 {
+        if self.ir_mapped {
+            // $C1 means "saw light" and $C0 means "didn't see light"
+            return 0xC0 | u8::from(self.ir_led_on);
+        }
+
+        basic_map_ram_address(true, address, self.ram_bank.into(), self.ram_addr_mask)
+            .map_or(0xFF, |ram_addr| sram[ram_addr as usize])
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, sram: &mut [u8]) {
+        if self.ir_mapped {
+            // Bit 0 turns the IR LED on or off
+            self.ir_led_on = value.bit(0);
+            log::trace!("HuC-1 IR LED on: {}", self.ir_led_on);
+            return;
+        }
+
+        if let Some(ram_addr) =
+            basic_map_ram_address(true, address, self.ram_bank.into(), self.ram_addr_mask)
+        {
+            sram[ram_addr as usize] = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_LEN: u32 = 1024 * 1024;
+    const RAM_LEN: u32 = 32 * 1024;
+
+    #[test]
+    fn rom_banking() {
+        let mut huc1 = Huc1::new(ROM_LEN, RAM_LEN);
+
+        assert_eq!(huc1.map_rom_address(0x0123), 0x0123);
+        assert_eq!(huc1.map_rom_address(0x4123), 0x4123);
+
+        huc1.write_rom_address(0x2000, 0x25);
+        assert_eq!(huc1.map_rom_address(0x4123), (0x25 << 14) | 0x0123);
+        assert_eq!(huc1.map_rom_address(0x0123), 0x0123);
+
+        // Bank 0 maps to bank 1, and only 6 bits are used
+        huc1.write_rom_address(0x2000, 0x00);
+        assert_eq!(huc1.map_rom_address(0x4000), 0x4000);
+        huc1.write_rom_address(0x3FFF, 0xC3);
+        assert_eq!(huc1.map_rom_address(0x4000), 0x03 << 14);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut huc1 = Huc1::new(ROM_LEN, RAM_LEN);
+        let mut sram = vec![0; RAM_LEN as usize];
+
+        // RAM is always enabled
+        huc1.write_ram(0xA123, 0x45, &mut sram);
+        assert_eq!(sram[0x0123], 0x45);
+
+        huc1.write_rom_address(0x4000, 0x02);
+        huc1.write_ram(0xA123, 0x67, &mut sram);
+        assert_eq!(sram[0x4123], 0x67);
+        assert_eq!(huc1.read_ram(0xA123, &sram), 0x67);
+
+        huc1.write_rom_address(0x4000, 0x00);
+        assert_eq!(huc1.read_ram(0xA123, &sram), 0x45);
+    }
+
+    #[test]
+    fn ir_mapping() {
+        let mut huc1 = Huc1::new(ROM_LEN, RAM_LEN);
+        let mut sram = vec![0x12; RAM_LEN as usize];
+
+        // $0E maps IR in place of RAM, and writes no longer reach RAM
+        huc1.write_rom_address(0x0000, 0x0E);
+        assert_eq!(huc1.read_ram(0xA000, &sram), 0xC0);
+
+        huc1.write_ram(0xA000, 0x01, &mut sram);
+        assert_eq!(huc1.read_ram(0xA000, &sram), 0xC1);
+        assert_eq!(sram[0], 0x12);
+
+        huc1.write_ram(0xA000, 0x00, &mut sram);
+        assert_eq!(huc1.read_ram(0xA000, &sram), 0xC0);
+
+        // Any other value maps RAM back
+        huc1.write_rom_address(0x0000, 0x0A);
+        assert_eq!(huc1.read_ram(0xA000, &sram), 0x12);
+        huc1.write_rom_address(0x0000, 0x00);
+        assert_eq!(huc1.read_ram(0xA000, &sram), 0x12);
+    }
+}
@@ -0,0 +1,391 @@
+//! MBC6 mapper, used only by Net de Get: Minigame @ 100
+//!
+//! MBC6 splits $4000-$7FFF into two independently switchable 8KB windows, each of which can map
+//! either ROM or a 1MB Macronix flash chip, and splits $A000-$BFFF into two switchable 4KB RAM
+//! windows.
+//!
+//! Flash is stored after RAM in the save file so that it persists along with RAM.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/MBC6.html>
+
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+pub const MBC6_FLASH_LEN: usize = 1024 * 1024;
+
+const FLASH_ADDR_MASK: u32 = (MBC6_FLASH_LEN - 1) as u32;
+const FLASH_SECTOR_LEN: u32 = 128 * 1024;
+const FLASH_PAGE_LEN: u32 = 128;
+
+const FLASH_MANUFACTURER_ID: u8 = 0xC2;
+const FLASH_DEVICE_ID: u8 = 0x81;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+enum FlashState {
+    #[default]
+    Read,
+    // Received $AA at $5555
+    Unlock1,
+    // Received $55 at $2AAA
+    Unlock2,
+    Id,
+    // Received $80 command, waiting for second unlock sequence
+    EraseUnlock0,
+    EraseUnlock1,
+    EraseUnlock2,
+    // Programming up to one 128-byte page
+    Program {
+        page: Option<u32>,
+        bytes_written: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct BankWindow {
+    bank: u8,
+    flash_mapped: bool,
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Mbc6 {
+    rom_addr_mask: u32,
+    ram_len: u32,
+    ram_addr_mask: u32,
+    ram_enabled: bool,
+    ram_banks: [u8; 2],
+    rom_windows: [BankWindow; 2],
+    flash_enabled: bool,
+    flash_write_enabled: bool,
+    flash_state: FlashState,
+}
+
+impl Mbc6 {
+    pub fn new(rom_len: u32, ram_len: u32) -> Self {
+        Self {
+            rom_addr_mask: rom_len - 1,
+            ram_len,
+            ram_addr_mask: ram_len.saturating_sub(1),
+            ram_enabled: false,
+            ram_banks: [0, 0],
+            rom_windows: [BankWindow::default(); 2],
+            flash_enabled: false,
+            flash_write_enabled: false,
+            flash_state: FlashState::default(),
+        }
+    }
+
+    fn flash_address(window: BankWindow, address: u16) -> u32 {
+        ((u32::from(window.bank) << 13) | u32::from(address & 0x1FFF)) & FLASH_ADDR_MASK
+    }
+
+    fn window(&self, address: u16) -> BankWindow {
+        self.rom_windows[usize::from(address.bit(13))]
+    }
+
+    pub fn read_rom(&self, address: u16, rom: &[u8], sram: &[u8]) -> u8 {
+        if address < 0x4000 {
+            // First 16KB of ROM
+            return rom[usize::from(address)];
+        }
+
+        let window = self.window(address);
+        if !window.flash_mapped {
+            let rom_addr =
+                ((u32::from(window.bank) << 13) | u32::from(address & 0x1FFF)) & self.rom_addr_mask;
+            return rom[rom_addr as usize];
+        }
+
+        let flash_addr = Self::flash_address(window, address);
+        if self.flash_state == FlashState::Id {
+            return if flash_addr.bit(0) { FLASH_DEVICE_ID } else { FLASH_MANUFACTURER_ID };
+        }
+
+        // Programs and erases complete instantly, so reads always return the data
+        sram.get((self.ram_len + flash_addr) as usize).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_rom_address(&mut self, address: u16, value: u8, sram: &mut [u8]) -> bool {
+        match address {
+            0x0000..=0x03FF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+            }
+            0x0400..=0x07FF => {
+                self.ram_banks[0] = value;
+            }
+            0x0800..=0x0BFF => {
+                self.ram_banks[1] = value;
+            }
+            0x0C00..=0x0FFF => {
+                // Flash can only be enabled or disabled while flash writes are enabled
+                if self.flash_write_enabled {
+                    self.flash_enabled = value.bit(0);
+                }
+            }
+            0x1000..=0x1FFF => {
+                self.flash_write_enabled = value.bit(0);
+            }
+            0x2000..=0x27FF => {
+                self.rom_windows[0].bank = value & 0x7F;
+            }
+            0x2800..=0x2FFF => {
+                self.rom_windows[0].flash_mapped = value == 0x08;
+            }
+            0x3000..=0x37FF => {
+                self.rom_windows[1].bank = value & 0x7F;
+            }
+            0x3800..=0x3FFF => {
+                self.rom_windows[1].flash_mapped = value == 0x08;
+            }
+            0x4000..=0x7FFF => {
+                let window = self.window(address);
+                if window.flash_mapped && self.flash_enabled {
+                    let flash_addr = Self::flash_address(window, address);
+                    return self.write_flash(flash_addr, value, sram);
+                }
+            }
+            0x8000..=0xFFFF => panic!("Invalid ROM address: {address:04X}"),
+        }
+
+        false
+    }
+
+    // Returns whether flash contents changed
+    fn write_flash(&mut self, flash_addr: u32, value: u8, sram: &mut [u8]) -> bool {
+        let Some(flash) = sram.get_mut(self.ram_len as usize..) else { return false };
+        let command_addr = flash_addr & 0x7FFF;
+
+        log::trace!(
+            "MBC6 flash write {flash_addr:05X} {value:02X} in state {:?}",
+            self.flash_state
+        );
+
+        if let FlashState::Program { page, bytes_written } = self.flash_state {
+            let write_page = flash_addr / FLASH_PAGE_LEN;
+            if page.is_none_or(|page| page == write_page) {
+                // Programming can only clear bits
+                flash[flash_addr as usize] &= value;
+
+                self.flash_state = if bytes_written + 1 < FLASH_PAGE_LEN {
+                    FlashState::Program { page: Some(write_page), bytes_written: bytes_written + 1 }
+                } else {
+                    FlashState::Read
+                };
+
+                return true;
+            }
+
+            // A write outside of the page ends programming; handle it as a command
+            self.flash_state = FlashState::Read;
+        }
+
+        if value == 0xF0 {
+            // Reset
+            self.flash_state = FlashState::Read;
+            return false;
+        }
+
+        self.flash_state = match (self.flash_state, command_addr, value) {
+            (FlashState::Read | FlashState::Id, 0x5555, 0xAA) => FlashState::Unlock1,
+            (FlashState::Unlock1, 0x2AAA, 0x55) => FlashState::Unlock2,
+            (FlashState::Unlock2, 0x5555, 0x90) => FlashState::Id,
+            (FlashState::Unlock2, 0x5555, 0xA0) => {
+                FlashState::Program { page: None, bytes_written: 0 }
+            }
+            (FlashState::Unlock2, 0x5555, 0x80) => FlashState::EraseUnlock0,
+            (FlashState::EraseUnlock0, 0x5555, 0xAA) => FlashState::EraseUnlock1,
+            (FlashState::EraseUnlock1, 0x2AAA, 0x55) => FlashState::EraseUnlock2,
+            (FlashState::EraseUnlock2, _, 0x30) => {
+                // Sector erase
+                let sector_start = (flash_addr & !(FLASH_SECTOR_LEN - 1)) as usize;
+                flash[sector_start..sector_start + FLASH_SECTOR_LEN as usize].fill(0xFF);
+                log::trace!("MBC6 flash sector erased at {sector_start:05X}");
+
+                self.flash_state = FlashState::Read;
+                return true;
+            }
+            (FlashState::EraseUnlock2, 0x5555, 0x10) => {
+                // Chip erase
+                flash.fill(0xFF);
+                log::trace!("MBC6 flash chip erased");
+
+                self.flash_state = FlashState::Read;
+                return true;
+            }
+            (state, _, _) => {
+                log::debug!(
+                    "Unexpected MBC6 flash write in state {state:?}: {flash_addr:05X} {value:02X}"
+                );
+                FlashState::Read
+            }
+        };
+
+        false
+    }
+
+    fn map_ram_address(&self, address: u16) -> Option<u32> {
+        if !self.ram_enabled || self.ram_addr_mask == 0 {
+            return None;
+        }
+
+        let bank = self.ram_banks[usize::from(address.bit(12))];
+        Some(((u32::from(bank) << 12) | u32::from(address & 0x0FFF)) & self.ram_addr_mask)
+    }
+
+    pub fn read_ram(&self, address: u16, sram: &[u8]) -> u8 {
+        self.map_ram_address(address).map_or(0xFF, |ram_addr| sram[ram_addr as usize])
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, sram: &mut [u8]) {
+        if let Some(ram_addr) = self.map_ram_address(address) {
+            sram[ram_addr as usize] = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_LEN: u32 = 1024 * 1024;
+    const RAM_LEN: u32 = 32 * 1024;
+
+    struct TestMbc6 {
+        mbc6: Mbc6,
+        rom: Vec<u8>,
+        sram: Vec<u8>,
+    }
+
+    impl TestMbc6 {
+        fn new() -> Self {
+            // Every byte of ROM contains its 8KB bank number
+            let rom = (0..ROM_LEN).map(|address| (address >> 13) as u8).collect();
+            let sram = vec![0xFF; RAM_LEN as usize + MBC6_FLASH_LEN];
+
+            Self { mbc6: Mbc6::new(ROM_LEN, RAM_LEN), rom, sram }
+        }
+
+        fn write(&mut self, address: u16, value: u8) -> bool {
+            self.mbc6.write_rom_address(address, value, &mut self.sram)
+        }
+
+        fn read(&self, address: u16) -> u8 {
+            self.mbc6.read_rom(address, &self.rom, &self.sram)
+        }
+
+        // Map flash $4000-$5FFF to $4000-$5FFF and flash $2000-$3FFF to $6000-$7FFF so that both
+        // flash command addresses are accessible
+        fn map_flash(&mut self) {
+            self.write(0x1000, 0x01);
+            self.write(0x0C00, 0x01);
+            self.write(0x2000, 0x02);
+            self.write(0x2800, 0x08);
+            self.write(0x3000, 0x01);
+            self.write(0x3800, 0x08);
+        }
+
+        fn flash_command(&mut self, command: u8) {
+            self.write(0x5555, 0xAA);
+            self.write(0x6AAA, 0x55);
+            self.write(0x5555, command);
+        }
+
+        fn flash(&self, flash_addr: usize) -> u8 {
+            self.sram[RAM_LEN as usize + flash_addr]
+        }
+    }
+
+    #[test]
+    fn rom_banking() {
+        let mut mbc6 = TestMbc6::new();
+
+        mbc6.write(0x2000, 0x05);
+        mbc6.write(0x3000, 0x46);
+
+        assert_eq!(mbc6.read(0x0000), 0x00);
+        assert_eq!(mbc6.read(0x2000), 0x01);
+        assert_eq!(mbc6.read(0x4000), 0x05);
+        assert_eq!(mbc6.read(0x5FFF), 0x05);
+        assert_eq!(mbc6.read(0x6000), 0x46);
+        assert_eq!(mbc6.read(0x7FFF), 0x46);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut mbc6 = TestMbc6::new();
+
+        mbc6.mbc6.write_ram(0xA010, 0x12, &mut mbc6.sram);
+        assert_eq!(mbc6.mbc6.read_ram(0xA010, &mbc6.sram), 0xFF);
+
+        mbc6.write(0x0000, 0x0A);
+        mbc6.write(0x0400, 0x01);
+        mbc6.write(0x0800, 0x03);
+        mbc6.mbc6.write_ram(0xA010, 0x12, &mut mbc6.sram);
+        mbc6.mbc6.write_ram(0xB010, 0x34, &mut mbc6.sram);
+
+        assert_eq!(mbc6.sram[0x1010], 0x12);
+        assert_eq!(mbc6.sram[0x3010], 0x34);
+        assert_eq!(mbc6.mbc6.read_ram(0xA010, &mbc6.sram), 0x12);
+        assert_eq!(mbc6.mbc6.read_ram(0xB010, &mbc6.sram), 0x34);
+    }
+
+    #[test]
+    fn flash_id() {
+        let mut mbc6 = TestMbc6::new();
+        mbc6.map_flash();
+
+        mbc6.flash_command(0x90);
+        assert_eq!(mbc6.read(0x4000), FLASH_MANUFACTURER_ID);
+        assert_eq!(mbc6.read(0x4001), FLASH_DEVICE_ID);
+
+        mbc6.write(0x4000, 0xF0);
+        assert_eq!(mbc6.read(0x4000), 0xFF);
+    }
+
+    #[test]
+    fn flash_program_and_erase() {
+        let mut mbc6 = TestMbc6::new();
+        mbc6.map_flash();
+
+        mbc6.flash_command(0xA0);
+        assert!(mbc6.write(0x4100, 0x5A));
+        assert_eq!(mbc6.flash(0x4100), 0x5A);
+        assert_eq!(mbc6.read(0x4100), 0x5A);
+
+        // Programming can only clear bits
+        mbc6.flash_command(0xA0);
+        assert!(mbc6.write(0x4100, 0xA5));
+        assert_eq!(mbc6.read(0x4100), 0x00);
+
+        // Sector erase
+        mbc6.flash_command(0x80);
+        mbc6.flash_command(0x30);
+        assert_eq!(mbc6.read(0x4100), 0xFF);
+    }
+
+    #[test]
+    fn flash_chip_erase() {
+        let mut mbc6 = TestMbc6::new();
+        mbc6.sram[RAM_LEN as usize..].fill(0x00);
+        mbc6.map_flash();
+
+        mbc6.flash_command(0x80);
+        mbc6.flash_command(0x10);
+        assert_eq!(mbc6.flash(0), 0xFF);
+        assert_eq!(mbc6.flash(MBC6_FLASH_LEN - 1), 0xFF);
+
+        // RAM is not affected
+        assert_eq!(mbc6.sram[0], 0xFF);
+    }
+
+    #[test]
+    fn flash_writes_require_enable() {
+        let mut mbc6 = TestMbc6::new();
+        mbc6.write(0x2000, 0x02);
+        mbc6.write(0x2800, 0x08);
+
+        mbc6.flash_command(0xA0);
+        assert!(!mbc6.write(0x4100, 0x00));
+        assert_eq!(mbc6.flash(0x4100), 0xFF);
+    }
+}
@@ -0,0 +1,506 @@
+//! MBC7 mapper, used by Kirby Tilt 'n' Tumble and Command Master
+//!
+//! MBC7 has no SRAM; instead it has a 2-axis accelerometer and a serial 93LC56 EEPROM, both of which
+//! are accessed through registers mapped to $A000-$AFFF.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/MBC7.html>
+
+use crate::cartridge::mappers::basic_map_rom_address;
+use bincode::{Decode, Encode};
+use gb_config::GameBoyInputs;
+use jgenesis_common::num::GetBit;
+
+// 93LC56: 128 16-bit words
+pub const MBC7_EEPROM_LEN: usize = 256;
+
+const EEPROM_WORD_MASK: u8 = 0x7F;
+
+// Accelerometer reads $81D0 when level, and changes by roughly $70 per 1G of acceleration
+const ACCELEROMETER_CENTER: u16 = 0x81D0;
+const ACCELEROMETER_TILT: u16 = 0x70;
+
+// Value that the accelerometer registers hold after being erased and before being latched
+const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+enum EepromState {
+    Idle,
+    // Receiving 2 opcode bits and 8 address bits after the start bit
+    Command { bits_received: u8 },
+    // Shifting out a 16-bit word, most significant bit first
+    Read { bits_remaining: u8 },
+    // Receiving a 16-bit word to write to one address (WRITE) or all addresses (WRAL)
+    Write { address: Option<u8>, bits_received: u8 },
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct Eeprom {
+    state: EepromState,
+    chip_select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+    shift_register: u16,
+    write_enabled: bool,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            state: EepromState::Idle,
+            chip_select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+            shift_register: 0,
+            write_enabled: false,
+        }
+    }
+
+    fn read(&self) -> u8 {
+        (u8::from(self.chip_select) << 7)
+            | (u8::from(self.clock) << 6)
+            | (u8::from(self.data_in) << 1)
+            | u8::from(self.data_out)
+    }
+
+    fn write(&mut self, value: u8, eeprom: &mut [u8]) {
+        let chip_select = value.bit(7);
+        let clock = value.bit(6);
+        self.data_in = value.bit(1);
+
+        if !chip_select {
+            // Deselecting the chip aborts any in-progress command
+            self.state = EepromState::Idle;
+            self.chip_select = false;
+            self.clock = clock;
+            return;
+        }
+
+        if !self.chip_select {
+            // Writes and erases complete instantly, so always report ready after reselecting
+            self.data_out = true;
+        }
+        self.chip_select = true;
+
+        let prev_clock = self.clock;
+        self.clock = clock;
+        if !prev_clock && clock {
+            self.clock_rising_edge(eeprom);
+        }
+    }
+
+    fn clock_rising_edge(&mut self, eeprom: &mut [u8]) {
+        let bit = u16::from(self.data_in);
+
+        match self.state {
+            EepromState::Idle => {
+                // Wait for start bit
+                if self.data_in {
+                    self.state = EepromState::Command { bits_received: 0 };
+                    self.shift_register = 0;
+                }
+            }
+            EepromState::Command { bits_received } => {
+                self.shift_register = (self.shift_register << 1) | bit;
+
+                let bits_received = bits_received + 1;
+                if bits_received == 10 {
+                    self.execute_command(eeprom);
+                } else {
+                    self.state = EepromState::Command { bits_received };
+                }
+            }
+            EepromState::Read { bits_remaining } => {
+                self.data_out = self.shift_register.bit(15);
+                self.shift_register <<= 1;
+
+                self.state = if bits_remaining > 1 {
+                    EepromState::Read { bits_remaining: bits_remaining - 1 }
+                } else {
+                    EepromState::Idle
+                };
+            }
+            EepromState::Write { address, bits_received } => {
+                self.shift_register = (self.shift_register << 1) | bit;
+
+                let bits_received = bits_received + 1;
+                if bits_received < 16 {
+                    self.state = EepromState::Write { address, bits_received };
+                    return;
+                }
+
+                if self.write_enabled {
+                    match address {
+                        Some(address) => write_word(eeprom, address, self.shift_register),
+                        None => {
+                            for address in 0..=EEPROM_WORD_MASK {
+                                write_word(eeprom, address, self.shift_register);
+                            }
+                        }
+                    }
+                }
+
+                self.state = EepromState::Idle;
+            }
+        }
+    }
+
+    fn execute_command(&mut self, eeprom: &mut [u8]) {
+        let opcode = (self.shift_register >> 8) & 0x03;
+        let address = (self.shift_register & 0xFF) as u8;
+
+        log::trace!("MBC7 EEPROM command: opcode {opcode:02b}, address {address:02X}");
+
+        self.state = EepromState::Idle;
+
+        match opcode {
+            0b10 => {
+                // READ; a dummy 0 bit is output before the data
+                self.shift_register = read_word(eeprom, address);
+                self.data_out = false;
+                self.state = EepromState::Read { bits_remaining: 16 };
+            }
+            0b01 => {
+                // WRITE
+                self.state = EepromState::Write { address: Some(address), bits_received: 0 };
+            }
+            0b11 => {
+                // ERASE
+                if self.write_enabled {
+                    write_word(eeprom, address, 0xFFFF);
+                }
+            }
+            _ => match address >> 6 {
+                0b00 => {
+                    // EWDS: Disable writes
+                    self.write_enabled = false;
+                }
+                0b01 => {
+                    // WRAL: Write all
+                    self.state = EepromState::Write { address: None, bits_received: 0 };
+                }
+                0b10 => {
+                    // ERAL: Erase all
+                    if self.write_enabled {
+                        eeprom.fill(0xFF);
+                    }
+                }
+                _ => {
+                    // EWEN: Enable writes
+                    self.write_enabled = true;
+                }
+            },
+        }
+    }
+}
+
+fn read_word(eeprom: &[u8], address: u8) -> u16 {
+    let idx = 2 * usize::from(address & EEPROM_WORD_MASK);
+    u16::from_be_bytes([eeprom[idx], eeprom[idx + 1]])
+}
+
+fn write_word(eeprom: &mut [u8], address: u8, value: u16) {
+    let idx = 2 * usize::from(address & EEPROM_WORD_MASK);
+    eeprom[idx..idx + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Mbc7 {
+    rom_bank: u8,
+    rom_addr_mask: u32,
+    ram_enabled_1: bool,
+    ram_enabled_2: bool,
+    current_tilt: (u16, u16),
+    latched_x: u16,
+    latched_y: u16,
+    latch_erased: bool,
+    eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new(rom_len: u32) -> Self {
+        Self {
+            rom_bank: 1,
+            rom_addr_mask: rom_len - 1,
+            ram_enabled_1: false,
+            ram_enabled_2: false,
+            current_tilt: (ACCELEROMETER_CENTER, ACCELEROMETER_CENTER),
+            latched_x: ACCELEROMETER_ERASED,
+            latched_y: ACCELEROMETER_ERASED,
+            latch_erased: false,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    pub fn map_rom_address(&self, address: u16) -> u32 {
+        basic_map_rom_address(address, self.rom_bank.into(), true, self.rom_addr_mask)
+    }
+
+    pub fn write_rom_address(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                // RAM enable 1
+                self.ram_enabled_1 = value & 0x0F == 0x0A;
+            }
+            0x2000..=0x3FFF => {
+                // ROM bank
+                self.rom_bank = value;
+            }
+            0x4000..=0x5FFF => {
+                // RAM enable 2
+                self.ram_enabled_2 = value == 0x40;
+            }
+            0x6000..=0x7FFF => {}
+            0x8000..=0xFFFF => panic!("Invalid ROM address: {address:04X}"),
+        }
+    }
+
+    // Tilt buttons push the accelerometer reading to 1G in that direction
+    pub fn update_tilt(&mut self, inputs: &GameBoyInputs) {
+        fn axis(negative: bool, positive: bool) -> u16 {
+            match (negative, positive) {
+                (true, false) => ACCELEROMETER_CENTER - ACCELEROMETER_TILT,
+                (false, true) => ACCELEROMETER_CENTER + ACCELEROMETER_TILT,
+                _ => ACCELEROMETER_CENTER,
+            }
+        }
+
+        self.current_tilt =
+            (axis(inputs.tilt_right, inputs.tilt_left), axis(inputs.tilt_up, inputs.tilt_down));
+    }
+
+    fn registers_enabled(&self, address: u16) -> bool {
+        self.ram_enabled_1 && self.ram_enabled_2 && address < 0xB000
+    }
+
+    pub fn read_ram(&self, address: u16) -> u8 {
+        if !self.registers_enabled(address) {
+            return 0xFF;
+        }
+
+        match (address >> 4) & 0xF {
+            0x2 => self.latched_x as u8,
+            0x3 => (self.latched_x >> 8) as u8,
+            0x4 => self.latched_y as u8,
+            0x5 => (self.latched_y >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, eeprom: &mut [u8]) {
+        if !self.registers_enabled(address) {
+            return;
+        }
+
+        match (address >> 4) & 0xF {
+            // Writing $55 erases the latched accelerometer values
+            0x0 if value == 0x55 => {
+                self.latched_x = ACCELEROMETER_ERASED;
+                self.latched_y = ACCELEROMETER_ERASED;
+                self.latch_erased = true;
+            }
+            // Writing $AA latches the accelerometer values, but only if they were just erased
+            0x1 if value == 0xAA && self.latch_erased => {
+                (self.latched_x, self.latched_y) = self.current_tilt;
+                self.latch_erased = false;
+            }
+            0x8 => self.eeprom.write(value, eeprom),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EEPROM_ADDR: u16 = 0xA080;
+
+    struct TestMbc7 {
+        mbc7: Mbc7,
+        eeprom: Vec<u8>,
+    }
+
+    impl TestMbc7 {
+        fn new() -> Self {
+            let mut mbc7 = Mbc7::new(1024 * 1024);
+            mbc7.write_rom_address(0x0000, 0x0A);
+            mbc7.write_rom_address(0x4000, 0x40);
+
+            Self { mbc7, eeprom: vec![0xFF; MBC7_EEPROM_LEN] }
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            self.mbc7.write_ram(address, value, &mut self.eeprom);
+        }
+
+        fn read(&self, address: u16) -> u8 {
+            self.mbc7.read_ram(address)
+        }
+
+        fn data_out(&self) -> bool {
+            self.read(EEPROM_ADDR).bit(0)
+        }
+
+        fn clock_bit(&mut self, bit: bool) {
+            let data_in = u8::from(bit) << 1;
+            self.write(EEPROM_ADDR, 0x80 | data_in);
+            self.write(EEPROM_ADDR, 0xC0 | data_in);
+        }
+
+        fn send_bits(&mut self, value: u16, bits: u8) {
+            for i in (0..bits).rev() {
+                self.clock_bit(value.bit(i));
+            }
+        }
+
+        fn command(&mut self, opcode: u16, address: u8) {
+            self.send_bits(1, 1);
+            self.send_bits(opcode, 2);
+            self.send_bits(address.into(), 8);
+        }
+
+        fn deselect(&mut self) {
+            self.write(EEPROM_ADDR, 0x00);
+        }
+
+        fn read_word(&mut self, address: u8) -> u16 {
+            self.command(0b10, address);
+            assert!(!self.data_out(), "READ should output a dummy 0 bit");
+
+            let mut word = 0;
+            for _ in 0..16 {
+                self.clock_bit(false);
+                word = (word << 1) | u16::from(self.data_out());
+            }
+            self.deselect();
+
+            word
+        }
+
+        fn write_word(&mut self, address: u8, value: u16) {
+            self.command(0b01, address);
+            self.send_bits(value, 16);
+            self.deselect();
+        }
+
+        fn write_enable(&mut self, enabled: bool) {
+            self.command(0b00, if enabled { 0xC0 } else { 0x00 });
+            self.deselect();
+        }
+    }
+
+    #[test]
+    fn eeprom_write_and_read() {
+        let mut mbc7 = TestMbc7::new();
+
+        // Writes are ignored until enabled with EWEN
+        mbc7.write_word(0x05, 0x1234);
+        assert_eq!(mbc7.read_word(0x05), 0xFFFF);
+
+        mbc7.write_enable(true);
+        mbc7.write_word(0x05, 0x1234);
+        assert_eq!(mbc7.read_word(0x05), 0x1234);
+        assert_eq!(&mbc7.eeprom[0x0A..0x0C], [0x12, 0x34]);
+
+        // Address wraps to 128 words
+        assert_eq!(mbc7.read_word(0x85), 0x1234);
+
+        mbc7.write_enable(false);
+        mbc7.write_word(0x05, 0x5678);
+        assert_eq!(mbc7.read_word(0x05), 0x1234);
+    }
+
+    #[test]
+    fn eeprom_erase_and_write_all() {
+        let mut mbc7 = TestMbc7::new();
+        mbc7.write_enable(true);
+
+        // WRAL
+        mbc7.command(0b00, 0x40);
+        mbc7.send_bits(0xABCD, 16);
+        mbc7.deselect();
+        assert!(mbc7.eeprom.chunks_exact(2).all(|word| word == [0xAB, 0xCD]));
+
+        // ERASE
+        mbc7.command(0b11, 0x10);
+        mbc7.deselect();
+        assert_eq!(mbc7.read_word(0x10), 0xFFFF);
+        assert_eq!(mbc7.read_word(0x11), 0xABCD);
+
+        // ERAL
+        mbc7.command(0b00, 0x80);
+        mbc7.deselect();
+        assert!(mbc7.eeprom.iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn eeprom_deselect_aborts_write() {
+        let mut mbc7 = TestMbc7::new();
+        mbc7.write_enable(true);
+
+        mbc7.command(0b01, 0x20);
+        mbc7.send_bits(0x12, 8);
+        mbc7.deselect();
+
+        assert_eq!(mbc7.read_word(0x20), 0xFFFF);
+
+        // Chip reports ready after reselecting
+        mbc7.write(EEPROM_ADDR, 0x80);
+        assert!(mbc7.data_out());
+    }
+
+    #[test]
+    fn accelerometer_latch() {
+        let mut mbc7 = TestMbc7::new();
+        mbc7.mbc7.update_tilt(&GameBoyInputs {
+            tilt_left: true,
+            tilt_down: true,
+            ..GameBoyInputs::default()
+        });
+
+        let read_latched = |mbc7: &TestMbc7| {
+            let x = u16::from_le_bytes([mbc7.read(0xA020), mbc7.read(0xA030)]);
+            let y = u16::from_le_bytes([mbc7.read(0xA040), mbc7.read(0xA050)]);
+            (x, y)
+        };
+
+        // Latching without erasing first does nothing
+        mbc7.write(0xA010, 0xAA);
+        assert_eq!(read_latched(&mbc7), (ACCELEROMETER_ERASED, ACCELEROMETER_ERASED));
+
+        mbc7.write(0xA000, 0x55);
+        mbc7.write(0xA010, 0xAA);
+        let tilted = ACCELEROMETER_CENTER + ACCELEROMETER_TILT;
+        assert_eq!(read_latched(&mbc7), (tilted, tilted));
+
+        // Latched values don't change until erased and latched again
+        mbc7.mbc7.update_tilt(&GameBoyInputs::default());
+        mbc7.write(0xA010, 0xAA);
+        assert_eq!(read_latched(&mbc7), (tilted, tilted));
+
+        mbc7.write(0xA000, 0x55);
+        assert_eq!(read_latched(&mbc7), (ACCELEROMETER_ERASED, ACCELEROMETER_ERASED));
+        mbc7.write(0xA010, 0xAA);
+        assert_eq!(read_latched(&mbc7), (ACCELEROMETER_CENTER, ACCELEROMETER_CENTER));
+    }
+
+    #[test]
+    fn registers_require_both_enables() {
+        let mut mbc7 = Mbc7::new(1024 * 1024);
+        assert_eq!(mbc7.read_ram(0xA020), 0xFF);
+
+        mbc7.write_rom_address(0x0000, 0x0A);
+        assert_eq!(mbc7.read_ram(0xA020), 0xFF);
+
+        mbc7.write_rom_address(0x4000, 0x40);
+        assert_eq!(mbc7.read_ram(0xA020), ACCELEROMETER_ERASED as u8);
+
+        // Registers are not mapped at $B000-$BFFF
+        assert_eq!(mbc7.read_ram(0xB020), 0xFF);
+    }
+}
@@ -0,0 +1,241 @@
+//! MMM01 mapper, used by a few multicarts
+//!
+//! The MMM01 powers on in an "unmapped" mode where the last 32KB of ROM (the multicart menu) is
+//! mapped to $0000-$7FFF. While unmapped, the menu can set the upper ROM and RAM bank bits and
+//! choose which of the lower bank bits the game is allowed to modify. Setting the map enable bit
+//! locks these settings, after which the mapper behaves mostly like MBC1 within the selected game.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/MMM01.html>
+
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Mmm01 {
+    rom_len: u32,
+    rom_addr_mask: u32,
+    ram_addr_mask: u32,
+    mapped: bool,
+    ram_enabled: bool,
+    // ROM bank bits 0-4
+    rom_bank_low: u8,
+    // ROM bank bits 5-8; only writable while unmapped
+    rom_bank_high: u8,
+    // Mask for ROM bank bits 1-4; masked bits are not writable after mapping
+    rom_bank_mask: u8,
+    // RAM bank bits 0-1
+    ram_bank_low: u8,
+    // RAM bank bits 2-3; only writable while unmapped
+    ram_bank_high: u8,
+    // Mask for RAM bank bits 0-1; masked bits are not writable after mapping
+    ram_bank_mask: u8,
+    mbc1_mode_locked: bool,
+    ram_banking_enabled: bool,
+}
+
+impl Mmm01 {
+    pub fn new(rom_len: u32, ram_len: u32) -> Self {
+        Self {
+            rom_len,
+            rom_addr_mask: rom_len - 1,
+            ram_addr_mask: ram_len.saturating_sub(1),
+            mapped: false,
+            ram_enabled: false,
+            rom_bank_low: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            mbc1_mode_locked: false,
+            ram_banking_enabled: false,
+        }
+    }
+
+    // Bits of the low ROM bank that the game can modify after mapping
+    fn rom_bank_low_writable(&self) -> u8 {
+        if self.mapped { 0x1F & !(self.rom_bank_mask << 1) } else { 0x1F }
+    }
+
+    fn ram_bank_low_writable(&self) -> u8 {
+        if self.mapped { 0x03 & !self.ram_bank_mask } else { 0x03 }
+    }
+
+    pub fn map_rom_address(&self, address: u16) -> u32 {
+        if !self.mapped {
+            // Menu in the last 32KB of ROM
+            return (self.rom_len.saturating_sub(0x8000) | u32::from(address & 0x7FFF))
+                & self.rom_addr_mask;
+        }
+
+        let writable = self.rom_bank_low_writable();
+        let rom_bank_base =
+            (u32::from(self.rom_bank_high) << 5) | u32::from(self.rom_bank_low & !writable & 0x1F);
+        let rom_bank = if !address.bit(14) {
+            rom_bank_base
+        } else {
+            // Same as MBC1, selecting bank 0 in the writable bits selects bank 1
+            let game_bank = self.rom_bank_low & writable;
+            rom_bank_base | u32::from(if game_bank == 0 { 1 } else { game_bank })
+        };
+
+        ((rom_bank << 14) | u32::from(address & 0x3FFF)) & self.rom_addr_mask
+    }
+
+    pub fn write_rom_address(&mut self, address: u16, value: u8) {
+        log::trace!("MMM01 register write: {address:04X} {value:02X}");
+
+        match address {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+
+                if !self.mapped {
+                    self.ram_bank_mask = (value >> 4) & 0x03;
+                    self.mapped = value.bit(6);
+
+                    if self.mapped {
+                        log::debug!(
+                            "MMM01 mapping locked; ROM bank {:03X}, RAM bank {:X}",
+                            (u16::from(self.rom_bank_high) << 5) | u16::from(self.rom_bank_low),
+                            (self.ram_bank_high << 2) | self.ram_bank_low
+                        );
+                    }
+                }
+            }
+            0x2000..=0x3FFF => {
+                let writable = self.rom_bank_low_writable();
+                self.rom_bank_low = (self.rom_bank_low & !writable) | (value & writable);
+
+                if !self.mapped {
+                    self.rom_bank_high = (self.rom_bank_high & !0x03) | ((value >> 5) & 0x03);
+                }
+            }
+            0x4000..=0x5FFF => {
+                let writable = self.ram_bank_low_writable();
+                self.ram_bank_low = (self.ram_bank_low & !writable) | (value & writable);
+
+                if !self.mapped {
+                    self.ram_bank_high = (value >> 2) & 0x03;
+                    self.rom_bank_high = (self.rom_bank_high & 0x03) | ((value >> 2) & 0x0C);
+                    self.mbc1_mode_locked = value.bit(6);
+                }
+            }
+            0x6000..=0x7FFF => {
+                if !(self.mapped && self.mbc1_mode_locked) {
+                    self.ram_banking_enabled = value.bit(0);
+                }
+
+                if !self.mapped {
+                    self.rom_bank_mask = (value >> 2) & 0x0F;
+                }
+            }
+            0x8000..=0xFFFF => panic!("Invalid ROM address: {address:04X}"),
+        }
+    }
+
+    fn map_ram_address(&self, address: u16) -> Option<u32> {
+        if !self.ram_enabled || self.ram_addr_mask == 0 {
+            return None;
+        }
+
+        let ram_bank_low = if self.ram_banking_enabled {
+            self.ram_bank_low
+        } else {
+            // Like MBC1 simple banking mode, but bits locked by the menu still apply
+            self.ram_bank_low & !self.ram_bank_low_writable()
+        };
+        let ram_bank = (u32::from(self.ram_bank_high) << 2) | u32::from(ram_bank_low);
+
+        Some(((ram_bank << 13) | u32::from(address & 0x1FFF)) & self.ram_addr_mask)
+    }
+
+    pub fn read_ram(&self, address: u16, sram: &[u8]) -> u8 {
+        self.map_ram_address(address).map_or(0xFF, |ram_addr| sram[ram_addr as usize])
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, sram: &mut [u8]) {
+        if let Some(ram_addr) = self.map_ram_address(address) {
+            sram[ram_addr as usize] = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_LEN: u32 = 512 * 1024;
+    const RAM_LEN: u32 = 32 * 1024;
+
+    fn rom_bank(mmm01: &Mmm01, address: u16) -> u32 {
+        mmm01.map_rom_address(address) >> 14
+    }
+
+    #[test]
+    fn boots_into_menu() {
+        let mmm01 = Mmm01::new(ROM_LEN, RAM_LEN);
+
+        // Last 32KB of ROM is mapped to $0000-$7FFF
+        assert_eq!(mmm01.map_rom_address(0x0000), ROM_LEN - 0x8000);
+        assert_eq!(mmm01.map_rom_address(0x4000), ROM_LEN - 0x4000);
+        assert_eq!(mmm01.map_rom_address(0x7FFF), ROM_LEN - 1);
+    }
+
+    #[test]
+    fn menu_selects_game() {
+        let mut mmm01 = Mmm01::new(ROM_LEN, RAM_LEN);
+
+        // Game starts at bank $08 and is 128KB (8 banks); lock ROM bank bits 3-4
+        mmm01.write_rom_address(0x2000, 0x08);
+        mmm01.write_rom_address(0x6000, 0x0C << 2);
+        mmm01.write_rom_address(0x0000, 0x40);
+
+        assert_eq!(rom_bank(&mmm01, 0x0000), 0x08);
+        assert_eq!(rom_bank(&mmm01, 0x4000), 0x09);
+
+        // Game can only switch banks within its own 128KB
+        mmm01.write_rom_address(0x2000, 0x1F);
+        assert_eq!(rom_bank(&mmm01, 0x4000), 0x0F);
+        assert_eq!(rom_bank(&mmm01, 0x0000), 0x08);
+
+        mmm01.write_rom_address(0x2000, 0x03);
+        assert_eq!(rom_bank(&mmm01, 0x4000), 0x0B);
+    }
+
+    #[test]
+    fn mapping_locks_menu_registers() {
+        let mut mmm01 = Mmm01::new(ROM_LEN, RAM_LEN);
+
+        mmm01.write_rom_address(0x2000, 0x10);
+        mmm01.write_rom_address(0x6000, 0x0F << 2);
+        mmm01.write_rom_address(0x0000, 0x40);
+        assert_eq!(rom_bank(&mmm01, 0x4000), 0x11);
+
+        // Further writes to the menu-only bits and the map enable bit are ignored
+        mmm01.write_rom_address(0x6000, 0x00);
+        mmm01.write_rom_address(0x0000, 0x00);
+        mmm01.write_rom_address(0x2000, 0x02);
+        assert_eq!(rom_bank(&mmm01, 0x4000), 0x11);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut mmm01 = Mmm01::new(ROM_LEN, RAM_LEN);
+        let mut sram = vec![0; RAM_LEN as usize];
+
+        // RAM is disabled on power-on
+        assert_eq!(mmm01.read_ram(0xA000, &sram), 0xFF);
+
+        mmm01.write_rom_address(0x6000, 0x01);
+        mmm01.write_rom_address(0x0000, 0x4A);
+        mmm01.write_rom_address(0x4000, 0x02);
+        mmm01.write_ram(0xA123, 0x55, &mut sram);
+
+        assert_eq!(sram[0x4123], 0x55);
+        assert_eq!(mmm01.read_ram(0xA123, &sram), 0x55);
+
+        mmm01.write_rom_address(0x0000, 0x00);
+        assert_eq!(mmm01.read_ram(0xA123, &sram), 0xFF);
+    }
+}
@@ -0,0 +1,190 @@
+//! Bandai TAMA5 mapper, used only by Game de Hakken!! Tamagotchi Osutchi to Mesutchi
+//!
+//! TAMA5 exposes all of its functionality through two registers: $A001 selects an internal
+//! register and $A000 reads or writes it 4 bits at a time. The cartridge's 32 bytes of save RAM are
+//! inside a TAMA6 microcontroller that is accessed through these registers.
+//!
+//! The TAMA6 real-time clock is not emulated; RTC commands are ignored, and a warning is logged
+//! when the cartridge is loaded since the game's clock-based events will not progress.
+//!
+//! References:
+//!   <https://gbdev.io/pandocs/TAMA5.html>
+
+use crate::cartridge::mappers::basic_map_rom_address;
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+pub const TAMA5_RAM_LEN: usize = 32;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Tama5 {
+    rom_bank: u8,
+    rom_addr_mask: u32,
+    register_select: u8,
+    write_value: u8,
+    command: u8,
+    ram_addr_high: bool,
+    read_value: u8,
+}
+
+impl Tama5 {
+    pub fn new(rom_len: u32) -> Self {
+        log::warn!("TAMA5 real-time clock is not emulated; time-based game events will not occur");
+
+        Self {
+            rom_bank: 0,
+            rom_addr_mask: rom_len - 1,
+            register_select: 0,
+            write_value: 0,
+            command: 0,
+            ram_addr_high: false,
+            read_value: 0,
+        }
+    }
+
+    pub fn map_rom_address(&self, address: u16) -> u32 {
+        basic_map_rom_address(address, self.rom_bank.into(), false, self.rom_addr_mask)
+    }
+
+    pub fn read_ram(&self, address: u16) -> u8 {
+        if address.bit(0) {
+            return 0xFF;
+        }
+
+        let value = match self.register_select {
+            // Ready flag; the TAMA6 completes all commands instantly
+            0x0A => 0x01,
+            0x0C => self.read_value & 0x0F,
+            0x0D => self.read_value >> 4,
+            _ => 0x0F,
+        };
+        0xF0 | value
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8, sram: &mut [u8]) {
+        if address.bit(0) {
+            self.register_select = value & 0x0F;
+            return;
+        }
+
+        let value = value & 0x0F;
+        match self.register_select {
+            0x00 => {
+                // ROM bank low bits
+                self.rom_bank = (self.rom_bank & 0x10) | value;
+            }
+            0x01 => {
+                // ROM bank high bit
+                self.rom_bank = (self.rom_bank & 0x0F) | ((value & 0x01) << 4);
+            }
+            0x04 => {
+                self.write_value = (self.write_value & 0xF0) | value;
+            }
+            0x05 => {
+                self.write_value = (self.write_value & 0x0F) | (value << 4);
+            }
+            0x06 => {
+                // Bit 0 is RAM address bit 4, bits 1-3 are the command
+                self.ram_addr_high = value.bit(0);
+                self.command = value >> 1;
+            }
+            0x07 => {
+                // Writing the low RAM address bits executes the command
+                let ram_addr = (usize::from(self.ram_addr_high) << 4) | usize::from(value);
+                self.execute_command(ram_addr, sram);
+            }
+            _ => {
+                log::trace!("TAMA5 write to register {:X}: {value:X}", self.register_select);
+            }
+        }
+    }
+
+    fn execute_command(&mut self, ram_addr: usize, sram: &mut [u8]) {
+        match self.command {
+            0 => {
+                sram[ram_addr] = self.write_value;
+                log::trace!("TAMA5 RAM write: {ram_addr:02X} {:02X}", self.write_value);
+            }
+            1 => {
+                self.read_value = sram[ram_addr];
+                log::trace!("TAMA5 RAM read: {ram_addr:02X} {:02X}", self.read_value);
+            }
+            _ => {
+                // All other commands access the TAMA6 RTC
+                log::debug!("Unsupported TAMA5 RTC command {:X} at {ram_addr:02X}", self.command);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_LEN: u32 = 512 * 1024;
+
+    fn write_register(tama5: &mut Tama5, register: u8, value: u8, sram: &mut [u8]) {
+        tama5.write_ram(0xA001, register, sram);
+        tama5.write_ram(0xA000, value, sram);
+    }
+
+    fn read_register(tama5: &mut Tama5, register: u8, sram: &mut [u8]) -> u8 {
+        tama5.write_ram(0xA001, register, sram);
+        tama5.read_ram(0xA000)
+    }
+
+    #[test]
+    fn rom_banking() {
+        let mut tama5 = Tama5::new(ROM_LEN);
+        let mut sram = [0; TAMA5_RAM_LEN];
+
+        // Bank 0 maps to bank 1
+        assert_eq!(tama5.map_rom_address(0x4123), 0x4123);
+
+        write_register(&mut tama5, 0x00, 0x05, &mut sram);
+        assert_eq!(tama5.map_rom_address(0x4123), (0x05 << 14) | 0x0123);
+
+        write_register(&mut tama5, 0x01, 0x01, &mut sram);
+        assert_eq!(tama5.map_rom_address(0x4123), (0x15 << 14) | 0x0123);
+
+        // Register writes only use the low 4 bits
+        write_register(&mut tama5, 0x00, 0xF2, &mut sram);
+        assert_eq!(tama5.map_rom_address(0x4000), 0x12 << 14);
+        assert_eq!(tama5.map_rom_address(0x0123), 0x0123);
+    }
+
+    #[test]
+    fn ram_access() {
+        let mut tama5 = Tama5::new(ROM_LEN);
+        let mut sram = [0; TAMA5_RAM_LEN];
+
+        // The TAMA6 is always ready
+        assert_eq!(read_register(&mut tama5, 0x0A, &mut sram), 0xF1);
+
+        // Write $A7 to RAM address $13
+        write_register(&mut tama5, 0x04, 0x07, &mut sram);
+        write_register(&mut tama5, 0x05, 0x0A, &mut sram);
+        write_register(&mut tama5, 0x06, 0x01, &mut sram);
+        write_register(&mut tama5, 0x07, 0x03, &mut sram);
+        assert_eq!(sram[0x13], 0xA7);
+
+        // Read it back a nibble at a time
+        write_register(&mut tama5, 0x06, 0x03, &mut sram);
+        write_register(&mut tama5, 0x07, 0x03, &mut sram);
+        assert_eq!(read_register(&mut tama5, 0x0C, &mut sram), 0xF7);
+        assert_eq!(read_register(&mut tama5, 0x0D, &mut sram), 0xFA);
+
+        // Reads from odd addresses are open bus
+        assert_eq!(tama5.read_ram(0xA001), 0xFF);
+    }
+
+    #[test]
+    fn rtc_commands_ignored() {
+        let mut tama5 = Tama5::new(ROM_LEN);
+        let mut sram = [0x55; TAMA5_RAM_LEN];
+
+        write_register(&mut tama5, 0x06, 0x04, &mut sram);
+        write_register(&mut tama5, 0x07, 0x00, &mut sram);
+        assert_eq!(sram, [0x55; TAMA5_RAM_LEN]);
+    }
+}
@@ -1,6 +1,7 @@
 //! Game Boy input handling
 
 use crate::interrupts::InterruptRegisters;
+use crate::sgb::SgbJoypadPort;
 use crate::sm83::InterruptType;
 use bincode::{Decode, Encode};
 use gb_config::GameBoyInputs;
@@ -12,6 +13,7 @@ pub(crate) struct InputState {
     d_pad_selected: bool,
     buttons_selected: bool,
     prev_joyp: u8,
+    sgb: Option<SgbJoypadPort>,
 }
 
 impl InputState {
@@ -21,9 +23,18 @@ impl InputState {
             d_pad_selected: false,
             buttons_selected: false,
             prev_joyp: 0xFF,
+            sgb: None,
         }
     }
 
+    pub(crate) fn new_sgb() -> Self {
+        Self { sgb: Some(SgbJoypadPort::new()), ..Self::new() }
+    }
+
+    pub(crate) fn sgb_mut(&mut self) -> Option<&mut SgbJoypadPort> {
+        self.sgb.as_mut()
+    }
+
     pub(crate) fn set_inputs(&mut self, inputs: GameBoyInputs) {
         self.inputs = inputs;
     }
@@ -32,6 +43,10 @@ impl InputState {
         self.buttons_selected = !value.bit(5);
         self.d_pad_selected = !value.bit(4);
 
+        if let Some(sgb) = &mut self.sgb {
+            sgb.write_joyp(value);
+        }
+
         log::trace!("JOYP write: {value:02X}");
     }
 
@@ -48,6 +63,14 @@ impl InputState {
     }
 
     pub(crate) fn read_joyp(&self) -> u8 {
+        if let Some(sgb) = &self.sgb {
+            // Super Game Boy joypads are supplied by the SNES through the ICD2
+            return 0xC0
+                | (u8::from(!self.buttons_selected) << 5)
+                | (u8::from(!self.d_pad_selected) << 4)
+                | sgb.read_joyp_low_nibble(self.d_pad_selected, self.buttons_selected);
+        }
+
         let bit_3_inverted = (self.buttons_selected && self.inputs.start)
             || (self.d_pad_selected && self.inputs.down);
         let bit_2_inverted = (self.buttons_selected && self.inputs.select)
@@ -2,6 +2,7 @@ pub mod api;
 pub mod apu;
 mod audio;
 mod bus;
+pub mod camera;
 mod cartridge;
 mod cgb;
 mod dma;
@@ -10,7 +11,9 @@ pub mod inputs;
 mod interrupts;
 mod memory;
 mod ppu;
+pub mod printer;
 mod serial;
+pub mod sgb;
 mod sm83;
 mod timer;
 
@@ -101,6 +101,12 @@ impl Memory {
         self.main_ram[ram_addr as usize] = value;
     }
 
+    /// Write to main RAM as if the given bank was mapped to $D000-$DFFF.
+    pub fn write_main_ram_bank(&mut self, bank: u8, address: u16, value: u8) {
+        let ram_addr = map_ram_address(address, bank & 0x07);
+        self.main_ram[ram_addr as usize] = value;
+    }
+
     pub fn read_hram(&self, address: u16) -> u8 {
         self.hram[(address & 0x7F) as usize]
     }
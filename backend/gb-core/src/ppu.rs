@@ -17,7 +17,7 @@ use jgenesis_common::num::GetBit;
 use jgenesis_proc_macros::{FakeDecode, FakeEncode};
 use std::ops::{Deref, DerefMut, Range};
 
-const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_WIDTH: usize = 160;
 const SCREEN_HEIGHT: usize = 144;
 
 pub const FRAME_BUFFER_LEN: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
@@ -404,6 +404,10 @@ impl Ppu {
         &self.frame_buffer
     }
 
+    pub fn scanline(&self) -> u8 {
+        self.state.scanline
+    }
+
     pub fn frame_complete(&self) -> bool {
         self.state.frame_complete
     }
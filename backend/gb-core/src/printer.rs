@@ -0,0 +1,491 @@
+//! Game Boy Printer, attached to the serial port
+//!
+//! The Game Boy always provides the serial clock when talking to the printer. Each packet looks
+//! like this:
+//!   $88 $33 <command> <compression> <length lo> <length hi> <data...> <checksum lo> <checksum hi> $00 $00
+//!
+//! The printer responds $00 to every byte except the last two: it responds $81 ("device is
+//! connected") during the first trailing $00 and its status byte during the second.
+//!
+//! Printed strips are collected into a page until a print command specifies a bottom margin (i.e.
+//! the paper is fed past the print head), at which point the page is made available to the
+//! frontend as a [`PrintedImage`].
+
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+use std::collections::VecDeque;
+
+pub const PRINTER_WIDTH: u32 = 160;
+
+const INITIALIZE_COMMAND: u8 = 0x01;
+const PRINT_COMMAND: u8 = 0x02;
+const DATA_COMMAND: u8 = 0x04;
+
+const KEEP_ALIVE_RESPONSE: u8 = 0x81;
+
+const STATUS_CHECKSUM_ERROR: u8 = 1 << 0;
+const STATUS_PRINTING: u8 = 1 << 1;
+const STATUS_IMAGE_DATA_FULL: u8 = 1 << 2;
+const STATUS_UNPROCESSED_DATA: u8 = 1 << 3;
+
+// 20 tiles per row * 16 bytes per tile
+const TILE_ROW_LEN: usize = 20 * 16;
+
+// The printer's buffer holds up to 9 data packets of 2 tile rows each (160x144 pixels)
+const MAX_BUFFER_LEN: usize = 9 * 2 * TILE_ROW_LEN;
+
+// Printing speed in link clock cycles (2.097152 MHz) per pixel line; roughly 1 second per 64 lines
+const PRINTING_CYCLES_PER_LINE: u64 = 2_097_152 / 64;
+
+const DEFAULT_PALETTE: u8 = 0xE4;
+const SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct PrintedImage {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit grayscale pixels in row-major order, 0 = black and 255 = white
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+enum PacketState {
+    MagicFirst,
+    MagicSecond,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    KeepAlive,
+    Status,
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct GameBoyPrinter {
+    // Serial shift registers
+    shift_in: u8,
+    shift_out: u8,
+    bits_received: u8,
+    // Packet being received
+    state: PacketState,
+    command: u8,
+    compressed: bool,
+    length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+    received_checksum: u16,
+    checksum_error: bool,
+    // Printer state
+    buffer: Vec<u8>,
+    printing_until: u64,
+    page: Vec<u8>,
+    printed_images: VecDeque<PrintedImage>,
+}
+
+impl GameBoyPrinter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            shift_in: 0,
+            shift_out: 0,
+            bits_received: 0,
+            state: PacketState::MagicFirst,
+            command: 0,
+            compressed: false,
+            length: 0,
+            data: Vec::new(),
+            checksum: 0,
+            received_checksum: 0,
+            checksum_error: false,
+            buffer: Vec::with_capacity(MAX_BUFFER_LEN),
+            printing_until: 0,
+            page: Vec::new(),
+            printed_images: VecDeque::new(),
+        }
+    }
+
+    /// Handle a clock pulse from the Game Boy. `link_clock` is the Game Boy's current
+    /// [`link_clock`](crate::api::GameBoyEmulator::link_clock) value, used to time printing.
+    /// Returns the bit to send back.
+    pub fn receive_clock_pulse(&mut self, bit: bool, link_clock: u64) -> bool {
+        let out_bit = self.shift_out.bit(7);
+        self.shift_out <<= 1;
+
+        self.shift_in = (self.shift_in << 1) | u8::from(bit);
+        self.bits_received += 1;
+        if self.bits_received == 8 {
+            self.bits_received = 0;
+            self.shift_out = self.receive_byte(self.shift_in, link_clock);
+        }
+
+        out_bit
+    }
+
+    // Returns the byte to send during the next transfer
+    fn receive_byte(&mut self, byte: u8, link_clock: u64) -> u8 {
+        match self.state {
+            PacketState::MagicFirst => {
+                if byte == 0x88 {
+                    self.state = PacketState::MagicSecond;
+                }
+            }
+            PacketState::MagicSecond => {
+                self.state = match byte {
+                    0x33 => PacketState::Command,
+                    0x88 => PacketState::MagicSecond,
+                    _ => PacketState::MagicFirst,
+                };
+            }
+            PacketState::Command => {
+                self.command = byte;
+                self.checksum = byte.into();
+                self.state = PacketState::Compression;
+            }
+            PacketState::Compression => {
+                self.compressed = byte.bit(0);
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.state = PacketState::LengthLow;
+            }
+            PacketState::LengthLow => {
+                self.length = byte.into();
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.state = PacketState::LengthHigh;
+            }
+            PacketState::LengthHigh => {
+                self.length |= u16::from(byte) << 8;
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                self.data.clear();
+                self.state =
+                    if self.length == 0 { PacketState::ChecksumLow } else { PacketState::Data };
+            }
+            PacketState::Data => {
+                self.data.push(byte);
+                self.checksum = self.checksum.wrapping_add(byte.into());
+                if self.data.len() == usize::from(self.length) {
+                    self.state = PacketState::ChecksumLow;
+                }
+            }
+            PacketState::ChecksumLow => {
+                self.received_checksum = byte.into();
+                self.state = PacketState::ChecksumHigh;
+            }
+            PacketState::ChecksumHigh => {
+                self.received_checksum |= u16::from(byte) << 8;
+                self.checksum_error = self.received_checksum != self.checksum;
+                self.state = PacketState::KeepAlive;
+                return KEEP_ALIVE_RESPONSE;
+            }
+            PacketState::KeepAlive => {
+                if !self.checksum_error {
+                    self.execute_command(link_clock);
+                } else {
+                    log::debug!("GB Printer checksum error in command {:02X}", self.command);
+                }
+
+                self.state = PacketState::Status;
+                return self.status(link_clock);
+            }
+            PacketState::Status => {
+                self.state = PacketState::MagicFirst;
+            }
+        }
+
+        0x00
+    }
+
+    fn execute_command(&mut self, link_clock: u64) {
+        log::trace!("GB Printer command {:02X}, length {}", self.command, self.data.len());
+
+        match self.command {
+            INITIALIZE_COMMAND => {
+                self.buffer.clear();
+            }
+            PRINT_COMMAND => self.print(link_clock),
+            DATA_COMMAND => {
+                let data = std::mem::take(&mut self.data);
+                if self.compressed {
+                    decompress_rle(&data, &mut self.buffer);
+                } else {
+                    self.buffer.extend_from_slice(&data);
+                }
+                self.buffer.truncate(MAX_BUFFER_LEN);
+                self.data = data;
+            }
+            // Status inquiry ($0F) has no effect other than returning status
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, link_clock: u64) {
+        // Exposure (4th byte) only affects print darkness on real hardware
+        let [sheets, margins, palette, _, ..] = self.data[..] else { return };
+
+        // Many games write 0 to mean the default palette
+        let palette = if palette == 0 { DEFAULT_PALETTE } else { palette };
+
+        let lines = render_tile_rows(&self.buffer, palette, &mut self.page);
+        self.buffer.clear();
+        self.printing_until = link_clock + lines * PRINTING_CYCLES_PER_LINE;
+
+        // Sheets=0 only feeds paper; a non-zero bottom margin also feeds the printed page out
+        let bottom_margin = margins & 0x0F;
+        if (sheets == 0 || bottom_margin != 0) && !self.page.is_empty() {
+            let pixels = std::mem::take(&mut self.page);
+            let height = (pixels.len() / PRINTER_WIDTH as usize) as u32;
+            self.printed_images.push_back(PrintedImage { width: PRINTER_WIDTH, height, pixels });
+        }
+    }
+
+    fn status(&self, link_clock: u64) -> u8 {
+        let mut status = 0;
+
+        if self.checksum_error {
+            status |= STATUS_CHECKSUM_ERROR;
+        }
+        if link_clock < self.printing_until {
+            status |= STATUS_PRINTING;
+        }
+        if self.buffer.len() >= MAX_BUFFER_LEN {
+            status |= STATUS_IMAGE_DATA_FULL;
+        }
+        if !self.buffer.is_empty() {
+            status |= STATUS_UNPROCESSED_DATA;
+        }
+
+        status
+    }
+
+    /// Take the oldest page that has finished printing, if any.
+    pub fn take_printed_image(&mut self) -> Option<PrintedImage> {
+        self.printed_images.pop_front()
+    }
+}
+
+impl Default for GameBoyPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Bit 7 set: repeat the next byte (N & $7F) + 2 times
+// Bit 7 clear: copy the next N + 1 bytes as-is
+fn decompress_rle(data: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < data.len() {
+        let control = data[i];
+        i += 1;
+
+        if control.bit(7) {
+            let Some(&value) = data.get(i) else { break };
+            i += 1;
+
+            let count = usize::from(control & 0x7F) + 2;
+            out.extend(std::iter::repeat_n(value, count));
+        } else {
+            let count = usize::from(control) + 1;
+            let end = (i + count).min(data.len());
+            out.extend_from_slice(&data[i..end]);
+            i = end;
+        }
+    }
+}
+
+// Returns the number of pixel lines rendered
+fn render_tile_rows(buffer: &[u8], palette: u8, out: &mut Vec<u8>) -> u64 {
+    let mut lines = 0;
+
+    for tile_row in buffer.chunks_exact(TILE_ROW_LEN) {
+        for fine_y in 0..8 {
+            for tile in tile_row.chunks_exact(16) {
+                let lsb = tile[2 * fine_y];
+                let msb = tile[2 * fine_y + 1];
+
+                for fine_x in (0..8).rev() {
+                    let color = (u8::from(msb.bit(fine_x)) << 1) | u8::from(lsb.bit(fine_x));
+                    let shade = (palette >> (2 * color)) & 3;
+                    out.push(SHADES[shade as usize]);
+                }
+            }
+
+            lines += 1;
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS_COMMAND: u8 = 0x0F;
+
+    fn send_byte(printer: &mut GameBoyPrinter, byte: u8, link_clock: u64) -> u8 {
+        (0..8).rev().fold(0, |response, i| {
+            let bit = printer.receive_clock_pulse(byte.bit(i), link_clock);
+            (response << 1) | u8::from(bit)
+        })
+    }
+
+    fn packet(command: u8, compressed: bool, data: &[u8]) -> Vec<u8> {
+        let length = data.len() as u16;
+        let mut packet = vec![command, compressed.into()];
+        packet.extend(length.to_le_bytes());
+        packet.extend_from_slice(data);
+
+        let checksum = packet.iter().fold(0_u16, |sum, &byte| sum.wrapping_add(byte.into()));
+
+        let mut bytes = vec![0x88, 0x33];
+        bytes.extend(packet);
+        bytes.extend(checksum.to_le_bytes());
+        bytes.extend([0x00, 0x00]);
+        bytes
+    }
+
+    // Returns the printer's responses to the last two bytes (keep-alive and status)
+    fn send_packet(printer: &mut GameBoyPrinter, bytes: &[u8], link_clock: u64) -> (u8, u8) {
+        let responses: Vec<_> =
+            bytes.iter().map(|&byte| send_byte(printer, byte, link_clock)).collect();
+
+        let (body, trailer) = responses.split_last_chunk::<2>().unwrap();
+        assert!(body.iter().all(|&response| response == 0x00), "responses: {responses:02X?}");
+
+        (trailer[0], trailer[1])
+    }
+
+    #[test]
+    fn initialize_and_status() {
+        let mut printer = GameBoyPrinter::new();
+
+        assert_eq!(
+            send_packet(&mut printer, &packet(INITIALIZE_COMMAND, false, &[]), 0),
+            (0x81, 0)
+        );
+        assert_eq!(send_packet(&mut printer, &packet(STATUS_COMMAND, false, &[]), 0), (0x81, 0));
+    }
+
+    #[test]
+    fn ignores_bytes_before_magic() {
+        let mut printer = GameBoyPrinter::new();
+
+        let mut bytes = vec![0x00, 0x12, 0x88, 0x88];
+        bytes.extend(&packet(STATUS_COMMAND, false, &[])[1..]);
+        assert_eq!(send_packet(&mut printer, &bytes, 0), (0x81, 0));
+    }
+
+    #[test]
+    fn checksum_error() {
+        let mut printer = GameBoyPrinter::new();
+
+        let mut bytes = packet(DATA_COMMAND, false, &[0x55; TILE_ROW_LEN]);
+        let checksum_index = bytes.len() - 4;
+        bytes[checksum_index] ^= 1;
+
+        // Data from a packet with a bad checksum is discarded
+        assert_eq!(send_packet(&mut printer, &bytes, 0), (0x81, STATUS_CHECKSUM_ERROR));
+        assert!(printer.buffer.is_empty());
+
+        // Error clears on the next valid packet
+        assert_eq!(send_packet(&mut printer, &packet(STATUS_COMMAND, false, &[]), 0), (0x81, 0));
+    }
+
+    #[test]
+    fn data_fills_buffer() {
+        let mut printer = GameBoyPrinter::new();
+
+        let bytes = packet(DATA_COMMAND, false, &[0x00; 2 * TILE_ROW_LEN]);
+        for _ in 0..8 {
+            assert_eq!(send_packet(&mut printer, &bytes, 0), (0x81, STATUS_UNPROCESSED_DATA));
+        }
+        assert_eq!(
+            send_packet(&mut printer, &bytes, 0),
+            (0x81, STATUS_UNPROCESSED_DATA | STATUS_IMAGE_DATA_FULL)
+        );
+
+        // Buffer never grows past the printer's capacity
+        send_packet(&mut printer, &bytes, 0);
+        assert_eq!(printer.buffer.len(), MAX_BUFFER_LEN);
+
+        assert_eq!(
+            send_packet(&mut printer, &packet(INITIALIZE_COMMAND, false, &[]), 0),
+            (0x81, 0)
+        );
+    }
+
+    #[test]
+    fn rle_decompression() {
+        let mut out = Vec::new();
+        decompress_rle(&[0x81, 0xAA, 0x02, 0x01, 0x02, 0x03, 0x80, 0x55], &mut out);
+        assert_eq!(out, [0xAA, 0xAA, 0xAA, 0x01, 0x02, 0x03, 0x55, 0x55]);
+
+        // Truncated input decompresses as much as is available
+        let mut out = Vec::new();
+        decompress_rle(&[0x03, 0x01, 0x02], &mut out);
+        assert_eq!(out, [0x01, 0x02]);
+
+        let mut out = Vec::new();
+        decompress_rle(&[0x85], &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn compressed_data_packet() {
+        let mut uncompressed = GameBoyPrinter::new();
+        let mut data = vec![0xFF; TILE_ROW_LEN / 2];
+        data.extend([0x0F; TILE_ROW_LEN / 2]);
+        send_packet(&mut uncompressed, &packet(DATA_COMMAND, false, &data), 0);
+
+        // Runs can be at most 129 bytes long, so each 160-byte run is split into 129 + 31
+        let mut compressed = GameBoyPrinter::new();
+        let rle = [0xFF, 0xFF, 0x9D, 0xFF, 0xFF, 0x0F, 0x9D, 0x0F];
+        send_packet(&mut compressed, &packet(DATA_COMMAND, true, &rle), 0);
+
+        assert_eq!(uncompressed.buffer.len(), TILE_ROW_LEN);
+        assert_eq!(compressed.buffer, uncompressed.buffer);
+    }
+
+    #[test]
+    fn print_page() {
+        let mut printer = GameBoyPrinter::new();
+
+        // Tile row where every pixel is color 3 in the first tile and color 0 elsewhere
+        let mut tile_row = vec![0x00; TILE_ROW_LEN];
+        tile_row[..16].fill(0xFF);
+        send_packet(&mut printer, &packet(DATA_COMMAND, false, &tile_row), 0);
+
+        // 1 sheet, no top margin, bottom margin of 3, default palette
+        let (_, status) =
+            send_packet(&mut printer, &packet(PRINT_COMMAND, false, &[1, 0x03, 0xE4, 0x40]), 0);
+        assert_eq!(status, STATUS_PRINTING);
+
+        let busy_until = 8 * PRINTING_CYCLES_PER_LINE;
+        let status_packet = packet(STATUS_COMMAND, false, &[]);
+        assert_eq!(send_packet(&mut printer, &status_packet, busy_until - 1).1, STATUS_PRINTING);
+        assert_eq!(send_packet(&mut printer, &status_packet, busy_until).1, 0);
+
+        let image = printer.take_printed_image().unwrap();
+        assert_eq!((image.width, image.height), (PRINTER_WIDTH, 8));
+        for row in image.pixels.chunks_exact(PRINTER_WIDTH as usize) {
+            assert!(row[..8].iter().all(|&pixel| pixel == 0x00));
+            assert!(row[8..].iter().all(|&pixel| pixel == 0xFF));
+        }
+        assert_eq!(printer.take_printed_image(), None);
+    }
+
+    #[test]
+    fn pages_without_bottom_margin_are_joined() {
+        let mut printer = GameBoyPrinter::new();
+        let data = packet(DATA_COMMAND, false, &[0x00; TILE_ROW_LEN]);
+
+        send_packet(&mut printer, &data, 0);
+        send_packet(&mut printer, &packet(PRINT_COMMAND, false, &[1, 0x00, 0xE4, 0x40]), 0);
+        assert_eq!(printer.take_printed_image(), None);
+
+        send_packet(&mut printer, &data, 0);
+        send_packet(&mut printer, &packet(PRINT_COMMAND, false, &[1, 0x01, 0xE4, 0x40]), 0);
+        assert_eq!(printer.take_printed_image().map(|image| image.height), Some(16));
+    }
+}
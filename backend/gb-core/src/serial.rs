@@ -1,18 +1,28 @@
 //! Game Boy serial port
 //!
-//! Accessories that use the serial port (e.g. link cable) are not emulated, but some games depend
-//! on the serial port responding correctly to reads/writes.
+//! Data is shifted one bit at a time, MSB first, on either the internal serial clock or on clock
+//! pulses received from a linked Game Boy. Each clock pulse exchanges one bit in both directions.
+//!
+//! When a link cable is connected, internal clock pulses are queued for the frontend to deliver to
+//! the other Game Boy, and the transfer does not complete until the other Game Boy has responded to
+//! every pulse. When no link
+//! cable is connected, the serial input line is pulled high and transfers always receive $FF.
 
 use crate::HardwareMode;
+use crate::cgb::CpuSpeed;
 use crate::interrupts::InterruptRegisters;
 use crate::sm83::InterruptType;
 use bincode::{Decode, Encode};
 use jgenesis_common::num::GetBit;
+use std::collections::VecDeque;
 
-// Base serial transfer rate is 8192 bits/second == 1024 bytes/second
+// Base serial transfer rate is 8192 bits/second
 // The normal-speed CPU M-cycle clock is 1.048576 MHz
-// (1048576 cycles/second) / (1024 bytes/second) == 1024 cycles/byte
-const BASE_CYCLES_PER_BYTE: u32 = 1024;
+// (1048576 cycles/second) / (8192 bits/second) == 128 cycles/bit
+const BASE_CYCLES_PER_BIT: u32 = 128;
+
+// GBC high-speed serial clock is 32x faster (262144 bits/second)
+const HIGH_SPEED_CYCLES_PER_BIT: u32 = BASE_CYCLES_PER_BIT / 32;
 
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct SerialPort {
@@ -20,8 +30,13 @@ pub struct SerialPort {
     transfer_enabled: bool,
     gbc_high_speed: bool,
     internal_clock: bool,
-    transfer_cycles_remaining: u32,
+    bit_cycles_remaining: u32,
+    bits_remaining: u8,
     transfer_data: u8,
+    link_connected: bool,
+    outgoing_pulses: VecDeque<bool>,
+    pending_responses: u8,
+    link_clock: u64,
 }
 
 impl SerialPort {
@@ -31,23 +46,118 @@ impl SerialPort {
             transfer_enabled: false,
             gbc_high_speed: false,
             internal_clock: false,
-            transfer_cycles_remaining: 0,
+            bit_cycles_remaining: 0,
+            bits_remaining: 0,
             transfer_data: 0,
+            link_connected: false,
+            outgoing_pulses: VecDeque::new(),
+            pending_responses: 0,
+            link_clock: 0,
         }
     }
 
-    pub fn tick(&mut self, interrupt_registers: &mut InterruptRegisters) {
-        if !self.transfer_enabled || !self.internal_clock || self.transfer_cycles_remaining == 0 {
+    pub fn tick(&mut self, speed: CpuSpeed, interrupt_registers: &mut InterruptRegisters) {
+        // Link clock counts double-speed M-cycles so that it advances at the same real-time rate
+        // regardless of CPU speed
+        self.link_clock += match speed {
+            CpuSpeed::Normal => 2,
+            CpuSpeed::Double => 1,
+        };
+
+        if !self.transfer_enabled || !self.internal_clock || self.bits_remaining == 0 {
+            return;
+        }
+
+        self.bit_cycles_remaining -= 1;
+        if self.bit_cycles_remaining != 0 {
             return;
         }
+        self.bit_cycles_remaining = self.cycles_per_bit();
+
+        let out_bit = self.transfer_data.bit(7);
+        self.transfer_data <<= 1;
+        self.bits_remaining -= 1;
+
+        if self.link_connected {
+            // Received bit will be filled in when the other Game Boy responds
+            self.outgoing_pulses.push_back(out_bit);
+            self.pending_responses += 1;
+        } else {
+            self.transfer_data |= 1;
+        }
+
+        self.check_transfer_complete(interrupt_registers);
+    }
 
-        self.transfer_cycles_remaining -= 1;
-        if self.transfer_cycles_remaining == 0 {
+    fn cycles_per_bit(&self) -> u32 {
+        if self.gbc_high_speed { HIGH_SPEED_CYCLES_PER_BIT } else { BASE_CYCLES_PER_BIT }
+    }
+
+    fn check_transfer_complete(&mut self, interrupt_registers: &mut InterruptRegisters) {
+        if self.transfer_enabled && self.bits_remaining == 0 && self.pending_responses == 0 {
             self.transfer_enabled = false;
             interrupt_registers.set_flag(InterruptType::Serial);
         }
     }
 
+    pub fn link_clock(&self) -> u64 {
+        self.link_clock
+    }
+
+    pub fn set_link_connected(
+        &mut self,
+        connected: bool,
+        interrupt_registers: &mut InterruptRegisters,
+    ) {
+        if self.link_connected == connected {
+            return;
+        }
+
+        self.link_connected = connected;
+
+        if !connected {
+            // Any bits still in flight read as 1 now that the serial input line is floating
+            while self.pending_responses != 0 {
+                self.receive_response(true, interrupt_registers);
+            }
+            self.outgoing_pulses.clear();
+        }
+    }
+
+    pub fn pop_outgoing_pulse(&mut self) -> Option<bool> {
+        self.outgoing_pulses.pop_front()
+    }
+
+    /// Handle a clock pulse from the linked Game Boy. Returns the bit to send back.
+    pub fn receive_clock_pulse(
+        &mut self,
+        in_bit: bool,
+        interrupt_registers: &mut InterruptRegisters,
+    ) -> bool {
+        let out_bit = self.transfer_data.bit(7);
+
+        if self.transfer_enabled && !self.internal_clock && self.bits_remaining != 0 {
+            self.transfer_data = (self.transfer_data << 1) | u8::from(in_bit);
+            self.bits_remaining -= 1;
+            self.check_transfer_complete(interrupt_registers);
+        }
+
+        out_bit
+    }
+
+    /// Handle the linked Game Boy's response to the oldest unanswered internal clock pulse.
+    pub fn receive_response(&mut self, in_bit: bool, interrupt_registers: &mut InterruptRegisters) {
+        if self.pending_responses == 0 {
+            return;
+        }
+
+        // Every pulse sent after the one being answered shifted the data left by one more bit
+        self.pending_responses -= 1;
+        self.transfer_data |= u8::from(in_bit) << self.pending_responses;
+
+        self.check_transfer_complete(interrupt_registers);
+    }
+
     // $FF01: SB (Serial transfer data)
     pub fn read_data(&self) -> u8 {
         self.transfer_data
@@ -73,8 +183,10 @@ impl SerialPort {
         self.gbc_high_speed = self.hardware_mode == HardwareMode::Cgb && value.bit(1);
         self.internal_clock = value.bit(0);
 
-        if self.transfer_enabled && self.internal_clock {
-            self.transfer_cycles_remaining = BASE_CYCLES_PER_BYTE >> u8::from(self.gbc_high_speed);
+        if self.transfer_enabled {
+            self.bits_remaining = 8;
+            self.bit_cycles_remaining = self.cycles_per_bit();
+            self.pending_responses = 0;
         }
 
         log::trace!("SC write: {value:02X}");
@@ -83,3 +195,164 @@ impl SerialPort {
         log::trace!("  Internal clock: {}", self.internal_clock);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERIAL_MASK: u8 = 1 << 3;
+
+    struct TestPort {
+        serial: SerialPort,
+        interrupts: InterruptRegisters,
+    }
+
+    impl TestPort {
+        fn new(hardware_mode: HardwareMode) -> Self {
+            Self {
+                serial: SerialPort::new(hardware_mode),
+                interrupts: InterruptRegisters::default(),
+            }
+        }
+
+        fn linked() -> Self {
+            let mut port = Self::new(HardwareMode::Dmg);
+            port.serial.set_link_connected(true, &mut port.interrupts);
+            port
+        }
+
+        fn start_transfer(&mut self, data: u8, control: u8) {
+            self.serial.write_data(data);
+            self.serial.write_control(control);
+        }
+
+        fn tick(&mut self, cycles: u32) {
+            for _ in 0..cycles {
+                self.serial.tick(CpuSpeed::Normal, &mut self.interrupts);
+            }
+        }
+
+        fn serial_irq_pending(&self) -> bool {
+            self.interrupts.read_if() & SERIAL_MASK != 0
+        }
+    }
+
+    // Deliver all pending clock pulses from `master` to `slave` and the responses back
+    fn exchange(master: &mut TestPort, slave: &mut TestPort) {
+        while let Some(bit) = master.serial.pop_outgoing_pulse() {
+            let response = slave.serial.receive_clock_pulse(bit, &mut slave.interrupts);
+            master.serial.receive_response(response, &mut master.interrupts);
+        }
+    }
+
+    #[test]
+    fn unlinked_transfer_receives_ff() {
+        let mut port = TestPort::new(HardwareMode::Dmg);
+        port.start_transfer(0x5A, 0x81);
+        assert_eq!(port.serial.read_control(), 0x81);
+
+        port.tick(8 * BASE_CYCLES_PER_BIT - 1);
+        assert!(!port.serial_irq_pending());
+        // 7 bits shifted out, 7 1s shifted in
+        assert_eq!(port.serial.read_data(), 0x7F);
+
+        port.tick(1);
+        assert!(port.serial_irq_pending());
+        assert_eq!(port.serial.read_data(), 0xFF);
+        assert_eq!(port.serial.read_control(), 0x01);
+    }
+
+    #[test]
+    fn external_clock_without_link_never_completes() {
+        let mut port = TestPort::new(HardwareMode::Dmg);
+        port.start_transfer(0x5A, 0x80);
+
+        port.tick(100 * BASE_CYCLES_PER_BIT);
+        assert!(!port.serial_irq_pending());
+        assert_eq!(port.serial.read_data(), 0x5A);
+        assert_eq!(port.serial.read_control(), 0x80);
+    }
+
+    #[test]
+    fn gbc_high_speed_timing() {
+        let mut port = TestPort::new(HardwareMode::Cgb);
+        port.start_transfer(0x00, 0x83);
+        port.tick(8 * HIGH_SPEED_CYCLES_PER_BIT);
+        assert!(port.serial_irq_pending());
+
+        // High speed bit is ignored on DMG
+        let mut port = TestPort::new(HardwareMode::Dmg);
+        port.start_transfer(0x00, 0x83);
+        assert_eq!(port.serial.read_control(), 0x81);
+        port.tick(8 * HIGH_SPEED_CYCLES_PER_BIT);
+        assert!(!port.serial_irq_pending());
+        port.tick(8 * BASE_CYCLES_PER_BIT - 8 * HIGH_SPEED_CYCLES_PER_BIT);
+        assert!(port.serial_irq_pending());
+    }
+
+    #[test]
+    fn linked_exchange() {
+        let mut master = TestPort::linked();
+        let mut slave = TestPort::linked();
+        slave.start_transfer(0x3C, 0x80);
+        master.start_transfer(0xA5, 0x81);
+
+        for _ in 0..8 {
+            assert!(!master.serial_irq_pending());
+            assert!(!slave.serial_irq_pending());
+
+            master.tick(BASE_CYCLES_PER_BIT);
+            exchange(&mut master, &mut slave);
+        }
+
+        assert!(master.serial_irq_pending());
+        assert!(slave.serial_irq_pending());
+        assert_eq!(master.serial.read_data(), 0x3C);
+        assert_eq!(slave.serial.read_data(), 0xA5);
+    }
+
+    #[test]
+    fn transfer_waits_for_delayed_responses() {
+        let mut master = TestPort::linked();
+        let mut slave = TestPort::linked();
+        slave.start_transfer(0xC3, 0x80);
+        master.start_transfer(0x96, 0x81);
+
+        // All 8 bits are clocked out before the other side responds to any of them
+        master.tick(8 * BASE_CYCLES_PER_BIT);
+        assert!(!master.serial_irq_pending());
+        assert_eq!(master.serial.outgoing_pulses.len(), 8);
+
+        exchange(&mut master, &mut slave);
+        assert!(master.serial_irq_pending());
+        assert!(slave.serial_irq_pending());
+        assert_eq!(master.serial.read_data(), 0xC3);
+        assert_eq!(slave.serial.read_data(), 0x96);
+    }
+
+    #[test]
+    fn disconnect_completes_pending_bits_with_ones() {
+        let mut master = TestPort::linked();
+        master.start_transfer(0x00, 0x81);
+
+        master.tick(8 * BASE_CYCLES_PER_BIT);
+        assert!(!master.serial_irq_pending());
+
+        master.serial.set_link_connected(false, &mut master.interrupts);
+        assert!(master.serial_irq_pending());
+        assert_eq!(master.serial.read_data(), 0xFF);
+        assert_eq!(master.serial.pop_outgoing_pulse(), None);
+    }
+
+    #[test]
+    fn link_clock_is_independent_of_cpu_speed() {
+        let mut port = TestPort::new(HardwareMode::Cgb);
+
+        port.serial.tick(CpuSpeed::Normal, &mut port.interrupts);
+        assert_eq!(port.serial.link_clock(), 2);
+
+        port.serial.tick(CpuSpeed::Double, &mut port.interrupts);
+        port.serial.tick(CpuSpeed::Double, &mut port.interrupts);
+        assert_eq!(port.serial.link_clock(), 4);
+    }
+}
@@ -0,0 +1,260 @@
+//! Game Boy side of the Super Game Boy's ICD2 interface chip
+//!
+//! The ICD2 watches the Game Boy's JOYP writes and supplies its joypad reads. Games send command
+//! packets to the SNES by pulsing the P14/P15 select lines: a reset pulse (both low) starts each
+//! 16-byte packet, then each bit is sent by pulling P14 low (0) or P15 low (1) and then releasing
+//! both lines, LSB first, followed by a 0 stop bit.
+//!
+//! Multiplayer mode is enabled by the `MLT_REQ` command. While enabled, reading JOYP with both lines
+//! high returns the current joypad ID, and releasing both lines advances to the next joypad.
+//!
+//! Everything else (LCD capture, command interpretation) happens on the SNES side of the ICD2.
+
+use bincode::{Decode, Encode};
+use std::collections::VecDeque;
+use std::mem;
+
+pub const SGB_PACKET_LEN: usize = 16;
+
+pub type SgbPacket = [u8; SGB_PACKET_LEN];
+
+const PACKET_BITS: u8 = 8 * SGB_PACKET_LEN as u8;
+
+// The SNES side reads packets much more slowly than games can send them while it's busy
+const MAX_QUEUED_PACKETS: usize = 64;
+
+const MLT_REQ_COMMAND: u8 = 0x11;
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct SgbJoypadPort {
+    // Active low: bits 0-3 are Right/Left/Up/Down and bits 4-7 are A/B/Select/Start
+    joypads: [u8; 4],
+    // 0 = 1 player, 1 = 2 players, 3 = 4 players
+    player_mask: u8,
+    joypad_id: u8,
+    prev_select: u8,
+    transfer_active: bool,
+    ready_for_bit: bool,
+    packet: SgbPacket,
+    bits_received: u8,
+    packets: VecDeque<SgbPacket>,
+}
+
+impl SgbJoypadPort {
+    pub(crate) fn new() -> Self {
+        Self {
+            joypads: [0xFF; 4],
+            player_mask: 0,
+            joypad_id: 0,
+            prev_select: 3,
+            transfer_active: false,
+            ready_for_bit: false,
+            packet: [0; SGB_PACKET_LEN],
+            bits_received: 0,
+            packets: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn write_joyp(&mut self, value: u8) {
+        // Bit 4 = P14, bit 5 = P15
+        let select = (value >> 4) & 3;
+        let prev_select = mem::replace(&mut self.prev_select, select);
+
+        match select {
+            0 => {
+                // Reset pulse; start a new packet
+                self.transfer_active = true;
+                self.ready_for_bit = false;
+                self.packet = [0; SGB_PACKET_LEN];
+                self.bits_received = 0;
+            }
+            3 => {
+                self.ready_for_bit = true;
+
+                if prev_select != 3 {
+                    self.joypad_id = (self.joypad_id + 1) & self.player_mask;
+                }
+            }
+            _ => {
+                if !self.transfer_active || !self.ready_for_bit {
+                    return;
+                }
+                self.ready_for_bit = false;
+
+                // P15 low = 1, P14 low = 0
+                let bit = select == 1;
+
+                if self.bits_received == PACKET_BITS {
+                    self.transfer_active = false;
+                    if bit {
+                        log::debug!("SGB packet stop bit was 1; discarding packet");
+                    } else {
+                        self.packet_complete();
+                    }
+                    return;
+                }
+
+                let byte = &mut self.packet[(self.bits_received / 8) as usize];
+                *byte = (*byte >> 1) | (u8::from(bit) << 7);
+                self.bits_received += 1;
+            }
+        }
+    }
+
+    fn packet_complete(&mut self) {
+        log::trace!("Received SGB packet: {:02X?}", self.packet);
+
+        if self.packet[0] >> 3 == MLT_REQ_COMMAND {
+            // MLT_REQ needs to take effect immediately because games check for a joypad ID change
+            // right after sending it to detect the SGB
+            self.set_player_count(self.packet[1]);
+        }
+
+        if self.packets.len() == MAX_QUEUED_PACKETS {
+            log::warn!("SGB packet queue is full; dropping oldest packet");
+            self.packets.pop_front();
+        }
+        self.packets.push_back(self.packet);
+    }
+
+    pub(crate) fn set_player_count(&mut self, value: u8) {
+        self.player_mask = match value & 3 {
+            0 => 0,
+            1 => 1,
+            // 2 is not a valid player count; treat it as 4 players
+            _ => 3,
+        };
+
+        // The next time both select lines are released, the ID will advance to joypad 1
+        self.joypad_id = self.player_mask;
+    }
+
+    pub(crate) fn set_joypad(&mut self, player: usize, value: u8) {
+        self.joypads[player & 3] = value;
+    }
+
+    pub(crate) fn take_packet(&mut self) -> Option<SgbPacket> {
+        self.packets.pop_front()
+    }
+
+    pub(crate) fn read_joyp_low_nibble(&self, d_pad_selected: bool, buttons_selected: bool) -> u8 {
+        if !d_pad_selected && !buttons_selected {
+            return 0xF - self.joypad_id;
+        }
+
+        let joypad = self.joypads[self.joypad_id as usize];
+        let mut nibble = 0xF;
+        if d_pad_selected {
+            nibble &= joypad & 0xF;
+        }
+        if buttons_selected {
+            nibble &= joypad >> 4;
+        }
+        nibble
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESET: u8 = 0x00;
+    const RELEASE: u8 = 0x30;
+    const BIT_0: u8 = 0x20;
+    const BIT_1: u8 = 0x10;
+
+    fn send_bits(port: &mut SgbJoypadPort, bits: impl IntoIterator<Item = bool>) {
+        for bit in bits {
+            port.write_joyp(if bit { BIT_1 } else { BIT_0 });
+            port.write_joyp(RELEASE);
+        }
+    }
+
+    fn send_packet(port: &mut SgbJoypadPort, packet: SgbPacket, stop_bit: bool) {
+        port.write_joyp(RESET);
+        port.write_joyp(RELEASE);
+        send_bits(port, packet.iter().flat_map(|&byte| (0..8).map(move |i| byte & (1 << i) != 0)));
+        send_bits(port, [stop_bit]);
+    }
+
+    fn mlt_req(player_count: u8) -> SgbPacket {
+        let mut packet = [0; SGB_PACKET_LEN];
+        packet[0] = (MLT_REQ_COMMAND << 3) | 1;
+        packet[1] = player_count;
+        packet
+    }
+
+    #[test]
+    fn packet_assembly() {
+        let mut port = SgbJoypadPort::new();
+        let packet: SgbPacket = std::array::from_fn(|i| (i as u8).wrapping_mul(0x35) ^ 0xA5);
+
+        send_packet(&mut port, packet, false);
+        assert_eq!(port.take_packet(), Some(packet));
+        assert_eq!(port.take_packet(), None);
+
+        // A stop bit of 1 discards the packet
+        send_packet(&mut port, packet, true);
+        assert_eq!(port.take_packet(), None);
+
+        // A reset pulse partway through restarts the packet
+        port.write_joyp(RESET);
+        port.write_joyp(RELEASE);
+        send_bits(&mut port, [true; 20]);
+        send_packet(&mut port, packet, false);
+        assert_eq!(port.take_packet(), Some(packet));
+
+        // Bits are ignored outside of a transfer, and holding a line low only sends one bit
+        send_bits(&mut port, [true; 8]);
+        port.write_joyp(RESET);
+        port.write_joyp(RELEASE);
+        port.write_joyp(BIT_1);
+        port.write_joyp(BIT_1);
+        port.write_joyp(RELEASE);
+        send_bits(&mut port, [false; 127]);
+        send_bits(&mut port, [false]);
+        let mut expected = [0; SGB_PACKET_LEN];
+        expected[0] = 0x01;
+        assert_eq!(port.take_packet(), Some(expected));
+    }
+
+    #[test]
+    fn mlt_req_player_cycling() {
+        let mut port = SgbJoypadPort::new();
+        port.set_joypad(1, 0xFE);
+
+        // 1 player: ID is always 0 (reads $F)
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xF);
+        send_bits(&mut port, [false]);
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xF);
+
+        // MLT_REQ takes effect immediately; releasing the lines after the stop bit advances from
+        // the last joypad to joypad 1
+        send_packet(&mut port, mlt_req(1), false);
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xF);
+        port.write_joyp(BIT_0);
+        port.write_joyp(RELEASE);
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xE);
+        assert_eq!(port.read_joyp_low_nibble(true, false), 0xE);
+        port.write_joyp(BIT_0);
+        port.write_joyp(RELEASE);
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xF);
+        assert_eq!(port.read_joyp_low_nibble(true, false), 0xF);
+
+        // 4 players cycle through IDs 0-3
+        send_packet(&mut port, mlt_req(3), false);
+        let ids: Vec<_> = (0..5)
+            .map(|_| {
+                let id = 0xF - port.read_joyp_low_nibble(false, false);
+                port.write_joyp(BIT_0);
+                port.write_joyp(RELEASE);
+                id
+            })
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 0]);
+
+        // Player count 2 is treated as 4 players
+        port.set_player_count(2);
+        assert_eq!(port.read_joyp_low_nibble(false, false), 0xC);
+    }
+}
@@ -115,6 +115,24 @@ impl Registers {
         }
     }
 
+    fn new_super_game_boy() -> Self {
+        // Values from https://gbdev.io/pandocs/Power_Up_Sequence.html
+        // SGB sets A=$01 like DMG, but several other registers differ
+        Self {
+            a: 0x01,
+            f: Flags { zero: false, subtract: false, half_carry: false, carry: false },
+            b: 0x00,
+            c: 0x14,
+            d: 0x00,
+            e: 0x00,
+            h: 0xC0,
+            l: 0x60,
+            sp: HRAM_END,
+            pc: CARTRIDGE_ENTRY_POINT,
+            ime: false,
+        }
+    }
+
     fn bc(&self) -> u16 {
         u16::from_be_bytes([self.b, self.c])
     }
@@ -250,6 +268,13 @@ impl Sm83 {
         }
     }
 
+    pub fn new_super_game_boy() -> Self {
+        Self {
+            registers: Registers::new_super_game_boy(),
+            state: State::new(HardwareMode::Dmg, false),
+        }
+    }
+
     pub fn execute_instruction<B: BusInterface>(&mut self, bus: &mut B) {
         if self.state.executed_invalid_opcode || bus.halt() {
             // CPU is halted or frozen
@@ -1,8 +1,12 @@
 //! GBA emulator public interface and main loop
 
 pub mod debug;
+pub mod link;
 
+use crate::api::debug::{GbaDebugger, Watchpoints};
 use crate::apu::Apu;
+use crate::bios;
+use crate::bios::HleBios;
 use crate::bus::{Bus, BusState};
 use crate::cartridge::Cartridge;
 use crate::dma::DmaState;
@@ -15,10 +19,11 @@ use crate::prefetch::GamePakPrefetcher;
 use crate::scheduler::{Scheduler, SchedulerEvent};
 use crate::sio::SerialPort;
 use crate::timers::Timers;
+use arm7tdmi_emu::Arm7Tdmi;
 use arm7tdmi_emu::bus::BusInterface;
-use arm7tdmi_emu::{Arm7Tdmi, Arm7TdmiResetArgs, CpuMode};
 use bincode::{Decode, Encode};
 use gba_config::{GbaAspectRatio, GbaAudioInterpolation, GbaButton, GbaInputs, GbaSaveMemory};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, Color, ColorCorrection, EmulatorConfigTrait, EmulatorTrait, InputPoller,
     RenderFrameOptions, Renderer, SaveWriter, TickEffect, TickResult,
@@ -27,6 +32,9 @@ use jgenesis_proc_macros::{ConfigDisplay, PartialClone};
 use std::fmt::{Debug, Display};
 use thiserror::Error;
 
+// Stop mode arbitrarily advances by the equivalent of 2048 clock cycles per tick
+const STOPPED_TICK_CYCLES: u64 = 2048;
+
 // Roughly 59.73 fps
 const TARGET_FPS: f64 =
     (crate::GBA_CLOCK_SPEED as f64) / (ppu::LINES_PER_FRAME as f64) / (ppu::DOTS_PER_LINE as f64);
@@ -119,6 +127,7 @@ pub struct GameBoyAdvanceEmulator {
     cpu: Arm7Tdmi<Bus>,
     #[partial_clone(partial)]
     bus: Bus,
+    hle_bios: Option<HleBios>,
     config: GbaEmulatorConfig,
     last_apu_sync_cycles: u64,
     frame_count: u64,
@@ -126,25 +135,34 @@ pub struct GameBoyAdvanceEmulator {
 }
 
 impl GameBoyAdvanceEmulator {
+    /// If `bios_rom` is `None`, the emulator uses a built-in high-level emulated BIOS. This always
+    /// skips the BIOS boot animation.
+    ///
     /// # Errors
     ///
     /// Returns an error if emulator initialization fails, e.g. because the BIOS ROM is invalid.
     pub fn create<S: SaveWriter>(
         rom: Vec<u8>,
-        bios_rom: Vec<u8>,
+        bios_rom: Option<Vec<u8>>,
         config: GbaEmulatorConfig,
         save_writer: &mut S,
     ) -> Result<Self, GbaLoadError> {
         let initial_save = save_writer.load_bytes("sav").ok();
         let initial_rtc = save_writer.load_serialized("rtc").ok();
 
-        let memory = Memory::new(bios_rom, config)?;
+        let hle_bios = bios_rom.is_none().then(HleBios::new);
+        let bios_rom = bios_rom.unwrap_or_else(bios::hle_bios_rom);
+
+        // The built-in BIOS has no boot animation
+        let skip_bios_animation = config.skip_bios_animation || hle_bios.is_some();
+
+        let memory = Memory::new(bios_rom, skip_bios_animation)?;
         let cartridge =
             Cartridge::new(rom, initial_save, initial_rtc, config.forced_save_memory_type);
 
         let mut cpu = Arm7Tdmi::new();
         let mut bus = Bus {
-            ppu: Ppu::new(config.skip_bios_animation),
+            ppu: Ppu::new(skip_bios_animation),
             apu: Apu::new(config.audio),
             memory,
             cartridge,
@@ -156,23 +174,16 @@ impl GameBoyAdvanceEmulator {
             inputs: InputState::new(),
             state: BusState::new(),
             scheduler: Scheduler::new(),
+            watchpoints: Watchpoints::default(),
         };
 
-        if !config.skip_bios_animation {
+        if !skip_bios_animation {
             cpu.reset(&mut bus);
         } else {
-            // Based on values that the GBA BIOS sets
-            cpu.manual_reset(
-                Arm7TdmiResetArgs {
-                    pc: 0x8000000,
-                    sp_usr: 0x3007F00,
-                    sp_svc: 0x3007FE0,
-                    sp_irq: 0x3007FA0,
-                    sp_fiq: 0,
-                    mode: CpuMode::System,
-                },
-                &mut bus,
-            );
+            cpu.manual_reset(bios::post_boot_reset_args(0x8000000), &mut bus);
+            if hle_bios.is_some() {
+                bios::init_post_boot_state(&mut bus);
+            }
         }
 
         // Schedule initial PPU event to guarantee that PPU starts running even if never accessed
@@ -181,6 +192,7 @@ impl GameBoyAdvanceEmulator {
         Ok(Self {
             cpu,
             bus,
+            hle_bios,
             config,
             last_apu_sync_cycles: 0,
             frame_count: 0,
@@ -193,6 +205,20 @@ impl GameBoyAdvanceEmulator {
         self.bus.cartridge.rw_memory().is_some()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no save memory.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        self.bus.cartridge.rw_memory()
+    }
+
+    /// Largest size that [`Self::save_ram`] can have. This is non-zero even if the save memory type
+    /// has not been auto-detected yet, which happens the first time the game accesses it.
+    #[must_use]
+    pub fn max_save_ram_len(&self) -> usize {
+        self.bus.cartridge.max_rw_memory_len()
+    }
+
     fn drain_apu<A: AudioOutput>(&mut self, audio_output: &mut A) -> Result<(), A::Err> {
         self.bus.apu.step_to(self.bus.state.cycles);
         self.bus.apu.drain_audio_output(audio_output)?;
@@ -207,9 +233,6 @@ impl GameBoyAdvanceEmulator {
         renderer: &mut R,
         audio_output: &mut A,
     ) -> TickResult<GbaError<R::Err, A::Err, S::Err>> {
-        // Arbitrarily advance by the equivalent of 2048 clock cycles
-        const INCREMENT: u64 = 2048;
-
         const CYCLES_PER_FRAME: u64 = (ppu::LINES_PER_FRAME * ppu::DOTS_PER_LINE) as u64;
 
         // Actual hardware turns off the screen in stop mode; emulate this by displaying solid white
@@ -223,7 +246,8 @@ impl GameBoyAdvanceEmulator {
         }
 
         // Output constant 0s for audio
-        self.stop_state.audio_output_counter += INCREMENT * self.stop_state.output_frequency;
+        self.stop_state.audio_output_counter +=
+            STOPPED_TICK_CYCLES * self.stop_state.output_frequency;
         while self.stop_state.audio_output_counter >= crate::GBA_CLOCK_SPEED {
             self.stop_state.audio_output_counter -= crate::GBA_CLOCK_SPEED;
             audio_output.push_sample(0.0, 0.0).map_err(GbaError::Audio)?;
@@ -232,7 +256,7 @@ impl GameBoyAdvanceEmulator {
         let mut tick_effect = TickEffect::None;
 
         // Repeatedly render a blank frame
-        self.stop_state.render_counter += INCREMENT;
+        self.stop_state.render_counter += STOPPED_TICK_CYCLES;
         while self.stop_state.render_counter >= CYCLES_PER_FRAME {
             self.stop_state.render_counter -= CYCLES_PER_FRAME;
             renderer
@@ -248,7 +272,7 @@ impl GameBoyAdvanceEmulator {
 
         match &mut self.stop_state.cycles_remaining {
             Some(cycles_remaining) => {
-                *cycles_remaining = cycles_remaining.saturating_sub(INCREMENT);
+                *cycles_remaining = cycles_remaining.saturating_sub(STOPPED_TICK_CYCLES);
                 if *cycles_remaining == 0 {
                     // Stop has ended
                     self.stop_state.stopped_last_tick = false;
@@ -266,32 +290,20 @@ impl GameBoyAdvanceEmulator {
 
         Ok(tick_effect)
     }
-}
-
-impl EmulatorConfigTrait for GbaEmulatorConfig {}
-
-impl EmulatorTrait for GameBoyAdvanceEmulator {
-    type Button = GbaButton;
-    type Inputs = GbaInputs;
-    type Config = GbaEmulatorConfig;
-    type Err<
-        RErr: Debug + Display + Send + Sync + 'static,
-        AErr: Debug + Display + Send + Sync + 'static,
-        SErr: Debug + Display + Send + Sync + 'static,
-    > = GbaError<RErr, AErr, SErr>;
 
     #[inline]
-    fn tick<R, A, I, S>(
+    fn tick_inner<const DEBUG: bool, R, A, I, S>(
         &mut self,
         renderer: &mut R,
         audio_output: &mut A,
         input_poller: &mut I,
         save_writer: &mut S,
-    ) -> TickResult<Self::Err<R::Err, A::Err, S::Err>>
+        mut debugger: Option<&mut GbaDebugger>,
+    ) -> TickResult<GbaError<R::Err, A::Err, S::Err>>
     where
         R: Renderer,
         A: AudioOutput,
-        I: InputPoller<Self::Inputs>,
+        I: InputPoller<GbaInputs>,
         S: SaveWriter,
     {
         let inputs = *input_poller.poll();
@@ -312,7 +324,21 @@ impl EmulatorTrait for GameBoyAdvanceEmulator {
         // This is difficult/impossible to implement without being able to suspend CPU execution
         // mid-instruction
         if !self.bus.interrupts.cpu_halted() {
-            self.cpu.execute_instruction(&mut self.bus);
+            if DEBUG && let Some(debugger) = &mut debugger {
+                debugger.before_instruction(self);
+            }
+
+            if let Some(hle_bios) = &mut self.hle_bios
+                && self.cpu.next_instruction_address() == bios::SWI_VECTOR
+            {
+                hle_bios.handle_swi(&mut self.cpu, &mut self.bus);
+            } else {
+                self.cpu.execute_instruction(&mut self.bus);
+            }
+
+            if DEBUG && let Some(debugger) = &mut debugger {
+                debugger.after_instruction(self);
+            }
         } else {
             self.bus.internal_cycles(1);
             if !self.bus.interrupts.cpu_halted() {
@@ -326,8 +352,6 @@ impl EmulatorTrait for GameBoyAdvanceEmulator {
             self.drain_apu(audio_output).map_err(GbaError::Audio)?;
         }
 
-        self.bus.sio.check_for_interrupt(self.bus.state.cycles, &mut self.bus.interrupts);
-
         if self.bus.ppu.frame_complete() {
             self.bus.ppu.clear_frame_complete();
 
@@ -342,6 +366,8 @@ impl EmulatorTrait for GameBoyAdvanceEmulator {
                 )
                 .map_err(GbaError::Render)?;
 
+            self.bus.cartridge.apply_cheat_ram_writes(&mut self.bus.memory);
+
             self.bus.cartridge.update_rtc_time(self.bus.state.cycles, &mut self.bus.interrupts);
 
             if self.bus.cartridge.take_rw_memory_dirty()
@@ -365,6 +391,74 @@ impl EmulatorTrait for GameBoyAdvanceEmulator {
         Ok(TickEffect::None)
     }
 
+    /// Equivalent to [`EmulatorTrait::tick`], but checks breakpoints and handles commands from the
+    /// given debugger.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any errors returned by the emulator
+    pub fn debug_tick<R, A, I, S>(
+        &mut self,
+        renderer: &mut R,
+        audio_output: &mut A,
+        input_poller: &mut I,
+        save_writer: &mut S,
+        debugger: &mut GbaDebugger,
+    ) -> TickResult<GbaError<R::Err, A::Err, S::Err>>
+    where
+        R: Renderer,
+        A: AudioOutput,
+        I: InputPoller<GbaInputs>,
+        S: SaveWriter,
+    {
+        self.tick_inner::<true, _, _, _, _>(
+            renderer,
+            audio_output,
+            input_poller,
+            save_writer,
+            Some(debugger),
+        )
+    }
+}
+
+impl EmulatorConfigTrait for GbaEmulatorConfig {}
+
+impl EmulatorTrait for GameBoyAdvanceEmulator {
+    type Button = GbaButton;
+    type Inputs = GbaInputs;
+    type Config = GbaEmulatorConfig;
+
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::GameBoyAdvance;
+
+    type Err<
+        RErr: Debug + Display + Send + Sync + 'static,
+        AErr: Debug + Display + Send + Sync + 'static,
+        SErr: Debug + Display + Send + Sync + 'static,
+    > = GbaError<RErr, AErr, SErr>;
+
+    #[inline]
+    fn tick<R, A, I, S>(
+        &mut self,
+        renderer: &mut R,
+        audio_output: &mut A,
+        input_poller: &mut I,
+        save_writer: &mut S,
+    ) -> TickResult<Self::Err<R::Err, A::Err, S::Err>>
+    where
+        R: Renderer,
+        A: AudioOutput,
+        I: InputPoller<Self::Inputs>,
+        S: SaveWriter,
+    {
+        self.tick_inner::<false, _, _, _, _>(
+            renderer,
+            audio_output,
+            input_poller,
+            save_writer,
+            None,
+        )
+    }
+
     fn force_render<R>(&mut self, renderer: &mut R) -> Result<(), R::Err>
     where
         R: Renderer,
@@ -386,13 +480,17 @@ impl EmulatorTrait for GameBoyAdvanceEmulator {
         self.bus.cartridge.take_rom_from(&mut other.bus.cartridge);
     }
 
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.bus.cartridge.set_cheats(cheats);
+    }
+
     fn soft_reset(&mut self) {
         log::warn!("GBA does not support soft reset except in software");
     }
 
     fn hard_reset<S: SaveWriter>(&mut self, save_writer: &mut S) {
         let rom = self.bus.cartridge.take_rom();
-        let bios_rom = self.bus.memory.clone_bios_rom();
+        let bios_rom = self.hle_bios.is_none().then(|| self.bus.memory.clone_bios_rom());
 
         *self = Self::create(rom, bios_rom, self.config, save_writer)
             .expect("Emulator creation should never fail during hard reset");
@@ -1,7 +1,12 @@
 use crate::api::GameBoyAdvanceEmulator;
-use jgenesis_common::debug::DebugMemoryView;
+use arm7tdmi_emu::bus::OpSize;
+use jgenesis_common::debug::{AtomicWatchpointHit, DebugMemoryView, WatchpointHit};
 use jgenesis_common::frontend::Color;
-use jgenesis_proc_macros::EnumAll;
+use jgenesis_common::sync::SharedVarSender;
+use jgenesis_proc_macros::{EnumAll, FakeDecode, FakeEncode};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{Receiver, SendError, Sender, TryRecvError};
+use std::sync::{Arc, mpsc};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumAll)]
 pub enum GbaMemoryArea {
@@ -56,3 +61,470 @@ impl<'emu> GbaDebugView<'emu> {
         self.0.bus.ppu.copy_palette_ram(out);
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GbaBreakpoint {
+    pub start_address: u32,
+    pub end_address: u32,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WatchpointRanges {
+    read: Vec<(u32, u32)>,
+    write: Vec<(u32, u32)>,
+}
+
+fn access_overlaps<const SIZE: u8>(ranges: &[(u32, u32)], address: u32) -> bool {
+    let len: u32 = match SIZE {
+        OpSize::BYTE => 1,
+        OpSize::HALFWORD => 2,
+        _ => 4,
+    };
+    let start = address & !(len - 1);
+    let end = start + (len - 1);
+
+    ranges.iter().any(|&(range_start, range_end)| start <= range_end && range_start <= end)
+}
+
+/// Read/write breakpoints, checked on CPU data accesses. These live on the bus because the CPU is
+/// not generic over a debug bus type.
+///
+/// Not saved in save states; [`GbaDebugger`] restores the current ranges after a state load.
+#[derive(Debug, Clone, Default, FakeEncode, FakeDecode)]
+pub(crate) struct Watchpoints {
+    ranges: Arc<WatchpointRanges>,
+    active: bool,
+    // First access that hit a watchpoint during the current instruction
+    hit: Option<WatchpointHit>,
+}
+
+impl Watchpoints {
+    #[inline]
+    pub fn check_read<const SIZE: u8>(&mut self, address: u32) {
+        if self.active && self.hit.is_none() && access_overlaps::<SIZE>(&self.ranges.read, address)
+        {
+            self.hit = Some(WatchpointHit { address, write: false });
+        }
+    }
+
+    #[inline]
+    pub fn check_write<const SIZE: u8>(&mut self, address: u32) {
+        if self.active && self.hit.is_none() && access_overlaps::<SIZE>(&self.ranges.write, address)
+        {
+            self.hit = Some(WatchpointHit { address, write: true });
+        }
+    }
+
+    fn update(&mut self, ranges: &Arc<WatchpointRanges>) {
+        if !Arc::ptr_eq(&self.ranges, ranges) {
+            self.ranges = Arc::clone(ranges);
+            self.active = !ranges.read.is_empty() || !ranges.write.is_empty();
+            self.hit = None;
+        }
+    }
+
+    fn take_hit(&mut self) -> Option<WatchpointHit> {
+        self.hit.take()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GbaDebugCommand {
+    EditMemory(GbaMemoryArea, usize, u8),
+    UpdateBreakpoints(Vec<GbaBreakpoint>),
+    BreakPause,
+    BreakResume,
+    BreakStep,
+}
+
+/// Snapshot of CPU registers and memory, sent whenever the CPU breaks
+#[derive(Debug, Clone)]
+pub struct GbaDebugState {
+    /// R0-R15 in the current CPU mode. R15 is the address of the next instruction to execute
+    /// rather than the pipelined PC value.
+    pub registers: [u32; 16],
+    pub cpsr: u32,
+    bios_rom: Box<[u8]>,
+    ewram: Box<[u8]>,
+    iwram: Box<[u8]>,
+    palette_ram: Box<[u8]>,
+    vram: Box<[u8]>,
+    oam: Box<[u8]>,
+    cartridge_rom: Arc<[u8]>,
+}
+
+impl GbaDebugState {
+    #[must_use]
+    pub fn memory(&self, area: GbaMemoryArea) -> &[u8] {
+        match area {
+            GbaMemoryArea::CartridgeRom => &self.cartridge_rom,
+            GbaMemoryArea::Ewram => &self.ewram,
+            GbaMemoryArea::Iwram => &self.iwram,
+            GbaMemoryArea::Vram => &self.vram,
+            GbaMemoryArea::PaletteRam => &self.palette_ram,
+            GbaMemoryArea::Oam => &self.oam,
+            GbaMemoryArea::BiosRom => &self.bios_rom,
+        }
+    }
+
+    /// Apply a memory edit to this snapshot, e.g. to mirror an edit sent to the emulator using
+    /// [`GbaDebugCommand::EditMemory`]
+    pub fn apply_memory_edit(&mut self, area: GbaMemoryArea, address: usize, value: u8) {
+        let memory: &mut [u8] = match area {
+            GbaMemoryArea::CartridgeRom => Arc::make_mut(&mut self.cartridge_rom),
+            GbaMemoryArea::Ewram => &mut self.ewram,
+            GbaMemoryArea::Iwram => &mut self.iwram,
+            GbaMemoryArea::Vram => &mut self.vram,
+            GbaMemoryArea::PaletteRam => &mut self.palette_ram,
+            GbaMemoryArea::Oam => &mut self.oam,
+            GbaMemoryArea::BiosRom => &mut self.bios_rom,
+        };
+
+        if let Some(byte) = memory.get_mut(address) {
+            *byte = value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GbaBreakStatus {
+    pub breaking: bool,
+    pub pc: u32,
+    pub watchpoint: Option<WatchpointHit>,
+}
+
+pub struct GbaBreakStatusAtomic {
+    pub breaking: AtomicBool,
+    pub pc: AtomicU32,
+    pub watchpoint: AtomicWatchpointHit,
+}
+
+impl GbaBreakStatusAtomic {
+    fn new() -> Self {
+        Self {
+            breaking: AtomicBool::new(false),
+            pc: AtomicU32::new(0),
+            watchpoint: AtomicWatchpointHit::default(),
+        }
+    }
+}
+
+pub struct GbaDebugger {
+    command_receiver: Receiver<GbaDebugCommand>,
+    state_sender: SharedVarSender<GbaDebugState>,
+    execute_breakpoints: Vec<(u32, u32)>,
+    watchpoints: Arc<WatchpointRanges>,
+    break_status: Arc<GbaBreakStatusAtomic>,
+    step: Option<u32>,
+    // Cached to avoid copying up to 32MB of ROM every time the CPU breaks
+    cartridge_rom: Option<Arc<[u8]>>,
+}
+
+pub struct GbaDebuggerHandle {
+    pub command_sender: Sender<GbaDebugCommand>,
+    pub break_status: Arc<GbaBreakStatusAtomic>,
+}
+
+impl GbaDebuggerHandle {
+    /// # Errors
+    ///
+    /// Propagates any errors returned by the MPSC [`Sender`]
+    pub fn send_command(&self, command: GbaDebugCommand) -> Result<(), SendError<GbaDebugCommand>> {
+        self.command_sender.send(command)
+    }
+
+    #[must_use]
+    pub fn break_status(&self) -> GbaBreakStatus {
+        let breaking = self.break_status.breaking.load(Ordering::Acquire);
+        let pc = self.break_status.pc.load(Ordering::Relaxed);
+        let watchpoint = self.break_status.watchpoint.load();
+        GbaBreakStatus { breaking, pc, watchpoint }
+    }
+}
+
+impl GbaDebugger {
+    #[must_use]
+    pub fn new(state_sender: SharedVarSender<GbaDebugState>) -> (Self, GbaDebuggerHandle) {
+        let (command_sender, command_receiver) = mpsc::channel();
+
+        let debugger = Self {
+            command_receiver,
+            state_sender,
+            execute_breakpoints: vec![],
+            watchpoints: Arc::default(),
+            break_status: Arc::new(GbaBreakStatusAtomic::new()),
+            step: None,
+            cartridge_rom: None,
+        };
+
+        let handle =
+            GbaDebuggerHandle { command_sender, break_status: Arc::clone(&debugger.break_status) };
+
+        (debugger, handle)
+    }
+
+    pub fn process_commands(&mut self, emulator: &mut GameBoyAdvanceEmulator) {
+        loop {
+            match self.command_receiver.try_recv() {
+                Ok(command) => self.process_command(command, emulator),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.clear_breakpoints();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn process_command(&mut self, command: GbaDebugCommand, emulator: &mut GameBoyAdvanceEmulator) {
+        match command {
+            GbaDebugCommand::EditMemory(area, address, value) => {
+                emulator.debug().memory_view(area).write(address, value);
+                if area == GbaMemoryArea::CartridgeRom {
+                    self.cartridge_rom = None;
+                }
+            }
+            GbaDebugCommand::UpdateBreakpoints(breakpoints) => {
+                let ranges = |f: fn(&GbaBreakpoint) -> bool| {
+                    breakpoints
+                        .iter()
+                        .filter(|&breakpoint| f(breakpoint))
+                        .map(|breakpoint| (breakpoint.start_address, breakpoint.end_address))
+                        .collect::<Vec<_>>()
+                };
+
+                self.execute_breakpoints = ranges(|breakpoint| breakpoint.execute);
+                self.watchpoints = Arc::new(WatchpointRanges {
+                    read: ranges(|breakpoint| breakpoint.read),
+                    write: ranges(|breakpoint| breakpoint.write),
+                });
+                emulator.bus.watchpoints.update(&self.watchpoints);
+            }
+            GbaDebugCommand::BreakPause => {
+                // Break at start of next instruction
+                self.step = Some(1);
+            }
+            GbaDebugCommand::BreakResume | GbaDebugCommand::BreakStep => {}
+        }
+    }
+
+    fn clear_breakpoints(&mut self) {
+        self.execute_breakpoints.clear();
+        self.watchpoints = Arc::default();
+        self.step = None;
+    }
+
+    fn check_break_step(&mut self) -> bool {
+        let Some(remaining) = &mut self.step else { return false };
+
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.step = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn before_instruction(&mut self, emulator: &mut GameBoyAdvanceEmulator) {
+        // Watchpoints are not persisted in save states
+        emulator.bus.watchpoints.update(&self.watchpoints);
+
+        let pc = emulator.cpu.next_instruction_address();
+        let check_step = self.check_break_step();
+        let check_execute =
+            self.execute_breakpoints.iter().any(|&(start, end)| (start..=end).contains(&pc));
+
+        if check_step || check_execute {
+            if check_execute {
+                log::info!("Execute breakpoint hit at PC={pc:08X}");
+            }
+
+            self.handle_breakpoint(emulator, None);
+        }
+    }
+
+    pub(crate) fn after_instruction(&mut self, emulator: &mut GameBoyAdvanceEmulator) {
+        if let Some(hit) = emulator.bus.watchpoints.take_hit() {
+            log::info!(
+                "{} breakpoint hit at {:08X}, next PC={:08X}",
+                if hit.write { "Write" } else { "Read" },
+                hit.address,
+                emulator.cpu.next_instruction_address()
+            );
+
+            self.handle_breakpoint(emulator, Some(hit));
+        }
+    }
+
+    fn capture_debug_state(&mut self, emulator: &mut GameBoyAdvanceEmulator) -> GbaDebugState {
+        fn copy_memory(emulator: &mut GameBoyAdvanceEmulator, area: GbaMemoryArea) -> Box<[u8]> {
+            let view = emulator.debug().memory_view(area);
+            (0..view.len()).map(|address| view.read(address)).collect()
+        }
+
+        let cartridge_rom = Arc::clone(
+            self.cartridge_rom
+                .get_or_insert_with(|| copy_memory(emulator, GbaMemoryArea::CartridgeRom).into()),
+        );
+
+        let mut registers = [0; 16];
+        for (r, register) in registers[..15].iter_mut().enumerate() {
+            *register = emulator.cpu.register(r);
+        }
+        registers[15] = emulator.cpu.next_instruction_address();
+
+        GbaDebugState {
+            registers,
+            cpsr: emulator.cpu.cpsr(),
+            bios_rom: copy_memory(emulator, GbaMemoryArea::BiosRom),
+            ewram: copy_memory(emulator, GbaMemoryArea::Ewram),
+            iwram: copy_memory(emulator, GbaMemoryArea::Iwram),
+            palette_ram: copy_memory(emulator, GbaMemoryArea::PaletteRam),
+            vram: copy_memory(emulator, GbaMemoryArea::Vram),
+            oam: copy_memory(emulator, GbaMemoryArea::Oam),
+            cartridge_rom,
+        }
+    }
+
+    // Watchpoint hits break after the instruction that accessed memory; other breaks happen before
+    // the instruction at PC executes
+    fn handle_breakpoint(
+        &mut self,
+        emulator: &mut GameBoyAdvanceEmulator,
+        watchpoint: Option<WatchpointHit>,
+    ) {
+        let after_instruction = watchpoint.is_some();
+
+        let state = self.capture_debug_state(emulator);
+        self.state_sender.update(state);
+
+        self.break_status.pc.store(emulator.cpu.next_instruction_address(), Ordering::Relaxed);
+        self.break_status.watchpoint.store(watchpoint);
+        self.break_status.breaking.store(true, Ordering::Release);
+
+        self.step = None;
+
+        loop {
+            match self.command_receiver.recv() {
+                Ok(GbaDebugCommand::BreakResume) => break,
+                Ok(GbaDebugCommand::BreakStep) => {
+                    // If the break happened after an instruction, the next instruction has not
+                    // executed yet
+                    self.step = Some(1 + u32::from(after_instruction));
+                    break;
+                }
+                Ok(command) => self.process_command(command, emulator),
+                Err(_) => {
+                    // Debugger disconnected
+                    self.clear_breakpoints();
+                    emulator.bus.watchpoints.update(&self.watchpoints);
+                    break;
+                }
+            }
+        }
+
+        self.break_status.breaking.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchpoints(read: &[(u32, u32)], write: &[(u32, u32)]) -> Watchpoints {
+        let mut watchpoints = Watchpoints::default();
+        watchpoints
+            .update(&Arc::new(WatchpointRanges { read: read.to_vec(), write: write.to_vec() }));
+        watchpoints
+    }
+
+    #[test]
+    fn inactive_watchpoints_never_hit() {
+        let mut watchpoints = Watchpoints::default();
+        watchpoints.check_read::<{ OpSize::WORD }>(0x02000000);
+        watchpoints.check_write::<{ OpSize::BYTE }>(0x03000000);
+        assert_eq!(watchpoints.take_hit(), None);
+    }
+
+    #[test]
+    fn read_and_write_ranges_are_separate() {
+        let mut watchpoints = watchpoints(&[(0x02000100, 0x02000103)], &[(0x03000010, 0x03000010)]);
+
+        watchpoints.check_write::<{ OpSize::WORD }>(0x02000100);
+        watchpoints.check_read::<{ OpSize::BYTE }>(0x03000010);
+        assert_eq!(watchpoints.take_hit(), None);
+
+        watchpoints.check_read::<{ OpSize::HALFWORD }>(0x02000102);
+        assert_eq!(
+            watchpoints.take_hit(),
+            Some(WatchpointHit { address: 0x02000102, write: false })
+        );
+        assert_eq!(watchpoints.take_hit(), None);
+
+        watchpoints.check_write::<{ OpSize::BYTE }>(0x03000010);
+        assert_eq!(
+            watchpoints.take_hit(),
+            Some(WatchpointHit { address: 0x03000010, write: true })
+        );
+    }
+
+    #[test]
+    fn accesses_are_aligned_to_size() {
+        let mut watchpoints = watchpoints(&[(0x02000003, 0x02000003)], &[]);
+
+        // Misaligned word read of 0x02000001 accesses 0x02000000-0x02000003
+        watchpoints.check_read::<{ OpSize::WORD }>(0x02000001);
+        assert_eq!(
+            watchpoints.take_hit(),
+            Some(WatchpointHit { address: 0x02000001, write: false })
+        );
+
+        // Halfword read of 0x02000001 accesses 0x02000000-0x02000001
+        watchpoints.check_read::<{ OpSize::HALFWORD }>(0x02000001);
+        assert_eq!(watchpoints.take_hit(), None);
+
+        watchpoints.check_read::<{ OpSize::BYTE }>(0x02000002);
+        watchpoints.check_read::<{ OpSize::BYTE }>(0x02000004);
+        assert_eq!(watchpoints.take_hit(), None);
+
+        watchpoints.check_read::<{ OpSize::HALFWORD }>(0x02000002);
+        assert!(watchpoints.take_hit().is_some());
+    }
+
+    #[test]
+    fn first_hit_in_instruction_is_kept() {
+        let mut watchpoints = watchpoints(&[(0x02000000, 0x0200000F)], &[(0x02000000, 0x0200000F)]);
+
+        // e.g. LDM/STM or SWP touching several watched addresses
+        watchpoints.check_read::<{ OpSize::WORD }>(0x02000004);
+        watchpoints.check_write::<{ OpSize::WORD }>(0x02000008);
+        assert_eq!(
+            watchpoints.take_hit(),
+            Some(WatchpointHit { address: 0x02000004, write: false })
+        );
+    }
+
+    #[test]
+    fn update_clears_pending_hit() {
+        let ranges =
+            Arc::new(WatchpointRanges { read: vec![(0x04000000, 0x04000000)], write: vec![] });
+        let mut watchpoints = Watchpoints::default();
+        watchpoints.update(&ranges);
+
+        watchpoints.check_read::<{ OpSize::BYTE }>(0x04000000);
+
+        // Same ranges (e.g. restored after a state load) keep the hit
+        watchpoints.update(&ranges);
+        assert!(watchpoints.hit.is_some());
+
+        watchpoints.update(&Arc::default());
+        assert_eq!(watchpoints.take_hit(), None);
+
+        watchpoints.check_read::<{ OpSize::BYTE }>(0x04000000);
+        assert_eq!(watchpoints.take_hit(), None);
+    }
+}
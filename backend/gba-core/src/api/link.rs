@@ -0,0 +1,342 @@
+//! In-process link cable connecting 2-4 GBA emulator instances
+//!
+//! Linked emulators run in lockstep: each emulator runs for a short slice of cycles, and then
+//! serial port state is exchanged between all of them. A transfer started during a slice reaches
+//! the other GBAs at the end of that slice, which is much shorter than a transfer at any of the
+//! speeds that games use.
+//!
+//! A GBA with no cartridge can boot software sent over the link cable (e.g. for single-pak
+//! multiplayer) by creating it with an empty ROM and booting through the BIOS.
+
+use crate::api::{GameBoyAdvanceEmulator, STOPPED_TICK_CYCLES};
+use crate::ppu;
+use crate::sio::{self, LinkedPort, MAX_LINKED_GBAS};
+use jgenesis_common::frontend::TickEffect;
+use thiserror::Error;
+
+// Cycles that each emulator runs between link data exchanges
+const SLICE_CYCLES: u64 = 256;
+
+// If an emulator's cycle count jumps by more than this (e.g. because of a reset or a save state
+// load), restart lockstep from its new cycle count instead of trying to catch up
+const RESYNC_THRESHOLD_CYCLES: u64 = (ppu::LINES_PER_FRAME * ppu::DOTS_PER_LINE) as u64;
+
+#[derive(Debug, Error)]
+pub enum GbaLinkError {
+    #[error("GBA link cable supports 2 to {MAX_LINKED_GBAS} GBAs; got {0}")]
+    InvalidPlayerCount(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct GbaLinkCable {
+    num_players: usize,
+    slice_end_cycles: [Option<u64>; MAX_LINKED_GBAS],
+    stopped_cycles: [u64; MAX_LINKED_GBAS],
+}
+
+impl GbaLinkCable {
+    /// # Errors
+    ///
+    /// Returns an error if `num_players` is not between 2 and 4.
+    pub fn new(num_players: usize) -> Result<Self, GbaLinkError> {
+        if !(2..=MAX_LINKED_GBAS).contains(&num_players) {
+            return Err(GbaLinkError::InvalidPlayerCount(num_players));
+        }
+
+        Ok(Self {
+            num_players,
+            slice_end_cycles: [None; MAX_LINKED_GBAS],
+            stopped_cycles: [0; MAX_LINKED_GBAS],
+        })
+    }
+
+    #[must_use]
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
+    /// Run each emulator for one lockstep slice, then exchange serial port data between them.
+    ///
+    /// `tick` should tick the given player's emulator once, handling its video, audio, input, and
+    /// save output the same way as an unlinked emulator. Player 1 (index 0) is the Multi-Player
+    /// parent.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any error returned by `tick`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of emulators does not match the number of players.
+    pub fn run_slice<E>(
+        &mut self,
+        emulators: &mut [GameBoyAdvanceEmulator],
+        mut tick: impl FnMut(usize, &mut GameBoyAdvanceEmulator) -> Result<TickEffect, E>,
+    ) -> Result<(), E> {
+        assert_eq!(
+            emulators.len(),
+            self.num_players,
+            "Number of linked emulators must match link cable player count"
+        );
+
+        for (i, emulator) in emulators.iter_mut().enumerate() {
+            let cycles = emulator.bus.state.cycles;
+
+            if emulator.bus.interrupts.stopped() {
+                // Cycles do not advance during stop; tick at the same rate as an unlinked emulator
+                // so that stopped GBAs don't slow down the others
+                self.slice_end_cycles[i] = Some(cycles);
+                self.stopped_cycles[i] += SLICE_CYCLES;
+                if self.stopped_cycles[i] >= STOPPED_TICK_CYCLES {
+                    self.stopped_cycles[i] -= STOPPED_TICK_CYCLES;
+                    tick(i, emulator)?;
+                }
+                continue;
+            }
+
+            let slice_start = match self.slice_end_cycles[i] {
+                Some(slice_end) if slice_end.abs_diff(cycles) <= RESYNC_THRESHOLD_CYCLES => {
+                    slice_end
+                }
+                _ => cycles,
+            };
+            let slice_end = slice_start + SLICE_CYCLES;
+            self.slice_end_cycles[i] = Some(slice_end);
+
+            while emulator.bus.state.cycles < slice_end && !emulator.bus.interrupts.stopped() {
+                tick(i, emulator)?;
+            }
+        }
+
+        let mut ports: Vec<_> = emulators
+            .iter_mut()
+            .map(|emulator| LinkedPort {
+                sio: &mut emulator.bus.sio,
+                scheduler: &mut emulator.bus.scheduler,
+                cycles: emulator.bus.state.cycles,
+            })
+            .collect();
+        sio::exchange_link_data(&mut ports);
+
+        Ok(())
+    }
+
+    /// Disconnect the emulators from the link cable. Afterwards their serial ports behave as if
+    /// nothing is connected.
+    pub fn disconnect(&mut self, emulators: &mut [GameBoyAdvanceEmulator]) {
+        for emulator in emulators {
+            emulator.bus.sio.disconnect_link();
+        }
+
+        self.slice_end_cycles = [None; MAX_LINKED_GBAS];
+        self.stopped_cycles = [0; MAX_LINKED_GBAS];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{GbaEmulatorConfig, GbaError};
+    use gba_config::GbaInputs;
+    use jgenesis_common::frontend::{
+        AudioOutput, Color, ConstantInputPoller, EmulatorTrait, FrameSize, RenderFrameOptions,
+        Renderer, SaveWriter,
+    };
+    use jgenesis_common::num::GetBit;
+    use std::convert::Infallible;
+
+    // ARM: b .
+    const INFINITE_LOOP: u32 = 0xEAFFFFFE;
+
+    // Longest that one tick of the test program can run past the end of a slice
+    const MAX_OVERSHOOT_CYCLES: u64 = 64;
+
+    const SIOCNT: u32 = 0x4000128;
+    const SIODATA8: u32 = 0x400012A;
+    const RCNT: u32 = 0x4000134;
+
+    struct NullFrontend;
+
+    impl Renderer for NullFrontend {
+        type Err = Infallible;
+
+        fn render_frame(
+            &mut self,
+            _frame_buffer: &[Color],
+            _frame_size: FrameSize,
+            _target_fps: f64,
+            _options: RenderFrameOptions,
+        ) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl AudioOutput for NullFrontend {
+        type Err = Infallible;
+
+        fn push_sample(&mut self, _sample_l: f64, _sample_r: f64) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    impl SaveWriter for NullFrontend {
+        type Err = String;
+
+        fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+            Err(format!("no file: {extension}"))
+        }
+
+        fn persist_bytes(&mut self, _extension: &str, _bytes: &[u8]) -> Result<(), Self::Err> {
+            Ok(())
+        }
+
+        fn load_serialized<D: bincode::Decode<()>>(
+            &mut self,
+            extension: &str,
+        ) -> Result<D, Self::Err> {
+            Err(format!("no file: {extension}"))
+        }
+
+        fn persist_serialized<E: bincode::Encode>(
+            &mut self,
+            _extension: &str,
+            _data: E,
+        ) -> Result<(), Self::Err> {
+            Ok(())
+        }
+    }
+
+    type TestError = GbaError<Infallible, Infallible, String>;
+
+    fn new_emulator() -> GameBoyAdvanceEmulator {
+        let rom = INFINITE_LOOP.to_le_bytes().repeat(0x100);
+        GameBoyAdvanceEmulator::create(rom, None, GbaEmulatorConfig::default(), &mut NullFrontend)
+            .unwrap()
+    }
+
+    /// Run one slice, returning how many times each emulator was ticked
+    fn run_slice(link: &mut GbaLinkCable, emulators: &mut [GameBoyAdvanceEmulator]) -> Vec<u32> {
+        let mut ticks = vec![0; emulators.len()];
+        let inputs = GbaInputs::default();
+        link.run_slice(emulators, |i, emulator| -> Result<TickEffect, TestError> {
+            ticks[i] += 1;
+            emulator.tick(
+                &mut NullFrontend,
+                &mut NullFrontend,
+                &mut ConstantInputPoller(&inputs),
+                &mut NullFrontend,
+            )
+        })
+        .unwrap();
+        ticks
+    }
+
+    fn assert_cycles_near(emulator: &GameBoyAdvanceEmulator, expected: u64) {
+        let cycles = emulator.bus.state.cycles;
+        assert!(
+            (expected..expected + MAX_OVERSHOOT_CYCLES).contains(&cycles),
+            "expected cycles near {expected}, was {cycles}"
+        );
+    }
+
+    fn write_sio(emulator: &mut GameBoyAdvanceEmulator, address: u32, value: u16) {
+        let bus = &mut emulator.bus;
+        bus.sio.write_register(address, value, bus.state.cycles, &mut bus.scheduler);
+    }
+
+    #[test]
+    fn player_count() {
+        assert!(matches!(GbaLinkCable::new(1), Err(GbaLinkError::InvalidPlayerCount(1))));
+        assert!(matches!(GbaLinkCable::new(5), Err(GbaLinkError::InvalidPlayerCount(5))));
+        assert_eq!(GbaLinkCable::new(4).unwrap().num_players(), 4);
+    }
+
+    #[test]
+    fn slices_run_in_lockstep() {
+        let mut link = GbaLinkCable::new(3).unwrap();
+        let mut emulators = [new_emulator(), new_emulator(), new_emulator()];
+        emulators[2].bus.state.cycles += 1000;
+        let start_cycles = emulators.each_ref().map(|emulator| emulator.bus.state.cycles);
+
+        // Overshooting the end of a slice should not accumulate across slices
+        for slice in 1..=100 {
+            run_slice(&mut link, &mut emulators);
+            for (emulator, start) in emulators.iter().zip(start_cycles) {
+                assert_cycles_near(emulator, start + slice * SLICE_CYCLES);
+            }
+        }
+    }
+
+    #[test]
+    fn resyncs_after_large_cycle_jump() {
+        let mut link = GbaLinkCable::new(2).unwrap();
+        let mut emulators = [new_emulator(), new_emulator()];
+        for _ in 0..10 {
+            run_slice(&mut link, &mut emulators);
+        }
+
+        // Small jumps (e.g. a save state from slightly later) wait for the other GBA to catch up
+        emulators[1].bus.state.cycles += 1000;
+        let ticks = run_slice(&mut link, &mut emulators);
+        assert_ne!(ticks[0], 0);
+        assert_eq!(ticks[1], 0);
+
+        // Large jumps restart lockstep from the new cycle count rather than stalling
+        emulators[1].bus.state.cycles += RESYNC_THRESHOLD_CYCLES + 1;
+        let jumped_cycles = emulators[1].bus.state.cycles;
+        let ticks = run_slice(&mut link, &mut emulators);
+        assert_ne!(ticks[1], 0);
+        assert_cycles_near(&emulators[1], jumped_cycles + SLICE_CYCLES);
+    }
+
+    #[test]
+    fn stopped_gba_does_not_stall_others() {
+        let mut link = GbaLinkCable::new(2).unwrap();
+        let mut emulators = [new_emulator(), new_emulator()];
+        run_slice(&mut link, &mut emulators);
+
+        emulators[1].bus.interrupts.write_haltcnt(0x80);
+        let start_cycles = emulators.each_ref().map(|emulator| emulator.bus.state.cycles);
+
+        let slices = 4 * STOPPED_TICK_CYCLES / SLICE_CYCLES;
+        let mut stopped_ticks = 0;
+        for _ in 0..slices {
+            stopped_ticks += run_slice(&mut link, &mut emulators)[1];
+        }
+
+        // Stopped GBA ticks at the same rate as when unlinked, and its cycle count does not advance
+        assert_eq!(stopped_ticks, 4);
+        assert_eq!(emulators[1].bus.state.cycles, start_cycles[1]);
+        assert_cycles_near(&emulators[0], start_cycles[0] + slices * SLICE_CYCLES);
+
+        // Resumes from its current cycle count once stop ends
+        emulators[1].bus.interrupts.clear_stop();
+        run_slice(&mut link, &mut emulators);
+        assert_cycles_near(&emulators[1], start_cycles[1] + SLICE_CYCLES);
+    }
+
+    #[test]
+    fn normal_mode_transfer() {
+        let mut link = GbaLinkCable::new(2).unwrap();
+        let mut emulators = [new_emulator(), new_emulator()];
+        for (emulator, data) in emulators.iter_mut().zip([0x12, 0x34]) {
+            write_sio(emulator, RCNT, 0x0000);
+            write_sio(emulator, SIODATA8, data);
+        }
+        run_slice(&mut link, &mut emulators);
+
+        // Player 2 on external clock, player 1 on internal 256 KHz clock; 8 bits take 512 cycles
+        write_sio(&mut emulators[1], SIOCNT, 0x0080);
+        write_sio(&mut emulators[0], SIOCNT, 0x0081);
+        for _ in 0..4 {
+            run_slice(&mut link, &mut emulators);
+        }
+
+        let received = emulators.each_mut().map(|emulator| {
+            let bus = &mut emulator.bus;
+            assert!(!bus.sio.read_register(SIOCNT).bit(7), "transfer still active");
+            bus.sio.read_register(SIODATA8) & 0xFF
+        });
+        assert_eq!(received, [0x34, 0x12]);
+    }
+}
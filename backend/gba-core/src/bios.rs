@@ -0,0 +1,1008 @@
+//! High-level emulation of the GBA BIOS, used to run games without a BIOS ROM dump
+//!
+//! The built-in BIOS ROM contains only the exception vectors and the IRQ handler. The IRQ handler
+//! is the same code as in the actual BIOS because games depend on exactly how it behaves, e.g.
+//! which registers it saves and where it reads the user IRQ handler address from.
+//!
+//! Software interrupts are emulated outside of the CPU: when the CPU enters the SWI vector, the
+//! requested BIOS function is performed directly and then the CPU returns to the calling code.
+
+use crate::bus::Bus;
+use crate::memory::BIOS_ROM_LEN;
+use arm7tdmi_emu::bus::{BusInterface, MemoryCycle};
+use arm7tdmi_emu::{Arm7Tdmi, Arm7TdmiResetArgs, CpuMode};
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+use std::f64::consts::TAU;
+
+pub const SWI_VECTOR: u32 = 0x00000008;
+
+const IRQ_VECTOR: usize = 0x18;
+const IRQ_HANDLER_ADDRESS: usize = 0x128;
+
+// Same code as the actual BIOS IRQ handler:
+//   stmfd sp!, {r0-r3, r12, lr}
+//   mov r0, #0x04000000
+//   add lr, pc, #0
+//   ldr pc, [r0, #-4]
+//   ldmfd sp!, {r0-r3, r12, lr}
+//   subs pc, lr, #4
+const IRQ_HANDLER: [u32; 6] =
+    [0xE92D500F, 0xE3A00301, 0xE28FE000, 0xE510F004, 0xE8BD500F, 0xE25EF004];
+
+const ARM_SWI_SOFT_RESET: u32 = 0xEF000000;
+const ARM_MOVS_PC_LR: u32 = 0xE1B0F00E;
+const ARM_SUBS_PC_LR_4: u32 = 0xE25EF004;
+
+// Values that BIOS ROM reads return after the actual BIOS boots and after it returns from an SWI
+const POST_BOOT_BIOS_OPEN_BUS: u32 = 0xE129F000;
+const POST_SWI_BIOS_OPEN_BUS: u32 = 0xE3A02004;
+
+// Rough approximation of the time that the actual BIOS spends entering and exiting its SWI handler
+const SWI_OVERHEAD_CYCLES: u32 = 50;
+
+const SOFT_RESET_FLAG_ADDRESS: u32 = 0x03007FFA;
+const BIOS_IF_ADDRESS: u32 = 0x03007FF8;
+const IME_ADDRESS: u32 = 0x04000208;
+const SOUNDBIAS_ADDRESS: u32 = 0x04000088;
+
+const GBA_BIOS_CHECKSUM: u32 = 0xBAAE187F;
+
+/// Build the built-in BIOS ROM image.
+#[must_use]
+pub fn hle_bios_rom() -> Vec<u8> {
+    let mut rom = vec![0; BIOS_ROM_LEN];
+
+    // Reset; jumping to the reset vector is handled the same as SoftReset
+    write_rom_word(&mut rom, 0x00, ARM_SWI_SOFT_RESET);
+    // Undefined instruction; skip the instruction
+    write_rom_word(&mut rom, 0x04, ARM_MOVS_PC_LR);
+    // SWI; never executed normally because SWIs are intercepted at the vector
+    write_rom_word(&mut rom, SWI_VECTOR as usize, ARM_MOVS_PC_LR);
+    // Prefetch abort and data abort; these cannot occur on GBA
+    write_rom_word(&mut rom, 0x0C, ARM_SUBS_PC_LR_4);
+    write_rom_word(&mut rom, 0x10, ARM_SUBS_PC_LR_4);
+    // IRQ; B IRQ_HANDLER_ADDRESS
+    let branch_offset = (IRQ_HANDLER_ADDRESS - (IRQ_VECTOR + 8)) >> 2;
+    write_rom_word(&mut rom, IRQ_VECTOR, 0xEA000000 | branch_offset as u32);
+    // FIQ; cannot occur on GBA
+    write_rom_word(&mut rom, 0x1C, ARM_SUBS_PC_LR_4);
+
+    for (i, opcode) in IRQ_HANDLER.into_iter().enumerate() {
+        write_rom_word(&mut rom, IRQ_HANDLER_ADDRESS + 4 * i, opcode);
+    }
+
+    rom
+}
+
+fn write_rom_word(rom: &mut [u8], address: usize, value: u32) {
+    rom[address..address + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// CPU state that the actual BIOS leaves when it hands over control to the game.
+#[must_use]
+pub fn post_boot_reset_args(pc: u32) -> Arm7TdmiResetArgs {
+    Arm7TdmiResetArgs {
+        pc,
+        sp_usr: 0x3007F00,
+        sp_svc: 0x3007FE0,
+        sp_irq: 0x3007FA0,
+        sp_fiq: 0,
+        mode: CpuMode::System,
+    }
+}
+
+pub fn init_post_boot_state(bus: &mut Bus) {
+    bus.state.last_bios_read = POST_BOOT_BIOS_OPEN_BUS;
+}
+
+#[derive(Debug, Clone, Copy)]
+enum WriteUnit {
+    Byte,
+    Halfword,
+    Word,
+}
+
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct HleBios {
+    // Set while waiting inside IntrWait/VBlankIntrWait. The SWI instruction is re-executed after
+    // every interrupt until one of the requested BIOS interrupt flags is set
+    intr_wait_active: bool,
+}
+
+impl HleBios {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Perform the BIOS call for the SWI that the CPU just executed. Should be called when the CPU
+    /// is about to execute the instruction at the SWI vector.
+    pub fn handle_swi(&mut self, cpu: &mut Arm7Tdmi<Bus>, bus: &mut Bus) {
+        let thumb = cpu.spsr().is_some_and(|spsr| spsr.bit(5));
+        let swi_address = cpu.register(14).wrapping_sub(if thumb { 2 } else { 4 });
+        let comment = if thumb {
+            bus.read_halfword(swi_address, MemoryCycle::N) as u8
+        } else {
+            (bus.read_word(swi_address, MemoryCycle::N) >> 16) as u8
+        };
+
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|r| cpu.register(r));
+        log::trace!(
+            "HLE BIOS call {comment:02X} from {swi_address:08X}: R0={r0:08X} R1={r1:08X} R2={r2:08X} R3={r3:08X}"
+        );
+
+        bus.internal_cycles(SWI_OVERHEAD_CYCLES);
+
+        match comment {
+            0x00 => {
+                self.soft_reset(cpu, bus);
+                return;
+            }
+            0x01 => register_ram_reset(bus, r0),
+            0x02 => bus.interrupts.write_haltcnt(0x00),
+            0x03 => bus.interrupts.write_haltcnt(0x80),
+            0x04 | 0x05 => {
+                let (discard_old_flags, flags) = if comment == 0x05 {
+                    // VBlankIntrWait is IntrWait with R0=1 and R1=1
+                    cpu.set_register(0, 1);
+                    cpu.set_register(1, 1);
+                    (true, 1)
+                } else {
+                    (r0 != 0, r1 as u16)
+                };
+
+                if !self.intr_wait(bus, discard_old_flags, flags) {
+                    // Return to the SWI instruction so that it executes again after the interrupt
+                    cpu.set_register(14, swi_address);
+                }
+            }
+            0x06 => div(cpu, r0 as i32, r1 as i32),
+            0x07 => div(cpu, r1 as i32, r0 as i32),
+            0x08 => cpu.set_register(0, r0.isqrt()),
+            0x09 => {
+                let (result, r1, r3) = arctan(r0 as i32);
+                cpu.set_register(0, i32::from(result as i16) as u32);
+                cpu.set_register(1, r1 as u32);
+                cpu.set_register(3, r3 as u32);
+            }
+            0x0A => {
+                let result = arctan2((r0 as i16).into(), (r1 as i16).into());
+                cpu.set_register(0, result.into());
+            }
+            0x0B => cpu_set(bus, r0, r1, r2),
+            0x0C => cpu_fast_set(bus, r0, r1, r2),
+            0x0D => cpu.set_register(0, GBA_BIOS_CHECKSUM),
+            0x0E => bg_affine_set(bus, r0, r1, r2),
+            0x0F => obj_affine_set(bus, r0, r1, r2, r3),
+            0x10 => bit_unpack(bus, r0, r1, r2),
+            0x11 => decompress(bus, r0, r1, lz77_decompress, WriteUnit::Byte),
+            0x12 => decompress(bus, r0, r1, lz77_decompress, WriteUnit::Halfword),
+            0x13 => decompress(bus, r0, r1, huffman_decompress, WriteUnit::Word),
+            0x14 => decompress(bus, r0, r1, rl_decompress, WriteUnit::Byte),
+            0x15 => decompress(bus, r0, r1, rl_decompress, WriteUnit::Halfword),
+            0x16 => decompress(bus, r0, r1, diff_8_unfilter, WriteUnit::Byte),
+            0x17 => decompress(bus, r0, r1, diff_8_unfilter, WriteUnit::Halfword),
+            0x18 => decompress(bus, r0, r1, diff_16_unfilter, WriteUnit::Halfword),
+            0x19 => sound_bias(bus, r0),
+            0x1F => {
+                let frequency = midi_key_to_frequency(bus, r0, r1, r2);
+                cpu.set_register(0, frequency);
+            }
+            0x1A..=0x1E | 0x20..=0x24 | 0x28..=0x2A => {
+                log::warn!("Unimplemented BIOS sound driver call {comment:02X}");
+            }
+            0x25 => {
+                log::warn!("MultiBoot BIOS call is not supported");
+                // Failure
+                cpu.set_register(0, 1);
+            }
+            0x26 => {
+                register_ram_reset(bus, 0xFF);
+                bus.write_byte(SOFT_RESET_FLAG_ADDRESS, 0, MemoryCycle::N);
+                self.soft_reset(cpu, bus);
+                return;
+            }
+            0x27 => bus.interrupts.write_haltcnt(r2 as u8),
+            _ => log::warn!("Invalid BIOS call {comment:02X} from {swi_address:08X}"),
+        }
+
+        cpu.return_from_exception(bus);
+        bus.state.last_bios_read = POST_SWI_BIOS_OPEN_BUS;
+    }
+
+    // SWI $00: SoftReset
+    fn soft_reset(&mut self, cpu: &mut Arm7Tdmi<Bus>, bus: &mut Bus) {
+        self.intr_wait_active = false;
+
+        let boot_from_ewram = bus.read_byte(SOFT_RESET_FLAG_ADDRESS, MemoryCycle::N) != 0;
+
+        // Clears the top of IWRAM, which includes the stacks and the BIOS variables
+        fill_words(bus, 0x3007E00, 0x3008000, 0);
+
+        let pc = if boot_from_ewram { 0x2000000 } else { 0x8000000 };
+        cpu.manual_reset(post_boot_reset_args(pc), bus);
+        init_post_boot_state(bus);
+    }
+
+    // SWI $04: IntrWait
+    // Returns true if the wait is over, false if the CPU should halt and wait for another interrupt
+    fn intr_wait(&mut self, bus: &mut Bus, discard_old_flags: bool, flags: u16) -> bool {
+        if discard_old_flags && !self.intr_wait_active {
+            let bios_if = bus.read_halfword(BIOS_IF_ADDRESS, MemoryCycle::N);
+            bus.write_halfword(BIOS_IF_ADDRESS, bios_if & !flags, MemoryCycle::N);
+        }
+
+        let bios_if = bus.read_halfword(BIOS_IF_ADDRESS, MemoryCycle::N);
+        if bios_if & flags != 0 {
+            bus.write_halfword(BIOS_IF_ADDRESS, bios_if & !flags, MemoryCycle::N);
+            self.intr_wait_active = false;
+            return true;
+        }
+
+        // IntrWait always enables interrupts before halting
+        bus.write_halfword(IME_ADDRESS, 1, MemoryCycle::N);
+        bus.interrupts.write_haltcnt(0x00);
+        self.intr_wait_active = true;
+
+        false
+    }
+}
+
+fn fill_words(bus: &mut Bus, start: u32, end: u32, value: u32) {
+    for address in (start..end).step_by(4) {
+        bus.write_word(address, value, MemoryCycle::S);
+    }
+}
+
+// SWI $01: RegisterRamReset
+fn register_ram_reset(bus: &mut Bus, flags: u32) {
+    // DISPCNT is always reset, with forced blanking enabled
+    bus.write_halfword(0x4000000, 0x0080, MemoryCycle::N);
+
+    if flags.bit(0) {
+        // EWRAM
+        fill_words(bus, 0x2000000, 0x2040000, 0);
+    }
+
+    if flags.bit(1) {
+        // IWRAM, excluding the stacks and BIOS variables at the top
+        fill_words(bus, 0x3000000, 0x3007E00, 0);
+    }
+
+    if flags.bit(2) {
+        // Palette RAM
+        fill_words(bus, 0x5000000, 0x5000400, 0);
+    }
+
+    if flags.bit(3) {
+        // VRAM
+        fill_words(bus, 0x6000000, 0x6018000, 0);
+    }
+
+    if flags.bit(4) {
+        // OAM
+        fill_words(bus, 0x7000000, 0x7000400, 0);
+    }
+
+    if flags.bit(5) {
+        // Serial registers; RCNT is set to general-purpose mode
+        fill_words(bus, 0x4000120, 0x4000130, 0);
+        bus.write_halfword(0x4000134, 0x8000, MemoryCycle::N);
+    }
+
+    if flags.bit(6) {
+        // Sound registers and wave RAM, excluding SOUNDBIAS and the DMA sound FIFOs
+        fill_words(bus, 0x4000060, 0x4000088, 0);
+        fill_words(bus, 0x4000090, 0x40000A0, 0);
+    }
+
+    if flags.bit(7) {
+        // All other registers
+        fill_words(bus, 0x4000004, 0x4000060, 0);
+        fill_words(bus, 0x40000B0, 0x40000E0, 0);
+        fill_words(bus, 0x4000100, 0x4000110, 0);
+        bus.write_halfword(0x4000132, 0, MemoryCycle::N);
+        bus.write_halfword(0x4000200, 0, MemoryCycle::N);
+        bus.write_halfword(0x4000202, 0xFFFF, MemoryCycle::N);
+        bus.write_halfword(0x4000204, 0, MemoryCycle::N);
+        bus.write_halfword(IME_ADDRESS, 0, MemoryCycle::N);
+    }
+}
+
+// SWI $06: Div, and SWI $07: DivArm with the operands swapped
+fn div(cpu: &mut Arm7Tdmi<Bus>, numerator: i32, denominator: i32) {
+    let (quotient, remainder) = if denominator == 0 {
+        // The actual BIOS hangs in an infinite loop if the numerator's magnitude is greater than 1;
+        // return something reasonable instead
+        log::warn!("BIOS Div called with denominator of 0, numerator {numerator}");
+        (if numerator < 0 { -1 } else { 1 }, numerator)
+    } else {
+        (numerator.wrapping_div(denominator), numerator.wrapping_rem(denominator))
+    };
+
+    cpu.set_register(0, quotient as u32);
+    cpu.set_register(1, remainder as u32);
+    cpu.set_register(3, quotient.unsigned_abs());
+}
+
+// SWI $09: ArcTan
+// Returns the result along with the values that the actual BIOS leaves in R1 and R3
+fn arctan(tan: i32) -> (i32, i32, i32) {
+    let a = -(tan.wrapping_mul(tan) >> 14);
+    let mut b = ((0xA9 * a) >> 14) + 0x390;
+    for c in [0x91C, 0xFB6, 0x16AA, 0x2081, 0x3651, 0xA2F9] {
+        b = (b.wrapping_mul(a) >> 14) + c;
+    }
+
+    (tan.wrapping_mul(b) >> 16, a, b)
+}
+
+// SWI $0A: ArcTan2
+fn arctan2(x: i32, y: i32) -> u16 {
+    let arctan = |tan: i32| arctan(tan).0;
+
+    let angle = if y == 0 {
+        if x >= 0 { 0 } else { 0x8000 }
+    } else if x == 0 {
+        if y >= 0 { 0x4000 } else { 0xC000 }
+    } else if y >= 0 {
+        if x >= 0 && x >= y {
+            arctan((y << 14) / x)
+        } else if x < 0 && -x >= y {
+            arctan((y << 14) / x) + 0x8000
+        } else {
+            0x4000 - arctan((x << 14) / y)
+        }
+    } else if x <= 0 && -x > -y {
+        arctan((y << 14) / x) + 0x8000
+    } else if x > 0 && x >= -y {
+        arctan((y << 14) / x) + 0x10000
+    } else {
+        0xC000 - arctan((x << 14) / y)
+    };
+
+    angle as u16
+}
+
+// BIOS functions that read memory refuse to read from BIOS ROM
+fn is_bios_address(address: u32) -> bool {
+    address & 0x0E000000 == 0
+}
+
+// SWI $0B: CpuSet
+fn cpu_set(bus: &mut Bus, source: u32, dest: u32, control: u32) {
+    if is_bios_address(source) {
+        log::warn!("BIOS CpuSet called with source address in BIOS ROM: {source:08X}");
+        return;
+    }
+
+    let count = control & 0x1FFFFF;
+    let fill = control.bit(24);
+
+    if control.bit(26) {
+        // 32-bit units
+        let (source, dest) = (source & !3, dest & !3);
+        let fill_value = fill.then(|| bus.read_word(source, MemoryCycle::N));
+        for i in 0..count {
+            let value = fill_value
+                .unwrap_or_else(|| bus.read_word(source.wrapping_add(4 * i), MemoryCycle::S));
+            bus.write_word(dest.wrapping_add(4 * i), value, MemoryCycle::S);
+        }
+    } else {
+        // 16-bit units
+        let (source, dest) = (source & !1, dest & !1);
+        let fill_value = fill.then(|| bus.read_halfword(source, MemoryCycle::N));
+        for i in 0..count {
+            let value = fill_value
+                .unwrap_or_else(|| bus.read_halfword(source.wrapping_add(2 * i), MemoryCycle::S));
+            bus.write_halfword(dest.wrapping_add(2 * i), value, MemoryCycle::S);
+        }
+    }
+}
+
+// SWI $0C: CpuFastSet
+fn cpu_fast_set(bus: &mut Bus, source: u32, dest: u32, control: u32) {
+    if is_bios_address(source) {
+        log::warn!("BIOS CpuFastSet called with source address in BIOS ROM: {source:08X}");
+        return;
+    }
+
+    // Always copies in blocks of 8 words
+    let count = (control & 0x1FFFFF).next_multiple_of(8);
+    let (source, dest) = (source & !3, dest & !3);
+
+    let fill_value = control.bit(24).then(|| bus.read_word(source, MemoryCycle::N));
+    for i in 0..count {
+        let value =
+            fill_value.unwrap_or_else(|| bus.read_word(source.wrapping_add(4 * i), MemoryCycle::S));
+        bus.write_word(dest.wrapping_add(4 * i), value, MemoryCycle::S);
+    }
+}
+
+fn sin_lut(angle: u8) -> i32 {
+    // Sine in signed 1.14 fixed point, with 256 steps per full rotation
+    ((f64::from(angle) * TAU / 256.0).sin() * 16384.0).round() as i32
+}
+
+// Rotation/scaling matrix parameters (PA, PB, PC, PD) in signed 8.8 fixed point
+fn affine_parameters(scale_x: i32, scale_y: i32, angle: u16) -> [i32; 4] {
+    let angle = (angle >> 8) as u8;
+    let sin = sin_lut(angle);
+    let cos = sin_lut(angle.wrapping_add(64));
+
+    [(scale_x * cos) >> 14, -(scale_x * sin) >> 14, (scale_y * sin) >> 14, (scale_y * cos) >> 14]
+}
+
+fn read_i16(bus: &mut Bus, address: u32) -> i32 {
+    (bus.read_halfword(address, MemoryCycle::S) as i16).into()
+}
+
+// SWI $0E: BgAffineSet
+fn bg_affine_set(bus: &mut Bus, source: u32, dest: u32, count: u32) {
+    for i in 0..count {
+        let source = source.wrapping_add(20 * i);
+        let dest = dest.wrapping_add(16 * i);
+
+        let origin_x = bus.read_word(source, MemoryCycle::N) as i32;
+        let origin_y = bus.read_word(source.wrapping_add(4), MemoryCycle::S) as i32;
+        let display_x = read_i16(bus, source.wrapping_add(8));
+        let display_y = read_i16(bus, source.wrapping_add(10));
+        let scale_x = read_i16(bus, source.wrapping_add(12));
+        let scale_y = read_i16(bus, source.wrapping_add(14));
+        let angle = bus.read_halfword(source.wrapping_add(16), MemoryCycle::S);
+
+        let [pa, pb, pc, pd] = affine_parameters(scale_x, scale_y, angle);
+        let start_x = origin_x.wrapping_sub(pa * display_x + pb * display_y);
+        let start_y = origin_y.wrapping_sub(pc * display_x + pd * display_y);
+
+        for (j, parameter) in [pa, pb, pc, pd].into_iter().enumerate() {
+            bus.write_halfword(dest.wrapping_add(2 * j as u32), parameter as u16, MemoryCycle::S);
+        }
+        bus.write_word(dest.wrapping_add(8), start_x as u32, MemoryCycle::S);
+        bus.write_word(dest.wrapping_add(12), start_y as u32, MemoryCycle::S);
+    }
+}
+
+// SWI $0F: ObjAffineSet
+fn obj_affine_set(bus: &mut Bus, source: u32, dest: u32, count: u32, stride: u32) {
+    for i in 0..count {
+        let source = source.wrapping_add(8 * i);
+        let dest = dest.wrapping_add(4 * stride * i);
+
+        let scale_x = read_i16(bus, source);
+        let scale_y = read_i16(bus, source.wrapping_add(2));
+        let angle = bus.read_halfword(source.wrapping_add(4), MemoryCycle::S);
+
+        let parameters = affine_parameters(scale_x, scale_y, angle);
+        for (j, parameter) in parameters.into_iter().enumerate() {
+            bus.write_halfword(
+                dest.wrapping_add(stride * j as u32),
+                parameter as u16,
+                MemoryCycle::S,
+            );
+        }
+    }
+}
+
+// SWI $10: BitUnPack
+fn bit_unpack(bus: &mut Bus, source: u32, dest: u32, info_address: u32) {
+    let source_len = bus.read_halfword(info_address, MemoryCycle::N);
+    let source_width = bus.read_byte(info_address.wrapping_add(2), MemoryCycle::S);
+    let dest_width = bus.read_byte(info_address.wrapping_add(3), MemoryCycle::S);
+    let offset_word = bus.read_word(info_address.wrapping_add(4), MemoryCycle::S);
+
+    if !matches!(source_width, 1 | 2 | 4 | 8) || !matches!(dest_width, 1 | 2 | 4 | 8 | 16 | 32) {
+        log::warn!("Invalid BIOS BitUnPack widths: source {source_width}, dest {dest_width}");
+        return;
+    }
+
+    let data_offset = offset_word & 0x7FFFFFFF;
+    let offset_zeroes = offset_word.bit(31);
+    let source_mask = (1 << source_width) - 1;
+
+    let mut dest = dest & !3;
+    let mut buffer = 0_u32;
+    let mut buffer_bits = 0;
+    for i in 0..u32::from(source_len) {
+        let byte = bus.read_byte(source.wrapping_add(i), MemoryCycle::S);
+        for shift in (0..8).step_by(source_width.into()) {
+            let mut value = (u32::from(byte) >> shift) & source_mask;
+            if value != 0 || offset_zeroes {
+                value = value.wrapping_add(data_offset);
+            }
+
+            buffer |= value << buffer_bits;
+            buffer_bits += u32::from(dest_width);
+            if buffer_bits == 32 {
+                bus.write_word(dest, buffer, MemoryCycle::S);
+                dest = dest.wrapping_add(4);
+                buffer = 0;
+                buffer_bits = 0;
+            }
+        }
+    }
+}
+
+// Shared by all of the decompression functions, which all start with a 4-byte header where the
+// highest 24 bits are the decompressed size in bytes
+fn decompress(
+    bus: &mut Bus,
+    source: u32,
+    dest: u32,
+    decompress_fn: fn(&mut Bus, u32, usize) -> Vec<u8>,
+    write_unit: WriteUnit,
+) {
+    if is_bios_address(source) {
+        log::warn!("BIOS decompression called with source address in BIOS ROM: {source:08X}");
+        return;
+    }
+
+    let header = bus.read_word(source, MemoryCycle::N);
+    let size = (header >> 8) as usize;
+    let data = decompress_fn(bus, source, size);
+
+    match write_unit {
+        WriteUnit::Byte => {
+            for (i, &byte) in data.iter().enumerate() {
+                bus.write_byte(dest.wrapping_add(i as u32), byte, MemoryCycle::S);
+            }
+        }
+        WriteUnit::Halfword => {
+            let dest = dest & !1;
+            for (i, chunk) in data.chunks(2).enumerate() {
+                let value = u16::from_le_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]);
+                bus.write_halfword(dest.wrapping_add(2 * i as u32), value, MemoryCycle::S);
+            }
+        }
+        WriteUnit::Word => {
+            let dest = dest & !3;
+            for (i, chunk) in data.chunks(4).enumerate() {
+                let mut bytes = [0; 4];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                let value = u32::from_le_bytes(bytes);
+                bus.write_word(dest.wrapping_add(4 * i as u32), value, MemoryCycle::S);
+            }
+        }
+    }
+}
+
+struct ByteReader {
+    address: u32,
+}
+
+impl ByteReader {
+    fn next(&mut self, bus: &mut Bus) -> u8 {
+        let byte = bus.read_byte(self.address, MemoryCycle::S);
+        self.address = self.address.wrapping_add(1);
+        byte
+    }
+}
+
+// SWI $11: LZ77UnCompWram, and SWI $12: LZ77UnCompVram
+fn lz77_decompress(bus: &mut Bus, source: u32, size: usize) -> Vec<u8> {
+    let mut reader = ByteReader { address: source.wrapping_add(4) };
+    let mut out = Vec::with_capacity(size);
+
+    while out.len() < size {
+        let flags = reader.next(bus);
+        for bit in (0..8).rev() {
+            if out.len() >= size {
+                break;
+            }
+
+            if !flags.bit(bit) {
+                // Uncompressed byte
+                out.push(reader.next(bus));
+                continue;
+            }
+
+            // Compressed block: copy 3-18 bytes from 1-4096 bytes back
+            let [b0, b1] = [reader.next(bus), reader.next(bus)];
+            let len = usize::from(b0 >> 4) + 3;
+            let displacement = ((usize::from(b0 & 0xF) << 8) | usize::from(b1)) + 1;
+            for _ in 0..len {
+                let byte = out.len().checked_sub(displacement).map_or(0, |i| out[i]);
+                out.push(byte);
+            }
+        }
+    }
+
+    out.truncate(size);
+    out
+}
+
+// SWI $13: HuffUnComp
+fn huffman_decompress(bus: &mut Bus, source: u32, size: usize) -> Vec<u8> {
+    let data_bits = bus.read_byte(source, MemoryCycle::S) & 0xF;
+    if !matches!(data_bits, 1 | 2 | 4 | 8) {
+        log::warn!("Invalid BIOS HuffUnComp data size: {data_bits} bits");
+        return vec![];
+    }
+
+    let tree_size = bus.read_byte(source.wrapping_add(4), MemoryCycle::S);
+    let root_address = source.wrapping_add(5);
+    let mut bitstream_address = source.wrapping_add(4 + 2 * (u32::from(tree_size) + 1));
+
+    let mut out = Vec::with_capacity(size);
+    let mut node_address = root_address;
+    let mut node = bus.read_byte(root_address, MemoryCycle::S);
+    let mut buffer = 0_u32;
+    let mut buffer_bits = 0;
+
+    while out.len() < size {
+        let bitstream = bus.read_word(bitstream_address, MemoryCycle::S);
+        bitstream_address = bitstream_address.wrapping_add(4);
+
+        for bit in (0..32).rev() {
+            // Node bits 0-5 are the offset to the child pair, and bits 6-7 are whether the right
+            // and left children are data leaves
+            let right = bitstream.bit(bit);
+            let child_address =
+                (node_address & !1).wrapping_add(2 * u32::from(node & 0x3F) + 2 + u32::from(right));
+            let child_is_data = if right { node.bit(6) } else { node.bit(7) };
+
+            if !child_is_data {
+                node_address = child_address;
+                node = bus.read_byte(child_address, MemoryCycle::S);
+                continue;
+            }
+
+            let value = bus.read_byte(child_address, MemoryCycle::S);
+            buffer |= u32::from(value) << buffer_bits;
+            buffer_bits += u32::from(data_bits);
+            if buffer_bits == 32 {
+                out.extend(buffer.to_le_bytes());
+                buffer = 0;
+                buffer_bits = 0;
+
+                if out.len() >= size {
+                    break;
+                }
+            }
+
+            node_address = root_address;
+            node = bus.read_byte(root_address, MemoryCycle::S);
+        }
+    }
+
+    out
+}
+
+// SWI $14: RLUnCompWram, and SWI $15: RLUnCompVram
+fn rl_decompress(bus: &mut Bus, source: u32, size: usize) -> Vec<u8> {
+    let mut reader = ByteReader { address: source.wrapping_add(4) };
+    let mut out = Vec::with_capacity(size);
+
+    while out.len() < size {
+        let flag = reader.next(bus);
+        if flag.bit(7) {
+            // Run of 3-130 copies of the same byte
+            let len = usize::from(flag & 0x7F) + 3;
+            let byte = reader.next(bus);
+            out.extend(std::iter::repeat_n(byte, len));
+        } else {
+            // 1-128 uncompressed bytes
+            let len = usize::from(flag & 0x7F) + 1;
+            for _ in 0..len {
+                out.push(reader.next(bus));
+            }
+        }
+    }
+
+    out.truncate(size);
+    out
+}
+
+// SWI $16: Diff8bitUnFilterWram, and SWI $17: Diff8bitUnFilterVram
+fn diff_8_unfilter(bus: &mut Bus, source: u32, size: usize) -> Vec<u8> {
+    let mut reader = ByteReader { address: source.wrapping_add(4) };
+
+    let mut value = 0_u8;
+    (0..size)
+        .map(|_| {
+            value = value.wrapping_add(reader.next(bus));
+            value
+        })
+        .collect()
+}
+
+// SWI $18: Diff16bitUnFilter
+fn diff_16_unfilter(bus: &mut Bus, source: u32, size: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(size);
+
+    let mut value = 0_u16;
+    for i in (0..size as u32).step_by(2) {
+        let delta = bus.read_halfword(source.wrapping_add(4 + i), MemoryCycle::S);
+        value = value.wrapping_add(delta);
+        out.extend(value.to_le_bytes());
+    }
+
+    out
+}
+
+// SWI $19: SoundBias
+// The actual BIOS gradually moves the bias level; this sets it immediately
+fn sound_bias(bus: &mut Bus, level: u32) {
+    let level = if level != 0 { 0x200 } else { 0x000 };
+    let soundbias = bus.read_halfword(SOUNDBIAS_ADDRESS, MemoryCycle::N);
+    bus.write_halfword(SOUNDBIAS_ADDRESS, (soundbias & !0x3FF) | level, MemoryCycle::N);
+}
+
+// SWI $1F: MidiKey2Freq
+fn midi_key_to_frequency(bus: &mut Bus, wave_address: u32, key: u32, fine_pitch: u32) -> u32 {
+    let frequency = bus.read_word(wave_address.wrapping_add(4), MemoryCycle::N);
+    let exponent = (180.0 - key as f32 - fine_pitch as f32 / 256.0) / 12.0;
+    (frequency as f32 / exponent.exp2()) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GbaAudioConfig;
+    use crate::api::debug::Watchpoints;
+    use crate::apu::Apu;
+    use crate::bus::BusState;
+    use crate::cartridge::Cartridge;
+    use crate::dma::DmaState;
+    use crate::input::InputState;
+    use crate::interrupts::InterruptRegisters;
+    use crate::memory::Memory;
+    use crate::ppu;
+    use crate::ppu::Ppu;
+    use crate::prefetch::GamePakPrefetcher;
+    use crate::scheduler::{Scheduler, SchedulerEvent};
+    use crate::sio::SerialPort;
+    use crate::timers::Timers;
+
+    const CODE_ADDRESS: u32 = 0x03000000;
+    const DATA_ADDRESS: u32 = 0x02000000;
+    const OUT_ADDRESS: u32 = 0x02001000;
+
+    struct TestGba {
+        cpu: Arm7Tdmi<Bus>,
+        bus: Bus,
+        hle_bios: HleBios,
+    }
+
+    impl TestGba {
+        fn new() -> Self {
+            let mut bus = Bus {
+                ppu: Ppu::new(true),
+                apu: Apu::new(GbaAudioConfig::default()),
+                memory: Memory::new(hle_bios_rom(), true).unwrap(),
+                cartridge: Cartridge::new(vec![0; 4 * 1024 * 1024], None, None, None),
+                prefetch: GamePakPrefetcher::new(),
+                dma: DmaState::new(),
+                timers: Timers::new(),
+                interrupts: InterruptRegisters::new(),
+                sio: SerialPort::new(),
+                inputs: InputState::new(),
+                state: BusState::new(),
+                scheduler: Scheduler::new(),
+                watchpoints: Watchpoints::default(),
+            };
+            bus.scheduler.insert_or_update(SchedulerEvent::PpuEvent, ppu::DOTS_PER_LINE.into());
+
+            Self { cpu: Arm7Tdmi::new(), bus, hle_bios: HleBios::new() }
+        }
+
+        fn run_code(&mut self, code: &[u8], stop_address: u32) {
+            for (i, &byte) in code.iter().enumerate() {
+                self.bus.write_byte(CODE_ADDRESS + i as u32, byte, MemoryCycle::S);
+            }
+
+            self.cpu.manual_reset(post_boot_reset_args(CODE_ADDRESS), &mut self.bus);
+
+            let max_cycles = self.bus.state.cycles + 10 * 280896;
+            while self.cpu.next_instruction_address() != stop_address {
+                assert!(self.bus.state.cycles < max_cycles, "Test code did not finish");
+
+                if self.bus.interrupts.cpu_halted() {
+                    self.bus.internal_cycles(1);
+                } else if self.cpu.next_instruction_address() == SWI_VECTOR {
+                    self.hle_bios.handle_swi(&mut self.cpu, &mut self.bus);
+                } else {
+                    self.cpu.execute_instruction(&mut self.bus);
+                }
+            }
+        }
+
+        // Run until reaching the first infinite loop
+        fn run_arm(&mut self, code: &[u32]) {
+            let stop_idx = code.iter().position(|&opcode| opcode == ARM_LOOP).unwrap();
+            let stop_address = CODE_ADDRESS + 4 * stop_idx as u32;
+
+            let code: Vec<_> = code.iter().flat_map(|opcode| opcode.to_le_bytes()).collect();
+            self.run_code(&code, stop_address);
+        }
+
+        fn write_data(&mut self, data: &[u8]) {
+            for (i, &byte) in data.iter().enumerate() {
+                self.bus.write_byte(DATA_ADDRESS + i as u32, byte, MemoryCycle::S);
+            }
+        }
+
+        fn read_out(&mut self, len: u32) -> Vec<u8> {
+            (0..len).map(|i| self.bus.read_byte(OUT_ADDRESS + i, MemoryCycle::S)).collect()
+        }
+    }
+
+    // B .
+    const ARM_LOOP: u32 = 0xEAFFFFFE;
+
+    #[test]
+    fn arm_div_and_sqrt() {
+        let mut gba = TestGba::new();
+        gba.run_arm(&[
+            0xE3A00064, // mov r0, #100
+            0xE3A01007, // mov r1, #7
+            0xEF060000, // swi 0x060000 (Div)
+            0xE1A04000, // mov r4, r0
+            0xE1A05001, // mov r5, r1
+            0xE3A00801, // mov r0, #0x10000
+            0xEF080000, // swi 0x080000 (Sqrt)
+            ARM_LOOP,
+        ]);
+
+        assert_eq!(gba.cpu.register(4), 14);
+        assert_eq!(gba.cpu.register(5), 2);
+        assert_eq!(gba.cpu.register(3), 14);
+        assert_eq!(gba.cpu.register(0), 256);
+    }
+
+    #[test]
+    fn thumb_div() {
+        let mut gba = TestGba::new();
+        let code: Vec<_> = [0xE28F0001_u32, 0xE12FFF10] // add r0, pc, #1; bx r0
+            .iter()
+            .flat_map(|opcode| opcode.to_le_bytes())
+            .chain(
+                // mov r0, #9; mov r1, #2; swi 6 (Div); b .
+                [0x2009_u16, 0x2102, 0xDF06, 0xE7FE].iter().flat_map(|op| op.to_le_bytes()),
+            )
+            .collect();
+        gba.run_code(&code, CODE_ADDRESS + 14);
+
+        assert_eq!(gba.cpu.register(0), 4);
+        assert_eq!(gba.cpu.register(1), 1);
+    }
+
+    #[test]
+    fn vblank_intr_wait() {
+        let mut gba = TestGba::new();
+        gba.run_arm(&[
+            0xE3A00301, // mov r0, #0x04000000
+            0xE3A01008, // mov r1, #8
+            0xE1C010B4, // strh r1, [r0, #4] (DISPSTAT: VBlank IRQ enabled)
+            0xE2802C02, // add r2, r0, #0x200
+            0xE3A01001, // mov r1, #1
+            0xE1C210B0, // strh r1, [r2] (IE: VBlank)
+            0xE28F3020, // add r3, pc, #0x20
+            0xE3A04403, // mov r4, #0x03000000
+            0xE2844C7F, // add r4, r4, #0x7F00
+            0xE58430FC, // str r3, [r4, #0xFC] (user IRQ handler address)
+            0xE3A05000, // mov r5, #0
+            0xEF050000, // swi 0x050000 (VBlankIntrWait)
+            0xE2855001, // add r5, r5, #1
+            0xE3550003, // cmp r5, #3
+            0x1AFFFFFB, // bne (VBlankIntrWait)
+            ARM_LOOP,
+            // IRQ handler; acknowledges VBlank in both IF and BIOS IF
+            0xE3A00301, // mov r0, #0x04000000
+            0xE2800C02, // add r0, r0, #0x200
+            0xE3A01001, // mov r1, #1
+            0xE1C010B2, // strh r1, [r0, #2]
+            0xE3A00403, // mov r0, #0x03000000
+            0xE2800C7F, // add r0, r0, #0x7F00
+            0xE1D02FB8, // ldrh r2, [r0, #0xF8]
+            0xE3822001, // orr r2, r2, #1
+            0xE1C02FB8, // strh r2, [r0, #0xF8]
+            0xE12FFF1E, // bx lr
+        ]);
+
+        assert_eq!(gba.cpu.register(5), 3);
+        // Should have halted for at least 2 full frames
+        assert!(gba.bus.state.cycles >= 2 * u64::from(ppu::LINES_PER_FRAME * ppu::DOTS_PER_LINE));
+    }
+
+    #[test]
+    fn arctan2() {
+        assert_eq!(super::arctan2(1, 0), 0x0000);
+        assert_eq!(super::arctan2(0, 1), 0x4000);
+        assert_eq!(super::arctan2(-1, 0), 0x8000);
+        assert_eq!(super::arctan2(0, -1), 0xC000);
+
+        for (x, y, expected) in [(100, 100, 0x2000), (-100, 100, 0x6000), (-100, -100, 0xA000)] {
+            let angle = super::arctan2(x, y);
+            assert!(angle.abs_diff(expected) < 0x10, "({x}, {y}): {angle:04X}");
+        }
+    }
+
+    #[test]
+    fn obj_affine_set() {
+        let mut gba = TestGba::new();
+        // Scale 1.0 and rotation 0, then scale 2.0 and rotation 90 degrees
+        gba.write_data(&[
+            0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0, 0, 0x00, 0x02, 0x00, 0x02, 0x00, 0x40, 0, 0,
+        ]);
+
+        super::obj_affine_set(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, 2, 2);
+
+        let out: Vec<_> = gba
+            .read_out(16)
+            .chunks(2)
+            .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect();
+        assert_eq!(out, vec![0x100, 0, 0, 0x100, 0, -0x200, 0x200, 0]);
+    }
+
+    #[test]
+    fn lz77() {
+        let mut gba = TestGba::new();
+        // "ABC" followed by a 9-byte copy from 3 bytes back
+        gba.write_data(&[0x10, 12, 0, 0, 0b0001_0000, b'A', b'B', b'C', 0x60, 0x02]);
+
+        decompress(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, lz77_decompress, WriteUnit::Halfword);
+
+        assert_eq!(gba.read_out(12), b"ABCABCABCABC");
+    }
+
+    #[test]
+    fn huffman() {
+        let mut gba = TestGba::new();
+        gba.write_data(&[
+            0x28, 4, 0, 0, // 8-bit data, 4 bytes
+            1, 0xC0, b'a', b'b', // Tree: root node whose children are both data
+            0x00, 0x00, 0x00, 0x60, // Bitstream: 0110
+        ]);
+
+        decompress(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, huffman_decompress, WriteUnit::Word);
+
+        assert_eq!(gba.read_out(4), b"abba");
+    }
+
+    #[test]
+    fn run_length() {
+        let mut gba = TestGba::new();
+        gba.write_data(&[0x30, 8, 0, 0, 0x82, b'x', 0x02, b'a', b'b', b'c']);
+
+        decompress(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, rl_decompress, WriteUnit::Byte);
+
+        assert_eq!(gba.read_out(8), b"xxxxxabc");
+    }
+
+    #[test]
+    fn diff_8_bit() {
+        let mut gba = TestGba::new();
+        gba.write_data(&[0x81, 4, 0, 0, 1, 1, 1, 0xFF]);
+
+        decompress(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, diff_8_unfilter, WriteUnit::Byte);
+
+        assert_eq!(gba.read_out(4), [1, 2, 3, 2]);
+    }
+
+    #[test]
+    fn bit_unpack_with_offset() {
+        let mut gba = TestGba::new();
+        // 1 source byte, 1bpp -> 4bpp, add 1 to non-zero values
+        gba.write_data(&[0b0000_0101, 0, 0, 0, 1, 0, 1, 4, 1, 0, 0, 0]);
+
+        bit_unpack(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, DATA_ADDRESS + 4);
+
+        assert_eq!(gba.read_out(4), [0x02, 0x02, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn cpu_set_fill() {
+        let mut gba = TestGba::new();
+        gba.write_data(&[0x78, 0x56, 0x34, 0x12]);
+
+        // 32-bit fill, 3 words
+        cpu_set(&mut gba.bus, DATA_ADDRESS, OUT_ADDRESS, (1 << 26) | (1 << 24) | 3);
+
+        assert_eq!(
+            gba.read_out(16),
+            [0x78, 0x56, 0x34, 0x12].repeat(3).into_iter().chain([0; 4]).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! GBA memory map and bus code
 
+use crate::api::debug::Watchpoints;
 use crate::apu;
 use crate::apu::Apu;
 use crate::cartridge::Cartridge;
@@ -78,6 +79,7 @@ pub struct Bus {
     pub inputs: InputState,
     pub state: BusState,
     pub scheduler: Scheduler,
+    pub watchpoints: Watchpoints,
 }
 
 impl Bus {
@@ -463,7 +465,12 @@ impl Bus {
             }
             0x4000120..=0x400012F | 0x4000134..=0x400015A => {
                 // Serial port registers
-                self.sio.write_register(address & !1, value, self.state.cycles);
+                self.sio.write_register(
+                    address & !1,
+                    value,
+                    self.state.cycles,
+                    &mut self.scheduler,
+                );
             }
             0x4000132..=0x4000133 => {
                 // KEYCNT
@@ -954,6 +961,12 @@ impl Bus {
                 SchedulerEvent::TimerOverflow => {
                     self.sync_timers();
                 }
+                SchedulerEvent::SioTransferComplete => {
+                    self.sio.complete_transfer(cycles, &mut self.interrupts, &mut self.scheduler);
+                }
+                SchedulerEvent::SioUartReceive => {
+                    self.sio.receive_uart_bytes(cycles, &mut self.interrupts, &mut self.scheduler);
+                }
                 SchedulerEvent::Dummy => {}
             }
         }
@@ -979,6 +992,7 @@ impl Bus {
 impl BusInterface for Bus {
     #[inline]
     fn read<const SIZE: u8>(&mut self, address: u32, cycle: MemoryCycle) -> u32 {
+        self.watchpoints.check_read::<SIZE>(address);
         self.read_internal::<SIZE, { AccessCtx::CPU_DATA }>(address, cycle)
     }
 
@@ -990,6 +1004,7 @@ impl BusInterface for Bus {
 
     #[inline]
     fn write<const SIZE: u8>(&mut self, address: u32, value: u32, cycle: MemoryCycle) {
+        self.watchpoints.check_write::<SIZE>(address);
         self.write_internal::<SIZE, { AccessCtx::CPU_DATA }>(address, value, cycle);
     }
 
@@ -1033,14 +1048,14 @@ impl BusInterface for Bus {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::{GbaAudioConfig, GbaEmulatorConfig};
+    use crate::api::GbaAudioConfig;
 
     #[test]
     fn no_io_addresses_panic() {
         let mut bus = Bus {
             ppu: Ppu::new(false),
             apu: Apu::new(GbaAudioConfig::default()),
-            memory: Memory::new(vec![0; 16 * 1024], GbaEmulatorConfig::default()).unwrap(),
+            memory: Memory::new(vec![0; 16 * 1024], false).unwrap(),
             cartridge: Cartridge::new(vec![0; 4 * 1024 * 1024], None, None, None),
             prefetch: GamePakPrefetcher::new(),
             dma: DmaState::new(),
@@ -1050,6 +1065,7 @@ mod tests {
             inputs: InputState::new(),
             state: BusState::new(),
             scheduler: Scheduler::new(),
+            watchpoints: Watchpoints::default(),
         };
 
         for address in 0x04000000..=0x0400FFFF {
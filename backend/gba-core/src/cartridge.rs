@@ -13,10 +13,12 @@ use crate::cartridge::rtc::SeikoRealTimeClock;
 use crate::cartridge::solar::SolarSensor;
 use crate::dma::TransferUnit;
 use crate::interrupts::InterruptRegisters;
+use crate::memory::Memory;
 use bincode::{Decode, Encode};
 use crc::Crc;
 use gba_config::GbaSaveMemory;
 use jgenesis_common::boxedarray::BoxedByteArray;
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::debug::{DebugBytesView, DebugMemoryView};
 use jgenesis_common::num::GetBit;
 use jgenesis_proc_macros::{FakeDecode, FakeEncode, PartialClone};
@@ -209,6 +211,8 @@ pub struct Cartridge {
     // Kept here so that they can be copied into R/W memory after auto-detection
     initial_save: Option<Vec<u8>>,
     initial_rtc: Option<SeikoRealTimeClock>,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl Cartridge {
@@ -249,6 +253,7 @@ impl Cartridge {
             solar: has_solar_sensor.then(SolarSensor::new),
             initial_save,
             initial_rtc,
+            cheats: CheatSet::default(),
         }
     }
 
@@ -312,7 +317,8 @@ impl Cartridge {
             return open_bus as u16;
         }
 
-        u16::from_le_bytes(self.rom[rom_addr..rom_addr + 2].try_into().unwrap())
+        let value = u16::from_le_bytes(self.rom[rom_addr..rom_addr + 2].try_into().unwrap());
+        self.cheats.patch_rom_u16_le(0x08000000 | rom_addr as u32, value)
     }
 
     fn try_eeprom_read(&mut self) -> Option<bool> {
@@ -419,6 +425,22 @@ impl Cartridge {
 
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.rom = mem::take(&mut other.rom);
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    pub fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    // Cheat RAM writes should be applied once per frame
+    pub fn apply_cheat_ram_writes(&self, memory: &mut Memory) {
+        for &RamWrite { address, value, .. } in self.cheats.ram_writes() {
+            match address >> 24 {
+                0x02 => memory.write_ewram_byte(address, value),
+                0x03 => memory.write_iwram_byte(address, value),
+                _ => {}
+            }
+        }
     }
 
     pub fn read_sram(&mut self, address: u32) -> u8 {
@@ -494,6 +516,18 @@ impl Cartridge {
         }
     }
 
+    /// Largest size that [`Self::rw_memory`] can have, including save memory types that have not
+    /// been auto-detected yet.
+    pub fn max_rw_memory_len(&self) -> usize {
+        match &self.rw_memory {
+            // Largest possible type is 128 KB Flash ROM
+            RwMemory::Unknown => 128 * 1024,
+            RwMemory::EepromUnknownSize => 8 * 1024,
+            RwMemory::None => 0,
+            _ => self.rw_memory().map_or(0, <[u8]>::len),
+        }
+    }
+
     pub fn update_rtc_time(&mut self, cycles: u64, interrupts: &mut InterruptRegisters) {
         if let Some(rtc) = &mut self.rtc {
             rtc.update_time(cycles, interrupts);
@@ -1,5 +1,6 @@
 pub mod api;
 mod apu;
+mod bios;
 mod bus;
 mod cartridge;
 mod dma;
@@ -1,13 +1,13 @@
 //! GBA internal memory
 
-use crate::api::{GbaEmulatorConfig, GbaLoadError};
+use crate::api::GbaLoadError;
 use bincode::{Decode, Encode};
 use jgenesis_common::boxedarray::BoxedByteArray;
 use jgenesis_common::debug::{DebugBytesView, DebugMemoryView};
 use jgenesis_common::num::GetBit;
 use std::array;
 
-const BIOS_ROM_LEN: usize = 16 * 1024;
+pub const BIOS_ROM_LEN: usize = 16 * 1024;
 const IWRAM_LEN: usize = 32 * 1024;
 const EWRAM_LEN: usize = 256 * 1024;
 
@@ -98,7 +98,7 @@ pub struct Memory {
 }
 
 impl Memory {
-    pub fn new(bios_rom: Vec<u8>, config: GbaEmulatorConfig) -> Result<Self, GbaLoadError> {
+    pub fn new(bios_rom: Vec<u8>, skip_bios_animation: bool) -> Result<Self, GbaLoadError> {
         if bios_rom.len() != BIOS_ROM_LEN {
             return Err(GbaLoadError::InvalidBiosLength {
                 expected: BIOS_ROM_LEN,
@@ -113,7 +113,7 @@ impl Memory {
             iwram: BoxedByteArray::new(),
             ewram: BoxedByteArray::new(),
             memory_control: MemoryControl::new(),
-            post_boot: config.skip_bios_animation,
+            post_boot: skip_bios_animation,
         })
     }
 
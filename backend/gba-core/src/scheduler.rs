@@ -10,6 +10,8 @@ pub enum SchedulerEvent {
     VCounterIrq,
     PpuEvent,
     TimerOverflow,
+    SioTransferComplete,
+    SioUartReceive,
     Dummy,
 }
 
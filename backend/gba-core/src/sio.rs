@@ -1,10 +1,25 @@
 //! GBA SIO / serial port
 //!
-//! SIO is largely not emulated, only stubbed out enough for games that access it to kind of work
+//! Normal (8-bit and 32-bit), Multi-Player, and UART modes are emulated. Transfers between GBAs
+//! are coordinated by the link cable in [`crate::api::link`], which runs linked emulators in
+//! lockstep and calls [`exchange_link_data`] between slices. When not linked, the serial port
+//! behaves as if nothing is connected.
+//!
+//! JOY Bus and General-Purpose modes are not emulated beyond reading and writing registers.
 
 use crate::interrupts::{InterruptRegisters, InterruptType};
+use crate::scheduler::{Scheduler, SchedulerEvent};
 use bincode::{Decode, Encode};
 use jgenesis_common::num::GetBit;
+use std::collections::VecDeque;
+use std::mem;
+
+pub const MAX_LINKED_GBAS: usize = 4;
+
+// Multi-Player and UART baud rates
+const BAUD_RATES: [u64; 4] = [9600, 38400, 57600, 115200];
+
+const UART_FIFO_LEN: usize = 4;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
 enum Mode {
@@ -16,14 +31,51 @@ enum Mode {
     GeneralPurpose,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+enum TransferResult {
+    Normal8(u8),
+    Normal32(u32),
+    MultiPlayer { data: [u16; 4], id: u8 },
+}
+
+// Serial lines from the other linked GBAs, updated by the link cable between slices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+struct LinkState {
+    player: u8,
+    // Multi-Player SD terminal; set when all linked GBAs are in Multi-Player mode
+    all_ready: bool,
+    // Normal mode SI terminal; the other GBA's SO output
+    peer_so: bool,
+    // UART CTS; the other GBA is ready to receive
+    peer_ready_to_receive: bool,
+}
+
+#[derive(Debug, Clone, Default, Encode, Decode)]
+struct UartState {
+    tx_fifo: VecDeque<u8>,
+    tx_active: bool,
+    rx_fifo: VecDeque<u8>,
+    // Bytes in flight from the other GBA, along with the cycles when they finish arriving
+    incoming: VecDeque<(u64, u8)>,
+    // Bytes that finished sending and have not yet been delivered by the link cable
+    outgoing: Vec<u8>,
+    error: bool,
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct SerialPort {
     mode: Mode,
     rcnt: u16,
     siocnt: u16,
-    siodata32: u32,
+    // SIODATA32 in Normal mode, SIOMULTI0-3 in Multi-Player mode
+    data: [u16; 4],
     siodata8: u16,
-    next_irq_cycles: Option<u64>,
+    multiplayer_id: u8,
+    link: Option<LinkState>,
+    // Cycles when a transfer started that the link cable has not yet handled
+    pending_link_start: Option<u64>,
+    transfer_result: Option<TransferResult>,
+    uart: UartState,
 }
 
 impl SerialPort {
@@ -32,9 +84,13 @@ impl SerialPort {
             mode: Mode::default(),
             rcnt: 0x8000, // Sonic Advance boots into multiplayer mode if this defaults to 0
             siocnt: 0,
-            siodata32: !0,
+            data: [!0; 4],
             siodata8: !0,
-            next_irq_cycles: None,
+            multiplayer_id: 0,
+            link: None,
+            pending_link_start: None,
+            transfer_result: None,
+            uart: UartState::default(),
         }
     }
 
@@ -42,10 +98,14 @@ impl SerialPort {
         log::trace!("SIO read {address:08X}");
 
         match address {
-            0x4000120 => self.siodata32 as u16,
-            0x4000122 => (self.siodata32 >> 16) as u16,
+            0x4000120..=0x4000127 => self.data[((address >> 1) & 3) as usize],
             0x4000128 => self.read_siocnt(),
-            0x400012A => self.siodata8,
+            0x400012A => {
+                if self.mode == Mode::Uart {
+                    self.read_uart_data();
+                }
+                self.siodata8
+            }
             0x4000134 => self.read_rcnt(),
             0x4000136 | 0x4000142 | 0x400015A => 0, // Invalid addresses that return 0
             _ => {
@@ -55,22 +115,28 @@ impl SerialPort {
         }
     }
 
-    pub fn write_register(&mut self, address: u32, value: u16, cycles: u64) {
+    pub fn write_register(
+        &mut self,
+        address: u32,
+        value: u16,
+        cycles: u64,
+        scheduler: &mut Scheduler,
+    ) {
         match address {
-            0x4000120 => {
-                self.siodata32 = (self.siodata32 & !0xFFFF) | u32::from(value);
-                log::trace!("SIODATA32: {:08X}", self.siodata32);
-            }
-            0x4000122 => {
-                self.siodata32 = (self.siodata32 & 0xFFFF) | (u32::from(value) << 16);
-                log::trace!("SIODATA32: {:08X}", self.siodata32);
+            0x4000120..=0x4000127 => {
+                self.data[((address >> 1) & 3) as usize] = value;
+                log::trace!("SIODATA32/SIOMULTI write: {address:08X} {value:04X}");
             }
-            0x4000128 => self.write_siocnt(value, cycles),
+            0x4000128 => self.write_siocnt(value, cycles, scheduler),
             0x400012A => {
                 self.siodata8 = value;
                 log::trace!("SIODATA8: {:04X}", self.siodata8);
+
+                if self.mode == Mode::Uart {
+                    self.write_uart_data(value as u8, cycles, scheduler);
+                }
             }
-            0x4000134 => self.write_rcnt(value),
+            0x4000134 => self.write_rcnt(value, scheduler),
             _ => {
                 log::debug!("Unimplemented SIO write {address:08X} {value:04X}");
             }
@@ -81,52 +147,83 @@ impl SerialPort {
         self.rcnt
     }
 
-    fn write_rcnt(&mut self, value: u16) {
+    fn write_rcnt(&mut self, value: u16, scheduler: &mut Scheduler) {
         self.rcnt = value;
-        self.update_mode();
+        self.update_mode(scheduler);
 
         log::trace!("RCNT write: {value:04X}");
         log::trace!("  SIO mode: {:?}", self.mode);
     }
 
-    fn read_siocnt(&self) -> u16 {
-        self.siocnt
+    fn read_siocnt(&mut self) -> u16 {
+        match (self.mode, self.link) {
+            (Mode::Normal, Some(link)) => {
+                // Bit 2 is the SI terminal, connected to the other GBA's SO terminal
+                (self.siocnt & !(1 << 2)) | (u16::from(link.peer_so) << 2)
+            }
+            (Mode::MultiPlayer, Some(link)) => {
+                // Bit 2 is the SI terminal (0 = parent, 1 = child), bit 3 is the SD terminal
+                // (1 = all GBAs ready), and bits 4-5 are the ID from the last transfer
+                (self.siocnt & !0x3C)
+                    | (u16::from(link.player != 0) << 2)
+                    | (u16::from(link.all_ready) << 3)
+                    | (u16::from(self.multiplayer_id) << 4)
+            }
+            (Mode::Uart, _) => {
+                let value = (self.siocnt & !0x70)
+                    | (u16::from(self.uart_send_full()) << 4)
+                    | (u16::from(self.uart.rx_fifo.is_empty()) << 5)
+                    | (u16::from(self.uart.error) << 6);
+
+                // Reading SIOCNT clears the error flag
+                self.uart.error = false;
+
+                value
+            }
+            _ => self.siocnt,
+        }
     }
 
-    fn write_siocnt(&mut self, value: u16, cycles: u64) {
+    fn write_siocnt(&mut self, value: u16, cycles: u64, scheduler: &mut Scheduler) {
+        let prev_mode = self.mode;
         let prev_active = self.siocnt.bit(7);
 
         self.siocnt = value;
-        self.update_mode();
-
-        let active = self.siocnt.bit(7);
-        if self.mode == Mode::Normal && !prev_active && active {
-            log::trace!("SIO transfer started in Normal mode");
-
-            let internal_clock = self.siocnt.bit(0);
-            if internal_clock {
-                let clock_rate = if self.siocnt.bit(1) {
-                    // 2 MHz
-                    2 * 1024 * 1024
-                } else {
-                    // 256 KHz
-                    256 * 1024
-                };
+        self.update_mode(scheduler);
 
-                let cycles_per_bit = crate::GBA_CLOCK_SPEED / clock_rate;
-                let bits = if self.siocnt.bit(12) { 32 } else { 8 };
+        // Changing modes cancels any in-progress transfer
+        let prev_active = prev_active && self.mode == prev_mode;
 
-                self.next_irq_cycles = Some(cycles + bits * cycles_per_bit);
+        match self.mode {
+            Mode::Normal => {
+                if !value.bit(7) {
+                    self.cancel_transfer(scheduler);
+                } else if !prev_active {
+                    self.start_normal_transfer(cycles, scheduler);
+                }
             }
+            Mode::MultiPlayer => {
+                if self.link.is_some_and(|link| link.player != 0) {
+                    // Only the parent can start transfers; the busy bit is read-only for children
+                    self.siocnt = (value & !(1 << 7)) | (u16::from(prev_active) << 7);
+                } else if value.bit(7) && !prev_active {
+                    self.start_multiplayer_transfer(cycles);
+                }
+            }
+            Mode::Uart => {
+                self.try_start_uart_send(cycles, scheduler);
+            }
+            Mode::JoyBus | Mode::GeneralPurpose => {}
         }
 
         log::trace!("SIOCNT write: {value:04X}");
         log::trace!("  SIO mode: {:?}", self.mode);
     }
 
-    fn update_mode(&mut self) {
+    fn update_mode(&mut self, scheduler: &mut Scheduler) {
         let bits = ((self.rcnt >> 14) << 2) | ((self.siocnt >> 12) & 3);
 
+        let prev_mode = self.mode;
         self.mode = if bits & 0b1010 == 0b0000 {
             Mode::Normal
         } else if bits & 0b1011 == 0b0010 {
@@ -140,30 +237,580 @@ impl SerialPort {
             Mode::JoyBus
         };
 
-        if self.mode == Mode::MultiPlayer {
-            // Pretend transfer finished with no GBAs connected
-            self.siocnt &= !(1 << 7);
-            self.siodata32 = u32::from(self.siodata8) | (0xFFFF << 16);
+        if self.mode != prev_mode {
+            self.cancel_transfer(scheduler);
+            self.uart = UartState::default();
+            scheduler.remove(SchedulerEvent::SioUartReceive);
         }
     }
 
-    pub fn check_for_interrupt(&mut self, cycles: u64, interrupts: &mut InterruptRegisters) {
-        let Some(next_irq_cycles) = self.next_irq_cycles else { return };
+    fn cancel_transfer(&mut self, scheduler: &mut Scheduler) {
+        self.pending_link_start = None;
+        self.transfer_result = None;
+        self.uart.tx_active = false;
+        scheduler.remove(SchedulerEvent::SioTransferComplete);
+    }
 
-        if next_irq_cycles >= cycles {
-            if self.siocnt.bit(14) {
-                interrupts.set_flag(InterruptType::Serial, next_irq_cycles);
+    fn normal_transfer_cycles(&self) -> u64 {
+        // Internal clock is either 256 KHz or 2 MHz
+        let cycles_per_bit = if self.siocnt.bit(1) { 8 } else { 64 };
+        let bits = if self.siocnt.bit(12) { 32 } else { 8 };
+        bits * cycles_per_bit
+    }
+
+    fn cycles_per_baud_bit(&self) -> u64 {
+        crate::GBA_CLOCK_SPEED / BAUD_RATES[(self.siocnt & 3) as usize]
+    }
+
+    fn multiplayer_transfer_cycles(&self, num_players: usize) -> u64 {
+        // Each GBA in turn sends a start bit, 16 data bits, and a stop bit
+        (num_players as u64) * 18 * self.cycles_per_baud_bit()
+    }
+
+    fn uart_byte_cycles(&self) -> u64 {
+        // Start bit, 7 or 8 data bits, optional parity bit, and a stop bit
+        let data_bits = if self.siocnt.bit(7) { 8 } else { 7 };
+        let parity_bits = u64::from(self.siocnt.bit(9));
+        (2 + data_bits + parity_bits) * self.cycles_per_baud_bit()
+    }
+
+    fn start_normal_transfer(&mut self, cycles: u64, scheduler: &mut Scheduler) {
+        log::trace!("SIO transfer started in Normal mode");
+
+        let internal_clock = self.siocnt.bit(0);
+        if !internal_clock {
+            // Transfer runs when the other GBA starts a transfer using its internal clock
+            return;
+        }
+
+        if self.link.is_some() {
+            self.pending_link_start = Some(cycles);
+            return;
+        }
+
+        // Act like nothing is connected
+        let result = if self.siocnt.bit(12) {
+            TransferResult::Normal32(!0)
+        } else {
+            TransferResult::Normal8(!0)
+        };
+        self.schedule_transfer(result, cycles + self.normal_transfer_cycles(), scheduler);
+    }
+
+    fn start_multiplayer_transfer(&mut self, cycles: u64) {
+        log::trace!("SIO transfer started in Multi-Player mode");
+
+        if self.link.is_some() {
+            self.pending_link_start = Some(cycles);
+            return;
+        }
+
+        // Pretend transfer finished with no GBAs connected
+        self.siocnt &= !(1 << 7);
+        self.data = [self.siodata8, !0, !0, !0];
+    }
+
+    fn schedule_transfer(
+        &mut self,
+        result: TransferResult,
+        complete_cycles: u64,
+        scheduler: &mut Scheduler,
+    ) {
+        self.transfer_result = Some(result);
+        self.siocnt |= 1 << 7;
+        scheduler.insert_or_update(SchedulerEvent::SioTransferComplete, complete_cycles);
+    }
+
+    pub fn complete_transfer(
+        &mut self,
+        cycles: u64,
+        interrupts: &mut InterruptRegisters,
+        scheduler: &mut Scheduler,
+    ) {
+        if self.mode == Mode::Uart {
+            self.complete_uart_send(cycles, interrupts, scheduler);
+            return;
+        }
+
+        let Some(result) = self.transfer_result.take() else { return };
+
+        log::trace!("SIO transfer complete: {result:X?}");
+
+        match result {
+            TransferResult::Normal8(value) => {
+                self.siodata8 = (self.siodata8 & 0xFF00) | u16::from(value);
+            }
+            TransferResult::Normal32(value) => {
+                self.data[0] = value as u16;
+                self.data[1] = (value >> 16) as u16;
             }
+            TransferResult::MultiPlayer { data, id } => {
+                self.data = data;
+                self.multiplayer_id = id;
+            }
+        }
+
+        self.siocnt &= !(1 << 7);
+        if self.siocnt.bit(14) {
+            interrupts.set_flag(InterruptType::Serial, cycles);
+        }
+    }
+
+    fn uart_fifo_len(&self) -> usize {
+        if self.siocnt.bit(8) { UART_FIFO_LEN } else { 1 }
+    }
+
+    fn uart_send_full(&self) -> bool {
+        self.uart.tx_fifo.len() >= self.uart_fifo_len()
+    }
+
+    fn uart_ready_to_receive(&self) -> bool {
+        self.mode == Mode::Uart
+            && self.siocnt.bit(11)
+            && self.uart.rx_fifo.len() < self.uart_fifo_len()
+    }
+
+    fn read_uart_data(&mut self) {
+        if let Some(value) = self.uart.rx_fifo.pop_front() {
+            self.siodata8 = value.into();
+        }
+    }
+
+    fn write_uart_data(&mut self, value: u8, cycles: u64, scheduler: &mut Scheduler) {
+        if !self.siocnt.bit(10) {
+            // Send disabled
+            return;
+        }
+
+        if self.uart_send_full() {
+            log::debug!("UART send FIFO full; dropping byte {value:02X}");
+            return;
+        }
 
-            // Act like nothing is connected
-            if self.siocnt.bit(12) {
-                self.siodata32 = !0;
+        let value = if self.siocnt.bit(7) { value } else { value & 0x7F };
+        self.uart.tx_fifo.push_back(value);
+        self.try_start_uart_send(cycles, scheduler);
+    }
+
+    fn try_start_uart_send(&mut self, cycles: u64, scheduler: &mut Scheduler) {
+        if self.mode != Mode::Uart
+            || self.uart.tx_active
+            || self.uart.tx_fifo.is_empty()
+            || !self.siocnt.bit(10)
+        {
+            return;
+        }
+
+        // With CTS enabled, only send while the other GBA is ready to receive
+        if self.siocnt.bit(2) && self.link.is_some_and(|link| !link.peer_ready_to_receive) {
+            return;
+        }
+
+        self.uart.tx_active = true;
+        scheduler.insert_or_update(
+            SchedulerEvent::SioTransferComplete,
+            cycles + self.uart_byte_cycles(),
+        );
+    }
+
+    fn complete_uart_send(
+        &mut self,
+        cycles: u64,
+        interrupts: &mut InterruptRegisters,
+        scheduler: &mut Scheduler,
+    ) {
+        if !self.uart.tx_active {
+            return;
+        }
+        self.uart.tx_active = false;
+
+        let was_full = self.uart_send_full();
+        let Some(value) = self.uart.tx_fifo.pop_front() else { return };
+
+        log::trace!("UART sent byte {value:02X}");
+
+        if self.link.is_some() {
+            self.uart.outgoing.push(value);
+        }
+
+        if was_full && self.siocnt.bit(14) {
+            // Send data flag changed from full to not full
+            interrupts.set_flag(InterruptType::Serial, cycles);
+        }
+
+        self.try_start_uart_send(cycles, scheduler);
+    }
+
+    fn receive_uart_byte(&mut self, value: u8, arrival_cycles: u64, scheduler: &mut Scheduler) {
+        if self.mode != Mode::Uart || !self.siocnt.bit(11) {
+            // Receive disabled
+            return;
+        }
+
+        self.uart.incoming.push_back((arrival_cycles, value));
+        if let Some(&(next_cycles, _)) = self.uart.incoming.front() {
+            scheduler.insert_or_update(SchedulerEvent::SioUartReceive, next_cycles);
+        }
+    }
+
+    pub fn receive_uart_bytes(
+        &mut self,
+        cycles: u64,
+        interrupts: &mut InterruptRegisters,
+        scheduler: &mut Scheduler,
+    ) {
+        while let Some(&(arrival_cycles, value)) = self.uart.incoming.front() {
+            if arrival_cycles > cycles {
+                scheduler.insert_or_update(SchedulerEvent::SioUartReceive, arrival_cycles);
+                return;
+            }
+            self.uart.incoming.pop_front();
+
+            log::trace!("UART received byte {value:02X}");
+
+            if self.uart.rx_fifo.len() >= self.uart_fifo_len() {
+                // Overrun
+                self.uart.error = true;
             } else {
-                self.siodata8 = !0;
+                let was_empty = self.uart.rx_fifo.is_empty();
+                self.uart.rx_fifo.push_back(value);
+                if !was_empty {
+                    continue;
+                }
+            }
+
+            // Receive data flag changed from empty to not empty, or an error occurred
+            if self.siocnt.bit(14) {
+                interrupts.set_flag(InterruptType::Serial, cycles);
+            }
+        }
+    }
+
+    fn normal_send_data(&self) -> u32 {
+        if self.siocnt.bit(12) {
+            u32::from(self.data[0]) | (u32::from(self.data[1]) << 16)
+        } else {
+            (self.siodata8 & 0xFF).into()
+        }
+    }
+
+    fn waiting_for_external_clock(&self, transfer_32_bit: bool) -> bool {
+        self.mode == Mode::Normal
+            && !self.siocnt.bit(0)
+            && self.siocnt.bit(7)
+            && self.siocnt.bit(12) == transfer_32_bit
+            && self.transfer_result.is_none()
+    }
+
+    pub fn disconnect_link(&mut self) {
+        self.link = None;
+        self.pending_link_start = None;
+        self.uart.outgoing.clear();
+    }
+}
+
+/// A linked GBA's serial port, along with what's needed to schedule events on that GBA.
+pub struct LinkedPort<'a> {
+    pub sio: &'a mut SerialPort,
+    pub scheduler: &'a mut Scheduler,
+    pub cycles: u64,
+}
+
+// In Normal and UART modes, only players 1 and 2 (indices 0 and 1) are connected to each other
+fn normal_peer(player: usize, num_players: usize) -> Option<usize> {
+    let peer = player ^ 1;
+    (peer < num_players).then_some(peer)
+}
+
+/// Update each port's view of the serial lines from the other GBAs, and start or deliver any
+/// transfers that began since the last exchange.
+///
+/// Each port's `cycles` should be that GBA's current cycle count. Transfers are scheduled relative
+/// to each GBA's own cycle count so that GBAs do not need to share a timeline.
+pub fn exchange_link_data(ports: &mut [LinkedPort<'_>]) {
+    let num_players = ports.len();
+    debug_assert!(num_players <= MAX_LINKED_GBAS);
+
+    let all_ready = ports.iter().all(|port| port.sio.mode == Mode::MultiPlayer);
+    let mut so = [true; MAX_LINKED_GBAS];
+    let mut ready_to_receive = [false; MAX_LINKED_GBAS];
+    for (i, port) in ports.iter().enumerate() {
+        so[i] = port.sio.siocnt.bit(3);
+        ready_to_receive[i] = port.sio.uart_ready_to_receive();
+    }
+
+    for (i, port) in ports.iter_mut().enumerate() {
+        let peer = normal_peer(i, num_players);
+        port.sio.link = Some(LinkState {
+            player: i as u8,
+            all_ready,
+            peer_so: peer.is_none_or(|peer| so[peer]),
+            peer_ready_to_receive: peer.is_some_and(|peer| ready_to_receive[peer]),
+        });
+    }
+
+    for i in 0..num_players {
+        let Some(start_cycles) = ports[i].sio.pending_link_start.take() else { continue };
+
+        match ports[i].sio.mode {
+            Mode::MultiPlayer if i == 0 => start_linked_multiplayer_transfer(ports, start_cycles),
+            Mode::Normal => start_linked_normal_transfer(ports, i, start_cycles),
+            _ => {}
+        }
+    }
+
+    for i in 0..num_players {
+        let outgoing = mem::take(&mut ports[i].sio.uart.outgoing);
+        let Some(peer) = normal_peer(i, num_players) else { continue };
+
+        let LinkedPort { sio, scheduler, cycles } = &mut ports[peer];
+        for value in outgoing {
+            sio.receive_uart_byte(value, *cycles, scheduler);
+        }
+    }
+
+    for LinkedPort { sio, scheduler, cycles } in ports {
+        // Sends may have been waiting on CTS
+        sio.try_start_uart_send(*cycles, scheduler);
+    }
+}
+
+fn start_linked_multiplayer_transfer(ports: &mut [LinkedPort<'_>], start_cycles: u64) {
+    let parent = &ports[0];
+    let elapsed = parent.cycles.saturating_sub(start_cycles);
+    let remaining = parent.sio.multiplayer_transfer_cycles(ports.len()).saturating_sub(elapsed);
+
+    // GBAs that are not in Multi-Player mode do not respond
+    let mut data = [!0; 4];
+    for (i, port) in ports.iter().enumerate() {
+        if port.sio.mode == Mode::MultiPlayer {
+            data[i] = port.sio.siodata8;
+        }
+    }
+
+    log::trace!("Linked Multi-Player transfer: {data:04X?}");
+
+    for (i, LinkedPort { sio, scheduler, cycles }) in ports.iter_mut().enumerate() {
+        if sio.mode != Mode::MultiPlayer {
+            continue;
+        }
+
+        // SIOMULTI0-3 reset to $FFFF when the transfer starts
+        sio.data = [!0; 4];
+        sio.schedule_transfer(
+            TransferResult::MultiPlayer { data, id: i as u8 },
+            *cycles + remaining,
+            scheduler,
+        );
+    }
+}
+
+fn start_linked_normal_transfer(ports: &mut [LinkedPort<'_>], player: usize, start_cycles: u64) {
+    let transfer_32_bit = ports[player].sio.siocnt.bit(12);
+    let elapsed = ports[player].cycles.saturating_sub(start_cycles);
+    let remaining = ports[player].sio.normal_transfer_cycles().saturating_sub(elapsed);
+
+    let to_result = |value: u32| {
+        if transfer_32_bit {
+            TransferResult::Normal32(value)
+        } else {
+            TransferResult::Normal8(value as u8)
+        }
+    };
+
+    let send_data = ports[player].sio.normal_send_data();
+    let peer = normal_peer(player, ports.len())
+        .filter(|&peer| ports[peer].sio.waiting_for_external_clock(transfer_32_bit));
+
+    // If the other GBA is not waiting on an external clock, it does not shift out any data
+    let receive_data = match peer {
+        Some(peer) => {
+            let LinkedPort { sio, scheduler, cycles } = &mut ports[peer];
+            let peer_data = sio.normal_send_data();
+            sio.schedule_transfer(to_result(send_data), *cycles + remaining, scheduler);
+            peer_data
+        }
+        None => !0,
+    };
+
+    log::trace!("Linked Normal mode transfer: sent {send_data:08X}, received {receive_data:08X}");
+
+    let LinkedPort { sio, scheduler, cycles } = &mut ports[player];
+    sio.schedule_transfer(to_result(receive_data), *cycles + remaining, scheduler);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIODATA32_LOW: u32 = 0x4000120;
+    const SIODATA32_HIGH: u32 = 0x4000122;
+    const SIOCNT: u32 = 0x4000128;
+    const SIODATA8: u32 = 0x400012A;
+    const RCNT: u32 = 0x4000134;
+
+    struct TestGba {
+        sio: SerialPort,
+        scheduler: Scheduler,
+        interrupts: InterruptRegisters,
+        cycles: u64,
+    }
+
+    impl TestGba {
+        fn new() -> Self {
+            let mut gba = Self {
+                sio: SerialPort::new(),
+                scheduler: Scheduler::new(),
+                interrupts: InterruptRegisters::new(),
+                cycles: 0,
+            };
+            gba.write(RCNT, 0x0000);
+            gba
+        }
+
+        fn write(&mut self, address: u32, value: u16) {
+            self.sio.write_register(address, value, self.cycles, &mut self.scheduler);
+        }
+
+        fn read(&mut self, address: u32) -> u16 {
+            self.sio.read_register(address)
+        }
+
+        fn run(&mut self, cycles: u64) {
+            self.cycles += cycles;
+            while let Some((event, cycles)) = self.scheduler.pop(self.cycles) {
+                match event {
+                    SchedulerEvent::SioTransferComplete => {
+                        self.sio.complete_transfer(
+                            cycles,
+                            &mut self.interrupts,
+                            &mut self.scheduler,
+                        );
+                    }
+                    SchedulerEvent::SioUartReceive => {
+                        self.sio.receive_uart_bytes(
+                            cycles,
+                            &mut self.interrupts,
+                            &mut self.scheduler,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        fn serial_irq_pending(&mut self) -> bool {
+            self.interrupts.read_if(self.cycles + 10).bit(InterruptType::Serial as u8)
+        }
+    }
+
+    fn exchange(gbas: &mut [TestGba]) {
+        let mut ports: Vec<_> = gbas
+            .iter_mut()
+            .map(|gba| LinkedPort {
+                sio: &mut gba.sio,
+                scheduler: &mut gba.scheduler,
+                cycles: gba.cycles,
+            })
+            .collect();
+        exchange_link_data(&mut ports);
+    }
+
+    fn run_all(gbas: &mut [TestGba], cycles: u64) {
+        for _ in 0..cycles / 256 {
+            for gba in gbas.iter_mut() {
+                gba.run(256);
             }
+            exchange(gbas);
+        }
+    }
+
+    #[test]
+    fn unlinked_normal_transfer_receives_ones() {
+        let mut gba = TestGba::new();
+        gba.write(SIODATA8, 0x12);
+        // Internal 2 MHz clock, 8-bit, IRQ enabled
+        gba.write(SIOCNT, 0x4003);
+        gba.write(SIOCNT, 0x4083);
+
+        gba.run(63);
+        assert!(gba.read(SIOCNT).bit(7));
+
+        gba.run(1);
+        assert!(!gba.read(SIOCNT).bit(7));
+        assert_eq!(gba.read(SIODATA8) & 0xFF, 0xFF);
+        assert!(gba.serial_irq_pending());
+    }
+
+    #[test]
+    fn linked_multiplayer_transfer() {
+        let mut gbas: Vec<_> = (0..3).map(|_| TestGba::new()).collect();
+        for (i, gba) in gbas.iter_mut().enumerate() {
+            // Multi-Player mode, 115200 bps, IRQ enabled
+            gba.write(SIOCNT, 0x6003);
+            gba.write(SIODATA8, 0x1111 * (i as u16 + 1));
+        }
+        exchange(&mut gbas);
 
-            self.siocnt &= !(1 << 7);
-            self.next_irq_cycles = None;
+        assert_eq!(gbas[0].read(SIOCNT) & 0x0C, 0x08, "parent with all GBAs ready");
+        assert_eq!(gbas[1].read(SIOCNT) & 0x0C, 0x0C, "child with all GBAs ready");
+
+        // Children can't start transfers
+        gbas[1].write(SIOCNT, 0x6083);
+        exchange(&mut gbas);
+        assert!(!gbas[1].read(SIOCNT).bit(7));
+
+        gbas[0].write(SIOCNT, 0x6083);
+        run_all(&mut gbas, 256);
+        assert!(gbas.iter_mut().all(|gba| gba.read(SIOCNT).bit(7)));
+
+        run_all(&mut gbas, 3 * 18 * (crate::GBA_CLOCK_SPEED / 115200) + 256);
+        for (i, gba) in gbas.iter_mut().enumerate() {
+            let siocnt = gba.read(SIOCNT);
+            assert!(!siocnt.bit(7));
+            assert_eq!((siocnt >> 4) & 3, i as u16);
+            assert_eq!(gba.sio.data, [0x1111, 0x2222, 0x3333, 0xFFFF]);
+            assert!(gba.serial_irq_pending());
+        }
+    }
+
+    #[test]
+    fn linked_normal_32_bit_transfer() {
+        let mut gbas = [TestGba::new(), TestGba::new()];
+        gbas[0].write(SIODATA32_LOW, 0x5678);
+        gbas[0].write(SIODATA32_HIGH, 0x1234);
+        gbas[1].write(SIODATA32_LOW, 0xCDEF);
+        gbas[1].write(SIODATA32_HIGH, 0x89AB);
+        exchange(&mut gbas);
+
+        // External clock
+        gbas[1].write(SIOCNT, 0x1080);
+        // Internal 256 KHz clock
+        gbas[0].write(SIOCNT, 0x1081);
+
+        run_all(&mut gbas, 32 * 64 + 512);
+        assert_eq!(gbas[0].sio.normal_send_data(), 0x89ABCDEF);
+        assert_eq!(gbas[1].sio.normal_send_data(), 0x12345678);
+        assert!(gbas.iter_mut().all(|gba| !gba.read(SIOCNT).bit(7)));
+    }
+
+    #[test]
+    fn linked_uart_transfer() {
+        let mut gbas = [TestGba::new(), TestGba::new()];
+        for gba in &mut gbas {
+            // UART mode, 115200 bps, 8-bit data, FIFO, send + receive enabled
+            gba.write(SIOCNT, 0x3D83);
         }
+        exchange(&mut gbas);
+        assert!(gbas[1].read(SIOCNT).bit(5), "receive FIFO empty");
+
+        gbas[0].write(SIODATA8, 0xA5);
+        gbas[0].write(SIODATA8, 0x5A);
+        run_all(&mut gbas, 2 * 10 * (crate::GBA_CLOCK_SPEED / 115200) + 512);
+
+        assert!(!gbas[1].read(SIOCNT).bit(5), "receive FIFO not empty");
+        assert_eq!(gbas[1].read(SIODATA8), 0xA5);
+        assert_eq!(gbas[1].read(SIODATA8), 0x5A);
+        assert!(gbas[1].read(SIOCNT).bit(5), "receive FIFO empty");
     }
 }
@@ -17,6 +17,7 @@ use genesis_config::{
     GenParParams, GenesisAspectRatio, GenesisButton, GenesisControllerType, GenesisInputs,
     GenesisRegion, Opn2BusyBehavior,
 };
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, EmulatorConfigTrait, EmulatorTrait, InputPoller, PartialClone, RenderFrameOptions,
     Renderer, SaveWriter, TickEffect, TickResult, TimingMode,
@@ -202,17 +203,22 @@ macro_rules! new_main_bus {
 impl GenesisEmulator {
     /// Initialize the emulator from the given ROM.
     ///
+    /// `lock_on_rom` is an optional second cartridge inserted into the Sonic & Knuckles lock-on
+    /// slot; it is ignored if the ROM is not Sonic & Knuckles.
+    ///
     /// # Errors
     ///
     /// Returns an error if unable to parse the ROM header.
     #[must_use]
     pub fn create<S: SaveWriter>(
         rom: Vec<u8>,
+        lock_on_rom: Option<Vec<u8>>,
         config: GenesisEmulatorConfig,
         save_writer: &mut S,
     ) -> Self {
         let initial_ram = save_writer.load_bytes("sav").ok();
-        let cartridge = Cartridge::from_rom(rom, initial_ram, config.forced_region);
+        let cartridge =
+            Cartridge::from_rom_with_lock_on(rom, lock_on_rom, initial_ram, config.forced_region);
         let memory = Memory::new(cartridge);
 
         let timing_mode =
@@ -264,6 +270,13 @@ impl GenesisEmulator {
         self.memory.is_external_ram_persistent()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no persistent RAM.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        self.has_sram().then(|| self.memory.external_ram()).filter(|ram| !ram.is_empty())
+    }
+
     #[inline]
     #[must_use]
     pub fn timing_mode(&self) -> TimingMode {
@@ -348,10 +361,17 @@ impl GenesisEmulator {
 
         self.audio_resampler.output_samples(audio_output).map_err(GenesisError::Audio)?;
 
+        // Possibly latch the H/V counter and raise an external interrupt from a light gun
+        if let Some((x, y)) = self.input.light_gun_hl_position() {
+            self.vdp.update_light_gun_latch(x, y, elapsed_mclk_cycles);
+        }
+
         let mut tick_effect = TickEffect::None;
         if self.vdp.tick(elapsed_mclk_cycles, &mut self.memory) == VdpTickEffect::FrameComplete {
             self.render_frame(renderer).map_err(GenesisError::Render)?;
 
+            self.memory.apply_cheat_ram_writes();
+
             if self.memory.is_external_ram_persistent()
                 && self.memory.get_and_clear_external_ram_dirty()
             {
@@ -435,6 +455,8 @@ impl EmulatorTrait for GenesisEmulator {
     type Inputs = GenesisInputs;
     type Config = GenesisEmulatorConfig;
 
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::Genesis;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -492,6 +514,10 @@ impl EmulatorTrait for GenesisEmulator {
         self.memory.take_rom_from(&mut other.memory);
     }
 
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.memory.set_cheats(cheats);
+    }
+
     fn soft_reset(&mut self) {
         log::info!("Soft resetting console");
 
@@ -504,7 +530,8 @@ impl EmulatorTrait for GenesisEmulator {
         log::info!("Hard resetting console");
 
         let rom = self.memory.take_rom();
-        *self = GenesisEmulator::create(rom, self.config, save_writer);
+        let lock_on_rom = self.memory.take_lock_on_rom();
+        *self = GenesisEmulator::create(rom, lock_on_rom, self.config, save_writer);
     }
 
     fn target_fps(&self) -> f64 {
@@ -41,7 +41,8 @@ use crate::vdp::debug::VdpDebugState;
 use crate::vdp::{ColorModifier, Vdp};
 use crate::ym2612::Ym2612;
 use jgenesis_common::debug::{
-    DebugBytesView, DebugMemoryView, DebugWordsView, EmptyDebugView, Endian,
+    AtomicWatchpointHit, DebugBytesView, DebugMemoryView, DebugWordsView, EmptyDebugView, Endian,
+    WatchpointHit,
 };
 use jgenesis_common::frontend::Color;
 use jgenesis_common::sync::SharedVarSender;
@@ -383,12 +384,14 @@ pub struct M68000BreakStatus {
     pub breaking: bool,
     pub pc: u32,
     pub previous_pcs: [u32; PREV_PC_COUNT],
+    pub watchpoint: Option<WatchpointHit>,
 }
 
 pub struct M68000BreakStatusAtomic {
     pub breaking: AtomicBool,
     pub pc: AtomicU32,
     pub previous_pcs: [AtomicU32; PREV_PC_COUNT],
+    pub watchpoint: AtomicWatchpointHit,
 }
 
 impl M68000BreakStatusAtomic {
@@ -398,6 +401,7 @@ impl M68000BreakStatusAtomic {
             breaking: AtomicBool::new(false),
             pc: AtomicU32::new(0),
             previous_pcs: array::from_fn(|_| AtomicU32::new(0)),
+            watchpoint: AtomicWatchpointHit::default(),
         }
     }
 
@@ -406,15 +410,21 @@ impl M68000BreakStatusAtomic {
         let breaking = self.breaking.load(Ordering::Acquire);
         let pc = self.pc.load(Ordering::Relaxed);
         let previous_pcs = array::from_fn(|i| self.previous_pcs[i].load(Ordering::Relaxed));
+        let watchpoint = self.watchpoint.load();
 
-        M68000BreakStatus { breaking, pc, previous_pcs }
+        M68000BreakStatus { breaking, pc, previous_pcs, watchpoint }
     }
 
-    pub fn set_breaking(&self, pcs_rev_iter: impl Iterator<Item = u32>) {
+    pub fn set_breaking(
+        &self,
+        pcs_rev_iter: impl Iterator<Item = u32>,
+        watchpoint: Option<WatchpointHit>,
+    ) {
         for (in_pc, out_pc) in pcs_rev_iter.zip(&self.previous_pcs) {
             out_pc.store(in_pc, Ordering::Relaxed);
         }
         self.pc.store(self.previous_pcs[0].load(Ordering::Relaxed), Ordering::Relaxed);
+        self.watchpoint.store(watchpoint);
 
         self.breaking.store(true, Ordering::Release);
     }
@@ -499,6 +509,8 @@ pub struct M68000BreakpointManager {
     pub last_pcs: RingBuffer<u32, PREV_PC_COUNT>,
     pub status: Arc<M68000BreakStatusAtomic>,
     pub step: Option<u32>,
+    // Read/write breakpoint that triggered the pending break, if any
+    pub watchpoint_hit: Option<WatchpointHit>,
 }
 
 impl M68000BreakpointManager {
@@ -509,11 +521,12 @@ impl M68000BreakpointManager {
             last_pcs: RingBuffer::new(),
             status: Arc::new(M68000BreakStatusAtomic::new()),
             step: None,
+            watchpoint_hit: None,
         }
     }
 
-    pub fn set_break_status(&self) {
-        self.status.set_breaking(self.last_pcs.reverse_iter());
+    pub fn set_break_status(&mut self) {
+        self.status.set_breaking(self.last_pcs.reverse_iter(), self.watchpoint_hit.take());
     }
 
     pub fn clear_break_status(&self) {
@@ -526,13 +539,21 @@ impl M68000BreakpointManager {
     }
 
     #[must_use]
-    pub fn check_read<const WORD: bool>(&self, address: u32) -> bool {
-        self.breakpoints.check_read::<WORD>(address)
+    pub fn check_read<const WORD: bool>(&mut self, address: u32) -> bool {
+        let hit = self.breakpoints.check_read::<WORD>(address);
+        if hit {
+            self.watchpoint_hit = Some(WatchpointHit { address, write: false });
+        }
+        hit
     }
 
     #[must_use]
-    pub fn check_write<const WORD: bool>(&self, address: u32) -> bool {
-        self.breakpoints.check_write::<WORD>(address)
+    pub fn check_write<const WORD: bool>(&mut self, address: u32) -> bool {
+        let hit = self.breakpoints.check_write::<WORD>(address);
+        if hit {
+            self.watchpoint_hit = Some(WatchpointHit { address, write: true });
+        }
+        hit
     }
 
     #[must_use]
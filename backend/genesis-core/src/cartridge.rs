@@ -1,4 +1,5 @@
 use crate::cartridge::external::ExternalMemory;
+use crate::cartridge::unlicensed::UnlicensedMapper;
 use crate::memory::PhysicalMedium;
 use crate::svp::Svp;
 use bincode::{Decode, Encode};
@@ -12,6 +13,7 @@ use std::{array, iter, mem};
 
 pub mod eeprom;
 pub mod external;
+pub mod unlicensed;
 
 const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
 
@@ -213,51 +215,184 @@ fn is_super_street_fighter_2(serial_number: &[u8]) -> bool {
     serial_number == b"T-12056 " || serial_number == b"MK-12056" || serial_number == b"T-12043 "
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+enum LockOnCartridge {
+    Sonic2,
+    Sonic3,
+    Other,
+}
+
+impl LockOnCartridge {
+    fn from_rom(rom: &[u8]) -> Self {
+        // Matched on serial number rather than title so that every revision is detected
+        match &rom[0x183..0x18B] {
+            SONIC_2_SERIAL => Self::Sonic2,
+            SONIC_3_SERIAL => Self::Sonic3,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LockOnAddress {
+    Base(u32),
+    LockOn(u32),
+}
+
+// Sonic & Knuckles with a second cartridge inserted into the lock-on slot:
+//   $000000-$1FFFFF: S&K ROM
+//   $200000-$3FFFFF: Lock-on cartridge ROM, with the lock-on cartridge's SRAM mapped in when
+//                    enabled through $A130F1
+// With Sonic 2 locked on, the 256KB "upmem" patch ROM (stored after the 2MB S&K ROM in dumps that
+// include it) replaces the lock-on cartridge at $300000-$3FFFFF. Sonic 3 and other cartridges need
+// no special handling; Sonic 3's FRAM is the lock-on cartridge's SRAM and is mapped through
+// $A130F1 like any other cartridge RAM
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct SonicAndKnucklesMapper {
+    lock_on: LockOnCartridge,
+    upmem_present: bool,
+    ram_mapped: bool,
+}
+
+impl SonicAndKnucklesMapper {
+    const UPMEM_START: u32 = 0x200000;
+    const UPMEM_LEN: u32 = 0x40000;
+
+    fn new(lock_on: LockOnCartridge, upmem_present: bool) -> Self {
+        Self { lock_on, upmem_present, ram_mapped: false }
+    }
+
+    fn map_address(self, address: u32, lock_on_rom: &Rom) -> LockOnAddress {
+        match address {
+            0x300000..=0x3FFFFF
+                if self.lock_on == LockOnCartridge::Sonic2 && self.upmem_present =>
+            {
+                LockOnAddress::Base(Self::UPMEM_START | (address & (Self::UPMEM_LEN - 1)))
+            }
+            0x200000..=0x3FFFFF => {
+                let lock_on_len = ((lock_on_rom.0.len() as u32) << 1).max(2);
+                LockOnAddress::LockOn((address - 0x200000) % lock_on_len)
+            }
+            _ => LockOnAddress::Base(address),
+        }
+    }
+
+    fn read_byte(
+        self,
+        address: u32,
+        rom: &Rom,
+        lock_on_rom: &Rom,
+        external: &ExternalMemory,
+    ) -> Option<u8> {
+        if self.ram_mapped
+            && let Some(byte) = external.read_byte(address)
+        {
+            return Some(byte);
+        }
+
+        match self.map_address(address, lock_on_rom) {
+            LockOnAddress::Base(rom_addr) => rom.read_byte(rom_addr),
+            LockOnAddress::LockOn(rom_addr) => lock_on_rom.read_byte(rom_addr),
+        }
+    }
+
+    fn read_word(
+        self,
+        address: u32,
+        rom: &Rom,
+        lock_on_rom: &Rom,
+        external: &ExternalMemory,
+    ) -> Option<u16> {
+        if self.ram_mapped
+            && let Some(word) = external.read_word(address)
+        {
+            return Some(word);
+        }
+
+        match self.map_address(address, lock_on_rom) {
+            LockOnAddress::Base(rom_addr) => rom.read_word(rom_addr),
+            LockOnAddress::LockOn(rom_addr) => lock_on_rom.read_word(rom_addr),
+        }
+    }
+
+    fn write_byte(self, address: u32, value: u8, external: &mut ExternalMemory) {
+        if self.ram_mapped {
+            external.write_byte(address, value);
+        }
+    }
+
+    fn write_word(self, address: u32, value: u16, external: &mut ExternalMemory) {
+        if self.ram_mapped {
+            external.write_word(address, value);
+        }
+    }
+
+    fn write_register(&mut self, address: u32, value: u8) {
+        if address == 0xA130F1 {
+            write_ram_mapped_register(&mut self.ram_mapped, value);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 enum Mapper {
     Basic(BasicMapper),
     Ssf(SsfMapper),
     Svp(Box<Svp>),
-    // TODO this mapper is probably not implemented correctly - seems to have ROM banking
-    UnlRockmanX3,
+    SonicAndKnuckles(SonicAndKnucklesMapper),
+    Unlicensed(UnlicensedMapper),
 }
 
 impl Mapper {
-    fn read_byte(&mut self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u8> {
+    fn read_byte(
+        &mut self,
+        address: u32,
+        rom: &Rom,
+        lock_on_rom: &Rom,
+        external: &ExternalMemory,
+    ) -> Option<u8> {
         match self {
             Self::Basic(mapper) => mapper.read_byte(address, rom, external),
             Self::Ssf(mapper) => mapper.read_byte(address, rom, external),
+            Self::SonicAndKnuckles(mapper) => mapper.read_byte(address, rom, lock_on_rom, external),
             Self::Svp(svp) => {
                 let word = svp.m68k_read(address, &rom.0);
                 let byte = if !address.bit(0) { word.msb() } else { word.lsb() };
                 Some(byte)
             }
-            Self::UnlRockmanX3 => rom.read_byte(address),
+            Self::Unlicensed(mapper) => mapper.read_byte(address, rom, external),
         }
     }
 
-    fn read_word(&mut self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u16> {
+    fn read_word(
+        &mut self,
+        address: u32,
+        rom: &Rom,
+        lock_on_rom: &Rom,
+        external: &ExternalMemory,
+    ) -> Option<u16> {
         match self {
             Self::Basic(mapper) => mapper.read_word(address, rom, external),
             Self::Ssf(mapper) => mapper.read_word(address, rom, external),
+            Self::SonicAndKnuckles(mapper) => mapper.read_word(address, rom, lock_on_rom, external),
             Self::Svp(svp) => Some(svp.m68k_read(address, &rom.0)),
-            Self::UnlRockmanX3 => {
-                match address {
-                    // The unlicensed Rockman X3 port depends on $A13000 reads returning a value where the lower
-                    // 4 bits are $C or else it will immediately crash and display "decode error"
-                    0xA13000 => Some(0xC),
-                    _ => rom.read_word(address),
-                }
-            }
+            Self::Unlicensed(mapper) => mapper.read_word(address, rom, external),
         }
     }
 
-    fn peek_word(&self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u16> {
+    fn peek_word(
+        &self,
+        address: u32,
+        rom: &Rom,
+        lock_on_rom: &Rom,
+        external: &ExternalMemory,
+    ) -> Option<u16> {
         match self {
             Self::Basic(mapper) => mapper.read_word(address, rom, external),
             Self::Ssf(mapper) => mapper.read_word(address, rom, external),
+            Self::SonicAndKnuckles(mapper) => mapper.read_word(address, rom, lock_on_rom, external),
             Self::Svp(svp) => Some(svp.m68k_peek(address, &rom.0)),
-            Self::UnlRockmanX3 => rom.read_word(address),
+            Self::Unlicensed(mapper) => mapper.peek_word(address, rom, external),
         }
     }
 
@@ -265,8 +400,9 @@ impl Mapper {
         match self {
             Self::Basic(mapper) => mapper.write_byte(address, value, external),
             Self::Ssf(mapper) => mapper.write_byte(address, value, external),
+            Self::SonicAndKnuckles(mapper) => mapper.write_byte(address, value, external),
             Self::Svp(svp) => svp.m68k_write_byte(address, value),
-            Self::UnlRockmanX3 => {}
+            Self::Unlicensed(mapper) => mapper.write_byte(address, value, external),
         }
     }
 
@@ -274,8 +410,9 @@ impl Mapper {
         match self {
             Self::Basic(mapper) => mapper.write_word(address, value, external),
             Self::Ssf(mapper) => mapper.write_word(address, value, external),
+            Self::SonicAndKnuckles(mapper) => mapper.write_word(address, value, external),
             Self::Svp(svp) => svp.m68k_write_word(address, value),
-            Self::UnlRockmanX3 => {}
+            Self::Unlicensed(mapper) => mapper.write_word(address, value, external),
         }
     }
 
@@ -283,8 +420,9 @@ impl Mapper {
         match self {
             Self::Basic(mapper) => mapper.write_register(address, value),
             Self::Ssf(mapper) => mapper.write_register(address, value),
+            Self::SonicAndKnuckles(mapper) => mapper.write_register(address, value),
             Self::Svp(svp) => svp.m68k_write_byte(address, value),
-            Self::UnlRockmanX3 => {}
+            Self::Unlicensed(mapper) => mapper.write_register_byte(address, value),
         }
     }
 
@@ -292,8 +430,9 @@ impl Mapper {
         match self {
             Self::Basic(mapper) => mapper.write_register(address | 1, value as u8),
             Self::Ssf(mapper) => mapper.write_register(address | 1, value as u8),
+            Self::SonicAndKnuckles(mapper) => mapper.write_register(address | 1, value as u8),
             Self::Svp(svp) => svp.m68k_write_word(address, value),
-            Self::UnlRockmanX3 => {}
+            Self::Unlicensed(mapper) => mapper.write_register_word(address, value),
         }
     }
 
@@ -302,7 +441,8 @@ impl Mapper {
             Self::Basic(..) => "Basic",
             Self::Ssf(..) => "SSF",
             Self::Svp(..) => "SVP",
-            Self::UnlRockmanX3 => "Unlicensed Rockman X3",
+            Self::SonicAndKnuckles(..) => "Sonic & Knuckles Lock-On",
+            Self::Unlicensed(mapper) => mapper.name(),
         }
     }
 }
@@ -404,6 +544,8 @@ pub struct CartridgeHeader {
 pub struct Cartridge {
     #[partial_clone(default)]
     rom: Rom,
+    #[partial_clone(default)]
+    lock_on_rom: Rom,
     external: ExternalMemory,
     mapper: Mapper,
     region: GenesisRegion,
@@ -415,7 +557,9 @@ const TRIPLE_PLAY_96_SERIAL: &[u8] = b"T-172026";
 
 const QUACKSHOT_REV_A_SERIAL: &[u8] = b"GM 00004054-01";
 
-const ROCKMAN_X3_CHECKSUM: u32 = 0x3EE639F0;
+const SONIC_AND_KNUCKLES_SERIAL: &[u8] = b"MK-1563 ";
+const SONIC_2_SERIAL: &[u8] = b"00001051";
+const SONIC_3_SERIAL: &[u8] = b"MK-1079 ";
 
 impl Cartridge {
     #[must_use]
@@ -423,6 +567,20 @@ impl Cartridge {
         rom_bytes: Vec<u8>,
         initial_ram_bytes: Option<Vec<u8>>,
         forced_region: Option<GenesisRegion>,
+    ) -> Self {
+        Self::from_rom_with_lock_on(rom_bytes, None, initial_ram_bytes, forced_region)
+    }
+
+    /// Create a cartridge with an optional second cartridge inserted into the lock-on slot.
+    ///
+    /// The lock-on ROM is only used if the base cartridge is Sonic & Knuckles; it is ignored
+    /// otherwise.
+    #[must_use]
+    pub fn from_rom_with_lock_on(
+        rom_bytes: Vec<u8>,
+        lock_on_rom_bytes: Option<Vec<u8>>,
+        initial_ram_bytes: Option<Vec<u8>>,
+        forced_region: Option<GenesisRegion>,
     ) -> Self {
         // Take checksum before potentially byteswapping the ROM
         let checksum = CRC.checksum(&rom_bytes);
@@ -438,6 +596,19 @@ impl Cartridge {
         });
         log::info!("Genesis hardware region: {region:?}");
 
+        if let Some(lock_on_rom_bytes) = lock_on_rom_bytes {
+            if &rom_bytes[0x183..0x18B] == SONIC_AND_KNUCKLES_SERIAL {
+                return Self::new_sonic_and_knuckles(
+                    rom_bytes,
+                    lock_on_rom_bytes,
+                    initial_ram_bytes,
+                    region,
+                );
+            }
+
+            log::warn!("Ignoring lock-on ROM because the cartridge is not Sonic & Knuckles");
+        }
+
         let external_memory = ExternalMemory::from_rom(&rom_bytes, checksum, initial_ram_bytes);
 
         let serial_number = &rom_bytes[0x183..0x18B];
@@ -465,8 +636,8 @@ impl Cartridge {
             Mapper::Svp(Box::new(Svp::new()))
         } else if SsfMapper::should_use(&rom_bytes) {
             Mapper::Ssf(SsfMapper::new(initial_ram_mapped))
-        } else if checksum == ROCKMAN_X3_CHECKSUM {
-            Mapper::UnlRockmanX3
+        } else if let Some(mapper_type) = unlicensed::detect(&rom_bytes, checksum) {
+            Mapper::Unlicensed(UnlicensedMapper::new(mapper_type, initial_ram_mapped))
         } else {
             Mapper::Basic(BasicMapper::new(initial_ram_mapped))
         };
@@ -479,7 +650,54 @@ impl Cartridge {
 
         let program_title = parse_title_from_header(&rom_bytes, region);
 
-        Self { rom: Rom::new(rom_bytes), external: external_memory, mapper, region, program_title }
+        Self {
+            rom: Rom::new(rom_bytes),
+            lock_on_rom: Rom::default(),
+            external: external_memory,
+            mapper,
+            region,
+            program_title,
+        }
+    }
+
+    fn new_sonic_and_knuckles(
+        rom_bytes: Vec<u8>,
+        lock_on_rom_bytes: Vec<u8>,
+        initial_ram_bytes: Option<Vec<u8>>,
+        region: GenesisRegion,
+    ) -> Self {
+        let lock_on_checksum = CRC.checksum(&lock_on_rom_bytes);
+        log::info!("Lock-on ROM CRC32: {lock_on_checksum:08X}");
+
+        let lock_on_rom_bytes = ensure_rom_in_expected_format(lock_on_rom_bytes);
+        let lock_on = LockOnCartridge::from_rom(&lock_on_rom_bytes);
+        log::info!("Lock-on cartridge: {lock_on:?}");
+
+        // S&K has no SRAM of its own, but the lock-on cartridge might (e.g. Sonic 3's FRAM).
+        // Lock-on SRAM is only mapped in after a write to $A130F1
+        let external_memory =
+            ExternalMemory::from_rom(&lock_on_rom_bytes, lock_on_checksum, initial_ram_bytes);
+
+        let upmem_present = rom_bytes.len() > SonicAndKnucklesMapper::UPMEM_START as usize;
+        if lock_on == LockOnCartridge::Sonic2 && !upmem_present {
+            log::warn!(
+                "S&K ROM does not contain the 256KB Sonic 2 patch ROM; Knuckles in Sonic 2 will not work"
+            );
+        }
+
+        let mapper = Mapper::SonicAndKnuckles(SonicAndKnucklesMapper::new(lock_on, upmem_present));
+        log::info!("Using mapper {}", mapper.name());
+
+        let program_title = parse_title_from_header(&rom_bytes, region);
+
+        Self {
+            rom: Rom::new(rom_bytes),
+            lock_on_rom: Rom::new(lock_on_rom_bytes),
+            external: external_memory,
+            mapper,
+            region,
+            program_title,
+        }
     }
 
     #[inline]
@@ -495,8 +713,16 @@ impl Cartridge {
         words_to_bytes(words)
     }
 
+    /// Take the lock-on cartridge ROM, or `None` if no lock-on cartridge is inserted.
+    #[must_use]
+    pub fn take_lock_on_rom(&mut self) -> Option<Vec<u8>> {
+        let words = mem::take(&mut self.lock_on_rom.0);
+        (!words.is_empty()).then(|| words_to_bytes(words))
+    }
+
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.rom = mem::take(&mut other.rom);
+        self.lock_on_rom = mem::take(&mut other.lock_on_rom);
     }
 
     #[inline]
@@ -531,7 +757,9 @@ impl Cartridge {
 
     #[must_use]
     pub fn peek_word(&self, address: u32) -> u16 {
-        self.mapper.peek_word(address, &self.rom, &self.external).unwrap_or(0xFFFF)
+        self.mapper
+            .peek_word(address, &self.rom, &self.lock_on_rom, &self.external)
+            .unwrap_or(0xFFFF)
     }
 
     #[must_use]
@@ -684,12 +912,12 @@ fn is_virtua_racing(serial_number: &[u8]) -> bool {
 impl PhysicalMedium for Cartridge {
     #[inline]
     fn read_byte(&mut self, address: u32) -> u8 {
-        self.mapper.read_byte(address, &self.rom, &self.external).unwrap_or(!0)
+        self.mapper.read_byte(address, &self.rom, &self.lock_on_rom, &self.external).unwrap_or(!0)
     }
 
     #[inline]
     fn read_word(&mut self, address: u32) -> u16 {
-        self.mapper.read_word(address, &self.rom, &self.external).unwrap_or(!0)
+        self.mapper.read_word(address, &self.rom, &self.lock_on_rom, &self.external).unwrap_or(!0)
     }
 
     #[inline]
@@ -744,3 +972,108 @@ impl PhysicalMedium for Cartridge {
         Some(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_rom(len: usize, serial: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0; len];
+        rom[0x100..0x110].copy_from_slice(b"SEGA MEGA DRIVE ");
+        rom[0x180..0x183].copy_from_slice(b"GM ");
+        rom[0x183..0x18B].copy_from_slice(serial);
+        rom[0x1F0..0x1F3].copy_from_slice(b"JUE");
+        rom
+    }
+
+    fn sonic_and_knuckles_rom(len: usize) -> Vec<u8> {
+        let mut rom = test_rom(len, SONIC_AND_KNUCKLES_SERIAL);
+        rom[0x1000] = 0x11;
+        rom
+    }
+
+    #[test]
+    fn sonic_and_knuckles_with_sonic_2() {
+        let mut sk_rom = sonic_and_knuckles_rom(0x240000);
+        sk_rom[0x201000] = 0x22;
+        let mut s2_rom = test_rom(0x100000, SONIC_2_SERIAL);
+        s2_rom[0x1000] = 0x33;
+
+        let mut cartridge =
+            Cartridge::from_rom_with_lock_on(sk_rom, Some(s2_rom.clone()), None, None);
+        assert!(matches!(
+            cartridge.mapper,
+            Mapper::SonicAndKnuckles(SonicAndKnucklesMapper {
+                lock_on: LockOnCartridge::Sonic2,
+                ..
+            })
+        ));
+
+        // S&K ROM, then Sonic 2, then the upmem patch ROM at $300000
+        assert_eq!(cartridge.read_byte(0x001000), 0x11);
+        assert_eq!(cartridge.read_byte(0x201000), 0x33);
+        assert_eq!(cartridge.read_byte(0x301000), 0x22);
+        assert_eq!(cartridge.read_byte(0x341000), 0x22);
+
+        // Without upmem, Sonic 2 is mirrored to $300000
+        let mut cartridge = Cartridge::from_rom_with_lock_on(
+            sonic_and_knuckles_rom(0x200000),
+            Some(s2_rom),
+            None,
+            None,
+        );
+        assert_eq!(cartridge.read_byte(0x301000), 0x33);
+    }
+
+    #[test]
+    fn sonic_and_knuckles_with_sonic_3() {
+        let mut s3_rom = test_rom(0x200000, SONIC_3_SERIAL);
+        // 8-bit FRAM at odd addresses $200001-$2003FF
+        s3_rom[0x1B0..0x1BC].copy_from_slice(&[
+            b'R', b'A', 0xF8, 0x20, 0x00, 0x20, 0x00, 0x01, 0x00, 0x20, 0x03, 0xFF,
+        ]);
+        s3_rom[0x000001] = 0x44;
+
+        let mut cartridge = Cartridge::from_rom_with_lock_on(
+            sonic_and_knuckles_rom(0x200000),
+            Some(s3_rom),
+            Some(vec![0x55; 0x200]),
+            None,
+        );
+        assert!(matches!(
+            cartridge.mapper,
+            Mapper::SonicAndKnuckles(SonicAndKnucklesMapper {
+                lock_on: LockOnCartridge::Sonic3,
+                ..
+            })
+        ));
+        assert!(cartridge.is_ram_persistent());
+
+        // Lock-on ROM until SRAM is mapped in through $A130F1; writes to ROM are ignored
+        assert_eq!(cartridge.read_byte(0x200001), 0x44);
+        cartridge.write_byte(0x200001, 0x99);
+        assert_eq!(cartridge.read_byte(0x200001), 0x44);
+
+        cartridge.write_byte(0xA130F1, 0x01);
+        assert_eq!(cartridge.read_byte(0x200001), 0x55);
+        cartridge.write_byte(0x200001, 0x99);
+        assert_eq!(cartridge.read_byte(0x200001), 0x99);
+        assert_eq!(cartridge.external_ram()[0], 0x99);
+        assert!(cartridge.get_and_clear_ram_dirty());
+
+        cartridge.write_byte(0xA130F1, 0x00);
+        assert_eq!(cartridge.read_byte(0x200001), 0x44);
+    }
+
+    #[test]
+    fn lock_on_ignored_without_sonic_and_knuckles() {
+        let cartridge = Cartridge::from_rom_with_lock_on(
+            test_rom(0x100000, b"MK-1234 "),
+            Some(test_rom(0x100000, SONIC_2_SERIAL)),
+            None,
+            None,
+        );
+        assert!(matches!(cartridge.mapper, Mapper::Basic(_)));
+        assert!(cartridge.lock_on_rom.0.is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! Mappers and copy protection hardware used by unlicensed and pirate cartridges
+
+mod metadata;
+
+use crate::cartridge::external::ExternalMemory;
+use crate::cartridge::{Rom, write_ram_mapped_register};
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+pub use metadata::UnlicensedMapperType;
+
+#[must_use]
+pub fn detect(rom: &[u8], checksum: u32) -> Option<UnlicensedMapperType> {
+    metadata::detect(rom, checksum)
+}
+
+fn rom_len(rom: &Rom) -> u32 {
+    ((rom.0.len() as u32) << 1).max(2)
+}
+
+// Realtec boards boot with the last 8KB of a 512KB ROM mirrored across the entire ROM address space.
+// Writing to $400000 switches to mapping a range of 64KB banks, configured through $402000 (range
+// size in 128KB units) and $404000 (low 3 bits of the range start in 128KB units)
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+pub struct RealtecMapper {
+    rom_mapped: bool,
+    base_bank: u8,
+    base_bank_low: u8,
+    bank_count: u8,
+}
+
+impl RealtecMapper {
+    const BOOT_ROM_ADDR: u32 = 0x7E000;
+
+    fn map_address(self, address: u32) -> u32 {
+        if !self.rom_mapped {
+            return Self::BOOT_ROM_ADDR | (address & 0x1FFF);
+        }
+
+        let bank_count = u32::from(self.bank_count).max(1);
+        let bank = u32::from(self.base_bank) + ((address >> 16) % bank_count);
+        (bank << 16) | (address & 0xFFFF)
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        match address & !1 {
+            0x400000 => {
+                // Bits 1-2 are the high bits of the range start in 1MB units
+                self.base_bank = (self.base_bank_low << 1) | ((value & 0x06) << 3);
+                self.rom_mapped = true;
+
+                log::trace!(
+                    "Realtec ROM mapping set; base bank {}, bank count {}",
+                    self.base_bank,
+                    self.bank_count
+                );
+            }
+            0x402000 => {
+                self.bank_count = value << 1;
+            }
+            0x404000 => {
+                self.base_bank_low = value & 0x07;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Multi-game cartridges map 64 64KB banks starting from a selectable bank. X-in-1 multicarts select
+// the starting bank by writing to $A130xx and Radica plug & play consoles select it by reading
+// from $A130xx, with the bank number coming from the address in both cases
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+pub struct MultiGameMapper {
+    base_bank: u8,
+}
+
+impl MultiGameMapper {
+    fn map_address(self, address: u32) -> u32 {
+        let bank = (u32::from(self.base_bank) + (address >> 16)) & 0x3F;
+        (bank << 16) | (address & 0xFFFF)
+    }
+
+    fn select_bank(&mut self, bank: u32) {
+        self.base_bank = (bank & 0x3F) as u8;
+
+        log::trace!("Multi-game base bank set to {}", self.base_bank);
+    }
+}
+
+// Protection chip used by Lion King 3 and a number of other games from the same developer. The game
+// writes a value to $600000 and a mode to $600002, then reads back the value transformed based on
+// the mode. Writes to $700000-$7FFFFF select the 32KB ROM bank mapped to $000000-$007FFF
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+pub struct LionKing3Mapper {
+    value: u8,
+    mode: u8,
+    rom_bank: u8,
+}
+
+impl LionKing3Mapper {
+    fn map_address(self, address: u32, rom: &Rom) -> u32 {
+        let bank_addr = u32::from(self.rom_bank) << 15;
+        if address <= 0x007FFF && bank_addr < rom_len(rom) { bank_addr | address } else { address }
+    }
+
+    fn protection_value(self) -> u8 {
+        match self.mode & 0x03 {
+            0 => self.value << 1,
+            1 => self.value >> 1,
+            2 => self.value.rotate_left(4),
+            _ => self.value.reverse_bits(),
+        }
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        match address {
+            0x600000..=0x6FFFFF => {
+                if address.bit(1) {
+                    self.mode = value & 0x03;
+                } else {
+                    self.value = value;
+                }
+            }
+            0x700000..=0x7FFFFF => {
+                self.rom_bank = value;
+
+                log::trace!("Lion King 3 ROM bank set to {value:02X}");
+            }
+            _ => {}
+        }
+    }
+}
+
+// Simple protection registers at $400000, $400002, and $400004. Some games only check that the
+// registers read back whatever was last written, while others expect hardcoded values.
+// Registers respond on the even byte
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+pub struct ProtectionRegisters {
+    registers: [u8; 4],
+    writable: bool,
+}
+
+impl ProtectionRegisters {
+    fn register_idx(address: u32) -> Option<usize> {
+        (0x400000..=0x4FFFFF).contains(&address).then_some(((address & 0x07) >> 1) as usize)
+    }
+
+    fn read(self, address: u32) -> Option<u8> {
+        Self::register_idx(address).map(|idx| self.registers[idx])
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        if self.writable
+            && let Some(idx) = Self::register_idx(address)
+        {
+            self.registers[idx] = value;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+enum UnlicensedBoard {
+    RockmanX3,
+    Realtec(RealtecMapper),
+    MultiGame(MultiGameMapper),
+    Radica(MultiGameMapper),
+    LionKing3(LionKing3Mapper),
+    Protection(ProtectionRegisters),
+}
+
+impl UnlicensedBoard {
+    fn new(mapper_type: UnlicensedMapperType) -> Self {
+        match mapper_type {
+            UnlicensedMapperType::RockmanX3 => Self::RockmanX3,
+            UnlicensedMapperType::Realtec => Self::Realtec(RealtecMapper::default()),
+            UnlicensedMapperType::MultiGame => Self::MultiGame(MultiGameMapper::default()),
+            UnlicensedMapperType::Radica => Self::Radica(MultiGameMapper::default()),
+            UnlicensedMapperType::LionKing3 => Self::LionKing3(LionKing3Mapper::default()),
+            UnlicensedMapperType::ReadbackProtection => {
+                Self::Protection(ProtectionRegisters { registers: [0; 4], writable: true })
+            }
+            UnlicensedMapperType::FixedProtection(registers) => {
+                Self::Protection(ProtectionRegisters { registers, writable: false })
+            }
+        }
+    }
+
+    fn map_rom_address(&self, address: u32, rom: &Rom) -> u32 {
+        match self {
+            Self::Realtec(mapper) if address <= 0x3FFFFF => {
+                mapper.map_address(address) % rom_len(rom)
+            }
+            Self::MultiGame(mapper) | Self::Radica(mapper) if address <= 0x3FFFFF => {
+                mapper.map_address(address) % rom_len(rom)
+            }
+            Self::LionKing3(mapper) => mapper.map_address(address, rom),
+            _ => address,
+        }
+    }
+
+    fn read_register(&mut self, address: u32) -> Option<u8> {
+        match self {
+            Self::Radica(mapper) if (0xA13000..=0xA1307F).contains(&address) => {
+                mapper.select_bank(address >> 1);
+                Some(0xFF)
+            }
+            _ => self.peek_register(address),
+        }
+    }
+
+    fn peek_register(&self, address: u32) -> Option<u8> {
+        match self {
+            Self::LionKing3(mapper) if (0x600000..=0x7FFFFF).contains(&address) => {
+                Some(mapper.protection_value())
+            }
+            Self::Protection(registers) => registers.read(address),
+            _ => None,
+        }
+    }
+
+    fn read_byte(&mut self, address: u32, rom: &Rom) -> Option<u8> {
+        match self.read_register(address) {
+            Some(byte) => Some(byte),
+            None => rom.read_byte(self.map_rom_address(address, rom)),
+        }
+    }
+
+    fn read_word(&mut self, address: u32, rom: &Rom) -> Option<u16> {
+        if let Some(byte) = self.read_register(address) {
+            return Some(self.register_word(byte));
+        }
+
+        match self {
+            // The unlicensed Rockman X3 port depends on $A13000 reads returning a value where the
+            // lower 4 bits are $C or else it will immediately crash and display "decode error"
+            Self::RockmanX3 if address == 0xA13000 => Some(0xC),
+            _ => rom.read_word(self.map_rom_address(address, rom)),
+        }
+    }
+
+    fn peek_word(&self, address: u32, rom: &Rom) -> Option<u16> {
+        match self.peek_register(address) {
+            Some(byte) => Some(self.register_word(byte)),
+            None => rom.read_word(self.map_rom_address(address, rom)),
+        }
+    }
+
+    fn register_word(&self, byte: u8) -> u16 {
+        match self {
+            // Protection registers are on the even byte
+            Self::Protection(..) => u16::from(byte) << 8,
+            Self::LionKing3(..) => byte.into(),
+            _ => u16::from_le_bytes([byte, byte]),
+        }
+    }
+
+    fn write_byte(&mut self, address: u32, value: u8) {
+        match self {
+            Self::RockmanX3 | Self::Radica(..) => {}
+            Self::Realtec(mapper) => mapper.write(address, value),
+            Self::MultiGame(mapper) => {
+                if (0xA13000..=0xA130FF).contains(&address) {
+                    mapper.select_bank(address);
+                }
+            }
+            Self::LionKing3(mapper) => mapper.write(address, value),
+            Self::Protection(registers) => registers.write(address, value),
+        }
+    }
+
+    fn write_word(&mut self, address: u32, value: u16) {
+        match self {
+            // Protection registers are on the even byte
+            Self::Protection(registers) => registers.write(address, (value >> 8) as u8),
+            _ => self.write_byte(address, value as u8),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::RockmanX3 => "Unlicensed Rockman X3",
+            Self::Realtec(..) => "Realtec",
+            Self::MultiGame(..) => "Multi-Game",
+            Self::Radica(..) => "Radica",
+            Self::LionKing3(..) => "Lion King 3 Protection",
+            Self::Protection(..) => "Protection Registers",
+        }
+    }
+}
+
+// Some unlicensed cartridges also have SRAM, which is mapped the same way as in licensed cartridges:
+// mapped on power-on if it's past the end of ROM, and otherwise mapped in by a write to $A130F1.
+// SRAM takes priority over ROM but never overlaps any of the boards' registers
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct UnlicensedMapper {
+    board: UnlicensedBoard,
+    ram_mapped: bool,
+}
+
+impl UnlicensedMapper {
+    #[must_use]
+    pub fn new(mapper_type: UnlicensedMapperType, initial_ram_mapped: bool) -> Self {
+        Self { board: UnlicensedBoard::new(mapper_type), ram_mapped: initial_ram_mapped }
+    }
+
+    #[must_use]
+    pub fn read_byte(&mut self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u8> {
+        if self.ram_mapped
+            && let Some(byte) = external.read_byte(address)
+        {
+            return Some(byte);
+        }
+
+        self.board.read_byte(address, rom)
+    }
+
+    #[must_use]
+    pub fn read_word(&mut self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u16> {
+        if self.ram_mapped
+            && let Some(word) = external.read_word(address)
+        {
+            return Some(word);
+        }
+
+        self.board.read_word(address, rom)
+    }
+
+    #[must_use]
+    pub fn peek_word(&self, address: u32, rom: &Rom, external: &ExternalMemory) -> Option<u16> {
+        if self.ram_mapped
+            && let Some(word) = external.read_word(address)
+        {
+            return Some(word);
+        }
+
+        self.board.peek_word(address, rom)
+    }
+
+    pub fn write_byte(&mut self, address: u32, value: u8, external: &mut ExternalMemory) {
+        if self.ram_mapped {
+            external.write_byte(address, value);
+        }
+
+        self.board.write_byte(address, value);
+    }
+
+    pub fn write_word(&mut self, address: u32, value: u16, external: &mut ExternalMemory) {
+        if self.ram_mapped {
+            external.write_word(address, value);
+        }
+
+        self.board.write_word(address, value);
+    }
+
+    pub fn write_register_byte(&mut self, address: u32, value: u8) {
+        self.update_ram_mapped(address, value);
+        self.board.write_byte(address, value);
+    }
+
+    pub fn write_register_word(&mut self, address: u32, value: u16) {
+        self.update_ram_mapped(address | 1, value as u8);
+        self.board.write_word(address, value);
+    }
+
+    fn update_ram_mapped(&mut self, address: u32, value: u8) {
+        // Multi-game cartridges use every $A130xx write for bank selection
+        if address == 0xA130F1 && !matches!(self.board, UnlicensedBoard::MultiGame(..)) {
+            write_ram_mapped_register(&mut self.ram_mapped, value);
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        self.board.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every word in the test ROM contains bits 8-23 of its own address
+    fn test_rom(len: usize) -> Rom {
+        Rom((0..len / 2).map(|i| ((i * 2) >> 8) as u16).collect())
+    }
+
+    #[test]
+    fn realtec_banking() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x80000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::Realtec, false);
+
+        // Last 8KB of the first 512KB is mirrored across the ROM address space on boot
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x7E0));
+        assert_eq!(mapper.read_word(0x123456, &rom, &external), Some(0x7F4));
+
+        // Map 2 banks starting from bank 2
+        mapper.write_byte(0x404000, 0x01, &mut external);
+        mapper.write_byte(0x402000, 0x01, &mut external);
+        mapper.write_byte(0x400000, 0x00, &mut external);
+
+        assert_eq!(mapper.read_word(0x000100, &rom, &external), Some(0x201));
+        assert_eq!(mapper.read_word(0x010100, &rom, &external), Some(0x301));
+        assert_eq!(mapper.read_word(0x020100, &rom, &external), Some(0x201));
+    }
+
+    #[test]
+    fn multi_game_banking() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x400000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::MultiGame, false);
+
+        assert_eq!(mapper.read_word(0x010000, &rom, &external), Some(0x100));
+
+        // Bank comes from the address, not the value
+        mapper.write_byte(0xA13005, 0x00, &mut external);
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x500));
+        assert_eq!(mapper.read_word(0x3F0000, &rom, &external), Some(0x400));
+
+        // Writes outside of $A130xx are ignored
+        mapper.write_byte(0xA14003, 0x00, &mut external);
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x500));
+    }
+
+    #[test]
+    fn radica_banking() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x400000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::Radica, false);
+
+        // Radica selects the bank by reading from $A130xx; writes are ignored
+        mapper.write_byte(0xA13006, 0x00, &mut external);
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x000));
+
+        assert_eq!(mapper.read_byte(0xA13004, &rom, &external), Some(0xFF));
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x200));
+        assert_eq!(mapper.read_word(0x010000, &rom, &external), Some(0x300));
+    }
+
+    #[test]
+    fn lion_king_3_protection() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x80000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::LionKing3, false);
+
+        mapper.write_byte(0x600000, 0x12, &mut external);
+        for (mode, expected) in [(0, 0x24), (1, 0x09), (2, 0x21), (3, 0x48)] {
+            mapper.write_byte(0x600002, mode, &mut external);
+            assert_eq!(mapper.read_byte(0x600000, &rom, &external), Some(expected), "mode {mode}");
+            assert_eq!(
+                mapper.read_word(0x600000, &rom, &external),
+                Some(expected.into()),
+                "mode {mode}"
+            );
+        }
+    }
+
+    #[test]
+    fn lion_king_3_banking() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x80000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::LionKing3, false);
+
+        mapper.write_byte(0x700000, 0x03, &mut external);
+        assert_eq!(mapper.read_word(0x000100, &rom, &external), Some(0x181));
+
+        // Only $000000-$007FFF is banked
+        assert_eq!(mapper.read_word(0x008100, &rom, &external), Some(0x081));
+
+        // Banks past the end of the ROM are ignored
+        mapper.write_byte(0x700000, 0x10, &mut external);
+        assert_eq!(mapper.read_word(0x000100, &rom, &external), Some(0x001));
+    }
+
+    #[test]
+    fn readback_protection() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x80000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::ReadbackProtection, false);
+
+        // Registers respond on the even byte
+        mapper.write_word(0x400000, 0xAB00, &mut external);
+        mapper.write_byte(0x400002, 0xCD, &mut external);
+        assert_eq!(mapper.read_word(0x400000, &rom, &external), Some(0xAB00));
+        assert_eq!(mapper.read_word(0x400002, &rom, &external), Some(0xCD00));
+        assert_eq!(mapper.read_byte(0x400004, &rom, &external), Some(0x00));
+    }
+
+    #[test]
+    fn fixed_protection() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x80000);
+        let mut mapper = UnlicensedMapper::new(
+            UnlicensedMapperType::FixedProtection([0x55, 0x0F, 0xC9, 0x18]),
+            false,
+        );
+
+        // Writes are ignored
+        mapper.write_word(0x400000, 0xAB00, &mut external);
+        assert_eq!(mapper.read_word(0x400000, &rom, &external), Some(0x5500));
+        assert_eq!(mapper.read_word(0x400004, &rom, &external), Some(0xC900));
+        assert_eq!(mapper.read_byte(0x400006, &rom, &external), Some(0x18));
+    }
+
+    #[test]
+    fn lion_king_3_banking_past_4mb() {
+        let mut external = ExternalMemory::None;
+        let rom = test_rom(0x600000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::LionKing3, false);
+
+        // Top Fighter's upper 2MB is only reachable through the bank register
+        mapper.write_byte(0x700000, 0xBC, &mut external);
+        assert_eq!(mapper.read_word(0x000100, &rom, &external), Some(0x5E01));
+    }
+
+    #[test]
+    fn sram_mapping() {
+        // 8KB of SRAM at $200001-$203FFF
+        let mut external = ExternalMemory::from_rom(&[], 0x8135702C, None);
+        let rom = test_rom(0x400000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::ReadbackProtection, false);
+
+        // SRAM overlaps ROM, so it's not mapped until a write to $A130F1
+        mapper.write_byte(0x200001, 0x5A, &mut external);
+        assert_eq!(mapper.read_byte(0x200001, &rom, &external), Some(0x00));
+
+        mapper.write_register_byte(0xA130F1, 0x01);
+        mapper.write_byte(0x200001, 0x5A, &mut external);
+        assert_eq!(mapper.read_byte(0x200001, &rom, &external), Some(0x5A));
+        assert_eq!(mapper.read_word(0x200000, &rom, &external), Some(0x5A5A));
+
+        // Protection registers are unaffected
+        mapper.write_word(0x400000, 0xAB00, &mut external);
+        assert_eq!(mapper.read_word(0x400000, &rom, &external), Some(0xAB00));
+
+        mapper.write_register_byte(0xA130F1, 0x00);
+        assert_eq!(mapper.read_byte(0x200001, &rom, &external), Some(0x00));
+
+        // SRAM past the end of ROM is mapped on power-on
+        let rom = test_rom(0x100000);
+        let mapper = UnlicensedMapper::new(UnlicensedMapperType::ReadbackProtection, true);
+        assert_eq!(mapper.peek_word(0x200000, &rom, &external), Some(0x5A5A));
+    }
+
+    #[test]
+    fn multi_game_ignores_ram_register() {
+        let mut external = ExternalMemory::from_rom(&[], 0x8135702C, None);
+        let rom = test_rom(0x400000);
+        let mut mapper = UnlicensedMapper::new(UnlicensedMapperType::MultiGame, false);
+
+        // $A130F1 selects bank $31 instead of mapping SRAM
+        mapper.write_register_byte(0xA130F1, 0x01);
+        mapper.write_byte(0x200001, 0x5A, &mut external);
+        assert_eq!(mapper.read_word(0x000000, &rom, &external), Some(0x3100));
+        assert_eq!(mapper.read_word(0x200000, &rom, &external), Some(0x1100));
+    }
+
+    #[test]
+    fn detect_by_computed_checksum() {
+        let mut rom = vec![0; 0x400];
+        rom[0x18E..0x190].copy_from_slice(&[0x4E, 0xB9]);
+        rom[0x200..0x202].copy_from_slice(&[0x5D, 0x00]);
+        rom[0x3FE..0x400].copy_from_slice(&[0x00, 0x8B]);
+        assert_eq!(detect(&rom, 0), Some(UnlicensedMapperType::LionKing3));
+
+        // Header checksum and computed checksum must both match
+        rom[0x18E] = 0x00;
+        assert_eq!(detect(&rom, 0), None);
+
+        rom[0x18E..0x190].copy_from_slice(&[0xFF, 0xFF]);
+        rom[0x200..0x202].copy_from_slice(&[0xD4, 0x00]);
+        rom[0x3FE..0x400].copy_from_slice(&[0x00, 0x72]);
+        assert_eq!(
+            detect(&rom, 0),
+            Some(UnlicensedMapperType::FixedProtection([0x63, 0x98, 0xC9, 0x18]))
+        );
+    }
+
+    #[test]
+    fn detect_by_header() {
+        let mut rom = vec![0; 0x200000];
+        rom[0x120..0x126].copy_from_slice(b"FLICKY");
+
+        // Multi-game header only matches if the ROM is larger than the first game
+        assert_eq!(detect(&rom, 0), None);
+        rom.resize(0x400000, 0);
+        assert_eq!(detect(&rom, 0), Some(UnlicensedMapperType::MultiGame));
+
+        assert_eq!(detect(&[], 0x3EE639F0), Some(UnlicensedMapperType::RockmanX3));
+    }
+}
@@ -0,0 +1,140 @@
+//! Detection for unlicensed and pirate cartridges that need custom mapper or copy protection
+//! handling
+//!
+//! Most of these cartridges have generic or copied ROM headers, so they're identified by ROM
+//! checksum where possible. Cartridges with a distinctive header are identified by header contents
+//! instead, which also catches alternate dumps. The remaining cartridges are identified the same way
+//! as Genesis Plus GX: by the checksum stored in the header combined with the checksum computed over
+//! the ROM, which is unique even though most of these headers store a checksum of 0.
+//!
+//! Mapper and protection details are from Genesis Plus GX and Picodrive.
+//!
+//! _Top Fighter 2000 MK VIII_ and several other games from the same developer use a PAL with the
+//! same value/mode transform and 32KB ROM banking as _Lion King 3_; _Top Fighter_'s ROM is larger
+//! than 4MB, and the upper half is only reachable through the bank register.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlicensedMapperType {
+    RockmanX3,
+    Realtec,
+    MultiGame,
+    Radica,
+    LionKing3,
+    ReadbackProtection,
+    FixedProtection([u8; 4]),
+}
+
+#[rustfmt::skip]
+const CHECKSUM_TO_MAPPER: &[(u32, UnlicensedMapperType)] = &[
+    (0x3EE639F0, UnlicensedMapperType::RockmanX3),                                    // Rockman X3 (Unl)
+    (0xC9706E25, UnlicensedMapperType::LionKing3),                                    // Lion King 3 (Unl)
+    (0x4EF5D411, UnlicensedMapperType::LionKing3),                                    // Super King Kong 99 (Unl)
+    (0x30F7031F, UnlicensedMapperType::LionKing3),                                    // Pocket Monster II (Unl)
+    (0xB8261FF5, UnlicensedMapperType::ReadbackProtection),                           // Squirrel King (Unl)
+    (0xAFF46765, UnlicensedMapperType::ReadbackProtection),                           // Lion King II (Unl)
+    (0x4820A161, UnlicensedMapperType::FixedProtection([0x55, 0x0F, 0x00, 0x00])),    // Super Bubble Bobble (Unl)
+];
+
+struct ComputedChecksumMatch {
+    header_checksum: u16,
+    computed_checksum: u16,
+    mapper_type: UnlicensedMapperType,
+}
+
+#[rustfmt::skip]
+const COMPUTED_CHECKSUM_TO_MAPPER: &[ComputedChecksumMatch] = &[
+    // Top Fighter 2000 MK VIII (Unl)
+    ComputedChecksumMatch { header_checksum: 0x4EB9, computed_checksum: 0x5D8B, mapper_type: UnlicensedMapperType::LionKing3 },
+    // Soul Edge vs Samurai Spirits (Unl)
+    ComputedChecksumMatch { header_checksum: 0x00FF, computed_checksum: 0x5D34, mapper_type: UnlicensedMapperType::LionKing3 },
+    // Mulan (Unl)
+    ComputedChecksumMatch { header_checksum: 0x0404, computed_checksum: 0x1B40, mapper_type: UnlicensedMapperType::LionKing3 },
+    // Ya Se Chuan Shuo (Unl)
+    ComputedChecksumMatch { header_checksum: 0xFFFF, computed_checksum: 0xD472, mapper_type: UnlicensedMapperType::FixedProtection([0x63, 0x98, 0xC9, 0x18]) },
+    // Tenchi wo Kurau II - The Battle of Red Cliffs (Unl)
+    ComputedChecksumMatch { header_checksum: 0x0000, computed_checksum: 0xED61, mapper_type: UnlicensedMapperType::FixedProtection([0x55, 0x0F, 0xAA, 0xF0]) },
+    // Huan Le Tao Qi Shu - Smart Mouse (Unl)
+    ComputedChecksumMatch { header_checksum: 0x0000, computed_checksum: 0x1A28, mapper_type: UnlicensedMapperType::FixedProtection([0x55, 0x0F, 0xAA, 0xF0]) },
+    // Mighty Morphin Power Rangers - The Fighting Edition (Unl)
+    ComputedChecksumMatch { header_checksum: 0x0000, computed_checksum: 0x5FAD, mapper_type: UnlicensedMapperType::FixedProtection([0x00, 0x00, 0xC9, 0x00]) },
+];
+
+// Checksum as computed by the standard header check: a 16-bit sum of every word after the header
+fn compute_checksum(rom: &[u8]) -> u16 {
+    rom.get(0x200..)
+        .unwrap_or_default()
+        .chunks(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk.get(1).copied().unwrap_or(0)]))
+        .fold(0, u16::wrapping_add)
+}
+
+struct HeaderMatch {
+    address: usize,
+    bytes: &'static [u8],
+    // Multi-game cartridges reuse the header of the first game, so also check that the ROM is
+    // larger than that game
+    min_rom_len: usize,
+    mapper_type: UnlicensedMapperType,
+}
+
+impl HeaderMatch {
+    const fn new(address: usize, bytes: &'static [u8], mapper_type: UnlicensedMapperType) -> Self {
+        Self { address, bytes, min_rom_len: 0, mapper_type }
+    }
+
+    const fn with_min_rom_len(self, min_rom_len: usize) -> Self {
+        Self { min_rom_len, ..self }
+    }
+
+    fn matches(&self, rom: &[u8]) -> bool {
+        rom.len() >= self.min_rom_len
+            && rom.get(self.address..self.address + self.bytes.len()) == Some(self.bytes)
+    }
+}
+
+const HEADER_TO_MAPPER: &[HeaderMatch] = &[
+    // The Earth Defense (Realtec)
+    HeaderMatch::new(0x094, b"THE EARTH DEFEND", UnlicensedMapperType::Realtec),
+    // Funny World & Balloon Boy (Realtec)
+    HeaderMatch::new(0x0FE, b"WISEGAME 11-03-1993", UnlicensedMapperType::Realtec),
+    // Whac-a-Critter (Realtec)
+    HeaderMatch::new(0x095, b"MY HAMMER", UnlicensedMapperType::Realtec),
+    // 12-in-1 (Unl)
+    HeaderMatch::new(0x120, b"FLICKY", UnlicensedMapperType::MultiGame).with_min_rom_len(0x200001),
+    // Super 15-in-1 (Unl)
+    HeaderMatch::new(0x120, b" SHOVE IT!", UnlicensedMapperType::MultiGame)
+        .with_min_rom_len(0x200001),
+    // Super 19-in-1 (Unl)
+    HeaderMatch::new(0x120, b"MS PACMAN", UnlicensedMapperType::MultiGame)
+        .with_min_rom_len(0x200001),
+    // Radica: Volume 1
+    HeaderMatch::new(0x150, b"KID CHAMELEON", UnlicensedMapperType::Radica)
+        .with_min_rom_len(0x300001),
+    // Elf Wor (Unl)
+    HeaderMatch::new(
+        0x172,
+        b"GAME : ELF WOR",
+        UnlicensedMapperType::FixedProtection([0x55, 0x0F, 0xC9, 0x18]),
+    ),
+];
+
+pub fn detect(rom: &[u8], checksum: u32) -> Option<UnlicensedMapperType> {
+    for &(mapper_checksum, mapper_type) in CHECKSUM_TO_MAPPER {
+        if checksum == mapper_checksum {
+            return Some(mapper_type);
+        }
+    }
+
+    if let Some(header) = HEADER_TO_MAPPER.iter().find(|header| header.matches(rom)) {
+        return Some(header.mapper_type);
+    }
+
+    let header_checksum = u16::from_be_bytes([*rom.get(0x18E)?, *rom.get(0x18F)?]);
+    let computed_checksum = compute_checksum(rom);
+    COMPUTED_CHECKSUM_TO_MAPPER
+        .iter()
+        .find(|entry| {
+            entry.header_checksum == header_checksum && entry.computed_checksum == computed_checksum
+        })
+        .map(|entry| entry.mapper_type)
+}
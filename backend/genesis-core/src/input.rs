@@ -1,30 +1,72 @@
 //! Code for handling Genesis controller input I/O registers
+//!
+//! In addition to standard 3-button and 6-button controllers, this supports two multitaps:
+//! - Sega Team Player: Plugs into a single port and supports up to 4 controllers. The game reads
+//!   controllers one nibble at a time, using TR as a request line and TL as an acknowledge line
+//! - EA 4-Way Play: Plugs into both ports. The game selects a controller by writing to port 2's
+//!   data register and then reads the selected controller through port 1
+//!
+//! It also supports the Sega Mega Mouse and two light guns, the Sega Menacer and the Konami
+//! Justifier. Light guns assert the HL line when the beam passes the pointer position, which
+//! latches the VDP's H/V counter and can trigger an external interrupt (INT2).
 
 use crate::GenesisEmulatorConfig;
 use bincode::{Decode, Encode};
-use genesis_config::{GenesisControllerType, GenesisInputs, GenesisJoypadState};
+use genesis_config::{
+    GenesisControllerType, GenesisInputs, GenesisJoypadState, LightGunState, MegaMouseState,
+};
 use jgenesis_common::num::GetBit;
 
 // Produces roughly the expected timeout value in Joystick Test Program (PD)
 const FLIP_COUNTER_CYCLES: u32 = 12150;
 
 const TH_BIT: u8 = 6;
+const TR_BIT: u8 = 5;
+const TL_BIT: u8 = 4;
 
-#[derive(Debug, Clone, Copy, Encode, Decode)]
-struct PinDirections {
+// CTRL bit 7 routes TH input to the HL line
+const TH_INTERRUPT_BIT: u8 = 7;
+
+const MAX_PLAYERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct Port {
     last_data_write: u8,
     last_ctrl_write: u8,
+}
+
+impl Port {
+    fn th_is_output(self) -> bool {
+        self.last_ctrl_write.bit(TH_BIT)
+    }
+
+    // Pins set to input are pulled high
+    fn pin_states(self) -> u8 {
+        (self.last_data_write & self.last_ctrl_write) | (!self.last_ctrl_write & 0x7F)
+    }
+
+    fn to_data_byte(self, device_byte: u8) -> u8 {
+        // Only bits set to input come from the device (corresponding bit in CTRL = 0)
+        let device_byte = device_byte & !self.last_ctrl_write;
+
+        // Bit 7 always comes from the last data write
+        let outputs_byte = self.last_data_write & (self.last_ctrl_write | 0x80);
+
+        device_byte | outputs_byte
+    }
+}
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct Gamepad {
     th_flip_count: u8,
     flip_reset_counter: u32,
     cycles_until_th_high: u32,
     controller_th: bool,
 }
 
-impl Default for PinDirections {
+impl Default for Gamepad {
     fn default() -> Self {
         Self {
-            last_data_write: 0,
-            last_ctrl_write: 0,
             th_flip_count: 0,
             flip_reset_counter: 0,
             cycles_until_th_high: 0,
@@ -34,42 +76,36 @@ impl Default for PinDirections {
     }
 }
 
-impl PinDirections {
-    fn write_ctrl(&mut self, ctrl_byte: u8, controller_type: GenesisControllerType) {
-        self.last_ctrl_write = ctrl_byte;
-        self.maybe_set_th(controller_type);
+impl Gamepad {
+    fn handle_ctrl_write(&mut self, port: Port, six_button: bool) {
+        self.maybe_set_th(port, six_button);
 
         // When TH is set to input, the controller's TH should get pulled high, but only after a
         // short delay.
         // Micro Machines depends on it getting pulled high within ~70 68K CPU cycles, while
         // Trouble Shooter depends on it _not_ getting pulled high until after ~15 68K CPU cycles.
-        self.cycles_until_th_high = if !ctrl_byte.bit(TH_BIT) { 30 } else { 0 };
+        self.cycles_until_th_high = if !port.th_is_output() { 30 } else { 0 };
     }
 
-    fn write_data(&mut self, data_byte: u8, controller_type: GenesisControllerType) {
-        self.last_data_write = data_byte;
-        self.maybe_set_th(controller_type);
-    }
-
-    fn maybe_set_th(&mut self, controller_type: GenesisControllerType) {
-        if !self.last_ctrl_write.bit(TH_BIT) {
+    fn maybe_set_th(&mut self, port: Port, six_button: bool) {
+        if !port.th_is_output() {
             // TH bit is set to input; writes won't take effect until it's changed back to output
             return;
         }
 
-        let th = self.last_data_write.bit(TH_BIT);
+        let th = port.last_data_write.bit(TH_BIT);
 
         // 6-button controller cycles through 4 different modes whenever TH flips from 0 to 1,
         // resetting after ~1.5ms have passed without such a flip
-        if controller_type == GenesisControllerType::SixButton && !self.controller_th && th {
+        if six_button && !self.controller_th && th {
             self.th_flip_count = (self.th_flip_count + 1) % 4;
             self.flip_reset_counter = FLIP_COUNTER_CYCLES;
         }
         self.controller_th = th;
     }
 
-    fn to_data_byte(self, joypad_state: GenesisJoypadState) -> u8 {
-        let mut controller_byte = match (self.th_flip_count, self.controller_th) {
+    fn read(self, joypad_state: GenesisJoypadState) -> u8 {
+        let controller_byte = match (self.th_flip_count, self.controller_th) {
             (0..=2, true) => {
                 // 3-button: B, C, and directional inputs
                 (u8::from(!joypad_state.c) << 5)
@@ -105,19 +141,8 @@ impl PinDirections {
             }
             _ => panic!("th_flip_count should always be <= 3, was {}", self.th_flip_count),
         };
-        controller_byte |= u8::from(self.controller_th) << 6;
-
-        // Only bits set to input come from the controller (corresponding bit in CTRL = 0)
-        controller_byte &= !self.last_ctrl_write;
-
-        // Bit 7 always comes from the last data write
-        let outputs_byte = self.last_data_write & (self.last_ctrl_write | 0x80);
 
-        controller_byte | outputs_byte
-    }
-
-    fn to_ctrl_byte(self) -> u8 {
-        self.last_ctrl_write
+        controller_byte | (u8::from(self.controller_th) << 6)
     }
 
     fn tick(&mut self, m68k_cycles: u32) {
@@ -135,13 +160,249 @@ impl PinDirections {
     }
 }
 
+// Controller type IDs reported by the Team Player during its ID sequence
+const TEAM_PLAYER_SIX_BUTTON: u8 = 0x1;
+const TEAM_PLAYER_NOT_CONNECTED: u8 = 0xF;
+
+// Team Player always reports all 6 buttons, so every connected controller takes 3 nibbles
+const TEAM_PLAYER_NIBBLES_PER_CONTROLLER: u8 = 3;
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct TeamPlayer {
+    th_tr: u8,
+    counter: u8,
+}
+
+impl Default for TeamPlayer {
+    fn default() -> Self {
+        Self { th_tr: (1 << TH_BIT) | (1 << TR_BIT), counter: 0 }
+    }
+}
+
+impl TeamPlayer {
+    fn update_pins(&mut self, port: Port) {
+        let th_tr = port.pin_states() & ((1 << TH_BIT) | (1 << TR_BIT));
+        if th_tr == self.th_tr {
+            return;
+        }
+        self.th_tr = th_tr;
+
+        // TH high resets the sequence, and every TH/TR change while TH is low advances it
+        self.counter = if th_tr.bit(TH_BIT) { 0 } else { self.counter.saturating_add(1) };
+    }
+
+    fn read(self, joypads: [Option<GenesisJoypadState>; 4]) -> u8 {
+        let nibble = match self.counter {
+            // ID sequence: 3, F, 0, 0
+            0 => 0x3,
+            1 => 0xF,
+            2 | 3 => 0x0,
+            4..=7 => {
+                if joypads[(self.counter - 4) as usize].is_some() {
+                    TEAM_PLAYER_SIX_BUTTON
+                } else {
+                    TEAM_PLAYER_NOT_CONNECTED
+                }
+            }
+            _ => {
+                let data_idx = self.counter - 8;
+                let controller_idx = data_idx / TEAM_PLAYER_NIBBLES_PER_CONTROLLER;
+                joypads.into_iter().flatten().nth(controller_idx.into()).map_or(
+                    0xF,
+                    |joypad_state| {
+                        team_player_nibble(
+                            joypad_state,
+                            data_idx % TEAM_PLAYER_NIBBLES_PER_CONTROLLER,
+                        )
+                    },
+                )
+            }
+        };
+
+        // TL acknowledges each request by matching TR
+        let tl = u8::from(self.th_tr.bit(TR_BIT)) << TL_BIT;
+
+        self.th_tr | tl | nibble
+    }
+}
+
+fn team_player_nibble(joypad_state: GenesisJoypadState, nibble_idx: u8) -> u8 {
+    let buttons = match nibble_idx {
+        0 => [joypad_state.up, joypad_state.down, joypad_state.left, joypad_state.right],
+        1 => [joypad_state.b, joypad_state.c, joypad_state.a, joypad_state.start],
+        2 => [joypad_state.z, joypad_state.y, joypad_state.x, joypad_state.mode],
+        _ => panic!("Team Player nibble index should always be <= 2, was {nibble_idx}"),
+    };
+
+    buttons
+        .into_iter()
+        .enumerate()
+        .fold(0, |nibble, (bit, pressed)| nibble | (u8::from(!pressed) << bit))
+}
+
+// Number of reads after a TR change before the mouse acknowledges it on TL. Several games with
+// sloppy mouse routines depend on TL not updating immediately (e.g. Cannon Fodder)
+const MEGA_MOUSE_BUSY_READS: u8 = 2;
+
+const MEGA_MOUSE_LAST_NIBBLE: u8 = 9;
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct MegaMouse {
+    th_tr: u8,
+    counter: u8,
+    busy_reads: u8,
+    last_position: Option<(u16, u16)>,
+    delta_x: i16,
+    delta_y: i16,
+}
+
+impl Default for MegaMouse {
+    fn default() -> Self {
+        Self {
+            th_tr: (1 << TH_BIT) | (1 << TR_BIT),
+            counter: 0,
+            busy_reads: 0,
+            last_position: None,
+            delta_x: 0,
+            delta_y: 0,
+        }
+    }
+}
+
+impl MegaMouse {
+    fn update_pins(&mut self, port: Port, pointer_position: Option<(u16, u16)>) {
+        let th_tr = port.pin_states() & ((1 << TH_BIT) | (1 << TR_BIT));
+        let changed = th_tr ^ self.th_tr;
+        self.th_tr = th_tr;
+
+        // TH low starts a new transfer and TH high aborts it
+        if changed.bit(TH_BIT) {
+            if th_tr.bit(TH_BIT) {
+                self.counter = 0;
+            } else {
+                self.counter = 1;
+                self.latch_motion(pointer_position);
+            }
+        }
+
+        // Every TR change during a transfer requests the next nibble
+        if changed.bit(TR_BIT) {
+            if (1..MEGA_MOUSE_LAST_NIBBLE).contains(&self.counter) {
+                self.counter += 1;
+            }
+            self.busy_reads = MEGA_MOUSE_BUSY_READS;
+        }
+    }
+
+    fn latch_motion(&mut self, pointer_position: Option<(u16, u16)>) {
+        (self.delta_x, self.delta_y) = match (self.last_position, pointer_position) {
+            (Some((last_x, last_y)), Some((x, y))) => {
+                // Mouse Y axis is positive going up, the opposite of the frame buffer
+                (x as i16 - last_x as i16, last_y as i16 - y as i16)
+            }
+            _ => (0, 0),
+        };
+        self.last_position = pointer_position;
+    }
+
+    fn read(&mut self, buttons: MegaMouseState) -> u8 {
+        let x_overflow = !(-255..=255).contains(&self.delta_x);
+        let y_overflow = !(-255..=255).contains(&self.delta_y);
+        let x = self.delta_x.clamp(-255, 255) as u8;
+        let y = self.delta_y.clamp(-255, 255) as u8;
+
+        let nibble = match self.counter {
+            0 => 0x0,
+            // ID sequence: B, F, F
+            1 => 0xB,
+            2 | 3 => 0xF,
+            4 => {
+                (u8::from(y_overflow) << 3)
+                    | (u8::from(x_overflow) << 2)
+                    | (u8::from(self.delta_y < 0) << 1)
+                    | u8::from(self.delta_x < 0)
+            }
+            // Buttons are active high
+            5 => {
+                (u8::from(buttons.start) << 3)
+                    | (u8::from(buttons.middle) << 2)
+                    | (u8::from(buttons.right) << 1)
+                    | u8::from(buttons.left)
+            }
+            6 => x >> 4,
+            7 => x & 0xF,
+            8 => y >> 4,
+            _ => y & 0xF,
+        };
+
+        // TL acknowledges each request by matching TR, but only once the mouse is no longer busy
+        let tr = self.th_tr.bit(TR_BIT);
+        let tl = if self.busy_reads != 0 {
+            self.busy_reads -= 1;
+            !tr
+        } else {
+            tr
+        };
+
+        (u8::from(tl) << TL_BIT) | nibble
+    }
+}
+
+fn menacer_read(light_gun: LightGunState) -> u8 {
+    // Buttons are active high; TH is high whenever the beam is not on the target
+    (1 << TH_BIT)
+        | (u8::from(light_gun.start) << 3)
+        | (u8::from(light_gun.trigger) << 2)
+        | (u8::from(light_gun.b) << 1)
+        | u8::from(light_gun.a)
+}
+
+// The Justifier's select lines follow the last values written to TH and TR, even while TH is set
+// to input so that it can drive the HL line
+fn justifier_selected_gun(port: Port) -> Option<u8> {
+    (!port.last_data_write.bit(TH_BIT)).then_some(u8::from(port.last_data_write.bit(TR_BIT)))
+}
+
+fn justifier_read(port: Port, light_gun: LightGunState) -> u8 {
+    // TL and TR read as 1 and left/right as 0
+    const BASE: u8 = (1 << TR_BIT) | (1 << TL_BIT);
+
+    // TH high is used for gun detection, and TR selects which gun to read. Only the first (blue)
+    // gun is emulated
+    match justifier_selected_gun(port) {
+        None => return BASE,
+        Some(0) => {}
+        Some(_) => return BASE | 0x03,
+    }
+
+    // Buttons are active low
+    BASE | (u8::from(!light_gun.start) << 1) | u8::from(!light_gun.trigger)
+}
+
+// Value read from port 1 while the 4-Way Play's detection bit is set
+const EA_4WAY_ID: u8 = 0x7C;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PortDevice {
+    None,
+    Gamepad { player: usize, six_button: bool },
+    TeamPlayer { first_player: usize },
+    EaFourWayPlay,
+    MegaMouse,
+    Menacer,
+    Justifier,
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct InputState {
     inputs: GenesisInputs,
     p1_controller_type: GenesisControllerType,
     p2_controller_type: GenesisControllerType,
-    p1_pin_directions: PinDirections,
-    p2_pin_directions: PinDirections,
+    ports: [Port; 2],
+    gamepads: [Gamepad; MAX_PLAYERS],
+    team_players: [TeamPlayer; 2],
+    mice: [MegaMouse; 2],
+    ea_4way_select: u8,
 }
 
 // All 1s signals to games that nothing is connected to the port
@@ -157,8 +418,11 @@ impl InputState {
             inputs: GenesisInputs::default(),
             p1_controller_type,
             p2_controller_type,
-            p1_pin_directions: PinDirections::default(),
-            p2_pin_directions: PinDirections::default(),
+            ports: [Port::default(); 2],
+            gamepads: [Gamepad::default(); MAX_PLAYERS],
+            team_players: [TeamPlayer::default(); 2],
+            mice: [MegaMouse::default(); 2],
+            ea_4way_select: 0,
         }
     }
 
@@ -176,52 +440,470 @@ impl InputState {
         (self.p1_controller_type, self.p2_controller_type)
     }
 
-    #[must_use]
-    pub fn read_p1_data(&self) -> u8 {
-        if self.p1_controller_type == GenesisControllerType::None {
-            return DATA_NO_CONTROLLER;
+    fn ea_4way_connected(&self) -> bool {
+        self.p1_controller_type == GenesisControllerType::EaFourWayPlay
+            || self.p2_controller_type == GenesisControllerType::EaFourWayPlay
+    }
+
+    // Players are assigned in port order, with a Team Player taking up to 4 players. If the port 1
+    // device uses all 4 players, nothing is connected to port 2
+    fn port_device(&self, port: usize) -> PortDevice {
+        if self.ea_4way_connected() {
+            // The 4-Way Play occupies both ports
+            return if port == 0 { PortDevice::EaFourWayPlay } else { PortDevice::None };
+        }
+
+        let (controller_type, first_player) = match port {
+            0 => (self.p1_controller_type, 0),
+            _ => {
+                let first_player = if self.p1_controller_type == GenesisControllerType::TeamPlayer {
+                    MAX_PLAYERS
+                } else {
+                    1
+                };
+                (self.p2_controller_type, first_player)
+            }
+        };
+
+        if first_player >= MAX_PLAYERS {
+            return PortDevice::None;
         }
 
-        self.p1_pin_directions.to_data_byte(self.inputs.p1)
+        match controller_type {
+            GenesisControllerType::ThreeButton => {
+                PortDevice::Gamepad { player: first_player, six_button: false }
+            }
+            GenesisControllerType::SixButton => {
+                PortDevice::Gamepad { player: first_player, six_button: true }
+            }
+            GenesisControllerType::TeamPlayer => PortDevice::TeamPlayer { first_player },
+            GenesisControllerType::MegaMouse => PortDevice::MegaMouse,
+            GenesisControllerType::Menacer => PortDevice::Menacer,
+            GenesisControllerType::Justifier => PortDevice::Justifier,
+            GenesisControllerType::EaFourWayPlay | GenesisControllerType::None => PortDevice::None,
+        }
     }
 
-    #[must_use]
-    pub fn read_p2_data(&self) -> u8 {
-        if self.p2_controller_type == GenesisControllerType::None {
-            return DATA_NO_CONTROLLER;
+    fn joypad_state(&self, player: usize) -> GenesisJoypadState {
+        match player {
+            0 => self.inputs.p1,
+            1 => self.inputs.p2,
+            2 => self.inputs.p3,
+            3 => self.inputs.p4,
+            _ => GenesisJoypadState::default(),
         }
+    }
 
-        self.p2_pin_directions.to_data_byte(self.inputs.p2)
+    fn ea_4way_selected_player(&self) -> Option<usize> {
+        // Bit 2 set selects the 4-Way Play's ID instead of a controller
+        (!self.ea_4way_select.bit(2)).then_some((self.ea_4way_select & 3).into())
+    }
+
+    fn read_data(&mut self, port: usize) -> u8 {
+        let device_byte = match self.port_device(port) {
+            PortDevice::None => return DATA_NO_CONTROLLER,
+            PortDevice::Gamepad { player, .. } => {
+                self.gamepads[player].read(self.joypad_state(player))
+            }
+            PortDevice::TeamPlayer { first_player } => {
+                let joypads = std::array::from_fn(|i| {
+                    let player = first_player + i;
+                    (player < MAX_PLAYERS).then(|| self.joypad_state(player))
+                });
+                self.team_players[port].read(joypads)
+            }
+            PortDevice::EaFourWayPlay => match self.ea_4way_selected_player() {
+                Some(player) => self.gamepads[player].read(self.joypad_state(player)),
+                None => EA_4WAY_ID,
+            },
+            PortDevice::MegaMouse => self.mice[port].read(self.inputs.mouse),
+            PortDevice::Menacer => menacer_read(self.inputs.light_gun),
+            PortDevice::Justifier => justifier_read(self.ports[port], self.inputs.light_gun),
+        };
+
+        self.ports[port].to_data_byte(device_byte)
+    }
+
+    fn write_data(&mut self, port: usize, value: u8) {
+        self.ports[port].last_data_write = value;
+        self.update_device_pins(port, false);
+
+        // The 4-Way Play controller select lines are port 2's TH/TR/TL pins
+        if port == 1 && self.ea_4way_connected() && self.ports[1].last_ctrl_write & 0x70 == 0x70 {
+            self.ea_4way_select = (value >> 4) & 7;
+        }
+    }
+
+    fn write_ctrl(&mut self, port: usize, value: u8) {
+        self.ports[port].last_ctrl_write = value;
+        self.update_device_pins(port, true);
+    }
+
+    fn update_device_pins(&mut self, port: usize, ctrl_write: bool) {
+        let pins = self.ports[port];
+
+        let (player, six_button) = match self.port_device(port) {
+            PortDevice::None | PortDevice::Menacer | PortDevice::Justifier => return,
+            PortDevice::Gamepad { player, six_button } => (player, six_button),
+            PortDevice::TeamPlayer { .. } => {
+                self.team_players[port].update_pins(pins);
+                return;
+            }
+            PortDevice::MegaMouse => {
+                self.mice[port].update_pins(pins, self.inputs.pointer_position);
+                return;
+            }
+            PortDevice::EaFourWayPlay => {
+                let Some(player) = self.ea_4way_selected_player() else { return };
+
+                // EA 4-Way Play games predate the 6-button controller
+                (player, false)
+            }
+        };
+
+        if ctrl_write {
+            self.gamepads[player].handle_ctrl_write(pins, six_button);
+        } else {
+            self.gamepads[player].maybe_set_th(pins, six_button);
+        }
+    }
+
+    pub fn read_p1_data(&mut self) -> u8 {
+        self.read_data(0)
+    }
+
+    pub fn read_p2_data(&mut self) -> u8 {
+        self.read_data(1)
     }
 
     pub fn write_p1_data(&mut self, value: u8) {
-        self.p1_pin_directions.write_data(value, self.p1_controller_type);
+        self.write_data(0, value);
     }
 
     pub fn write_p2_data(&mut self, value: u8) {
-        self.p2_pin_directions.write_data(value, self.p2_controller_type);
+        self.write_data(1, value);
     }
 
     #[must_use]
     pub fn read_p1_ctrl(&self) -> u8 {
-        self.p1_pin_directions.to_ctrl_byte()
+        self.ports[0].last_ctrl_write
     }
 
     #[must_use]
     pub fn read_p2_ctrl(&self) -> u8 {
-        self.p2_pin_directions.to_ctrl_byte()
+        self.ports[1].last_ctrl_write
     }
 
     pub fn write_p1_ctrl(&mut self, value: u8) {
-        self.p1_pin_directions.write_ctrl(value, self.p1_controller_type);
+        self.write_ctrl(0, value);
     }
 
     pub fn write_p2_ctrl(&mut self, value: u8) {
-        self.p2_pin_directions.write_ctrl(value, self.p2_controller_type);
+        self.write_ctrl(1, value);
+    }
+
+    /// Returns the frame buffer position that a light gun is pointed at, but only if the light gun
+    /// is currently able to assert the HL line.
+    #[must_use]
+    pub fn light_gun_hl_position(&self) -> Option<(u16, u16)> {
+        if self.inputs.light_gun.force_offscreen {
+            return None;
+        }
+
+        let gun_port_enabled = (0..2).any(|port| {
+            let port_state = self.ports[port];
+            let hl_enabled = port_state.last_ctrl_write.bit(TH_INTERRUPT_BIT);
+            match self.port_device(port) {
+                PortDevice::Menacer => hl_enabled,
+                // The Justifier only asserts HL for the gun currently selected by TH and TR
+                PortDevice::Justifier => {
+                    hl_enabled && justifier_selected_gun(port_state) == Some(0)
+                }
+                _ => false,
+            }
+        });
+
+        if gun_port_enabled { self.inputs.pointer_position } else { None }
     }
 
     pub fn tick(&mut self, m68k_cycles: u32) {
-        self.p1_pin_directions.tick(m68k_cycles);
-        self.p2_pin_directions.tick(m68k_cycles);
+        for gamepad in &mut self.gamepads {
+            gamepad.tick(m68k_cycles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // TH and TR set to output
+    const CTRL_TH_TR_OUTPUT: u8 = (1 << TH_BIT) | (1 << TR_BIT);
+
+    fn input_state(
+        p1_controller_type: GenesisControllerType,
+        p2_controller_type: GenesisControllerType,
+        inputs: GenesisInputs,
+    ) -> InputState {
+        let mut state = InputState::new(p1_controller_type, p2_controller_type);
+        state.set_inputs(inputs);
+        state
+    }
+
+    // Set TH/TR and read the next mouse nibble once TL acknowledges the TR change
+    fn mouse_nibble(state: &mut InputState, th: bool, tr: bool) -> u8 {
+        state.write_p1_data((u8::from(th) << TH_BIT) | (u8::from(tr) << TR_BIT));
+
+        for _ in 0..=MEGA_MOUSE_BUSY_READS {
+            let value = state.read_p1_data();
+            if value.bit(TL_BIT) == tr {
+                return value & 0x0F;
+            }
+        }
+        panic!("Mega Mouse never acknowledged TR={tr}");
+    }
+
+    fn mouse_transfer(state: &mut InputState) -> Vec<u8> {
+        let mut nibbles = vec![mouse_nibble(state, false, true)];
+        for i in 0..MEGA_MOUSE_LAST_NIBBLE - 1 {
+            nibbles.push(mouse_nibble(state, false, i % 2 != 0));
+        }
+        mouse_nibble(state, true, true);
+        nibbles
+    }
+
+    // Read `count` Team Player nibbles from port 1, starting from the ID sequence
+    fn team_player_nibbles(state: &mut InputState, count: usize) -> Vec<u8> {
+        state.write_p1_data(CTRL_TH_TR_OUTPUT);
+        let mut nibbles = vec![state.read_p1_data() & 0x0F];
+
+        for i in 1..count {
+            let tr = i % 2 == 1;
+            state.write_p1_data(u8::from(tr) << TR_BIT);
+
+            let value = state.read_p1_data();
+            assert_eq!(value.bit(TL_BIT), tr, "TL should acknowledge TR on nibble {i}");
+            nibbles.push(value & 0x0F);
+        }
+
+        nibbles
+    }
+
+    #[test]
+    fn team_player_read_sequence() {
+        let mut state = input_state(
+            GenesisControllerType::TeamPlayer,
+            GenesisControllerType::SixButton,
+            GenesisInputs {
+                p1: GenesisJoypadState { up: true, a: true, ..GenesisJoypadState::default() },
+                p2: GenesisJoypadState { start: true, ..GenesisJoypadState::default() },
+                p3: GenesisJoypadState { z: true, ..GenesisJoypadState::default() },
+                ..GenesisInputs::default()
+            },
+        );
+        state.write_p1_ctrl(CTRL_TH_TR_OUTPUT);
+
+        #[rustfmt::skip]
+        let expected = vec![
+            // ID sequence, then 4 six-button controllers
+            0x3, 0xF, 0x0, 0x0, 0x1, 0x1, 0x1, 0x1,
+            // P1-P4: directions, B/C/A/Start, Z/Y/X/Mode
+            0xE, 0xB, 0xF,
+            0xF, 0x7, 0xF,
+            0xF, 0xF, 0xE,
+            0xF, 0xF, 0xF,
+            // Past the last controller
+            0xF,
+        ];
+        assert_eq!(team_player_nibbles(&mut state, expected.len()), expected);
+
+        // TH high restarts the sequence
+        assert_eq!(team_player_nibbles(&mut state, 2), vec![0x3, 0xF]);
+
+        // A Team Player in port 1 takes all 4 players
+        assert_eq!(state.read_p2_data(), DATA_NO_CONTROLLER);
+    }
+
+    #[test]
+    fn team_player_in_port_2() {
+        let mut state = input_state(
+            GenesisControllerType::ThreeButton,
+            GenesisControllerType::TeamPlayer,
+            GenesisInputs {
+                p2: GenesisJoypadState { right: true, ..GenesisJoypadState::default() },
+                ..GenesisInputs::default()
+            },
+        );
+        state.write_p2_ctrl(CTRL_TH_TR_OUTPUT);
+        state.write_p2_data(CTRL_TH_TR_OUTPUT);
+
+        let mut nibbles = vec![];
+        for i in 0..9 {
+            if i > 0 {
+                state.write_p2_data(u8::from(i % 2 == 1) << TR_BIT);
+            }
+            nibbles.push(state.read_p2_data() & 0x0F);
+        }
+
+        // Players 2-4 are connected, and the 4th slot is empty
+        assert_eq!(nibbles, vec![0x3, 0xF, 0x0, 0x0, 0x1, 0x1, 0x1, 0xF, 0x7]);
+    }
+
+    #[test]
+    fn ea_4way_play_select() {
+        let mut state = input_state(
+            GenesisControllerType::EaFourWayPlay,
+            GenesisControllerType::None,
+            GenesisInputs {
+                p3: GenesisJoypadState { b: true, start: true, ..GenesisJoypadState::default() },
+                ..GenesisInputs::default()
+            },
+        );
+        state.write_p1_ctrl(1 << TH_BIT);
+        state.write_p1_data(1 << TH_BIT);
+        state.write_p2_ctrl(0x70);
+
+        // Select bit 2 reads the 4-Way Play's ID through port 1
+        state.write_p2_data(0x40);
+        assert_eq!(state.read_p1_data(), EA_4WAY_ID);
+
+        // Select player 3; TH high reads C/B/directions and TH low reads Start/A
+        state.write_p2_data(0x20);
+        assert_eq!(state.read_p1_data(), 0x6F);
+        state.write_p1_data(0x00);
+        assert_eq!(state.read_p1_data(), 0x13);
+
+        // Port 2 writes only change the selection while TH/TR/TL are outputs
+        state.write_p2_ctrl(0x00);
+        state.write_p2_data(0x40);
+        assert_eq!(state.read_p1_data(), 0x13);
+
+        // Port 2 itself reads as disconnected
+        assert_eq!(state.read_p2_data(), DATA_NO_CONTROLLER);
+    }
+
+    #[test]
+    fn mega_mouse_read_sequence() {
+        let mut state = input_state(
+            GenesisControllerType::MegaMouse,
+            GenesisControllerType::None,
+            GenesisInputs { pointer_position: Some((10, 10)), ..GenesisInputs::default() },
+        );
+        state.write_p1_data(CTRL_TH_TR_OUTPUT);
+        state.write_p1_ctrl(CTRL_TH_TR_OUTPUT);
+
+        // TH high: idle
+        assert_eq!(state.read_p1_data() & 0x0F, 0x0);
+
+        // First transfer only latches the pointer position
+        assert_eq!(mouse_transfer(&mut state), vec![0xB, 0xF, 0xF, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0]);
+
+        // Moved right 30 and up 10, with left and start pressed
+        state.set_inputs(GenesisInputs {
+            mouse: MegaMouseState { left: true, start: true, ..MegaMouseState::default() },
+            pointer_position: Some((40, 0)),
+            ..GenesisInputs::default()
+        });
+        assert_eq!(mouse_transfer(&mut state), vec![0xB, 0xF, 0xF, 0x0, 0x9, 0x1, 0xE, 0x0, 0xA]);
+
+        // Moved left 300 (overflow) and down 5
+        state.set_inputs(GenesisInputs {
+            pointer_position: Some((340, 5)),
+            ..GenesisInputs::default()
+        });
+        mouse_transfer(&mut state);
+        state.set_inputs(GenesisInputs {
+            pointer_position: Some((40, 10)),
+            ..GenesisInputs::default()
+        });
+        let nibbles = mouse_transfer(&mut state);
+        assert_eq!(nibbles[3], 0b0111);
+        // -255 as 8-bit two's complement
+        assert_eq!(&nibbles[5..], &[0x0, 0x1, 0xF, 0xB]);
+    }
+
+    #[test]
+    fn mega_mouse_acknowledge_is_delayed() {
+        let mut state = input_state(
+            GenesisControllerType::MegaMouse,
+            GenesisControllerType::None,
+            GenesisInputs::default(),
+        );
+        state.write_p1_data(CTRL_TH_TR_OUTPUT);
+        state.write_p1_ctrl(CTRL_TH_TR_OUTPUT);
+
+        // TH low starts the transfer; TR stays high
+        state.write_p1_data(1 << TR_BIT);
+        assert_eq!(state.read_p1_data() & 0x1F, (1 << TL_BIT) | 0xB);
+
+        // TR low requests the next nibble, but TL only follows after the busy reads
+        state.write_p1_data(0);
+        for _ in 0..MEGA_MOUSE_BUSY_READS {
+            assert!(state.read_p1_data().bit(TL_BIT));
+        }
+        assert_eq!(state.read_p1_data() & 0x1F, 0xF);
+
+        // TH high aborts the transfer
+        state.write_p1_data(CTRL_TH_TR_OUTPUT);
+        assert_eq!(state.read_p1_data() & 0x0F, 0x0);
+    }
+
+    #[test]
+    fn menacer_read_and_hl() {
+        let mut state = input_state(
+            GenesisControllerType::SixButton,
+            GenesisControllerType::Menacer,
+            GenesisInputs {
+                light_gun: LightGunState { trigger: true, a: true, ..LightGunState::default() },
+                pointer_position: Some((100, 50)),
+                ..GenesisInputs::default()
+            },
+        );
+
+        // All pins input; buttons are active high and TH reads high while off target
+        state.write_p2_ctrl(0x00);
+        assert_eq!(state.read_p2_data(), 0x45);
+
+        // HL is only asserted with the TH interrupt enabled
+        assert_eq!(state.light_gun_hl_position(), None);
+        state.write_p2_ctrl(1 << TH_INTERRUPT_BIT);
+        assert_eq!(state.light_gun_hl_position(), Some((100, 50)));
+
+        state.set_inputs(GenesisInputs {
+            light_gun: LightGunState { force_offscreen: true, ..LightGunState::default() },
+            pointer_position: Some((100, 50)),
+            ..GenesisInputs::default()
+        });
+        assert_eq!(state.light_gun_hl_position(), None);
+        assert_eq!(state.read_p2_data(), 0x40);
+    }
+
+    #[test]
+    fn justifier_gun_select() {
+        let mut state = input_state(
+            GenesisControllerType::SixButton,
+            GenesisControllerType::Justifier,
+            GenesisInputs {
+                light_gun: LightGunState { trigger: true, ..LightGunState::default() },
+                pointer_position: Some((20, 30)),
+                ..GenesisInputs::default()
+            },
+        );
+        state.write_p2_ctrl((1 << TH_INTERRUPT_BIT) | CTRL_TH_TR_OUTPUT);
+
+        // TH low and TR low select the first gun; buttons are active low
+        state.write_p2_data(0x00);
+        assert_eq!(state.read_p2_data(), (1 << TL_BIT) | 0x02);
+        assert_eq!(state.light_gun_hl_position(), Some((20, 30)));
+
+        // TR high selects the second gun, which is not connected
+        state.write_p2_data(1 << TR_BIT);
+        assert_eq!(state.read_p2_data(), (1 << TR_BIT) | (1 << TL_BIT) | 0x03);
+        assert_eq!(state.light_gun_hl_position(), None);
+
+        // TH high deselects both guns
+        state.write_p2_data(1 << TH_BIT);
+        assert_eq!(state.read_p2_data(), (1 << TH_BIT) | (1 << TL_BIT));
+        assert_eq!(state.light_gun_hl_position(), None);
     }
 }
@@ -12,6 +12,7 @@ use crate::vdp::Vdp;
 use crate::ym2612::Ym2612;
 use bincode::{Decode, Encode};
 use genesis_config::GenesisRegion;
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::frontend::TimingMode;
 use jgenesis_common::num::{GetBit, U16Ext};
 use jgenesis_proc_macros::PartialClone;
@@ -89,6 +90,8 @@ pub struct Memory<Medium> {
     z80_bank_register: Z80BankRegister,
     signals: Signals,
     pub open_bus: u16,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl<Medium: PhysicalMedium> Memory<Medium> {
@@ -102,6 +105,7 @@ impl<Medium: PhysicalMedium> Memory<Medium> {
             z80_bank_register: Z80BankRegister::default(),
             signals: Signals::default(),
             open_bus: 0,
+            cheats: CheatSet::default(),
         }
     }
 
@@ -159,6 +163,30 @@ impl<Medium: PhysicalMedium> Memory<Medium> {
         self.medium().clone_cartridge()
     }
 
+    pub fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    pub fn take_cheats_from(&mut self, other: &mut Self) {
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    // Cheat RAM writes should be applied once per frame. Only main RAM is supported
+    pub fn apply_cheat_ram_writes(&mut self) {
+        for &RamWrite { address, value, .. } in self.cheats.ram_writes() {
+            if !(0xE00000..=0xFFFFFF).contains(&address) {
+                continue;
+            }
+
+            let word = &mut self.main_ram[((address & 0xFFFF) >> 1) as usize];
+            if !address.bit(0) {
+                word.set_msb(value);
+            } else {
+                word.set_lsb(value);
+            }
+        }
+    }
+
     pub fn clone_working_ram(&self) -> Box<[u16]> {
         self.main_ram.clone()
     }
@@ -190,8 +218,14 @@ impl Memory<Cartridge> {
         self.physical_medium.take_rom()
     }
 
+    #[must_use]
+    pub fn take_lock_on_rom(&mut self) -> Option<Vec<u8>> {
+        self.physical_medium.take_lock_on_rom()
+    }
+
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.physical_medium.take_rom_from(&mut other.physical_medium);
+        self.take_cheats_from(other);
     }
 
     #[must_use]
@@ -286,7 +320,7 @@ impl<'a, Medium: PhysicalMedium, const REFRESH_INTERVAL: u32>
         }
     }
 
-    fn read_io_register(&self, address: u32) -> u8 {
+    fn read_io_register(&mut self, address: u32) -> u8 {
         match address {
             // Version register
             0xA10000 | 0xA10001 => {
@@ -515,7 +549,11 @@ impl<Medium: PhysicalMedium, const REFRESH_INTERVAL: u32> m68000_emu::BusInterfa
         let address = address & ADDRESS_MASK;
         log::trace!("Main bus byte read, address={address:06X}");
         let byte = match address {
-            0x000000..=0x9FFFFF | 0xA12000..=0xA153FF => {
+            0x000000..=0x3FFFFF => {
+                let byte = self.memory.physical_medium.read_byte(address);
+                self.memory.cheats.patch_rom_u8(address, byte)
+            }
+            0x400000..=0x9FFFFF | 0xA12000..=0xA153FF => {
                 self.memory.physical_medium.read_byte(address)
             }
             0xA00000..=0xA0FFFF => {
@@ -551,7 +589,11 @@ impl<Medium: PhysicalMedium, const REFRESH_INTERVAL: u32> m68000_emu::BusInterfa
         log::trace!("Main bus word read, address={address:06X}");
 
         self.memory.open_bus = match address {
-            0x000000..=0x9FFFFF | 0xA12000..=0xA153FF => {
+            0x000000..=0x3FFFFF => {
+                let word = self.memory.physical_medium.read_word(address);
+                self.memory.cheats.patch_rom_u16_be(address, word)
+            }
+            0x400000..=0x9FFFFF | 0xA12000..=0xA153FF => {
                 self.memory.physical_medium.read_word(address)
             }
             0xA00000..=0xA0FFFF => {
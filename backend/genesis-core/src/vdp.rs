@@ -276,6 +276,8 @@ struct InternalState {
     // Whether the VDP is actively raising INT6
     v_interrupt_pending: bool,
     h_interrupt_pending: bool,
+    // Raised by the I/O HL line, e.g. when a light gun detects the beam
+    external_interrupt_pending: bool,
     // V/H interrupts must be delayed by 1 CPU instruction if they are enabled while an interrupt is
     // pending; Sesame Street Counting Cafe and Fatal Rewind depend on this
     v_interrupt_enabled_latch: bool,
@@ -311,6 +313,7 @@ impl InternalState {
         Self {
             v_interrupt_pending: false,
             h_interrupt_pending: false,
+            external_interrupt_pending: false,
             v_interrupt_enabled_latch: false,
             h_interrupt_enabled_latch: false,
             h_interrupt_counter: 0,
@@ -1787,11 +1790,13 @@ impl Vdp {
 
     #[must_use]
     pub fn m68k_interrupt_level(&self) -> u8 {
-        // TODO external interrupts at level 2
         if self.state.v_interrupt_pending && self.state.v_interrupt_enabled_latch {
             6
         } else if self.state.h_interrupt_pending && self.state.h_interrupt_enabled_latch {
             4
+        } else if self.state.external_interrupt_pending && self.registers.external_interrupt_enabled
+        {
+            2
         } else {
             0
         }
@@ -1810,6 +1815,59 @@ impl Vdp {
             self.state.v_interrupt_pending = false;
         } else if interrupt_level == 4 {
             self.state.h_interrupt_pending = false;
+        } else if interrupt_level == 2 {
+            self.state.external_interrupt_pending = false;
+        }
+    }
+
+    /// Update the H/V counter latch and external interrupt state for a light gun pointed at the
+    /// given position in the frame buffer. Should be called before [`Self::tick`] with the same
+    /// number of master clock cycles.
+    ///
+    /// The HL line is asserted when the beam passes the target pixel; this latches the H/V counter
+    /// if the latch is enabled (register #0 bit 1) and raises INT2 if external interrupts are
+    /// enabled (register #11 bit 3).
+    pub fn update_light_gun_latch(&mut self, frame_x: u16, frame_y: u16, master_clock_cycles: u64) {
+        let border_size = self.border_size();
+        let Some(x) = frame_x.checked_sub(border_size.left as u16) else { return };
+        let Some(mut y) = frame_y.checked_sub(border_size.top as u16) else { return };
+        if self.state.interlaced_frame {
+            y /= 2;
+        }
+
+        let h_display_size = self.state.frame_h_resolution;
+        if x >= h_display_size.active_display_pixels()
+            || y >= self.registers.vertical_display_size.active_scanlines()
+            || y != self.state.scanline
+        {
+            return;
+        }
+
+        let target_mclk = match h_display_size {
+            HorizontalDisplaySize::ThirtyTwoCell => 10 * u64::from(x),
+            HorizontalDisplaySize::FortyCell => 8 * u64::from(x),
+        };
+        let scanline_mclk = self.state.scanline_mclk_cycles;
+        if !(scanline_mclk < target_mclk && target_mclk <= scanline_mclk + master_clock_cycles) {
+            return;
+        }
+
+        if self.registers.hv_counter_stopped {
+            // Compute the counter directly rather than through hv_counter_internal(), which returns
+            // the existing latched value
+            let VCounter { counter: v_counter, .. } = self.v_counter(target_mclk);
+            let internal_h = pixel_to_internal_h(x, h_display_size);
+            let h_counter = (internal_h >> 1) as u8;
+            self.state.latched_hv_counter = Some(u16::from_be_bytes([v_counter, h_counter]));
+
+            log::trace!(
+                "Light gun latched HV counter at line {} pixel {x}: V={v_counter:02X} H={h_counter:02X}",
+                self.state.scanline
+            );
+        }
+
+        if self.registers.external_interrupt_enabled {
+            self.state.external_interrupt_pending = true;
         }
     }
 
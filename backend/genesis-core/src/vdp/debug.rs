@@ -167,6 +167,7 @@ impl VdpDebugState {
         callback(
             "Register #11",
             &[
+                ("External interrupt enabled", bool_str(self.registers.external_interrupt_enabled)),
                 ("Vertical scroll mode", &self.registers.vertical_scroll_mode.to_string()),
                 ("Horizontal scroll mode", &self.registers.horizontal_scroll_mode.to_string()),
             ],
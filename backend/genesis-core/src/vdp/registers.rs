@@ -384,7 +384,7 @@ pub struct Registers {
     // Register #10
     pub h_interrupt_interval: u16,
     // Register #11
-    // TODO external interrupts enabled
+    pub external_interrupt_enabled: bool,
     pub vertical_scroll_mode: VerticalScrollMode,
     pub horizontal_scroll_mode: HorizontalScrollMode,
     // Register #12
@@ -429,6 +429,7 @@ impl Registers {
             background_palette: 0,
             background_color_id: 0,
             h_interrupt_interval: 0,
+            external_interrupt_enabled: false,
             vertical_scroll_mode: VerticalScrollMode::default(),
             horizontal_scroll_mode: HorizontalScrollMode::default(),
             horizontal_display_size: HorizontalDisplaySize::default(),
@@ -544,7 +545,7 @@ impl Registers {
             }
             11 => {
                 // Register #11: Mode set register 3
-                // TODO external interrupt enable
+                self.external_interrupt_enabled = value.bit(3);
                 self.vertical_scroll_mode = if value.bit(2) {
                     VerticalScrollMode::TwoCell
                 } else {
@@ -558,6 +559,7 @@ impl Registers {
                     _ => unreachable!("value & 0x03 is always <= 0x03"),
                 };
 
+                log::trace!("  External interrupt enabled: {}", self.external_interrupt_enabled);
                 log::trace!("  Vertical scroll mode: {:?}", self.vertical_scroll_mode);
                 log::trace!("  Horizontal scroll mode: {:?}", self.horizontal_scroll_mode);
             }
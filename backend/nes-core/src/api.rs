@@ -1,6 +1,7 @@
 use crate::apu::ApuState;
 use crate::audio::AudioResampler;
 use crate::bus::cartridge::CartridgeFileError;
+use crate::bus::cartridge::Mapper;
 use crate::bus::{Bus, cartridge};
 use crate::cpu::CpuState;
 use crate::graphics::TimingModeGraphicsExt;
@@ -8,6 +9,7 @@ use crate::input::NesInputs;
 use crate::ppu::PpuState;
 use crate::{apu, audio, cpu, graphics, ppu};
 use bincode::{Decode, Encode};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, Color, EmulatorConfigTrait, EmulatorTrait, FrameSize, InputPoller,
     RenderFrameOptions, Renderer, SaveWriter, TickEffect, TickResult, TimingMode,
@@ -27,6 +29,9 @@ const PAL_MASTER_CLOCK_TICKS: u32 = 80;
 const PAL_CPU_DIVIDER: u32 = 16;
 const PAL_PPU_DIVIDER: u32 = 5;
 
+// Save file extension for Famicom Disk System disk modifications
+const FDS_DISK_DIFF_EXTENSION: &str = "fdsdiff";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, ConfigDisplay)]
 pub struct NesEmulatorConfig {
     /// Force timing mode to NTSC/PAL if set
@@ -91,22 +96,71 @@ pub struct NesEmulator {
     // Kept around to enable hard reset
     #[partial_clone(default)]
     raw_rom_bytes: Vec<u8>,
+    #[partial_clone(default)]
+    fds_bios_rom: Option<Vec<u8>>,
 }
 
 impl NesEmulator {
     /// Create a new emulator instance.
     ///
+    /// NSF music files (`.nsf` / `.nsfe`) are also accepted, in which case the emulator runs as a
+    /// music player.
+    ///
     /// # Errors
     ///
     /// This function will return an error if it cannot successfully parse NES ROM data out of the
-    /// given ROM bytes.
+    /// given ROM bytes, or if the ROM bytes are an FDS disk image (use [`Self::create_fds`]).
     pub fn create<S: SaveWriter>(
         rom_bytes: Vec<u8>,
         config: NesEmulatorConfig,
         save_writer: &mut S,
     ) -> Result<Self, NesInitializationError> {
-        let sav_bytes = save_writer.load_bytes("sav").ok();
-        let mapper = cartridge::from_ines_file(&rom_bytes, sav_bytes, config.forced_timing_mode)?;
+        if cartridge::is_fds_file(&rom_bytes) {
+            return Err(CartridgeFileError::FdsNoBios.into());
+        }
+
+        let mapper = if cartridge::is_nsf_file(&rom_bytes) {
+            cartridge::from_nsf_file(&rom_bytes, config.forced_timing_mode)?
+        } else {
+            let sav_bytes = save_writer.load_bytes("sav").ok();
+            cartridge::from_ines_file(&rom_bytes, sav_bytes, config.forced_timing_mode)?
+        };
+
+        Ok(Self::from_mapper(mapper, rom_bytes, None, &config))
+    }
+
+    /// Create a new emulator instance running the Famicom Disk System, using the given disk image
+    /// (.fds or .qd) and FDS BIOS ROM.
+    ///
+    /// Any disk modifications from a previous session will be loaded from the save writer.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the BIOS ROM is invalid or if it cannot parse disk
+    /// sides out of the given disk image.
+    pub fn create_fds<S: SaveWriter>(
+        disk_bytes: Vec<u8>,
+        bios_rom: Vec<u8>,
+        config: NesEmulatorConfig,
+        save_writer: &mut S,
+    ) -> Result<Self, NesInitializationError> {
+        let disk_diff: Option<Vec<_>> = save_writer.load_serialized(FDS_DISK_DIFF_EXTENSION).ok();
+        let mapper = cartridge::from_fds_file(
+            &disk_bytes,
+            &bios_rom,
+            disk_diff.as_deref(),
+            config.forced_timing_mode,
+        )?;
+
+        Ok(Self::from_mapper(mapper, disk_bytes, Some(bios_rom), &config))
+    }
+
+    fn from_mapper(
+        mapper: Mapper,
+        raw_rom_bytes: Vec<u8>,
+        fds_bios_rom: Option<Vec<u8>>,
+        config: &NesEmulatorConfig,
+    ) -> Self {
         let timing_mode = mapper.timing_mode();
 
         let mut bus = Bus::from_cartridge(mapper, config.overscan);
@@ -115,18 +169,19 @@ impl NesEmulator {
         let ppu_state = PpuState::new(timing_mode, config.ntsc_crop_vertical_overscan);
         let mut apu_state = ApuState::new(timing_mode);
 
-        init_apu(&mut apu_state, &mut bus, &config);
+        init_apu(&mut apu_state, &mut bus, config);
 
-        Ok(Self {
+        Self {
             bus,
             cpu_state,
             ppu_state,
             apu_state,
-            config,
+            config: *config,
             rgba_frame_buffer: new_rgba_frame_buffer(),
-            audio_resampler: AudioResampler::new(timing_mode, &config),
-            raw_rom_bytes: rom_bytes,
-        })
+            audio_resampler: AudioResampler::new(timing_mode, config),
+            raw_rom_bytes,
+            fds_bios_rom,
+        }
     }
 
     #[inline]
@@ -135,6 +190,43 @@ impl NesEmulator {
         self.bus.mapper().timing_mode()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the board has
+    /// no persistent memory.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        let mapper = self.bus.mapper();
+        mapper.has_persistent_ram().then(|| mapper.get_prg_ram())
+    }
+
+    /// Eject the current Famicom Disk System disk side and insert the next side, wrapping around
+    /// to the first side after the last side. Does nothing if not running the Famicom Disk System.
+    pub fn change_fds_disk_side(&mut self) {
+        self.bus.mapper_mut().change_fds_disk_side();
+    }
+
+    /// If playing an NSF file, switch to the given track (0-based index in playback order) and
+    /// start playing it. Does nothing if not playing an NSF file or if the track index is invalid.
+    pub fn nsf_select_track(&mut self, track_index: usize) {
+        if self.bus.mapper_mut().nsf_select_track(track_index) {
+            self.soft_reset();
+        }
+    }
+
+    /// If playing an NSF file, switch to the next track, wrapping around after the last track.
+    pub fn nsf_next_track(&mut self) {
+        if self.bus.mapper_mut().nsf_next_track() {
+            self.soft_reset();
+        }
+    }
+
+    /// If playing an NSF file, switch to the previous track, wrapping around before the first
+    /// track.
+    pub fn nsf_previous_track(&mut self) {
+        if self.bus.mapper_mut().nsf_previous_track() {
+            self.soft_reset();
+        }
+    }
+
     fn ntsc_tick(&mut self) {
         cpu::tick(&mut self.cpu_state, &mut self.bus.cpu(), &mut self.apu_state, &self.config);
         apu::tick(&mut self.apu_state, &mut self.bus.cpu(), &self.config);
@@ -194,6 +286,29 @@ impl NesEmulator {
         } else {
             self.bus.mapper().timing_mode()
         };
+
+        if let Some(nsf_info) = self.bus.mapper().nsf_player_info() {
+            // NSF playback never renders anything through the PPU; draw the track info screen
+            // instead, ignoring overscan settings
+            let frame_size = FrameSize {
+                width: ppu::SCREEN_WIDTH.into(),
+                height: display_mode.visible_screen_height().into(),
+            };
+            graphics::render_nsf_player(
+                &nsf_info,
+                &mut self.rgba_frame_buffer,
+                frame_size.width as usize,
+                frame_size.height as usize,
+            );
+
+            let pixel_aspect_ratio = self.config.aspect_ratio.to_pixel_aspect_ratio();
+            return renderer.render_frame(
+                &self.rgba_frame_buffer,
+                frame_size,
+                self.target_fps(),
+                RenderFrameOptions::pixel_aspect_ratio(pixel_aspect_ratio),
+            );
+        }
         graphics::ppu_frame_buffer_to_rgba(
             self.ppu_state.frame_buffer(),
             &mut self.rgba_frame_buffer,
@@ -269,6 +384,9 @@ impl EmulatorTrait for NesEmulator {
     type Button = NesButton;
     type Inputs = NesInputs;
     type Config = NesEmulatorConfig;
+
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::Nes;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -299,6 +417,16 @@ impl EmulatorTrait for NesEmulator {
         let inputs = input_poller.poll();
         self.bus.update_p1_joypad_state(inputs.p1, self.config.allow_opposing_joypad_inputs);
         self.bus.update_p2_joypad_state(inputs.p2, self.config.allow_opposing_joypad_inputs);
+        self.bus.update_four_player_state(
+            inputs.four_player_adapter,
+            inputs.p3,
+            inputs.p4,
+            self.config.allow_opposing_joypad_inputs,
+        );
+
+        if self.bus.mapper_mut().process_nsf_input(inputs.p1) {
+            self.soft_reset();
+        }
 
         let timing_mode = self.bus.mapper().timing_mode();
 
@@ -316,11 +444,19 @@ impl EmulatorTrait for NesEmulator {
 
             self.render_frame(renderer).map_err(NesError::Render)?;
 
+            self.bus.apply_cheat_ram_writes();
+
             if self.bus.mapper_mut().get_and_clear_ram_dirty_bit() {
                 let sram = self.bus.mapper().get_prg_ram();
                 save_writer.persist_bytes("sav", sram).map_err(NesError::SaveWrite)?;
             }
 
+            if let Some(disk_diff) = self.bus.mapper_mut().take_fds_disk_diff() {
+                save_writer
+                    .persist_serialized(FDS_DISK_DIFF_EXTENSION, &disk_diff)
+                    .map_err(NesError::SaveWrite)?;
+            }
+
             return Ok(TickEffect::FrameRendered);
         }
 
@@ -345,6 +481,11 @@ impl EmulatorTrait for NesEmulator {
     fn take_rom_from(&mut self, other: &mut Self) {
         self.bus.move_rom_from(&mut other.bus);
         self.raw_rom_bytes = mem::take(&mut other.raw_rom_bytes);
+        self.fds_bios_rom = other.fds_bios_rom.take();
+    }
+
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.bus.set_cheats(cheats);
     }
 
     fn soft_reset(&mut self) {
@@ -361,8 +502,11 @@ impl EmulatorTrait for NesEmulator {
     fn hard_reset<S: SaveWriter>(&mut self, save_writer: &mut S) {
         let rom_bytes = mem::take(&mut self.raw_rom_bytes);
 
-        *self = Self::create(rom_bytes, self.config, save_writer)
-            .expect("Creation during hard reset should never fail");
+        *self = match self.fds_bios_rom.take() {
+            Some(bios_rom) => Self::create_fds(rom_bytes, bios_rom, self.config, save_writer),
+            None => Self::create(rom_bytes, self.config, save_writer),
+        }
+        .expect("Creation during hard reset should never fail");
     }
 
     fn target_fps(&self) -> f64 {
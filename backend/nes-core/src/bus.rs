@@ -39,14 +39,17 @@ use crate::api::NesEmulatorConfig;
 use crate::apu::ApuState;
 use crate::bus::cartridge::Mapper;
 use crate::graphics::TimingModeGraphicsExt;
-use crate::input::{LatchedJoypadState, NesInputDevice, NesJoypadStateExt, ZapperState};
+use crate::input::{
+    LatchedSerialData, NesFourPlayerAdapter, NesInputDevice, NesJoypadStateExt, ZapperState,
+};
 use bincode::{Decode, Encode};
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::frontend::TimingMode;
 use jgenesis_common::num::GetBit;
 use jgenesis_proc_macros::PartialClone;
 use mos6502_emu::bus::BusInterface;
 use nes_config::{NesJoypadState, Overscan};
-use std::array;
+use std::{array, mem};
 
 pub const CPU_RAM_START: u16 = 0x0000;
 pub const CPU_RAM_END: u16 = 0x1FFF;
@@ -509,11 +512,41 @@ fn should_trigger_zapper_sensor(pixel: u8) -> bool {
         || (0x30..0x3E).contains(&pixel)
 }
 
+// Serial data for the data lines that controller port devices can use (D0, D1, D3, D4)
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct PortSerialData {
+    d0: LatchedSerialData,
+    d1: LatchedSerialData,
+    d3: LatchedSerialData,
+    d4: LatchedSerialData,
+}
+
+impl PortSerialData {
+    fn next_bits(self) -> u8 {
+        self.d0.next_bit()
+            | (self.d1.next_bit() << 1)
+            | (self.d3.next_bit() << 3)
+            | (self.d4.next_bit() << 4)
+    }
+
+    #[must_use]
+    fn shift(self) -> Self {
+        Self { d0: self.d0.shift(), d1: self.d1.shift(), d3: self.d3.shift(), d4: self.d4.shift() }
+    }
+}
+
+// Power Pad buttons in serial read order
+const POWER_PAD_D3_BUTTONS: [u8; 8] = [2, 1, 5, 9, 6, 10, 11, 7];
+const POWER_PAD_D4_BUTTONS: [u8; 4] = [4, 3, 12, 8];
+
+// Four player adapter signatures, returned after the player 3/4 inputs
+const JOY1_FOUR_SCORE_SIGNATURE: u8 = 0x10;
+const JOY2_FOUR_SCORE_SIGNATURE: u8 = 0x20;
+
 #[derive(Debug, Clone, Encode, Decode)]
 struct JoypadRegisterState {
-    joypad_state: NesJoypadState,
-    latched_bit: u8,
-    latched_joypad_state: LatchedJoypadState,
+    latched_bits: u8,
+    latched_data: PortSerialData,
     read_last_cycle: bool,
     read_this_cycle: bool,
 }
@@ -521,9 +554,11 @@ struct JoypadRegisterState {
 impl JoypadRegisterState {
     fn new() -> Self {
         Self {
-            joypad_state: NesJoypadState::default(),
-            latched_bit: 1,
-            latched_joypad_state: LatchedJoypadState::default(),
+            latched_bits: 1,
+            latched_data: PortSerialData {
+                d0: LatchedSerialData::eight_bit(0xFF),
+                ..PortSerialData::default()
+            },
             read_last_cycle: false,
             read_this_cycle: false,
         }
@@ -533,11 +568,11 @@ impl JoypadRegisterState {
         self.read_this_cycle = true;
 
         if !self.read_last_cycle {
-            self.latched_bit = self.latched_joypad_state.next_bit();
-            self.latched_joypad_state = self.latched_joypad_state.shift();
+            self.latched_bits = self.latched_data.next_bits();
+            self.latched_data = self.latched_data.shift();
         }
 
-        self.latched_bit
+        self.latched_bits
     }
 
     fn tick_cpu(&mut self) {
@@ -545,8 +580,8 @@ impl JoypadRegisterState {
         self.read_this_cycle = false;
     }
 
-    fn strobe(&mut self) {
-        self.latched_joypad_state = self.joypad_state.latch();
+    fn strobe(&mut self, data: PortSerialData) {
+        self.latched_data = data;
     }
 }
 
@@ -558,6 +593,11 @@ pub struct IoRegisters {
     snd_chn_read: bool,
     p1: JoypadRegisterState,
     p2: JoypadRegisterState,
+    p1_joypad_state: NesJoypadState,
+    p2_device: NesInputDevice,
+    p3_joypad_state: NesJoypadState,
+    p4_joypad_state: NesJoypadState,
+    four_player_adapter: NesFourPlayerAdapter,
     joypad_strobe: bool,
     zapper_state: Option<ZapperBusState>,
     // Needed for zapper positioning
@@ -573,6 +613,11 @@ impl IoRegisters {
             snd_chn_read: false,
             p1: JoypadRegisterState::new(),
             p2: JoypadRegisterState::new(),
+            p1_joypad_state: NesJoypadState::default(),
+            p2_device: NesInputDevice::default(),
+            p3_joypad_state: NesJoypadState::default(),
+            p4_joypad_state: NesJoypadState::default(),
+            four_player_adapter: NesFourPlayerAdapter::default(),
             joypad_strobe: false,
             zapper_state: None,
             overscan,
@@ -596,9 +641,14 @@ impl IoRegisters {
                 self.data[register.to_relative_address()] | (cpu_open_bus & (1 << 5))
             }
             IoRegister::JOY1 => self.p1.handle_read() | joy_open_bus,
-            IoRegister::JOY2 => match &self.zapper_state {
-                Some(zapper_state) => zapper_state.read() | joy_open_bus,
-                None => self.p2.handle_read() | joy_open_bus,
+            IoRegister::JOY2 => match (&self.zapper_state, self.p2_device) {
+                (Some(zapper_state), _) => zapper_state.read() | joy_open_bus,
+                (None, NesInputDevice::ArkanoidVaus(vaus_state)) => {
+                    // Vaus button is not serial; D4 reads 1 while the button is held
+                    let button_bit = u8::from(vaus_state.button) << 4;
+                    self.p2.handle_read() | button_bit | joy_open_bus
+                }
+                (None, _) => self.p2.handle_read() | joy_open_bus,
             },
             _ => {
                 // Other I/O registers are write-only
@@ -651,13 +701,82 @@ impl IoRegisters {
         snd_chn_read
     }
 
+    fn p1_serial_data(&self) -> PortSerialData {
+        let p1 = self.p1_joypad_state.serial_bits();
+        let p3 = self.p3_joypad_state.serial_bits();
+
+        match self.four_player_adapter {
+            NesFourPlayerAdapter::None => {
+                PortSerialData { d0: LatchedSerialData::eight_bit(p1), ..PortSerialData::default() }
+            }
+            NesFourPlayerAdapter::FourScore => PortSerialData {
+                d0: LatchedSerialData::four_player(p1, p3, JOY1_FOUR_SCORE_SIGNATURE),
+                ..PortSerialData::default()
+            },
+            NesFourPlayerAdapter::Hori => PortSerialData {
+                d0: LatchedSerialData::eight_bit(p1),
+                d1: LatchedSerialData::four_player(p1, p3, JOY2_FOUR_SCORE_SIGNATURE),
+                ..PortSerialData::default()
+            },
+        }
+    }
+
+    fn p2_serial_data(&self) -> PortSerialData {
+        let p2 = match self.p2_device {
+            NesInputDevice::Controller(joypad_state) => joypad_state.serial_bits(),
+            _ => 0,
+        };
+        let p4 = self.p4_joypad_state.serial_bits();
+
+        let mut data = match self.four_player_adapter {
+            NesFourPlayerAdapter::None => {
+                PortSerialData { d0: LatchedSerialData::eight_bit(p2), ..PortSerialData::default() }
+            }
+            NesFourPlayerAdapter::FourScore => PortSerialData {
+                d0: LatchedSerialData::four_player(p2, p4, JOY2_FOUR_SCORE_SIGNATURE),
+                ..PortSerialData::default()
+            },
+            NesFourPlayerAdapter::Hori => PortSerialData {
+                d0: LatchedSerialData::eight_bit(p2),
+                d1: LatchedSerialData::four_player(p2, p4, JOY1_FOUR_SCORE_SIGNATURE),
+                ..PortSerialData::default()
+            },
+        };
+
+        match self.p2_device {
+            NesInputDevice::Controller(_) => {}
+            NesInputDevice::Zapper(_) => {
+                data.d0 = LatchedSerialData::NONE;
+            }
+            NesInputDevice::ArkanoidVaus(vaus_state) => {
+                // Potentiometer value is inverted and returned MSB first
+                let potentiometer = !vaus_state.potentiometer();
+                data.d0 = LatchedSerialData::NONE;
+                data.d3 = LatchedSerialData::eight_bit(potentiometer.reverse_bits());
+            }
+            NesInputDevice::PowerPad(power_pad_state) => {
+                let serialize = |buttons: &[u8]| {
+                    buttons.iter().enumerate().fold(0, |bits, (i, &button)| {
+                        bits | (u32::from(power_pad_state.pressed(button)) << i)
+                    })
+                };
+
+                data.d0 = LatchedSerialData::NONE;
+                data.d3 = LatchedSerialData::with_len(serialize(&POWER_PAD_D3_BUTTONS), 8);
+                data.d4 = LatchedSerialData::with_len(serialize(&POWER_PAD_D4_BUTTONS), 4);
+            }
+        }
+
+        data
+    }
+
     fn tick_cpu(&mut self, apu_odd_cycle: bool) {
         self.p1.tick_cpu();
         self.p2.tick_cpu();
 
         if self.joypad_strobe && apu_odd_cycle {
-            self.p1.strobe();
-            self.p2.strobe();
+            self.p1.strobe(self.p1_serial_data());
+            self.p2.strobe(self.p2_serial_data());
         }
 
         if let Some(zapper_state) = &mut self.zapper_state {
@@ -779,6 +898,8 @@ pub struct Bus {
     ppu_bus_address: u16,
     interrupt_lines: InterruptLines,
     pending_write: Option<PendingCpuWrite>,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl Bus {
@@ -804,6 +925,7 @@ impl Bus {
             ppu_bus_address: 0,
             interrupt_lines: InterruptLines::new(),
             pending_write: None,
+            cheats: CheatSet::default(),
         }
     }
 
@@ -820,13 +942,33 @@ impl Bus {
         p1_joypad_state: NesJoypadState,
         allow_opposing_inputs: bool,
     ) {
-        self.io_registers.p1.joypad_state = if allow_opposing_inputs {
+        self.io_registers.p1_joypad_state = if allow_opposing_inputs {
             p1_joypad_state
         } else {
             p1_joypad_state.sanitize_opposing_directions()
         };
     }
 
+    pub fn update_four_player_state(
+        &mut self,
+        adapter: NesFourPlayerAdapter,
+        p3_joypad_state: NesJoypadState,
+        p4_joypad_state: NesJoypadState,
+        allow_opposing_inputs: bool,
+    ) {
+        let sanitize = |joypad_state: NesJoypadState| {
+            if allow_opposing_inputs {
+                joypad_state
+            } else {
+                joypad_state.sanitize_opposing_directions()
+            }
+        };
+
+        self.io_registers.four_player_adapter = adapter;
+        self.io_registers.p3_joypad_state = sanitize(p3_joypad_state);
+        self.io_registers.p4_joypad_state = sanitize(p4_joypad_state);
+    }
+
     pub fn update_p2_joypad_state(
         &mut self,
         p2_inputs: NesInputDevice,
@@ -834,11 +976,12 @@ impl Bus {
     ) {
         match p2_inputs {
             NesInputDevice::Controller(joypad_state) => {
-                self.io_registers.p2.joypad_state = if allow_opposing_inputs {
-                    joypad_state
-                } else {
-                    joypad_state.sanitize_opposing_directions()
-                };
+                self.io_registers.p2_device =
+                    NesInputDevice::Controller(if allow_opposing_inputs {
+                        joypad_state
+                    } else {
+                        joypad_state.sanitize_opposing_directions()
+                    });
                 self.io_registers.zapper_state = None;
             }
             NesInputDevice::Zapper(zapper_state) => {
@@ -850,7 +993,11 @@ impl Bus {
                         self.io_registers.zapper_state = Some(ZapperBusState::new(zapper_state));
                     }
                 }
-                self.io_registers.p2.joypad_state = NesJoypadState::default();
+                self.io_registers.p2_device = p2_inputs;
+            }
+            NesInputDevice::ArkanoidVaus(_) | NesInputDevice::PowerPad(_) => {
+                self.io_registers.p2_device = p2_inputs;
+                self.io_registers.zapper_state = None;
             }
         }
     }
@@ -889,6 +1036,24 @@ impl Bus {
 
     pub(crate) fn move_rom_from(&mut self, other: &mut Self) {
         self.mapper.move_rom_from(&mut other.mapper);
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    pub(crate) fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    // Cheat RAM writes should be applied once per frame. Writes go directly to the backing memory
+    // rather than through the CPU bus so that they cannot trigger register side effects
+    pub(crate) fn apply_cheat_ram_writes(&mut self) {
+        for &RamWrite { address, value, .. } in self.cheats.ram_writes() {
+            let address = address as u16;
+            match address {
+                0x0000..=0x1FFF => self.cpu_internal_ram[(address & 0x07FF) as usize] = value,
+                0x6000..=0xFFFF => self.mapper.apply_cheat_prg_ram_write(address, value),
+                _ => {}
+            }
+        }
     }
 
     pub(crate) fn reload_config(&mut self, config: &NesEmulatorConfig) {
@@ -919,7 +1084,12 @@ impl BusInterface for CpuBus<'_> {
                 .unwrap_or(self.0.cpu_open_bus),
             _address @ CPU_IO_TEST_MODE_START..=CPU_IO_TEST_MODE_END => self.0.cpu_open_bus,
             address @ CPU_CARTRIDGE_START..=CPU_CARTRIDGE_END => {
-                self.0.mapper.read_cpu_address(address, self.0.cpu_open_bus)
+                let value = self.0.mapper.read_cpu_address(address, self.0.cpu_open_bus);
+                if address >= 0x8000 {
+                    self.0.cheats.patch_rom_u8(address.into(), value)
+                } else {
+                    value
+                }
             }
         };
 
@@ -1331,6 +1501,7 @@ impl PpuBus<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::{ArkanoidVausState, PowerPadState};
 
     #[test]
     fn randomized_ram_on_startup() {
@@ -1340,4 +1511,144 @@ mod tests {
 
         assert_ne!(bus1.cpu_internal_ram, bus2.cpu_internal_ram);
     }
+
+    fn new_bus() -> Bus {
+        Bus::from_cartridge(cartridge::new_mmc1(vec![0; 32768]), Overscan::default())
+    }
+
+    fn strobe_and_read(bus: &mut Bus, register: IoRegister, count: usize) -> Vec<u8> {
+        let io = &mut bus.io_registers;
+        for strobe in [1, 0] {
+            io.write_register(IoRegister::JOY1, strobe);
+            io.take_dirty_register();
+            io.tick_cpu(true);
+        }
+
+        (0..count)
+            .map(|_| {
+                let value = io.read_register(register, 0x00);
+                // Reads on consecutive cycles only shift once
+                io.tick_cpu(false);
+                io.tick_cpu(false);
+                value
+            })
+            .collect()
+    }
+
+    fn bit_line(values: &[u8], bit: u8) -> Vec<u8> {
+        values.iter().map(|&value| (value >> bit) & 1).collect()
+    }
+
+    fn a_pressed() -> NesJoypadState {
+        NesJoypadState { a: true, ..NesJoypadState::default() }
+    }
+
+    fn start_pressed() -> NesJoypadState {
+        NesJoypadState { start: true, ..NesJoypadState::default() }
+    }
+
+    #[test]
+    fn four_score_signature() {
+        let mut bus = new_bus();
+        bus.update_p1_joypad_state(a_pressed(), false);
+        bus.update_p2_joypad_state(NesInputDevice::Controller(start_pressed()), false);
+        bus.update_four_player_state(
+            NesFourPlayerAdapter::FourScore,
+            start_pressed(),
+            a_pressed(),
+            false,
+        );
+
+        let joy1 = bit_line(&strobe_and_read(&mut bus, IoRegister::JOY1, 26), 0);
+        #[rustfmt::skip]
+        assert_eq!(joy1, [
+            1, 0, 0, 0, 0, 0, 0, 0, // P1
+            0, 0, 0, 1, 0, 0, 0, 0, // P3
+            0, 0, 0, 0, 1, 0, 0, 0, // Signature
+            1, 1,
+        ]);
+
+        let joy2 = bit_line(&strobe_and_read(&mut bus, IoRegister::JOY2, 26), 0);
+        #[rustfmt::skip]
+        assert_eq!(joy2, [
+            0, 0, 0, 1, 0, 0, 0, 0, // P2
+            1, 0, 0, 0, 0, 0, 0, 0, // P4
+            0, 0, 0, 0, 0, 1, 0, 0, // Signature
+            1, 1,
+        ]);
+    }
+
+    #[test]
+    fn hori_adapter() {
+        let mut bus = new_bus();
+        bus.update_p1_joypad_state(a_pressed(), false);
+        bus.update_p2_joypad_state(NesInputDevice::Controller(start_pressed()), false);
+        bus.update_four_player_state(
+            NesFourPlayerAdapter::Hori,
+            start_pressed(),
+            a_pressed(),
+            false,
+        );
+
+        let joy1 = strobe_and_read(&mut bus, IoRegister::JOY1, 26);
+        // Built-in controller on D0 stops after 8 bits
+        assert_eq!(bit_line(&joy1, 0), [[1, 0, 0, 0, 0, 0, 0, 0].as_slice(), &[1; 18]].concat());
+        #[rustfmt::skip]
+        assert_eq!(bit_line(&joy1, 1), [
+            1, 0, 0, 0, 0, 0, 0, 0, // P1
+            0, 0, 0, 1, 0, 0, 0, 0, // P3
+            0, 0, 0, 0, 0, 1, 0, 0, // Signature (swapped relative to Four Score)
+            1, 1,
+        ]);
+
+        let joy2 = strobe_and_read(&mut bus, IoRegister::JOY2, 26);
+        assert_eq!(bit_line(&joy2, 0), [[0, 0, 0, 1, 0, 0, 0, 0].as_slice(), &[1; 18]].concat());
+        #[rustfmt::skip]
+        assert_eq!(bit_line(&joy2, 1), [
+            0, 0, 0, 1, 0, 0, 0, 0, // P2
+            1, 0, 0, 0, 0, 0, 0, 0, // P4
+            0, 0, 0, 0, 1, 0, 0, 0, // Signature
+            1, 1,
+        ]);
+    }
+
+    #[test]
+    fn arkanoid_vaus_serial_read() {
+        let mut bus = new_bus();
+        bus.update_p2_joypad_state(
+            NesInputDevice::ArkanoidVaus(ArkanoidVausState { button: false, x: 0 }),
+            false,
+        );
+
+        // Potentiometer value 0x54 is inverted to 0xAB and returned MSB first on D3
+        let joy2 = strobe_and_read(&mut bus, IoRegister::JOY2, 10);
+        assert_eq!(bit_line(&joy2, 3), [1, 0, 1, 0, 1, 0, 1, 1, 1, 1]);
+        assert_eq!(bit_line(&joy2, 0), [0; 10]);
+        assert_eq!(bit_line(&joy2, 4), [0; 10]);
+
+        bus.update_p2_joypad_state(
+            NesInputDevice::ArkanoidVaus(ArkanoidVausState { button: true, x: 255 }),
+            false,
+        );
+
+        // 0xF4 inverted is 0x0B
+        let joy2 = strobe_and_read(&mut bus, IoRegister::JOY2, 10);
+        assert_eq!(bit_line(&joy2, 3), [0, 0, 0, 0, 1, 0, 1, 1, 1, 1]);
+        assert_eq!(bit_line(&joy2, 4), [1; 10]);
+    }
+
+    #[test]
+    fn power_pad_bit_order() {
+        let mut bus = new_bus();
+
+        // Buttons 2, 3, 7, 9, and 12 pressed
+        let buttons = [2, 3, 7, 9, 12].into_iter().fold(0, |buttons, n| buttons | (1 << (n - 1)));
+        bus.update_p2_joypad_state(NesInputDevice::PowerPad(PowerPadState { buttons }), false);
+
+        // D3: 2, 1, 5, 9, 6, 10, 11, 7; D4: 4, 3, 12, 8
+        let joy2 = strobe_and_read(&mut bus, IoRegister::JOY2, 10);
+        assert_eq!(bit_line(&joy2, 3), [1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+        assert_eq!(bit_line(&joy2, 4), [0, 1, 1, 0, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(bit_line(&joy2, 0), [0; 10]);
+    }
 }
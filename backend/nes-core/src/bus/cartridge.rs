@@ -7,12 +7,14 @@ use bincode::{BorrowDecode, Decode, Encode};
 use jgenesis_common::frontend::{PartialClone, TimingMode};
 use jgenesis_common::num::GetBit;
 use jgenesis_proc_macros::MatchEachVariantMacro;
+use nes_config::NesJoypadState;
 use std::fmt::{Display, Formatter};
 use std::{io, mem};
 use thiserror::Error;
 
 use crate::bus::cartridge::mappers::action52::Action52;
 use crate::bus::cartridge::mappers::bandai::BandaiFcg;
+use crate::bus::cartridge::mappers::fds::{DiskDiffRun, DiskDrive, Fds};
 use crate::bus::cartridge::mappers::konami::{Vrc4, Vrc6, Vrc7};
 use crate::bus::cartridge::mappers::mmc1::Mmc1;
 use crate::bus::cartridge::mappers::mmc2::Mmc2;
@@ -21,11 +23,16 @@ use crate::bus::cartridge::mappers::mmc5::Mmc5;
 use crate::bus::cartridge::mappers::namco163::Namco163;
 use crate::bus::cartridge::mappers::namco175::Namco175;
 use crate::bus::cartridge::mappers::nrom::{Axrom, Bnrom, Cnrom, Gxrom, Nrom, Uxrom};
+use crate::bus::cartridge::mappers::nsf::{Nsf, NsfFile, NsfRegion};
 use crate::bus::cartridge::mappers::sunsoft::Sunsoft;
 use crate::bus::cartridge::mappers::unrom512::Unrom512;
-use crate::bus::cartridge::mappers::{ChrType, NametableMirroring, PpuMapResult, unrom512};
+use crate::bus::cartridge::mappers::{
+    ChrType, NametableMirroring, PpuMapResult, fds, nsf, unrom512,
+};
+pub(crate) use mappers::fds::is_fds_file;
 #[cfg(test)]
 pub(crate) use mappers::new_mmc1;
+pub(crate) use mappers::nsf::{NsfPlayerInfo, is_nsf_file};
 
 #[derive(Debug, Clone, PartialClone)]
 pub struct Cartridge {
@@ -177,6 +184,7 @@ pub(crate) enum Mapper {
     BandaiFcg(#[partial_clone(partial)] MapperImpl<BandaiFcg>),
     Bnrom(#[partial_clone(partial)] MapperImpl<Bnrom>),
     Cnrom(#[partial_clone(partial)] MapperImpl<Cnrom>),
+    Fds(#[partial_clone(partial)] MapperImpl<Fds>),
     Gxrom(#[partial_clone(partial)] MapperImpl<Gxrom>),
     Mmc1(#[partial_clone(partial)] MapperImpl<Mmc1>),
     Mmc2(#[partial_clone(partial)] MapperImpl<Mmc2>),
@@ -185,6 +193,7 @@ pub(crate) enum Mapper {
     Namco163(#[partial_clone(partial)] MapperImpl<Namco163>),
     Namco175(#[partial_clone(partial)] MapperImpl<Namco175>),
     Nrom(#[partial_clone(partial)] MapperImpl<Nrom>),
+    Nsf(#[partial_clone(partial)] MapperImpl<Nsf>),
     Sunsoft(#[partial_clone(partial)] MapperImpl<Sunsoft>),
     Unrom512(#[partial_clone(partial)] MapperImpl<Unrom512>),
     Uxrom(#[partial_clone(partial)] MapperImpl<Uxrom>),
@@ -202,6 +211,7 @@ impl Mapper {
             Self::BandaiFcg(bandai_fcg) => bandai_fcg.name(),
             Self::Bnrom(..) => "BNROM / NINA-001",
             Self::Cnrom(..) => "CNROM",
+            Self::Fds(..) => "Famicom Disk System",
             Self::Gxrom(gxrom) => gxrom.name(),
             Self::Mmc1(..) => "MMC1",
             Self::Mmc2(mmc2) => mmc2.name(),
@@ -210,6 +220,7 @@ impl Mapper {
             Self::Namco163(..) => "Namco 163",
             Self::Namco175(..) => "Namco 175",
             Self::Nrom(..) => "NROM",
+            Self::Nsf(..) => "NSF",
             Self::Sunsoft(..) => "Sunsoft",
             Self::Unrom512(..) => "UNROM 512",
             Self::Uxrom(uxrom) => uxrom.name(),
@@ -253,6 +264,9 @@ impl Mapper {
             Self::BandaiFcg(bandai_fcg) => {
                 bandai_fcg.tick_cpu();
             }
+            Self::Fds(fds) => {
+                fds.tick_cpu();
+            }
             Self::Mmc1(mmc1) => {
                 mmc1.tick_cpu();
             }
@@ -262,6 +276,9 @@ impl Mapper {
             Self::Namco163(namco163) => {
                 namco163.tick_cpu();
             }
+            Self::Nsf(nsf) => {
+                nsf.tick_cpu();
+            }
             Self::Sunsoft(sunsoft) => {
                 sunsoft.tick_cpu();
             }
@@ -282,9 +299,11 @@ impl Mapper {
     pub(crate) fn interrupt_flag(&self) -> bool {
         match self {
             Self::BandaiFcg(bandai_fcg) => bandai_fcg.interrupt_flag(),
+            Self::Fds(fds) => fds.interrupt_flag(),
             Self::Mmc3(mmc3) => mmc3.interrupt_flag(),
             Self::Mmc5(mmc5) => mmc5.interrupt_flag(),
             Self::Namco163(namco163) => namco163.interrupt_flag(),
+            Self::Nsf(nsf) => nsf.interrupt_flag(),
             Self::Sunsoft(sunsoft) => sunsoft.interrupt_flag(),
             Self::Vrc4(vrc4) => vrc4.interrupt_flag(),
             Self::Vrc6(vrc6) => vrc6.interrupt_flag(),
@@ -349,6 +368,16 @@ impl Mapper {
         })
     }
 
+    /// Return whether the board's writable memory should be persisted, i.e. it is battery-backed
+    /// PRG RAM, EEPROM, or flashable PRG ROM.
+    pub(crate) fn has_persistent_ram(&self) -> bool {
+        match self {
+            Mapper::BandaiFcg(mapper) if mapper.eeprom().is_some() => true,
+            Mapper::Unrom512(mapper) if mapper.is_flashable() => true,
+            _ => match_each_variant!(self, mapper => mapper.cartridge.has_ram_battery),
+        }
+    }
+
     /// Return the board's writable memory as a slice. This will be an empty slice if the board
     /// has no PRG RAM or EEPROM.
     pub(crate) fn get_prg_ram(&self) -> &[u8] {
@@ -375,6 +404,23 @@ impl Mapper {
         match_each_variant!(self, mapper => &mapper.cartridge.prg_ram)
     }
 
+    /// Apply a cheat write to PRG RAM at a CPU address in $6000-$7FFF ($6000-$DFFF for FDS),
+    /// bypassing the mapper so that PRG RAM write protection and mapper registers are not affected.
+    /// Boards with banked PRG RAM are addressed as if the first 8KB was mapped.
+    ///
+    /// The dirty bit is only set if the write changes the contents of PRG RAM.
+    pub(crate) fn apply_cheat_prg_ram_write(&mut self, address: u16, value: u8) {
+        let prg_ram_addr = match (&*self, address) {
+            (Self::Fds(_), 0x6000..=0xDFFF) | (_, 0x6000..=0x7FFF) => u32::from(address - 0x6000),
+            _ => return,
+        };
+        match_each_variant!(self, mapper => {
+            if mapper.cartridge.get_prg_ram(prg_ram_addr) != value {
+                mapper.cartridge.set_prg_ram(prg_ram_addr, value);
+            }
+        });
+    }
+
     /// Retrieve the timing mode of the cartridge (NTSC/PAL).
     pub(crate) fn timing_mode(&self) -> TimingMode {
         match_each_variant!(self, mapper => mapper.cartridge.timing_mode)
@@ -387,8 +433,10 @@ impl Mapper {
     /// return the mixed APU sample as-is.
     pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
         match self {
+            Self::Fds(fds) => fds.sample_audio(mixed_apu_sample),
             Self::Mmc5(mmc5) => mmc5.sample_audio(mixed_apu_sample),
             Self::Namco163(namco163) => namco163.sample_audio(mixed_apu_sample),
+            Self::Nsf(nsf) => nsf.sample_audio(mixed_apu_sample),
             Self::Sunsoft(sunsoft) => sunsoft.sample_audio(mixed_apu_sample),
             Self::Vrc6(vrc6) => vrc6.sample_audio(mixed_apu_sample),
             Self::Vrc7(vrc7) => vrc7.sample_audio(mixed_apu_sample),
@@ -398,13 +446,85 @@ impl Mapper {
 
     /// Move cartridge ROM fields from another `Mapper` instance. Used when loading save states.
     pub(crate) fn move_rom_from(&mut self, other: &mut Self) {
+        match (&mut *self, &mut *other) {
+            (Self::Fds(fds), Self::Fds(other_fds)) => fds.move_disk_images_from(other_fds),
+            (Self::Nsf(nsf), Self::Nsf(other_nsf)) => nsf.move_metadata_from(other_nsf),
+            _ => {}
+        }
+
         let other_cartridge = match_each_variant!(other, mapper => &mut mapper.cartridge);
         match_each_variant!(self, mapper => mapper.cartridge.move_rom_from(other_cartridge));
     }
 
+    /// If this is the Famicom Disk System and disk contents have been modified since the last
+    /// call, return a diff of the modified disk contents against the original disk image.
+    pub(crate) fn take_fds_disk_diff(&mut self) -> Option<Vec<DiskDiffRun>> {
+        match self {
+            Self::Fds(fds) => fds.take_disk_diff_if_dirty(),
+            _ => None,
+        }
+    }
+
+    /// Eject the current FDS disk side and insert the next side. Does nothing if this is not the
+    /// Famicom Disk System.
+    pub(crate) fn change_fds_disk_side(&mut self) {
+        if let Self::Fds(fds) = self {
+            fds.change_disk_side();
+        }
+    }
+
+    /// If this is an NSF file, process P1 joypad inputs for track selection. Returns true if the
+    /// emulator should be reset in order to (re)start the selected track.
+    pub(crate) fn process_nsf_input(&mut self, joypad_state: NesJoypadState) -> bool {
+        match self {
+            Self::Nsf(nsf) => nsf.process_joypad_input(joypad_state),
+            _ => false,
+        }
+    }
+
+    /// Select an NSF track by its index in playback order. Returns true if the track is valid and
+    /// the emulator should be reset to start it.
+    pub(crate) fn nsf_select_track(&mut self, track_index: usize) -> bool {
+        match self {
+            Self::Nsf(nsf) => nsf.select_track(track_index),
+            _ => false,
+        }
+    }
+
+    /// Advance to the next NSF track. Returns true if this is an NSF file.
+    pub(crate) fn nsf_next_track(&mut self) -> bool {
+        match self {
+            Self::Nsf(nsf) => {
+                nsf.next_track();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Go back to the previous NSF track. Returns true if this is an NSF file.
+    pub(crate) fn nsf_previous_track(&mut self) -> bool {
+        match self {
+            Self::Nsf(nsf) => {
+                nsf.previous_track();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn nsf_player_info(&self) -> Option<NsfPlayerInfo<'_>> {
+        match self {
+            Self::Nsf(nsf) => Some(nsf.player_info()),
+            _ => None,
+        }
+    }
+
     pub(crate) fn reset(&mut self) {
-        if let Self::Action52(action52) = self {
-            action52.reset();
+        match self {
+            Self::Action52(action52) => action52.reset(),
+            Self::Nsf(nsf) => nsf.reset(),
+            _ => {}
         }
     }
 }
@@ -428,6 +548,12 @@ pub enum CartridgeFileError {
     InvalidRomSize { file_size: u32, prg_rom_size: u32, chr_rom_size: u32 },
     #[error("unsupported timing mode byte: {byte}")]
     UnsupportedTimingMode { byte: u8 },
+    #[error("Famicom Disk System BIOS is required to load FDS disk images")]
+    FdsNoBios,
+    #[error("invalid FDS BIOS size; expected {expected} bytes, was {actual} bytes")]
+    InvalidFdsBiosSize { expected: usize, actual: usize },
+    #[error("invalid NSF file: {reason}")]
+    InvalidNsf { reason: &'static str },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -810,3 +936,96 @@ pub(crate) fn from_ines_file(
 
     Ok(mapper)
 }
+
+/// Parse disk sides out of a Famicom Disk System image (.fds or .qd) and create a mapper that
+/// emulates the FDS RAM adapter using the given BIOS ROM.
+///
+/// # Errors
+///
+/// This function will return an error if the BIOS is the wrong size or the given bytes do not
+/// appear to be an FDS disk image.
+pub(crate) fn from_fds_file(
+    file_bytes: &[u8],
+    bios_rom: &[u8],
+    disk_diff: Option<&[DiskDiffRun]>,
+    forced_timing_mode: Option<TimingMode>,
+) -> Result<Mapper, CartridgeFileError> {
+    if bios_rom.len() != fds::BIOS_LEN {
+        return Err(CartridgeFileError::InvalidFdsBiosSize {
+            expected: fds::BIOS_LEN,
+            actual: bios_rom.len(),
+        });
+    }
+
+    let disk_sides = fds::parse_disk_image(file_bytes).ok_or(CartridgeFileError::Format)?;
+
+    // The Famicom Disk System was only released in Japan
+    let timing_mode = forced_timing_mode.unwrap_or(TimingMode::Ntsc);
+
+    log::info!("Timing mode: {timing_mode}");
+    log::info!("Famicom Disk System image with {} disk sides", disk_sides.len());
+
+    let cartridge = Cartridge {
+        timing_mode,
+        prg_rom: bios_rom.to_vec(),
+        prg_ram: vec![0; fds::PRG_RAM_LEN],
+        has_ram_battery: false,
+        prg_ram_dirty_bit: false,
+        chr_rom: vec![],
+        chr_ram: vec![0; fds::CHR_RAM_LEN],
+    };
+
+    Ok(Mapper::Fds(MapperImpl { cartridge, data: Fds::new(DiskDrive::new(disk_sides, disk_diff)) }))
+}
+
+/// Parse an NSF music file (`.nsf` or `.nsfe`) and create a mapper that plays it.
+///
+/// # Errors
+///
+/// This function will return an error if the file is not a valid NSF/NSFe file or if it uses
+/// unsupported features.
+pub(crate) fn from_nsf_file(
+    file_bytes: &[u8],
+    forced_timing_mode: Option<TimingMode>,
+) -> Result<Mapper, CartridgeFileError> {
+    let nsf_file = NsfFile::parse(file_bytes)?;
+
+    let timing_mode = forced_timing_mode.unwrap_or(match nsf_file.region {
+        NsfRegion::Pal => TimingMode::Pal,
+        NsfRegion::Ntsc | NsfRegion::Dual => TimingMode::Ntsc,
+    });
+
+    let prg_rom = nsf::build_prg_rom(&nsf_file)?;
+
+    log::info!("Timing mode: {timing_mode}");
+    log::info!("NSF title: {}", nsf_file.metadata.title);
+    log::info!(
+        "NSF songs: {} (starting song {})",
+        nsf_file.total_songs,
+        nsf_file.starting_song + 1
+    );
+    log::info!(
+        "NSF load/init/play addresses: ${:04X} / ${:04X} / ${:04X}",
+        nsf_file.load_address,
+        nsf_file.init_address,
+        nsf_file.play_address
+    );
+    log::info!("NSF initial banks: {:?}", nsf_file.initial_banks);
+    log::info!("NSF expansion audio: {:?}", nsf_file.chips.names());
+
+    let prg_ram_len = if nsf_file.chips.fds { nsf::FDS_PRG_RAM_LEN } else { nsf::PRG_RAM_LEN };
+    let cartridge = Cartridge {
+        timing_mode,
+        prg_rom,
+        prg_ram: vec![0; prg_ram_len],
+        has_ram_battery: false,
+        prg_ram_dirty_bit: false,
+        chr_rom: vec![],
+        chr_ram: vec![0; nsf::CHR_RAM_LEN],
+    };
+
+    let mut mapper = MapperImpl { cartridge, data: Nsf::new(nsf_file, timing_mode) };
+    mapper.load_initial_fds_ram_banks();
+
+    Ok(Mapper::Nsf(mapper))
+}
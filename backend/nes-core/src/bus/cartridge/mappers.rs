@@ -1,5 +1,6 @@
 pub(crate) mod action52;
 pub(crate) mod bandai;
+pub(crate) mod fds;
 pub(crate) mod konami;
 pub(crate) mod mmc1;
 pub(crate) mod mmc2;
@@ -8,6 +9,7 @@ pub(crate) mod mmc5;
 pub(crate) mod namco163;
 pub(crate) mod namco175;
 pub(crate) mod nrom;
+pub(crate) mod nsf;
 pub(crate) mod sunsoft;
 pub(crate) mod unrom512;
 
@@ -0,0 +1,206 @@
+//! Code for the Famicom Disk System RAM adapter.
+//!
+//! The BIOS is mapped to $E000-$FFFF and the RAM adapter's 32KB of PRG RAM is mapped to
+//! $6000-$DFFF. CHR memory is always 8KB of RAM.
+
+mod audio;
+mod disk;
+
+use crate::bus::cartridge::mappers::{CpuMapResult, NametableMirroring, PpuMapResult};
+use crate::bus::cartridge::{HasBasicPpuMapping, MapperImpl};
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+pub(crate) use audio::FdsAudioUnit;
+pub(crate) use disk::{DiskDiffRun, DiskDrive, is_fds_file, parse_disk_image};
+
+pub(crate) const BIOS_LEN: usize = 8 * 1024;
+pub(crate) const PRG_RAM_LEN: usize = 32 * 1024;
+pub(crate) const CHR_RAM_LEN: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct IrqTimer {
+    reload_value: u16,
+    counter: u16,
+    repeat: bool,
+    enabled: bool,
+    pending: bool,
+}
+
+impl IrqTimer {
+    fn new() -> Self {
+        Self { reload_value: 0, counter: 0, repeat: false, enabled: false, pending: false }
+    }
+
+    fn handle_control_write(&mut self, value: u8, disk_registers_enabled: bool) {
+        self.repeat = value.bit(0);
+        self.enabled = value.bit(1) && disk_registers_enabled;
+
+        if self.enabled {
+            self.counter = self.reload_value;
+        } else {
+            self.pending = false;
+        }
+    }
+
+    fn tick_cpu(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.counter == 0 {
+            self.pending = true;
+            self.counter = self.reload_value;
+            if !self.repeat {
+                self.enabled = false;
+            }
+        } else {
+            self.counter -= 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct Fds {
+    drive: DiskDrive,
+    irq_timer: IrqTimer,
+    audio: FdsAudioUnit,
+    disk_registers_enabled: bool,
+    sound_registers_enabled: bool,
+    nametable_mirroring: NametableMirroring,
+    external_output: u8,
+}
+
+impl Fds {
+    pub(crate) fn new(drive: DiskDrive) -> Self {
+        Self {
+            drive,
+            irq_timer: IrqTimer::new(),
+            audio: FdsAudioUnit::new(),
+            disk_registers_enabled: true,
+            sound_registers_enabled: true,
+            nametable_mirroring: NametableMirroring::Horizontal,
+            external_output: 0,
+        }
+    }
+}
+
+impl MapperImpl<Fds> {
+    fn map_cpu_address(address: u16) -> CpuMapResult {
+        match address {
+            0x0000..=0x401F => panic!("invalid CPU map address: {address:04X}"),
+            0x4020..=0x5FFF => CpuMapResult::None,
+            0x6000..=0xDFFF => CpuMapResult::PrgRAM(u32::from(address - 0x6000)),
+            0xE000..=0xFFFF => CpuMapResult::PrgROM(u32::from(address & 0x1FFF)),
+        }
+    }
+
+    pub(crate) fn read_cpu_address(&mut self, address: u16, cpu_open_bus: u8) -> u8 {
+        match address {
+            0x4030 if self.data.disk_registers_enabled => {
+                let status = u8::from(self.data.irq_timer.pending) | self.data.drive.read_status();
+                self.data.irq_timer.pending = false;
+                status | (cpu_open_bus & 0x2C)
+            }
+            0x4031 if self.data.disk_registers_enabled => self.data.drive.read_data(),
+            0x4032 if self.data.disk_registers_enabled => {
+                self.data.drive.read_drive_status() | (cpu_open_bus & 0xF8)
+            }
+            0x4033 if self.data.disk_registers_enabled => {
+                // Bit 7 indicates good battery voltage; bits 0-6 read back the external connector
+                0x80 | (self.data.external_output & 0x7F)
+            }
+            0x4040..=0x4097 if self.data.sound_registers_enabled => {
+                self.data.audio.read_register(address, cpu_open_bus).unwrap_or(cpu_open_bus)
+            }
+            _ => Self::map_cpu_address(address).read(&self.cartridge).unwrap_or(cpu_open_bus),
+        }
+    }
+
+    pub(crate) fn write_cpu_address(&mut self, address: u16, value: u8) {
+        match address {
+            0x4020 => {
+                self.data.irq_timer.reload_value =
+                    (self.data.irq_timer.reload_value & 0xFF00) | u16::from(value);
+            }
+            0x4021 => {
+                self.data.irq_timer.reload_value =
+                    (self.data.irq_timer.reload_value & 0x00FF) | (u16::from(value) << 8);
+            }
+            0x4022 => {
+                self.data.irq_timer.handle_control_write(value, self.data.disk_registers_enabled);
+            }
+            0x4023 => {
+                self.data.disk_registers_enabled = value.bit(0);
+                self.data.sound_registers_enabled = value.bit(1);
+
+                if !self.data.disk_registers_enabled {
+                    self.data.irq_timer.enabled = false;
+                    self.data.irq_timer.pending = false;
+                }
+            }
+            0x4024 if self.data.disk_registers_enabled => {
+                self.data.drive.handle_data_write(value);
+            }
+            0x4025 if self.data.disk_registers_enabled => {
+                self.data.drive.handle_control_write(value);
+                self.data.nametable_mirroring = if value.bit(3) {
+                    NametableMirroring::Horizontal
+                } else {
+                    NametableMirroring::Vertical
+                };
+            }
+            0x4026 if self.data.disk_registers_enabled => {
+                self.data.external_output = value;
+            }
+            0x4040..=0x4097 if self.data.sound_registers_enabled => {
+                self.data.audio.write_register(address, value);
+            }
+            0x6000..=0xDFFF => {
+                Self::map_cpu_address(address).write(value, &mut self.cartridge);
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        self.data.irq_timer.tick_cpu();
+        self.data.drive.tick_cpu();
+        self.data.audio.tick_cpu();
+    }
+
+    pub(crate) fn interrupt_flag(&self) -> bool {
+        self.data.irq_timer.pending || self.data.drive.interrupt_flag()
+    }
+
+    pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
+        // At max volume, the FDS channel is roughly 2.4x as loud as an APU pulse channel at max
+        // volume (~0.149 on the APU's 0-1 scale)
+        let fds_sample = 0.358 * self.data.audio.sample();
+        (mixed_apu_sample + fds_sample).clamp(-1.0, 1.0)
+    }
+
+    pub(crate) fn change_disk_side(&mut self) {
+        self.data.drive.change_side();
+    }
+
+    pub(crate) fn take_disk_diff_if_dirty(&mut self) -> Option<Vec<DiskDiffRun>> {
+        self.data.drive.take_diff_if_dirty()
+    }
+
+    pub(crate) fn move_disk_images_from(&mut self, other: &mut Self) {
+        self.data.drive.move_original_sides_from(&mut other.data.drive);
+    }
+}
+
+impl HasBasicPpuMapping for MapperImpl<Fds> {
+    fn map_ppu_address(&self, address: u16) -> PpuMapResult {
+        match address {
+            0x0000..=0x1FFF => PpuMapResult::ChrRAM(address.into()),
+            0x2000..=0x3EFF => {
+                PpuMapResult::Vram(self.data.nametable_mirroring.map_to_vram(address))
+            }
+            0x3F00..=0xFFFF => panic!("invalid PPU map address: {address:04X}"),
+        }
+    }
+}
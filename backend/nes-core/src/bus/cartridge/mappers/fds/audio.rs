@@ -0,0 +1,332 @@
+//! Code for the Famicom Disk System's expansion audio channel, a single wavetable channel with a
+//! frequency modulation unit.
+//!
+//! Implementation references:
+//! <https://www.nesdev.org/wiki/FDS_audio>
+
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+
+// Master volume levels of 2/2, 2/3, 2/4, and 2/5, scaled so that full volume is 36
+const MASTER_VOLUME_TABLE: [u32; 4] = [36, 24, 18, 14];
+
+// Signed modulation table entries; 4 is a special value that resets the mod counter to 0
+const MOD_TABLE_ADJUSTMENTS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+const MOD_TABLE_RESET: u8 = 4;
+
+const MAX_GAIN: u8 = 32;
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct Envelope {
+    speed: u8,
+    increase: bool,
+    disabled: bool,
+    gain: u8,
+    timer: u32,
+}
+
+impl Envelope {
+    fn new() -> Self {
+        Self { speed: 0, increase: false, disabled: true, gain: 0, timer: 0 }
+    }
+
+    fn handle_write(&mut self, value: u8, master_speed: u8) {
+        self.speed = value & 0x3F;
+        self.increase = value.bit(6);
+        self.disabled = value.bit(7);
+
+        if self.disabled {
+            self.gain = self.speed;
+        }
+
+        self.reset_timer(master_speed);
+    }
+
+    fn reset_timer(&mut self, master_speed: u8) {
+        self.timer = 8 * (u32::from(self.speed) + 1) * u32::from(master_speed);
+    }
+
+    // Returns true if the gain was clocked
+    fn tick_cpu(&mut self, master_speed: u8) -> bool {
+        if self.disabled || master_speed == 0 {
+            return false;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer != 0 {
+            return false;
+        }
+
+        self.reset_timer(master_speed);
+        if self.increase && self.gain < MAX_GAIN {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct ModulationUnit {
+    envelope: Envelope,
+    frequency: u16,
+    halted: bool,
+    accumulator: u16,
+    // 7-bit signed counter, -64 to 63
+    counter: i8,
+    table: [u8; 64],
+    table_position: u8,
+    pitch_adjustment: i32,
+}
+
+impl ModulationUnit {
+    fn new() -> Self {
+        Self {
+            envelope: Envelope::new(),
+            frequency: 0,
+            halted: true,
+            accumulator: 0,
+            counter: 0,
+            table: [0; 64],
+            table_position: 0,
+            pitch_adjustment: 0,
+        }
+    }
+
+    fn handle_table_write(&mut self, value: u8) {
+        if !self.halted {
+            return;
+        }
+
+        // Each write fills two consecutive table entries
+        let position = usize::from(self.table_position & 0x3E);
+        self.table[position] = value & 0x07;
+        self.table[position + 1] = value & 0x07;
+        self.table_position = (self.table_position + 2) & 0x3F;
+    }
+
+    fn handle_counter_write(&mut self, value: u8) {
+        // Sign extend from 7 bits
+        self.counter = ((value << 1) as i8) >> 1;
+    }
+
+    fn clock_counter(&mut self) {
+        let entry = self.table[self.table_position as usize];
+        if entry == MOD_TABLE_RESET {
+            self.counter = 0;
+        } else {
+            let adjusted = self.counter.wrapping_add(MOD_TABLE_ADJUSTMENTS[entry as usize]);
+            // Wrap to 7-bit signed
+            self.counter = (adjusted << 1) >> 1;
+        }
+        self.table_position = (self.table_position + 1) & 0x3F;
+    }
+
+    // Returns true if the mod counter was clocked
+    fn tick_cpu(&mut self) -> bool {
+        if self.halted || self.frequency == 0 {
+            return false;
+        }
+
+        let (accumulator, overflowed) = self.accumulator.overflowing_add(self.frequency);
+        self.accumulator = accumulator;
+        if overflowed {
+            self.clock_counter();
+        }
+
+        overflowed
+    }
+
+    // Pitch adjustment formula from https://www.nesdev.org/wiki/FDS_audio#Frequency_calculation
+    fn update_pitch_adjustment(&mut self, wave_frequency: u16) {
+        let mut temp = i32::from(self.counter) * i32::from(self.envelope.gain);
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            if self.counter < 0 {
+                temp -= 1;
+            } else {
+                temp += 2;
+            }
+        }
+
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= i32::from(wave_frequency);
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+
+        self.pitch_adjustment = temp;
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct FdsAudioUnit {
+    wave_table: [u8; 64],
+    wave_write_enabled: bool,
+    wave_halted: bool,
+    wave_frequency: u16,
+    wave_accumulator: u16,
+    wave_position: u8,
+    volume_envelope: Envelope,
+    modulation: ModulationUnit,
+    envelopes_disabled: bool,
+    master_envelope_speed: u8,
+    master_volume: u8,
+    current_output: u8,
+}
+
+impl FdsAudioUnit {
+    pub(crate) fn new() -> Self {
+        Self {
+            wave_table: [0; 64],
+            wave_write_enabled: false,
+            wave_halted: true,
+            wave_frequency: 0,
+            wave_accumulator: 0,
+            wave_position: 0,
+            volume_envelope: Envelope::new(),
+            modulation: ModulationUnit::new(),
+            envelopes_disabled: false,
+            master_envelope_speed: 0xE8,
+            master_volume: 0,
+            current_output: 0,
+        }
+    }
+
+    pub(crate) fn read_register(&self, address: u16, cpu_open_bus: u8) -> Option<u8> {
+        match address {
+            0x4040..=0x407F => {
+                // Wavetable reads only return the true value while writes are enabled; otherwise
+                // they return the sample currently being output
+                let position = if self.wave_write_enabled {
+                    address & 0x3F
+                } else {
+                    self.wave_position.into()
+                };
+                Some(self.wave_table[position as usize] | (cpu_open_bus & 0xC0))
+            }
+            0x4090 => Some(self.volume_envelope.gain | (cpu_open_bus & 0xC0)),
+            0x4092 => Some(self.modulation.envelope.gain | (cpu_open_bus & 0xC0)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x4040..=0x407F if self.wave_write_enabled => {
+                self.wave_table[(address & 0x3F) as usize] = value & 0x3F;
+            }
+            0x4080 => {
+                self.volume_envelope.handle_write(value, self.master_envelope_speed);
+            }
+            0x4082 => {
+                self.wave_frequency = (self.wave_frequency & 0x0F00) | u16::from(value);
+            }
+            0x4083 => {
+                self.wave_frequency =
+                    (self.wave_frequency & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.envelopes_disabled = value.bit(6);
+                self.wave_halted = value.bit(7);
+
+                if self.wave_halted {
+                    self.wave_accumulator = 0;
+                    self.wave_position = 0;
+                }
+
+                if self.envelopes_disabled {
+                    self.volume_envelope.reset_timer(self.master_envelope_speed);
+                    self.modulation.envelope.reset_timer(self.master_envelope_speed);
+                }
+            }
+            0x4084 => {
+                self.modulation.envelope.handle_write(value, self.master_envelope_speed);
+            }
+            0x4085 => {
+                self.modulation.handle_counter_write(value);
+            }
+            0x4086 => {
+                self.modulation.frequency = (self.modulation.frequency & 0x0F00) | u16::from(value);
+            }
+            0x4087 => {
+                self.modulation.frequency =
+                    (self.modulation.frequency & 0x00FF) | (u16::from(value & 0x0F) << 8);
+                self.modulation.halted = value.bit(7);
+
+                if self.modulation.halted {
+                    self.modulation.accumulator = 0;
+                }
+            }
+            0x4088 => {
+                self.modulation.handle_table_write(value);
+            }
+            0x4089 => {
+                self.wave_write_enabled = value.bit(7);
+                self.master_volume = value & 0x03;
+            }
+            0x408A => {
+                self.master_envelope_speed = value;
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        if !self.wave_halted && !self.envelopes_disabled {
+            self.volume_envelope.tick_cpu(self.master_envelope_speed);
+            if self.modulation.envelope.tick_cpu(self.master_envelope_speed) {
+                self.modulation.update_pitch_adjustment(self.wave_frequency);
+            }
+        }
+
+        if self.modulation.tick_cpu() {
+            self.modulation.update_pitch_adjustment(self.wave_frequency);
+        }
+
+        if self.wave_halted {
+            self.wave_position = 0;
+            self.update_output();
+            return;
+        }
+
+        self.update_output();
+
+        let pitch = i32::from(self.wave_frequency) + self.modulation.pitch_adjustment;
+        if pitch > 0 && !self.wave_write_enabled {
+            let (accumulator, overflowed) = self.wave_accumulator.overflowing_add(pitch as u16);
+            self.wave_accumulator = accumulator;
+            if overflowed {
+                self.wave_position = (self.wave_position + 1) & 0x3F;
+            }
+        }
+    }
+
+    fn update_output(&mut self) {
+        // Output is held at the last value while wavetable writes are enabled
+        if self.wave_write_enabled {
+            return;
+        }
+
+        let gain = u32::from(self.volume_envelope.gain.min(MAX_GAIN));
+        let level = gain * MASTER_VOLUME_TABLE[self.master_volume as usize];
+        let sample = u32::from(self.wave_table[self.wave_position as usize]);
+
+        // Max value of sample * level is 63 * 32 * 36, so dividing by 1152 (32 * 36) gives a
+        // value in the range 0-63
+        self.current_output = ((sample * level) / 1152) as u8;
+    }
+
+    /// Return the current audio sample in the range 0 to 1.
+    pub(crate) fn sample(&self) -> f64 {
+        f64::from(self.current_output) / 63.0
+    }
+}
@@ -0,0 +1,619 @@
+//! Code for parsing Famicom Disk System disk images and emulating the RAM adapter's disk drive.
+//!
+//! Disk images are expanded to a raw format that includes the gaps, block start marks, and CRCs
+//! that are omitted from .fds files, so that the drive can stream bytes to and from the disk the
+//! same way it would from a physical disk.
+//!
+//! Implementation references:
+//! <https://www.nesdev.org/wiki/FDS_disk_format>
+//! <https://www.nesdev.org/wiki/Family_Computer_Disk_System>
+
+use bincode::{Decode, Encode};
+use jgenesis_proc_macros::{FakeDecode, FakeEncode};
+use std::mem;
+use std::ops::Deref;
+
+pub(crate) const FDS_HEADER_LEN: usize = 16;
+pub(crate) const FDS_SIDE_LEN: usize = 65500;
+pub(crate) const QD_SIDE_LEN: usize = 65536;
+
+// 28300 bits of gap before the first block and 976 bits of gap between blocks
+const LEADING_GAP_LEN: usize = 28300 / 8;
+const BLOCK_GAP_LEN: usize = 976 / 8;
+
+const BLOCK_START_MARK: u8 = 0x80;
+
+// Raw sides are padded to this length so that there is room for games to append files
+const RAW_SIDE_LEN: usize = LEADING_GAP_LEN + QD_SIDE_LEN + 16 * BLOCK_GAP_LEN;
+
+// Roughly 96.4 Kbit/s
+const CPU_CYCLES_PER_BYTE: u32 = 149;
+
+// Delay after the head returns to the start of the disk before data starts streaming
+const HEAD_RESET_DELAY_CPU_CYCLES: u32 = 50000;
+
+// How long the drive reports no disk after ejecting a side during a side change; games generally
+// will not notice a new side unless they first see the old side ejected
+const DISK_CHANGE_DELAY_CPU_CYCLES: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DiskImageFormat {
+    // .fds files: blocks without gaps or CRCs
+    Fds,
+    // .qd files: blocks with CRCs but without gaps
+    QuickDisk,
+}
+
+impl DiskImageFormat {
+    fn side_len(self) -> usize {
+        match self {
+            Self::Fds => FDS_SIDE_LEN,
+            Self::QuickDisk => QD_SIDE_LEN,
+        }
+    }
+
+    fn has_crcs(self) -> bool {
+        self == Self::QuickDisk
+    }
+}
+
+/// Returns whether the given file looks like an FDS disk image, either with an fwNES header or
+/// starting directly with a disk info block.
+pub(crate) fn is_fds_file(file_bytes: &[u8]) -> bool {
+    file_bytes.starts_with(b"FDS\x1A") || file_bytes.starts_with(b"\x01*NINTENDO-HVC*")
+}
+
+/// Parse disk sides out of a .fds or .qd file, expanding each side to the raw format used by the
+/// drive emulation. Returns None if the file does not look like a disk image.
+pub(crate) fn parse_disk_image(file_bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let (format, sides_bytes) = if file_bytes.starts_with(b"FDS\x1A") {
+        (DiskImageFormat::Fds, &file_bytes[FDS_HEADER_LEN.min(file_bytes.len())..])
+    } else if !file_bytes.is_empty() && file_bytes.len().is_multiple_of(FDS_SIDE_LEN) {
+        (DiskImageFormat::Fds, file_bytes)
+    } else if !file_bytes.is_empty() && file_bytes.len().is_multiple_of(QD_SIDE_LEN) {
+        (DiskImageFormat::QuickDisk, file_bytes)
+    } else {
+        return None;
+    };
+
+    let sides: Vec<_> = sides_bytes
+        .chunks_exact(format.side_len())
+        .map(|side_bytes| expand_disk_side(side_bytes, format))
+        .collect();
+
+    (!sides.is_empty()).then_some(sides)
+}
+
+fn expand_disk_side(side_bytes: &[u8], format: DiskImageFormat) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(RAW_SIDE_LEN);
+    raw.resize(LEADING_GAP_LEN, 0);
+
+    let mut file_size = 0_usize;
+    let mut i = 0;
+    while i < side_bytes.len() {
+        let block_len = match side_bytes[i] {
+            // Disk info block
+            1 => 56,
+            // File amount block
+            2 => 2,
+            // File header block; bytes 13-14 are the file size
+            3 => {
+                if i + 15 <= side_bytes.len() {
+                    file_size = u16::from_le_bytes([side_bytes[i + 13], side_bytes[i + 14]]).into();
+                }
+                16
+            }
+            // File data block; block code byte followed by file contents
+            4 => 1 + file_size,
+            // Anything else marks the end of the data on this side
+            _ => break,
+        };
+
+        let block_end = (i + block_len).min(side_bytes.len());
+        raw.push(BLOCK_START_MARK);
+        raw.extend_from_slice(&side_bytes[i..block_end]);
+
+        if format.has_crcs() {
+            let crc_end = (block_end + 2).min(side_bytes.len());
+            raw.extend_from_slice(&side_bytes[block_end..crc_end]);
+            i = crc_end;
+        } else {
+            let crc = compute_block_crc(&side_bytes[i..block_end]);
+            raw.extend_from_slice(&crc.to_le_bytes());
+            i = block_end;
+        }
+
+        raw.resize(raw.len() + BLOCK_GAP_LEN, 0);
+    }
+
+    if raw.len() < RAW_SIDE_LEN {
+        raw.resize(RAW_SIDE_LEN, 0);
+    }
+
+    raw
+}
+
+fn update_crc(crc: u16, value: u8) -> u16 {
+    let mut crc = crc;
+    for bit in 0..8 {
+        let carry = crc & 1 != 0;
+        crc >>= 1;
+        if carry {
+            crc ^= 0x8408;
+        }
+        if value & (1 << bit) != 0 {
+            crc ^= 0x8000;
+        }
+    }
+    crc
+}
+
+// The drive computes the CRC over the block start mark, the block contents, and then two 0 bytes
+fn compute_block_crc(block: &[u8]) -> u16 {
+    let crc = update_crc(0, BLOCK_START_MARK);
+    let crc = block.iter().fold(crc, |crc, &byte| update_crc(crc, byte));
+    update_crc(update_crc(crc, 0), 0)
+}
+
+/// A run of modified bytes on one disk side, relative to the original disk image.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub(crate) struct DiskDiffRun {
+    side: u8,
+    offset: u32,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, FakeEncode, FakeDecode)]
+pub(crate) struct OriginalDiskSides(Vec<Vec<u8>>);
+
+impl Deref for OriginalDiskSides {
+    type Target = Vec<Vec<u8>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+fn compute_diff(original: &[Vec<u8>], current: &[Vec<u8>]) -> Vec<DiskDiffRun> {
+    let mut runs = Vec::new();
+
+    for (side, (original_side, current_side)) in original.iter().zip(current).enumerate() {
+        let mut i = 0;
+        while i < current_side.len() {
+            if original_side.get(i) == Some(&current_side[i]) {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < current_side.len() && original_side.get(i) != Some(&current_side[i]) {
+                i += 1;
+            }
+
+            runs.push(DiskDiffRun {
+                side: side as u8,
+                offset: start as u32,
+                bytes: current_side[start..i].to_vec(),
+            });
+        }
+    }
+
+    runs
+}
+
+fn apply_diff(sides: &mut [Vec<u8>], diff: &[DiskDiffRun]) {
+    for run in diff {
+        let Some(side) = sides.get_mut(run.side as usize) else { continue };
+
+        let start = run.offset as usize;
+        let end = start + run.bytes.len();
+        if end > side.len() {
+            log::error!(
+                "Ignoring disk diff run that extends past end of side {}: {start}..{end}",
+                run.side
+            );
+            continue;
+        }
+
+        side[start..end].copy_from_slice(&run.bytes);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+enum DiskSlot {
+    Inserted(u8),
+    // Side number to insert after the delay expires
+    Changing { next_side: u8, delay: u32 },
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct DiskDrive {
+    sides: Vec<Vec<u8>>,
+    original_sides: OriginalDiskSides,
+    slot: DiskSlot,
+    disk_dirty: bool,
+    motor_on: bool,
+    transfer_reset: bool,
+    read_mode: bool,
+    crc_control: bool,
+    prev_crc_control: bool,
+    transfer_enabled: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    transfer_complete: bool,
+    scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+    position: usize,
+    delay: u32,
+    crc: u16,
+    read_data: u8,
+    write_data: u8,
+}
+
+impl DiskDrive {
+    pub(crate) fn new(sides: Vec<Vec<u8>>, diff: Option<&[DiskDiffRun]>) -> Self {
+        let original_sides = OriginalDiskSides(sides.clone());
+
+        let mut sides = sides;
+        if let Some(diff) = diff {
+            log::info!("Applying {} modified disk runs from save file", diff.len());
+            apply_diff(&mut sides, diff);
+        }
+
+        Self {
+            sides,
+            original_sides,
+            slot: DiskSlot::Inserted(0),
+            disk_dirty: false,
+            motor_on: false,
+            transfer_reset: false,
+            read_mode: true,
+            crc_control: false,
+            prev_crc_control: false,
+            transfer_enabled: false,
+            irq_enabled: false,
+            irq_pending: false,
+            transfer_complete: false,
+            scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+            position: 0,
+            delay: 0,
+            crc: 0,
+            read_data: 0,
+            write_data: 0,
+        }
+    }
+
+    fn inserted_side(&self) -> Option<usize> {
+        match self.slot {
+            DiskSlot::Inserted(side) => Some(side.into()),
+            DiskSlot::Changing { .. } => None,
+        }
+    }
+
+    /// Eject the current disk side and insert the next one after a short delay, wrapping around
+    /// to the first side after the last side.
+    pub(crate) fn change_side(&mut self) {
+        let next_side = match self.slot {
+            DiskSlot::Inserted(side) | DiskSlot::Changing { next_side: side, .. } => {
+                (side + 1) % self.sides.len() as u8
+            }
+        };
+
+        log::info!(
+            "Ejecting disk; inserting disk {} side {} ({}/{})",
+            next_side / 2 + 1,
+            if next_side % 2 == 0 { 'A' } else { 'B' },
+            next_side + 1,
+            self.sides.len()
+        );
+
+        self.slot = DiskSlot::Changing { next_side, delay: DISK_CHANGE_DELAY_CPU_CYCLES };
+    }
+
+    pub(crate) fn handle_control_write(&mut self, value: u8) {
+        self.motor_on = value & 0x01 != 0;
+        self.transfer_reset = value & 0x02 != 0;
+        self.read_mode = value & 0x04 != 0;
+        self.crc_control = value & 0x10 != 0;
+        self.transfer_enabled = value & 0x40 != 0;
+        self.irq_enabled = value & 0x80 != 0;
+
+        // Writing to $4025 acknowledges the disk transfer IRQ
+        self.irq_pending = false;
+    }
+
+    pub(crate) fn handle_data_write(&mut self, value: u8) {
+        self.write_data = value;
+        self.transfer_complete = false;
+        self.irq_pending = false;
+    }
+
+    pub(crate) fn read_data(&mut self) -> u8 {
+        self.transfer_complete = false;
+        self.irq_pending = false;
+        self.read_data
+    }
+
+    // $4030 bits 1, 4, 6, 7; reading acknowledges the disk transfer IRQ
+    pub(crate) fn read_status(&mut self) -> u8 {
+        let status = (u8::from(self.transfer_complete) << 1)
+            | (u8::from(self.end_of_head) << 6)
+            | (u8::from(self.transfer_enabled) << 7);
+
+        self.transfer_complete = false;
+        self.irq_pending = false;
+
+        status
+    }
+
+    // $4032 bits 0-2
+    pub(crate) fn read_drive_status(&self) -> u8 {
+        let inserted = self.inserted_side().is_some();
+        u8::from(!inserted)
+            | (u8::from(!inserted || !self.scanning) << 1)
+            | (u8::from(!inserted) << 2)
+    }
+
+    pub(crate) fn interrupt_flag(&self) -> bool {
+        self.irq_pending
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        if let DiskSlot::Changing { next_side, delay } = &mut self.slot {
+            *delay -= 1;
+            if *delay == 0 {
+                self.slot = DiskSlot::Inserted(*next_side);
+            }
+        }
+
+        let Some(side) = self.inserted_side() else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+
+        if !self.motor_on {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            self.delay = HEAD_RESET_DELAY_CPU_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        self.transfer_byte(side);
+
+        self.prev_crc_control = self.crc_control;
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.motor_on = false;
+            self.end_of_head = true;
+            if self.irq_enabled {
+                self.irq_pending = true;
+            }
+        } else {
+            self.delay = CPU_CYCLES_PER_BYTE;
+        }
+    }
+
+    fn transfer_byte(&mut self, side: usize) {
+        let mut trigger_irq = self.irq_enabled;
+
+        if self.read_mode {
+            let value = self.sides[side][self.position];
+            if !self.prev_crc_control {
+                self.crc = update_crc(self.crc, value);
+            }
+
+            if !self.transfer_enabled {
+                self.gap_ended = false;
+                self.crc = 0;
+            } else if value != 0 && !self.gap_ended {
+                // The first non-zero byte after a gap is the block start mark, which is not
+                // transferred to the CPU
+                self.gap_ended = true;
+                trigger_irq = false;
+            }
+
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = value;
+                if trigger_irq {
+                    self.irq_pending = true;
+                }
+            }
+        } else {
+            let mut value = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                value = self.write_data;
+                if trigger_irq {
+                    self.irq_pending = true;
+                }
+            }
+
+            if !self.transfer_enabled {
+                value = 0;
+            }
+
+            if !self.crc_control {
+                self.crc = update_crc(self.crc, value);
+            } else {
+                if !self.prev_crc_control {
+                    self.crc = update_crc(update_crc(self.crc, 0), 0);
+                }
+                value = self.crc as u8;
+                self.crc >>= 8;
+            }
+
+            if self.sides[side][self.position] != value {
+                self.sides[side][self.position] = value;
+                self.disk_dirty = true;
+            }
+            self.gap_ended = false;
+        }
+    }
+
+    /// If any disk contents have been modified since the last time this method returned a diff
+    /// and the drive is not actively writing, return a diff against the original disk image.
+    pub(crate) fn take_diff_if_dirty(&mut self) -> Option<Vec<DiskDiffRun>> {
+        let writing = self.motor_on && !self.read_mode;
+        if !self.disk_dirty || writing {
+            return None;
+        }
+
+        self.disk_dirty = false;
+        Some(compute_diff(&self.original_sides, &self.sides))
+    }
+
+    pub(crate) fn move_original_sides_from(&mut self, other: &mut Self) {
+        self.original_sides = mem::take(&mut other.original_sides);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_side() -> Vec<u8> {
+        let mut side = vec![0; FDS_SIDE_LEN];
+
+        // Disk info block
+        side[0] = 1;
+        side[1..15].copy_from_slice(b"*NINTENDO-HVC*");
+
+        // File amount block
+        side[56] = 2;
+        side[57] = 1;
+
+        // File header block with a file size of 3 bytes
+        side[58] = 3;
+        side[58 + 13] = 3;
+        side[58 + 14] = 0;
+
+        // File data block
+        side[74..78].copy_from_slice(&[4, 0xAA, 0xBB, 0xCC]);
+
+        side
+    }
+
+    #[test]
+    fn detect_disk_images() {
+        let mut headered = b"FDS\x1A\x01".to_vec();
+        headered.resize(FDS_HEADER_LEN, 0);
+        headered.extend(test_side());
+        assert!(is_fds_file(&headered));
+        assert!(is_fds_file(&test_side()));
+
+        assert!(!is_fds_file(b"NES\x1A"));
+        assert!(!is_fds_file(&[]));
+    }
+
+    #[test]
+    fn expand_fds_side() {
+        let mut file = b"FDS\x1A\x01".to_vec();
+        file.resize(FDS_HEADER_LEN, 0);
+        file.extend(test_side());
+
+        let sides = parse_disk_image(&file).expect("valid disk image");
+        assert_eq!(sides.len(), 1);
+
+        let raw = &sides[0];
+        assert_eq!(raw.len(), RAW_SIDE_LEN);
+        assert!(raw[..LEADING_GAP_LEN].iter().all(|&b| b == 0));
+        assert_eq!(raw[LEADING_GAP_LEN], BLOCK_START_MARK);
+        assert_eq!(&raw[LEADING_GAP_LEN + 1..LEADING_GAP_LEN + 15], b"\x01*NINTENDO-HVC");
+
+        // Start mark + 56-byte block + 2-byte CRC + gap
+        let second_block = LEADING_GAP_LEN + 1 + 56 + 2 + BLOCK_GAP_LEN;
+        assert_eq!(&raw[second_block..second_block + 3], &[BLOCK_START_MARK, 2, 1]);
+
+        let file_data_block = second_block + 3 + 2 + BLOCK_GAP_LEN + 1 + 16 + 2 + BLOCK_GAP_LEN;
+        assert_eq!(
+            &raw[file_data_block..file_data_block + 5],
+            &[BLOCK_START_MARK, 4, 0xAA, 0xBB, 0xCC]
+        );
+    }
+
+    #[test]
+    fn headerless_fds_and_qd() {
+        let sides = parse_disk_image(&[test_side(), test_side()].concat()).unwrap();
+        assert_eq!(sides.len(), 2);
+
+        let sides = parse_disk_image(&vec![0; QD_SIDE_LEN]).unwrap();
+        assert_eq!(sides.len(), 1);
+
+        assert!(parse_disk_image(&[0; 1000]).is_none());
+    }
+
+    #[test]
+    fn diff_round_trip() {
+        let original = vec![vec![0; 100], vec![0; 100]];
+
+        let mut modified = original.clone();
+        modified[0][10..13].copy_from_slice(&[1, 2, 3]);
+        modified[1][99] = 5;
+
+        let diff = compute_diff(&original, &modified);
+        assert_eq!(diff.len(), 2);
+
+        let mut restored = original.clone();
+        apply_diff(&mut restored, &diff);
+        assert_eq!(restored, modified);
+    }
+
+    fn finish_side_change(drive: &mut DiskDrive) {
+        for _ in 0..DISK_CHANGE_DELAY_CPU_CYCLES {
+            assert_eq!(drive.inserted_side(), None);
+            drive.tick_cpu();
+        }
+    }
+
+    #[test]
+    fn change_side_wraps() {
+        let mut drive = DiskDrive::new(vec![vec![0; 100]; 3], None);
+        assert_eq!(drive.inserted_side(), Some(0));
+        assert_eq!(drive.read_drive_status() & 0x01, 0);
+
+        drive.change_side();
+        assert_eq!(drive.read_drive_status() & 0x05, 0x05);
+        finish_side_change(&mut drive);
+        assert_eq!(drive.inserted_side(), Some(1));
+        assert_eq!(drive.read_drive_status() & 0x05, 0);
+
+        drive.change_side();
+        finish_side_change(&mut drive);
+        assert_eq!(drive.inserted_side(), Some(2));
+
+        // Last side wraps around to disk 1 side A
+        drive.change_side();
+        finish_side_change(&mut drive);
+        assert_eq!(drive.inserted_side(), Some(0));
+
+        // Changing again during the delay advances from the side that is about to be inserted
+        drive.change_side();
+        drive.tick_cpu();
+        drive.change_side();
+        drive.change_side();
+        finish_side_change(&mut drive);
+        assert_eq!(drive.inserted_side(), Some(0));
+    }
+}
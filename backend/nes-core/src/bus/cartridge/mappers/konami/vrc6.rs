@@ -150,6 +150,65 @@ impl SawtoothChannel {
     }
 }
 
+/// The VRC6's expansion audio: two pulse channels and a sawtooth channel.
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct Vrc6AudioUnit {
+    pulse_channel_1: Vrc6PulseChannel,
+    pulse_channel_2: Vrc6PulseChannel,
+    sawtooth_channel: SawtoothChannel,
+}
+
+impl Vrc6AudioUnit {
+    pub(crate) fn new() -> Self {
+        Self {
+            pulse_channel_1: Vrc6PulseChannel::new(),
+            pulse_channel_2: Vrc6PulseChannel::new(),
+            sawtooth_channel: SawtoothChannel::new(),
+        }
+    }
+
+    /// Handle a write to one of the audio registers. The address should already be remapped to
+    /// mapper 24 addressing ($9000-$9002, $A000-$A002, $B000-$B002).
+    pub(crate) fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x9000 => self.pulse_channel_1.process_control_update(value),
+            0x9001 => self.pulse_channel_1.process_freq_low_update(value),
+            0x9002 => self.pulse_channel_1.process_freq_high_update(value),
+            0xA000 => self.pulse_channel_2.process_control_update(value),
+            0xA001 => self.pulse_channel_2.process_freq_low_update(value),
+            0xA002 => self.pulse_channel_2.process_freq_high_update(value),
+            0xB000 => self.sawtooth_channel.process_control_update(value),
+            0xB001 => self.sawtooth_channel.process_freq_low_update(value),
+            0xB002 => self.sawtooth_channel.process_freq_high_update(value),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        self.pulse_channel_1.tick_cpu();
+        self.pulse_channel_2.tick_cpu();
+        self.sawtooth_channel.tick_cpu();
+    }
+
+    pub(crate) fn mix_sample(&self, mixed_apu_sample: f64) -> f64 {
+        let pulse1_sample = self.pulse_channel_1.sample();
+        let pulse2_sample = self.pulse_channel_2.sample();
+        let sawtooth_sample = self.sawtooth_channel.sample();
+
+        // VRC6 mixes channels linearly
+        // The pulse channels can each output 0-15 and the sawtooth channel can output 0-31
+        let vrc6_mix = f64::from(pulse1_sample + pulse2_sample + sawtooth_sample) / 61.0;
+
+        // Derived from https://www.nesdev.org/wiki/APU_Mixer by assuming the max value for each
+        // channel then multiplying by 61/30
+        let mixed_sample = mixed_apu_sample - 0.5255823148813802 * vrc6_mix;
+
+        // Slightly amplify because otherwise this chip is very quiet
+        let amplified = 1.25 * mixed_sample;
+        amplified.clamp(-1.0, 1.0)
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub(crate) struct Vrc6 {
     variant: Variant,
@@ -160,9 +219,7 @@ pub(crate) struct Vrc6 {
     nametable_mirroring: NametableMirroring,
     ram_enabled: bool,
     irq: VrcIrqCounter,
-    pulse_channel_1: Vrc6PulseChannel,
-    pulse_channel_2: Vrc6PulseChannel,
-    sawtooth_channel: SawtoothChannel,
+    audio: Vrc6AudioUnit,
 }
 
 impl Vrc6 {
@@ -184,9 +241,7 @@ impl Vrc6 {
             nametable_mirroring: NametableMirroring::Vertical,
             ram_enabled: false,
             irq: VrcIrqCounter::new(),
-            pulse_channel_1: Vrc6PulseChannel::new(),
-            pulse_channel_2: Vrc6PulseChannel::new(),
-            sawtooth_channel: SawtoothChannel::new(),
+            audio: Vrc6AudioUnit::new(),
         }
     }
 }
@@ -236,32 +291,8 @@ impl MapperImpl<Vrc6> {
                     0x8000..=0x8003 => {
                         self.data.prg_16kb_bank = value & 0x0F;
                     }
-                    0x9000 => {
-                        self.data.pulse_channel_1.process_control_update(value);
-                    }
-                    0x9001 => {
-                        self.data.pulse_channel_1.process_freq_low_update(value);
-                    }
-                    0x9002 => {
-                        self.data.pulse_channel_1.process_freq_high_update(value);
-                    }
-                    0xA000 => {
-                        self.data.pulse_channel_2.process_control_update(value);
-                    }
-                    0xA001 => {
-                        self.data.pulse_channel_2.process_freq_low_update(value);
-                    }
-                    0xA002 => {
-                        self.data.pulse_channel_2.process_freq_high_update(value);
-                    }
-                    0xB000 => {
-                        self.data.sawtooth_channel.process_control_update(value);
-                    }
-                    0xB001 => {
-                        self.data.sawtooth_channel.process_freq_low_update(value);
-                    }
-                    0xB002 => {
-                        self.data.sawtooth_channel.process_freq_high_update(value);
+                    0x9000..=0x9002 | 0xA000..=0xA002 | 0xB000..=0xB002 => {
+                        self.data.audio.write_register(remapped, value);
                     }
                     0xB003 => {
                         self.data.nametable_mirroring = match value & 0x0C {
@@ -309,28 +340,11 @@ impl MapperImpl<Vrc6> {
 
     pub(crate) fn tick_cpu(&mut self) {
         self.data.irq.tick_cpu();
-
-        self.data.pulse_channel_1.tick_cpu();
-        self.data.pulse_channel_2.tick_cpu();
-        self.data.sawtooth_channel.tick_cpu();
+        self.data.audio.tick_cpu();
     }
 
     pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
-        let pulse1_sample = self.data.pulse_channel_1.sample();
-        let pulse2_sample = self.data.pulse_channel_2.sample();
-        let sawtooth_sample = self.data.sawtooth_channel.sample();
-
-        // VRC6 mixes channels linearly
-        // The pulse channels can each output 0-15 and the sawtooth channel can output 0-31
-        let vrc6_mix = f64::from(pulse1_sample + pulse2_sample + sawtooth_sample) / 61.0;
-
-        // Derived from https://www.nesdev.org/wiki/APU_Mixer by assuming the max value for each
-        // channel then multiplying by 61/30
-        let mixed_sample = mixed_apu_sample - 0.5255823148813802 * vrc6_mix;
-
-        // Slightly amplify because otherwise this chip is very quiet
-        let amplified = 1.25 * mixed_sample;
-        amplified.clamp(-1.0, 1.0)
+        self.data.audio.mix_sample(mixed_apu_sample)
     }
 }
 
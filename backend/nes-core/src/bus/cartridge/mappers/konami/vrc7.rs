@@ -37,6 +37,20 @@ enum Variant {
 // VRC7 has its own oscillator, but the frequency is almost an exact division of the NES CPU clock speed
 const VRC7_AUDIO_CLOCK_INTERVAL: u8 = 36;
 
+pub(crate) fn new_vrc7_audio_unit() -> Vrc7AudioUnit {
+    ym_opll::new_vrc7(VRC7_AUDIO_CLOCK_INTERVAL)
+}
+
+pub(crate) fn mix_vrc7_sample(audio: &Vrc7AudioUnit, mixed_apu_sample: f64) -> f64 {
+    let vrc7_sample = audio.sample();
+
+    // Amplify the VRC7 samples by ~4dB because otherwise this chip is very quiet
+    let amplified_sample = vrc7_sample * 1.5848931924611136;
+    let clamped_sample = amplified_sample.clamp(-1.0, 1.0);
+
+    mixed_apu_sample - clamped_sample
+}
+
 impl Vrc7 {
     pub(crate) fn new(sub_mapper_number: u8, chr_type: ChrType) -> Self {
         let variant = match sub_mapper_number {
@@ -58,7 +72,7 @@ impl Vrc7 {
             nametable_mirroring: NametableMirroring::Vertical,
             irq: VrcIrqCounter::new(),
             ram_enabled: false,
-            audio: ym_opll::new_vrc7(VRC7_AUDIO_CLOCK_INTERVAL),
+            audio: new_vrc7_audio_unit(),
             audio_enabled: false,
         }
     }
@@ -150,7 +164,7 @@ impl MapperImpl<Vrc7> {
                     self.data.audio_enabled = !value.bit(6);
                     if !self.data.audio_enabled {
                         // Clear all audio state when audio is disabled
-                        self.data.audio = ym_opll::new_vrc7(VRC7_AUDIO_CLOCK_INTERVAL);
+                        self.data.audio = new_vrc7_audio_unit();
                     }
                 }
                 (Variant::Vrc7a | Variant::Unknown, 0xE010)
@@ -183,13 +197,7 @@ impl MapperImpl<Vrc7> {
             return mixed_apu_sample;
         }
 
-        let vrc7_sample = self.data.audio.sample();
-
-        // Amplify the VRC7 samples by ~4dB because otherwise this chip is very quiet
-        let amplified_sample = vrc7_sample * 1.5848931924611136;
-        let clamped_sample = amplified_sample.clamp(-1.0, 1.0);
-
-        mixed_apu_sample - clamped_sample
+        mix_vrc7_sample(&self.data.audio, mixed_apu_sample)
     }
 }
 
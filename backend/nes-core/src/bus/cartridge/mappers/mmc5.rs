@@ -461,17 +461,17 @@ impl ExtendedAttributesState {
 }
 
 #[derive(Debug, Clone, Copy, Encode, Decode)]
-struct MultiplierUnit {
-    operand_l: u16,
-    operand_r: u16,
+pub(crate) struct MultiplierUnit {
+    pub(crate) operand_l: u16,
+    pub(crate) operand_r: u16,
 }
 
 impl MultiplierUnit {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self { operand_l: 0xFF, operand_r: 0xFF }
     }
 
-    fn output(self) -> u16 {
+    pub(crate) fn output(self) -> u16 {
         self.operand_l * self.operand_r
     }
 }
@@ -540,6 +540,112 @@ impl PcmChannel {
     }
 }
 
+/// The MMC5's expansion audio: two pulse channels that are nearly identical to the APU pulse
+/// channels, plus a raw PCM channel.
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct Mmc5AudioUnit {
+    pulse_channel_1: PulseChannel,
+    pulse_channel_2: PulseChannel,
+    pcm_channel: PcmChannel,
+    frame_counter: FrameCounter,
+}
+
+impl Mmc5AudioUnit {
+    pub(crate) fn new() -> Self {
+        Self {
+            pulse_channel_1: PulseChannel::new_channel_1(SweepStatus::Disabled),
+            pulse_channel_2: PulseChannel::new_channel_2(SweepStatus::Disabled),
+            pcm_channel: PcmChannel::new(),
+            frame_counter: FrameCounter::new(TimingMode::Ntsc),
+        }
+    }
+
+    pub(crate) fn read_register(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x5010 => Some(self.pcm_channel.read_control()),
+            0x5015 => Some(
+                (u8::from(self.pulse_channel_2.length_counter() != 0) << 1)
+                    | u8::from(self.pulse_channel_1.length_counter() != 0),
+            ),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000 => {
+                self.pulse_channel_1.process_vol_update(value);
+            }
+            0x5002 => {
+                self.pulse_channel_1.process_lo_update(value);
+            }
+            0x5003 => {
+                self.pulse_channel_1.process_hi_update(value);
+            }
+            0x5004 => {
+                self.pulse_channel_2.process_vol_update(value);
+            }
+            0x5006 => {
+                self.pulse_channel_2.process_lo_update(value);
+            }
+            0x5007 => {
+                self.pulse_channel_2.process_hi_update(value);
+            }
+            0x5010 => {
+                self.pcm_channel.process_control_update(value);
+            }
+            0x5011 => {
+                self.pcm_channel.process_raw_pcm_update(value);
+            }
+            0x5015 => {
+                self.pulse_channel_1.process_snd_chn_update(value);
+                self.pulse_channel_2.process_snd_chn_update(value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Notify the PCM channel of a CPU read, which it can use as a sample source in read mode.
+    pub(crate) fn process_cpu_read(&mut self, address: u16, value: u8) {
+        self.pcm_channel.process_cpu_read(address, value);
+    }
+
+    pub(crate) fn irq_pending(&self) -> bool {
+        self.pcm_channel.irq_pending
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        self.pulse_channel_1.tick_cpu();
+        self.pulse_channel_2.tick_cpu();
+        self.frame_counter.tick();
+
+        if self.frame_counter.generate_quarter_frame_clock() {
+            // MMC5 channels clock both length counter and envelope at 240Hz
+            self.pulse_channel_1.clock_quarter_frame();
+            self.pulse_channel_1.clock_half_frame();
+
+            self.pulse_channel_2.clock_quarter_frame();
+            self.pulse_channel_2.clock_half_frame();
+        }
+    }
+
+    pub(crate) fn mix_sample(&self, mixed_apu_sample: f64) -> f64 {
+        let pulse1_sample = self.pulse_channel_1.sample();
+        let pulse2_sample = self.pulse_channel_2.sample();
+        let mmc5_pulse_mix = apu::mix_pulse_samples(pulse1_sample, pulse2_sample);
+
+        // Partial formula from from https://www.nesdev.org/wiki/APU_Mixer
+        let pcm_sample = self.pcm_channel.output_level;
+        let scaled_pcm_sample = if pcm_sample != 0 {
+            159.79 / (1.0 / (f64::from(pcm_sample) / 22638.0) + 100.0)
+        } else {
+            0.0
+        };
+
+        mixed_apu_sample - mmc5_pulse_mix - scaled_pcm_sample
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub(crate) struct Mmc5 {
     extended_ram: [u8; 1024],
@@ -554,10 +660,7 @@ pub(crate) struct Mmc5 {
     scanline_counter: ScanlineCounter,
     extended_attributes_state: ExtendedAttributesState,
     multiplier: MultiplierUnit,
-    pulse_channel_1: PulseChannel,
-    pulse_channel_2: PulseChannel,
-    pcm_channel: PcmChannel,
-    frame_counter: FrameCounter,
+    audio: Mmc5AudioUnit,
     ram_writes_enabled_1: bool,
     ram_writes_enabled_2: bool,
     rendering_enabled: bool,
@@ -579,10 +682,7 @@ impl Mmc5 {
             scanline_counter: ScanlineCounter::new(),
             extended_attributes_state: ExtendedAttributesState::new(),
             multiplier: MultiplierUnit::new(),
-            pulse_channel_1: PulseChannel::new_channel_1(SweepStatus::Disabled),
-            pulse_channel_2: PulseChannel::new_channel_2(SweepStatus::Disabled),
-            pcm_channel: PcmChannel::new(),
-            frame_counter: FrameCounter::new(TimingMode::Ntsc),
+            audio: Mmc5AudioUnit::new(),
             ram_writes_enabled_1: false,
             ram_writes_enabled_2: false,
             rendering_enabled: false,
@@ -606,11 +706,7 @@ impl MapperImpl<Mmc5> {
 
     fn read_internal_register(&mut self, address: u16, cpu_open_bus: u8) -> u8 {
         match address {
-            0x5010 => self.data.pcm_channel.read_control(),
-            0x5015 => {
-                (u8::from(self.data.pulse_channel_2.length_counter() != 0) << 1)
-                    | u8::from(self.data.pulse_channel_1.length_counter() != 0)
-            }
+            0x5010 | 0x5015 => self.data.audio.read_register(address).unwrap_or(cpu_open_bus),
             0x5204 => {
                 log::trace!("Scanline IRQ status register read, clearing IRQ pending flag");
 
@@ -627,33 +723,8 @@ impl MapperImpl<Mmc5> {
 
     fn write_internal_register(&mut self, address: u16, value: u8) {
         match address {
-            0x5000 => {
-                self.data.pulse_channel_1.process_vol_update(value);
-            }
-            0x5002 => {
-                self.data.pulse_channel_1.process_lo_update(value);
-            }
-            0x5003 => {
-                self.data.pulse_channel_1.process_hi_update(value);
-            }
-            0x5004 => {
-                self.data.pulse_channel_2.process_vol_update(value);
-            }
-            0x5006 => {
-                self.data.pulse_channel_2.process_lo_update(value);
-            }
-            0x5007 => {
-                self.data.pulse_channel_2.process_hi_update(value);
-            }
-            0x5010 => {
-                self.data.pcm_channel.process_control_update(value);
-            }
-            0x5011 => {
-                self.data.pcm_channel.process_raw_pcm_update(value);
-            }
-            0x5015 => {
-                self.data.pulse_channel_1.process_snd_chn_update(value);
-                self.data.pulse_channel_2.process_snd_chn_update(value);
+            0x5000..=0x5015 => {
+                self.data.audio.write_register(address, value);
             }
             0x5100 => {
                 self.data.prg_banking_mode = match value & 0x03 {
@@ -801,7 +872,7 @@ impl MapperImpl<Mmc5> {
                     .read(&self.cartridge)
                     .unwrap_or(cpu_open_bus);
 
-                self.data.pcm_channel.process_cpu_read(address, value);
+                self.data.audio.process_cpu_read(address, value);
 
                 value
             }
@@ -980,39 +1051,15 @@ impl MapperImpl<Mmc5> {
     }
 
     pub(crate) fn interrupt_flag(&self) -> bool {
-        self.data.scanline_counter.interrupt_flag() || self.data.pcm_channel.irq_pending
+        self.data.scanline_counter.interrupt_flag() || self.data.audio.irq_pending()
     }
 
     pub(crate) fn tick_cpu(&mut self) {
         self.data.scanline_counter.tick_cpu();
-
-        self.data.pulse_channel_1.tick_cpu();
-        self.data.pulse_channel_2.tick_cpu();
-        self.data.frame_counter.tick();
-
-        if self.data.frame_counter.generate_quarter_frame_clock() {
-            // MMC5 channels clock both length counter and envelope at 240Hz
-            self.data.pulse_channel_1.clock_quarter_frame();
-            self.data.pulse_channel_1.clock_half_frame();
-
-            self.data.pulse_channel_2.clock_quarter_frame();
-            self.data.pulse_channel_2.clock_half_frame();
-        }
+        self.data.audio.tick_cpu();
     }
 
     pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
-        let pulse1_sample = self.data.pulse_channel_1.sample();
-        let pulse2_sample = self.data.pulse_channel_2.sample();
-        let mmc5_pulse_mix = apu::mix_pulse_samples(pulse1_sample, pulse2_sample);
-
-        // Partial formula from from https://www.nesdev.org/wiki/APU_Mixer
-        let pcm_sample = self.data.pcm_channel.output_level;
-        let scaled_pcm_sample = if pcm_sample != 0 {
-            159.79 / (1.0 / (f64::from(pcm_sample) / 22638.0) + 100.0)
-        } else {
-            0.0
-        };
-
-        mixed_apu_sample - mmc5_pulse_mix - scaled_pcm_sample
+        self.data.audio.mix_sample(mixed_apu_sample)
     }
 }
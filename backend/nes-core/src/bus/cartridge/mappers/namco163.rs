@@ -113,8 +113,8 @@ impl Namco163AudioChannel {
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
-struct Namco163AudioUnit {
-    enabled: bool,
+pub(crate) struct Namco163AudioUnit {
+    pub(crate) enabled: bool,
     channels: [Namco163AudioChannel; 8],
     divider: u8,
     current_channel: u8,
@@ -122,7 +122,7 @@ struct Namco163AudioUnit {
 }
 
 impl Namco163AudioUnit {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             enabled: false,
             channels: array::from_fn(|i| Namco163AudioChannel::new(i as u8)),
@@ -132,7 +132,7 @@ impl Namco163AudioUnit {
         }
     }
 
-    fn process_internal_ram_update(&mut self, address: u8, value: u8) {
+    pub(crate) fn process_internal_ram_update(&mut self, address: u8, value: u8) {
         if address == 0x7F {
             // Bits 6-4 of $7F control which channels are enabled in addition to channel 8 volume
             self.enabled_channel_count = ((value & 0x70) >> 4) + 1;
@@ -153,7 +153,7 @@ impl Namco163AudioUnit {
         self.channels[self.current_channel as usize].clock(internal_ram);
     }
 
-    fn tick_cpu(&mut self, internal_ram: &mut [u8; 128]) {
+    pub(crate) fn tick_cpu(&mut self, internal_ram: &mut [u8; 128]) {
         self.divider -= 1;
         if self.divider == 0 {
             self.clock(internal_ram);
@@ -177,10 +177,19 @@ impl Namco163AudioUnit {
             channel_sum / f64::from(self.enabled_channel_count)
         }
     }
+
+    pub(crate) fn mix_sample(&self, mixed_apu_sample: f64, volume_variant: VolumeVariantDb) -> f64 {
+        if !self.enabled {
+            return mixed_apu_sample;
+        }
+
+        let n163_sample = self.sample() * volume_variant.n163_coefficient();
+        (mixed_apu_sample + n163_sample).clamp(-1.0, 1.0)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
-enum VolumeVariantDb {
+pub(crate) enum VolumeVariantDb {
     Twelve,
     Sixteen,
     Eighteen,
@@ -393,12 +402,7 @@ impl MapperImpl<Namco163> {
     }
 
     pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
-        if !self.data.audio.enabled {
-            return mixed_apu_sample;
-        }
-
-        let n163_sample = self.data.audio.sample() * self.data.volume_variant.n163_coefficient();
-        (mixed_apu_sample + n163_sample).clamp(-1.0, 1.0)
+        self.data.audio.mix_sample(mixed_apu_sample, self.data.volume_variant)
     }
 }
 
@@ -0,0 +1,768 @@
+//! Code for playing NSF music files (`.nsf` / `.nsfe`).
+//!
+//! NSF files contain a music driver ripped from a game, along with addresses of an init routine
+//! that selects a song and a play routine that should be called at a fixed rate (usually 60Hz).
+//! This "mapper" maps the program data to $8000-$FFFF using the NSF bank-switching scheme and
+//! provides a tiny driver program at $4100-$41FF that calls the init and play routines. The play
+//! routine is called from an IRQ handler driven by a timer in the mapper.
+//!
+//! Expansion audio is emulated for any chips that the NSF header declares, reusing the audio units
+//! from the corresponding mappers.
+//!
+//! NSFs that use FDS audio instead have 32KB of RAM at $6000-$DFFF, as on the FDS RAM adapter.
+//! Bank-switching into that range copies the selected bank into RAM, and $5FF6/$5FF7 additionally
+//! select the banks for $6000-$7FFF.
+
+mod file;
+
+use crate::audio;
+use crate::bus::cartridge::mappers::fds::FdsAudioUnit;
+use crate::bus::cartridge::mappers::konami::{Vrc6AudioUnit, mix_vrc7_sample, new_vrc7_audio_unit};
+use crate::bus::cartridge::mappers::mmc5::{Mmc5AudioUnit, MultiplierUnit};
+use crate::bus::cartridge::mappers::namco163::{Namco163AudioUnit, VolumeVariantDb};
+use crate::bus::cartridge::mappers::sunsoft::Sunsoft5bAudioUnit;
+use crate::bus::cartridge::mappers::{BankSizeKb, NametableMirroring, PpuMapResult};
+use crate::bus::cartridge::{CartridgeFileError, HasBasicPpuMapping, MapperImpl};
+use bincode::{Decode, Encode};
+use jgenesis_common::frontend::TimingMode;
+use jgenesis_common::num::GetBit;
+use nes_config::NesJoypadState;
+use std::mem;
+use ym_opll::Vrc7AudioUnit;
+
+pub(crate) use file::{NsfFile, NsfMetadata, NsfRegion, is_nsf_file};
+
+pub(crate) const PRG_RAM_LEN: usize = 8 * 1024;
+pub(crate) const FDS_PRG_RAM_LEN: usize = 32 * 1024;
+pub(crate) const CHR_RAM_LEN: usize = 8 * 1024;
+
+const DRIVER_START: u16 = 0x4100;
+const DRIVER_END: u16 = 0x41FF;
+
+// Driver registers
+const CURRENT_SONG_ADDR: u16 = 0x41F0;
+const REGION_ADDR: u16 = 0x41F1;
+const PLAY_TIMER_ADDR: u16 = 0x41F2;
+const INIT_ADDRESS_ADDR: u16 = 0x41F4;
+const PLAY_ADDRESS_ADDR: u16 = 0x41F6;
+
+const RESET_VECTOR: u16 = 0x4100;
+const IRQ_VECTOR: u16 = 0x4153;
+const NMI_VECTOR: u16 = 0x4166;
+
+// 6502 driver program, mapped to $4100
+#[rustfmt::skip]
+const DRIVER_CODE: &[u8] = &[
+    // $4100: Reset handler; initialize CPU, disable PPU rendering and NMIs, clear RAM
+    0x78,                   // SEI
+    0xD8,                   // CLD
+    0xA2, 0xFF,             // LDX #$FF
+    0x9A,                   // TXS
+    0xA9, 0x00,             // LDA #$00
+    0x8D, 0x00, 0x20,       // STA $2000
+    0x8D, 0x01, 0x20,       // STA $2001
+    0xAA,                   // TAX
+    // $410E: RAM clear loop
+    0x95, 0x00,             // STA $00,X
+    0x9D, 0x00, 0x01,       // STA $0100,X
+    0x9D, 0x00, 0x02,       // STA $0200,X
+    0x9D, 0x00, 0x03,       // STA $0300,X
+    0x9D, 0x00, 0x04,       // STA $0400,X
+    0x9D, 0x00, 0x05,       // STA $0500,X
+    0x9D, 0x00, 0x06,       // STA $0600,X
+    0x9D, 0x00, 0x07,       // STA $0700,X
+    0xE8,                   // INX
+    0xD0, 0xE6,             // BNE $410E
+    // $4128: Clear APU registers $4000-$4013
+    0xA2, 0x13,             // LDX #$13
+    0x9D, 0x00, 0x40,       // STA $4000,X
+    0xCA,                   // DEX
+    0x10, 0xFA,             // BPL $412A
+    0x8D, 0x15, 0x40,       // STA $4015
+    0xA9, 0x0F,             // LDA #$0F
+    0x8D, 0x15, 0x40,       // STA $4015
+    0xA9, 0x40,             // LDA #$40
+    0x8D, 0x17, 0x40,       // STA $4017
+    // $413D: Call init routine with song in A and region in X, then start the play timer
+    0xAD, 0xF0, 0x41,       // LDA $41F0
+    0xAE, 0xF1, 0x41,       // LDX $41F1
+    0x20, 0x4D, 0x41,       // JSR $414D
+    0x8D, 0xF2, 0x41,       // STA $41F2
+    0x58,                   // CLI
+    // $414A: Idle loop; the play routine is called from the IRQ handler
+    0x4C, 0x4A, 0x41,       // JMP $414A
+    // $414D: Init trampoline
+    0x6C, 0xF4, 0x41,       // JMP ($41F4)
+    // $4150: Play trampoline
+    0x6C, 0xF6, 0x41,       // JMP ($41F6)
+    // $4153: IRQ handler; reading $41F2 acknowledges the play timer IRQ
+    0x48,                   // PHA
+    0x8A,                   // TXA
+    0x48,                   // PHA
+    0x98,                   // TYA
+    0x48,                   // PHA
+    0xAD, 0xF2, 0x41,       // LDA $41F2
+    0xF0, 0x03,             // BEQ $4160
+    0x20, 0x50, 0x41,       // JSR $4150
+    // $4160
+    0x68,                   // PLA
+    0xA8,                   // TAY
+    0x68,                   // PLA
+    0xAA,                   // TAX
+    0x68,                   // PLA
+    0x40,                   // RTI
+    // $4166: NMI handler
+    0x40,                   // RTI
+];
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct PlayTimer {
+    period: f64,
+    counter: f64,
+    running: bool,
+    pending: bool,
+}
+
+impl PlayTimer {
+    fn new(play_speed_us: u16, timing_mode: TimingMode) -> Self {
+        let cpu_frequency = match timing_mode {
+            TimingMode::Ntsc => audio::NTSC_NES_AUDIO_FREQUENCY,
+            TimingMode::Pal => audio::PAL_NES_AUDIO_FREQUENCY,
+        };
+        let period = f64::from(play_speed_us) * cpu_frequency / 1_000_000.0;
+
+        Self { period, counter: period, running: false, pending: false }
+    }
+
+    fn start(&mut self) {
+        self.running = true;
+        self.counter = self.period;
+    }
+
+    fn tick_cpu(&mut self) {
+        if !self.running {
+            return;
+        }
+
+        self.counter -= 1.0;
+        if self.counter <= 0.0 {
+            self.counter += self.period;
+            self.pending = true;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct Mmc5Expansion {
+    audio: Mmc5AudioUnit,
+    extended_ram: [u8; 1024],
+    multiplier: MultiplierUnit,
+}
+
+impl Mmc5Expansion {
+    fn new() -> Self {
+        Self {
+            audio: Mmc5AudioUnit::new(),
+            extended_ram: [0; 1024],
+            multiplier: MultiplierUnit::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct Namco163Expansion {
+    audio: Namco163AudioUnit,
+    internal_ram: [u8; 128],
+    internal_ram_addr: u8,
+    internal_ram_auto_increment: bool,
+}
+
+impl Namco163Expansion {
+    fn new() -> Self {
+        let mut audio = Namco163AudioUnit::new();
+        // The N163 sound disable bit is not used in NSFs
+        audio.enabled = true;
+
+        Self {
+            audio,
+            internal_ram: [0; 128],
+            internal_ram_addr: 0,
+            internal_ram_auto_increment: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct ExpansionAudio {
+    vrc6: Option<Vrc6AudioUnit>,
+    vrc7: Option<Vrc7AudioUnit>,
+    fds: Option<FdsAudioUnit>,
+    mmc5: Option<Mmc5Expansion>,
+    namco_163: Option<Namco163Expansion>,
+    sunsoft_5b: Option<Sunsoft5bAudioUnit>,
+}
+
+impl ExpansionAudio {
+    fn new(chips: file::ExpansionChips) -> Self {
+        Self {
+            vrc6: chips.vrc6.then(Vrc6AudioUnit::new),
+            vrc7: chips.vrc7.then(new_vrc7_audio_unit),
+            fds: chips.fds.then(FdsAudioUnit::new),
+            mmc5: chips.mmc5.then(Mmc5Expansion::new),
+            namco_163: chips.namco_163.then(Namco163Expansion::new),
+            sunsoft_5b: chips.sunsoft_5b.then(Sunsoft5bAudioUnit::new),
+        }
+    }
+
+    fn read_register(&mut self, address: u16, cpu_open_bus: u8) -> Option<u8> {
+        if let Some(fds) = &self.fds
+            && let Some(value) = fds.read_register(address, cpu_open_bus)
+        {
+            return Some(value);
+        }
+
+        if let Some(mmc5) = &mut self.mmc5 {
+            match address {
+                0x5010 | 0x5015 => return mmc5.audio.read_register(address),
+                0x5205 => return Some((mmc5.multiplier.output() & 0x00FF) as u8),
+                0x5206 => return Some((mmc5.multiplier.output() >> 8) as u8),
+                0x5C00..=0x5FF5 => return Some(mmc5.extended_ram[(address - 0x5C00) as usize]),
+                _ => {}
+            }
+        }
+
+        if let Some(namco_163) = &mut self.namco_163
+            && (0x4800..=0x4FFF).contains(&address)
+        {
+            let byte = namco_163.internal_ram[namco_163.internal_ram_addr as usize];
+            if namco_163.internal_ram_auto_increment {
+                namco_163.internal_ram_addr = (namco_163.internal_ram_addr + 1) & 0x7F;
+            }
+            return Some(byte);
+        }
+
+        None
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.write_register(address, value);
+        }
+
+        if let Some(vrc7) = &mut self.vrc7 {
+            match address {
+                0x9010 => vrc7.select_register(value),
+                0x9030 => vrc7.write_data(value),
+                _ => {}
+            }
+        }
+
+        if let Some(fds) = &mut self.fds {
+            fds.write_register(address, value);
+        }
+
+        if let Some(mmc5) = &mut self.mmc5 {
+            match address {
+                0x5000..=0x5015 => mmc5.audio.write_register(address, value),
+                0x5205 => mmc5.multiplier.operand_l = value.into(),
+                0x5206 => mmc5.multiplier.operand_r = value.into(),
+                0x5C00..=0x5FF5 => mmc5.extended_ram[(address - 0x5C00) as usize] = value,
+                _ => {}
+            }
+        }
+
+        if let Some(namco_163) = &mut self.namco_163 {
+            match address {
+                0x4800..=0x4FFF => {
+                    let ram_addr = namco_163.internal_ram_addr;
+                    namco_163.internal_ram[ram_addr as usize] = value;
+                    namco_163.audio.process_internal_ram_update(ram_addr, value);
+                    if namco_163.internal_ram_auto_increment {
+                        namco_163.internal_ram_addr = (ram_addr + 1) & 0x7F;
+                    }
+                }
+                0xF800 => {
+                    namco_163.internal_ram_addr = value & 0x7F;
+                    namco_163.internal_ram_auto_increment = value.bit(7);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(sunsoft_5b) = &mut self.sunsoft_5b {
+            match address {
+                0xC000 => sunsoft_5b.handle_select_update(value),
+                0xE000 => sunsoft_5b.handle_write(value),
+                _ => {}
+            }
+        }
+    }
+
+    fn process_cpu_read(&mut self, address: u16, value: u8) {
+        if let Some(mmc5) = &mut self.mmc5 {
+            mmc5.audio.process_cpu_read(address, value);
+        }
+    }
+
+    fn tick_cpu(&mut self) {
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.tick_cpu();
+        }
+
+        if let Some(vrc7) = &mut self.vrc7 {
+            vrc7.tick();
+        }
+
+        if let Some(fds) = &mut self.fds {
+            fds.tick_cpu();
+        }
+
+        if let Some(mmc5) = &mut self.mmc5 {
+            mmc5.audio.tick_cpu();
+        }
+
+        if let Some(namco_163) = &mut self.namco_163 {
+            namco_163.audio.tick_cpu(&mut namco_163.internal_ram);
+        }
+
+        if let Some(sunsoft_5b) = &mut self.sunsoft_5b {
+            sunsoft_5b.tick_cpu();
+        }
+    }
+
+    fn mix_sample(&self, mixed_apu_sample: f64) -> f64 {
+        let mut sample = mixed_apu_sample;
+
+        if let Some(vrc6) = &self.vrc6 {
+            sample = vrc6.mix_sample(sample);
+        }
+
+        if let Some(vrc7) = &self.vrc7 {
+            sample = mix_vrc7_sample(vrc7, sample);
+        }
+
+        if let Some(fds) = &self.fds {
+            sample += 0.358 * fds.sample();
+        }
+
+        if let Some(mmc5) = &self.mmc5 {
+            sample = mmc5.audio.mix_sample(sample);
+        }
+
+        if let Some(namco_163) = &self.namco_163 {
+            sample = namco_163.audio.mix_sample(sample, VolumeVariantDb::Twelve);
+        }
+
+        if let Some(sunsoft_5b) = &self.sunsoft_5b {
+            sample = sunsoft_5b.mix_sample(sample);
+        }
+
+        sample.clamp(-1.0, 1.0)
+    }
+}
+
+/// Current player state, used to render the track info display.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NsfPlayerInfo<'a> {
+    pub(crate) metadata: &'a NsfMetadata,
+    pub(crate) track_number: usize,
+    pub(crate) track_count: usize,
+    pub(crate) track_label: Option<&'a str>,
+    pub(crate) elapsed_seconds: u64,
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub(crate) struct Nsf {
+    init_address: u16,
+    play_address: u16,
+    banked: bool,
+    initial_banks: [u8; 8],
+    // Initial banks for $6000-$7FFF in FDS NSFs
+    initial_fds_ram_banks: [u8; 2],
+    prg_banks: [u8; 8],
+    play_speed_us: u16,
+    play_timer: PlayTimer,
+    // Song numbers in playback order; either 0..total_songs or the NSFe playlist
+    tracks: Vec<u8>,
+    track_index: usize,
+    elapsed_cycles: u64,
+    chips: file::ExpansionChips,
+    audio: ExpansionAudio,
+    prev_joypad_state: NesJoypadState,
+    metadata: NsfMetadata,
+}
+
+impl Nsf {
+    pub(crate) fn new(nsf_file: NsfFile, timing_mode: TimingMode) -> Self {
+        let (banked, initial_banks, initial_fds_ram_banks) =
+            match (nsf_file.initial_banks, nsf_file.chips.fds) {
+                // FDS NSFs use the $E000/$F000 banks for $6000/$7000 at startup
+                (Some(initial_banks), _) => {
+                    (true, initial_banks, [initial_banks[6], initial_banks[7]])
+                }
+                (None, false) => (false, [0, 1, 2, 3, 4, 5, 6, 7], [0, 0]),
+                // Non-banked FDS NSFs are laid out from $6000 rather than $8000
+                (None, true) => (false, [2, 3, 4, 5, 6, 7, 8, 9], [0, 1]),
+            };
+
+        let play_speed_us = match timing_mode {
+            TimingMode::Ntsc => nsf_file.ntsc_play_speed_us,
+            TimingMode::Pal => nsf_file.pal_play_speed_us,
+        };
+
+        let tracks = nsf_file.playlist.unwrap_or_else(|| (0..nsf_file.total_songs).collect());
+        let track_index =
+            tracks.iter().position(|&song| song == nsf_file.starting_song).unwrap_or(0);
+
+        Self {
+            init_address: nsf_file.init_address,
+            play_address: nsf_file.play_address,
+            banked,
+            initial_banks,
+            initial_fds_ram_banks,
+            prg_banks: initial_banks,
+            play_speed_us,
+            play_timer: PlayTimer::new(play_speed_us, timing_mode),
+            tracks,
+            track_index,
+            elapsed_cycles: 0,
+            chips: nsf_file.chips,
+            audio: ExpansionAudio::new(nsf_file.chips),
+            prev_joypad_state: NesJoypadState::default(),
+            metadata: nsf_file.metadata,
+        }
+    }
+}
+
+/// Lay out NSF program data into a PRG ROM image of 4KB banks.
+///
+/// # Errors
+///
+/// Returns an error if the load address is invalid.
+pub(crate) fn build_prg_rom(nsf_file: &NsfFile) -> Result<Vec<u8>, CartridgeFileError> {
+    // FDS NSFs can load into RAM at $6000-$7FFF
+    let base_address: u16 = if nsf_file.chips.fds { 0x6000 } else { 0x8000 };
+    if nsf_file.load_address < base_address {
+        return Err(CartridgeFileError::InvalidNsf {
+            reason: if nsf_file.chips.fds {
+                "load address is below $6000"
+            } else {
+                "load address is below $8000"
+            },
+        });
+    }
+
+    let mut prg_rom = if nsf_file.initial_banks.is_some() {
+        // Bank 0 starts at the 4KB boundary at or below the load address
+        let padding = (nsf_file.load_address & 0x0FFF) as usize;
+        let mut prg_rom = vec![0; padding + nsf_file.data.len()];
+        prg_rom[padding..].copy_from_slice(&nsf_file.data);
+        prg_rom
+    } else {
+        // Non-banked NSFs are loaded directly into the address space starting at $8000 (or $6000
+        // for FDS); anything past $FFFF is truncated
+        let space_len = 0x10000 - usize::from(base_address);
+        let offset = (nsf_file.load_address - base_address) as usize;
+        let len = nsf_file.data.len().min(space_len - offset);
+        let mut prg_rom = vec![0; space_len];
+        prg_rom[offset..offset + len].copy_from_slice(&nsf_file.data[..len]);
+        prg_rom
+    };
+
+    // Bank numbers are masked by PRG ROM length, so size needs to be a power of two
+    let len = prg_rom.len().max(0x1000).next_power_of_two();
+    prg_rom.resize(len, 0);
+
+    Ok(prg_rom)
+}
+
+impl MapperImpl<Nsf> {
+    fn read_driver(&mut self, address: u16, cpu_open_bus: u8) -> u8 {
+        match address {
+            CURRENT_SONG_ADDR => self.data.tracks[self.data.track_index],
+            REGION_ADDR => match self.cartridge.timing_mode {
+                TimingMode::Ntsc => 0,
+                TimingMode::Pal => 1,
+            },
+            PLAY_TIMER_ADDR => {
+                let pending = self.data.play_timer.pending;
+                self.data.play_timer.pending = false;
+                pending.into()
+            }
+            INIT_ADDRESS_ADDR => self.data.init_address as u8,
+            0x41F5 => (self.data.init_address >> 8) as u8,
+            PLAY_ADDRESS_ADDR => self.data.play_address as u8,
+            0x41F7 => (self.data.play_address >> 8) as u8,
+            _ => {
+                DRIVER_CODE.get((address - DRIVER_START) as usize).copied().unwrap_or(cpu_open_bus)
+            }
+        }
+    }
+
+    pub(crate) fn read_cpu_address(&mut self, address: u16, cpu_open_bus: u8) -> u8 {
+        match address {
+            0x0000..=0x401F => panic!("invalid CPU map address: {address:04X}"),
+            DRIVER_START..=DRIVER_END => self.read_driver(address, cpu_open_bus),
+            0x4020..=0x5FFF => {
+                self.data.audio.read_register(address, cpu_open_bus).unwrap_or(cpu_open_bus)
+            }
+            0x6000..=0xDFFF if self.data.chips.fds => {
+                self.cartridge.get_prg_ram((address - 0x6000).into())
+            }
+            0x6000..=0x7FFF => self.cartridge.get_prg_ram((address & 0x1FFF).into()),
+            0x8000..=0xFFF9 => {
+                let bank_number = self.data.prg_banks[((address - 0x8000) >> 12) as usize];
+                let prg_rom_addr = BankSizeKb::Four.to_absolute_address(bank_number, address);
+                let value = self.cartridge.get_prg_rom(prg_rom_addr);
+                self.data.audio.process_cpu_read(address, value);
+                value
+            }
+            0xFFFA..=0xFFFF => {
+                let vector = match address & !1 {
+                    0xFFFA => NMI_VECTOR,
+                    0xFFFC => RESET_VECTOR,
+                    _ => IRQ_VECTOR,
+                };
+                if address.bit(0) { (vector >> 8) as u8 } else { vector as u8 }
+            }
+        }
+    }
+
+    pub(crate) fn write_cpu_address(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x401F => panic!("invalid CPU map address: {address:04X}"),
+            PLAY_TIMER_ADDR => {
+                // Written by the driver after the init routine returns
+                self.data.play_timer.start();
+            }
+            0x5FF6..=0x5FFF if self.data.banked && self.data.chips.fds => {
+                self.switch_fds_bank((address - 0x5FF6) as usize, value);
+            }
+            0x5FF8..=0x5FFF if self.data.banked => {
+                self.data.prg_banks[(address - 0x5FF8) as usize] = value;
+            }
+            0x6000..=0xDFFF if self.data.chips.fds => {
+                self.cartridge.set_prg_ram((address - 0x6000).into(), value);
+                // Expansion audio registers can overlap FDS RAM
+                self.data.audio.write_register(address, value);
+            }
+            0x6000..=0x7FFF => {
+                self.cartridge.set_prg_ram((address & 0x1FFF).into(), value);
+            }
+            _ => {
+                self.data.audio.write_register(address, value);
+            }
+        }
+    }
+
+    pub(crate) fn tick_cpu(&mut self) {
+        self.data.play_timer.tick_cpu();
+        self.data.audio.tick_cpu();
+
+        if self.data.play_timer.running {
+            self.data.elapsed_cycles += 1;
+        }
+    }
+
+    pub(crate) fn interrupt_flag(&self) -> bool {
+        self.data.play_timer.pending
+    }
+
+    pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
+        self.data.audio.mix_sample(mixed_apu_sample)
+    }
+
+    /// Reset playback state so that the driver can initialize the current track from scratch.
+    pub(crate) fn reset(&mut self) {
+        self.cartridge.prg_ram.fill(0);
+
+        self.data.prg_banks = self.data.initial_banks;
+        self.load_initial_fds_ram_banks();
+        self.data.play_timer = PlayTimer::new(self.data.play_speed_us, self.cartridge.timing_mode);
+        self.data.elapsed_cycles = 0;
+        self.data.audio = ExpansionAudio::new(self.data.chips);
+    }
+
+    /// Copy the initial banks into FDS RAM at $6000-$DFFF. Does nothing if this is not an FDS NSF.
+    pub(crate) fn load_initial_fds_ram_banks(&mut self) {
+        if !self.data.chips.fds {
+            return;
+        }
+
+        let [bank_6000, bank_7000] = self.data.initial_fds_ram_banks;
+        let banks = [bank_6000, bank_7000].into_iter().chain(self.data.initial_banks);
+        for (slot, bank) in banks.enumerate() {
+            self.switch_fds_bank(slot, bank);
+        }
+    }
+
+    // Slot 0 is $6000-$6FFF and slot 9 is $F000-$FFFF; slots below $E000 are RAM
+    fn switch_fds_bank(&mut self, slot: usize, bank_number: u8) {
+        if slot >= 2 {
+            self.data.prg_banks[slot - 2] = bank_number;
+        }
+
+        if slot < 8 {
+            for offset in 0..0x1000 {
+                let prg_rom_addr = BankSizeKb::Four.to_absolute_address(bank_number, offset as u16);
+                let value = self.cartridge.get_prg_rom(prg_rom_addr);
+                self.cartridge.set_prg_ram((slot * 0x1000 + offset) as u32, value);
+            }
+        }
+    }
+
+    /// Select a track by its index in playback order. Returns false if the index is invalid.
+    ///
+    /// The emulator must be reset for the track change to take effect.
+    pub(crate) fn select_track(&mut self, track_index: usize) -> bool {
+        if track_index >= self.data.tracks.len() {
+            return false;
+        }
+
+        self.data.track_index = track_index;
+        true
+    }
+
+    pub(crate) fn next_track(&mut self) {
+        self.data.track_index = (self.data.track_index + 1) % self.data.tracks.len();
+    }
+
+    pub(crate) fn previous_track(&mut self) {
+        let track_count = self.data.tracks.len();
+        self.data.track_index = (self.data.track_index + track_count - 1) % track_count;
+    }
+
+    /// Process P1 joypad state. Returns true if the current track should be (re)started.
+    ///
+    /// Right/A go to the next track, Left/B go to the previous track, and Start restarts the
+    /// current track.
+    pub(crate) fn process_joypad_input(&mut self, joypad_state: NesJoypadState) -> bool {
+        let prev = mem::replace(&mut self.data.prev_joypad_state, joypad_state);
+        let pressed = |current: bool, previous: bool| current && !previous;
+
+        if pressed(joypad_state.right, prev.right) || pressed(joypad_state.a, prev.a) {
+            self.next_track();
+            true
+        } else if pressed(joypad_state.left, prev.left) || pressed(joypad_state.b, prev.b) {
+            self.previous_track();
+            true
+        } else {
+            pressed(joypad_state.start, prev.start)
+        }
+    }
+
+    pub(crate) fn player_info(&self) -> NsfPlayerInfo<'_> {
+        let cpu_frequency = match self.cartridge.timing_mode {
+            TimingMode::Ntsc => audio::NTSC_NES_AUDIO_FREQUENCY,
+            TimingMode::Pal => audio::PAL_NES_AUDIO_FREQUENCY,
+        };
+
+        let song = self.data.tracks[self.data.track_index];
+        let track_label = self
+            .data
+            .metadata
+            .track_labels
+            .get(song as usize)
+            .map(String::as_str)
+            .filter(|label| !label.is_empty());
+
+        NsfPlayerInfo {
+            metadata: &self.data.metadata,
+            track_number: self.data.track_index + 1,
+            track_count: self.data.tracks.len(),
+            track_label,
+            elapsed_seconds: (self.data.elapsed_cycles as f64 / cpu_frequency) as u64,
+        }
+    }
+
+    pub(crate) fn move_metadata_from(&mut self, other: &mut Self) {
+        self.data.metadata = mem::take(&mut other.data.metadata);
+    }
+}
+
+impl HasBasicPpuMapping for MapperImpl<Nsf> {
+    fn map_ppu_address(&self, address: u16) -> PpuMapResult {
+        match address {
+            0x0000..=0x1FFF => PpuMapResult::ChrRAM(address.into()),
+            0x2000..=0x3EFF => {
+                PpuMapResult::Vram(NametableMirroring::Vertical.map_to_vram(address))
+            }
+            0x3F00..=0xFFFF => panic!("invalid PPU map address: {address:04X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::cartridge::{self, Mapper};
+
+    fn fds_nsf_mapper(load_address: u16, initial_banks: [u8; 8], data: &[u8]) -> MapperImpl<Nsf> {
+        let mut file = vec![0; 0x80];
+        file[..5].copy_from_slice(b"NESM\x1A");
+        file[0x05] = 1;
+        file[0x06] = 1;
+        file[0x07] = 1;
+        file[0x08..0x0A].copy_from_slice(&load_address.to_le_bytes());
+        file[0x70..0x78].copy_from_slice(&initial_banks);
+        file[0x7B] = 0x04;
+        file.extend_from_slice(data);
+
+        match cartridge::from_nsf_file(&file, None).unwrap() {
+            Mapper::Nsf(mapper) => mapper,
+            _ => panic!("expected NSF mapper"),
+        }
+    }
+
+    #[test]
+    fn fds_non_banked_loads_from_6000() {
+        let data: Vec<u8> = (1..=10).flat_map(|bank| [bank; 0x1000]).collect();
+        let mut mapper = fds_nsf_mapper(0x6000, [0; 8], &data);
+
+        assert_eq!(mapper.read_cpu_address(0x6000, 0), 1);
+        assert_eq!(mapper.read_cpu_address(0x7FFF, 0), 2);
+        assert_eq!(mapper.read_cpu_address(0x8000, 0), 3);
+        assert_eq!(mapper.read_cpu_address(0xE000, 0), 9);
+
+        // $6000-$DFFF is RAM; $E000-$FFFF is not
+        mapper.write_cpu_address(0xD000, 0x55);
+        mapper.write_cpu_address(0xE000, 0x55);
+        assert_eq!(mapper.read_cpu_address(0xD000, 0), 0x55);
+        assert_eq!(mapper.read_cpu_address(0xE000, 0), 9);
+    }
+
+    #[test]
+    fn fds_bank_switching_copies_into_ram() {
+        let data: Vec<u8> = (0..8).flat_map(|bank| [bank; 0x1000]).collect();
+        let mut mapper = fds_nsf_mapper(0x8000, [0, 1, 2, 3, 4, 5, 6, 7], &data);
+
+        // $6000/$7000 start with the same banks as $E000/$F000
+        assert_eq!(mapper.read_cpu_address(0x6000, 0), 6);
+        assert_eq!(mapper.read_cpu_address(0x7000, 0), 7);
+
+        mapper.write_cpu_address(0x5FF6, 2);
+        mapper.write_cpu_address(0x5FF8, 5);
+        mapper.write_cpu_address(0x5FFE, 1);
+        assert_eq!(mapper.read_cpu_address(0x6000, 0), 2);
+        assert_eq!(mapper.read_cpu_address(0x8000, 0), 5);
+        assert_eq!(mapper.read_cpu_address(0xE000, 0), 1);
+
+        mapper.write_cpu_address(0x8000, 0xAA);
+        assert_eq!(mapper.read_cpu_address(0x8000, 0), 0xAA);
+
+        mapper.reset();
+        assert_eq!(mapper.read_cpu_address(0x6000, 0), 6);
+        assert_eq!(mapper.read_cpu_address(0x8000, 0), 0);
+    }
+
+    #[test]
+    fn fds_audio_registers() {
+        let mut mapper = fds_nsf_mapper(0x8000, [0; 8], &[0x60; 0x1000]);
+
+        // Wavetable is only readable while writes are enabled
+        mapper.write_cpu_address(0x4089, 0x80);
+        mapper.write_cpu_address(0x4040, 0x3F);
+        assert_eq!(mapper.read_cpu_address(0x4040, 0), 0x3F);
+        assert_eq!(mapper.read_cpu_address(0x4040, 0xC0), 0xFF);
+    }
+}
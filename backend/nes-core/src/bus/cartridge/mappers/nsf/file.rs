@@ -0,0 +1,505 @@
+//! Parsing for NSF music files, including the NSF2 and `NSFe` variants.
+//!
+//! References:
+//! <https://www.nesdev.org/wiki/NSF>
+//! <https://www.nesdev.org/wiki/NSF2>
+//! <https://www.nesdev.org/wiki/NSFe>
+
+use crate::bus::cartridge::CartridgeFileError;
+use bincode::{Decode, Encode};
+use jgenesis_common::num::GetBit;
+use jgenesis_proc_macros::{FakeDecode, FakeEncode};
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+
+const NSF_HEADER_LEN: usize = 0x80;
+
+// Play rates used when a file does not specify one; these are the NTSC and PAL vblank rates
+const DEFAULT_NTSC_PLAY_SPEED_US: u16 = 16639;
+const DEFAULT_PAL_PLAY_SPEED_US: u16 = 19997;
+
+pub(crate) fn is_nsf_file(file_bytes: &[u8]) -> bool {
+    file_bytes.starts_with(NSF_MAGIC) || file_bytes.starts_with(NSFE_MAGIC)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NsfRegion {
+    Ntsc,
+    Pal,
+    Dual,
+}
+
+impl NsfRegion {
+    fn from_byte(byte: u8) -> Self {
+        match (byte.bit(0), byte.bit(1)) {
+            (_, true) => Self::Dual,
+            (true, false) => Self::Pal,
+            (false, false) => Self::Ntsc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub(crate) struct ExpansionChips {
+    pub(crate) vrc6: bool,
+    pub(crate) vrc7: bool,
+    pub(crate) fds: bool,
+    pub(crate) mmc5: bool,
+    pub(crate) namco_163: bool,
+    pub(crate) sunsoft_5b: bool,
+}
+
+impl ExpansionChips {
+    fn from_byte(byte: u8) -> Self {
+        Self {
+            vrc6: byte.bit(0),
+            vrc7: byte.bit(1),
+            fds: byte.bit(2),
+            mmc5: byte.bit(3),
+            namco_163: byte.bit(4),
+            sunsoft_5b: byte.bit(5),
+        }
+    }
+
+    pub(crate) fn names(self) -> Vec<&'static str> {
+        [
+            (self.vrc6, "VRC6"),
+            (self.vrc7, "VRC7"),
+            (self.fds, "FDS"),
+            (self.mmc5, "MMC5"),
+            (self.namco_163, "N163"),
+            (self.sunsoft_5b, "5B"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect()
+    }
+}
+
+/// Informational fields that are only used for display. Not included in save states.
+#[derive(Debug, Clone, Default, FakeEncode, FakeDecode)]
+pub(crate) struct NsfMetadata {
+    pub(crate) title: String,
+    pub(crate) artist: String,
+    pub(crate) copyright: String,
+    pub(crate) track_labels: Vec<String>,
+    pub(crate) chips: ExpansionChips,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct NsfFile {
+    pub(crate) load_address: u16,
+    pub(crate) init_address: u16,
+    pub(crate) play_address: u16,
+    pub(crate) total_songs: u8,
+    // 0-based
+    pub(crate) starting_song: u8,
+    pub(crate) initial_banks: Option<[u8; 8]>,
+    pub(crate) ntsc_play_speed_us: u16,
+    pub(crate) pal_play_speed_us: u16,
+    pub(crate) region: NsfRegion,
+    pub(crate) chips: ExpansionChips,
+    // Song order from an NSFe playlist chunk, if present
+    pub(crate) playlist: Option<Vec<u8>>,
+    pub(crate) metadata: NsfMetadata,
+    pub(crate) data: Vec<u8>,
+}
+
+fn invalid(reason: &'static str) -> CartridgeFileError {
+    CartridgeFileError::InvalidNsf { reason }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+// NSF header strings are null-padded and are usually ASCII, but some files use Shift-JIS or
+// Latin-1; treat bytes as Latin-1 so that anything non-ASCII at least decodes to something
+fn parse_header_string(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let s: String = bytes[..len].iter().map(|&b| char::from(b)).collect();
+    if s == "<?>" { String::new() } else { s }
+}
+
+// NSFe strings are null-terminated UTF-8
+fn parse_nsfe_strings(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|&b| b == 0)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .skip_while(String::is_empty)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect()
+}
+
+impl NsfFile {
+    pub(crate) fn parse(file_bytes: &[u8]) -> Result<Self, CartridgeFileError> {
+        if file_bytes.starts_with(NSF_MAGIC) {
+            Self::parse_nsf(file_bytes)
+        } else if file_bytes.starts_with(NSFE_MAGIC) {
+            Self::parse_nsfe(file_bytes)
+        } else {
+            Err(CartridgeFileError::Format)
+        }
+    }
+
+    fn parse_nsf(file_bytes: &[u8]) -> Result<Self, CartridgeFileError> {
+        if file_bytes.len() <= NSF_HEADER_LEN {
+            return Err(invalid("file is too short"));
+        }
+
+        let header = &file_bytes[..NSF_HEADER_LEN];
+
+        let version = header[0x05];
+        let total_songs = header[0x06];
+        if total_songs == 0 {
+            return Err(invalid("file contains no songs"));
+        }
+        let starting_song = header[0x07].saturating_sub(1).min(total_songs - 1);
+
+        let initial_banks: [u8; 8] = header[0x70..0x78].try_into().unwrap();
+        let initial_banks = initial_banks.iter().any(|&bank| bank != 0).then_some(initial_banks);
+
+        let ntsc_play_speed_us = match read_u16(header, 0x6E) {
+            0 => DEFAULT_NTSC_PLAY_SPEED_US,
+            speed => speed,
+        };
+        let pal_play_speed_us = match read_u16(header, 0x78) {
+            0 => DEFAULT_PAL_PLAY_SPEED_US,
+            speed => speed,
+        };
+
+        // NSF2 files can specify the program data length, in which case NSFe metadata chunks
+        // follow the program data
+        let data_len = u32::from_le_bytes([header[0x7D], header[0x7E], header[0x7F], 0]) as usize;
+        let (data, trailing_chunks) = if version >= 2 && data_len != 0 {
+            let data_end = NSF_HEADER_LEN + data_len;
+            if data_end > file_bytes.len() {
+                return Err(invalid("program data length is larger than file"));
+            }
+            (&file_bytes[NSF_HEADER_LEN..data_end], &file_bytes[data_end..])
+        } else {
+            (&file_bytes[NSF_HEADER_LEN..], &[][..])
+        };
+
+        let chips = ExpansionChips::from_byte(header[0x7B]);
+
+        let mut nsf = Self {
+            load_address: read_u16(header, 0x08),
+            init_address: read_u16(header, 0x0A),
+            play_address: read_u16(header, 0x0C),
+            total_songs,
+            starting_song,
+            initial_banks,
+            ntsc_play_speed_us,
+            pal_play_speed_us,
+            region: NsfRegion::from_byte(header[0x7A]),
+            chips,
+            playlist: None,
+            metadata: NsfMetadata {
+                title: parse_header_string(&header[0x0E..0x2E]),
+                artist: parse_header_string(&header[0x2E..0x4E]),
+                copyright: parse_header_string(&header[0x4E..0x6E]),
+                track_labels: vec![],
+                chips,
+            },
+            data: data.to_vec(),
+        };
+
+        if !trailing_chunks.is_empty() {
+            for chunk in NsfeChunks::new(trailing_chunks) {
+                let chunk = chunk?;
+                // Only metadata chunks are valid after NSF2 program data
+                if !matches!(&chunk.id, b"INFO" | b"DATA" | b"BANK") {
+                    nsf.apply_metadata_chunk(chunk)?;
+                }
+            }
+        }
+
+        nsf.filter_playlist();
+
+        Ok(nsf)
+    }
+
+    fn parse_nsfe(file_bytes: &[u8]) -> Result<Self, CartridgeFileError> {
+        let mut nsf = Self {
+            load_address: 0,
+            init_address: 0,
+            play_address: 0,
+            total_songs: 1,
+            starting_song: 0,
+            initial_banks: None,
+            ntsc_play_speed_us: DEFAULT_NTSC_PLAY_SPEED_US,
+            pal_play_speed_us: DEFAULT_PAL_PLAY_SPEED_US,
+            region: NsfRegion::Ntsc,
+            chips: ExpansionChips::default(),
+            playlist: None,
+            metadata: NsfMetadata::default(),
+            data: vec![],
+        };
+
+        let mut found_info = false;
+        let mut found_data = false;
+
+        for chunk in NsfeChunks::new(&file_bytes[NSFE_MAGIC.len()..]) {
+            let chunk = chunk?;
+            match &chunk.id {
+                b"INFO" => {
+                    let info = chunk.data;
+                    if info.len() < 9 {
+                        return Err(invalid("INFO chunk is too short"));
+                    }
+
+                    nsf.load_address = read_u16(info, 0);
+                    nsf.init_address = read_u16(info, 2);
+                    nsf.play_address = read_u16(info, 4);
+                    nsf.region = NsfRegion::from_byte(info[6]);
+                    nsf.chips = ExpansionChips::from_byte(info[7]);
+                    nsf.metadata.chips = nsf.chips;
+                    nsf.total_songs = info.get(8).copied().unwrap_or(1);
+                    nsf.starting_song = info.get(9).copied().unwrap_or(0);
+
+                    if nsf.total_songs == 0 {
+                        return Err(invalid("file contains no songs"));
+                    }
+                    nsf.starting_song = nsf.starting_song.min(nsf.total_songs - 1);
+
+                    found_info = true;
+                }
+                b"DATA" => {
+                    nsf.data = chunk.data.to_vec();
+                    found_data = true;
+                }
+                b"BANK" => {
+                    let mut banks = [0; 8];
+                    let len = chunk.data.len().min(8);
+                    banks[..len].copy_from_slice(&chunk.data[..len]);
+                    nsf.initial_banks = Some(banks);
+                }
+                b"NEND" => break,
+                _ => nsf.apply_metadata_chunk(chunk)?,
+            }
+        }
+
+        if !found_info {
+            return Err(invalid("missing INFO chunk"));
+        }
+
+        if !found_data {
+            return Err(invalid("missing DATA chunk"));
+        }
+
+        nsf.filter_playlist();
+
+        Ok(nsf)
+    }
+
+    // Remove playlist entries that refer to nonexistent songs
+    fn filter_playlist(&mut self) {
+        let total_songs = self.total_songs;
+        self.playlist = self.playlist.take().and_then(|playlist| {
+            let playlist: Vec<_> =
+                playlist.into_iter().filter(|&track| track < total_songs).collect();
+            (!playlist.is_empty()).then_some(playlist)
+        });
+    }
+
+    fn apply_metadata_chunk(&mut self, chunk: NsfeChunk<'_>) -> Result<(), CartridgeFileError> {
+        match &chunk.id {
+            b"RATE" => {
+                // A rate of 0 means to use the default for that region
+                let rate = chunk.data;
+                if rate.len() >= 2 && read_u16(rate, 0) != 0 {
+                    self.ntsc_play_speed_us = read_u16(rate, 0);
+                }
+                if rate.len() >= 4 && read_u16(rate, 2) != 0 {
+                    self.pal_play_speed_us = read_u16(rate, 2);
+                }
+            }
+            b"auth" => {
+                let mut strings = parse_nsfe_strings(chunk.data).into_iter();
+                self.metadata.title = strings.next().unwrap_or_default();
+                self.metadata.artist = strings.next().unwrap_or_default();
+                self.metadata.copyright = strings.next().unwrap_or_default();
+            }
+            b"tlbl" => {
+                self.metadata.track_labels = parse_nsfe_strings(chunk.data);
+            }
+            b"plst" => {
+                // Filtered against the song count once all chunks are parsed, since the playlist
+                // can come before INFO
+                self.playlist = Some(chunk.data.to_vec());
+            }
+            id => {
+                // Chunks with an uppercase first letter are required to be understood by the player
+                if id[0].is_ascii_uppercase() {
+                    log::error!(
+                        "Unrecognized required NSFe chunk: {}",
+                        String::from_utf8_lossy(id)
+                    );
+                    return Err(invalid("unrecognized required NSFe chunk"));
+                }
+
+                log::debug!("Skipping NSFe chunk: {}", String::from_utf8_lossy(id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct NsfeChunk<'a> {
+    id: [u8; 4],
+    data: &'a [u8],
+}
+
+struct NsfeChunks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> NsfeChunks<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for NsfeChunks<'a> {
+    type Item = Result<NsfeChunk<'a>, CartridgeFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        if self.bytes.len() < 8 {
+            self.bytes = &[];
+            return Some(Err(invalid("truncated NSFe chunk header")));
+        }
+
+        let len = u32::from_le_bytes(self.bytes[..4].try_into().unwrap()) as usize;
+        let id: [u8; 4] = self.bytes[4..8].try_into().unwrap();
+
+        let Some(data) = self.bytes.get(8..8 + len) else {
+            self.bytes = &[];
+            return Some(Err(invalid("truncated NSFe chunk")));
+        };
+
+        self.bytes = &self.bytes[8 + len..];
+        Some(Ok(NsfeChunk { id, data }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: [u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut bytes = (data.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&id);
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn parse_nsf_header() {
+        let mut file = vec![0; NSF_HEADER_LEN];
+        file[..5].copy_from_slice(NSF_MAGIC);
+        file[0x05] = 1;
+        file[0x06] = 12;
+        file[0x07] = 3;
+        file[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x03, 0x80, 0x06, 0x80]);
+        file[0x0E..0x13].copy_from_slice(b"Title");
+        file[0x2E..0x31].copy_from_slice(b"<?>");
+        file[0x72] = 5;
+        file[0x7B] = 0x11;
+        file.extend_from_slice(&[0xEA; 16]);
+
+        let nsf = NsfFile::parse(&file).unwrap();
+        assert_eq!(nsf.load_address, 0x8000);
+        assert_eq!(nsf.init_address, 0x8003);
+        assert_eq!(nsf.play_address, 0x8006);
+        assert_eq!(nsf.total_songs, 12);
+        assert_eq!(nsf.starting_song, 2);
+        assert_eq!(nsf.initial_banks, Some([0, 0, 5, 0, 0, 0, 0, 0]));
+        assert_eq!(nsf.ntsc_play_speed_us, DEFAULT_NTSC_PLAY_SPEED_US);
+        assert_eq!(nsf.region, NsfRegion::Ntsc);
+        assert!(nsf.chips.vrc6 && nsf.chips.namco_163 && !nsf.chips.vrc7);
+        assert_eq!(nsf.metadata.title, "Title");
+        assert_eq!(nsf.metadata.artist, "");
+        assert_eq!(nsf.data.len(), 16);
+    }
+
+    #[test]
+    fn parse_nsfe_chunks() {
+        let mut file = NSFE_MAGIC.to_vec();
+        file.extend(chunk(*b"INFO", &[0x00, 0xC0, 0x10, 0xC0, 0x20, 0xC0, 0x01, 0x20, 4, 1]));
+        file.extend(chunk(*b"DATA", &[0x60; 32]));
+        file.extend(chunk(*b"auth", b"Game\0Composer\0Company\0Ripper\0"));
+        file.extend(chunk(*b"tlbl", b"One\0Two\0Three\0Four\0"));
+        file.extend(chunk(*b"plst", &[3, 1, 9]));
+        file.extend(chunk(*b"xtra", &[1, 2, 3]));
+        file.extend(chunk(*b"NEND", &[]));
+
+        let nsf = NsfFile::parse(&file).unwrap();
+        assert_eq!(nsf.load_address, 0xC000);
+        assert_eq!(nsf.total_songs, 4);
+        assert_eq!(nsf.starting_song, 1);
+        assert_eq!(nsf.region, NsfRegion::Pal);
+        assert!(nsf.chips.sunsoft_5b);
+        assert_eq!(nsf.data, vec![0x60; 32]);
+        assert_eq!(nsf.metadata.title, "Game");
+        assert_eq!(nsf.metadata.artist, "Composer");
+        assert_eq!(nsf.metadata.copyright, "Company");
+        assert_eq!(nsf.metadata.track_labels, vec!["One", "Two", "Three", "Four"]);
+        assert_eq!(nsf.playlist, Some(vec![3, 1]));
+    }
+
+    #[test]
+    fn nsfe_zero_rate_uses_default() {
+        let mut file = NSFE_MAGIC.to_vec();
+        file.extend(chunk(*b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x00, 1]));
+        file.extend(chunk(*b"DATA", &[0x60]));
+        file.extend(chunk(*b"RATE", &[0x00, 0x00, 0x10, 0x27]));
+
+        let nsf = NsfFile::parse(&file).unwrap();
+        assert_eq!(nsf.ntsc_play_speed_us, DEFAULT_NTSC_PLAY_SPEED_US);
+        assert_eq!(nsf.pal_play_speed_us, 10000);
+    }
+
+    #[test]
+    fn nsfe_playlist_before_info() {
+        let mut file = NSFE_MAGIC.to_vec();
+        file.extend(chunk(*b"plst", &[5, 2, 7, 0]));
+        file.extend(chunk(*b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x00, 6]));
+        file.extend(chunk(*b"DATA", &[0x60]));
+
+        let nsf = NsfFile::parse(&file).unwrap();
+        assert_eq!(nsf.playlist, Some(vec![5, 2, 0]));
+    }
+
+    #[test]
+    fn nsfe_playlist_with_no_valid_songs() {
+        let mut file = NSFE_MAGIC.to_vec();
+        file.extend(chunk(*b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x00, 2]));
+        file.extend(chunk(*b"DATA", &[0x60]));
+        file.extend(chunk(*b"plst", &[2, 3]));
+
+        let nsf = NsfFile::parse(&file).unwrap();
+        assert_eq!(nsf.playlist, None);
+    }
+
+    #[test]
+    fn nsfe_unknown_required_chunk() {
+        let mut file = NSFE_MAGIC.to_vec();
+        file.extend(chunk(*b"INFO", &[0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x00, 1]));
+        file.extend(chunk(*b"DATA", &[0x60]));
+        file.extend(chunk(*b"ABCD", &[]));
+
+        assert!(NsfFile::parse(&file).is_err());
+    }
+}
@@ -113,7 +113,7 @@ impl Sunsoft5bChannel {
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
-struct Sunsoft5bAudioUnit {
+pub(crate) struct Sunsoft5bAudioUnit {
     register_select: u8,
     register_writes_enabled: bool,
     channel_1: Sunsoft5bChannel,
@@ -122,7 +122,7 @@ struct Sunsoft5bAudioUnit {
 }
 
 impl Sunsoft5bAudioUnit {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             register_select: 0,
             register_writes_enabled: false,
@@ -132,12 +132,12 @@ impl Sunsoft5bAudioUnit {
         }
     }
 
-    fn handle_select_update(&mut self, value: u8) {
+    pub(crate) fn handle_select_update(&mut self, value: u8) {
         self.register_select = value & 0x0F;
         self.register_writes_enabled = value & 0xF0 == 0;
     }
 
-    fn handle_write(&mut self, value: u8) {
+    pub(crate) fn handle_write(&mut self, value: u8) {
         if !self.register_writes_enabled {
             return;
         }
@@ -179,7 +179,7 @@ impl Sunsoft5bAudioUnit {
         }
     }
 
-    fn tick_cpu(&mut self) {
+    pub(crate) fn tick_cpu(&mut self) {
         self.channel_1.tick_cpu();
         self.channel_2.tick_cpu();
         self.channel_3.tick_cpu();
@@ -200,6 +200,15 @@ impl Sunsoft5bAudioUnit {
             || self.channel_2.volume != 0
             || self.channel_3.volume != 0
     }
+
+    pub(crate) fn mix_sample(&self, mixed_apu_sample: f64) -> f64 {
+        if !self.enabled() {
+            return mixed_apu_sample;
+        }
+
+        // This audio chip appears to slightly decrease APU channel volume
+        0.7 * mixed_apu_sample - self.sample()
+    }
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
@@ -354,14 +363,7 @@ impl MapperImpl<Sunsoft> {
     }
 
     pub(crate) fn sample_audio(&self, mixed_apu_sample: f64) -> f64 {
-        if !self.data.audio.enabled() {
-            return mixed_apu_sample;
-        }
-
-        let sunsoft_5b_sample = self.data.audio.sample();
-
-        // This audio chip appears to slightly decrease APU channel volume
-        0.7 * mixed_apu_sample - sunsoft_5b_sample
+        self.data.audio.mix_sample(mixed_apu_sample)
     }
 }
 
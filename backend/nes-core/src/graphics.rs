@@ -1,6 +1,8 @@
 mod debug;
+mod nsf;
 
 pub use debug::{PatternTable, copy_nametables, copy_oam, copy_palette_ram};
+pub(crate) use nsf::render_nsf_player;
 
 use crate::ppu;
 use crate::ppu::{ColorEmphasis, FrameBuffer};
@@ -0,0 +1,193 @@
+//! Track info display for the NSF player.
+
+use crate::bus::cartridge::NsfPlayerInfo;
+use jgenesis_common::frontend::Color;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 8;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+const LEFT_MARGIN: usize = 16;
+
+const TITLE_COLOR: Color = Color::rgb(255, 255, 255);
+const TEXT_COLOR: Color = Color::rgb(192, 192, 192);
+const ACCENT_COLOR: Color = Color::rgb(100, 176, 255);
+const DIM_COLOR: Color = Color::rgb(112, 112, 112);
+
+// Classic 5x7 LCD font covering printable ASCII ($20-$7E). Each glyph is 5 columns, left to right,
+// with the least significant bit of each column as the top row. Bit 7 is used for descenders.
+#[rustfmt::skip]
+const FONT: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x56, 0x20, 0x50], // '&'
+    [0x00, 0x08, 0x07, 0x03, 0x00], // '''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x2A, 0x1C, 0x7F, 0x1C, 0x2A], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x80, 0x70, 0x30, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x00, 0x60, 0x60, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x72, 0x49, 0x49, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x49, 0x4D, 0x33], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x31], // '6'
+    [0x41, 0x21, 0x11, 0x09, 0x07], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x46, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x00, 0x14, 0x00, 0x00], // ':'
+    [0x00, 0x40, 0x34, 0x00, 0x00], // ';'
+    [0x00, 0x08, 0x14, 0x22, 0x41], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x59, 0x09, 0x06], // '?'
+    [0x3E, 0x41, 0x5D, 0x59, 0x4E], // '@'
+    [0x7C, 0x12, 0x11, 0x12, 0x7C], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x41, 0x3E], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x41, 0x51, 0x73], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x1C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x26, 0x49, 0x49, 0x49, 0x32], // 'S'
+    [0x03, 0x01, 0x7F, 0x01, 0x03], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x03, 0x04, 0x78, 0x04, 0x03], // 'Y'
+    [0x61, 0x59, 0x49, 0x4D, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x41], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x41, 0x7F], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x03, 0x07, 0x08, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x78, 0x40], // 'a'
+    [0x7F, 0x28, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x28], // 'c'
+    [0x38, 0x44, 0x44, 0x28, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x00, 0x08, 0x7E, 0x09, 0x02], // 'f'
+    [0x18, 0xA4, 0xA4, 0x9C, 0x78], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x40, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x78, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0xFC, 0x18, 0x24, 0x24, 0x18], // 'p'
+    [0x18, 0x24, 0x24, 0x18, 0xFC], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x24], // 's'
+    [0x04, 0x04, 0x3F, 0x44, 0x24], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x4C, 0x90, 0x90, 0x90, 0x7C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x77, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x02, 0x01, 0x02, 0x04, 0x02], // '~'
+];
+
+struct TextCanvas<'a> {
+    frame_buffer: &'a mut [Color],
+    width: usize,
+    height: usize,
+}
+
+impl TextCanvas<'_> {
+    fn draw_char(&mut self, c: char, x: usize, y: usize, color: Color) {
+        // Anything outside of printable ASCII renders as '?'
+        let glyph_idx = match c {
+            ' '..='~' => c as usize - 0x20,
+            _ => '?' as usize - 0x20,
+        };
+
+        for (dx, &column) in FONT[glyph_idx].iter().enumerate() {
+            for dy in 0..GLYPH_HEIGHT {
+                if column & (1 << dy) == 0 {
+                    continue;
+                }
+
+                let (px, py) = (x + dx, y + dy);
+                if px < self.width && py < self.height {
+                    self.frame_buffer[py * self.width + px] = color;
+                }
+            }
+        }
+    }
+
+    fn draw_text(&mut self, text: &str, y: usize, color: Color) {
+        let max_chars = (self.width - 2 * LEFT_MARGIN) / CELL_WIDTH;
+        for (i, c) in text.chars().take(max_chars).enumerate() {
+            self.draw_char(c, LEFT_MARGIN + i * CELL_WIDTH, y, color);
+        }
+    }
+}
+
+/// Render the NSF player's track info screen into the given frame buffer.
+pub(crate) fn render_nsf_player(
+    info: &NsfPlayerInfo<'_>,
+    frame_buffer: &mut [Color],
+    width: usize,
+    height: usize,
+) {
+    frame_buffer[..width * height].fill(Color::BLACK);
+
+    let mut canvas = TextCanvas { frame_buffer, width, height };
+
+    let metadata = info.metadata;
+    let title = if metadata.title.is_empty() { "<Unknown title>" } else { &metadata.title };
+    canvas.draw_text(title, 24, TITLE_COLOR);
+    canvas.draw_text(&metadata.artist, 36, TEXT_COLOR);
+    canvas.draw_text(&metadata.copyright, 48, TEXT_COLOR);
+
+    canvas.draw_text(
+        &format!("Track {} / {}", info.track_number, info.track_count),
+        76,
+        ACCENT_COLOR,
+    );
+    if let Some(track_label) = info.track_label {
+        canvas.draw_text(track_label, 88, TITLE_COLOR);
+    }
+    canvas.draw_text(
+        &format!("{:02}:{:02}", info.elapsed_seconds / 60, info.elapsed_seconds % 60),
+        100,
+        TEXT_COLOR,
+    );
+
+    let mut chips = vec!["2A03"];
+    chips.extend(metadata.chips.names());
+    canvas.draw_text(&format!("Audio: {}", chips.join(" + ")), 128, TEXT_COLOR);
+
+    let controls_y = height.saturating_sub(36);
+    canvas.draw_text("Right / A: Next track", controls_y, DIM_COLOR);
+    canvas.draw_text("Left / B: Previous track", controls_y + 10, DIM_COLOR);
+    canvas.draw_text("Start: Restart track", controls_y + 20, DIM_COLOR);
+}
@@ -8,7 +8,7 @@ pub(crate) trait NesJoypadStateExt: Sized + Copy {
     fn sanitize_opposing_directions(self) -> Self;
 
     #[must_use]
-    fn latch(self) -> LatchedJoypadState;
+    fn serial_bits(self) -> u8;
 }
 
 impl NesJoypadStateExt for NesJoypadState {
@@ -32,18 +32,19 @@ impl NesJoypadStateExt for NesJoypadState {
         sanitized
     }
 
-    fn latch(self) -> LatchedJoypadState {
-        let bitstream = (u8::from(self.right) << 7)
+    /// Buttons in the order that they are returned by serial reads, starting from the lowest bit.
+    fn serial_bits(self) -> u8 {
+        (u8::from(self.right) << 7)
             | (u8::from(self.left) << 6)
             | (u8::from(self.down) << 5)
             | (u8::from(self.up) << 4)
             | (u8::from(self.start) << 3)
             | (u8::from(self.select) << 2)
             | (u8::from(self.b) << 1)
-            | u8::from(self.a);
-        LatchedJoypadState(bitstream)
+            | u8::from(self.a)
     }
 }
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
 pub struct ZapperState {
     pub fire: bool,
@@ -61,10 +62,53 @@ impl ZapperState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub struct ArkanoidVausState {
+    pub button: bool,
+    // Horizontal position in NES pixels, in the range 0..=255
+    // The last position is kept when the mouse leaves the window
+    pub x: u16,
+}
+
+impl ArkanoidVausState {
+    // Approximate range of the potentiometer value, from fully left to fully right
+    const MIN_POTENTIOMETER: u16 = 0x54;
+    const MAX_POTENTIOMETER: u16 = 0xF4;
+
+    pub(crate) fn potentiometer(self) -> u8 {
+        let x = self.x.min(255);
+        (Self::MIN_POTENTIOMETER + x * (Self::MAX_POTENTIOMETER - Self::MIN_POTENTIOMETER) / 255)
+            as u8
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub struct PowerPadState {
+    // Bit N-1 is set if button N is pressed (N in 1..=12)
+    pub buttons: u16,
+}
+
+impl PowerPadState {
+    pub(crate) fn pressed(self, button: u8) -> bool {
+        self.buttons & (1 << (button - 1)) != 0
+    }
+
+    fn set_button(&mut self, button: u8, pressed: bool) {
+        let mask = 1 << (button - 1);
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
 pub enum NesInputDevice {
     Controller(NesJoypadState),
     Zapper(ZapperState),
+    ArkanoidVaus(ArkanoidVausState),
+    PowerPad(PowerPadState),
 }
 
 impl Default for NesInputDevice {
@@ -73,10 +117,27 @@ impl Default for NesInputDevice {
     }
 }
 
+/// Adapter used to connect players 3 and 4.
+///
+/// The NES Four Score returns 8 bits of player 1/2 inputs, then 8 bits of player 3/4 inputs, then
+/// an 8-bit signature on D0. The Famicom Hori 4 Players Adapter (in 4-player mode) returns the same
+/// sequence on D1 with the two signatures swapped, and the built-in Famicom controllers continue to
+/// be read on D0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub enum NesFourPlayerAdapter {
+    #[default]
+    None,
+    FourScore,
+    Hori,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
 pub struct NesInputs {
     pub p1: NesJoypadState,
     pub p2: NesInputDevice,
+    pub p3: NesJoypadState,
+    pub p4: NesJoypadState,
+    pub four_player_adapter: NesFourPlayerAdapter,
 }
 
 impl MappableInputs<NesButton> for NesInputs {
@@ -92,12 +153,27 @@ impl MappableInputs<NesButton> for NesInputs {
                     }
                 }
             }
+            (NesButton::VausButton, _) => {
+                if let NesInputDevice::ArkanoidVaus(vaus_state) = &mut self.p2 {
+                    vaus_state.button = pressed;
+                }
+            }
+            (button, _) if button.is_power_pad() => {
+                if let (NesInputDevice::PowerPad(power_pad_state), Some(number)) =
+                    (&mut self.p2, button.power_pad_button())
+                {
+                    power_pad_state.set_button(number, pressed);
+                }
+            }
             (button, Player::One) => self.p1.set_button(button, pressed),
             (button, Player::Two) => {
                 if let NesInputDevice::Controller(joypad_state) = &mut self.p2 {
                     joypad_state.set_button(button, pressed);
                 }
             }
+            (button, Player::Three) => self.p3.set_button(button, pressed),
+            (button, Player::Four) => self.p4.set_button(button, pressed),
+            (_, Player::Five) => {}
         }
     }
 
@@ -109,14 +185,24 @@ impl MappableInputs<NesButton> for NesInputs {
         frame_size: FrameSize,
         display_area: DisplayArea,
     ) {
-        if let NesInputDevice::Zapper(zapper_state) = &mut self.p2 {
-            zapper_state.position = jgenesis_common::input::viewport_position_to_frame_position(
-                x,
-                y,
-                frame_size,
-                display_area,
-            );
-            log::debug!("Set Zapper position to {:?}", zapper_state.position);
+        let position = jgenesis_common::input::viewport_position_to_frame_position(
+            x,
+            y,
+            frame_size,
+            display_area,
+        );
+
+        match &mut self.p2 {
+            NesInputDevice::Zapper(zapper_state) => {
+                zapper_state.position = position;
+                log::debug!("Set Zapper position to {:?}", zapper_state.position);
+            }
+            NesInputDevice::ArkanoidVaus(vaus_state) => {
+                if let Some((x, _)) = position {
+                    vaus_state.x = x;
+                }
+            }
+            NesInputDevice::Controller(_) | NesInputDevice::PowerPad(_) => {}
         }
     }
 
@@ -128,22 +214,42 @@ impl MappableInputs<NesButton> for NesInputs {
     }
 }
 
-#[derive(Debug, Clone, Copy, Encode, Decode)]
-pub(crate) struct LatchedJoypadState(u8);
+/// Serial data for one data line of a controller port, latched while the strobe bit is set.
+///
+/// Bits are returned starting from the lowest bit. Once all bits have been shifted out, the
+/// highest bit is returned on every read.
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+pub(crate) struct LatchedSerialData(u32);
 
-impl Default for LatchedJoypadState {
-    fn default() -> Self {
-        Self(0xFF)
+impl LatchedSerialData {
+    pub const NONE: Self = Self(0);
+
+    /// 8 bits of data followed by 1s.
+    pub fn eight_bit(bits: u8) -> Self {
+        Self(0xFFFF_FF00 | u32::from(bits))
+    }
+
+    /// 8 bits from each of two controllers and an 8-bit signature, followed by 1s.
+    pub fn four_player(first: u8, second: u8, signature: u8) -> Self {
+        Self(
+            0xFF00_0000
+                | (u32::from(signature) << 16)
+                | (u32::from(second) << 8)
+                | u32::from(first),
+        )
+    }
+
+    /// Explicit bit count and data, followed by 1s.
+    pub fn with_len(bits: u32, len: u32) -> Self {
+        Self((u32::MAX << len) | bits)
     }
-}
 
-impl LatchedJoypadState {
     pub fn next_bit(self) -> u8 {
-        self.0 & 0x01
+        (self.0 & 0x01) as u8
     }
 
     #[must_use]
     pub fn shift(self) -> Self {
-        Self((self.0 >> 1) | 0x80)
+        Self(((self.0 as i32) >> 1) as u32)
     }
 }
@@ -18,6 +18,7 @@ use genesis_core::timing::GenesisCycleCounters;
 use genesis_core::vdp::{DarkenColors, Vdp, VdpTickEffect};
 use genesis_core::ym2612::Ym2612;
 use genesis_core::{GenesisEmulatorConfig, GenesisInputs};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, EmulatorConfigTrait, EmulatorTrait, InputPoller, Renderer, SaveWriter, TickEffect,
     TickResult, TimingMode,
@@ -260,11 +261,18 @@ impl Sega32XEmulator {
 
         self.audio_resampler.output_samples(audio_output).map_err(Sega32XError::Audio)?;
 
+        // Possibly latch the H/V counter and raise an external interrupt from a light gun
+        if let Some((x, y)) = self.input.light_gun_hl_position() {
+            self.vdp.update_light_gun_latch(x, y, mclk_cycles);
+        }
+
         let mut tick_effect = TickEffect::None;
         if self.vdp.tick(mclk_cycles, &mut self.memory) == VdpTickEffect::FrameComplete {
             self.memory.medium_mut().vdp().composite_frame(&mut self.vdp);
             self.render_frame(renderer).map_err(Sega32XError::Render)?;
 
+            self.memory.apply_cheat_ram_writes();
+
             let cartridge = self.memory.medium_mut().cartridge_mut();
             if cartridge.get_and_clear_ram_dirty() {
                 save_writer
@@ -298,6 +306,13 @@ impl Sega32XEmulator {
         self.memory.medium().cartridge().is_ram_persistent()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no persistent RAM.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        self.has_sram().then(|| self.memory.medium().cartridge().external_ram())
+    }
+
     #[inline]
     #[must_use]
     pub fn timing_mode(&self) -> TimingMode {
@@ -319,6 +334,9 @@ impl EmulatorTrait for Sega32XEmulator {
     type Button = GenesisButton;
     type Inputs = GenesisInputs;
     type Config = Sega32XEmulatorConfig;
+
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::Genesis;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -367,6 +385,11 @@ impl EmulatorTrait for Sega32XEmulator {
 
     fn take_rom_from(&mut self, other: &mut Self) {
         self.memory.medium_mut().take_rom_from(other.memory.medium_mut());
+        self.memory.take_cheats_from(&mut other.memory);
+    }
+
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.memory.set_cheats(cheats);
     }
 
     fn soft_reset(&mut self) {
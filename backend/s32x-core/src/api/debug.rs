@@ -16,7 +16,9 @@ use genesis_core::api::debug::{
 use genesis_core::cartridge::Cartridge;
 use genesis_core::memory::debug::GenesisMemory;
 use genesis_core::ym2612::Ym2612;
-use jgenesis_common::debug::{DebugMemoryView, DebugWordsView, Endian};
+use jgenesis_common::debug::{
+    AtomicWatchpointHit, DebugMemoryView, DebugWordsView, Endian, WatchpointHit,
+};
 use jgenesis_common::frontend::{
     AudioOutput, Color, InputPoller, Renderer, SaveWriter, TickResult,
 };
@@ -344,6 +346,7 @@ impl Sega32XEmulator {
 pub struct Sh2BreakStatus {
     pub breaking: bool,
     pub pc: u32,
+    pub watchpoint: Option<WatchpointHit>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -365,6 +368,7 @@ impl S32XSh2BreakStatus {
 pub struct Sh2BreakStatusAtomic {
     pub breaking: [AtomicBool; 2],
     pub break_pc: [AtomicU32; 2],
+    pub watchpoint: [AtomicWatchpointHit; 2],
 }
 
 impl Sh2BreakStatusAtomic {
@@ -372,6 +376,7 @@ impl Sh2BreakStatusAtomic {
         Self {
             breaking: array::from_fn(|_| AtomicBool::new(false)),
             break_pc: array::from_fn(|_| AtomicU32::new(0)),
+            watchpoint: array::from_fn(|_| AtomicWatchpointHit::default()),
         }
     }
 }
@@ -399,6 +404,7 @@ pub struct Sega32XDebugger {
     sh2_breakpoints: [Sh2Breakpoints; 2],
     sh2_break_status: Arc<Sh2BreakStatusAtomic>,
     sh2_break_step: Option<(WhichCpu, u32)>,
+    sh2_watchpoint_hit: Option<WatchpointHit>,
     m68k_breakpoints: M68000BreakpointManager,
     z80_breakpoints: Z80BreakpointManager,
 }
@@ -425,7 +431,8 @@ impl Sega32XDebuggerHandle {
         let break_idx = which as usize;
         let breaking = self.sh2_break_status.breaking[break_idx].load(Ordering::Acquire);
         let pc = self.sh2_break_status.break_pc[break_idx].load(Ordering::Relaxed);
-        Sh2BreakStatus { breaking, pc }
+        let watchpoint = self.sh2_break_status.watchpoint[break_idx].load();
+        Sh2BreakStatus { breaking, pc, watchpoint }
     }
 
     #[must_use]
@@ -459,6 +466,7 @@ impl Sega32XDebugger {
             sh2_breakpoints: array::from_fn(|_| Sh2Breakpoints::none()),
             sh2_break_status: Arc::new(Sh2BreakStatusAtomic::new()),
             sh2_break_step: None,
+            sh2_watchpoint_hit: None,
             m68k_breakpoints: M68000BreakpointManager::new(),
             z80_breakpoints: Z80BreakpointManager::new(),
         };
@@ -578,10 +586,11 @@ impl Sega32XDebugger {
         Sega32XDebuggerForZ80 { debugger: self, m68k }
     }
 
-    fn set_sh2_break_status(&self, which: WhichCpu) {
+    fn set_sh2_break_status(&mut self, which: WhichCpu) {
         let break_idx = which as usize;
         self.sh2_break_status.break_pc[break_idx]
             .store(self.last_sh2_pc[break_idx], Ordering::Relaxed);
+        self.sh2_break_status.watchpoint[break_idx].store(self.sh2_watchpoint_hit.take());
         self.sh2_break_status.breaking[break_idx].store(true, Ordering::Release);
     }
 
@@ -589,11 +598,11 @@ impl Sega32XDebugger {
         self.sh2_break_status.breaking[which as usize].store(false, Ordering::Release);
     }
 
-    fn set_68k_break_status(&self) {
+    fn set_68k_break_status(&mut self) {
         self.m68k_breakpoints.set_break_status();
     }
 
-    fn set_z80_break_status(&self) {
+    fn set_z80_break_status(&mut self) {
         self.z80_breakpoints.set_break_status();
     }
 
@@ -686,6 +695,10 @@ impl Sega32XDebugger {
         self.z80_breakpoints.check_break_step()
     }
 
+    pub(crate) fn set_sh2_watchpoint_hit(&mut self, hit: WatchpointHit) {
+        self.sh2_watchpoint_hit = Some(hit);
+    }
+
     pub(crate) fn update_sh2_pc_and_check_execute(&mut self, which: WhichCpu, pc: u32) -> bool {
         self.last_sh2_pc[which as usize] = pc;
         self.sh2_breakpoints(which).should_break_execute(pc)
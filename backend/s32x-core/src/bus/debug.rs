@@ -8,6 +8,7 @@ use crate::core::{Sega32X, Sega32XBus};
 use genesis_core::api::debug::{BaseGenesisDebugView, GenesisMemoryDebugView};
 use genesis_core::memory::MainBus;
 use genesis_core::memory::debug::{MainBus68kDebugger, MainBusZ80Debugger};
+use jgenesis_common::debug::WatchpointHit;
 use m68000_emu::M68000;
 use sh2_emu::Sh2;
 use sh2_emu::bus::{AccessContext, BusInterface, OpSize};
@@ -156,6 +157,10 @@ impl<'a> Sh2BusDebugView<'a> {
         unsafe { self.0.debugger.debugger.as_mut().check_sh2_break_step(which) }
     }
 
+    fn set_watchpoint_hit(&mut self, hit: WatchpointHit) {
+        unsafe { self.0.debugger.debugger.as_mut().set_sh2_watchpoint_hit(hit) }
+    }
+
     fn handle_breakpoint(&mut self, cpu: &mut Sh2) {
         let which = self.0.bus.which;
         let (mut debug_view, debugger) = self.as_32x_debug_view_and_debugger(cpu);
@@ -183,6 +188,7 @@ impl Sh2Debugger for Sh2BusDebugView<'_> {
                 self.0.bus.which,
                 OpSize::display::<SIZE>()
             );
+            self.set_watchpoint_hit(WatchpointHit { address, write: false });
             self.handle_breakpoint(cpu);
         }
     }
@@ -203,6 +209,7 @@ impl Sh2Debugger for Sh2BusDebugView<'_> {
                 self.0.bus.which,
                 OpSize::display::<SIZE>()
             );
+            self.set_watchpoint_hit(WatchpointHit { address, write: true });
             self.handle_breakpoint(cpu);
         }
     }
@@ -20,6 +20,7 @@ use genesis_core::timing::GenesisCycleCounters;
 use genesis_core::vdp::{DarkenColors, Vdp, VdpTickEffect};
 use genesis_core::ym2612::Ym2612;
 use genesis_core::{GenesisEmulatorConfig, GenesisInputs};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, EmulatorConfigTrait, EmulatorTrait, InputPoller, PartialClone, Renderer,
     SaveWriter, TickEffect, TickResult, TimingMode,
@@ -327,6 +328,12 @@ impl SegaCdEmulator {
         self.timing_mode
     }
 
+    /// Contents of the internal backup RAM, which is persisted under the "sav" extension.
+    #[must_use]
+    pub fn save_ram(&self) -> &[u8] {
+        self.memory.medium().backup_ram()
+    }
+
     pub fn remove_disc(&mut self) {
         self.memory.medium_mut().remove_disc();
         self.disc_title = "(no disc)".into();
@@ -490,11 +497,18 @@ impl SegaCdEmulator {
         // Output any audio samples that are queued up
         self.audio_resampler.output_samples(audio_output).map_err(SegaCdError::Audio)?;
 
+        // Possibly latch the H/V counter and raise an external interrupt from a light gun
+        if let Some((x, y)) = self.input.light_gun_hl_position() {
+            self.vdp.update_light_gun_latch(x, y, genesis_mclk_elapsed);
+        }
+
         // VDP
         let mut tick_effect = TickEffect::None;
         if self.vdp.tick(genesis_mclk_elapsed, &mut self.memory) == VdpTickEffect::FrameComplete {
             self.render_frame(renderer).map_err(SegaCdError::Render)?;
 
+            self.memory.apply_cheat_ram_writes();
+
             if self.memory.medium_mut().get_and_clear_backup_ram_dirty_bit() {
                 let sega_cd = self.memory.medium();
 
@@ -528,6 +542,8 @@ impl EmulatorTrait for SegaCdEmulator {
     type Inputs = GenesisInputs;
     type Config = SegaCdEmulatorConfig;
 
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::Genesis;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -580,6 +596,11 @@ impl EmulatorTrait for SegaCdEmulator {
 
     fn take_rom_from(&mut self, other: &mut Self) {
         self.memory.medium_mut().take_rom_from(other.memory.medium_mut());
+        self.memory.take_cheats_from(&mut other.memory);
+    }
+
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.memory.set_cheats(cheats);
     }
 
     fn soft_reset(&mut self) {
@@ -8,13 +8,15 @@ use crate::psg::{Sn76489, Sn76489TickEffect};
 use crate::vdp::{Vdp, VdpBuffer, VdpTickEffect, ViewportSize};
 use crate::{VdpVersion, vdp};
 use bincode::{Decode, Encode};
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, Color, EmulatorConfigTrait, EmulatorTrait, FrameSize, InputPoller, PartialClone,
     RenderFrameOptions, Renderer, SaveWriter, TickEffect, TimingMode,
 };
 use jgenesis_proc_macros::{ConfigDisplay, FakeDecode, FakeEncode};
 use smsgg_config::{
-    GgAspectRatio, SmsAspectRatio, SmsGgButton, SmsGgInputs, SmsGgRegion, SmsModel, Sn76489Version,
+    GgAspectRatio, SmsAspectRatio, SmsGgButton, SmsGgControllerType, SmsGgInputs, SmsGgRegion,
+    SmsModel, Sn76489Version,
 };
 use std::fmt::{Debug, Display};
 use std::num::NonZeroU32;
@@ -86,6 +88,8 @@ pub struct SmsGgEmulatorConfig {
     pub gg_use_sms_resolution: bool,
     pub fm_sound_unit_enabled: bool,
     pub z80_divider: NonZeroU32,
+    pub p1_controller_type: SmsGgControllerType,
+    pub p2_controller_type: SmsGgControllerType,
 }
 
 impl EmulatorConfigTrait for SmsGgEmulatorConfig {
@@ -164,7 +168,11 @@ impl SmsGgEmulator {
         let memory = Memory::new(rom, bios_rom, cartridge_ram, hardware);
         let vdp = Vdp::new(vdp_version, &config);
         let psg = Sn76489::new(psg_version);
-        let input = InputState::new(config.region(&memory));
+        let input = InputState::new(
+            config.region(&memory),
+            config.p1_controller_type,
+            config.p2_controller_type,
+        );
 
         log::info!("Region in cartridge header: {:?}", memory.guess_cartridge_region());
 
@@ -209,6 +217,38 @@ impl SmsGgEmulator {
         self.memory.cartridge_has_battery()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no battery.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        self.has_sram().then(|| self.memory.cartridge_ram())
+    }
+
+    /// Contents of cartridge RAM, whether or not it is known to be battery-backed. Cartridges that
+    /// are not in the battery database are assumed to be battery-backed once the game writes to
+    /// cartridge RAM.
+    #[must_use]
+    pub fn cartridge_ram(&self) -> &[u8] {
+        self.memory.cartridge_ram()
+    }
+
+    // Convert a position in the rendered frame to a (scanline, pixel) position in the active display
+    fn pointer_to_vdp_position(&self, pointer_position: Option<(u16, u16)>) -> Option<(u16, u16)> {
+        let (x, y) = pointer_position?;
+
+        let viewport = self.vdp.viewport();
+        let col = if self.config.sms_crop_left_border { x + viewport.left_border_width } else { x };
+        let row =
+            if self.config.sms_crop_vertical_border { y + viewport.top_border_height } else { y };
+
+        let row = row.checked_sub(viewport.top_border_height)?;
+        if col >= viewport.width || row >= viewport.height_without_border() {
+            return None;
+        }
+
+        Some((viewport.top + row, viewport.left + col))
+    }
+
     fn render_frame<R: Renderer>(&mut self, renderer: &mut R) -> Result<(), R::Err> {
         populate_frame_buffer(
             self.vdp.frame_buffer(),
@@ -292,6 +332,8 @@ impl EmulatorTrait for SmsGgEmulator {
     type Inputs = SmsGgInputs;
     type Config = SmsGgEmulatorConfig;
 
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::SmsGg;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -363,7 +405,11 @@ impl EmulatorTrait for SmsGgEmulator {
                 self.render_frame(renderer).map_err(SmsGgError::Render)?;
                 frame_rendered = true;
 
-                self.input.set_inputs(*input_poller.poll());
+                self.memory.apply_cheat_ram_writes();
+
+                let inputs = *input_poller.poll();
+                let pointer_target = self.pointer_to_vdp_position(inputs.pointer_position);
+                self.input.set_inputs(inputs, pointer_target);
                 self.input.set_reset(self.reset_frames_remaining != 0);
                 self.reset_frames_remaining = self.reset_frames_remaining.saturating_sub(1);
 
@@ -400,6 +446,7 @@ impl EmulatorTrait for SmsGgEmulator {
         self.psg.set_version(determine_psg_version(hardware, config));
 
         self.input.set_region(config.region(&self.memory));
+        self.input.set_controller_types(config.p1_controller_type, config.p2_controller_type);
         self.audio_resampler.update_timing_mode(self.vdp.timing_mode());
     }
 
@@ -407,6 +454,10 @@ impl EmulatorTrait for SmsGgEmulator {
         self.memory.take_rom_from(&mut other.memory);
     }
 
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.memory.set_cheats(cheats);
+    }
+
     fn soft_reset(&mut self) {
         log::info!("Soft resetting console");
 
@@ -425,7 +476,8 @@ impl EmulatorTrait for SmsGgEmulator {
 
         self.vdp = Vdp::new(self.vdp_version, &self.config);
         self.psg = Sn76489::new(self.psg.version());
-        self.input = InputState::new(self.input.region());
+        let (p1_controller_type, p2_controller_type) = self.input.controller_types();
+        self.input = InputState::new(self.input.region(), p1_controller_type, p2_controller_type);
 
         self.ym2413 =
             self.config.fm_sound_unit_enabled.then(|| ym_opll::new_ym2413(YM2413_CLOCK_INTERVAL));
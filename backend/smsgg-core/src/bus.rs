@@ -102,11 +102,11 @@ impl BusInterface for Bus<'_> {
             }
             (true, true, false) => {
                 log::trace!("I/O A/B read");
-                self.input.port_dc()
+                self.input.port_dc(self.vdp)
             }
             (true, true, true) => {
                 log::trace!("I/O B/misc. read");
-                self.input.port_dd()
+                self.input.port_dd(self.vdp)
             }
         }
     }
@@ -1,9 +1,18 @@
 //! Code for handling Sega Master System / Game Gear controller input I/O registers
+//!
+//! In addition to the standard joypad, each Master System controller port supports:
+//! - Light Phaser: TL is the trigger and TH is the light sensor. The sensor pulls TH low when the
+//!   beam passes near the pointer position, which latches the VDP's H counter
+//! - HPD-200 Paddle Control: Returns an 8-bit knob position one nibble at a time, with TR indicating
+//!   which nibble is available. The Japanese paddle switches nibbles on its own, while the export
+//!   paddle switches based on TH output
+//! - Sports Pad: Returns X and Y trackball motion one nibble at a time, advancing whenever the game
+//!   toggles TH. Only the export protocol is emulated
 
 use crate::vdp::Vdp;
 use bincode::{Decode, Encode};
 use jgenesis_common::num::GetBit;
-use smsgg_config::{SmsGgInputs, SmsGgRegion};
+use smsgg_config::{SmsGgControllerType, SmsGgInputs, SmsGgJoypadState, SmsGgRegion};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
 enum PinDirection {
@@ -20,25 +29,183 @@ impl PinDirection {
     }
 }
 
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct Port {
+    tr: PinDirection,
+    th: PinDirection,
+}
+
+impl Default for Port {
+    fn default() -> Self {
+        Self { tr: PinDirection::Input, th: PinDirection::Input }
+    }
+}
+
+// Pin values read from a controller port, all active low
+#[derive(Debug, Clone, Copy)]
+struct PortPins {
+    // Bits 0-5: Up, Down, Left, Right, TL, TR
+    data: u8,
+    th: bool,
+}
+
+impl PortPins {
+    const NONE: Self = Self { data: 0x3F, th: true };
+
+    fn joypad(joypad: SmsGgJoypadState) -> Self {
+        let data = (u8::from(!joypad.button2) << 5)
+            | (u8::from(!joypad.button1) << 4)
+            | (u8::from(!joypad.right) << 3)
+            | (u8::from(!joypad.left) << 2)
+            | (u8::from(!joypad.down) << 1)
+            | u8::from(!joypad.up);
+        Self { data, th: true }
+    }
+
+    fn nibble(nibble: u8, tl: bool, tr: bool) -> Self {
+        let data = (u8::from(tr) << 5) | (u8::from(tl) << 4) | (nibble & 0x0F);
+        Self { data, th: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct LightPhaser {
+    light_detected: bool,
+}
+
+impl LightPhaser {
+    fn read(&mut self, trigger: bool, target: Option<(u16, u16)>, vdp: &mut Vdp) -> PortPins {
+        let light_detected =
+            target.is_some_and(|(scanline, pixel)| vdp.is_beam_near(scanline, pixel));
+
+        // The H counter is latched when TH first goes low
+        if let Some((_, pixel)) = target
+            && light_detected
+            && !self.light_detected
+        {
+            vdp.latch_h_counter_at_pixel(pixel);
+        }
+        self.light_detected = light_detected;
+
+        PortPins { data: 0x2F | (u8::from(!trigger) << 4), th: !light_detected }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct Paddle {
+    // Flip-flop used by the Japanese paddle to alternate nibbles
+    high_nibble: bool,
+}
+
+impl Paddle {
+    fn read(
+        &mut self,
+        button: bool,
+        position: u8,
+        th: PinDirection,
+        region: SmsGgRegion,
+    ) -> PortPins {
+        let high_nibble = match region {
+            SmsGgRegion::Domestic => {
+                self.high_nibble = !self.high_nibble;
+                self.high_nibble
+            }
+            // Export paddle returns the high nibble while TH output is low
+            SmsGgRegion::International => !th.bit(true),
+        };
+
+        let nibble = if high_nibble { position >> 4 } else { position & 0x0F };
+        PortPins::nibble(nibble, !button, high_nibble)
+    }
+}
+
+// Sports Pad returns signed motion since the last read, with positive values meaning left/up
+const SPORTS_PAD_MAX_MOTION: i16 = 127;
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct SportsPad {
+    last_th: bool,
+    counter: u8,
+    last_position: Option<(u16, u16)>,
+    motion_x: i8,
+    motion_y: i8,
+}
+
+impl Default for SportsPad {
+    fn default() -> Self {
+        Self { last_th: true, counter: 0, last_position: None, motion_x: 0, motion_y: 0 }
+    }
+}
+
+impl SportsPad {
+    fn update_th(&mut self, th: bool, position: Option<(u16, u16)>) {
+        if th == self.last_th {
+            return;
+        }
+        self.last_th = th;
+
+        // Every TH transition advances through X high, X low, Y high, Y low
+        self.counter = (self.counter + 1) & 3;
+        if self.counter == 1 {
+            self.latch_motion(position);
+        }
+    }
+
+    fn latch_motion(&mut self, position: Option<(u16, u16)>) {
+        let (motion_x, motion_y) = match (self.last_position, position) {
+            (Some((last_x, last_y)), Some((x, y))) => {
+                (last_x as i16 - x as i16, last_y as i16 - y as i16)
+            }
+            _ => (0, 0),
+        };
+        self.last_position = position;
+
+        self.motion_x = motion_x.clamp(-SPORTS_PAD_MAX_MOTION, SPORTS_PAD_MAX_MOTION) as i8;
+        self.motion_y = motion_y.clamp(-SPORTS_PAD_MAX_MOTION, SPORTS_PAD_MAX_MOTION) as i8;
+    }
+
+    fn read(self, button_1: bool, button_2: bool) -> PortPins {
+        let motion_x = self.motion_x as u8;
+        let motion_y = self.motion_y as u8;
+        let nibble = match self.counter {
+            1 => motion_x >> 4,
+            2 => motion_x & 0x0F,
+            3 => motion_y >> 4,
+            _ => motion_y & 0x0F,
+        };
+
+        PortPins::nibble(nibble, !button_1, !button_2)
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct InputState {
     inputs: SmsGgInputs,
-    port_a_tr: PinDirection,
-    port_a_th: PinDirection,
-    port_b_tr: PinDirection,
-    port_b_th: PinDirection,
+    // Pointer position converted to (scanline, pixel)
+    pointer_target: Option<(u16, u16)>,
+    controller_types: [SmsGgControllerType; 2],
+    ports: [Port; 2],
+    light_phasers: [LightPhaser; 2],
+    paddles: [Paddle; 2],
+    sports_pads: [SportsPad; 2],
     region: SmsGgRegion,
     reset: bool,
 }
 
 impl InputState {
-    pub fn new(region: SmsGgRegion) -> Self {
+    pub fn new(
+        region: SmsGgRegion,
+        p1_controller_type: SmsGgControllerType,
+        p2_controller_type: SmsGgControllerType,
+    ) -> Self {
         Self {
             inputs: SmsGgInputs::default(),
-            port_a_tr: PinDirection::Input,
-            port_a_th: PinDirection::Input,
-            port_b_tr: PinDirection::Input,
-            port_b_th: PinDirection::Input,
+            pointer_target: None,
+            controller_types: [p1_controller_type, p2_controller_type],
+            ports: [Port::default(); 2],
+            light_phasers: [LightPhaser::default(); 2],
+            paddles: [Paddle::default(); 2],
+            sports_pads: [SportsPad::default(); 2],
             region,
             reset: false,
         }
@@ -48,8 +215,11 @@ impl InputState {
         self.inputs.pause
     }
 
-    pub fn set_inputs(&mut self, inputs: SmsGgInputs) {
+    /// Update inputs. `pointer_target` is the input pointer position converted to a
+    /// (scanline, pixel) position within the active display area.
+    pub fn set_inputs(&mut self, inputs: SmsGgInputs, pointer_target: Option<(u16, u16)>) {
         self.inputs = inputs;
+        self.pointer_target = pointer_target;
     }
 
     pub fn region(&self) -> SmsGgRegion {
@@ -60,6 +230,18 @@ impl InputState {
         self.region = region;
     }
 
+    pub fn controller_types(&self) -> (SmsGgControllerType, SmsGgControllerType) {
+        (self.controller_types[0], self.controller_types[1])
+    }
+
+    pub fn set_controller_types(
+        &mut self,
+        p1_controller_type: SmsGgControllerType,
+        p2_controller_type: SmsGgControllerType,
+    ) {
+        self.controller_types = [p1_controller_type, p2_controller_type];
+    }
+
     pub fn set_reset(&mut self, reset: bool) {
         self.reset = reset;
     }
@@ -67,52 +249,233 @@ impl InputState {
     pub fn write_control(&mut self, value: u8, vdp: &mut Vdp) {
         log::debug!("I/O control write {value:02X}");
 
-        let prev_a_th = self.port_a_th != PinDirection::Output(false);
-        let prev_b_th = self.port_b_th != PinDirection::Output(false);
+        let prev_a_th = self.ports[0].th != PinDirection::Output(false);
+        let prev_b_th = self.ports[1].th != PinDirection::Output(false);
 
-        self.port_b_th =
+        self.ports[1].th =
             if value.bit(3) { PinDirection::Input } else { PinDirection::Output(value.bit(7)) };
-        self.port_b_tr =
+        self.ports[1].tr =
             if value.bit(2) { PinDirection::Input } else { PinDirection::Output(value.bit(6)) };
-        self.port_a_th =
+        self.ports[0].th =
             if value.bit(1) { PinDirection::Input } else { PinDirection::Output(value.bit(5)) };
-        self.port_a_tr =
+        self.ports[0].tr =
             if value.bit(0) { PinDirection::Input } else { PinDirection::Output(value.bit(4)) };
 
-        if (!prev_a_th && self.port_a_th != PinDirection::Output(false))
-            || (!prev_b_th && self.port_b_th != PinDirection::Output(false))
+        if (!prev_a_th && self.ports[0].th != PinDirection::Output(false))
+            || (!prev_b_th && self.ports[1].th != PinDirection::Output(false))
         {
             vdp.latch_h_counter_on_th_change();
         }
+
+        for port in 0..2 {
+            if self.controller_types[port] == SmsGgControllerType::SportsPad {
+                let th = self.ports[port].th.bit(true);
+                self.sports_pads[port].update_th(th, self.pointer_position());
+            }
+        }
     }
 
-    pub fn port_dc(&self) -> u8 {
-        let port_a_tr_bit = u8::from(self.port_a_tr.bit(!self.inputs.p1.button2)) << 5;
+    fn pointer_position(&self) -> Option<(u16, u16)> {
+        self.pointer_target.map(|(scanline, pixel)| (pixel, scanline))
+    }
 
-        (u8::from(!self.inputs.p2.down) << 7)
-            | (u8::from(!self.inputs.p2.up) << 6)
-            | port_a_tr_bit
-            | (u8::from(!self.inputs.p1.button1) << 4)
-            | (u8::from(!self.inputs.p1.right) << 3)
-            | (u8::from(!self.inputs.p1.left) << 2)
-            | (u8::from(!self.inputs.p1.down) << 1)
-            | u8::from(!self.inputs.p1.up)
+    fn read_port(&mut self, port: usize, vdp: &mut Vdp) -> PortPins {
+        match self.controller_types[port] {
+            SmsGgControllerType::Gamepad => {
+                let joypad = if port == 0 { self.inputs.p1 } else { self.inputs.p2 };
+                PortPins::joypad(joypad)
+            }
+            SmsGgControllerType::LightPhaser => self.light_phasers[port].read(
+                self.inputs.light_phaser_trigger,
+                self.pointer_target,
+                vdp,
+            ),
+            SmsGgControllerType::Paddle => {
+                let Some((_, pixel)) = self.pointer_target else {
+                    return PortPins::NONE;
+                };
+                let position = pixel.min(u8::MAX.into()) as u8;
+                self.paddles[port].read(
+                    self.inputs.paddle_button,
+                    position,
+                    self.ports[port].th,
+                    self.region,
+                )
+            }
+            SmsGgControllerType::SportsPad => self.sports_pads[port]
+                .read(self.inputs.sports_pad_button_1, self.inputs.sports_pad_button_2),
+        }
     }
 
-    pub fn port_dd(&self) -> u8 {
+    pub fn port_dc(&mut self, vdp: &mut Vdp) -> u8 {
+        let port_a = self.read_port(0, vdp);
+        let port_b = self.read_port(1, vdp);
+
+        let port_a_tr_bit = u8::from(self.ports[0].tr.bit(port_a.data.bit(5))) << 5;
+
+        (port_b.data << 6) | port_a_tr_bit | (port_a.data & 0x1F)
+    }
+
+    pub fn port_dd(&mut self, vdp: &mut Vdp) -> u8 {
+        let port_a = self.read_port(0, vdp);
+        let port_b = self.read_port(1, vdp);
+
         let port_b_th_bit =
-            u8::from(self.region == SmsGgRegion::International && self.port_b_th.bit(true)) << 7;
+            u8::from(self.region == SmsGgRegion::International && self.ports[1].th.bit(port_b.th))
+                << 7;
         let port_a_th_bit =
-            u8::from(self.region == SmsGgRegion::International && self.port_a_th.bit(true)) << 6;
-        let port_b_tr_bit = u8::from(self.port_b_tr.bit(!self.inputs.p2.button2)) << 3;
+            u8::from(self.region == SmsGgRegion::International && self.ports[0].th.bit(port_a.th))
+                << 6;
+        let port_b_tr_bit = u8::from(self.ports[1].tr.bit(port_b.data.bit(5))) << 3;
 
         port_b_th_bit
             | port_a_th_bit
             | 0x20
             | (u8::from(!self.reset) << 4)
             | port_b_tr_bit
-            | (u8::from(!self.inputs.p2.button1) << 2)
-            | (u8::from(!self.inputs.p2.right) << 1)
-            | u8::from(!self.inputs.p2.left)
+            | ((port_b.data >> 2) & 0x07)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SmsGgEmulatorConfig;
+    use crate::vdp::VdpVersion;
+    use jgenesis_common::frontend::TimingMode;
+    use smsgg_config::{GgAspectRatio, SmsAspectRatio, SmsModel};
+    use std::num::NonZeroU32;
+
+    // Port A TR input, port B TR/TH input, port A TH output with the given level
+    const CTRL_PORT_A_TH_LOW: u8 = 0x0D;
+    const CTRL_PORT_A_TH_HIGH: u8 = 0x2D;
+
+    fn new_vdp() -> Vdp {
+        let config = SmsGgEmulatorConfig {
+            sms_timing_mode: TimingMode::Ntsc,
+            sms_model: SmsModel::default(),
+            forced_psg_version: None,
+            sms_aspect_ratio: SmsAspectRatio::default(),
+            gg_aspect_ratio: GgAspectRatio::default(),
+            remove_sprite_limit: false,
+            forced_region: None,
+            sms_crop_vertical_border: false,
+            sms_crop_left_border: false,
+            gg_frame_blending: false,
+            gg_use_sms_resolution: false,
+            fm_sound_unit_enabled: false,
+            z80_divider: NonZeroU32::new(crate::NATIVE_Z80_DIVIDER).unwrap(),
+            p1_controller_type: SmsGgControllerType::default(),
+            p2_controller_type: SmsGgControllerType::default(),
+        };
+        Vdp::new(VdpVersion::NtscMasterSystem2, &config)
+    }
+
+    #[test]
+    fn light_phaser_th_latching() {
+        let mut vdp = new_vdp();
+        let mut state = InputState::new(
+            SmsGgRegion::International,
+            SmsGgControllerType::LightPhaser,
+            SmsGgControllerType::Gamepad,
+        );
+        let inputs = SmsGgInputs { light_phaser_trigger: true, ..SmsGgInputs::default() };
+
+        // Beam is at line 0 dot 0; aiming elsewhere leaves TH high. Trigger is active low on TL
+        state.set_inputs(inputs, Some((100, 30)));
+        assert!(state.port_dd(&mut vdp).bit(6));
+        assert!(!state.port_dc(&mut vdp).bit(4));
+
+        // Aiming near the beam pulls TH low and latches the H counter at the pointer position
+        state.set_inputs(inputs, Some((0, 30)));
+        assert!(!state.port_dd(&mut vdp).bit(6));
+        assert_eq!(vdp.h_counter(), 20);
+
+        // The H counter is not latched again while TH stays low
+        state.set_inputs(inputs, Some((0, 50)));
+        assert!(!state.port_dd(&mut vdp).bit(6));
+        assert_eq!(vdp.h_counter(), 20);
+
+        state.set_inputs(inputs, None);
+        assert!(state.port_dd(&mut vdp).bit(6));
+        state.set_inputs(inputs, Some((0, 50)));
+        assert!(!state.port_dd(&mut vdp).bit(6));
+        assert_eq!(vdp.h_counter(), 30);
+    }
+
+    #[test]
+    fn paddle_japanese_flip_flop() {
+        let mut vdp = new_vdp();
+        let mut state = InputState::new(
+            SmsGgRegion::Domestic,
+            SmsGgControllerType::Paddle,
+            SmsGgControllerType::Gamepad,
+        );
+        state.set_inputs(SmsGgInputs::default(), Some((50, 0xA5)));
+
+        // Every read alternates nibbles, with TR high for the high nibble
+        assert_eq!(state.port_dc(&mut vdp), 0xFA);
+        assert_eq!(state.port_dc(&mut vdp), 0xD5);
+        assert_eq!(state.port_dc(&mut vdp), 0xFA);
+
+        // Button is active low on TL
+        state.set_inputs(
+            SmsGgInputs { paddle_button: true, ..SmsGgInputs::default() },
+            Some((50, 0xA5)),
+        );
+        assert_eq!(state.port_dc(&mut vdp), 0xC5);
+    }
+
+    #[test]
+    fn paddle_export_th_select() {
+        let mut vdp = new_vdp();
+        let mut state = InputState::new(
+            SmsGgRegion::International,
+            SmsGgControllerType::Paddle,
+            SmsGgControllerType::Gamepad,
+        );
+        state.set_inputs(SmsGgInputs::default(), Some((50, 0xA5)));
+
+        // Nibble only changes with TH output
+        state.write_control(CTRL_PORT_A_TH_LOW, &mut vdp);
+        assert_eq!(state.port_dc(&mut vdp), 0xFA);
+        assert_eq!(state.port_dc(&mut vdp), 0xFA);
+
+        state.write_control(CTRL_PORT_A_TH_HIGH, &mut vdp);
+        assert_eq!(state.port_dc(&mut vdp), 0xD5);
+        assert_eq!(state.port_dc(&mut vdp), 0xD5);
+    }
+
+    fn sports_pad_nibbles(state: &mut InputState, vdp: &mut Vdp) -> Vec<u8> {
+        [CTRL_PORT_A_TH_LOW, CTRL_PORT_A_TH_HIGH, CTRL_PORT_A_TH_LOW, CTRL_PORT_A_TH_HIGH]
+            .into_iter()
+            .map(|ctrl| {
+                state.write_control(ctrl, vdp);
+                state.port_dc(vdp)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sports_pad_nibble_sequence() {
+        let mut vdp = new_vdp();
+        let mut state = InputState::new(
+            SmsGgRegion::International,
+            SmsGgControllerType::SportsPad,
+            SmsGgControllerType::Gamepad,
+        );
+        let inputs = SmsGgInputs { sports_pad_button_1: true, ..SmsGgInputs::default() };
+
+        // First sequence only latches the position
+        state.set_inputs(inputs, Some((20, 50)));
+        assert_eq!(sports_pad_nibbles(&mut state, &mut vdp), vec![0xE0, 0xE0, 0xE0, 0xE0]);
+
+        // X high, X low, Y high, Y low; moved right 30 (-30) and up 10 (+10)
+        state.set_inputs(inputs, Some((10, 80)));
+        assert_eq!(sports_pad_nibbles(&mut state, &mut vdp), vec![0xEE, 0xE2, 0xE0, 0xEA]);
+
+        // Motion is clamped to 127
+        state.set_inputs(inputs, Some((10, 300)));
+        assert_eq!(sports_pad_nibbles(&mut state, &mut vdp), vec![0xE8, 0xE1, 0xE0, 0xE0]);
     }
 }
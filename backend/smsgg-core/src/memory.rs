@@ -7,6 +7,7 @@ use crate::SmsGgHardware;
 use crate::memory::mappers::{Mapper, Sg1000Mapper};
 use bincode::{Decode, Encode};
 use crc::Crc;
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::num::{GetBit, U16Ext};
 use jgenesis_proc_macros::{FakeDecode, FakeEncode, PartialClone};
 use smsgg_config::SmsGgRegion;
@@ -140,6 +141,8 @@ pub struct Memory {
     audio_control: AudioControl,
     gg_registers: GameGearRegisters,
     hardware: SmsGgHardware,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl Memory {
@@ -168,49 +171,15 @@ impl Memory {
             audio_control: AudioControl::default(),
             gg_registers: GameGearRegisters::new(),
             hardware,
+            cheats: CheatSet::default(),
         }
     }
 
     pub fn read(&self, address: u16) -> u8 {
         match address {
             0x0000..=0xBFFF => {
-                match self.hardware {
-                    SmsGgHardware::MasterSystem | SmsGgHardware::Sg1000 => {
-                        let bios_enabled = self.hardware == SmsGgHardware::MasterSystem
-                            && self.memory_control.bios_enabled;
-                        if self.memory_control.cartridge_enabled {
-                            let cartridge_byte = self.cartridge.read(address);
-                            if bios_enabled {
-                                // Cartridge and BIOS are both enabled; return logical AND of their bytes
-                                let bios_byte = self.read_bios_sms(address);
-                                cartridge_byte & bios_byte
-                            } else {
-                                cartridge_byte
-                            }
-                        } else if bios_enabled {
-                            self.read_bios_sms(address)
-                        } else {
-                            log::debug!(
-                                "Slot read ${address:04X} with neither cartridge nor BIOS enabled"
-                            );
-                            // TODO this should be open bus; fake it by returning address high byte
-                            address.msb()
-                        }
-                    }
-                    SmsGgHardware::GameGear => {
-                        // Cartridge is always enabled on Game Gear
-                        // BIOS is mapped to $0000-$03FF if enabled
-                        if self.memory_control.bios_enabled && address <= 0x03FF {
-                            self.bios_rom
-                                .as_ref()
-                                .and_then(|bios| bios.get(address as usize))
-                                .copied()
-                                .unwrap_or(0xFF)
-                        } else {
-                            self.cartridge.read(address)
-                        }
-                    }
-                }
+                let value = self.read_slot(address);
+                self.cheats.patch_rom_u8(address.into(), value)
             }
             0xC000..=0xFFFF => {
                 // TODO only if RAM enabled
@@ -220,6 +189,44 @@ impl Memory {
         }
     }
 
+    fn read_slot(&self, address: u16) -> u8 {
+        match self.hardware {
+            SmsGgHardware::MasterSystem | SmsGgHardware::Sg1000 => {
+                let bios_enabled = self.hardware == SmsGgHardware::MasterSystem
+                    && self.memory_control.bios_enabled;
+                if self.memory_control.cartridge_enabled {
+                    let cartridge_byte = self.cartridge.read(address);
+                    if bios_enabled {
+                        // Cartridge and BIOS are both enabled; return logical AND of their bytes
+                        let bios_byte = self.read_bios_sms(address);
+                        cartridge_byte & bios_byte
+                    } else {
+                        cartridge_byte
+                    }
+                } else if bios_enabled {
+                    self.read_bios_sms(address)
+                } else {
+                    log::debug!("Slot read ${address:04X} with neither cartridge nor BIOS enabled");
+                    // TODO this should be open bus; fake it by returning address high byte
+                    address.msb()
+                }
+            }
+            SmsGgHardware::GameGear => {
+                // Cartridge is always enabled on Game Gear
+                // BIOS is mapped to $0000-$03FF if enabled
+                if self.memory_control.bios_enabled && address <= 0x03FF {
+                    self.bios_rom
+                        .as_ref()
+                        .and_then(|bios| bios.get(address as usize))
+                        .copied()
+                        .unwrap_or(0xFF)
+                } else {
+                    self.cartridge.read(address)
+                }
+            }
+        }
+    }
+
     fn read_bios_sms(&self, address: u16) -> u8 {
         let Some(bios_rom) = &self.bios_rom else {
             log::debug!("BIOS ROM read ${address:04X} with no BIOS");
@@ -285,6 +292,20 @@ impl Memory {
 
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.cartridge.rom = mem::take(&mut other.cartridge.rom);
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    pub fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    // Cheat RAM writes should be applied once per frame. Writes go directly to system RAM to avoid
+    // triggering mapper register writes
+    pub fn apply_cheat_ram_writes(&mut self) {
+        for &RamWrite { address, value, .. } in self.cheats.ram_writes() {
+            let ram_addr = (address as u16) & self.hardware.ram_mask();
+            self.ram[ram_addr as usize] = value;
+        }
     }
 
     pub fn reset(&mut self) {
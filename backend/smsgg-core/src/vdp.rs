@@ -1414,17 +1414,7 @@ impl Vdp {
     }
 
     pub fn latch_h_counter_on_th_change(&mut self) {
-        let mut dot = self.dot + 10;
-        if dot >= DOTS_PER_SCANLINE {
-            dot -= DOTS_PER_SCANLINE;
-        }
-
-        self.latched_h_counter = if dot >= DOTS_PER_SCANLINE - 46 {
-            let diff = -((DOTS_PER_SCANLINE - dot) as i16);
-            (diff >> 1) as u8
-        } else {
-            (dot >> 1) as u8
-        };
+        self.latched_h_counter = dot_to_h_counter(self.dot);
 
         log::debug!(
             "Latched H counter at line {} dot {}, value {:02X}",
@@ -1434,6 +1424,28 @@ impl Vdp {
         );
     }
 
+    /// Returns whether the beam is close enough to the given active display position for a light
+    /// gun's sensor to detect it. The sensor sees a spot several lines tall, and it keeps seeing
+    /// light for a while after the beam passes.
+    pub fn is_beam_near(&self, scanline: u16, pixel: u16) -> bool {
+        const LINE_RADIUS: u16 = 5;
+        const DOT_RADIUS: u16 = 60;
+
+        self.scanline.abs_diff(scanline) <= LINE_RADIUS && self.dot.abs_diff(pixel) <= DOT_RADIUS
+    }
+
+    /// Latch the H counter as if TH changed while the beam was at the given pixel. Used for light
+    /// guns so that the latched value doesn't depend on how often the game polls TH.
+    pub fn latch_h_counter_at_pixel(&mut self, pixel: u16) {
+        self.latched_h_counter = dot_to_h_counter(pixel);
+
+        log::debug!(
+            "Latched H counter for light gun at line {} pixel {pixel}, value {:02X}",
+            self.scanline,
+            self.latched_h_counter
+        );
+    }
+
     pub fn interrupt_line(&self) -> InterruptLine {
         if (self.registers.frame_interrupt_enabled && self.registers.frame_interrupt_pending)
             || (self.registers.line_interrupt_enabled && self.registers.line_interrupt_pending)
@@ -1466,6 +1478,20 @@ fn get_color_id(tile: &[u8], tile_row: u16, tile_col: u16, horizontal_flip: bool
         | (((tile[(4 * tile_row + 3) as usize] & mask) >> shift) << 3)
 }
 
+fn dot_to_h_counter(dot: u16) -> u8 {
+    let mut dot = dot + 10;
+    if dot >= DOTS_PER_SCANLINE {
+        dot -= DOTS_PER_SCANLINE;
+    }
+
+    if dot >= DOTS_PER_SCANLINE - 46 {
+        let diff = -((DOTS_PER_SCANLINE - dot) as i16);
+        (diff >> 1) as u8
+    } else {
+        (dot >> 1) as u8
+    }
+}
+
 pub fn convert_sms_color(color: u16) -> u8 {
     [0, 85, 170, 255][color as usize]
 }
@@ -98,6 +98,27 @@ impl Sa1 {
             _ => {}
         }
     }
+
+    /// Write to I-RAM or BW-RAM at an SNES address, ignoring write protection. Used for cheats.
+    pub fn write_cheat_ram(&mut self, address: u32, value: u8) {
+        let bank = (address >> 16) & 0xFF;
+        let offset = address & 0xFFFF;
+        match (bank, offset) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x3000..=0x37FF) => {
+                self.iram[(address & 0x7FF) as usize] = value;
+            }
+            (0x00..=0x3F | 0x80..=0xBF, 0x6000..=0x7FFF) => {
+                let bwram_addr = (self.mmc.snes_bwram_base_addr | (address & 0x1FFF))
+                    & (self.bwram.len() as u32 - 1);
+                self.bwram[bwram_addr as usize] = value;
+            }
+            (0x40..=0x4F, _) => {
+                let bwram_addr = (address as usize) & (self.bwram.len() - 1);
+                self.bwram[bwram_addr] = value;
+            }
+            _ => {}
+        }
+    }
 }
 
 pub struct Sa1Bus<'a> {
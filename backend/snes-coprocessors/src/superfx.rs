@@ -118,6 +118,22 @@ impl SuperFx {
         }
     }
 
+    /// Write to GSU RAM at an SNES address, regardless of whether the GSU currently owns the RAM
+    /// bus. Used for cheats.
+    pub fn write_cheat_ram(&mut self, address: u32, value: u8) {
+        let bank = (address >> 16) & 0xFF;
+        let offset = address & 0xFFFF;
+        match (bank, offset) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x6000..=0x7FFF) => {
+                self.ram[(address & 0x1FFF) as usize] = value;
+            }
+            (0x70..=0x71 | 0xF0..=0xF1, _) => {
+                self.ram[(address as usize) & (self.ram.len() - 1)] = value;
+            }
+            _ => {}
+        }
+    }
+
     #[inline]
     pub fn tick(&mut self, master_cycles_elapsed: u64) {
         self.gsu.tick(self.gsu_overclock_factor * master_cycles_elapsed, &self.rom, &mut self.ram);
@@ -11,6 +11,8 @@ use crate::ppu::{Ppu, PpuTickEffect};
 use bincode::error::EncodeError;
 use bincode::{Decode, Encode};
 use crc::Crc;
+use gb_core::api::GameBoyLoadError;
+use jgenesis_common::cheats::{CheatSet, CheatSystem};
 use jgenesis_common::frontend::{
     AudioOutput, Color, EmulatorConfigTrait, EmulatorTrait, InputPoller, PartialClone,
     RenderFrameOptions, Renderer, SaveWriter, TickEffect, TimingMode,
@@ -20,6 +22,7 @@ use snes_config::{AudioInterpolationMode, SnesAspectRatio, SnesButton};
 use snes_coprocessors::st018::St018LoadError;
 use std::fmt::{Debug, Display};
 use std::num::NonZeroU64;
+use std::path::PathBuf;
 use std::{io, mem};
 use thiserror::Error;
 use wdc65816_emu::core::Wdc65816;
@@ -71,6 +74,10 @@ pub struct CoprocessorRoms {
     pub st010: Option<Box<CoprocessorRomFn>>,
     pub st011: Option<Box<CoprocessorRomFn>>,
     pub st018: Option<Box<CoprocessorRomFn>>,
+    pub sgb_cartridge: Option<Box<CoprocessorRomFn>>,
+    /// Path to the MSU-1 data file (`.msu`). If set, MSU-1 is enabled and audio tracks are loaded
+    /// from `<data file name>-<N>.pcm` in the same directory.
+    pub msu1_data_path: Option<PathBuf>,
 }
 
 impl CoprocessorRoms {
@@ -110,6 +117,8 @@ pub enum SnesLoadError {
     MissingSt011Rom,
     #[error("Cannot load ST018 cartridge because ST018 ROM is not configured")]
     MissingSt018Rom,
+    #[error("Cannot load Super Game Boy because no Game Boy cartridge is configured")]
+    MissingSgbCartridgeRom,
     #[error("Failed to load required coprocessor ROM from '{path}': {source}")]
     CoprocessorRomLoad {
         #[source]
@@ -118,6 +127,8 @@ pub enum SnesLoadError {
     },
     #[error("Invalid ST018 coprocessor ROM: {0}")]
     St018RomLoad(St018LoadError),
+    #[error("Error loading Super Game Boy cartridge: {0}")]
+    SgbCartridgeLoad(GameBoyLoadError),
 }
 
 pub type SnesLoadResult<T> = Result<T, SnesLoadError>;
@@ -187,6 +198,7 @@ impl SnesEmulator {
             &coprocessor_roms,
             config.forced_timing_mode,
             config.gsu_overclock_factor,
+            config.audio_60hz_hack,
             save_writer,
         )?;
 
@@ -194,6 +206,7 @@ impl SnesEmulator {
             config.forced_timing_mode.unwrap_or_else(|| memory.cartridge_timing_mode());
         let ppu = Ppu::new(timing_mode, config);
         let apu = Apu::new(timing_mode, config);
+        let audio_resampler = AudioResampler::new(memory.has_msu1());
 
         log::info!("Running with timing/display mode {timing_mode}");
 
@@ -204,7 +217,7 @@ impl SnesEmulator {
             memory,
             ppu,
             apu,
-            audio_resampler: AudioResampler::new(),
+            audio_resampler,
             total_master_cycles: 0,
             latched_interrupts: None,
             memory_refresh_pending: false,
@@ -233,6 +246,17 @@ impl SnesEmulator {
         self.memory.has_battery_backed_sram()
     }
 
+    /// Contents of the save file persisted under the "sav" extension, or `None` if the cartridge
+    /// has no battery-backed SRAM. Super Game Boy SRAM is stored under a different extension.
+    #[must_use]
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        if !self.has_sram() || self.memory.sram_extension() != "sav" {
+            return None;
+        }
+
+        self.memory.sram()
+    }
+
     pub fn copy_cgram(&self, out: &mut [Color]) {
         self.ppu.copy_cgram(out);
     }
@@ -259,6 +283,8 @@ impl EmulatorTrait for SnesEmulator {
     type Inputs = SnesInputs;
     type Config = SnesEmulatorConfig;
 
+    const CHEAT_SYSTEM: CheatSystem = CheatSystem::Snes;
+
     type Err<
         RErr: Debug + Display + Send + Sync + 'static,
         AErr: Debug + Display + Send + Sync + 'static,
@@ -323,12 +349,18 @@ impl EmulatorTrait for SnesEmulator {
             self.ppu.update_controller_hv_latch(h, v, master_cycles_elapsed);
         }
 
-        if let ApuTickEffect::OutputSample(sample_l, sample_r) =
+        if let ApuTickEffect::OutputSample(mut sample_l, mut sample_r) =
             self.apu.tick(master_cycles_elapsed)
         {
+            if let Some((cartridge_l, cartridge_r)) = self.memory.take_cartridge_audio_sample() {
+                sample_l += cartridge_l;
+                sample_r += cartridge_r;
+            }
             self.audio_resampler.collect_sample(sample_l, sample_r);
         }
 
+        self.memory.tick_msu1(master_cycles_elapsed, &mut self.audio_resampler);
+
         self.audio_resampler.output_samples(audio_output).map_err(SnesError::AudioOutput)?;
 
         self.memory.tick(master_cycles_elapsed);
@@ -348,6 +380,8 @@ impl EmulatorTrait for SnesEmulator {
                 )
                 .map_err(SnesError::Render)?;
 
+            self.memory.apply_cheat_ram_writes();
+
             // Only persist SRAM if it's changed since the last write, and only check ~twice per
             // second because of the checksum calculation
             if self.memory.has_battery_backed_sram()
@@ -356,7 +390,9 @@ impl EmulatorTrait for SnesEmulator {
             {
                 let checksum = CRC.checksum(sram);
                 if checksum != self.last_sram_checksum {
-                    save_writer.persist_bytes("sav", sram).map_err(SnesError::SaveWrite)?;
+                    save_writer
+                        .persist_bytes(&self.memory.sram_extension(), sram)
+                        .map_err(SnesError::SaveWrite)?;
                     self.memory
                         .write_auxiliary_save_files(save_writer)
                         .map_err(SnesError::SaveWrite)?;
@@ -408,6 +444,7 @@ impl EmulatorTrait for SnesEmulator {
         self.ppu.update_config(*config);
         self.apu.update_config(*config);
         self.memory.update_gsu_overclock_factor(config.gsu_overclock_factor);
+        self.memory.update_audio_60hz_hack(config.audio_60hz_hack);
 
         self.emulator_config = *config;
     }
@@ -417,6 +454,10 @@ impl EmulatorTrait for SnesEmulator {
         self.coprocessor_roms = mem::take(&mut other.coprocessor_roms);
     }
 
+    fn set_cheats(&mut self, cheats: CheatSet) {
+        self.memory.set_cheats(cheats);
+    }
+
     fn soft_reset(&mut self) {
         log::info!("Soft resetting");
 
@@ -1,6 +1,7 @@
 //! SNES audio resampling code
 
 use crate::apu;
+use crate::memory::msu1;
 use bincode::{Decode, Encode};
 use dsp::design::FilterType;
 use dsp::iir::FirstOrderIirFilter;
@@ -9,6 +10,9 @@ use jgenesis_common::frontend::AudioOutput;
 
 const SNES_AUDIO_FREQUENCY: f64 = apu::OUTPUT_FREQUENCY as f64;
 
+// Roughly 50ms at 48 KHz
+const MAX_UNMATCHED_SAMPLES: usize = 2400;
+
 fn new_dc_offset_filter() -> FirstOrderIirFilter {
     dsp::design::butterworth(5.0, SNES_AUDIO_FREQUENCY, FilterType::HighPass)
 }
@@ -18,14 +22,17 @@ pub struct AudioResampler {
     dc_offset_l: FirstOrderIirFilter,
     dc_offset_r: FirstOrderIirFilter,
     resampler: QualitySincResampler<2>,
+    msu1_resampler: Option<QualitySincResampler<2>>,
 }
 
 impl AudioResampler {
-    pub fn new() -> Self {
+    pub fn new(msu1_enabled: bool) -> Self {
         Self {
             dc_offset_l: new_dc_offset_filter(),
             dc_offset_r: new_dc_offset_filter(),
             resampler: QualitySincResampler::new(SNES_AUDIO_FREQUENCY, 48000.0),
+            msu1_resampler: msu1_enabled
+                .then(|| QualitySincResampler::new(msu1::MSU1_SAMPLE_RATE as f64, 48000.0)),
         }
     }
 
@@ -35,9 +42,36 @@ impl AudioResampler {
         self.resampler.collect([sample_l, sample_r]);
     }
 
+    pub fn collect_msu1_sample(&mut self, sample_l: f64, sample_r: f64) {
+        if let Some(msu1_resampler) = &mut self.msu1_resampler {
+            msu1_resampler.collect([sample_l, sample_r]);
+        }
+    }
+
     pub fn output_samples<A: AudioOutput>(&mut self, audio_output: &mut A) -> Result<(), A::Err> {
-        while let Some([sample_l, sample_r]) = self.resampler.output_buffer_pop_front() {
-            audio_output.push_sample(sample_l, sample_r)?;
+        let Some(msu1_resampler) = &mut self.msu1_resampler else {
+            while let Some([sample_l, sample_r]) = self.resampler.output_buffer_pop_front() {
+                audio_output.push_sample(sample_l, sample_r)?;
+            }
+
+            return Ok(());
+        };
+
+        // Both resamplers produce samples at the same rate, so mix whenever both have a sample ready
+        while self.resampler.output_buffer_len() != 0 && msu1_resampler.output_buffer_len() != 0 {
+            let [snes_l, snes_r] = self.resampler.output_buffer_pop_front().unwrap();
+            let [msu1_l, msu1_r] = msu1_resampler.output_buffer_pop_front().unwrap();
+            audio_output.push_sample(snes_l + msu1_l, snes_r + msu1_r)?;
+        }
+
+        // The two sample clocks can drift apart slightly (e.g. the MSU-1 has no samples while the
+        // game is lagging), so drop the oldest unmatched samples rather than letting whichever
+        // buffer is ahead grow without bound
+        while self.resampler.output_buffer_len() > MAX_UNMATCHED_SAMPLES {
+            let _ = self.resampler.output_buffer_pop_front();
+        }
+        while msu1_resampler.output_buffer_len() > MAX_UNMATCHED_SAMPLES {
+            let _ = msu1_resampler.output_buffer_pop_front();
         }
 
         Ok(())
@@ -45,5 +79,57 @@ impl AudioResampler {
 
     pub fn update_output_frequency(&mut self, output_frequency: u64) {
         self.resampler.update_output_frequency(output_frequency as f64);
+        if let Some(msu1_resampler) = &mut self.msu1_resampler {
+            msu1_resampler.update_output_frequency(output_frequency as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[derive(Default)]
+    struct CountingAudioOutput(u64);
+
+    impl AudioOutput for CountingAudioOutput {
+        type Err = Infallible;
+
+        fn push_sample(&mut self, _sample_l: f64, _sample_r: f64) -> Result<(), Self::Err> {
+            self.0 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn msu1_buffers_stay_bounded() {
+        let mut resampler = AudioResampler::new(true);
+        let mut audio_output = CountingAudioOutput::default();
+
+        // MSU-1 samples arrive ~1% faster than the SNES samples; without a bound, the MSU-1 buffer
+        // would grow by roughly 8 samples every frame
+        let snes_samples_per_frame = apu::OUTPUT_FREQUENCY / 60;
+        let msu1_samples_per_frame = msu1::MSU1_SAMPLE_RATE * 101 / 100 / 60;
+
+        for _ in 0..10 * 60 {
+            for _ in 0..snes_samples_per_frame {
+                resampler.collect_sample(0.0, 0.0);
+            }
+            for _ in 0..msu1_samples_per_frame {
+                resampler.collect_msu1_sample(0.0, 0.0);
+            }
+
+            let Ok(()) = resampler.output_samples(&mut audio_output);
+
+            assert!(resampler.resampler.output_buffer_len() <= MAX_UNMATCHED_SAMPLES);
+            assert!(
+                resampler.msu1_resampler.as_ref().unwrap().output_buffer_len()
+                    <= MAX_UNMATCHED_SAMPLES
+            );
+        }
+
+        // Roughly 10 seconds of audio at 48 KHz
+        assert!((470_000..490_000).contains(&audio_output.0), "{}", audio_output.0);
     }
 }
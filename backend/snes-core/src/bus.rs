@@ -88,8 +88,12 @@ impl Bus<'_> {
                 self.access_master_cycles = FAST_MASTER_CYCLES;
 
                 // Open bus with Fast memory speed
-                // Send to the cartridge first because some cartridges respond to these addresses
-                self.memory.read_cartridge(full_address).unwrap_or(self.memory.cpu_open_bus())
+                // Send to the MSU-1 and the cartridge first because some cartridges respond to
+                // these addresses
+                self.memory
+                    .read_msu1(full_address)
+                    .or_else(|| self.memory.read_cartridge(full_address))
+                    .unwrap_or(self.memory.cpu_open_bus())
             }
             0x6000..=0x7FFF => {
                 self.access_master_cycles = SLOW_MASTER_CYCLES;
@@ -109,9 +113,12 @@ impl Bus<'_> {
                 // First 8KB of WRAM
                 self.memory.write_wram(address, value);
             }
-            0x2000..=0x20FF | 0x2184..=0x21FF => {
-                // $2000-$2FFF: Open bus; do nothing (no coprocessors use this range)
-                // $2184-$21FF: Open bus in address bus B; do nothing
+            0x2000..=0x20FF => {
+                // Open bus unless MSU-1 is enabled (no coprocessors use this range)
+                self.memory.write_msu1(address, value);
+            }
+            0x2184..=0x21FF => {
+                // Open bus in address bus B; do nothing
             }
             0x2100..=0x213F => {
                 // PPU ports
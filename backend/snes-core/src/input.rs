@@ -2,6 +2,7 @@ use bincode::{Decode, Encode};
 use jgenesis_common::frontend::{DisplayArea, FrameSize, InputModal, MappableInputs};
 use jgenesis_common::input::Player;
 use snes_config::{SnesButton, SnesJoypadState, SuperScopeButton};
+use std::slice;
 
 pub(crate) trait SnesJoypadStateExt: Sized + Copy {
     #[must_use]
@@ -66,10 +67,33 @@ impl SuperScopeState {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub struct SnesMouseState {
+    pub left: bool,
+    pub right: bool,
+    // Cursor position in SNES pixels, or None if the cursor is outside of the display area. The
+    // mouse reports motion relative to the position at the previous latch
+    pub position: Option<(u16, u16)>,
+}
+
+/// Konami Justifier state. Only the first (blue) gun is supported; the second gun is always
+/// reported as disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub struct JustifierState {
+    pub trigger: bool,
+    pub start: bool,
+    // Same as Super Scope position
+    pub position: Option<(u16, u16)>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
 pub enum SnesInputDevice {
     Controller(SnesJoypadState),
     SuperScope(SuperScopeState),
+    Mouse(SnesMouseState),
+    Justifier(JustifierState),
+    /// Super Multitap with 4 controllers connected
+    Multitap([SnesJoypadState; 4]),
 }
 
 impl Default for SnesInputDevice {
@@ -78,31 +102,78 @@ impl Default for SnesInputDevice {
     }
 }
 
+impl SnesInputDevice {
+    fn joypads_mut(&mut self) -> &mut [SnesJoypadState] {
+        match self {
+            Self::Controller(joypad_state) => slice::from_mut(joypad_state),
+            Self::Multitap(joypad_states) => joypad_states,
+            Self::SuperScope(_) | Self::Mouse(_) | Self::Justifier(_) => &mut [],
+        }
+    }
+
+    fn set_position(&mut self, position: Option<(u16, u16)>) {
+        match self {
+            Self::SuperScope(super_scope_state) => super_scope_state.position = position,
+            Self::Mouse(mouse_state) => mouse_state.position = position,
+            Self::Justifier(justifier_state) => justifier_state.position = position,
+            Self::Controller(_) | Self::Multitap(_) => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
 pub struct SnesInputs {
-    pub p1: SnesJoypadState,
+    pub p1: SnesInputDevice,
     pub p2: SnesInputDevice,
 }
 
+impl SnesInputs {
+    fn set_peripheral_button(device: &mut SnesInputDevice, button: SnesButton, pressed: bool) {
+        match (device, button) {
+            (SnesInputDevice::SuperScope(super_scope_state), _) => {
+                if let Some(super_scope_button) = button.to_super_scope() {
+                    super_scope_state.set_button(super_scope_button, pressed);
+                }
+            }
+            (SnesInputDevice::Mouse(mouse_state), SnesButton::MouseLeft) => {
+                mouse_state.left = pressed;
+            }
+            (SnesInputDevice::Mouse(mouse_state), SnesButton::MouseRight) => {
+                mouse_state.right = pressed;
+            }
+            (SnesInputDevice::Justifier(justifier_state), SnesButton::JustifierTrigger) => {
+                justifier_state.trigger = pressed;
+            }
+            (SnesInputDevice::Justifier(justifier_state), SnesButton::JustifierStart) => {
+                justifier_state.start = pressed;
+            }
+            _ => {}
+        }
+    }
+}
+
 impl MappableInputs<SnesButton> for SnesInputs {
     #[inline]
     fn set_field(&mut self, button: SnesButton, player: Player, pressed: bool) {
-        if let Some(super_scope_button) = button.to_super_scope() {
-            if let SnesInputDevice::SuperScope(super_scope_state) = &mut self.p2 {
-                super_scope_state.set_button(super_scope_button, pressed);
-            }
+        if button.is_peripheral() {
+            Self::set_peripheral_button(&mut self.p1, button, pressed);
+            Self::set_peripheral_button(&mut self.p2, button, pressed);
             return;
         }
 
-        match player {
-            Player::One => {
-                self.p1.set_button(button, pressed);
-            }
-            Player::Two => {
-                if let SnesInputDevice::Controller(joypad_state) = &mut self.p2 {
-                    joypad_state.set_button(button, pressed);
-                }
-            }
+        // Players are assigned to controllers in port order, so with a multitap in port 2, players
+        // 2-5 are connected to the multitap
+        let player_idx = match player {
+            Player::One => 0,
+            Player::Two => 1,
+            Player::Three => 2,
+            Player::Four => 3,
+            Player::Five => 4,
+        };
+        if let Some(joypad_state) =
+            self.p1.joypads_mut().iter_mut().chain(self.p2.joypads_mut()).nth(player_idx)
+        {
+            joypad_state.set_button(button, pressed);
         }
     }
 
@@ -114,23 +185,21 @@ impl MappableInputs<SnesButton> for SnesInputs {
         frame_size: FrameSize,
         display_area: DisplayArea,
     ) {
-        if let SnesInputDevice::SuperScope(super_scope_state) = &mut self.p2 {
-            super_scope_state.position =
-                jgenesis_common::input::viewport_position_to_frame_position(
-                    x,
-                    y,
-                    frame_size,
-                    display_area,
-                );
-            log::debug!("Set Super Scope position to {:?}", super_scope_state.position);
-        }
+        let position = jgenesis_common::input::viewport_position_to_frame_position(
+            x,
+            y,
+            frame_size,
+            display_area,
+        );
+        self.p1.set_position(position);
+        self.p2.set_position(position);
+        log::debug!("Set pointer position to {position:?}");
     }
 
     #[inline]
     fn handle_mouse_leave(&mut self) {
-        if let SnesInputDevice::SuperScope(super_scope_state) = &mut self.p2 {
-            super_scope_state.position = None;
-        }
+        self.p1.set_position(None);
+        self.p2.set_position(None);
     }
 
     fn modal_for_input(
@@ -143,7 +212,10 @@ impl MappableInputs<SnesButton> for SnesInputs {
             return None;
         }
 
-        let SnesInputDevice::SuperScope(super_scope_state) = self.p2 else { return None };
+        let super_scope_state = [self.p1, self.p2].into_iter().find_map(|device| match device {
+            SnesInputDevice::SuperScope(super_scope_state) => Some(super_scope_state),
+            _ => None,
+        })?;
 
         let text =
             format!("Super Scope Turbo: {}", if super_scope_state.turbo { "On" } else { "Off" });
@@ -3,18 +3,23 @@
 pub(crate) mod cartridge;
 pub(crate) mod dma;
 mod inputs;
+pub(crate) mod msu1;
 
 use crate::api::{CoprocessorRoms, SnesLoadResult};
+use crate::audio::AudioResampler;
 use crate::input::SnesInputs;
 use crate::memory::cartridge::Cartridge;
 use crate::memory::inputs::InputState;
+use crate::memory::msu1::Msu1;
 use crate::ppu::Ppu;
 use bincode::{Decode, Encode};
+use jgenesis_common::cheats::{CheatSet, RamWrite};
 use jgenesis_common::frontend::{SaveWriter, TimingMode};
 use jgenesis_common::num::{GetBit, U16Ext, U24Ext};
 use jgenesis_proc_macros::PartialClone;
+use std::borrow::Cow;
 use std::num::NonZeroU64;
-use std::{array, iter};
+use std::{array, iter, mem};
 
 const MAIN_RAM_LEN: usize = 128 * 1024;
 
@@ -40,10 +45,13 @@ impl Memory2Speed {
 pub struct Memory {
     #[partial_clone(partial)]
     cartridge: Cartridge,
+    msu1: Option<Msu1>,
     main_ram: Box<MainRam>,
     wram_port_address: u32,
     cpu_open_bus: u8,
     cartridge_timing_mode: TimingMode,
+    #[partial_clone(default)]
+    cheats: CheatSet,
 }
 
 impl Memory {
@@ -53,6 +61,7 @@ impl Memory {
         coprocessor_roms: &CoprocessorRoms,
         forced_timing_mode: Option<TimingMode>,
         gsu_overclock_factor: NonZeroU64,
+        audio_60hz_hack: bool,
         save_writer: &mut S,
     ) -> SnesLoadResult<Self> {
         let (cartridge, cartridge_timing_mode) = Cartridge::create(
@@ -66,20 +75,29 @@ impl Memory {
 
         log::info!("Cartridge has battery-backed SRAM: {}", cartridge.has_battery());
 
+        let msu1_timing_mode = forced_timing_mode.unwrap_or(cartridge_timing_mode);
+        let msu1 = coprocessor_roms
+            .msu1_data_path
+            .as_ref()
+            .map(|path| Msu1::new(path, msu1_timing_mode, audio_60hz_hack));
+
         let main_ram: Vec<u8> = iter::repeat_with(rand::random).take(MAIN_RAM_LEN).collect();
 
         Ok(Self {
             cartridge,
+            msu1,
             main_ram: main_ram.into_boxed_slice().try_into().unwrap(),
             wram_port_address: 0,
             cpu_open_bus: 0,
             cartridge_timing_mode,
+            cheats: CheatSet::default(),
         })
     }
 
     pub fn read_cartridge(&mut self, address: u32) -> Option<u8> {
         match self.cartridge.read(address) {
             Some(value) => {
+                let value = self.cheats.patch_rom_u8(address, value);
                 self.cpu_open_bus = value;
                 Some(value)
             }
@@ -91,6 +109,26 @@ impl Memory {
         self.cartridge.write(address, value);
     }
 
+    pub fn read_msu1(&mut self, address: u32) -> Option<u8> {
+        self.msu1.as_mut().and_then(|msu1| msu1.read(address))
+    }
+
+    pub fn write_msu1(&mut self, address: u32, value: u8) {
+        if let Some(msu1) = &mut self.msu1 {
+            msu1.write(address, value);
+        }
+    }
+
+    pub fn has_msu1(&self) -> bool {
+        self.msu1.is_some()
+    }
+
+    pub fn tick_msu1(&mut self, master_cycles_elapsed: u64, audio_resampler: &mut AudioResampler) {
+        if let Some(msu1) = &mut self.msu1 {
+            msu1.tick(master_cycles_elapsed, audio_resampler);
+        }
+    }
+
     pub fn cartridge_irq(&self) -> bool {
         self.cartridge.irq()
     }
@@ -162,14 +200,42 @@ impl Memory {
 
     pub fn take_rom_from(&mut self, other: &mut Self) {
         self.cartridge.take_rom_from(&mut other.cartridge);
+        if let (Some(msu1), Some(other_msu1)) = (&mut self.msu1, &mut other.msu1) {
+            msu1.take_files_from(other_msu1);
+        }
+        self.cheats = mem::take(&mut other.cheats);
+    }
+
+    pub fn set_cheats(&mut self, cheats: CheatSet) {
+        self.cheats = cheats;
+    }
+
+    pub fn apply_cheat_ram_writes(&mut self) {
+        for &RamWrite { address, value, .. } in self.cheats.ram_writes() {
+            let bank = address >> 16;
+            let offset = address & 0xFFFF;
+            match (bank, offset) {
+                (0x7E..=0x7F, _) => {
+                    self.main_ram[(address as usize) & (MAIN_RAM_LEN - 1)] = value;
+                }
+                (0x00..=0x3F | 0x80..=0xBF, 0x0000..=0x1FFF) => {
+                    self.main_ram[offset as usize] = value;
+                }
+                _ => self.cartridge.write_cheat_ram(address, value),
+            }
+        }
     }
 
     pub fn sram(&self) -> Option<&[u8]> {
         self.cartridge.sram()
     }
 
+    pub fn sram_extension(&self) -> Cow<'static, str> {
+        self.cartridge.sram_extension()
+    }
+
     pub fn write_auxiliary_save_files<S: SaveWriter>(
-        &self,
+        &mut self,
         save_writer: &mut S,
     ) -> Result<(), S::Err> {
         self.cartridge.write_auxiliary_save_files(save_writer)
@@ -190,6 +256,9 @@ impl Memory {
     pub fn reset(&mut self) {
         self.wram_port_address = 0;
         self.cartridge.reset();
+        if let Some(msu1) = &mut self.msu1 {
+            msu1.reset();
+        }
     }
 
     // Called when GPDMA begins, or when it starts on a new channel
@@ -202,9 +271,19 @@ impl Memory {
         self.cartridge.notify_dma_end();
     }
 
+    pub fn take_cartridge_audio_sample(&mut self) -> Option<(f64, f64)> {
+        self.cartridge.take_audio_sample()
+    }
+
     pub fn update_gsu_overclock_factor(&mut self, overclock_factor: NonZeroU64) {
         self.cartridge.update_gsu_overclock_factor(overclock_factor);
     }
+
+    pub fn update_audio_60hz_hack(&mut self, audio_60hz_hack: bool) {
+        if let Some(msu1) = &mut self.msu1 {
+            msu1.update_audio_60hz_hack(audio_60hz_hack);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
@@ -394,13 +473,15 @@ impl CpuInternalRegisters {
             0x4016 => {
                 // JOYA: Manual joypad register A
                 // Bits 7-2 are open bus
-                u8::from(self.input_state.next_manual_p1_bit()) | (cpu_open_bus & 0xFC)
+                self.input_state.read_manual_port(0, self.programmable_joypad_port)
+                    | (cpu_open_bus & 0xFC)
             }
             0x4017 => {
                 // JOYB: Manual joypad register B
                 // Bits 2-4 always set
                 // Bits 7-5 are open bus
-                0x1C | u8::from(self.input_state.next_manual_p2_bit()) | (cpu_open_bus & 0xE0)
+                0x1C | self.input_state.read_manual_port(1, self.programmable_joypad_port)
+                    | (cpu_open_bus & 0xE0)
             }
             0x4210 => {
                 // RDNMI: VBlank NMI flag and CPU version number
@@ -453,23 +534,35 @@ impl CpuInternalRegisters {
             }
             0x4218 => {
                 // JOY1L: Joypad 1, low byte (auto read)
-                self.input_state.auto_joypad_p1_inputs().lsb()
+                self.input_state.auto_joypad_inputs(0).lsb()
             }
             0x4219 => {
                 // JOY1H: Joypad 1, high byte (auto read)
-                self.input_state.auto_joypad_p1_inputs().msb()
+                self.input_state.auto_joypad_inputs(0).msb()
             }
             0x421A => {
                 // JOY2L: Joypad 2, low byte (auto read)
-                self.input_state.auto_joypad_p2_inputs().lsb()
+                self.input_state.auto_joypad_inputs(1).lsb()
             }
             0x421B => {
                 // JOY2H: Joypad 2, high byte (auto read)
-                self.input_state.auto_joypad_p2_inputs().msb()
+                self.input_state.auto_joypad_inputs(1).msb()
+            }
+            0x421C => {
+                // JOY3L: Joypad 3, low byte (auto read)
+                self.input_state.auto_joypad_inputs(2).lsb()
+            }
+            0x421D => {
+                // JOY3H: Joypad 3, high byte (auto read)
+                self.input_state.auto_joypad_inputs(2).msb()
+            }
+            0x421E => {
+                // JOY4L: Joypad 4, low byte (auto read)
+                self.input_state.auto_joypad_inputs(3).lsb()
             }
-            0x421C..=0x421F => {
-                // JOY3L/JOY3H/JOY4L/JOY4H: Joypad 3/4 (not implemented)
-                0x00
+            0x421F => {
+                // JOY4H: Joypad 4, high byte (auto read)
+                self.input_state.auto_joypad_inputs(3).msb()
             }
             0x4300..=0x437F => {
                 // DMA registers
@@ -808,7 +901,7 @@ impl CpuInternalRegisters {
 
     pub fn tick(&mut self, master_cycles_elapsed: u64, ppu: &Ppu, inputs: &SnesInputs) {
         // Progress auto joypad read if it's running
-        self.input_state.tick(master_cycles_elapsed, *inputs);
+        self.input_state.tick(master_cycles_elapsed, *inputs, self.programmable_joypad_port);
 
         // Update VBlank, HBlank, and NMI flags
         self.update_hv_blank_flags(ppu);
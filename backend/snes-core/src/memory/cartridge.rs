@@ -1,8 +1,12 @@
 //! SNES cartridge loading and mapping code
 
+mod sgb;
+
 use crate::api::{CoprocessorRoms, SnesLoadError, SnesLoadResult};
+use crate::memory::cartridge::sgb::{GbSaveWriter, SuperGameBoy};
 use bincode::{Decode, Encode};
 use crc::Crc;
+use gb_core::api::GameBoyEmulator;
 use jgenesis_common::frontend::{PartialClone, SaveWriter, TimingMode};
 use jgenesis_proc_macros::{FakeDecode, FakeEncode};
 use snes_coprocessors::cx4::Cx4;
@@ -15,6 +19,7 @@ use snes_coprocessors::st018::St018;
 use snes_coprocessors::superfx::SuperFx;
 use snes_coprocessors::upd77c25::{Upd77c25, Upd77c25Variant};
 use snes_coprocessors::{superfx, upd77c25};
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use std::mem;
@@ -51,6 +56,7 @@ enum CartridgeType {
     Sdd1,
     Spc7110,
     SuperFx,
+    SuperGameBoy,
 }
 
 impl Display for CartridgeType {
@@ -65,6 +71,7 @@ impl Display for CartridgeType {
             Self::Sdd1 => write!(f, "S-DD1"),
             Self::Spc7110 => write!(f, "SPC7110"),
             Self::SuperFx => write!(f, "Super FX"),
+            Self::SuperGameBoy => write!(f, "Super Game Boy"),
         }
     }
 }
@@ -194,6 +201,7 @@ pub enum Cartridge {
     Sdd1(#[partial_clone(partial)] Sdd1),
     Spc7110(#[partial_clone(partial)] Spc7110),
     SuperFx(#[partial_clone(partial)] SuperFx),
+    SuperGameBoy(#[partial_clone(partial)] Box<SuperGameBoy>),
     St01x {
         #[partial_clone(default)]
         rom: Rom,
@@ -245,7 +253,8 @@ impl Cartridge {
             | CartridgeType::Obc1
             | CartridgeType::Sa1
             | CartridgeType::Sdd1
-            | CartridgeType::SuperFx => LOROM_HEADER_ADDR,
+            | CartridgeType::SuperFx
+            | CartridgeType::SuperGameBoy => LOROM_HEADER_ADDR,
             CartridgeType::HiRom | CartridgeType::Spc7110 => HIROM_HEADER_ADDR,
             CartridgeType::ExHiRom => EXHIROM_HEADER_ADDR,
         };
@@ -369,6 +378,24 @@ impl Cartridge {
             };
         }
 
+        if cartridge_type == CartridgeType::SuperGameBoy {
+            let gb_rom_fn = coprocessor_roms
+                .sgb_cartridge
+                .as_ref()
+                .ok_or(SnesLoadError::MissingSgbCartridgeRom)?;
+            let gb_rom = gb_rom_fn()
+                .map_err(|(source, path)| SnesLoadError::CoprocessorRomLoad { source, path })?;
+            let gb_rom_crc = sgb::gb_rom_crc(&gb_rom);
+            let gb = GameBoyEmulator::create_super_game_boy(
+                gb_rom,
+                &mut GbSaveWriter::new(save_writer, gb_rom_crc),
+            )
+            .map_err(SnesLoadError::SgbCartridgeLoad)?;
+
+            let sgb = SuperGameBoy::new(rom, gb, gb_rom_crc, timing_mode);
+            return Ok((Self::SuperGameBoy(Box::new(sgb)), timing_mode));
+        }
+
         let cartridge = match cartridge_type {
             CartridgeType::LoRom => Self::LoRom { rom: Rom(rom), sram },
             CartridgeType::HiRom => Self::HiRom { rom: Rom(rom), sram },
@@ -379,6 +406,7 @@ impl Cartridge {
             CartridgeType::Sdd1 => Self::Sdd1(Sdd1::new(rom, sram)),
             CartridgeType::Spc7110 => Self::Spc7110(Spc7110::new(rom, sram, save_writer)),
             CartridgeType::SuperFx => Self::SuperFx(SuperFx::new(rom, sram, gsu_overclock_factor)),
+            CartridgeType::SuperGameBoy => unreachable!("Super Game Boy handled above"),
         };
 
         Ok((cartridge, timing_mode))
@@ -422,6 +450,7 @@ impl Cartridge {
             Self::Sdd1(sdd1) => return sdd1.read(address),
             Self::Spc7110(spc7110) => return spc7110.read(address),
             Self::SuperFx(sfx) => return sfx.read(address),
+            Self::SuperGameBoy(sgb) => return sgb.read(address),
             Self::St01x { rom, upd77c25 } => {
                 return match (bank, offset) {
                     (0x60..=0x67, 0x0000) => Some(upd77c25.read_data()),
@@ -531,6 +560,9 @@ impl Cartridge {
             Self::SuperFx(sfx) => {
                 sfx.write(address, value);
             }
+            Self::SuperGameBoy(sgb) => {
+                sgb.write(address, value);
+            }
             Self::St01x { upd77c25, .. } => match (bank, offset) {
                 (0x60..=0x67, 0x0000) => upd77c25.write_data(value),
                 (0x68..=0x6F, 0x0000..=0x0FFF) => {
@@ -550,6 +582,38 @@ impl Cartridge {
         }
     }
 
+    /// Write directly to cartridge RAM at the given SNES address, bypassing coprocessor registers and
+    /// RAM write protection. Used for cheats.
+    ///
+    /// Writes to boards whose RAM is only accessible through a coprocessor other than the SA-1 or
+    /// Super FX are ignored.
+    pub fn write_cheat_ram(&mut self, address: u32, value: u8) {
+        let (sram_addr, sram) = match self {
+            Self::LoRom { rom, sram } | Self::DspLoRom { rom, sram, .. } => {
+                (lorom_map_address(address, rom.len() as u32, sram.len() as u32), sram)
+            }
+            Self::HiRom { rom, sram } | Self::DspHiRom { rom, sram, .. } => {
+                (hirom_map_address(address, rom.len() as u32, sram.len() as u32), sram)
+            }
+            Self::ExHiRom { rom, sram, .. } => {
+                (exhirom_map_address(address, rom.len() as u32, sram.len() as u32), sram)
+            }
+            Self::Sa1(sa1) => {
+                sa1.write_cheat_ram(address, value);
+                return;
+            }
+            Self::SuperFx(sfx) => {
+                sfx.write_cheat_ram(address, value);
+                return;
+            }
+            _ => return,
+        };
+
+        if let CartridgeAddress::Sram(sram_addr) = sram_addr {
+            sram[sram_addr as usize] = value;
+        }
+    }
+
     pub fn irq(&self) -> bool {
         match self {
             Self::Sa1(sa1) => sa1.snes_irq(),
@@ -573,6 +637,7 @@ impl Cartridge {
             Self::Sdd1(sdd1) => sdd1.take_rom(),
             Self::Spc7110(spc7110) => spc7110.take_rom(),
             Self::SuperFx(sfx) => sfx.take_rom(),
+            Self::SuperGameBoy(sgb) => sgb.take_rom(),
         }
     }
 
@@ -607,6 +672,12 @@ impl Cartridge {
             Self::SuperFx(sfx) => {
                 sfx.set_rom(other_rom);
             }
+            Self::SuperGameBoy(sgb) => {
+                // Also need to take the Game Boy cartridge ROM
+                if let Self::SuperGameBoy(other_sgb) = other {
+                    sgb.set_bios_and_take_gb_rom_from(other_rom, other_sgb);
+                }
+            }
         }
     }
 
@@ -625,6 +696,7 @@ impl Cartridge {
             Self::Sa1(sa1) => sa1.has_battery(),
             Self::Sdd1(sdd1) => sdd1.has_battery(),
             Self::SuperFx(sfx) => sfx.has_battery(),
+            Self::SuperGameBoy(sgb) => sgb.has_battery(),
         }
     }
 
@@ -652,22 +724,34 @@ impl Cartridge {
             Self::SuperFx(sfx) => Some(sfx.sram()),
             Self::St01x { upd77c25, .. } => Some(upd77c25.sram()),
             Self::St018 { sram, .. } => Some(sram),
+            Self::SuperGameBoy(sgb) => sgb.sram(),
+        }
+    }
+
+    /// Extension of the save file that [`Self::sram`] should be persisted to.
+    pub fn sram_extension(&self) -> Cow<'static, str> {
+        match self {
+            Self::SuperGameBoy(sgb) => Cow::Owned(sgb.sram_extension()),
+            _ => Cow::Borrowed("sav"),
         }
     }
 
     pub fn write_auxiliary_save_files<S: SaveWriter>(
-        &self,
+        &mut self,
         save_writer: &mut S,
     ) -> Result<(), S::Err> {
         match self {
             Self::ExHiRom { srtc: Some(srtc), .. } => {
-                save_writer.persist_serialized("rtc", srtc)?;
+                save_writer.persist_serialized("rtc", &*srtc)?;
             }
             Self::Spc7110(spc7110) => {
                 if let Some(rtc) = spc7110.rtc() {
                     save_writer.persist_serialized("rtc", rtc)?;
                 }
             }
+            Self::SuperGameBoy(sgb) => {
+                sgb.save_rtc_state(save_writer)?;
+            }
             _ => {}
         }
 
@@ -690,6 +774,9 @@ impl Cartridge {
             Self::St018 { st018, .. } => {
                 st018.tick(master_cycles_elapsed);
             }
+            Self::SuperGameBoy(sgb) => {
+                sgb.tick(master_cycles_elapsed);
+            }
             _ => {}
         }
     }
@@ -713,6 +800,9 @@ impl Cartridge {
             Self::SuperFx(sfx) => {
                 sfx.reset();
             }
+            Self::SuperGameBoy(sgb) => {
+                sgb.reset();
+            }
             _ => {}
         }
     }
@@ -733,6 +823,14 @@ impl Cartridge {
         }
     }
 
+    // Audio from the cartridge's audio input; called once per SNES audio sample
+    pub fn take_audio_sample(&mut self) -> Option<(f64, f64)> {
+        match self {
+            Self::SuperGameBoy(sgb) => sgb.take_audio_sample(),
+            _ => None,
+        }
+    }
+
     pub fn update_gsu_overclock_factor(&mut self, overclock_factor: NonZeroU64) {
         if let Self::SuperFx(sfx) = self {
             sfx.update_gsu_overclock_factor(overclock_factor);
@@ -906,6 +1004,12 @@ fn check_for_lorom_coprocessor(rom: &[u8]) -> Option<CartridgeType> {
         return Some(CartridgeType::Obc1);
     }
 
+    // Check for Super Game Boy / Super Game Boy 2
+    // Identified by chipset $E3 in the LoROM header area
+    if rom[LOROM_HEADER_ADDR + 0x16] == 0xE3 {
+        return Some(CartridgeType::SuperGameBoy);
+    }
+
     None
 }
 
@@ -0,0 +1,507 @@
+//! Super Game Boy cartridge: the SGB BIOS plus an embedded Game Boy, connected to the
+//! SNES through the ICD2 chip
+//!
+//! The ICD2 captures the Game Boy's LCD output into a 4-bank ring buffer of 2bpp tile rows that the
+//! SNES reads through a port, supplies joypad inputs to the Game Boy, and forwards the command
+//! packets that the Game Boy sends through JOYP. Everything visible about SGB enhancements
+//! (borders, palettes, attribute maps, multiplayer) is implemented by the SNES-side BIOS code,
+//! which reads the command packets through the ICD2 and renders the Game Boy screen using the
+//! SNES PPU.
+//!
+//! Game Boy audio is mixed into the SNES audio output, as the real SGB does through the cartridge
+//! audio input.
+
+use crate::apu;
+use crate::constants;
+use crate::memory::cartridge::{CartridgeAddress, Rom, lorom_map_address};
+use bincode::{Decode, Encode};
+use crc::Crc;
+use gb_core::api::GameBoyEmulator;
+use jgenesis_common::frontend::{AudioOutput, EmulatorTrait, PartialClone, SaveWriter, TimingMode};
+use jgenesis_common::num::GetBit;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+// The SGB2 clocks the Game Boy from its own 20.97152 MHz crystal instead of the SNES master clock
+const SGB2_CRYSTAL_FREQUENCY: u64 = 20_971_520;
+
+const SGB2_TITLE: &[u8] = b"Super GAMEBOY2";
+
+const GB_VISIBLE_LINES: u8 = 144;
+
+// 4 banks of 8 lines, each line 20 tiles * 2 bytes
+const LCD_BANK_LEN: usize = 512;
+const LCD_BUFFER_LEN: usize = 4 * LCD_BANK_LEN;
+const LCD_ROW_LEN: u16 = 320;
+
+const ICD2_VERSION: u8 = 0x21;
+
+// Roughly 1/8 second at 32 KHz; keeps the audio latency bounded if the two sample clocks drift
+const MAX_BUFFERED_AUDIO_SAMPLES: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum SgbVariant {
+    Sgb1,
+    Sgb2,
+}
+
+impl SgbVariant {
+    pub fn from_rom(rom: &[u8]) -> Self {
+        let title = &rom[super::LOROM_HEADER_ADDR..super::LOROM_HEADER_ADDR + 21];
+        if title.starts_with(SGB2_TITLE) { Self::Sgb2 } else { Self::Sgb1 }
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct Icd2 {
+    lcd_buffer: Box<[u8; LCD_BUFFER_LEN]>,
+    write_bank: u8,
+    lcd_line: u8,
+    read_bank: u8,
+    read_address: u16,
+    control: u8,
+    packet_buffer: [u8; gb_core::sgb::SGB_PACKET_LEN],
+}
+
+impl Icd2 {
+    fn new() -> Self {
+        Self {
+            lcd_buffer: vec![0; LCD_BUFFER_LEN].into_boxed_slice().try_into().unwrap(),
+            write_bank: 0,
+            lcd_line: 0,
+            read_bank: 0,
+            read_address: 0,
+            control: 0,
+            packet_buffer: [0; gb_core::sgb::SGB_PACKET_LEN],
+        }
+    }
+
+    fn gb_running(&self) -> bool {
+        // Bit 7 clear holds the Game Boy in reset
+        self.control.bit(7)
+    }
+
+    fn clock_divider(&self) -> u64 {
+        match self.control & 3 {
+            0 => 4,
+            1 => 5,
+            2 => 7,
+            3 => 9,
+            _ => unreachable!("value & 3 is always <= 3"),
+        }
+    }
+
+    fn capture_line(&mut self, shades: &[u16]) {
+        let bank_addr = usize::from(self.write_bank) * LCD_BANK_LEN;
+        let row_addr = bank_addr + 2 * usize::from(self.lcd_line & 7);
+
+        for (x, &shade) in shades.iter().enumerate() {
+            let addr = row_addr + (x / 8) * 16;
+            let bit = 7 - (x % 8);
+
+            let lsb = &mut self.lcd_buffer[addr];
+            *lsb = (*lsb & !(1 << bit)) | (u8::from(shade.bit(0)) << bit);
+
+            let msb = &mut self.lcd_buffer[addr + 1];
+            *msb = (*msb & !(1 << bit)) | (u8::from(shade.bit(1)) << bit);
+        }
+    }
+
+    fn next_line(&mut self, new_scanline: u8) {
+        if new_scanline == 0 {
+            self.lcd_line = 0;
+            return;
+        }
+
+        self.lcd_line = self.lcd_line.wrapping_add(1);
+        if self.lcd_line & 7 == 0 {
+            self.write_bank = (self.write_bank + 1) & 3;
+        }
+    }
+}
+
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Save writer for the Game Boy cartridge inside a Super Game Boy.
+///
+/// The SNES save writer's files are named after the SGB BIOS, so the Game Boy cartridge's save
+/// files additionally include a checksum of the Game Boy ROM in their extension, e.g.
+/// `gb-1a2b3c4d.sav`. This keeps different Game Boy games played through the same SGB BIOS from
+/// sharing saves.
+pub struct GbSaveWriter<'a, S> {
+    save_writer: &'a mut S,
+    gb_rom_crc: u32,
+}
+
+impl<'a, S> GbSaveWriter<'a, S> {
+    pub fn new(save_writer: &'a mut S, gb_rom_crc: u32) -> Self {
+        Self { save_writer, gb_rom_crc }
+    }
+
+    fn extension(&self, extension: &str) -> String {
+        gb_save_extension(self.gb_rom_crc, extension)
+    }
+}
+
+pub fn gb_rom_crc(gb_rom: &[u8]) -> u32 {
+    CRC.checksum(gb_rom)
+}
+
+fn gb_save_extension(gb_rom_crc: u32, extension: &str) -> String {
+    format!("gb-{gb_rom_crc:08x}.{extension}")
+}
+
+impl<S: SaveWriter> SaveWriter for GbSaveWriter<'_, S> {
+    type Err = S::Err;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        let extension = self.extension(extension);
+        self.save_writer.load_bytes(&extension)
+    }
+
+    fn persist_bytes(&mut self, extension: &str, bytes: &[u8]) -> Result<(), Self::Err> {
+        let extension = self.extension(extension);
+        self.save_writer.persist_bytes(&extension, bytes)
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        let extension = self.extension(extension);
+        self.save_writer.load_serialized(&extension)
+    }
+
+    fn persist_serialized<E: Encode>(&mut self, extension: &str, data: E) -> Result<(), Self::Err> {
+        let extension = self.extension(extension);
+        self.save_writer.persist_serialized(&extension, data)
+    }
+}
+
+// Collects Game Boy audio samples for mixing into the SNES audio output
+#[derive(Debug, Clone, Default, Encode, Decode)]
+struct SampleBuffer(VecDeque<(f64, f64)>);
+
+impl AudioOutput for SampleBuffer {
+    type Err = Infallible;
+
+    fn push_sample(&mut self, sample_l: f64, sample_r: f64) -> Result<(), Self::Err> {
+        if self.0.len() == MAX_BUFFERED_AUDIO_SAMPLES {
+            self.0.pop_front();
+        }
+        self.0.push_back((sample_l, sample_r));
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode, PartialClone)]
+pub struct SuperGameBoy {
+    #[partial_clone(default)]
+    bios: Rom,
+    #[partial_clone(partial)]
+    gb: GameBoyEmulator,
+    gb_rom_crc: u32,
+    icd2: Icd2,
+    variant: SgbVariant,
+    snes_master_clock_frequency: u64,
+    // In units of (crystal cycles * SNES master clock frequency); can go negative because
+    // instructions are executed atomically
+    cycle_accumulator: i64,
+    audio_samples: SampleBuffer,
+}
+
+impl SuperGameBoy {
+    pub fn new(
+        bios: Box<[u8]>,
+        gb: GameBoyEmulator,
+        gb_rom_crc: u32,
+        timing_mode: TimingMode,
+    ) -> Self {
+        let variant = SgbVariant::from_rom(&bios);
+        let snes_master_clock_frequency = match timing_mode {
+            TimingMode::Ntsc => constants::NTSC_MASTER_CLOCK_FREQUENCY,
+            TimingMode::Pal => constants::PAL_MASTER_CLOCK_FREQUENCY,
+        };
+
+        log::info!("Super Game Boy variant: {variant:?}");
+
+        let mut sgb = Self {
+            bios: Rom(bios),
+            gb,
+            gb_rom_crc,
+            icd2: Icd2::new(),
+            variant,
+            snes_master_clock_frequency,
+            cycle_accumulator: 0,
+            audio_samples: SampleBuffer::default(),
+        };
+        sgb.gb.update_audio_output_frequency(apu::OUTPUT_FREQUENCY);
+        sgb.update_gb_clock_frequency();
+
+        sgb
+    }
+
+    fn crystal_frequency(&self) -> u64 {
+        match self.variant {
+            SgbVariant::Sgb1 => self.snes_master_clock_frequency,
+            SgbVariant::Sgb2 => SGB2_CRYSTAL_FREQUENCY,
+        }
+    }
+
+    fn update_gb_clock_frequency(&mut self) {
+        let gb_clock_frequency = self.crystal_frequency() as f64 / self.icd2.clock_divider() as f64;
+        self.gb.sgb_set_clock_frequency(gb_clock_frequency);
+    }
+
+    pub fn read(&mut self, address: u32) -> Option<u8> {
+        let bank = (address >> 16) & 0xFF;
+        let offset = address & 0xFFFF;
+        match (bank, offset) {
+            (0x00..=0x3F | 0x80..=0xBF, 0x6000..=0x7FFF) => self.read_icd2(offset),
+            _ => match lorom_map_address(address, self.bios.len() as u32, 0) {
+                CartridgeAddress::Rom(rom_addr) => Some(self.bios[rom_addr as usize]),
+                _ => None,
+            },
+        }
+    }
+
+    fn read_icd2(&mut self, offset: u32) -> Option<u8> {
+        match offset {
+            0x6000 => Some((self.icd2.lcd_line & !7) | self.icd2.write_bank),
+            0x6002 => {
+                let packet = self.gb.sgb_take_packet();
+                if let Some(packet) = packet {
+                    self.icd2.packet_buffer = packet;
+                }
+                Some(packet.is_some().into())
+            }
+            0x600F => Some(ICD2_VERSION),
+            0x7000..=0x700F => Some(self.icd2.packet_buffer[(offset & 0xF) as usize]),
+            0x7800 => {
+                let addr = usize::from(self.icd2.read_bank) * LCD_BANK_LEN
+                    + usize::from(self.icd2.read_address);
+                self.icd2.read_address = (self.icd2.read_address + 1) % LCD_ROW_LEN;
+                Some(self.icd2.lcd_buffer[addr])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn write(&mut self, address: u32, value: u8) {
+        let bank = (address >> 16) & 0xFF;
+        let offset = address & 0xFFFF;
+        if !matches!(bank, 0x00..=0x3F | 0x80..=0xBF) {
+            return;
+        }
+
+        match offset {
+            0x6001 => {
+                self.icd2.read_bank = value & 3;
+                self.icd2.read_address = 0;
+            }
+            0x6003 => self.write_control(value),
+            0x6004..=0x6007 => {
+                self.gb.sgb_set_joypad((offset & 3) as usize, value);
+            }
+            _ => {}
+        }
+    }
+
+    fn write_control(&mut self, value: u8) {
+        log::trace!("ICD2 control write: {value:02X}");
+
+        if !self.icd2.gb_running() && value.bit(7) {
+            // Game Boy is coming out of reset
+            self.gb.sgb_reset();
+            self.cycle_accumulator = 0;
+        }
+
+        if (value ^ self.icd2.control) & 0x30 != 0 {
+            self.gb.sgb_set_player_count((value >> 4) & 3);
+        }
+
+        let prev_divider = self.icd2.clock_divider();
+        self.icd2.control = value;
+        if self.icd2.clock_divider() != prev_divider {
+            self.update_gb_clock_frequency();
+        }
+    }
+
+    pub fn tick(&mut self, master_cycles_elapsed: u64) {
+        if !self.icd2.gb_running() {
+            return;
+        }
+
+        self.cycle_accumulator += (master_cycles_elapsed * self.crystal_frequency()) as i64;
+
+        let gb_cycle_cost = (self.icd2.clock_divider() * self.snes_master_clock_frequency) as i64;
+        while self.cycle_accumulator >= gb_cycle_cost {
+            let prev_scanline = self.gb.sgb_scanline();
+            let gb_cycles = self.gb.sgb_step();
+            self.cycle_accumulator -= gb_cycles as i64 * gb_cycle_cost;
+
+            let scanline = self.gb.sgb_scanline();
+            if scanline != prev_scanline {
+                if prev_scanline < GB_VISIBLE_LINES {
+                    self.icd2.capture_line(self.gb.sgb_line_shades(prev_scanline));
+                }
+                self.icd2.next_line(scanline);
+            }
+        }
+
+        let Ok(()) = self.gb.sgb_drain_audio_samples(&mut self.audio_samples);
+    }
+
+    pub fn take_audio_sample(&mut self) -> Option<(f64, f64)> {
+        self.audio_samples.0.pop_front()
+    }
+
+    pub fn reset(&mut self) {
+        // SNES reset also resets the ICD2, which holds the Game Boy in reset
+        self.icd2 = Icd2::new();
+        self.update_gb_clock_frequency();
+        self.audio_samples.0.clear();
+    }
+
+    pub fn take_rom(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.bios.0).into_vec()
+    }
+
+    pub fn set_bios_and_take_gb_rom_from(&mut self, bios: Vec<u8>, other: &mut Self) {
+        self.bios = Rom(bios.into_boxed_slice());
+        self.gb.take_rom_from(&mut other.gb);
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.gb.has_battery()
+    }
+
+    pub fn sram(&self) -> Option<&[u8]> {
+        let sram = self.gb.sram();
+        (self.gb.has_battery() && !sram.is_empty()).then_some(sram)
+    }
+
+    pub fn sram_extension(&self) -> String {
+        gb_save_extension(self.gb_rom_crc, "sav")
+    }
+
+    pub fn save_rtc_state<S: SaveWriter>(&mut self, save_writer: &mut S) -> Result<(), S::Err> {
+        self.gb.save_rtc_state(&mut GbSaveWriter::new(save_writer, self.gb_rom_crc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct TestSaveWriter(HashMap<String, Vec<u8>>);
+
+    impl SaveWriter for TestSaveWriter {
+        type Err = String;
+
+        fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+            self.0.get(extension).cloned().ok_or_else(|| format!("no file: {extension}"))
+        }
+
+        fn persist_bytes(&mut self, extension: &str, bytes: &[u8]) -> Result<(), Self::Err> {
+            self.0.insert(extension.into(), bytes.to_vec());
+            Ok(())
+        }
+
+        fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+            let bytes = self.load_bytes(extension)?;
+            bincode::decode_from_slice(&bytes, bincode::config::standard())
+                .map(|(data, _)| data)
+                .map_err(|err| err.to_string())
+        }
+
+        fn persist_serialized<E: Encode>(
+            &mut self,
+            extension: &str,
+            data: E,
+        ) -> Result<(), Self::Err> {
+            let bytes = bincode::encode_to_vec(data, bincode::config::standard())
+                .map_err(|err| err.to_string())?;
+            self.persist_bytes(extension, &bytes)
+        }
+    }
+
+    fn gb_rom(title: &[u8]) -> Vec<u8> {
+        let mut rom = vec![0; 32 * 1024];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        // MBC1+RAM+BATTERY, 32KB ROM, 8KB RAM
+        rom[0x147] = 0x03;
+        rom[0x148] = 0x00;
+        rom[0x149] = 0x02;
+        rom
+    }
+
+    fn new_sgb(gb_rom: Vec<u8>, save_writer: &mut TestSaveWriter) -> SuperGameBoy {
+        let gb_rom_crc = gb_rom_crc(&gb_rom);
+        let gb = GameBoyEmulator::create_super_game_boy(
+            gb_rom,
+            &mut GbSaveWriter::new(save_writer, gb_rom_crc),
+        )
+        .unwrap();
+
+        let bios = vec![0; 0x8000].into_boxed_slice();
+        SuperGameBoy::new(bios, gb, gb_rom_crc, TimingMode::Ntsc)
+    }
+
+    #[test]
+    fn icd2_lcd_row_buffer() {
+        let mut save_writer = TestSaveWriter::default();
+        let mut sgb = new_sgb(gb_rom(b"GAME"), &mut save_writer);
+
+        let mut shades = [0_u16; 160];
+        shades[0] = 3;
+        shades[1] = 1;
+        shades[8] = 2;
+        shades[159] = 3;
+        sgb.icd2.capture_line(&shades);
+
+        // Each row is 20 2bpp tiles with the low bitplane first; the read address resets on bank
+        // select and wraps at the end of the row
+        sgb.write(0x006001, 0);
+        let row: Vec<_> = (0..LCD_ROW_LEN).map(|_| sgb.read(0x007800).unwrap()).collect();
+        assert_eq!(&row[..2], &[0xC0, 0x80]);
+        assert_eq!(&row[16..18], &[0x00, 0x80]);
+        assert_eq!(&row[19 * 16..19 * 16 + 2], &[0x01, 0x01]);
+        assert_eq!(sgb.read(0x007800), Some(0xC0));
+
+        // Lines 0-7 go to bank 0, lines 8-15 to bank 1, and so on
+        for line in 1..=9 {
+            sgb.icd2.next_line(line);
+        }
+        assert_eq!(sgb.read(0x006000), Some(0x09));
+        sgb.icd2.capture_line(&shades);
+
+        // Second line of bank 1
+        sgb.write(0x006001, 1);
+        let bank_1: Vec<_> = (0..4).map(|_| sgb.read(0x007800).unwrap()).collect();
+        assert_eq!(bank_1, vec![0x00, 0x00, 0xC0, 0x80]);
+
+        // New frame restarts at line 0 without resetting the bank
+        sgb.icd2.next_line(0);
+        assert_eq!(sgb.read(0x006000), Some(0x01));
+
+        assert_eq!(sgb.read(0x00600F), Some(ICD2_VERSION));
+    }
+
+    #[test]
+    fn gb_saves_are_separate_per_gb_rom() {
+        let mut save_writer = TestSaveWriter::default();
+
+        let sgb_a = new_sgb(gb_rom(b"GAME A"), &mut save_writer);
+        let sgb_b = new_sgb(gb_rom(b"GAME B"), &mut save_writer);
+        assert_ne!(sgb_a.sram_extension(), sgb_b.sram_extension());
+        assert_ne!(sgb_a.sram_extension(), "sav");
+
+        let sram_a = vec![0x5A; 8 * 1024];
+        save_writer.persist_bytes(&sgb_a.sram_extension(), &sram_a).unwrap();
+
+        // Game A loads its own save, and game B does not see it
+        assert_eq!(new_sgb(gb_rom(b"GAME A"), &mut save_writer).sram(), Some(sram_a.as_slice()));
+        assert_ne!(new_sgb(gb_rom(b"GAME B"), &mut save_writer).sram(), Some(sram_a.as_slice()));
+    }
+}
@@ -1,8 +1,24 @@
-use crate::input::{SnesInputDevice, SnesInputs, SnesJoypadStateExt, SuperScopeState};
+//! Controller port serial I/O, used by both manual reads through JOYA/JOYB and auto joypad read
+//!
+//! Each controller port has two serial data lines and an I/O line (`IOBit`) that is connected to
+//! WRIO/RDIO. Besides the standard controller, the following devices are supported:
+//! - Super Scope: Standard controller protocol with a different ID; latches H/V counters through
+//!   port 2 `IOBit`
+//! - SNES Mouse: 32-bit report containing buttons, sensitivity, and motion since the last latch.
+//!   Clocking the mouse while latch is high cycles through the 3 sensitivity settings
+//! - Konami Justifier: 32-bit report; latches H/V counters through port 2 `IOBit`, alternating
+//!   between the 2 guns on every latch
+//! - Super Multitap: Connects 4 controllers to one port. `IOBit` high selects the first 2
+//!   controllers on data lines 1 and 2, and `IOBit` low selects the last 2. Data line 2 reads 1
+//!   while latch is high, which games use for detection
+
+use crate::input::{
+    JustifierState, SnesInputDevice, SnesInputs, SnesJoypadStateExt, SnesMouseState,
+    SuperScopeState,
+};
 use bincode::{Decode, Encode};
 use jgenesis_common::num::GetBit;
 use snes_config::SnesJoypadState;
-use std::mem;
 
 const AUTO_JOYPAD_DURATION_MCLK: u64 = 4224;
 
@@ -68,125 +84,487 @@ impl SuperScopeRegister {
     }
 }
 
+// Serial data shifted out of a controller data line, MSB first
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct SerialData {
+    bits: u32,
+    fill: bool,
+}
+
+impl SerialData {
+    // Unconnected data lines always read 0
+    const DISCONNECTED: Self = Self { bits: 0, fill: false };
+
+    // All devices read out 1s after their last data bit
+    fn new(value: u32, len: u32) -> Self {
+        let bits = if len >= 32 { value } else { (value << (32 - len)) | ((1 << (32 - len)) - 1) };
+        Self { bits, fill: true }
+    }
+
+    fn from_word(word: u16) -> Self {
+        Self::new(word.into(), 16)
+    }
+
+    fn peek(self) -> bool {
+        self.bits.bit(31)
+    }
+
+    fn next_bit(&mut self) -> bool {
+        let bit = self.bits.bit(31);
+        self.bits = (self.bits << 1) | u32::from(self.fill);
+        bit
+    }
+}
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct ControllerPort {
+    // Data lines 1 and 2; a multitap additionally uses lines 3 and 4 for the controllers selected
+    // by IOBit=0
+    data: [SerialData; 4],
+    multitap: bool,
+}
+
+impl ControllerPort {
+    fn single(data: SerialData) -> Self {
+        Self {
+            data: [
+                data,
+                SerialData::DISCONNECTED,
+                SerialData::DISCONNECTED,
+                SerialData::DISCONNECTED,
+            ],
+            multitap: false,
+        }
+    }
+
+    fn multitap(joypads: [SnesJoypadState; 4]) -> Self {
+        Self {
+            data: joypads.map(|joypad| SerialData::from_word(joypad.to_register_word())),
+            multitap: true,
+        }
+    }
+
+    fn selected_pair(&self, io_bit: bool) -> usize {
+        if self.multitap && !io_bit { 2 } else { 0 }
+    }
+
+    fn clock(&mut self, io_bit: bool) -> (bool, bool) {
+        let pair = self.selected_pair(io_bit);
+        (self.data[pair].next_bit(), self.data[pair + 1].next_bit())
+    }
+
+    fn read_latched(&self, io_bit: bool) -> (bool, bool) {
+        let pair = self.selected_pair(io_bit);
+        (self.data[pair].peek(), self.multitap || self.data[pair + 1].peek())
+    }
+}
+
+const MOUSE_SENSITIVITY_SETTINGS: u8 = 3;
+const MOUSE_MAX_MOTION: i32 = 127;
+
+#[derive(Debug, Clone, Copy, Default, Encode, Decode)]
+struct MouseRegister {
+    sensitivity: u8,
+    last_position: Option<(u16, u16)>,
+    // Latched report, excluding the sensitivity bits
+    latched_report: u32,
+}
+
+impl MouseRegister {
+    fn latch(&mut self, state: SnesMouseState) {
+        let (dx, dy) = match (self.last_position, state.position) {
+            (Some((last_x, last_y)), Some((x, y))) => {
+                (i32::from(x) - i32::from(last_x), i32::from(y) - i32::from(last_y))
+            }
+            _ => (0, 0),
+        };
+        self.last_position = state.position;
+
+        // Higher sensitivity settings scale motion by 1.5x and 2x
+        let scale = |motion: i32| {
+            let scaled = match self.sensitivity {
+                0 => motion,
+                1 => motion * 3 / 2,
+                _ => motion * 2,
+            };
+            scaled.clamp(-MOUSE_MAX_MOTION, MOUSE_MAX_MOTION)
+        };
+        let dx = scale(dx);
+        let dy = scale(dy);
+
+        // Motion is sign-magnitude, with the sign bit set for up/left
+        let y_bits = (u32::from(dy < 0) << 7) | dy.unsigned_abs();
+        let x_bits = (u32::from(dx < 0) << 7) | dx.unsigned_abs();
+
+        // 8 unused bits, then R, L, 2 sensitivity bits, and ID 0001
+        self.latched_report = (u32::from(state.right) << 23)
+            | (u32::from(state.left) << 22)
+            | (0b0001 << 16)
+            | (y_bits << 8)
+            | x_bits;
+    }
+
+    fn report(&self) -> SerialData {
+        SerialData::new(self.latched_report | (u32::from(self.sensitivity) << 20), 32)
+    }
+
+    fn cycle_sensitivity(&mut self) {
+        self.sensitivity = (self.sensitivity + 1) % MOUSE_SENSITIVITY_SETTINGS;
+        log::debug!("Mouse sensitivity set to {}", self.sensitivity);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Encode, Decode)]
+struct JustifierRegister {
+    // Which gun is sensing light for H/V latching; switches on every latch
+    gun_1_active: bool,
+    position: Option<(u16, u16)>,
+}
+
+impl Default for JustifierRegister {
+    fn default() -> Self {
+        Self { gun_1_active: true, position: None }
+    }
+}
+
+impl JustifierRegister {
+    fn latch(&mut self, state: JustifierState) -> u32 {
+        self.position = state.position;
+
+        // 12 unused bits, ID 1110, extra ID $55, then triggers and starts for both guns, then which
+        // gun the previous frame's H/V latch was for
+        (0b1110 << 16)
+            | (0x55 << 8)
+            | (u32::from(state.trigger) << 7)
+            | (u32::from(state.start) << 5)
+            | (u32::from(self.gun_1_active) << 3)
+    }
+
+    fn hv_latch(self) -> Option<(u16, u16)> {
+        // Only gun 1 is connected
+        if !self.gun_1_active {
+            return None;
+        }
+
+        // Same timing as the Super Scope
+        self.position.map(|(x, y)| (x + 40, y + 1))
+    }
+}
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub struct InputState {
     auto_read_cycles_remaining: u64,
-    auto_joypad_p1_inputs: u16,
-    auto_joypad_p2_inputs: u16,
+    // JOY1-JOY4
+    auto_joypad_inputs: [u16; 4],
     strobe: bool,
-    manual_joypad_p1_inputs: u16,
-    manual_joypad_p2_inputs: u16,
+    ports: [ControllerPort; 2],
     current_inputs: SnesInputs,
     last_strobe_inputs: SnesInputs,
-    super_scope_register: SuperScopeRegister,
+    super_scope_registers: [SuperScopeRegister; 2],
+    mouse_registers: [MouseRegister; 2],
+    justifier_registers: [JustifierRegister; 2],
+}
+
+// WRIO bits 6 and 7 drive IOBit on ports 1 and 2 respectively
+fn io_bit(port: usize, wrio: u8) -> bool {
+    wrio.bit(6 + port as u8)
 }
 
 impl InputState {
     pub fn new() -> Self {
         Self {
             auto_read_cycles_remaining: 0,
-            auto_joypad_p1_inputs: SnesJoypadState::default().to_register_word(),
-            auto_joypad_p2_inputs: SnesJoypadState::default().to_register_word(),
+            auto_joypad_inputs: [
+                SnesJoypadState::default().to_register_word(),
+                SnesJoypadState::default().to_register_word(),
+                0,
+                0,
+            ],
             strobe: false,
-            manual_joypad_p1_inputs: SnesJoypadState::default().to_register_word(),
-            manual_joypad_p2_inputs: SnesJoypadState::default().to_register_word(),
+            ports: [ControllerPort::single(SerialData::from_word(
+                SnesJoypadState::default().to_register_word(),
+            )); 2],
             current_inputs: SnesInputs::default(),
             last_strobe_inputs: SnesInputs::default(),
-            super_scope_register: SuperScopeRegister::default(),
+            super_scope_registers: [SuperScopeRegister::default(); 2],
+            mouse_registers: [MouseRegister::default(); 2],
+            justifier_registers: [JustifierRegister::default(); 2],
         }
     }
 
     pub fn set_strobe(&mut self, strobe: bool) {
         if !self.strobe && strobe {
-            self.manual_joypad_p1_inputs = self.current_inputs.p1.to_register_word();
-            self.manual_joypad_p2_inputs = match self.current_inputs.p2 {
-                SnesInputDevice::Controller(joypad_state) => {
-                    self.super_scope_register = SuperScopeRegister::default();
-
-                    joypad_state.to_register_word()
-                }
-                SnesInputDevice::SuperScope(super_scope_state) => {
-                    // Read out the bits before updating them; otherwise the SNES will read Fire=1 on the frame before
-                    // the PPU latches H/V
-                    let word = self.super_scope_register.to_register_word();
-
-                    let last_strobe_state = match self.last_strobe_inputs.p2 {
-                        SnesInputDevice::SuperScope(last_state) => last_state,
-                        SnesInputDevice::Controller(_) => SuperScopeState::default(),
-                    };
-                    self.super_scope_register.update(super_scope_state, last_strobe_state);
-
-                    word
-                }
-            };
+            for port in 0..2 {
+                self.latch_port(port);
+            }
 
             self.last_strobe_inputs = self.current_inputs;
+        } else if self.strobe && !strobe {
+            // The Justifier switches guns on every latch, even if the second gun is not connected
+            for justifier_register in &mut self.justifier_registers {
+                justifier_register.gun_1_active = !justifier_register.gun_1_active;
+            }
         }
 
         self.strobe = strobe;
     }
 
+    fn latch_port(&mut self, port: usize) {
+        let (device, last_device) = if port == 0 {
+            (self.current_inputs.p1, self.last_strobe_inputs.p1)
+        } else {
+            (self.current_inputs.p2, self.last_strobe_inputs.p2)
+        };
+
+        if !matches!(device, SnesInputDevice::SuperScope(_)) {
+            self.super_scope_registers[port] = SuperScopeRegister::default();
+        }
+
+        self.ports[port] = match device {
+            SnesInputDevice::Controller(joypad_state) => {
+                ControllerPort::single(SerialData::from_word(joypad_state.to_register_word()))
+            }
+            SnesInputDevice::SuperScope(super_scope_state) => {
+                // Read out the bits before updating them; otherwise the SNES will read Fire=1 on
+                // the frame before the PPU latches H/V
+                let register = &mut self.super_scope_registers[port];
+                let word = register.to_register_word();
+
+                let last_strobe_state = match last_device {
+                    SnesInputDevice::SuperScope(last_state) => last_state,
+                    _ => SuperScopeState::default(),
+                };
+                register.update(super_scope_state, last_strobe_state);
+
+                ControllerPort::single(SerialData::from_word(word))
+            }
+            SnesInputDevice::Mouse(mouse_state) => {
+                self.mouse_registers[port].latch(mouse_state);
+                ControllerPort::single(self.mouse_registers[port].report())
+            }
+            SnesInputDevice::Justifier(justifier_state) => {
+                let report = self.justifier_registers[port].latch(justifier_state);
+                ControllerPort::single(SerialData::new(report, 32))
+            }
+            SnesInputDevice::Multitap(joypad_states) => ControllerPort::multitap(joypad_states),
+        };
+    }
+
     pub fn auto_joypad_read_in_progress(&self) -> bool {
         self.auto_read_cycles_remaining != 0
     }
 
-    pub fn auto_joypad_p1_inputs(&self) -> u16 {
-        self.auto_joypad_p1_inputs
+    pub fn auto_joypad_inputs(&self, i: usize) -> u16 {
+        self.auto_joypad_inputs[i]
     }
 
-    pub fn auto_joypad_p2_inputs(&self) -> u16 {
-        self.auto_joypad_p2_inputs
-    }
+    // Clock a controller port through JOYA/JOYB, returning the values of data lines 1 and 2 in bits
+    // 0 and 1
+    pub fn read_manual_port(&mut self, port: usize, wrio: u8) -> u8 {
+        let io_bit = io_bit(port, wrio);
 
-    pub fn next_manual_p1_bit(&mut self) -> bool {
-        let bit = self.manual_joypad_p1_inputs.bit(15);
-        self.manual_joypad_p1_inputs = (self.manual_joypad_p1_inputs << 1) | 0x0001;
-        bit
-    }
+        let (data1, data2) = if self.strobe {
+            // While latch is high, controllers continuously reload their shift registers, so clocks
+            // do not shift data
+            let device = if port == 0 { self.current_inputs.p1 } else { self.current_inputs.p2 };
+            if matches!(device, SnesInputDevice::Mouse(_)) {
+                // Clocking the mouse while latch is high cycles its sensitivity, which is
+                // immediately reflected in the latched report
+                self.mouse_registers[port].cycle_sensitivity();
+                self.ports[port] = ControllerPort::single(self.mouse_registers[port].report());
+                (false, false)
+            } else {
+                self.ports[port].read_latched(io_bit)
+            }
+        } else {
+            self.ports[port].clock(io_bit)
+        };
 
-    pub fn next_manual_p2_bit(&mut self) -> bool {
-        let bit = self.manual_joypad_p2_inputs.bit(15);
-        self.manual_joypad_p2_inputs = (self.manual_joypad_p2_inputs << 1) | 0x0001;
-        bit
+        (u8::from(data2) << 1) | u8::from(data1)
     }
 
     pub fn start_auto_joypad_read(&mut self) {
         self.auto_read_cycles_remaining = AUTO_JOYPAD_DURATION_MCLK;
     }
 
-    pub fn tick(&mut self, master_cycles_elapsed: u64, inputs: SnesInputs) {
+    pub fn tick(&mut self, master_cycles_elapsed: u64, inputs: SnesInputs, wrio: u8) {
         self.current_inputs = inputs;
 
         if self.auto_read_cycles_remaining != 0 {
-            self.progress_auto_joypad_read(master_cycles_elapsed);
+            self.progress_auto_joypad_read(master_cycles_elapsed, wrio);
         }
     }
 
-    fn progress_auto_joypad_read(&mut self, master_cycles_elapsed: u64) {
+    fn progress_auto_joypad_read(&mut self, master_cycles_elapsed: u64, wrio: u8) {
         self.auto_read_cycles_remaining =
             self.auto_read_cycles_remaining.saturating_sub(master_cycles_elapsed);
 
         if self.auto_read_cycles_remaining == 0 {
-            // Auto joypad read strobes the joypad while reading inputs; this populates the manual
-            // joypad read registers
+            // Auto joypad read strobes the joypad and then clocks both ports 16 times, shifting
+            // data lines 1 and 2 into JOY1/JOY3 (port 1) and JOY2/JOY4 (port 2).
+            // Devices with longer reports (e.g. the mouse) leave the remaining bits for manual
+            // reads, and Donkey Kong Country depends on manual reads returning 1s after auto joypad
+            // read finishes
             self.set_strobe(true);
             self.set_strobe(false);
 
-            // Drain the manual joypad read registers into the auto joypad read registers
-            // Donkey Kong Country depends on the manual joypad read registers reading out 1s after
-            // auto joypad read finishes
-            self.auto_joypad_p1_inputs = mem::replace(&mut self.manual_joypad_p1_inputs, !0);
-            self.auto_joypad_p2_inputs = mem::replace(&mut self.manual_joypad_p2_inputs, !0);
+            self.auto_joypad_inputs = [0; 4];
+            for _ in 0..16 {
+                for port in 0..2 {
+                    let (data1, data2) = self.ports[port].clock(io_bit(port, wrio));
+                    self.auto_joypad_inputs[port] =
+                        (self.auto_joypad_inputs[port] << 1) | u16::from(data1);
+                    self.auto_joypad_inputs[port + 2] =
+                        (self.auto_joypad_inputs[port + 2] << 1) | u16::from(data2);
+                }
+            }
         }
     }
 
     pub fn hv_latch(&self) -> Option<(u16, u16)> {
-        // Super Scope latches the PPU at H=X+40, V=Y+1 when Fire or Cursor is set
-        (self.super_scope_register.fire || self.super_scope_register.cursor)
-            .then(|| {
-                let (x, y) = self.super_scope_register.position?;
-                Some((x + 40, y + 1))
-            })
-            .flatten()
+        // Only port 2 IOBit is connected to the PPU's external latch pin
+        match self.current_inputs.p2 {
+            SnesInputDevice::SuperScope(_) => {
+                // Super Scope latches the PPU at H=X+40, V=Y+1 when Fire or Cursor is set
+                let register = &self.super_scope_registers[1];
+                (register.fire || register.cursor)
+                    .then(|| {
+                        let (x, y) = register.position?;
+                        Some((x + 40, y + 1))
+                    })
+                    .flatten()
+            }
+            SnesInputDevice::Justifier(_) => self.justifier_registers[1].hv_latch(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // WRIO with port 2 IOBit high
+    const WRIO_PORT_2_IO_HIGH: u8 = 0x80;
+
+    fn latched_state(inputs: SnesInputs) -> InputState {
+        let mut state = InputState::new();
+        latch(&mut state, inputs);
+        state
+    }
+
+    fn latch(state: &mut InputState, inputs: SnesInputs) {
+        state.tick(0, inputs, 0);
+        state.set_strobe(true);
+        state.set_strobe(false);
+    }
+
+    // Clock a port `len` times, returning data lines 1 and 2 shifted in MSB first
+    fn read_serial(state: &mut InputState, port: usize, wrio: u8, len: u32) -> (u32, u32) {
+        (0..len).fold((0, 0), |(data1, data2), _| {
+            let value = state.read_manual_port(port, wrio);
+            ((data1 << 1) | u32::from(value.bit(0)), (data2 << 1) | u32::from(value.bit(1)))
+        })
+    }
+
+    fn mouse_inputs(left: bool, right: bool, position: Option<(u16, u16)>) -> SnesInputs {
+        SnesInputs {
+            p1: SnesInputDevice::Mouse(SnesMouseState { left, right, position }),
+            p2: SnesInputDevice::default(),
+        }
+    }
+
+    #[test]
+    fn mouse_report() {
+        let mut state = latched_state(mouse_inputs(true, false, Some((100, 100))));
+
+        // No motion on the first latch; buttons, sensitivity 0, then ID 0001
+        assert_eq!(read_serial(&mut state, 0, 0, 32).0, 0x0041_0000);
+
+        // Moved left 10 and down 10; motion is sign-magnitude with the sign set for left/up
+        latch(&mut state, mouse_inputs(false, true, Some((90, 110))));
+        assert_eq!(read_serial(&mut state, 0, 0, 32).0, 0x0081_0A8A);
+
+        // Reads past the end of the report return 1s, and data line 2 is not connected
+        assert_eq!(read_serial(&mut state, 0, 0, 8), (0xFF, 0x00));
+    }
+
+    #[test]
+    fn mouse_sensitivity_cycling() {
+        let mut state = latched_state(mouse_inputs(false, false, Some((100, 100))));
+
+        // Clocking while latch is high cycles sensitivity, and the report reflects it immediately
+        state.tick(0, mouse_inputs(false, false, Some((110, 100))), 0);
+        state.set_strobe(true);
+        state.read_manual_port(0, 0);
+        state.set_strobe(false);
+        assert_eq!(read_serial(&mut state, 0, 0, 32).0, 0x0011_000A);
+
+        // Sensitivity 1 scales motion by 1.5x
+        latch(&mut state, mouse_inputs(false, false, Some((120, 100))));
+        assert_eq!(read_serial(&mut state, 0, 0, 32).0, 0x0011_000F);
+
+        // Sensitivity wraps back around to 0 after 3 settings; the cursor did not move since the
+        // last latch
+        state.set_strobe(true);
+        state.read_manual_port(0, 0);
+        state.read_manual_port(0, 0);
+        state.set_strobe(false);
+        assert_eq!(read_serial(&mut state, 0, 0, 32).0, 0x0001_0000);
+    }
+
+    #[test]
+    fn multitap_io_bit_select() {
+        let joypads = [
+            SnesJoypadState { b: true, ..SnesJoypadState::default() },
+            SnesJoypadState { y: true, ..SnesJoypadState::default() },
+            SnesJoypadState { select: true, ..SnesJoypadState::default() },
+            SnesJoypadState { start: true, ..SnesJoypadState::default() },
+        ];
+        let inputs =
+            SnesInputs { p1: SnesInputDevice::default(), p2: SnesInputDevice::Multitap(joypads) };
+        let words = joypads.map(|joypad| u32::from(joypad.to_register_word()));
+
+        // Data line 2 reads 1 while latch is high
+        let mut state = InputState::new();
+        state.tick(0, inputs, 0);
+        state.set_strobe(true);
+        assert_eq!(state.read_manual_port(1, WRIO_PORT_2_IO_HIGH), 0b11);
+        assert_eq!(state.read_manual_port(1, 0), 0b10);
+        state.set_strobe(false);
+
+        // IOBit high selects controllers 1 and 2, and IOBit low selects controllers 3 and 4
+        assert_eq!(read_serial(&mut state, 1, WRIO_PORT_2_IO_HIGH, 16), (words[0], words[1]));
+        latch(&mut state, inputs);
+        assert_eq!(read_serial(&mut state, 1, 0, 16), (words[2], words[3]));
+
+        // Auto joypad read uses the current IOBit state
+        state.start_auto_joypad_read();
+        state.tick(AUTO_JOYPAD_DURATION_MCLK, inputs, WRIO_PORT_2_IO_HIGH);
+        assert!(!state.auto_joypad_read_in_progress());
+        assert_eq!(u32::from(state.auto_joypad_inputs(1)), words[0]);
+        assert_eq!(u32::from(state.auto_joypad_inputs(3)), words[1]);
+    }
+
+    #[test]
+    fn justifier_report_and_gun_alternation() {
+        let inputs = SnesInputs {
+            p1: SnesInputDevice::default(),
+            p2: SnesInputDevice::Justifier(JustifierState {
+                trigger: true,
+                start: false,
+                position: Some((50, 60)),
+            }),
+        };
+
+        // ID 1110, extra ID $55, gun 1 trigger, and the gun 1 latch flag
+        let mut state = latched_state(inputs);
+        assert_eq!(read_serial(&mut state, 1, 0, 32).0, 0x000E_5588);
+
+        // Guns switch on every latch, and only gun 1 latches H/V
+        assert_eq!(state.hv_latch(), None);
+        latch(&mut state, inputs);
+        assert_eq!(read_serial(&mut state, 1, 0, 32).0, 0x000E_5580);
+        assert_eq!(state.hv_latch(), Some((90, 61)));
     }
 }
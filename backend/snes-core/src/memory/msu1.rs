@@ -0,0 +1,491 @@
+//! MSU-1 streaming data and audio enhancement
+//!
+//! The MSU-1 is not real hardware; it's a ROM hacking standard that gives games access to a large
+//! data file (`<name>.msu`) and to CD-quality audio tracks (`<name>-<N>.pcm`) stored next to the
+//! ROM file. Its registers are mapped to $2000-$2007 in banks $00-$3F and $80-$BF.
+//!
+//! PCM tracks consist of the 4 bytes "MSU1", a 32-bit little-endian loop point (in samples),
+//! and then 44100 Hz signed 16-bit little-endian stereo samples.
+//!
+//! Seeks and track loads complete instantly, so the data busy and audio busy status bits are never
+//! set.
+
+use crate::audio::AudioResampler;
+use crate::constants;
+use bincode::{Decode, Encode};
+use jgenesis_common::frontend::TimingMode;
+use jgenesis_common::num::{GetBit, U16Ext};
+use jgenesis_proc_macros::{FakeDecode, FakeEncode};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub const MSU1_SAMPLE_RATE: u64 = 44100;
+
+const IDENTIFIER: [u8; 6] = *b"S-MSU1";
+const REVISION: u8 = 1;
+
+const PCM_MAGIC: [u8; 4] = *b"MSU1";
+const PCM_HEADER_LEN: u64 = 8;
+const PCM_BYTES_PER_SAMPLE: u64 = 4;
+
+#[derive(Debug)]
+struct FileReader {
+    reader: BufReader<File>,
+    position: u64,
+    len: u64,
+}
+
+impl FileReader {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self { reader: BufReader::new(file), position: 0, len })
+    }
+
+    fn read_exact_at(&mut self, position: u64, buf: &mut [u8]) -> io::Result<()> {
+        if position != self.position {
+            self.reader.seek(SeekFrom::Start(position))?;
+        }
+
+        let result = self.reader.read_exact(buf);
+        self.position = if result.is_ok() {
+            position + buf.len() as u64
+        } else {
+            // Force a seek on the next read
+            u64::MAX
+        };
+
+        result
+    }
+}
+
+// Open file handles are not part of emulation state. They're reopened on demand after loading a
+// save state or cloning, based on the paths and positions in the emulation state.
+#[derive(Debug, Default, FakeEncode, FakeDecode)]
+struct Msu1Files {
+    data: Option<FileReader>,
+    data_open_failed: bool,
+    track: Option<(u16, FileReader)>,
+}
+
+impl Clone for Msu1Files {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct Msu1 {
+    data_path: String,
+    files: Msu1Files,
+    data_seek_latch: u32,
+    data_position: u64,
+    track_latch: u16,
+    track: Option<u16>,
+    track_missing: bool,
+    track_len_samples: u64,
+    loop_point: u64,
+    audio_position: u64,
+    volume: u8,
+    playing: bool,
+    repeat: bool,
+    main_master_clock_frequency: u64,
+    sample_cycles_product: u64,
+    enable_audio_60hz_hack: bool,
+}
+
+impl Msu1 {
+    pub fn new(data_path: &Path, timing_mode: TimingMode, enable_audio_60hz_hack: bool) -> Self {
+        let main_master_clock_frequency = match timing_mode {
+            TimingMode::Ntsc => constants::NTSC_MASTER_CLOCK_FREQUENCY,
+            TimingMode::Pal => constants::PAL_MASTER_CLOCK_FREQUENCY,
+        };
+
+        log::info!("MSU-1 enabled with data file '{}'", data_path.display());
+
+        Self {
+            data_path: data_path.to_string_lossy().into_owned(),
+            files: Msu1Files::default(),
+            data_seek_latch: 0,
+            data_position: 0,
+            track_latch: 0,
+            track: None,
+            track_missing: false,
+            track_len_samples: 0,
+            loop_point: 0,
+            audio_position: 0,
+            volume: 0,
+            playing: false,
+            repeat: false,
+            main_master_clock_frequency,
+            sample_cycles_product: 0,
+            enable_audio_60hz_hack,
+        }
+    }
+
+    fn track_path(&self, track: u16) -> PathBuf {
+        let data_path = Path::new(&self.data_path);
+        let file_stem =
+            data_path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+        data_path.with_file_name(format!("{file_stem}-{track}.pcm"))
+    }
+
+    fn data_file(&mut self) -> Option<&mut FileReader> {
+        if self.files.data.is_none() && !self.files.data_open_failed {
+            match FileReader::open(Path::new(&self.data_path)) {
+                Ok(reader) => self.files.data = Some(reader),
+                Err(err) => {
+                    log::error!("Unable to open MSU-1 data file '{}': {err}", self.data_path);
+                    self.files.data_open_failed = true;
+                }
+            }
+        }
+
+        self.files.data.as_mut()
+    }
+
+    // Returns None if the current track is missing
+    fn track_file(&mut self) -> Option<&mut FileReader> {
+        let track = self.track?;
+        if self.track_missing {
+            return None;
+        }
+
+        if self.files.track.as_ref().is_none_or(|&(open_track, _)| open_track != track) {
+            let path = self.track_path(track);
+            match FileReader::open(&path) {
+                Ok(reader) => self.files.track = Some((track, reader)),
+                Err(err) => {
+                    log::warn!("Unable to open MSU-1 track file '{}': {err}", path.display());
+                    self.files.track = None;
+                    self.track_missing = true;
+                    return None;
+                }
+            }
+        }
+
+        self.files.track.as_mut().map(|(_, reader)| reader)
+    }
+
+    pub fn read(&mut self, address: u32) -> Option<u8> {
+        match address & 0xFFFF {
+            0x2000 => Some(self.read_status()),
+            0x2001 => Some(self.read_data_port()),
+            address @ 0x2002..=0x2007 => Some(IDENTIFIER[(address - 0x2002) as usize]),
+            _ => None,
+        }
+    }
+
+    fn read_status(&self) -> u8 {
+        // Bits 7 and 6 are data busy and audio busy, which are never set
+        (u8::from(self.repeat) << 5)
+            | (u8::from(self.playing) << 4)
+            | (u8::from(self.track_missing) << 3)
+            | REVISION
+    }
+
+    fn read_data_port(&mut self) -> u8 {
+        let position = self.data_position;
+        self.data_position += 1;
+
+        let Some(data_file) = self.data_file() else { return 0x00 };
+        if position >= data_file.len {
+            return 0x00;
+        }
+
+        let mut byte = [0];
+        match data_file.read_exact_at(position, &mut byte) {
+            Ok(()) => byte[0],
+            Err(err) => {
+                log::error!("Error reading MSU-1 data file at {position:X}: {err}");
+                0x00
+            }
+        }
+    }
+
+    pub fn write(&mut self, address: u32, value: u8) {
+        match address & 0xFFFF {
+            0x2000..=0x2003 => {
+                let shift = 8 * (address & 3);
+                self.data_seek_latch =
+                    (self.data_seek_latch & !(0xFF << shift)) | (u32::from(value) << shift);
+
+                // Writing the highest byte performs the seek
+                if address & 3 == 3 {
+                    self.data_position = self.data_seek_latch.into();
+                    log::trace!("MSU-1 data seek to {:08X}", self.data_position);
+                }
+            }
+            0x2004 => self.track_latch.set_lsb(value),
+            0x2005 => {
+                self.track_latch.set_msb(value);
+                self.load_track(self.track_latch);
+            }
+            0x2006 => self.volume = value,
+            // Control bits are ignored while the current track is missing
+            0x2007 if !self.track_missing => {
+                self.playing = value.bit(0);
+                self.repeat = value.bit(1);
+            }
+            _ => {}
+        }
+    }
+
+    fn load_track(&mut self, track: u16) {
+        log::debug!("MSU-1 loading audio track {track}");
+
+        self.track = Some(track);
+        self.track_missing = false;
+        self.playing = false;
+        self.repeat = false;
+        self.audio_position = 0;
+        self.loop_point = 0;
+        self.track_len_samples = 0;
+
+        let Some(track_file) = self.track_file() else { return };
+
+        let len_samples = track_file.len.saturating_sub(PCM_HEADER_LEN) / PCM_BYTES_PER_SAMPLE;
+
+        let mut header = [0; PCM_HEADER_LEN as usize];
+        if let Err(err) = track_file.read_exact_at(0, &mut header) {
+            log::error!("Error reading MSU-1 track {track} header: {err}");
+            self.track_missing = true;
+            return;
+        }
+
+        if header[..4] != PCM_MAGIC {
+            log::warn!("MSU-1 track {track} does not have the expected header; playing anyway");
+        }
+
+        self.track_len_samples = len_samples;
+        self.loop_point = u32::from_le_bytes(header[4..8].try_into().unwrap()).into();
+    }
+
+    pub fn tick(&mut self, main_master_cycles: u64, audio_resampler: &mut AudioResampler) {
+        // Same adjustment as the APU; speed up audio slightly so it's timed to 60Hz. The ratio is
+        // applied to both sides of the comparison rather than to the sample rate so that the
+        // fractional part of the adjusted sample rate is not truncated
+        let (rate_numerator, rate_denominator) =
+            if self.enable_audio_60hz_hack { (60099, 60000) } else { (1, 1) };
+        self.sample_cycles_product += main_master_cycles * MSU1_SAMPLE_RATE * rate_numerator;
+
+        let cycles_per_sample_product = self.main_master_clock_frequency * rate_denominator;
+        while self.sample_cycles_product >= cycles_per_sample_product {
+            self.sample_cycles_product -= cycles_per_sample_product;
+
+            let (sample_l, sample_r) = self.next_sample();
+            audio_resampler.collect_msu1_sample(sample_l, sample_r);
+        }
+    }
+
+    fn next_sample(&mut self) -> (f64, f64) {
+        if !self.playing {
+            return (0.0, 0.0);
+        }
+
+        if self.audio_position >= self.track_len_samples {
+            if self.repeat && self.loop_point < self.track_len_samples {
+                self.audio_position = self.loop_point;
+            } else {
+                self.playing = false;
+                return (0.0, 0.0);
+            }
+        }
+
+        let position = PCM_HEADER_LEN + self.audio_position * PCM_BYTES_PER_SAMPLE;
+        self.audio_position += 1;
+
+        let Some(track_file) = self.track_file() else { return (0.0, 0.0) };
+
+        let mut bytes = [0; PCM_BYTES_PER_SAMPLE as usize];
+        if let Err(err) = track_file.read_exact_at(position, &mut bytes) {
+            log::error!("Error reading MSU-1 track at {position:X}: {err}");
+            self.playing = false;
+            return (0.0, 0.0);
+        }
+
+        let volume = f64::from(self.volume) / 255.0;
+        let sample_l = f64::from(i16::from_le_bytes([bytes[0], bytes[1]])) / -f64::from(i16::MIN);
+        let sample_r = f64::from(i16::from_le_bytes([bytes[2], bytes[3]])) / -f64::from(i16::MIN);
+
+        (sample_l * volume, sample_r * volume)
+    }
+
+    pub fn reset(&mut self) {
+        self.data_seek_latch = 0;
+        self.data_position = 0;
+        self.track_latch = 0;
+        self.track = None;
+        self.track_missing = false;
+        self.volume = 0;
+        self.playing = false;
+        self.repeat = false;
+    }
+
+    pub fn take_files_from(&mut self, other: &mut Self) {
+        // Use the current paths in case a save state was created with the files in a different location
+        self.data_path.clone_from(&other.data_path);
+        self.files = std::mem::take(&mut other.files);
+    }
+
+    pub fn update_audio_60hz_hack(&mut self, enable_audio_60hz_hack: bool) {
+        if enable_audio_60hz_hack != self.enable_audio_60hz_hack {
+            // Product is scaled differently depending on whether the hack is enabled
+            self.sample_cycles_product = 0;
+        }
+        self.enable_audio_60hz_hack = enable_audio_60hz_hack;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jgenesis-msu1-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_track(path: &Path, loop_point: u32, samples: &[(i16, i16)]) {
+        let mut bytes = PCM_MAGIC.to_vec();
+        bytes.extend(loop_point.to_le_bytes());
+        for &(sample_l, sample_r) in samples {
+            bytes.extend(sample_l.to_le_bytes());
+            bytes.extend(sample_r.to_le_bytes());
+        }
+        fs::write(path, bytes).unwrap();
+    }
+
+    fn next_sample_i16(msu1: &mut Msu1) -> (i16, i16) {
+        let (sample_l, sample_r) = msu1.next_sample();
+        let to_i16 = |sample: f64| (sample * -f64::from(i16::MIN)).round() as i16;
+        (to_i16(sample_l), to_i16(sample_r))
+    }
+
+    #[test]
+    fn identifier_and_status() {
+        let mut msu1 = Msu1::new(Path::new("test.msu"), TimingMode::Ntsc, false);
+
+        let identifier: Vec<_> =
+            (0x2002..=0x2007).map(|address| msu1.read(address).unwrap()).collect();
+        assert_eq!(identifier, b"S-MSU1");
+
+        // Mirrored in banks $80-$BF
+        assert_eq!(msu1.read(0x802000), Some(REVISION));
+        assert_eq!(msu1.read(0x2008), None);
+        assert_eq!(msu1.read(0x1FFF), None);
+    }
+
+    #[test]
+    fn data_seek() {
+        let dir = test_dir("data");
+        let data_path = dir.join("game.msu");
+        fs::write(&data_path, (0..=0xFF).collect::<Vec<u8>>()).unwrap();
+
+        let mut msu1 = Msu1::new(&data_path, TimingMode::Ntsc, false);
+        assert_eq!(msu1.read(0x2001), Some(0x00));
+        assert_eq!(msu1.read(0x2001), Some(0x01));
+
+        // Seek does not take effect until the highest byte is written
+        msu1.write(0x2000, 0x80);
+        msu1.write(0x2001, 0x00);
+        msu1.write(0x2002, 0x00);
+        assert_eq!(msu1.read(0x2001), Some(0x02));
+
+        msu1.write(0x2003, 0x00);
+        assert_eq!(msu1.read(0x2001), Some(0x80));
+        assert_eq!(msu1.read(0x2001), Some(0x81));
+
+        // Reads past the end of the file return 0
+        msu1.write(0x2000, 0xFF);
+        msu1.write(0x2003, 0x00);
+        assert_eq!(msu1.read(0x2001), Some(0xFF));
+        assert_eq!(msu1.read(0x2001), Some(0x00));
+        assert_eq!(msu1.read(0x2001), Some(0x00));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_track() {
+        let dir = test_dir("missing-track");
+        let mut msu1 = Msu1::new(&dir.join("game.msu"), TimingMode::Ntsc, false);
+
+        msu1.write(0x2004, 0x02);
+        msu1.write(0x2005, 0x00);
+        assert_eq!(msu1.read(0x2000), Some(0x08 | REVISION));
+
+        // Control writes are ignored while the track is missing
+        msu1.write(0x2007, 0x03);
+        assert_eq!(msu1.read(0x2000), Some(0x08 | REVISION));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn audio_repeat_and_loop_point() {
+        let dir = test_dir("audio");
+        let samples: Vec<_> = (1..=4).map(|i| (i * 0x1000, -i * 0x1000)).collect();
+        write_track(&dir.join("game-1.pcm"), 2, &samples);
+        write_track(&dir.join("game-300.pcm"), 0, &samples);
+
+        let mut msu1 = Msu1::new(&dir.join("game.msu"), TimingMode::Ntsc, false);
+        msu1.write(0x2006, 0xFF);
+
+        // Track number latch is 16 bits; the track loads when the high byte is written
+        msu1.write(0x2004, 0x01);
+        msu1.write(0x2005, 0x00);
+        assert_eq!(msu1.read(0x2000), Some(REVISION));
+
+        // Play with repeat; after the last sample, playback restarts from the loop point
+        msu1.write(0x2007, 0x03);
+        assert_eq!(msu1.read(0x2000), Some(0x30 | REVISION));
+        let played: Vec<_> = (0..8).map(|_| next_sample_i16(&mut msu1).0).collect();
+        assert_eq!(played, [0x1000, 0x2000, 0x3000, 0x4000, 0x3000, 0x4000, 0x3000, 0x4000]);
+        assert_eq!(next_sample_i16(&mut msu1), (0x3000, -0x3000));
+
+        // Loading a track stops playback; without repeat, playback stops at the end of the track
+        msu1.write(0x2004, 0x2C);
+        msu1.write(0x2005, 0x01);
+        assert_eq!(msu1.read(0x2000), Some(REVISION));
+        assert_eq!(next_sample_i16(&mut msu1), (0, 0));
+
+        msu1.write(0x2007, 0x01);
+        assert_eq!(msu1.read(0x2000), Some(0x10 | REVISION));
+        let played: Vec<_> = (0..6).map(|_| next_sample_i16(&mut msu1).0).collect();
+        assert_eq!(played, [0x1000, 0x2000, 0x3000, 0x4000, 0, 0]);
+        assert_eq!(msu1.read(0x2000), Some(REVISION));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn samples_in_seconds(enable_audio_60hz_hack: bool, seconds: u64) -> u64 {
+        let mut msu1 = Msu1::new(Path::new("test.msu"), TimingMode::Ntsc, enable_audio_60hz_hack);
+        let mut audio_resampler = AudioResampler::new(false);
+
+        // No track file is open, but the audio position still advances once per sample
+        msu1.playing = true;
+        msu1.track_len_samples = u64::MAX;
+
+        for _ in 0..seconds {
+            msu1.tick(constants::NTSC_MASTER_CLOCK_FREQUENCY, &mut audio_resampler);
+        }
+
+        msu1.audio_position
+    }
+
+    #[test]
+    fn sample_rate() {
+        assert_eq!(samples_in_seconds(false, 100), 100 * MSU1_SAMPLE_RATE);
+    }
+
+    #[test]
+    fn sample_rate_60hz_hack() {
+        // 44100 * 60099 / 60000 = 44172.765; the fractional part should not be truncated
+        assert_eq!(samples_in_seconds(true, 100), 4417276);
+    }
+}
@@ -0,0 +1,721 @@
+//! Cheat code decoding and application, shared by all emulation backends.
+//!
+//! Cheat codes are decoded into two kinds of effects:
+//! * ROM patches, which substitute the value returned when the CPU reads a specific ROM address
+//!   (this is how Game Genie devices work)
+//! * RAM writes, which write a value to a specific address once per frame (this is how Pro Action
+//!   Replay and GameShark devices work)
+//!
+//! Backends store a [`CheatSet`] and call [`CheatSet::patch_rom_u8`] (or one of the wider variants)
+//! on cartridge ROM reads, and write each of [`CheatSet::ram_writes`] once per frame. RAM writes
+//! should go directly to the backing memory rather than through the bus so that they never trigger
+//! side effects, e.g. from hardware registers or mapper bank switching.
+
+use jgenesis_proc_macros::{FakeDecode, FakeEncode};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CheatSystem {
+    Nes,
+    Snes,
+    Genesis,
+    SmsGg,
+    GameBoy,
+    GameBoyAdvance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatEffect {
+    /// Substitute `value` when the CPU reads `address` from ROM. If `compare` is set, the
+    /// substitution only applies if the original ROM value matches.
+    RomPatch { address: u32, value: u8, compare: Option<u8> },
+    /// Write `value` to `address` once per frame. If `bank` is set, the write goes to that bank of
+    /// the banked RAM window containing `address` rather than to whichever bank is mapped.
+    RamWrite { address: u32, value: u8, bank: Option<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CheatDecodeError {
+    #[error("unrecognized cheat code format")]
+    InvalidFormat,
+    #[error("invalid character in cheat code: '{0}'")]
+    InvalidCharacter(char),
+    #[error("unsupported code type: {0:02X}")]
+    UnsupportedCodeType(u8),
+    #[error("cheat address is out of range: {0:X}")]
+    AddressOutOfRange(u32),
+}
+
+const NES_GAME_GENIE_CHARS: &[u8] = b"APZLGITYEOXUKSVN";
+const SNES_GAME_GENIE_CHARS: &[u8] = b"DF4709156BC8A23E";
+const GENESIS_GAME_GENIE_CHARS: &[u8] = b"ABCDEFGHJKLMNPRSTVWXYZ0123456789";
+
+// Scrambled bit layouts. In each string, the first character is the most significant bit of the
+// encoded value; uppercase letters are address bits (A = MSB) and lowercase letters are data bits
+// (a = MSB)
+const SNES_GAME_GENIE_ADDRESS_LAYOUT: &[u8] = b"ijklqrstopabcduvwxefghmn";
+const GENESIS_GAME_GENIE_LAYOUT: &[u8] = b"ijklmnopIJKLMNOPABCDEFGHdefghabcQRSTUVWX";
+
+// Encryption seeds used by GameShark (v1/v2) codes for the Game Boy Advance
+const GBA_GAMESHARK_SEEDS: [u32; 4] = [0x09F4FBBD, 0x9681884A, 0x352027E9, 0xF3DEE5A7];
+
+/// Decode a cheat code for the given system.
+///
+/// Supported formats:
+/// * All systems: raw `ADDRESS:VALUE` codes (hex), where a 4-digit value writes 2 bytes and an
+///   8-digit value writes 4 bytes. Addresses in ROM are treated as ROM patches, and all other
+///   addresses are treated as RAM writes
+/// * NES: 6- and 8-letter Game Genie codes, and `AAAA?CC:VV` ROM patches with a compare value
+/// * SNES: Game Genie codes (`XXXX-XXXX`) and Pro Action Replay codes (`AAAAAAVV`); 8-digit codes
+///   without a dash are detected by which format decodes to a plausible address
+/// * Genesis: Game Genie codes (`XXXX-XXXX`)
+/// * Master System / Game Gear: Game Genie codes (`XXX-XXX(-XXX)`) and Pro Action Replay codes
+///   (`00AA-AAVV`)
+/// * Game Boy: Game Genie codes (`XXX-XXX(-XXX)`) and GameShark codes (`01VVLLHH`), including
+///   the banked forms `8BVVLLHH` (cartridge RAM bank B) and `9BVVLLHH` (GBC work RAM bank B)
+/// * Game Boy Advance: encrypted GameShark / Action Replay v1/v2 codes (`XXXXXXXX YYYYYYYY`), one
+///   or more per cheat
+///
+/// # Errors
+///
+/// Returns an error if the code is not in a recognized format for the system.
+pub fn decode(system: CheatSystem, code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    // Lengths of the dash-separated groups, e.g. [4, 4] for XXXX-XXXX
+    let group_lens: Vec<usize> =
+        code.split('-').map(|group| group.chars().filter(|c| !c.is_whitespace()).count()).collect();
+    let cleaned: String = code
+        .chars()
+        .filter(|&c| !c.is_whitespace() && c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if cleaned.is_empty() {
+        return Err(CheatDecodeError::InvalidFormat);
+    }
+
+    if cleaned.contains(':') {
+        return decode_raw(system, &cleaned);
+    }
+
+    match (system, cleaned.len()) {
+        (CheatSystem::Nes, 6 | 8) => decode_nes_game_genie(&cleaned).map(|effect| vec![effect]),
+        (CheatSystem::Snes, 8) => match group_lens.as_slice() {
+            [4, 4] => decode_snes_game_genie(&cleaned),
+            [8] => decode_snes_undashed(&cleaned),
+            _ => Err(CheatDecodeError::InvalidFormat),
+        },
+        (CheatSystem::Genesis, 8) => decode_genesis_game_genie(&cleaned),
+        (CheatSystem::SmsGg | CheatSystem::GameBoy, 6 | 9) => {
+            decode_gb_game_genie(&cleaned).map(|effect| vec![effect])
+        }
+        (CheatSystem::SmsGg, 8) => decode_sms_par(&cleaned),
+        (CheatSystem::GameBoy, 8) => decode_gb_gameshark(&cleaned),
+        (CheatSystem::GameBoyAdvance, len) if len != 0 && len % 16 == 0 => {
+            decode_gba_gameshark(&cleaned)
+        }
+        _ => Err(CheatDecodeError::InvalidFormat),
+    }
+}
+
+fn parse_hex(s: &str) -> Result<u32, CheatDecodeError> {
+    if s.is_empty() || s.len() > 8 {
+        return Err(CheatDecodeError::InvalidFormat);
+    }
+
+    s.chars().try_fold(0_u32, |acc, c| {
+        let digit = c.to_digit(16).ok_or(CheatDecodeError::InvalidCharacter(c))?;
+        Ok((acc << 4) | digit)
+    })
+}
+
+fn decode_chars(s: &str, charset: &[u8]) -> Result<Vec<u8>, CheatDecodeError> {
+    s.chars()
+        .map(|c| {
+            u8::try_from(c)
+                .ok()
+                .and_then(|b| charset.iter().position(|&cs| cs == b))
+                .map(|idx| idx as u8)
+                .ok_or(CheatDecodeError::InvalidCharacter(c))
+        })
+        .collect()
+}
+
+fn is_rom_address(system: CheatSystem, address: u32) -> bool {
+    match system {
+        CheatSystem::Nes => (0x8000..=0xFFFF).contains(&address),
+        CheatSystem::Snes => {
+            // Banks $40-$6F and $C0-$FF are entirely ROM, and other banks (except for the WRAM
+            // banks $7E-$7F) map ROM to $8000-$FFFF
+            let bank = address >> 16;
+            matches!(bank, 0x40..=0x6F | 0xC0..=0xFF)
+                || (address & 0xFFFF >= 0x8000 && !matches!(bank, 0x7E..=0x7F))
+        }
+        CheatSystem::Genesis => address < 0x400000,
+        CheatSystem::SmsGg => address < 0xC000,
+        CheatSystem::GameBoy => address < 0x8000,
+        CheatSystem::GameBoyAdvance => (0x08000000..0x0E000000).contains(&address),
+    }
+}
+
+fn max_address(system: CheatSystem) -> u32 {
+    match system {
+        CheatSystem::Nes | CheatSystem::SmsGg | CheatSystem::GameBoy => 0xFFFF,
+        CheatSystem::Snes | CheatSystem::Genesis => 0xFFFFFF,
+        CheatSystem::GameBoyAdvance => 0x0FFFFFFF,
+    }
+}
+
+fn is_big_endian(system: CheatSystem) -> bool {
+    matches!(system, CheatSystem::Genesis)
+}
+
+/// Split a multi-byte value into per-byte effects, using the system's endianness.
+fn value_effects(
+    system: CheatSystem,
+    address: u32,
+    value: u32,
+    len: u32,
+    rom_compare: Option<u8>,
+) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let last_address = address.checked_add(len - 1);
+    if last_address.is_none_or(|last_address| last_address > max_address(system)) {
+        return Err(CheatDecodeError::AddressOutOfRange(address));
+    }
+
+    Ok((0..len)
+        .map(|i| {
+            let shift = if is_big_endian(system) { 8 * (len - 1 - i) } else { 8 * i };
+            let byte_address = address + i;
+            let byte = (value >> shift) as u8;
+            if is_rom_address(system, byte_address) {
+                CheatEffect::RomPatch { address: byte_address, value: byte, compare: rom_compare }
+            } else {
+                CheatEffect::RamWrite { address: byte_address, value: byte, bank: None }
+            }
+        })
+        .collect())
+}
+
+// ADDRESS:VALUE or ADDRESS?COMPARE:VALUE
+fn decode_raw(system: CheatSystem, code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let (address_part, value) = code.split_once(':').ok_or(CheatDecodeError::InvalidFormat)?;
+    let (address, compare) = match address_part.split_once('?') {
+        Some((address, compare)) => {
+            if compare.len() != 2 {
+                return Err(CheatDecodeError::InvalidFormat);
+            }
+            (address, Some(parse_hex(compare)? as u8))
+        }
+        None => (address_part, None),
+    };
+
+    let address = parse_hex(address)?;
+    let len = match value.len() {
+        1 | 2 => 1,
+        4 => 2,
+        8 => 4,
+        _ => return Err(CheatDecodeError::InvalidFormat),
+    };
+    let value = parse_hex(value)?;
+
+    if compare.is_some() && (len != 1 || !is_rom_address(system, address)) {
+        return Err(CheatDecodeError::InvalidFormat);
+    }
+
+    value_effects(system, address, value, len, compare)
+}
+
+fn decode_nes_game_genie(code: &str) -> Result<CheatEffect, CheatDecodeError> {
+    let n = decode_chars(code, NES_GAME_GENIE_CHARS)?;
+
+    let address = 0x8000
+        | (u32::from(n[3] & 7) << 12)
+        | (u32::from(n[5] & 7) << 8)
+        | (u32::from(n[4] & 8) << 8)
+        | (u32::from(n[2] & 7) << 4)
+        | (u32::from(n[1] & 8) << 4)
+        | u32::from(n[4] & 7)
+        | u32::from(n[3] & 8);
+
+    if n.len() == 6 {
+        let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7) | (n[5] & 8);
+        Ok(CheatEffect::RomPatch { address, value, compare: None })
+    } else {
+        let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7) | (n[7] & 8);
+        let compare = ((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8);
+        Ok(CheatEffect::RomPatch { address, value, compare: Some(compare) })
+    }
+}
+
+/// Unscramble bits according to a layout string; returns (address, data).
+fn unscramble(encoded: u64, encoded_len: usize, layout: &[u8]) -> (u32, u32) {
+    let mut address = 0;
+    let mut data = 0;
+
+    for (i, &c) in layout.iter().enumerate() {
+        let bit = (encoded >> (encoded_len - 1 - i)) & 1 == 1;
+        if !bit {
+            continue;
+        }
+
+        if c.is_ascii_uppercase() {
+            address |= 1 << (23 - (c - b'A'));
+        } else {
+            data |= 1 << (15 - (c - b'a'));
+        }
+    }
+
+    (address, data)
+}
+
+fn decode_snes_game_genie(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let n = decode_chars(code, SNES_GAME_GENIE_CHARS)?;
+    let encoded = n.iter().fold(0_u32, |acc, &digit| (acc << 4) | u32::from(digit));
+
+    let value = (encoded >> 24) as u8;
+
+    // The address layout only uses letters a-x, all of which are address bits
+    let mut address = 0;
+    for (i, &c) in SNES_GAME_GENIE_ADDRESS_LAYOUT.iter().enumerate() {
+        if (encoded >> (23 - i)) & 1 != 0 {
+            address |= 1 << (23 - (c - b'a'));
+        }
+    }
+
+    Ok(vec![CheatEffect::RomPatch { address, value, compare: None }])
+}
+
+// Game Genie and Pro Action Replay codes are both 8 hex digits; the Game Genie alphabet is a
+// permutation of the hex digits, so the characters alone cannot tell them apart. Pro Action Replay
+// codes almost always write to RAM, while Game Genie codes can only patch ROM.
+fn decode_snes_undashed(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let par_address = parse_hex(code)? >> 8;
+    if is_rom_address(CheatSystem::Snes, par_address) {
+        let game_genie = decode_snes_game_genie(code)?;
+        if game_genie.iter().all(|effect| matches!(effect, CheatEffect::RomPatch { address, .. } if is_rom_address(CheatSystem::Snes, *address))) {
+            return Ok(game_genie);
+        }
+    }
+
+    decode_snes_par(code)
+}
+
+fn decode_snes_par(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let encoded = parse_hex(code)?;
+    let address = encoded >> 8;
+    let value = encoded & 0xFF;
+
+    value_effects(CheatSystem::Snes, address, value, 1, None)
+}
+
+fn decode_genesis_game_genie(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let n = decode_chars(code, GENESIS_GAME_GENIE_CHARS)?;
+    let encoded = n.iter().fold(0_u64, |acc, &digit| (acc << 5) | u64::from(digit));
+
+    let (address, value) = unscramble(encoded, 40, GENESIS_GAME_GENIE_LAYOUT);
+
+    // Game Genie patches are always to even addresses
+    value_effects(CheatSystem::Genesis, address & !1, value, 2, None)
+}
+
+fn decode_gb_game_genie(code: &str) -> Result<CheatEffect, CheatDecodeError> {
+    let n: Vec<u8> = code
+        .chars()
+        .map(|c| {
+            c.to_digit(16).map(|digit| digit as u8).ok_or(CheatDecodeError::InvalidCharacter(c))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let value = (n[0] << 4) | n[1];
+    let address = (u32::from(n[5] ^ 0xF) << 12)
+        | (u32::from(n[2]) << 8)
+        | (u32::from(n[3]) << 4)
+        | u32::from(n[4]);
+
+    let compare = (n.len() == 9).then(|| ((n[6] << 4) | n[8]).rotate_right(2) ^ 0xBA);
+
+    Ok(CheatEffect::RomPatch { address, value, compare })
+}
+
+fn decode_sms_par(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let encoded = parse_hex(code)?;
+    let address = (encoded >> 8) & 0xFFFF;
+    let value = encoded & 0xFF;
+
+    value_effects(CheatSystem::SmsGg, address, value, 1, None)
+}
+
+fn decode_gb_gameshark(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let encoded = parse_hex(code)?;
+    let code_type = (encoded >> 24) as u8;
+    let value = (encoded >> 16) as u8;
+    let address = u32::from(u16::from_le_bytes([(encoded >> 8) as u8, encoded as u8]));
+
+    // Banked forms select the bank of the switchable RAM window that the address is in
+    let (bank, window) = match code_type {
+        0x00 | 0x01 => return value_effects(CheatSystem::GameBoy, address, value.into(), 1, None),
+        0x80..=0x8F => (code_type & 0x0F, 0xA000..=0xBFFF),
+        0x90..=0x97 => (code_type & 0x07, 0xD000..=0xDFFF),
+        _ => return Err(CheatDecodeError::UnsupportedCodeType(code_type)),
+    };
+
+    if !window.contains(&address) {
+        return Err(CheatDecodeError::AddressOutOfRange(address));
+    }
+
+    Ok(vec![CheatEffect::RamWrite { address, value, bank: Some(bank) }])
+}
+
+fn decrypt_gba_gameshark(mut address: u32, mut value: u32) -> (u32, u32) {
+    let [k0, k1, k2, k3] = GBA_GAMESHARK_SEEDS;
+    let mut sum: u32 = 0xC6EF3720;
+
+    for _ in 0..32 {
+        value = value.wrapping_sub(
+            (address << 4).wrapping_add(k2)
+                ^ address.wrapping_add(sum)
+                ^ (address >> 5).wrapping_add(k3),
+        );
+        address = address.wrapping_sub(
+            (value << 4).wrapping_add(k0) ^ value.wrapping_add(sum) ^ (value >> 5).wrapping_add(k1),
+        );
+        sum = sum.wrapping_sub(0x9E3779B9);
+    }
+
+    (address, value)
+}
+
+fn decode_gba_gameshark(code: &str) -> Result<Vec<CheatEffect>, CheatDecodeError> {
+    let mut effects = Vec::new();
+
+    for i in (0..code.len()).step_by(16) {
+        // Code length is in bytes, so slicing can fail if the code contains non-ASCII characters
+        let address = parse_hex(code.get(i..i + 8).ok_or(CheatDecodeError::InvalidFormat)?)?;
+        let value = parse_hex(code.get(i + 8..i + 16).ok_or(CheatDecodeError::InvalidFormat)?)?;
+        let (address, value) = decrypt_gba_gameshark(address, value);
+
+        let code_type = (address >> 28) as u8;
+        let target = address & 0x0FFFFFFF;
+        let system = CheatSystem::GameBoyAdvance;
+        match code_type {
+            0x0 => effects.extend(value_effects(system, target, value & 0xFF, 1, None)?),
+            0x1 => effects.extend(value_effects(system, target, value & 0xFFFF, 2, None)?),
+            0x2 => effects.extend(value_effects(system, target, value, 4, None)?),
+            0x6 => {
+                // ROM patch; address is in halfwords relative to the start of ROM
+                let rom_address = 0x08000000 + ((target << 1) & 0x01FFFFFF);
+                effects.extend(value_effects(system, rom_address, value & 0xFFFF, 2, None)?);
+            }
+            _ => return Err(CheatDecodeError::UnsupportedCodeType(code_type)),
+        }
+    }
+
+    Ok(effects)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RomPatch {
+    value: u8,
+    compare: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RamWrite {
+    pub address: u32,
+    pub value: u8,
+    pub bank: Option<u8>,
+}
+
+/// The set of currently active cheat effects.
+///
+/// Not included in save states; backends should move this along with ROM when loading a state.
+#[derive(Debug, Clone, Default, FakeEncode, FakeDecode)]
+pub struct CheatSet {
+    rom_patches: HashMap<u32, Vec<RomPatch>>,
+    ram_writes: Vec<RamWrite>,
+}
+
+impl CheatSet {
+    #[must_use]
+    pub fn new<I: IntoIterator<Item = CheatEffect>>(effects: I) -> Self {
+        let mut rom_patches: HashMap<u32, Vec<RomPatch>> = HashMap::new();
+        let mut ram_writes = Vec::new();
+
+        for effect in effects {
+            match effect {
+                CheatEffect::RomPatch { address, value, compare } => {
+                    rom_patches.entry(address).or_default().push(RomPatch { value, compare });
+                }
+                CheatEffect::RamWrite { address, value, bank } => {
+                    ram_writes.push(RamWrite { address, value, bank });
+                }
+            }
+        }
+
+        Self { rom_patches, ram_writes }
+    }
+
+    /// Decode the given codes and build a cheat set from them. Invalid codes are logged and
+    /// skipped.
+    #[must_use]
+    pub fn from_codes<S: AsRef<str>>(system: CheatSystem, codes: &[S]) -> Self {
+        Self::new(codes.iter().flat_map(|code| {
+            let code = code.as_ref();
+            decode(system, code).unwrap_or_else(|err| {
+                log::error!("Ignoring invalid cheat code '{code}': {err}");
+                vec![]
+            })
+        }))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rom_patches.is_empty() && self.ram_writes.is_empty()
+    }
+
+    /// Apply any ROM patches for the given address to a byte read from ROM.
+    #[inline]
+    #[must_use]
+    pub fn patch_rom_u8(&self, address: u32, value: u8) -> u8 {
+        if self.rom_patches.is_empty() {
+            return value;
+        }
+
+        self.patch_rom_u8_slow(address, value)
+    }
+
+    fn patch_rom_u8_slow(&self, address: u32, value: u8) -> u8 {
+        let Some(patches) = self.rom_patches.get(&address) else { return value };
+
+        patches
+            .iter()
+            .find(|patch| patch.compare.is_none_or(|compare| compare == value))
+            .map_or(value, |patch| patch.value)
+    }
+
+    /// Apply any ROM patches to a big-endian 16-bit word read from ROM.
+    #[inline]
+    #[must_use]
+    pub fn patch_rom_u16_be(&self, address: u32, value: u16) -> u16 {
+        if self.rom_patches.is_empty() {
+            return value;
+        }
+
+        let [msb, lsb] = value.to_be_bytes();
+        u16::from_be_bytes([
+            self.patch_rom_u8_slow(address, msb),
+            self.patch_rom_u8_slow(address.wrapping_add(1), lsb),
+        ])
+    }
+
+    /// Apply any ROM patches to a little-endian 16-bit halfword read from ROM.
+    #[inline]
+    #[must_use]
+    pub fn patch_rom_u16_le(&self, address: u32, value: u16) -> u16 {
+        if self.rom_patches.is_empty() {
+            return value;
+        }
+
+        let [lsb, msb] = value.to_le_bytes();
+        u16::from_le_bytes([
+            self.patch_rom_u8_slow(address, lsb),
+            self.patch_rom_u8_slow(address.wrapping_add(1), msb),
+        ])
+    }
+
+    /// Apply any ROM patches to a little-endian 32-bit word read from ROM.
+    #[inline]
+    #[must_use]
+    pub fn patch_rom_u32_le(&self, address: u32, value: u32) -> u32 {
+        if self.rom_patches.is_empty() {
+            return value;
+        }
+
+        let bytes = value.to_le_bytes();
+        u32::from_le_bytes(std::array::from_fn(|i| {
+            self.patch_rom_u8_slow(address.wrapping_add(i as u32), bytes[i])
+        }))
+    }
+
+    /// RAM writes that should be applied once per frame.
+    #[inline]
+    #[must_use]
+    pub fn ram_writes(&self) -> &[RamWrite] {
+        &self.ram_writes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nes_game_genie() {
+        assert_eq!(
+            decode(CheatSystem::Nes, "SXIOPO"),
+            Ok(vec![CheatEffect::RomPatch { address: 0x91D9, value: 0xAD, compare: None }])
+        );
+        assert_eq!(
+            decode(CheatSystem::Nes, "YEUZUGAA"),
+            Ok(vec![CheatEffect::RomPatch { address: 0xACB3, value: 0x07, compare: Some(0x00) }])
+        );
+    }
+
+    #[test]
+    fn raw_codes() {
+        assert_eq!(
+            decode(CheatSystem::Genesis, "FF0010:1234"),
+            Ok(vec![
+                CheatEffect::RamWrite { address: 0xFF0010, value: 0x12, bank: None },
+                CheatEffect::RamWrite { address: 0xFF0011, value: 0x34, bank: None },
+            ])
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoyAdvance, "03000100:BEEF"),
+            Ok(vec![
+                CheatEffect::RamWrite { address: 0x03000100, value: 0xEF, bank: None },
+                CheatEffect::RamWrite { address: 0x03000101, value: 0xBE, bank: None },
+            ])
+        );
+        assert_eq!(
+            decode(CheatSystem::Nes, "C123?45:67"),
+            Ok(vec![CheatEffect::RomPatch { address: 0xC123, value: 0x67, compare: Some(0x45) }])
+        );
+    }
+
+    #[test]
+    fn gb_codes() {
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "01FF34C1"),
+            Ok(vec![CheatEffect::RamWrite { address: 0xC134, value: 0xFF, bank: None }])
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "830512A0"),
+            Ok(vec![CheatEffect::RamWrite { address: 0xA012, value: 0x05, bank: Some(3) }])
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "9263E0D4"),
+            Ok(vec![CheatEffect::RamWrite { address: 0xD4E0, value: 0x63, bank: Some(2) }])
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "9263E0C4"),
+            Err(CheatDecodeError::AddressOutOfRange(0xC4E0))
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "A163E0C4"),
+            Err(CheatDecodeError::UnsupportedCodeType(0xA1))
+        );
+        assert_eq!(
+            decode(CheatSystem::GameBoy, "004-5CF-E62"),
+            Ok(vec![CheatEffect::RomPatch { address: 0x045C, value: 0x00, compare: Some(0x02) }])
+        );
+    }
+
+    #[test]
+    fn snes_game_genie() {
+        assert_eq!(
+            decode(CheatSystem::Snes, "3C60-67AC"),
+            Ok(vec![CheatEffect::RomPatch { address: 0x028A4F, value: 0xEA, compare: None }])
+        );
+
+        // Without a dash, detected because the Pro Action Replay interpretation would patch ROM
+        let expected =
+            Ok(vec![CheatEffect::RomPatch { address: 0xC0FFEE, value: 0x12, compare: None }]);
+        assert_eq!(decode(CheatSystem::Snes, "F4E3-E767"), expected);
+        assert_eq!(decode(CheatSystem::Snes, "f4e3e767"), expected);
+
+        assert_eq!(decode(CheatSystem::Snes, "3C-6067AC"), Err(CheatDecodeError::InvalidFormat));
+        assert_eq!(
+            decode(CheatSystem::Snes, "3C60-67AG"),
+            Err(CheatDecodeError::InvalidCharacter('G'))
+        );
+    }
+
+    #[test]
+    fn snes_pro_action_replay() {
+        assert_eq!(
+            decode(CheatSystem::Snes, "7E0DBE63"),
+            Ok(vec![CheatEffect::RamWrite { address: 0x7E0DBE, value: 0x63, bank: None }])
+        );
+        assert_eq!(
+            decode(CheatSystem::Snes, "701234FF"),
+            Ok(vec![CheatEffect::RamWrite { address: 0x701234, value: 0xFF, bank: None }])
+        );
+    }
+
+    #[test]
+    fn genesis_game_genie() {
+        assert_eq!(
+            decode(CheatSystem::Genesis, "RH3A-C6ZE"),
+            Ok(vec![
+                CheatEffect::RomPatch { address: 0x01F2A4, value: 0x4E, compare: None },
+                CheatEffect::RomPatch { address: 0x01F2A5, value: 0x71, compare: None },
+            ])
+        );
+        assert_eq!(
+            decode(CheatSystem::Genesis, "RH3A-C6ZI"),
+            Err(CheatDecodeError::InvalidCharacter('I'))
+        );
+    }
+
+    #[test]
+    fn sms_codes() {
+        assert_eq!(
+            decode(CheatSystem::SmsGg, "00C1-2305"),
+            Ok(vec![CheatEffect::RamWrite { address: 0xC123, value: 0x05, bank: None }])
+        );
+        assert_eq!(
+            decode(CheatSystem::SmsGg, "004-5CF-E62"),
+            Ok(vec![CheatEffect::RomPatch { address: 0x045C, value: 0x00, compare: Some(0x02) }])
+        );
+    }
+
+    #[test]
+    fn gba_gameshark() {
+        // 8-bit write
+        assert_eq!(
+            decode(CheatSystem::GameBoyAdvance, "9E724895 D30BA106"),
+            Ok(vec![CheatEffect::RamWrite { address: 0x03001234, value: 0x63, bank: None }])
+        );
+        // 16-bit write and ROM patch in the same cheat
+        assert_eq!(
+            decode(CheatSystem::GameBoyAdvance, "906C8658 933D874E\n451A1BD0 EF967646"),
+            Ok(vec![
+                CheatEffect::RamWrite { address: 0x02001000, value: 0xEF, bank: None },
+                CheatEffect::RamWrite { address: 0x02001001, value: 0xBE, bank: None },
+                CheatEffect::RomPatch { address: 0x08000200, value: 0x34, compare: None },
+                CheatEffect::RomPatch { address: 0x08000201, value: 0x12, compare: None },
+            ])
+        );
+    }
+
+    #[test]
+    fn gba_gameshark_non_ascii() {
+        assert_eq!(
+            decode(CheatSystem::GameBoyAdvance, "0123456\u{E9}012345"),
+            Err(CheatDecodeError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn address_overflow() {
+        assert_eq!(
+            decode(CheatSystem::GameBoyAdvance, "FFFFFFFF:12345678"),
+            Err(CheatDecodeError::AddressOutOfRange(0xFFFFFFFF))
+        );
+    }
+
+    #[test]
+    fn rom_patch_compare() {
+        let cheats = CheatSet::new([
+            CheatEffect::RomPatch { address: 0x8000, value: 0x11, compare: Some(0x22) },
+            CheatEffect::RomPatch { address: 0x8001, value: 0x33, compare: None },
+        ]);
+
+        assert_eq!(cheats.patch_rom_u8(0x8000, 0x22), 0x11);
+        assert_eq!(cheats.patch_rom_u8(0x8000, 0x23), 0x23);
+        assert_eq!(cheats.patch_rom_u16_le(0x8000, 0x4422), 0x3311);
+        assert_eq!(cheats.patch_rom_u8(0x8002, 0x55), 0x55);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::num::{GetBit, U16Ext};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[allow(clippy::len_without_is_empty)]
 pub trait DebugMemoryView {
@@ -154,3 +155,33 @@ impl DebugMemoryView for EmptyDebugView {
 
     fn write(&mut self, _address: usize, _value: u8) {}
 }
+
+/// CPU data access that triggered a read or write breakpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    pub address: u32,
+    pub write: bool,
+}
+
+/// Lock-free storage for the watchpoint hit that caused the current break, if any
+#[derive(Debug, Default)]
+pub struct AtomicWatchpointHit(AtomicU64);
+
+impl AtomicWatchpointHit {
+    const VALID_BIT: u64 = 1 << 32;
+    const WRITE_BIT: u64 = 1 << 33;
+
+    pub fn store(&self, hit: Option<WatchpointHit>) {
+        let value = hit.map_or(0, |hit| {
+            Self::VALID_BIT | (u64::from(hit.write) * Self::WRITE_BIT) | u64::from(hit.address)
+        });
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn load(&self) -> Option<WatchpointHit> {
+        let value = self.0.load(Ordering::Relaxed);
+        (value & Self::VALID_BIT != 0)
+            .then_some(WatchpointHit { address: value as u32, write: value & Self::WRITE_BIT != 0 })
+    }
+}
@@ -1,5 +1,6 @@
 mod finitefloat;
 
+use crate::cheats::{CheatSet, CheatSystem};
 use bincode::{Decode, Encode};
 pub use finitefloat::{FiniteF32, FiniteF64};
 use jgenesis_proc_macros::{EnumAll, EnumDisplay, EnumFromStr};
@@ -186,6 +187,12 @@ pub trait PartialClone {
     fn partial_clone(&self) -> Self;
 }
 
+impl<T: PartialClone> PartialClone for Box<T> {
+    fn partial_clone(&self) -> Self {
+        Box::new(self.as_ref().partial_clone())
+    }
+}
+
 use crate::input::Player;
 pub use jgenesis_proc_macros::PartialClone;
 
@@ -255,9 +262,20 @@ pub trait EmulatorConfigTrait: Clone + Send + Sync + 'static {
 
 pub trait EmulatorTrait: Encode + Decode<()> + PartialClone + 'static {
     type Button: Debug + Copy + Eq + Hash;
-    type Inputs: Clone + Eq + Default + MappableInputs<Self::Button> + Send + Sync + 'static;
+    type Inputs: Clone
+        + Eq
+        + Default
+        + Encode
+        + Decode<()>
+        + MappableInputs<Self::Button>
+        + Send
+        + Sync
+        + 'static;
     type Config: EmulatorConfigTrait;
 
+    /// The system that this emulator's cheat codes are written for.
+    const CHEAT_SYSTEM: CheatSystem;
+
     type Err<RErr: Debug + Display + Send + Sync + 'static, AErr: Debug + Display + Send + Sync + 'static, SErr: Debug + Display + Send + Sync + 'static>: Error + Send + Sync + 'static;
 
     /// Tick the emulator for a small amount of time, e.g. a single CPU instruction.
@@ -293,6 +311,9 @@ pub trait EmulatorTrait: Encode + Decode<()> + PartialClone + 'static {
 
     fn take_rom_from(&mut self, other: &mut Self);
 
+    /// Replace the set of active cheats. Cheats are not persisted in save states.
+    fn set_cheats(&mut self, cheats: CheatSet);
+
     fn soft_reset(&mut self);
 
     fn hard_reset<S: SaveWriter>(&mut self, save_writer: &mut S);
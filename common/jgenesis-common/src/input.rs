@@ -2,6 +2,9 @@
 pub enum Player {
     One,
     Two,
+    Three,
+    Four,
+    Five,
 }
 
 #[inline]
@@ -143,6 +146,7 @@ macro_rules! define_controller_inputs {
 
             impl ::jgenesis_common::frontend::MappableInputs<$button_enum> for $inputs_struct {
                 #[inline]
+                #[allow(unreachable_patterns, clippy::match_wildcard_for_single_variants)]
                 fn set_field(
                     &mut self,
                     button: $button_enum,
@@ -160,6 +164,8 @@ macro_rules! define_controller_inputs {
                                 self.$player_field.set_button(button, pressed);
                             }
                         )*
+                        // Player not supported by this system
+                        _ => {}
                     }
                 }
             }
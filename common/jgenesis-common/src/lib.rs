@@ -1,6 +1,7 @@
 pub mod appimage;
 pub mod audio;
 pub mod boxedarray;
+pub mod cheats;
 pub mod debug;
 pub mod frontend;
 pub mod input;
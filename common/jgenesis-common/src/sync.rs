@@ -34,6 +34,22 @@ impl<T> SharedVarReceiver<T> {
 
         self.latest.as_mut()
     }
+
+    /// Returns the most recently received value only if a new value has been received since the
+    /// last call to [`Self::get`] or [`Self::get_if_updated`].
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // Mutex poisoning is impossible here
+    pub fn get_if_updated(&mut self) -> Option<&mut T> {
+        if self.state.updated.compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+            == Ok(true)
+            && let Some(value) = self.state.locked.lock().unwrap().take()
+        {
+            self.latest = Some(value);
+            return self.latest.as_mut();
+        }
+
+        None
+    }
 }
 
 impl<T> SharedVarSender<T> {
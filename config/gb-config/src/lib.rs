@@ -64,6 +64,10 @@ define_controller_inputs! {
         B -> b,
         Start -> start,
         Select -> select,
+        TiltUp -> tilt_up,
+        TiltLeft -> tilt_left,
+        TiltRight -> tilt_right,
+        TiltDown -> tilt_down,
     },
     joypad: GameBoyInputs,
 }
@@ -1,6 +1,7 @@
 use bincode::{Decode, Encode};
 use jgenesis_common::define_controller_inputs;
-use jgenesis_common::frontend::{FiniteF64, FrameSize, TimingMode};
+use jgenesis_common::frontend::{DisplayArea, FiniteF64, FrameSize, MappableInputs, TimingMode};
+use jgenesis_common::input::Player;
 use jgenesis_proc_macros::{EnumAll, EnumDisplay, EnumFromStr};
 use std::fmt::{Display, Formatter};
 
@@ -162,9 +163,22 @@ pub enum GenesisControllerType {
     ThreeButton,
     #[default]
     SixButton,
+    TeamPlayer,
+    EaFourWayPlay,
+    MegaMouse,
+    Menacer,
+    Justifier,
     None,
 }
 
+impl GenesisControllerType {
+    #[inline]
+    #[must_use]
+    pub fn is_light_gun(self) -> bool {
+        matches!(self, Self::Menacer | Self::Justifier)
+    }
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode, EnumDisplay, EnumFromStr, EnumAll,
 )]
@@ -255,11 +269,113 @@ define_controller_inputs! {
         Start -> start,
         Mode -> mode,
     },
+    non_gamepad_buttons: [
+        MouseLeft,
+        MouseRight,
+        MouseMiddle,
+        MouseStart,
+        LightGunTrigger,
+        LightGunA,
+        LightGunB,
+        LightGunStart,
+        LightGunForceOffscreen,
+    ],
     joypad: GenesisJoypadState,
-    inputs: GenesisInputs {
-        players: {
-            p1: Player::One,
-            p2: Player::Two,
-        },
-    },
+}
+
+impl GenesisButton {
+    #[inline]
+    #[must_use]
+    pub fn is_mouse(self) -> bool {
+        matches!(self, Self::MouseLeft | Self::MouseRight | Self::MouseMiddle | Self::MouseStart)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_light_gun(self) -> bool {
+        matches!(
+            self,
+            Self::LightGunTrigger
+                | Self::LightGunA
+                | Self::LightGunB
+                | Self::LightGunStart
+                | Self::LightGunForceOffscreen
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Encode, Decode)]
+pub struct MegaMouseState {
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+    pub start: bool,
+}
+
+/// Button state for the Menacer and the Justifier. The Justifier only has a trigger and a start
+/// button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Encode, Decode)]
+pub struct LightGunState {
+    pub trigger: bool,
+    pub a: bool,
+    pub b: bool,
+    pub start: bool,
+    pub force_offscreen: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Encode, Decode)]
+pub struct GenesisInputs {
+    pub p1: GenesisJoypadState,
+    pub p2: GenesisJoypadState,
+    pub p3: GenesisJoypadState,
+    pub p4: GenesisJoypadState,
+    pub mouse: MegaMouseState,
+    pub light_gun: LightGunState,
+    /// Mouse cursor position in frame buffer coordinates, shared by the Mega Mouse and light guns
+    pub pointer_position: Option<(u16, u16)>,
+}
+
+impl MappableInputs<GenesisButton> for GenesisInputs {
+    #[inline]
+    fn set_field(&mut self, button: GenesisButton, player: Player, pressed: bool) {
+        match (button, player) {
+            (GenesisButton::MouseLeft, _) => self.mouse.left = pressed,
+            (GenesisButton::MouseRight, _) => self.mouse.right = pressed,
+            (GenesisButton::MouseMiddle, _) => self.mouse.middle = pressed,
+            (GenesisButton::MouseStart, _) => self.mouse.start = pressed,
+            (GenesisButton::LightGunTrigger, _) => self.light_gun.trigger = pressed,
+            (GenesisButton::LightGunA, _) => self.light_gun.a = pressed,
+            (GenesisButton::LightGunB, _) => self.light_gun.b = pressed,
+            (GenesisButton::LightGunStart, _) => self.light_gun.start = pressed,
+            (GenesisButton::LightGunForceOffscreen, _) => {
+                self.light_gun.force_offscreen = pressed;
+            }
+            (button, Player::One) => self.p1.set_button(button, pressed),
+            (button, Player::Two) => self.p2.set_button(button, pressed),
+            (button, Player::Three) => self.p3.set_button(button, pressed),
+            (button, Player::Four) => self.p4.set_button(button, pressed),
+            (_, Player::Five) => {}
+        }
+    }
+
+    #[inline]
+    fn handle_mouse_motion(
+        &mut self,
+        x: f32,
+        y: f32,
+        frame_size: FrameSize,
+        display_area: DisplayArea,
+    ) {
+        self.pointer_position = jgenesis_common::input::viewport_position_to_frame_position(
+            x,
+            y,
+            frame_size,
+            display_area,
+        );
+    }
+
+    #[inline]
+    fn handle_mouse_leave(&mut self) {
+        self.pointer_position = None;
+    }
 }
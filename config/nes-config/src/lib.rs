@@ -194,7 +194,23 @@ define_controller_inputs! {
         Start -> start,
         Select -> select,
     },
-    non_gamepad_buttons: [ZapperFire, ZapperForceOffscreen],
+    non_gamepad_buttons: [
+        ZapperFire,
+        ZapperForceOffscreen,
+        VausButton,
+        PowerPad1,
+        PowerPad2,
+        PowerPad3,
+        PowerPad4,
+        PowerPad5,
+        PowerPad6,
+        PowerPad7,
+        PowerPad8,
+        PowerPad9,
+        PowerPad10,
+        PowerPad11,
+        PowerPad12,
+    ],
     joypad: NesJoypadState,
 }
 
@@ -204,6 +220,45 @@ impl NesButton {
     pub fn is_zapper(self) -> bool {
         matches!(self, Self::ZapperFire | Self::ZapperForceOffscreen)
     }
+
+    #[inline]
+    #[must_use]
+    pub fn is_vaus(self) -> bool {
+        self == Self::VausButton
+    }
+
+    /// Returns the Power Pad button number (1-12) if this is a Power Pad button.
+    #[inline]
+    #[must_use]
+    pub fn power_pad_button(self) -> Option<u8> {
+        match self {
+            Self::PowerPad1 => Some(1),
+            Self::PowerPad2 => Some(2),
+            Self::PowerPad3 => Some(3),
+            Self::PowerPad4 => Some(4),
+            Self::PowerPad5 => Some(5),
+            Self::PowerPad6 => Some(6),
+            Self::PowerPad7 => Some(7),
+            Self::PowerPad8 => Some(8),
+            Self::PowerPad9 => Some(9),
+            Self::PowerPad10 => Some(10),
+            Self::PowerPad11 => Some(11),
+            Self::PowerPad12 => Some(12),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_power_pad(self) -> bool {
+        self.power_pad_button().is_some()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_peripheral(self) -> bool {
+        self.is_zapper() || self.is_vaus() || self.is_power_pad()
+    }
 }
 
 #[cfg(test)]
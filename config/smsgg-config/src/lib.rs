@@ -1,6 +1,7 @@
 use bincode::{Decode, Encode};
 use jgenesis_common::define_controller_inputs;
-use jgenesis_common::frontend::FiniteF64;
+use jgenesis_common::frontend::{DisplayArea, FiniteF64, FrameSize, MappableInputs};
+use jgenesis_common::input::Player;
 use jgenesis_proc_macros::{EnumAll, EnumDisplay, EnumFromStr};
 
 pub const NATIVE_Z80_DIVIDER: u32 = 15;
@@ -44,6 +45,17 @@ pub enum SmsGgRegion {
     Domestic,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode, EnumDisplay, EnumAll)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "clap", derive(jgenesis_proc_macros::CustomValueEnum))]
+pub enum SmsGgControllerType {
+    #[default]
+    Gamepad,
+    LightPhaser,
+    Paddle,
+    SportsPad,
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode, EnumDisplay, EnumFromStr, EnumAll,
 )]
@@ -117,15 +129,79 @@ define_controller_inputs! {
         Button1 -> button1,
         Button2 -> button2,
     },
-    non_gamepad_buttons: [Pause],
+    non_gamepad_buttons: [
+        Pause,
+        LightPhaserTrigger,
+        PaddleButton,
+        SportsPadButton1,
+        SportsPadButton2,
+    ],
     joypad: SmsGgJoypadState,
-    inputs: SmsGgInputs {
-        players: {
-            p1: Player::One,
-            p2: Player::Two,
-        },
-        buttons: [Pause -> pause],
-    },
+}
+
+impl SmsGgButton {
+    #[inline]
+    #[must_use]
+    pub fn is_peripheral(self) -> bool {
+        matches!(
+            self,
+            Self::LightPhaserTrigger
+                | Self::PaddleButton
+                | Self::SportsPadButton1
+                | Self::SportsPadButton2
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Encode, Decode)]
+pub struct SmsGgInputs {
+    pub p1: SmsGgJoypadState,
+    pub p2: SmsGgJoypadState,
+    pub pause: bool,
+    pub light_phaser_trigger: bool,
+    pub paddle_button: bool,
+    pub sports_pad_button_1: bool,
+    pub sports_pad_button_2: bool,
+    /// Mouse cursor position in frame buffer coordinates, shared by the Light Phaser, the Paddle
+    /// Control, and the Sports Pad
+    pub pointer_position: Option<(u16, u16)>,
+}
+
+impl MappableInputs<SmsGgButton> for SmsGgInputs {
+    #[inline]
+    fn set_field(&mut self, button: SmsGgButton, player: Player, pressed: bool) {
+        match (button, player) {
+            (SmsGgButton::Pause, _) => self.pause = pressed,
+            (SmsGgButton::LightPhaserTrigger, _) => self.light_phaser_trigger = pressed,
+            (SmsGgButton::PaddleButton, _) => self.paddle_button = pressed,
+            (SmsGgButton::SportsPadButton1, _) => self.sports_pad_button_1 = pressed,
+            (SmsGgButton::SportsPadButton2, _) => self.sports_pad_button_2 = pressed,
+            (button, Player::One) => self.p1.set_button(button, pressed),
+            (button, Player::Two) => self.p2.set_button(button, pressed),
+            (_, Player::Three | Player::Four | Player::Five) => {}
+        }
+    }
+
+    #[inline]
+    fn handle_mouse_motion(
+        &mut self,
+        x: f32,
+        y: f32,
+        frame_size: FrameSize,
+        display_area: DisplayArea,
+    ) {
+        self.pointer_position = jgenesis_common::input::viewport_position_to_frame_position(
+            x,
+            y,
+            frame_size,
+            display_area,
+        );
+    }
+
+    #[inline]
+    fn handle_mouse_leave(&mut self) {
+        self.pointer_position = None;
+    }
 }
 
 #[cfg(test)]
@@ -74,7 +74,16 @@ define_controller_inputs! {
         Start -> start,
         Select -> select,
     },
-    non_gamepad_buttons: [SuperScopeFire, SuperScopeCursor, SuperScopePause, SuperScopeTurboToggle],
+    non_gamepad_buttons: [
+        SuperScopeFire,
+        SuperScopeCursor,
+        SuperScopePause,
+        SuperScopeTurboToggle,
+        MouseLeft,
+        MouseRight,
+        JustifierTrigger,
+        JustifierStart,
+    ],
     joypad: SnesJoypadState,
 }
 
@@ -98,6 +107,24 @@ impl SnesButton {
             _ => None,
         }
     }
+
+    #[inline]
+    #[must_use]
+    pub fn is_mouse(self) -> bool {
+        matches!(self, Self::MouseLeft | Self::MouseRight)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_justifier(self) -> bool {
+        matches!(self, Self::JustifierTrigger | Self::JustifierStart)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_peripheral(self) -> bool {
+        self.to_super_scope().is_some() || self.is_mouse() || self.is_justifier()
+    }
 }
 
 impl SuperScopeButton {
@@ -262,6 +262,60 @@ impl<Bus: BusInterface> Arm7Tdmi<Bus> {
         }
     }
 
+    /// Address of the next instruction that the CPU will execute
+    #[must_use]
+    pub fn next_instruction_address(&self) -> u32 {
+        match self.registers.cpsr.state {
+            CpuState::Arm => self.registers.r[15].wrapping_sub(8),
+            CpuState::Thumb => self.registers.r[15].wrapping_sub(4),
+        }
+    }
+
+    /// Read R0-R14 in the current CPU mode
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r` is greater than 14.
+    #[must_use]
+    pub fn register(&self, r: usize) -> u32 {
+        assert!(r < 15, "Invalid register: R{r}");
+        self.registers.r[r]
+    }
+
+    /// Write R0-R14 in the current CPU mode
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r` is greater than 14.
+    pub fn set_register(&mut self, r: usize, value: u32) {
+        assert!(r < 15, "Invalid register: R{r}");
+        self.registers.r[r] = value;
+    }
+
+    /// Read the CPSR
+    #[must_use]
+    pub fn cpsr(&self) -> u32 {
+        self.registers.cpsr.into()
+    }
+
+    /// Read the SPSR for the current CPU mode, or `None` if the current mode does not have an SPSR
+    #[must_use]
+    pub fn spsr(&mut self) -> Option<u32> {
+        self.registers.cpsr.mode.spsr(&mut self.registers).map(|spsr| u32::from(*spsr))
+    }
+
+    /// Return from an exception handler, equivalent to executing MOVS PC, R14.
+    ///
+    /// Intended for emulating exception handlers outside of the CPU, e.g. high-level emulation of
+    /// software interrupts.
+    pub fn return_from_exception(&mut self, bus: &mut Bus) {
+        let return_address = self.registers.r[14];
+        self.spsr_to_cpsr();
+
+        self.registers.r[15] = return_address;
+        self.refill_prefetch(bus);
+    }
+
     fn refill_prefetch(&mut self, bus: &mut Bus) {
         self.fetch_cycle = MemoryCycle::N;
 
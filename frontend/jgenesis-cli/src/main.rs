@@ -11,20 +11,27 @@ use genesis_config::{GenesisAspectRatio, GenesisControllerType, GenesisRegion, O
 use jgenesis_common::frontend::{EmulatorTrait, TimingMode};
 use jgenesis_native_config::AppConfig;
 use jgenesis_native_config::common::{ConfigSavePath, HideMouseCursor};
-use jgenesis_native_config::input::mappings::{NesControllerType, SnesControllerType};
+use jgenesis_native_config::input::mappings::{
+    NesControllerType, NesFourPlayerAdapterType, SnesControllerType,
+};
 use jgenesis_native_driver::config::AppConfigExt;
 use jgenesis_native_driver::extensions::{Console, ConsoleWithSize};
-use jgenesis_native_driver::{NativeEmulator, NativeTickEffect};
+use jgenesis_native_driver::{
+    HeadlessConfig, LinkCableConfig, NativeEmulator, NativeTickEffect, NetplayConfig,
+};
 use jgenesis_proc_macros::{CustomValueEnum, EnumAll, EnumDisplay};
 use jgenesis_renderer::config::{
     FilterMode, PreprocessShader, PrescaleFactor, Scanlines, VSyncMode, WgpuBackend,
 };
 use nes_config::{NesAspectRatio, NesAudioResampler, NesPalette};
-use smsgg_config::{GgAspectRatio, SmsAspectRatio, SmsGgRegion, SmsModel, Sn76489Version};
+use smsgg_config::{
+    GgAspectRatio, SmsAspectRatio, SmsGgControllerType, SmsGgRegion, SmsModel, Sn76489Version,
+};
 use smsgg_core::SmsGgHardware;
 use snes_config::{AudioInterpolationMode, SnesAspectRatio};
 use std::fmt::Debug;
 use std::fs;
+use std::net::SocketAddr;
 use std::num::{NonZeroU16, NonZeroU32, NonZeroU64};
 use std::path::{Path, PathBuf};
 
@@ -53,6 +60,12 @@ const GBA_OPTIONS_HEADING: &str = "Game Boy Advance Options";
 const VIDEO_OPTIONS_HEADING: &str = "Video Options";
 const AUDIO_OPTIONS_HEADING: &str = "Audio Options";
 const HOTKEY_OPTIONS_HEADING: &str = "Hotkey Options";
+const NETPLAY_OPTIONS_HEADING: &str = "Netplay Options";
+const HEADLESS_OPTIONS_HEADING: &str = "Headless Options";
+const DEBUG_OPTIONS_HEADING: &str = "Debug Options";
+
+const DEFAULT_NETPLAY_PORT: u16 = 7845;
+const DEFAULT_LINK_CABLE_PORT: u16 = 7846;
 
 #[derive(Debug, Parser)]
 struct VersionArgs {
@@ -70,6 +83,10 @@ struct Args {
     #[arg(short = 'f', long)]
     file_path: PathBuf,
 
+    /// IPS, BPS, or UPS patch file to apply to the ROM; if not set, a patch file next to the ROM with the same file name is applied if present
+    #[arg(long, value_name = "FILE")]
+    patch_path: Option<PathBuf>,
+
     /// Override default config file path (jgenesis-config.toml)
     #[arg(long = "config")]
     config_path_override: Option<PathBuf>,
@@ -151,6 +168,14 @@ struct Args {
     #[arg(long, help_heading = SMSGG_OPTIONS_HEADING)]
     smsgg_z80_divider: Option<NonZeroU32>,
 
+    /// P1 Master System controller port device
+    #[arg(long, help_heading = SMSGG_OPTIONS_HEADING)]
+    smsgg_p1_controller_type: Option<SmsGgControllerType>,
+
+    /// P2 Master System controller port device
+    #[arg(long, help_heading = SMSGG_OPTIONS_HEADING)]
+    smsgg_p2_controller_type: Option<SmsGgControllerType>,
+
     /// Boot from the SMS BIOS
     #[arg(long, help_heading = SMSGG_OPTIONS_HEADING)]
     sms_boot_from_bios: Option<bool>,
@@ -244,6 +269,10 @@ struct Args {
     #[arg(long, help_heading = GENESIS_OPTIONS_HEADING)]
     genesis_p2_controller_type: Option<GenesisControllerType>,
 
+    /// Specify a ROM to insert into the Sonic & Knuckles lock-on slot (ignored for other games)
+    #[arg(long, help_heading = GENESIS_OPTIONS_HEADING)]
+    lock_on_rom_path: Option<PathBuf>,
+
     /// Sega CD BIOS path
     #[arg(short = 'b', long, help_heading = SCD_OPTIONS_HEADING)]
     bios_path: Option<PathBuf>,
@@ -317,6 +346,10 @@ struct Args {
     #[arg(long, help_heading = NES_OPTIONS_HEADING)]
     nes_p2_controller_type: Option<NesControllerType>,
 
+    /// NES four player adapter (Four Score / Hori 4 Players Adapter)
+    #[arg(long, help_heading = NES_OPTIONS_HEADING)]
+    nes_four_player_adapter: Option<NesFourPlayerAdapterType>,
+
     /// Top overscan in pixels
     #[arg(long, help_heading = NES_OPTIONS_HEADING)]
     overscan_top: Option<u16>,
@@ -357,6 +390,10 @@ struct Args {
     #[arg(long, help_heading = NES_OPTIONS_HEADING)]
     nes_palette_file: Option<PathBuf>,
 
+    /// Famicom Disk System BIOS ROM path (required for FDS disk images)
+    #[arg(long, help_heading = NES_OPTIONS_HEADING)]
+    fds_bios_path: Option<PathBuf>,
+
     /// SNES aspect ratio
     #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
     snes_aspect_ratio: Option<SnesAspectRatio>,
@@ -377,6 +414,10 @@ struct Args {
     #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
     gsu_overclock_factor: Option<NonZeroU64>,
 
+    /// Player 1 input device
+    #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
+    snes_p1_controller_type: Option<SnesControllerType>,
+
     /// Player 2 input device
     #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
     snes_p2_controller_type: Option<SnesControllerType>,
@@ -409,6 +450,10 @@ struct Args {
     #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
     st018_rom_path: Option<PathBuf>,
 
+    /// Specify Game Boy ROM path to run in the Super Game Boy (required to run the SGB BIOS)
+    #[arg(long, help_heading = SNES_OPTIONS_HEADING)]
+    sgb_cartridge_path: Option<PathBuf>,
+
     /// Force DMG / original Game Boy mode in software with Game Boy Color support
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     force_dmg_mode: Option<bool>,
@@ -421,6 +466,10 @@ struct Args {
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     pretend_to_be_gba: Option<bool>,
 
+    /// Attach a Game Boy Printer to the serial port; printed pages are saved as PNG files next to the save file
+    #[arg(long, help_heading = GB_OPTIONS_HEADING)]
+    gb_printer: Option<bool>,
+
     /// Boot from boot ROM when running in DMG mode
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     dmg_boot_rom: Option<bool>,
@@ -437,6 +486,10 @@ struct Args {
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     cgb_boot_rom_path: Option<PathBuf>,
 
+    /// Game Boy Camera image path; can be an image file or a directory of images to cycle through
+    #[arg(long, help_heading = GB_OPTIONS_HEADING)]
+    gb_camera_image_path: Option<PathBuf>,
+
     /// Aspect ratio
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     gb_aspect_ratio: Option<GbAspectRatio>,
@@ -461,7 +514,7 @@ struct Args {
     #[arg(long, help_heading = GB_OPTIONS_HEADING)]
     gb_audio_60hz_hack: Option<bool>,
 
-    /// Game Boy Advance BIOS ROM path (required for GBA emulation)
+    /// Game Boy Advance BIOS ROM path (uses a built-in BIOS if not set)
     #[arg(long, help_heading = GBA_OPTIONS_HEADING)]
     gba_bios_path: Option<PathBuf>,
 
@@ -553,13 +606,95 @@ struct Args {
     #[arg(long, help_heading = HOTKEY_OPTIONS_HEADING)]
     fast_forward_multiplier: Option<u64>,
 
-    /// Rewind buffer length in seconds
+    /// Rewind buffer memory budget in megabytes
+    #[arg(long, help_heading = HOTKEY_OPTIONS_HEADING)]
+    rewind_buffer_size_mb: Option<u64>,
+
+    /// Record a rewind state every N frames
     #[arg(long, help_heading = HOTKEY_OPTIONS_HEADING)]
-    rewind_buffer_length_seconds: Option<u64>,
+    rewind_frame_interval: Option<u64>,
 
     /// Attempt to load the most recent save state slot during startup
     #[arg(long, help_heading = HOTKEY_OPTIONS_HEADING)]
     load_recent_state_at_launch: Option<bool>,
+
+    /// Host a two-player netplay session as player 1
+    #[arg(long, default_value_t, help_heading = NETPLAY_OPTIONS_HEADING)]
+    netplay_host: bool,
+
+    /// Connect to a netplay host at the given address as player 2, e.g. 127.0.0.1:7845
+    #[arg(long, value_name = "ADDR", conflicts_with = "netplay_host", help_heading = NETPLAY_OPTIONS_HEADING)]
+    netplay_connect: Option<SocketAddr>,
+
+    /// Local UDP port for netplay; defaults to 7845 when hosting and any available port when connecting
+    #[arg(long, value_name = "PORT", help_heading = NETPLAY_OPTIONS_HEADING)]
+    netplay_port: Option<u16>,
+
+    /// Number of frames to delay local inputs during netplay
+    #[arg(long, default_value_t = 2, value_name = "FRAMES", help_heading = NETPLAY_OPTIONS_HEADING)]
+    netplay_input_delay: u8,
+
+    /// Listen for another instance to connect a Game Boy link cable
+    #[arg(long, default_value_t, help_heading = GB_OPTIONS_HEADING)]
+    gb_link_listen: bool,
+
+    /// Connect a Game Boy link cable to a listening instance at the given address, e.g. 127.0.0.1:7846
+    #[arg(long, value_name = "ADDR", conflicts_with = "gb_link_listen", help_heading = GB_OPTIONS_HEADING)]
+    gb_link_connect: Option<SocketAddr>,
+
+    /// Local TCP port to listen on for a Game Boy link cable connection; defaults to 7846
+    #[arg(long, value_name = "PORT", help_heading = GB_OPTIONS_HEADING)]
+    gb_link_port: Option<u16>,
+
+    /// Run for the given number of frames without opening a window or audio device, then write the final frame and audio to the output directory and print hashes of the output
+    #[arg(long, value_name = "FRAMES", conflicts_with_all = ["netplay_host", "netplay_connect"], help_heading = HEADLESS_OPTIONS_HEADING)]
+    headless_frames: Option<NonZeroU64>,
+
+    /// Input script or movie file to play in headless mode
+    #[arg(long, value_name = "FILE", requires = "headless_frames", help_heading = HEADLESS_OPTIONS_HEADING)]
+    headless_input: Option<PathBuf>,
+
+    /// Directory to write headless mode output to
+    #[arg(long, value_name = "DIR", default_value = ".", help_heading = HEADLESS_OPTIONS_HEADING)]
+    headless_output_dir: PathBuf,
+
+    /// Start a GDB remote debugging server on the given localhost port (Genesis, Sega CD, 32X, and GBA only)
+    #[arg(long, value_name = "PORT", conflicts_with = "headless_frames", help_heading = DEBUG_OPTIONS_HEADING)]
+    gdb_port: Option<u16>,
+}
+
+impl Args {
+    fn netplay_config(&self) -> Option<NetplayConfig> {
+        if !self.netplay_host && self.netplay_connect.is_none() {
+            return None;
+        }
+
+        let default_port = if self.netplay_host { DEFAULT_NETPLAY_PORT } else { 0 };
+        Some(NetplayConfig {
+            local_port: self.netplay_port.unwrap_or(default_port),
+            remote_addr: self.netplay_connect,
+            input_delay: self.netplay_input_delay,
+        })
+    }
+
+    fn link_cable_config(&self) -> Option<LinkCableConfig> {
+        if !self.gb_link_listen && self.gb_link_connect.is_none() {
+            return None;
+        }
+
+        Some(LinkCableConfig {
+            local_port: self.gb_link_port.unwrap_or(DEFAULT_LINK_CABLE_PORT),
+            remote_addr: self.gb_link_connect,
+        })
+    }
+
+    fn headless_config(&self) -> Option<HeadlessConfig> {
+        Some(HeadlessConfig {
+            frames: self.headless_frames?,
+            input_path: self.headless_input.clone(),
+            output_dir: self.headless_output_dir.clone(),
+        })
+    }
 }
 
 macro_rules! apply_overrides {
@@ -592,6 +727,7 @@ impl Args {
     fn fix_appimage_relative_paths(mut self) -> Self {
         self.file_path = jgenesis_common::fix_appimage_relative_path(self.file_path);
 
+        fix_optional_relative_path(&mut self.patch_path);
         fix_optional_relative_path(&mut self.config_path_override);
         fix_optional_relative_path(&mut self.custom_save_path);
         fix_optional_relative_path(&mut self.custom_state_path);
@@ -603,15 +739,23 @@ impl Args {
         fix_optional_relative_path(&mut self.st010_rom_path);
         fix_optional_relative_path(&mut self.st011_rom_path);
         fix_optional_relative_path(&mut self.st018_rom_path);
+        fix_optional_relative_path(&mut self.sgb_cartridge_path);
 
+        fix_optional_relative_path(&mut self.lock_on_rom_path);
         fix_optional_relative_path(&mut self.bios_path);
         fix_optional_relative_path(&mut self.sms_bios_path);
         fix_optional_relative_path(&mut self.gg_bios_path);
         fix_optional_relative_path(&mut self.dmg_boot_rom_path);
         fix_optional_relative_path(&mut self.cgb_boot_rom_path);
+        fix_optional_relative_path(&mut self.gb_camera_image_path);
+        fix_optional_relative_path(&mut self.fds_bios_path);
 
         fix_optional_relative_path(&mut self.gba_bios_path);
 
+        fix_optional_relative_path(&mut self.headless_input);
+        self.headless_output_dir =
+            jgenesis_common::fix_appimage_relative_path(self.headless_output_dir);
+
         self
     }
 
@@ -675,6 +819,11 @@ impl Args {
             config.smsgg.forced_region = Some(region);
         }
 
+        apply_overrides!(self, config.input.smsgg, [
+            smsgg_p1_controller_type -> p1_type,
+            smsgg_p2_controller_type -> p2_type,
+        ]);
+
         if let Some(psg_version) = self.psg_version {
             config.smsgg.psg_version = Some(psg_version);
         }
@@ -711,6 +860,8 @@ impl Args {
             config.genesis.forced_region = Some(region);
         }
 
+        apply_path_overrides!(self, config.genesis, [lock_on_rom_path]);
+
         apply_overrides!(self, config.input.genesis, [
             genesis_p1_controller_type -> p1_type,
             genesis_p2_controller_type -> p2_type,
@@ -760,13 +911,20 @@ impl Args {
             overscan_right -> right,
         ]);
 
-        apply_overrides!(self, config.input.nes, [nes_p2_controller_type -> p2_type]);
+        apply_overrides!(self, config.input.nes, [
+            nes_p2_controller_type -> p2_type,
+            nes_four_player_adapter -> four_player_adapter,
+        ]);
 
         if let Some(path) = &self.nes_palette_file {
             config.nes.palette =
                 NesPalette::read_from(path).context("Failed to load NES palette file")?;
         }
 
+        if let Some(path) = &self.fds_bios_path {
+            config.nes.fds_bios_path = Some(path.clone());
+        }
+
         Ok(())
     }
 
@@ -779,9 +937,10 @@ impl Args {
             gsu_overclock_factor,
         ]);
 
-        if let Some(p2_controller_type) = self.snes_p2_controller_type {
-            config.input.snes.p2_type = p2_controller_type;
-        }
+        apply_overrides!(self, config.input.snes, [
+            snes_p1_controller_type -> p1_type,
+            snes_p2_controller_type -> p2_type,
+        ]);
 
         apply_path_overrides!(
             self,
@@ -794,6 +953,7 @@ impl Args {
                 st010_rom_path,
                 st011_rom_path,
                 st018_rom_path,
+                sgb_cartridge_path,
             ]
         );
     }
@@ -803,6 +963,7 @@ impl Args {
             force_dmg_mode,
             force_cgb_mode,
             pretend_to_be_gba,
+            gb_printer -> printer_connected,
             dmg_boot_rom,
             cgb_boot_rom,
             gb_aspect_ratio -> aspect_ratio,
@@ -815,6 +976,10 @@ impl Args {
 
         apply_path_overrides!(self, config.game_boy, [dmg_boot_rom_path, cgb_boot_rom_path]);
 
+        if let Some(gb_camera_image_path) = &self.gb_camera_image_path {
+            config.game_boy.camera_image_path = Some(gb_camera_image_path.clone());
+        }
+
         if self.force_cgb_mode == Some(true) {
             config.game_boy.cgb_boot_rom = true;
         }
@@ -889,7 +1054,7 @@ impl Args {
         apply_overrides!(
             self,
             config.common,
-            [fast_forward_multiplier, rewind_buffer_length_seconds,]
+            [fast_forward_multiplier, rewind_buffer_size_mb, rewind_frame_interval]
         );
 
         if self.load_save_state.is_some() {
@@ -1014,15 +1179,31 @@ fn guess_hardware(args: &Args) -> Hardware {
 
 fn run_smsgg(args: Args, config: AppConfig, hardware: SmsGgHardware) -> anyhow::Result<()> {
     let mut smsgg_config = config.smsgg_config(args.file_path.clone(), Some(hardware));
+    smsgg_config.common.patch_path.clone_from(&args.patch_path);
     smsgg_config.run_without_cartridge = args.sms_no_cartridge;
 
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_smsgg(smsgg_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
     let mut emulator = jgenesis_native_driver::create_smsgg(smsgg_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_genesis(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator =
-        jgenesis_native_driver::create_genesis(config.genesis_config(args.file_path.clone()))?;
+    let mut genesis_config = config.genesis_config(args.file_path.clone());
+    genesis_config.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report =
+            jgenesis_native_driver::run_headless_genesis(genesis_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_genesis(genesis_config)?;
     run_emulator(&mut emulator, &args)
 }
 
@@ -1030,36 +1211,83 @@ fn run_sega_cd(args: Args, config: AppConfig) -> anyhow::Result<()> {
     let mut scd_config = config.sega_cd_config(args.file_path.clone());
     scd_config.run_without_disc = args.scd_no_disc;
 
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_sega_cd(scd_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
     let mut emulator = jgenesis_native_driver::create_sega_cd(scd_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_32x(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator =
-        jgenesis_native_driver::create_32x(config.sega_32x_config(args.file_path.clone()))?;
+    let mut s32x_config = config.sega_32x_config(args.file_path.clone());
+    s32x_config.genesis.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_32x(s32x_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_32x(s32x_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_nes(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator =
-        jgenesis_native_driver::create_nes(config.nes_config(args.file_path.clone()))?;
+    let mut nes_config = config.nes_config(args.file_path.clone());
+    nes_config.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_nes(nes_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_nes(nes_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_snes(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator =
-        jgenesis_native_driver::create_snes(config.snes_config(args.file_path.clone()))?;
+    let mut snes_config = config.snes_config(args.file_path.clone());
+    snes_config.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_snes(snes_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_snes(snes_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_gb(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator = jgenesis_native_driver::create_gb(config.gb_config(args.file_path.clone()))?;
+    let mut gb_config = config.gb_config(args.file_path.clone());
+    gb_config.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_gb(gb_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_gb(gb_config)?;
     run_emulator(&mut emulator, &args)
 }
 
 fn run_gba(args: Args, config: AppConfig) -> anyhow::Result<()> {
-    let mut emulator =
-        jgenesis_native_driver::create_gba(config.gba_config(args.file_path.clone()))?;
+    let mut gba_config = config.gba_config(args.file_path.clone());
+    gba_config.common.patch_path.clone_from(&args.patch_path);
+
+    if let Some(headless_config) = args.headless_config() {
+        let report = jgenesis_native_driver::run_headless_gba(gba_config, &headless_config)?;
+        println!("{report}");
+        return Ok(());
+    }
+
+    let mut emulator = jgenesis_native_driver::create_gba(gba_config)?;
     run_emulator(&mut emulator, &args)
 }
 
@@ -1078,6 +1306,18 @@ where
         }
     }
 
+    if let Some(netplay_config) = args.netplay_config() {
+        emulator.start_netplay(netplay_config)?;
+    }
+
+    if let Some(link_cable_config) = args.link_cable_config() {
+        emulator.start_link_cable(link_cable_config)?;
+    }
+
+    if let Some(gdb_port) = args.gdb_port {
+        emulator.start_gdb_server(gdb_port)?;
+    }
+
     loop {
         match emulator.run()? {
             Some(NativeTickEffect::PowerOff | NativeTickEffect::Exit) => return Ok(()),
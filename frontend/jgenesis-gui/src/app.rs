@@ -1,3 +1,4 @@
+mod cheats;
 mod common;
 mod gb;
 mod gba;
@@ -9,6 +10,7 @@ mod smsgg;
 mod snes;
 mod widgets;
 
+use crate::app::cheats::CheatsState;
 use crate::app::genesis::{GenesisVolumeState, S32XPriorityState};
 use crate::app::input::{GenericButton, InputMappingSet};
 use crate::app::nes::{NesPaletteState, OverscanState};
@@ -29,7 +31,7 @@ use emath::Pos2;
 use jgenesis_native_config::common::{HideMouseCursor, PauseEmulator};
 use jgenesis_native_config::{AppConfig, EguiTheme, ListFilters, RecentOpen};
 use jgenesis_native_driver::extensions::Console;
-use jgenesis_native_driver::{NativeEmulatorError, extensions};
+use jgenesis_native_driver::{MovieStartType, NativeEmulatorError, extensions};
 use jgenesis_renderer::config::Scanlines;
 use rfd::FileDialog;
 use std::collections::{HashMap, HashSet};
@@ -117,7 +119,9 @@ enum OpenWindow {
     GbaAudio,
     GeneralInput,
     SmsGgInput,
+    SmsGgPeripherals,
     GenesisInput,
+    GenesisPeripherals,
     NesInput,
     NesPeripherals,
     SnesInput,
@@ -129,6 +133,7 @@ enum OpenWindow {
     SmsGgOverclock,
     GenesisOverclock,
     SnesOverclock,
+    Cheats,
     About,
 }
 
@@ -153,8 +158,10 @@ struct AppState {
     prescale_factor_raw: u32,
     ff_multiplier_text: String,
     ff_multiplier_invalid: bool,
-    rewind_buffer_len_text: String,
-    rewind_buffer_len_invalid: bool,
+    rewind_buffer_size_text: String,
+    rewind_buffer_size_invalid: bool,
+    rewind_interval_text: String,
+    rewind_interval_invalid: bool,
     audio_buffer_size_text: String,
     audio_buffer_size_invalid: bool,
     audio_hardware_queue_size_text: String,
@@ -166,6 +173,8 @@ struct AppState {
     genesis_volume: GenesisVolumeState,
     s32x_priority: S32XPriorityState,
     overscan: OverscanState,
+    cheats: CheatsState,
+    movie_read_only: bool,
     waiting_for_input: Option<WaitingForInput>,
     rom_list: Arc<Mutex<Vec<RomMetadata>>>,
     filtered_rom_list: Rc<[RomMetadata]>,
@@ -191,8 +200,10 @@ impl AppState {
             prescale_factor_raw: config.common.prescale_factor.get(),
             ff_multiplier_text: config.common.fast_forward_multiplier.to_string(),
             ff_multiplier_invalid: false,
-            rewind_buffer_len_text: config.common.rewind_buffer_length_seconds.to_string(),
-            rewind_buffer_len_invalid: false,
+            rewind_buffer_size_text: config.common.rewind_buffer_size_mb.to_string(),
+            rewind_buffer_size_invalid: false,
+            rewind_interval_text: config.common.rewind_frame_interval.to_string(),
+            rewind_interval_invalid: false,
             audio_buffer_size_text: config.common.audio_buffer_size.to_string(),
             audio_buffer_size_invalid: false,
             audio_hardware_queue_size_text: config.common.audio_hardware_queue_size.to_string(),
@@ -203,6 +214,8 @@ impl AppState {
             genesis_volume: GenesisVolumeState::from_config(config),
             s32x_priority: S32XPriorityState::from_config(&config.sega_32x),
             overscan: config.nes.overscan().into(),
+            cheats: CheatsState::default(),
+            movie_read_only: true,
             display_scanlines_warning: should_display_scanlines_warning(config),
             waiting_for_input: None,
             rom_list: Arc::new(Mutex::new(vec![])),
@@ -682,6 +695,13 @@ impl App {
                     ui.close_kind(UiKind::Menu);
                 }
 
+                if ui.button("Cheats").clicked() {
+                    self.state.open_windows.insert(OpenWindow::Cheats);
+                    ui.close_kind(UiKind::Menu);
+                }
+
+                self.render_movie_menu(ui);
+
                 ui.add_space(15.0);
 
                 let show_soft_reset = !matches!(
@@ -752,6 +772,53 @@ impl App {
         });
     }
 
+    fn render_movie_menu(&mut self, ui: &mut Ui) {
+        let extensions = movie_extensions(self.emu_thread.status());
+        let filter_name = extensions.join("/");
+
+        ui.menu_button("Input Movie", |ui| {
+            for (label, start) in [
+                ("Record from Power-On...", MovieStartType::PowerOn),
+                ("Record from Current State...", MovieStartType::CurrentState),
+            ] {
+                if ui.button(label).clicked() {
+                    if let Some(path) =
+                        FileDialog::new().add_filter(&filter_name, extensions).save_file()
+                    {
+                        self.emu_thread.send(EmuThreadCommand::RecordMovie { path, start });
+                    }
+
+                    ui.close_kind(UiKind::Menu);
+                }
+            }
+
+            if ui.button("Play...").clicked() {
+                if let Some(path) =
+                    FileDialog::new().add_filter(&filter_name, extensions).pick_file()
+                {
+                    let read_only = self.state.movie_read_only;
+                    self.emu_thread.send(EmuThreadCommand::PlayMovie { path, read_only });
+                }
+
+                ui.close_kind(UiKind::Menu);
+            }
+
+            ui.checkbox(&mut self.state.movie_read_only, "Start playback in read-only mode");
+
+            ui.separator();
+
+            if ui.button("Toggle Read-Only").clicked() {
+                self.emu_thread.send(EmuThreadCommand::ToggleMovieReadOnly);
+                ui.close_kind(UiKind::Menu);
+            }
+
+            if ui.button("Stop Movie").clicked() {
+                self.emu_thread.send(EmuThreadCommand::StopMovie);
+                ui.close_kind(UiKind::Menu);
+            }
+        });
+    }
+
     fn render_settings_menu(&mut self, ui: &mut Ui) {
         ui.menu_button("Settings", |ui| {
             for (label, window) in [
@@ -846,15 +913,29 @@ impl App {
 
             ui.separator();
 
-            if ui.button("SMS / Game Gear / SG").clicked() {
-                self.state.open_windows.insert(OpenWindow::SmsGgInput);
-                ui.close_kind(UiKind::Menu);
-            }
+            ui.menu_button("SMS / Game Gear / SG", |ui| {
+                if ui.button("Gamepads").clicked() {
+                    self.state.open_windows.insert(OpenWindow::SmsGgInput);
+                    ui.close_kind(UiKind::Menu);
+                }
 
-            if ui.button("Genesis / Sega CD / 32X").clicked() {
-                self.state.open_windows.insert(OpenWindow::GenesisInput);
-                ui.close_kind(UiKind::Menu);
-            }
+                if ui.button("Peripherals").clicked() {
+                    self.state.open_windows.insert(OpenWindow::SmsGgPeripherals);
+                    ui.close_kind(UiKind::Menu);
+                }
+            });
+
+            ui.menu_button("Genesis / Sega CD / 32X", |ui| {
+                if ui.button("Gamepads").clicked() {
+                    self.state.open_windows.insert(OpenWindow::GenesisInput);
+                    ui.close_kind(UiKind::Menu);
+                }
+
+                if ui.button("Peripherals").clicked() {
+                    self.state.open_windows.insert(OpenWindow::GenesisPeripherals);
+                    ui.close_kind(UiKind::Menu);
+                }
+            });
 
             ui.menu_button("NES", |ui| {
                 if ui.button("Gamepads").clicked() {
@@ -1092,7 +1173,9 @@ impl App {
                 OpenWindow::GbaAudio => self.render_gba_audio_settings(ctx),
                 OpenWindow::GeneralInput => self.render_general_input_settings(ctx),
                 OpenWindow::SmsGgInput => self.render_smsgg_input_settings(ctx),
+                OpenWindow::SmsGgPeripherals => self.render_smsgg_peripheral_settings(ctx),
                 OpenWindow::GenesisInput => self.render_genesis_input_settings(ctx),
+                OpenWindow::GenesisPeripherals => self.render_genesis_peripheral_settings(ctx),
                 OpenWindow::NesInput => self.render_nes_input_settings(ctx),
                 OpenWindow::NesPeripherals => self.render_nes_peripheral_settings(ctx),
                 OpenWindow::SnesInput => self.render_snes_input_settings(ctx),
@@ -1104,6 +1187,7 @@ impl App {
                 OpenWindow::SmsGgOverclock => self.render_smsgg_overclock_settings(ctx),
                 OpenWindow::GenesisOverclock => self.render_genesis_overclock_settings(ctx),
                 OpenWindow::SnesOverclock => self.render_snes_overclock_settings(ctx),
+                OpenWindow::Cheats => self.render_cheats_window(ctx),
                 OpenWindow::About => self.render_about(ctx),
             }
         }
@@ -1151,7 +1235,6 @@ impl App {
                         HandledError::No => Self::render_generic_error_window(ctx, err, &mut open),
                     }
                 }
-                NativeEmulatorError::GbaNoBios => self.render_gba_bios_error(ctx, &mut open),
                 _ => Self::render_generic_error_window(ctx, err, &mut open),
             };
 
@@ -1366,6 +1449,17 @@ fn should_reload_config(prev_config: &AppConfig, new_config: &AppConfig) -> bool
     prev_no_ui_settings != new_no_ui_settings
 }
 
+// jgenesis movies plus the external format supported by the running system, if any
+fn movie_extensions(status: EmuThreadStatus) -> &'static [&'static str] {
+    match status {
+        EmuThreadStatus::RunningNes => &["jmv", "fm2"],
+        EmuThreadStatus::RunningGenesis
+        | EmuThreadStatus::RunningSegaCd
+        | EmuThreadStatus::Running32X => &["jmv", "gmv"],
+        _ => &["jmv"],
+    }
+}
+
 fn format_time_nanos(time_nanos: u128) -> Option<String> {
     let utc_date_time = OffsetDateTime::from_unix_timestamp_nanos(time_nanos as i128)
         .unwrap_or(OffsetDateTime::UNIX_EPOCH);
@@ -0,0 +1,136 @@
+use crate::app::{App, OpenWindow};
+use crate::emuthread::EmuThreadStatus;
+use egui::{Color32, Context, Grid, TextEdit, Ui, Window};
+use jgenesis_common::cheats::{self, CheatSystem};
+use jgenesis_native_config::cheats::{CheatConfig, CheatsAppConfig};
+
+#[derive(Debug, Clone, Default)]
+pub struct CheatsState {
+    new_code: String,
+    new_description: String,
+    error: Option<String>,
+}
+
+fn cheat_system(status: EmuThreadStatus) -> Option<CheatSystem> {
+    match status {
+        EmuThreadStatus::RunningSmsGg => Some(CheatSystem::SmsGg),
+        EmuThreadStatus::RunningGenesis
+        | EmuThreadStatus::RunningSegaCd
+        | EmuThreadStatus::Running32X => Some(CheatSystem::Genesis),
+        EmuThreadStatus::RunningNes => Some(CheatSystem::Nes),
+        EmuThreadStatus::RunningSnes => Some(CheatSystem::Snes),
+        EmuThreadStatus::RunningGameBoy => Some(CheatSystem::GameBoy),
+        EmuThreadStatus::RunningGba => Some(CheatSystem::GameBoyAdvance),
+        EmuThreadStatus::Idle
+        | EmuThreadStatus::WaitingForFirstCommand
+        | EmuThreadStatus::Terminated => None,
+    }
+}
+
+fn format_hint(system: CheatSystem) -> &'static str {
+    match system {
+        CheatSystem::Nes => "Game Genie (6 or 8 letters) or raw AAAA:VV / AAAA?CC:VV",
+        CheatSystem::Snes => "Game Genie (XXXX-XXXX), Pro Action Replay (AAAAAAVV), or AAAAAA:VV",
+        CheatSystem::Genesis => "Game Genie (XXXX-XXXX) or raw AAAAAA:VV / AAAAAA:VVVV",
+        CheatSystem::SmsGg => "Game Genie (XXX-XXX-XXX), Pro Action Replay (00AA-AAVV), or AAAA:VV",
+        CheatSystem::GameBoy => "Game Genie (XXX-XXX-XXX), GameShark (01VVLLHH), or AAAA:VV",
+        CheatSystem::GameBoyAdvance => {
+            "GameShark / Action Replay v1/v2 (XXXXXXXX YYYYYYYY) or AAAAAAAA:VV"
+        }
+    }
+}
+
+impl App {
+    pub(super) fn render_cheats_window(&mut self, ctx: &Context) {
+        let mut open = true;
+        Window::new("Cheats")
+            .default_width(450.0)
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| self.render_cheats_contents(ui));
+        if !open {
+            self.state.open_windows.remove(&OpenWindow::Cheats);
+        }
+    }
+
+    fn render_cheats_contents(&mut self, ui: &mut Ui) {
+        let Some(system) = cheat_system(self.emu_thread.status()) else {
+            ui.label("Start a game to manage its cheats");
+            return;
+        };
+
+        let rom_path = self.state.current_file_path.clone();
+        let game_key = CheatsAppConfig::game_key(&rom_path);
+        if game_key.is_empty() {
+            ui.label("Cheats are not available when running without a game");
+            return;
+        }
+
+        ui.heading(&game_key);
+
+        ui.add_space(10.0);
+
+        if let Some(game_cheats) = self.config.cheats.games.get_mut(&game_key) {
+            let mut remove_idx = None;
+
+            Grid::new("cheats_grid").striped(true).show(ui, |ui| {
+                for (i, cheat) in game_cheats.iter_mut().enumerate() {
+                    ui.checkbox(&mut cheat.enabled, "");
+                    ui.monospace(&cheat.code);
+                    ui.label(&cheat.description);
+
+                    if ui.button("Remove").clicked() {
+                        remove_idx = Some(i);
+                    }
+
+                    ui.end_row();
+                }
+            });
+
+            if let Some(remove_idx) = remove_idx {
+                game_cheats.remove(remove_idx);
+                self.config.cheats.remove_empty();
+            }
+
+            ui.add_space(10.0);
+        }
+
+        ui.group(|ui| {
+            Grid::new("cheats_add_grid").show(ui, |ui| {
+                ui.label("Code");
+                ui.add(TextEdit::singleline(&mut self.state.cheats.new_code).desired_width(250.0));
+                ui.end_row();
+
+                ui.label("Description");
+                ui.add(
+                    TextEdit::singleline(&mut self.state.cheats.new_description)
+                        .desired_width(250.0),
+                );
+                ui.end_row();
+            });
+
+            ui.label(format_hint(system));
+
+            if ui.button("Add").clicked() {
+                let code = self.state.cheats.new_code.trim().to_string();
+                match cheats::decode(system, &code) {
+                    Ok(_) => {
+                        self.config.cheats.game_cheats_mut(&rom_path).push(CheatConfig {
+                            code,
+                            description: self.state.cheats.new_description.trim().into(),
+                            enabled: true,
+                        });
+                        self.state.cheats = CheatsState::default();
+                    }
+                    Err(err) => {
+                        self.state.cheats.error = Some(format!("Invalid code: {err}"));
+                    }
+                }
+            }
+
+            if let Some(error) = &self.state.cheats.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    }
+}
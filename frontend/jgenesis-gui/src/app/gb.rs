@@ -30,6 +30,16 @@ impl App {
                     self.state.help_text.insert(WINDOW, helptext::PRETEND_GBA_MODE);
                 }
 
+                let rect = ui
+                    .checkbox(
+                        &mut self.config.game_boy.printer_connected,
+                        "Game Boy Printer connected",
+                    )
+                    .interact_rect;
+                if ui.rect_contains_pointer(rect) {
+                    self.state.help_text.insert(WINDOW, helptext::GB_PRINTER);
+                }
+
                 ui.add_space(5.0);
 
                 let running_gb = self.emu_thread.status() != EmuThreadStatus::RunningGameBoy;
@@ -104,6 +114,19 @@ impl App {
                     if ui.rect_contains_pointer(rect) {
                         self.state.help_text.insert(WINDOW, helptext::BOOT_ROM);
                     }
+
+                    ui.add_space(5.0);
+
+                    let rect = ui
+                        .add(OptionalPathSelector::new(
+                            "Game Boy Camera image",
+                            &mut self.config.game_boy.camera_image_path,
+                            pick_camera_image_path,
+                        ))
+                        .interact_rect;
+                    if ui.rect_contains_pointer(rect) {
+                        self.state.help_text.insert(WINDOW, helptext::GB_CAMERA);
+                    }
                 });
 
                 self.render_help_text(ui, WINDOW);
@@ -369,3 +392,7 @@ fn pick_boot_rom_path(default_extension: &str) -> Option<PathBuf> {
         .add_filter("All Files", &["*"])
         .pick_file()
 }
+
+fn pick_camera_image_path() -> Option<PathBuf> {
+    FileDialog::new().add_filter("PNG", &["png"]).add_filter("All Files", &["*"]).pick_file()
+}
@@ -24,6 +24,24 @@ pub const PRETEND_GBA_MODE: HelpText = HelpText {
     ],
 };
 
+pub const GB_PRINTER: HelpText = HelpText {
+    heading: "Game Boy Printer",
+    text: &[
+        "Attach a Game Boy Printer to the serial port. Games that support printing (e.g. Pokémon, Link's Awakening DX, and Game Boy Camera) will print to it.",
+        "Each printed page is saved as a PNG file in the same directory as the save file.",
+        "The link cable cannot be used while the printer is connected.",
+    ],
+};
+
+pub const GB_CAMERA: HelpText = HelpText {
+    heading: "Game Boy Camera Image",
+    text: &[
+        "Image shown to the Game Boy Camera sensor. The image is converted to grayscale and resized to the sensor's 128x112 resolution.",
+        "The path can also be set to a directory of images in the config file or on the command line, in which case the camera cycles through them in file name order, one image per second.",
+        "Takes effect the next time a game is launched.",
+    ],
+};
+
 pub const BOOT_ROM: HelpText = HelpText {
     heading: "Boot ROM",
     text: &[
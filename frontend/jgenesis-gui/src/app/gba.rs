@@ -1,9 +1,8 @@
-use crate::app::widgets::{BiosErrorStrings, OptionalPathSelector, RenderErrorEffect};
-use crate::app::{App, OpenWindow, widgets};
+use crate::app::widgets::OptionalPathSelector;
+use crate::app::{App, OpenWindow};
 use egui::{Context, Slider, Window};
 use gba_config::{GbaAspectRatio, GbaAudioInterpolation, GbaColorCorrection, GbaSaveMemory};
 use jgenesis_native_config::gba::GameBoyAdvanceAppConfig;
-use jgenesis_native_driver::extensions::Console;
 use rfd::FileDialog;
 use std::path::PathBuf;
 
@@ -258,25 +257,6 @@ impl App {
             self.state.open_windows.remove(&WINDOW);
         }
     }
-
-    pub(super) fn render_gba_bios_error(
-        &mut self,
-        ctx: &Context,
-        open: &mut bool,
-    ) -> RenderErrorEffect {
-        widgets::render_bios_error(
-            ctx,
-            open,
-            BiosErrorStrings {
-                title: "Missing GBA BIOS",
-                text: "No GBA BIOS path is configured. A BIOS ROM is required for GBA emulation.",
-                button_label: "Configure GBA BIOS path",
-            },
-            &mut self.config.game_boy_advance.bios_path,
-            Console::GameBoyAdvance,
-            pick_bios_path,
-        )
-    }
 }
 
 fn pick_bios_path() -> Option<PathBuf> {
@@ -2,7 +2,10 @@ use crate::app::HelpText;
 
 pub const BIOS_PATH: HelpText = HelpText {
     heading: "BIOS Path",
-    text: &["Path to a 16 KB Game Boy Advance BIOS ROM.", "This is required for GBA emulation."],
+    text: &[
+        "Path to a 16 KB Game Boy Advance BIOS ROM.",
+        "If not set, a built-in BIOS replacement is used instead. Most games work with the built-in BIOS, but it does not include the BIOS intro animation or the BIOS sound driver functions.",
+    ],
 };
 
 pub const SKIP_BIOS_ANIMATION: HelpText = HelpText {
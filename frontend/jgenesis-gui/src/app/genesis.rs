@@ -152,6 +152,26 @@ impl App {
 
             ui.add_space(5.0);
 
+            let rect = ui
+                .group(|ui| {
+                    ui.add_enabled_ui(!running_genesis, |ui| {
+                        ui.label("Sonic & Knuckles lock-on cartridge");
+
+                        ui.add(OptionalPathSelector::new(
+                            "ROM",
+                            &mut self.config.genesis.lock_on_rom_path,
+                            pick_lock_on_rom_path,
+                        ));
+                    });
+                })
+                .response
+                .interact_rect;
+            if ui.rect_contains_pointer(rect) {
+                self.state.help_text.insert(WINDOW, helptext::LOCK_ON_ROM_PATH);
+            }
+
+            ui.add_space(5.0);
+
             let rect = ui
                 .group(|ui| {
                     ui.label("Sega CD BIOS paths");
@@ -1075,6 +1095,13 @@ fn render_volume_adjustment(
     });
 }
 
+fn pick_lock_on_rom_path() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter("md", &["md", "gen", "bin", "smd"])
+        .add_filter("All Types", &["*"])
+        .pick_file()
+}
+
 fn pick_scd_bios_path() -> Option<PathBuf> {
     FileDialog::new().add_filter("bin", &["bin"]).add_filter("All Types", &["*"]).pick_file()
 }
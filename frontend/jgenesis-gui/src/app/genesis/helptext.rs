@@ -16,6 +16,14 @@ pub const REGION: HelpText = HelpText {
     ],
 };
 
+pub const LOCK_ON_ROM_PATH: HelpText = HelpText {
+    heading: "Sonic & Knuckles Lock-On Cartridge",
+    text: &[
+        "Optional ROM to insert into the Sonic & Knuckles lock-on slot, e.g. Sonic 2 or Sonic 3. This is ignored for games other than Sonic & Knuckles.",
+        "Knuckles in Sonic 2 requires a Sonic & Knuckles ROM image that includes the 256KB Sonic 2 patch ROM.",
+    ],
+};
+
 pub const SCD_BIOS_PATH: HelpText = HelpText {
     heading: "Sega CD BIOS Paths",
     text: &[
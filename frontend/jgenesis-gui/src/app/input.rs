@@ -9,14 +9,16 @@ use jgenesis_common::input::Player;
 use jgenesis_native_config::input::InputAppConfig;
 use jgenesis_native_config::input::mappings::{
     GameBoyInputMapping, GbaInputMapping, GbaJoypadMapping, GbaSolarMapping,
-    GenesisControllerMapping, GenesisInputMapping, HotkeyMapping, NesControllerMapping,
-    NesControllerType, NesInputMapping, NesZapperMapping, SmsGgControllerMapping,
-    SmsGgInputMapping, SnesControllerMapping, SnesControllerType, SnesInputMapping,
-    SnesSuperScopeMapping,
+    GenesisControllerMapping, GenesisInputMapping, GenesisLightGunMapping, GenesisMouseMapping,
+    HotkeyMapping, NesControllerMapping, NesControllerType, NesFourPlayerAdapterType,
+    NesInputMapping, NesPowerPadMapping, NesVausMapping, NesZapperMapping, SmsGgControllerMapping,
+    SmsGgInputMapping, SmsGgLightPhaserMapping, SmsGgPaddleMapping, SmsGgSportsPadMapping,
+    SnesControllerMapping, SnesControllerType, SnesInputMapping, SnesJustifierMapping,
+    SnesMouseMapping, SnesSuperScopeMapping,
 };
 use jgenesis_native_config::input::{GenericInput, Hotkey};
 use nes_config::NesButton;
-use smsgg_config::SmsGgButton;
+use smsgg_config::{SmsGgButton, SmsGgControllerType};
 use snes_config::SnesButton;
 use std::mem;
 use std::sync::LazyLock;
@@ -186,6 +188,10 @@ fn smsgg_label(button: SmsGgButton) -> &'static str {
         Button1 => "Button 1:",
         Button2 => "Button 2:",
         Pause => "Start/Pause:",
+        LightPhaserTrigger => "Trigger:",
+        PaddleButton => "Button:",
+        SportsPadButton1 => "Button 1:",
+        SportsPadButton2 => "Button 2:",
     }
 }
 
@@ -205,6 +211,15 @@ fn genesis_label(button: GenesisButton) -> &'static str {
         Z => "Z:",
         Start => "Start:",
         Mode => "Mode:",
+        MouseLeft => "Left button:",
+        MouseRight => "Right button:",
+        MouseMiddle => "Middle button:",
+        MouseStart => "Start:",
+        LightGunTrigger => "Trigger:",
+        LightGunA => "A (Menacer only):",
+        LightGunB => "B (Menacer only):",
+        LightGunStart => "Start:",
+        LightGunForceOffscreen => "Force offscreen (Hold):",
     }
 }
 
@@ -222,6 +237,19 @@ fn nes_label(button: NesButton) -> &'static str {
         Select => "Select:",
         ZapperFire => "Fire:",
         ZapperForceOffscreen => "Force offscreen (Hold):",
+        VausButton => "Button:",
+        PowerPad1 => "Button 1:",
+        PowerPad2 => "Button 2:",
+        PowerPad3 => "Button 3:",
+        PowerPad4 => "Button 4:",
+        PowerPad5 => "Button 5:",
+        PowerPad6 => "Button 6:",
+        PowerPad7 => "Button 7:",
+        PowerPad8 => "Button 8:",
+        PowerPad9 => "Button 9:",
+        PowerPad10 => "Button 10:",
+        PowerPad11 => "Button 11:",
+        PowerPad12 => "Button 12:",
     }
 }
 
@@ -245,6 +273,10 @@ fn snes_label(button: SnesButton) -> &'static str {
         SuperScopeCursor => "Cursor:",
         SuperScopePause => "Pause:",
         SuperScopeTurboToggle => "Turbo (Toggle):",
+        MouseLeft => "Left Button:",
+        MouseRight => "Right Button:",
+        JustifierTrigger => "Trigger:",
+        JustifierStart => "Start:",
     }
 }
 
@@ -260,6 +292,10 @@ fn gb_label(button: GameBoyButton) -> &'static str {
         B => "B:",
         Start => "Start:",
         Select => "Select:",
+        TiltUp => "Tilt Up:",
+        TiltLeft => "Tilt Left:",
+        TiltRight => "Tilt Right:",
+        TiltDown => "Tilt Down:",
     }
 }
 
@@ -303,6 +339,8 @@ fn hotkey_label(hotkey: Hotkey) -> &'static str {
         Rewind => "Rewind:",
         ToggleOverclocking => "Toggle overclocking enabled:",
         OpenDebugger => "Open memory viewer:",
+        ChangeDiskSide => "Change FDS disk side:",
+        ToggleMovieReadOnly => "Toggle movie read-only:",
         SaveStateSlot0 => "Save state to slot 0:",
         SaveStateSlot1 => "Save state to slot 1:",
         SaveStateSlot2 => "Save state to slot 2:",
@@ -335,8 +373,13 @@ fn access_smsgg_value(
 ) -> &mut Option<Vec<GenericInput>> {
     let mapping_config = mapping.smsgg(config);
 
-    if button == SmsGgButton::Pause {
-        return &mut mapping_config.pause;
+    match button {
+        SmsGgButton::Pause => return &mut mapping_config.pause,
+        SmsGgButton::LightPhaserTrigger => return &mut mapping_config.light_phaser.trigger,
+        SmsGgButton::PaddleButton => return &mut mapping_config.paddle.button,
+        SmsGgButton::SportsPadButton1 => return &mut mapping_config.sports_pad.button_1,
+        SmsGgButton::SportsPadButton2 => return &mut mapping_config.sports_pad.button_2,
+        _ => {}
     }
 
     let player_config = match (player, turbo) {
@@ -344,6 +387,9 @@ fn access_smsgg_value(
         (Player::One, true) => &mut mapping_config.p1_turbo,
         (Player::Two, false) => &mut mapping_config.p2,
         (Player::Two, true) => &mut mapping_config.p2_turbo,
+        (Player::Three | Player::Four | Player::Five, _) => {
+            unreachable!("SMS/GG only supports 2 players")
+        }
     };
 
     match button {
@@ -353,7 +399,13 @@ fn access_smsgg_value(
         SmsGgButton::Down => &mut player_config.down,
         SmsGgButton::Button1 => &mut player_config.button1,
         SmsGgButton::Button2 => &mut player_config.button2,
-        SmsGgButton::Pause => unreachable!("early return for Pause"),
+        SmsGgButton::Pause
+        | SmsGgButton::LightPhaserTrigger
+        | SmsGgButton::PaddleButton
+        | SmsGgButton::SportsPadButton1
+        | SmsGgButton::SportsPadButton2 => {
+            unreachable!("early return for Pause and peripheral buttons")
+        }
     }
 }
 
@@ -366,11 +418,31 @@ fn access_genesis_value(
 ) -> &mut Option<Vec<GenericInput>> {
     let mapping_config = mapping.genesis(config);
 
+    match button {
+        GenesisButton::MouseLeft => return &mut mapping_config.mouse.left,
+        GenesisButton::MouseRight => return &mut mapping_config.mouse.right,
+        GenesisButton::MouseMiddle => return &mut mapping_config.mouse.middle,
+        GenesisButton::MouseStart => return &mut mapping_config.mouse.start,
+        GenesisButton::LightGunTrigger => return &mut mapping_config.light_gun.trigger,
+        GenesisButton::LightGunA => return &mut mapping_config.light_gun.a,
+        GenesisButton::LightGunB => return &mut mapping_config.light_gun.b,
+        GenesisButton::LightGunStart => return &mut mapping_config.light_gun.start,
+        GenesisButton::LightGunForceOffscreen => {
+            return &mut mapping_config.light_gun.force_offscreen;
+        }
+        _ => {}
+    }
+
     let player_config = match (player, turbo) {
         (Player::One, false) => &mut mapping_config.p1,
         (Player::One, true) => &mut mapping_config.p1_turbo,
         (Player::Two, false) => &mut mapping_config.p2,
         (Player::Two, true) => &mut mapping_config.p2_turbo,
+        (Player::Three, false) => &mut mapping_config.p3,
+        (Player::Three, true) => &mut mapping_config.p3_turbo,
+        (Player::Four, false) => &mut mapping_config.p4,
+        (Player::Four, true) => &mut mapping_config.p4_turbo,
+        (Player::Five, _) => unreachable!("Genesis only supports 4 players"),
     };
 
     match button {
@@ -386,6 +458,17 @@ fn access_genesis_value(
         GenesisButton::Z => &mut player_config.z,
         GenesisButton::Start => &mut player_config.start,
         GenesisButton::Mode => &mut player_config.mode,
+        GenesisButton::MouseLeft
+        | GenesisButton::MouseRight
+        | GenesisButton::MouseMiddle
+        | GenesisButton::MouseStart
+        | GenesisButton::LightGunTrigger
+        | GenesisButton::LightGunA
+        | GenesisButton::LightGunB
+        | GenesisButton::LightGunStart
+        | GenesisButton::LightGunForceOffscreen => {
+            unreachable!("early return for mouse and light gun buttons")
+        }
     }
 }
 
@@ -401,6 +484,19 @@ fn access_nes_value(
     match button {
         NesButton::ZapperFire => return &mut mapping_config.zapper.fire,
         NesButton::ZapperForceOffscreen => return &mut mapping_config.zapper.force_offscreen,
+        NesButton::VausButton => return &mut mapping_config.vaus.button,
+        NesButton::PowerPad1 => return &mut mapping_config.power_pad.button_1,
+        NesButton::PowerPad2 => return &mut mapping_config.power_pad.button_2,
+        NesButton::PowerPad3 => return &mut mapping_config.power_pad.button_3,
+        NesButton::PowerPad4 => return &mut mapping_config.power_pad.button_4,
+        NesButton::PowerPad5 => return &mut mapping_config.power_pad.button_5,
+        NesButton::PowerPad6 => return &mut mapping_config.power_pad.button_6,
+        NesButton::PowerPad7 => return &mut mapping_config.power_pad.button_7,
+        NesButton::PowerPad8 => return &mut mapping_config.power_pad.button_8,
+        NesButton::PowerPad9 => return &mut mapping_config.power_pad.button_9,
+        NesButton::PowerPad10 => return &mut mapping_config.power_pad.button_10,
+        NesButton::PowerPad11 => return &mut mapping_config.power_pad.button_11,
+        NesButton::PowerPad12 => return &mut mapping_config.power_pad.button_12,
         _ => {}
     }
 
@@ -409,6 +505,11 @@ fn access_nes_value(
         (Player::One, true) => &mut mapping_config.p1_turbo,
         (Player::Two, false) => &mut mapping_config.p2,
         (Player::Two, true) => &mut mapping_config.p2_turbo,
+        (Player::Three, false) => &mut mapping_config.p3,
+        (Player::Three, true) => &mut mapping_config.p3_turbo,
+        (Player::Four, false) => &mut mapping_config.p4,
+        (Player::Four, true) => &mut mapping_config.p4_turbo,
+        (Player::Five, _) => unreachable!("NES only supports 4 players"),
     };
 
     match button {
@@ -420,9 +521,7 @@ fn access_nes_value(
         NesButton::B => &mut player_config.b,
         NesButton::Start => &mut player_config.start,
         NesButton::Select => &mut player_config.select,
-        NesButton::ZapperFire | NesButton::ZapperForceOffscreen => {
-            unreachable!("early return for Zapper buttons")
-        }
+        _ => unreachable!("early return for peripheral buttons"),
     }
 }
 
@@ -440,6 +539,10 @@ fn access_snes_value(
         SnesButton::SuperScopeCursor => return &mut mapping_config.super_scope.cursor,
         SnesButton::SuperScopePause => return &mut mapping_config.super_scope.pause,
         SnesButton::SuperScopeTurboToggle => return &mut mapping_config.super_scope.turbo_toggle,
+        SnesButton::MouseLeft => return &mut mapping_config.mouse.left,
+        SnesButton::MouseRight => return &mut mapping_config.mouse.right,
+        SnesButton::JustifierTrigger => return &mut mapping_config.justifier.trigger,
+        SnesButton::JustifierStart => return &mut mapping_config.justifier.start,
         _ => {}
     }
 
@@ -448,6 +551,12 @@ fn access_snes_value(
         (Player::One, true) => &mut mapping_config.p1_turbo,
         (Player::Two, false) => &mut mapping_config.p2,
         (Player::Two, true) => &mut mapping_config.p2_turbo,
+        (Player::Three, false) => &mut mapping_config.p3,
+        (Player::Three, true) => &mut mapping_config.p3_turbo,
+        (Player::Four, false) => &mut mapping_config.p4,
+        (Player::Four, true) => &mut mapping_config.p4_turbo,
+        (Player::Five, false) => &mut mapping_config.p5,
+        (Player::Five, true) => &mut mapping_config.p5_turbo,
     };
 
     match button {
@@ -463,10 +572,7 @@ fn access_snes_value(
         SnesButton::R => &mut player_config.r,
         SnesButton::Start => &mut player_config.start,
         SnesButton::Select => &mut player_config.select,
-        SnesButton::SuperScopeFire
-        | SnesButton::SuperScopeCursor
-        | SnesButton::SuperScopePause
-        | SnesButton::SuperScopeTurboToggle => unreachable!("early return for Super Scope buttons"),
+        _ => unreachable!("early return for peripheral buttons"),
     }
 }
 
@@ -487,6 +593,10 @@ fn access_gb_value(
         GameBoyButton::B => &mut mapping_config.b,
         GameBoyButton::Start => &mut mapping_config.start,
         GameBoyButton::Select => &mut mapping_config.select,
+        GameBoyButton::TiltUp => &mut mapping_config.tilt_up,
+        GameBoyButton::TiltLeft => &mut mapping_config.tilt_left,
+        GameBoyButton::TiltRight => &mut mapping_config.tilt_right,
+        GameBoyButton::TiltDown => &mut mapping_config.tilt_down,
     }
 }
 
@@ -541,6 +651,8 @@ fn access_hotkey(
         Rewind => &mut mapping_config.rewind,
         ToggleOverclocking => &mut mapping_config.toggle_overclocking,
         OpenDebugger => &mut mapping_config.open_debugger,
+        ChangeDiskSide => &mut mapping_config.change_disk_side,
+        ToggleMovieReadOnly => &mut mapping_config.toggle_movie_read_only,
         SaveStateSlot0 => &mut mapping_config.save_state_slot_0,
         SaveStateSlot1 => &mut mapping_config.save_state_slot_1,
         SaveStateSlot2 => &mut mapping_config.save_state_slot_2,
@@ -583,7 +695,7 @@ impl App {
             SmsGgButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    (button != SmsGgButton::Pause)
+                    (button != SmsGgButton::Pause && !button.is_peripheral())
                         .then_some(GenericButton::SmsGg(button, Player::One))
                 })
                 .collect()
@@ -592,7 +704,7 @@ impl App {
             SmsGgButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    (button != SmsGgButton::Pause)
+                    (button != SmsGgButton::Pause && !button.is_peripheral())
                         .then_some(GenericButton::SmsGg(button, Player::Two))
                 })
                 .collect()
@@ -655,23 +767,150 @@ impl App {
                     mapping_config.p2_turbo = SmsGgControllerMapping::default();
                 }
             });
+
+            ui.separator();
+
+            let smsgg_config = &mut self.config.input.smsgg;
+            for (label, controller_type_field) in [
+                ("Port 1 device (Master System only)", &mut smsgg_config.p1_type),
+                ("Port 2 device (Master System only)", &mut smsgg_config.p2_type),
+            ] {
+                ui.group(|ui| {
+                    ui.label(label);
+
+                    ui.horizontal(|ui| {
+                        ui.radio_value(
+                            controller_type_field,
+                            SmsGgControllerType::Gamepad,
+                            "Gamepad",
+                        );
+                        ui.radio_value(
+                            controller_type_field,
+                            SmsGgControllerType::LightPhaser,
+                            "Light Phaser",
+                        );
+                        ui.radio_value(
+                            controller_type_field,
+                            SmsGgControllerType::Paddle,
+                            "Paddle Control",
+                        );
+                        ui.radio_value(
+                            controller_type_field,
+                            SmsGgControllerType::SportsPad,
+                            "Sports Pad",
+                        )
+                        .on_hover_text("Only the export Sports Pad protocol is supported");
+                    });
+                });
+            }
         });
         if !open {
             self.state.open_windows.remove(&OpenWindow::SmsGgInput);
         }
     }
 
+    pub(super) fn render_smsgg_peripheral_settings(&mut self, ctx: &Context) {
+        static LIGHT_PHASER_BUTTONS: [GenericButton; 1] =
+            [GenericButton::SmsGg(SmsGgButton::LightPhaserTrigger, Player::One)];
+        static PADDLE_BUTTONS: [GenericButton; 1] =
+            [GenericButton::SmsGg(SmsGgButton::PaddleButton, Player::One)];
+        static SPORTS_PAD_BUTTONS: [GenericButton; 2] = [
+            GenericButton::SmsGg(SmsGgButton::SportsPadButton1, Player::One),
+            GenericButton::SmsGg(SmsGgButton::SportsPadButton2, Player::One),
+        ];
+
+        let mut open = true;
+        Window::new("SMS Peripheral Settings").open(&mut open).show(ctx, |ui| {
+            self.disable_if_waiting_for_input(ui);
+
+            ui.label(
+                "Peripherals are connected by changing the port devices in the Gamepads window.",
+            );
+            ui.label("The Light Phaser and Paddle Control follow the mouse cursor position.");
+
+            ui.separator();
+            let mapping = self.render_mapping_set_selector(OpenWindow::SmsGgPeripherals, ui);
+            ui.separator();
+
+            Grid::new("smsgg_peripherals").spacing([50.0, 5.0]).show(ui, |ui| {
+                ui.heading("Light Phaser");
+                ui.heading("Paddle Control");
+                ui.heading("Sports Pad");
+                ui.end_row();
+
+                self.render_input_buttons(
+                    "smsgg_light_phaser_inputs",
+                    mapping,
+                    &LIGHT_PHASER_BUTTONS,
+                    ui,
+                );
+                self.render_input_buttons("smsgg_paddle_inputs", mapping, &PADDLE_BUTTONS, ui);
+                self.render_input_buttons(
+                    "smsgg_sports_pad_inputs",
+                    mapping,
+                    &SPORTS_PAD_BUTTONS,
+                    ui,
+                );
+                ui.end_row();
+            });
+
+            ui.add_space(15.0);
+
+            let mapping_config = mapping.smsgg(&mut self.config.input);
+            ui.horizontal(|ui| {
+                if ui.button("Restore Defaults").clicked() {
+                    mapping_config.light_phaser = SmsGgLightPhaserMapping::mouse();
+                    mapping_config.paddle = SmsGgPaddleMapping::mouse();
+                    mapping_config.sports_pad = SmsGgSportsPadMapping::mouse();
+                }
+
+                if ui.button("Clear All").clicked() {
+                    mapping_config.light_phaser = SmsGgLightPhaserMapping::default();
+                    mapping_config.paddle = SmsGgPaddleMapping::default();
+                    mapping_config.sports_pad = SmsGgSportsPadMapping::default();
+                }
+            });
+        });
+        if !open {
+            self.state.open_windows.remove(&OpenWindow::SmsGgPeripherals);
+        }
+    }
+
     pub(super) fn render_genesis_input_settings(&mut self, ctx: &Context) {
         static P1_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
             GenesisButton::ALL
                 .into_iter()
-                .map(|button| GenericButton::Genesis(button, Player::One))
+                .filter_map(|button| {
+                    (!button.is_mouse() && !button.is_light_gun())
+                        .then_some(GenericButton::Genesis(button, Player::One))
+                })
                 .collect()
         });
         static P2_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
             GenesisButton::ALL
                 .into_iter()
-                .map(|button| GenericButton::Genesis(button, Player::Two))
+                .filter_map(|button| {
+                    (!button.is_mouse() && !button.is_light_gun())
+                        .then_some(GenericButton::Genesis(button, Player::Two))
+                })
+                .collect()
+        });
+        static P3_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            GenesisButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_mouse() && !button.is_light_gun())
+                        .then_some(GenericButton::Genesis(button, Player::Three))
+                })
+                .collect()
+        });
+        static P4_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            GenesisButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_mouse() && !button.is_light_gun())
+                        .then_some(GenericButton::Genesis(button, Player::Four))
+                })
                 .collect()
         });
 
@@ -690,6 +929,14 @@ impl App {
                 self.render_input_buttons("genesis_p1_input_settings", mapping, &P1_BUTTONS, ui);
                 self.render_input_buttons("genesis_p2_input_settings", mapping, &P2_BUTTONS, ui);
                 ui.end_row();
+
+                ui.heading("Player 3");
+                ui.heading("Player 4");
+                ui.end_row();
+
+                self.render_input_buttons("genesis_p3_input_settings", mapping, &P3_BUTTONS, ui);
+                self.render_input_buttons("genesis_p4_input_settings", mapping, &P4_BUTTONS, ui);
+                ui.end_row();
             });
 
             ui.add_space(15.0);
@@ -720,23 +967,28 @@ impl App {
                     mapping_config.p2 = GenesisControllerMapping::default();
                     mapping_config.p2_turbo = GenesisControllerMapping::default();
                 }
+
+                if ui.button("Clear All P3").clicked() {
+                    mapping_config.p3 = GenesisControllerMapping::default();
+                    mapping_config.p3_turbo = GenesisControllerMapping::default();
+                }
+
+                if ui.button("Clear All P4").clicked() {
+                    mapping_config.p4 = GenesisControllerMapping::default();
+                    mapping_config.p4_turbo = GenesisControllerMapping::default();
+                }
             });
 
             ui.separator();
 
-            for player in [Player::One, Player::Two] {
+            let genesis_config = &mut self.config.input.genesis;
+            for (label, controller_type_field) in [
+                ("Port 1 controller type", &mut genesis_config.p1_type),
+                ("Port 2 controller type", &mut genesis_config.p2_type),
+            ] {
                 ui.group(|ui| {
-                    let label = match player {
-                        Player::One => "Player 1 controller type",
-                        Player::Two => "Player 2 controller type",
-                    };
                     ui.label(label);
 
-                    let controller_type_field = match player {
-                        Player::One => &mut self.config.input.genesis.p1_type,
-                        Player::Two => &mut self.config.input.genesis.p2_type,
-                    };
-
                     ui.horizontal(|ui| {
                         ui.radio_value(
                             controller_type_field,
@@ -748,22 +1000,123 @@ impl App {
                             GenesisControllerType::SixButton,
                             "6-button",
                         );
+                        ui.radio_value(
+                            controller_type_field,
+                            GenesisControllerType::TeamPlayer,
+                            "Team Player",
+                        )
+                        .on_hover_text("Sega multitap with 4 controllers");
+                        ui.radio_value(
+                            controller_type_field,
+                            GenesisControllerType::EaFourWayPlay,
+                            "EA 4-Way Play",
+                        )
+                        .on_hover_text("EA multitap with 4 controllers; occupies both ports");
                         ui.radio_value(controller_type_field, GenesisControllerType::None, "None");
                     });
+
+                    ui.horizontal(|ui| {
+                        ui.radio_value(
+                            controller_type_field,
+                            GenesisControllerType::MegaMouse,
+                            "Mega Mouse",
+                        );
+                        ui.radio_value(
+                            controller_type_field,
+                            GenesisControllerType::Menacer,
+                            "Menacer",
+                        )
+                        .on_hover_text("Sega light gun; usually connected to port 2");
+                        ui.radio_value(
+                            controller_type_field,
+                            GenesisControllerType::Justifier,
+                            "Justifier",
+                        )
+                        .on_hover_text("Konami light gun; usually connected to port 2");
+                    });
                 });
             }
+
+            ui.label(
+                "Players are assigned in port order. A Team Player in port 1 uses players 1-4.",
+            );
         });
         if !open {
             self.state.open_windows.remove(&OpenWindow::GenesisInput);
         }
     }
 
+    pub(super) fn render_genesis_peripheral_settings(&mut self, ctx: &Context) {
+        static MOUSE_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            GenesisButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_mouse().then_some(GenericButton::Genesis(button, Player::One))
+                })
+                .collect()
+        });
+        static LIGHT_GUN_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            GenesisButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_light_gun().then_some(GenericButton::Genesis(button, Player::One))
+                })
+                .collect()
+        });
+
+        let mut open = true;
+        Window::new("Genesis Peripheral Settings").open(&mut open).show(ctx, |ui| {
+            self.disable_if_waiting_for_input(ui);
+
+            ui.label(
+                "Peripherals are connected by changing the port controller types in the Gamepads window.",
+            );
+
+            ui.separator();
+            let mapping = self.render_mapping_set_selector(OpenWindow::GenesisPeripherals, ui);
+            ui.separator();
+
+            Grid::new("genesis_peripherals").spacing([50.0, 5.0]).show(ui, |ui| {
+                ui.heading("Mega Mouse");
+                ui.heading("Menacer / Justifier");
+                ui.end_row();
+
+                self.render_input_buttons("genesis_mouse_inputs", mapping, &MOUSE_BUTTONS, ui);
+                self.render_input_buttons(
+                    "genesis_light_gun_inputs",
+                    mapping,
+                    &LIGHT_GUN_BUTTONS,
+                    ui,
+                );
+                ui.end_row();
+            });
+
+            ui.add_space(15.0);
+
+            let mapping_config = mapping.genesis(&mut self.config.input);
+            ui.horizontal(|ui| {
+                if ui.button("Restore Defaults").clicked() {
+                    mapping_config.mouse = GenesisMouseMapping::mouse();
+                    mapping_config.light_gun = GenesisLightGunMapping::mouse();
+                }
+
+                if ui.button("Clear All").clicked() {
+                    mapping_config.mouse = GenesisMouseMapping::default();
+                    mapping_config.light_gun = GenesisLightGunMapping::default();
+                }
+            });
+        });
+        if !open {
+            self.state.open_windows.remove(&OpenWindow::GenesisPeripherals);
+        }
+    }
+
     pub(super) fn render_nes_input_settings(&mut self, ctx: &Context) {
         static P1_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
             NesButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    (!button.is_zapper()).then_some(GenericButton::Nes(button, Player::One))
+                    (!button.is_peripheral()).then_some(GenericButton::Nes(button, Player::One))
                 })
                 .collect()
         });
@@ -771,7 +1124,23 @@ impl App {
             NesButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    (!button.is_zapper()).then_some(GenericButton::Nes(button, Player::Two))
+                    (!button.is_peripheral()).then_some(GenericButton::Nes(button, Player::Two))
+                })
+                .collect()
+        });
+        static P3_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            NesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_peripheral()).then_some(GenericButton::Nes(button, Player::Three))
+                })
+                .collect()
+        });
+        static P4_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            NesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_peripheral()).then_some(GenericButton::Nes(button, Player::Four))
                 })
                 .collect()
         });
@@ -791,6 +1160,14 @@ impl App {
                 self.render_input_buttons("nes_p1_inputs", mapping, &P1_BUTTONS, ui);
                 self.render_input_buttons("nes_p2_inputs", mapping, &P2_BUTTONS, ui);
                 ui.end_row();
+
+                ui.heading("Player 3");
+                ui.heading("Player 4");
+                ui.end_row();
+
+                self.render_input_buttons("nes_p3_inputs", mapping, &P3_BUTTONS, ui);
+                self.render_input_buttons("nes_p4_inputs", mapping, &P4_BUTTONS, ui);
+                ui.end_row();
             });
 
             ui.add_space(15.0);
@@ -821,6 +1198,40 @@ impl App {
                     mapping_config.p2 = NesControllerMapping::default();
                     mapping_config.p2_turbo = NesControllerMapping::default();
                 }
+
+                if ui.button("Clear All P3").clicked() {
+                    mapping_config.p3 = NesControllerMapping::default();
+                    mapping_config.p3_turbo = NesControllerMapping::default();
+                }
+
+                if ui.button("Clear All P4").clicked() {
+                    mapping_config.p4 = NesControllerMapping::default();
+                    mapping_config.p4_turbo = NesControllerMapping::default();
+                }
+            });
+
+            ui.separator();
+
+            ui.group(|ui| {
+                ui.label("Players 3-4 adapter");
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.config.input.nes.four_player_adapter,
+                        NesFourPlayerAdapterType::None,
+                        "None",
+                    );
+                    ui.radio_value(
+                        &mut self.config.input.nes.four_player_adapter,
+                        NesFourPlayerAdapterType::FourScore,
+                        "NES Four Score",
+                    );
+                    ui.radio_value(
+                        &mut self.config.input.nes.four_player_adapter,
+                        NesFourPlayerAdapterType::Hori,
+                        "Famicom Hori 4 Players Adapter",
+                    );
+                });
             });
         });
         if !open {
@@ -837,6 +1248,22 @@ impl App {
                 })
                 .collect()
         });
+        static VAUS_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            NesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_vaus().then_some(GenericButton::Nes(button, Player::One))
+                })
+                .collect()
+        });
+        static POWER_PAD_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            NesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_power_pad().then_some(GenericButton::Nes(button, Player::One))
+                })
+                .collect()
+        });
 
         let mut open = true;
         Window::new("NES Peripheral Settings").open(&mut open).show(ctx, |ui| {
@@ -856,6 +1283,17 @@ impl App {
                         NesControllerType::Zapper,
                         "Zapper",
                     );
+                    ui.radio_value(
+                        &mut self.config.input.nes.p2_type,
+                        NesControllerType::ArkanoidVaus,
+                        "Arkanoid Vaus",
+                    )
+                    .on_hover_text("Paddle position follows the mouse cursor");
+                    ui.radio_value(
+                        &mut self.config.input.nes.p2_type,
+                        NesControllerType::PowerPad,
+                        "Power Pad",
+                    );
                 });
             });
 
@@ -863,11 +1301,17 @@ impl App {
             let mapping = self.render_mapping_set_selector(OpenWindow::NesPeripherals, ui);
             ui.separator();
 
-            ui.heading("Zapper");
-
-            ui.add_space(5.0);
+            Grid::new("nes_peripherals").spacing([50.0, 5.0]).show(ui, |ui| {
+                ui.heading("Zapper");
+                ui.heading("Arkanoid Vaus");
+                ui.heading("Power Pad");
+                ui.end_row();
 
-            self.render_input_buttons("nes_zapper_inputs", mapping, &ZAPPER_BUTTONS, ui);
+                self.render_input_buttons("nes_zapper_inputs", mapping, &ZAPPER_BUTTONS, ui);
+                self.render_input_buttons("nes_vaus_inputs", mapping, &VAUS_BUTTONS, ui);
+                self.render_input_buttons("nes_power_pad_inputs", mapping, &POWER_PAD_BUTTONS, ui);
+                ui.end_row();
+            });
 
             ui.add_space(15.0);
 
@@ -875,10 +1319,14 @@ impl App {
             ui.horizontal(|ui| {
                 if ui.button("Restore Defaults").clicked() {
                     mapping_config.zapper = NesZapperMapping::mouse();
+                    mapping_config.vaus = NesVausMapping::mouse();
+                    mapping_config.power_pad = NesPowerPadMapping::keyboard();
                 }
 
                 if ui.button("Clear All").clicked() {
                     mapping_config.zapper = NesZapperMapping::default();
+                    mapping_config.vaus = NesVausMapping::default();
+                    mapping_config.power_pad = NesPowerPadMapping::default();
                 }
             });
         });
@@ -892,10 +1340,7 @@ impl App {
             SnesButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    button
-                        .to_super_scope()
-                        .is_none()
-                        .then_some(GenericButton::Snes(button, Player::One))
+                    (!button.is_peripheral()).then_some(GenericButton::Snes(button, Player::One))
                 })
                 .collect()
         });
@@ -903,10 +1348,31 @@ impl App {
             SnesButton::ALL
                 .into_iter()
                 .filter_map(|button| {
-                    button
-                        .to_super_scope()
-                        .is_none()
-                        .then_some(GenericButton::Snes(button, Player::Two))
+                    (!button.is_peripheral()).then_some(GenericButton::Snes(button, Player::Two))
+                })
+                .collect()
+        });
+        static P3_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            SnesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_peripheral()).then_some(GenericButton::Snes(button, Player::Three))
+                })
+                .collect()
+        });
+        static P4_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            SnesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_peripheral()).then_some(GenericButton::Snes(button, Player::Four))
+                })
+                .collect()
+        });
+        static P5_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            SnesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    (!button.is_peripheral()).then_some(GenericButton::Snes(button, Player::Five))
                 })
                 .collect()
         });
@@ -926,8 +1392,20 @@ impl App {
                 self.render_input_buttons("snes_p1_inputs", mapping, &P1_BUTTONS, ui);
                 self.render_input_buttons("snes_p2_inputs", mapping, &P2_BUTTONS, ui);
                 ui.end_row();
+
+                ui.heading("Player 3");
+                ui.heading("Player 4");
+                ui.heading("Player 5");
+                ui.end_row();
+
+                self.render_input_buttons("snes_p3_inputs", mapping, &P3_BUTTONS, ui);
+                self.render_input_buttons("snes_p4_inputs", mapping, &P4_BUTTONS, ui);
+                self.render_input_buttons("snes_p5_inputs", mapping, &P5_BUTTONS, ui);
+                ui.end_row();
             });
 
+            ui.label("Players 3-5 require a Super Multitap in port 2");
+
             ui.add_space(15.0);
 
             let mapping_config = mapping.snes(&mut self.config.input);
@@ -956,6 +1434,21 @@ impl App {
                     mapping_config.p2 = SnesControllerMapping::default();
                     mapping_config.p2_turbo = SnesControllerMapping::default();
                 }
+
+                if ui.button("Clear All P3").clicked() {
+                    mapping_config.p3 = SnesControllerMapping::default();
+                    mapping_config.p3_turbo = SnesControllerMapping::default();
+                }
+
+                if ui.button("Clear All P4").clicked() {
+                    mapping_config.p4 = SnesControllerMapping::default();
+                    mapping_config.p4_turbo = SnesControllerMapping::default();
+                }
+
+                if ui.button("Clear All P5").clicked() {
+                    mapping_config.p5 = SnesControllerMapping::default();
+                    mapping_config.p5_turbo = SnesControllerMapping::default();
+                }
             });
         });
         if !open {
@@ -972,11 +1465,44 @@ impl App {
                 })
                 .collect()
         });
+        static MOUSE_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            SnesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_mouse().then_some(GenericButton::Snes(button, Player::One))
+                })
+                .collect()
+        });
+        static JUSTIFIER_BUTTONS: LazyLock<Vec<GenericButton>> = LazyLock::new(|| {
+            SnesButton::ALL
+                .into_iter()
+                .filter_map(|button| {
+                    button.is_justifier().then_some(GenericButton::Snes(button, Player::One))
+                })
+                .collect()
+        });
 
         let mut open = true;
         Window::new("SNES Peripheral Settings").open(&mut open).show(ctx, |ui| {
             self.disable_if_waiting_for_input(ui);
 
+            ui.group(|ui| {
+                ui.label("Player 1 device");
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.config.input.snes.p1_type,
+                        SnesControllerType::Gamepad,
+                        "Gamepad",
+                    );
+                    ui.radio_value(
+                        &mut self.config.input.snes.p1_type,
+                        SnesControllerType::Mouse,
+                        "SNES Mouse",
+                    );
+                });
+            });
+
             ui.group(|ui| {
                 ui.label("Player 2 device");
 
@@ -991,6 +1517,22 @@ impl App {
                         SnesControllerType::SuperScope,
                         "Super Scope",
                     );
+                    ui.radio_value(
+                        &mut self.config.input.snes.p2_type,
+                        SnesControllerType::Mouse,
+                        "SNES Mouse",
+                    );
+                    ui.radio_value(
+                        &mut self.config.input.snes.p2_type,
+                        SnesControllerType::Justifier,
+                        "Justifier",
+                    );
+                    ui.radio_value(
+                        &mut self.config.input.snes.p2_type,
+                        SnesControllerType::Multitap,
+                        "Super Multitap",
+                    )
+                    .on_hover_text("Players 2-5 are connected to the multitap");
                 });
             });
 
@@ -998,11 +1540,17 @@ impl App {
             let mapping = self.render_mapping_set_selector(OpenWindow::SnesPeripherals, ui);
             ui.separator();
 
-            ui.heading("Super Scope");
-
-            ui.add_space(5.0);
+            Grid::new("snes_peripherals").spacing([50.0, 5.0]).show(ui, |ui| {
+                ui.heading("Super Scope");
+                ui.heading("SNES Mouse");
+                ui.heading("Justifier");
+                ui.end_row();
 
-            self.render_input_buttons("super_scope_inputs", mapping, &SUPER_SCOPE_BUTTONS, ui);
+                self.render_input_buttons("super_scope_inputs", mapping, &SUPER_SCOPE_BUTTONS, ui);
+                self.render_input_buttons("snes_mouse_inputs", mapping, &MOUSE_BUTTONS, ui);
+                self.render_input_buttons("justifier_inputs", mapping, &JUSTIFIER_BUTTONS, ui);
+                ui.end_row();
+            });
 
             ui.add_space(15.0);
 
@@ -1010,10 +1558,14 @@ impl App {
             ui.horizontal(|ui| {
                 if ui.button("Restore Defaults").clicked() {
                     mapping_config.super_scope = SnesSuperScopeMapping::mouse();
+                    mapping_config.mouse = SnesMouseMapping::mouse();
+                    mapping_config.justifier = SnesJustifierMapping::mouse();
                 }
 
                 if ui.button("Clear All").clicked() {
                     mapping_config.super_scope = SnesSuperScopeMapping::default();
+                    mapping_config.mouse = SnesMouseMapping::default();
+                    mapping_config.justifier = SnesJustifierMapping::default();
                 }
             });
         });
@@ -1266,20 +1818,34 @@ impl App {
             ui.horizontal(|ui| {
                 ui.add(
                     NumericTextEdit::new(
-                        &mut self.state.rewind_buffer_len_text,
-                        &mut self.config.common.rewind_buffer_length_seconds,
-                        &mut self.state.rewind_buffer_len_invalid,
+                        &mut self.state.rewind_buffer_size_text,
+                        &mut self.config.common.rewind_buffer_size_mb,
+                        &mut self.state.rewind_buffer_size_invalid,
                     )
                     .desired_width(30.0),
                 );
 
-                ui.label("Rewind buffer length in seconds");
+                ui.label("Rewind buffer size in MB");
             });
-            if self.state.rewind_buffer_len_invalid {
-                ui.colored_label(
-                    Color32::RED,
-                    "Rewind buffer length must be a non-negative integer",
+            if self.state.rewind_buffer_size_invalid {
+                ui.colored_label(Color32::RED, "Rewind buffer size must be a non-negative integer");
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    NumericTextEdit::new(
+                        &mut self.state.rewind_interval_text,
+                        &mut self.config.common.rewind_frame_interval,
+                        &mut self.state.rewind_interval_invalid,
+                    )
+                    .with_validation(|value| value != 0)
+                    .desired_width(30.0),
                 );
+
+                ui.label("Rewind state interval in frames");
+            });
+            if self.state.rewind_interval_invalid {
+                ui.colored_label(Color32::RED, "Rewind state interval must be a positive integer");
             }
 
             ui.checkbox(
@@ -1398,7 +1964,8 @@ impl HotkeyExt for Hotkey {
 
         match self {
             PowerOff | Exit | ToggleFullscreen | SoftReset | HardReset | Pause | StepFrame
-            | FastForward | Rewind | ToggleOverclocking | OpenDebugger => HotkeyCategory::General,
+            | FastForward | Rewind | ToggleOverclocking | OpenDebugger | ChangeDiskSide
+            | ToggleMovieReadOnly => HotkeyCategory::General,
             SaveState | LoadState | NextSaveStateSlot | PrevSaveStateSlot | SaveStateSlot0
             | SaveStateSlot1 | SaveStateSlot2 | SaveStateSlot3 | SaveStateSlot4
             | SaveStateSlot5 | SaveStateSlot6 | SaveStateSlot7 | SaveStateSlot8
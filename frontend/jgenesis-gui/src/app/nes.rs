@@ -1,6 +1,6 @@
 mod helptext;
 
-use crate::app::widgets::NumericTextEdit;
+use crate::app::widgets::{NumericTextEdit, OptionalPathSelector};
 use crate::app::{App, OpenWindow, widgets};
 use crate::emuthread::EmuThreadStatus;
 use eframe::emath::Align;
@@ -17,6 +17,7 @@ use nes_config::palettes::PaletteGenerationArgs;
 use nes_config::{NesAspectRatio, NesAudioResampler, NesPalette, Overscan, PaletteLoadError};
 use rfd::FileDialog;
 use std::mem;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 pub struct OverscanState {
@@ -127,6 +128,19 @@ impl App {
                 self.state.help_text.insert(WINDOW, helptext::DMA_DUMMY_JOY_READ);
             }
 
+            ui.add_space(5.0);
+
+            let rect = ui
+                .add(OptionalPathSelector::new(
+                    "FDS BIOS path",
+                    &mut self.config.nes.fds_bios_path,
+                    pick_fds_bios_path,
+                ))
+                .interact_rect;
+            if ui.rect_contains_pointer(rect) {
+                self.state.help_text.insert(WINDOW, helptext::FDS_BIOS_PATH);
+            }
+
             self.render_help_text(ui, WINDOW);
         });
         if !open {
@@ -571,3 +585,10 @@ pub fn update_palette_textures(ctx: &Context, state: &NesPaletteState, palette:
         },
     );
 }
+
+fn pick_fds_bios_path() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter("rom/bin", &["rom", "bin"])
+        .add_filter("All Files", &["*"])
+        .pick_file()
+}
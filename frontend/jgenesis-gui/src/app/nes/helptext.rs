@@ -24,6 +24,14 @@ pub const DMA_DUMMY_JOY_READ: HelpText = HelpText {
     ],
 };
 
+pub const FDS_BIOS_PATH: HelpText = HelpText {
+    heading: "FDS BIOS Path",
+    text: &[
+        "Path to an 8 KB Famicom Disk System BIOS ROM.",
+        "This is required to load FDS disk images (.fds / .qd), but not for NES cartridge ROMs.",
+    ],
+};
+
 pub const ASPECT_RATIO: HelpText = HelpText {
     heading: "Aspect Ratio",
     text: &[
@@ -112,6 +112,31 @@ impl App {
                 self.state.help_text.insert(WINDOW, helptext::COPROCESSOR_ROM_PATHS);
             }
 
+            let rect = ui
+                .group(|ui| {
+                    ui.label("Super Game Boy");
+                    Grid::new("sgb_path_grid").show(ui, |ui| {
+                        ui.label("  Game Boy cartridge");
+
+                        let path = &mut self.config.snes.sgb_cartridge_path;
+                        let button_label = path
+                            .as_deref()
+                            .map_or_else(|| "<None>".into(), |path| path.display().to_string());
+                        if ui.button(button_label).clicked()
+                            && let Some(new_path) = pick_sgb_cartridge_path()
+                        {
+                            *path = Some(new_path);
+                        }
+
+                        ui.end_row();
+                    });
+                })
+                .response
+                .interact_rect;
+            if ui.rect_contains_pointer(rect) {
+                self.state.help_text.insert(WINDOW, helptext::SGB_CARTRIDGE_PATH);
+            }
+
             self.render_help_text(ui, WINDOW);
         });
         if !open {
@@ -274,6 +299,23 @@ impl App {
         err: &SnesLoadError,
         open: &mut bool,
     ) -> HandledError {
+        if let SnesLoadError::MissingSgbCartridgeRom = err {
+            let render_effect = widgets::render_bios_error(
+                ctx,
+                open,
+                BiosErrorStrings {
+                    title: "Missing Game Boy cartridge",
+                    text: "No Game Boy ROM is configured for the Super Game Boy. This is required to run the Super Game Boy BIOS.",
+                    button_label: "Configure Game Boy ROM path",
+                },
+                &mut self.config.snes.sgb_cartridge_path,
+                Console::Snes,
+                pick_sgb_cartridge_path,
+            );
+
+            return HandledError::Yes(render_effect);
+        }
+
         let coprocessor_rom = match err {
             SnesLoadError::MissingDsp1Rom => CoprocessorRom::Dsp1,
             SnesLoadError::MissingDsp2Rom => CoprocessorRom::Dsp2,
@@ -283,8 +325,10 @@ impl App {
             SnesLoadError::MissingSt011Rom => CoprocessorRom::St011,
             SnesLoadError::MissingSt018Rom => CoprocessorRom::St018,
             SnesLoadError::RomTooSmall { .. }
+            | SnesLoadError::MissingSgbCartridgeRom
             | SnesLoadError::CoprocessorRomLoad { .. }
-            | SnesLoadError::St018RomLoad(..) => {
+            | SnesLoadError::St018RomLoad(..)
+            | SnesLoadError::SgbCartridgeLoad(..) => {
                 return HandledError::No;
             }
         };
@@ -327,3 +371,7 @@ fn render_coprocessor_path_select(label: &str, value: &mut Option<PathBuf>, ui:
 fn pick_coprocessor_rom_path() -> Option<PathBuf> {
     FileDialog::new().add_filter("bin", &["rom", "bin"]).add_filter("All Types", &["*"]).pick_file()
 }
+
+fn pick_sgb_cartridge_path() -> Option<PathBuf> {
+    FileDialog::new().add_filter("gb", &["gb", "gbc"]).add_filter("All Types", &["*"]).pick_file()
+}
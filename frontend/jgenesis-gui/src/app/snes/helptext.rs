@@ -24,6 +24,14 @@ pub const COPROCESSOR_ROM_PATHS: HelpText = HelpText {
     ],
 };
 
+pub const SGB_CARTRIDGE_PATH: HelpText = HelpText {
+    heading: "Super Game Boy",
+    text: &[
+        "To play Game Boy games in Super Game Boy mode, load a Super Game Boy or Super Game Boy 2 BIOS ROM as the SNES game. The Game Boy ROM configured here is inserted into the Super Game Boy.",
+        "Game Boy save files are stored alongside the Super Game Boy BIOS save file.",
+    ],
+};
+
 pub const ASPECT_RATIO: HelpText = HelpText {
     heading: "Aspect Ratio",
     text: &[
@@ -8,9 +8,10 @@ use jgenesis_native_driver::config::AppConfigExt;
 use jgenesis_native_driver::extensions::Console;
 use jgenesis_native_driver::input::Joysticks;
 use jgenesis_native_driver::{
-    Native32XEmulator, NativeEmulatorError, NativeEmulatorResult, NativeGameBoyEmulator,
-    NativeGbaEmulator, NativeGenesisEmulator, NativeNesEmulator, NativeSegaCdEmulator,
-    NativeSmsGgEmulator, NativeSnesEmulator, NativeTickEffect, SaveStateMetadata,
+    MovieStartType, Native32XEmulator, NativeEmulatorError, NativeEmulatorResult,
+    NativeGameBoyEmulator, NativeGbaEmulator, NativeGenesisEmulator, NativeNesEmulator,
+    NativeSegaCdEmulator, NativeSmsGgEmulator, NativeSnesEmulator, NativeTickEffect,
+    SaveStateMetadata,
 };
 use jgenesis_proc_macros::MatchEachVariantMacro;
 use sdl3::EventPump;
@@ -108,6 +109,10 @@ pub enum EmuThreadCommand {
     LoadState { slot: usize },
     SegaCdRemoveDisc,
     SegaCdChangeDisc(PathBuf),
+    RecordMovie { path: PathBuf, start: MovieStartType },
+    PlayMovie { path: PathBuf, read_only: bool },
+    StopMovie,
+    ToggleMovieReadOnly,
 }
 
 pub struct EmuThreadHandle {
@@ -308,7 +313,11 @@ fn thread_run(ctx: EmuThreadContext) {
                 | EmuThreadCommand::SaveState { .. }
                 | EmuThreadCommand::LoadState { .. }
                 | EmuThreadCommand::SegaCdRemoveDisc
-                | EmuThreadCommand::SegaCdChangeDisc(_),
+                | EmuThreadCommand::SegaCdChangeDisc(_)
+                | EmuThreadCommand::RecordMovie { .. }
+                | EmuThreadCommand::PlayMovie { .. }
+                | EmuThreadCommand::StopMovie
+                | EmuThreadCommand::ToggleMovieReadOnly,
             ) => {}
             Err(err) => {
                 log::info!(
@@ -564,6 +573,10 @@ fn handle_command(
         EmuThreadCommand::LoadState { slot } => emulator.load_state(slot),
         EmuThreadCommand::SegaCdRemoveDisc => emulator.remove_disc()?,
         EmuThreadCommand::SegaCdChangeDisc(path) => emulator.change_disc(path)?,
+        EmuThreadCommand::RecordMovie { path, start } => emulator.record_movie(path, start)?,
+        EmuThreadCommand::PlayMovie { path, read_only } => emulator.play_movie(path, read_only)?,
+        EmuThreadCommand::StopMovie => emulator.stop_movie()?,
+        EmuThreadCommand::ToggleMovieReadOnly => emulator.toggle_movie_read_only()?,
         EmuThreadCommand::Run { .. } | EmuThreadCommand::RunBios { .. } => {}
     }
 
@@ -0,0 +1,24 @@
+//! [`AudioOutput`] implementation that buffers samples for `retro_audio_sample_batch`.
+
+use jgenesis_common::frontend::AudioOutput;
+use std::convert::Infallible;
+
+pub const SAMPLE_RATE: u64 = 48000;
+
+/// Interleaved stereo samples, drained once per `retro_run`
+#[derive(Debug, Default)]
+pub struct RetroAudioOutput {
+    pub samples: Vec<i16>,
+}
+
+impl AudioOutput for RetroAudioOutput {
+    type Err = Infallible;
+
+    fn push_sample(&mut self, sample_l: f64, sample_r: f64) -> Result<(), Self::Err> {
+        for sample in [sample_l, sample_r] {
+            self.samples.push((sample.clamp(-1.0, 1.0) * f64::from(i16::MAX)).round() as i16);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,627 @@
+//! Backend selection and per-backend dispatch, modeled after the web frontend's `Emulator` enum.
+
+use crate::ffi;
+use crate::options::{self, OptionVisitor};
+use crate::save::{RetroSaveWriter, bincode_config};
+use bincode::error::{DecodeError, EncodeError};
+use cdrom::reader::CdRom;
+use gb_config::{GameBoyButton, GameBoyInputs};
+use gb_core::api::{BootRoms, GameBoyEmulator, GameBoyLoadError};
+use gba_config::{GbaButton, GbaInputs};
+use gba_core::api::{GameBoyAdvanceEmulator, GbaLoadError};
+use genesis_config::{GenesisButton, GenesisInputs, GenesisRegion};
+use genesis_core::GenesisEmulator;
+use jgenesis_common::cheats::CheatSet;
+use jgenesis_common::frontend::{
+    AudioOutput, ConstantInputPoller, EmulatorTrait, MappableInputs, Renderer, TickEffect,
+};
+use jgenesis_common::input::Player;
+use nes_config::NesButton;
+use nes_core::api::{NesEmulator, NesInitializationError};
+use nes_core::input::NesInputs;
+use s32x_core::api::Sega32XEmulator;
+use segacd_core::CdRomFileFormat;
+use segacd_core::api::{SegaCdEmulator, SegaCdLoadError};
+use smsgg_config::{SmsGgButton, SmsGgInputs};
+use smsgg_core::{SmsGgEmulator, SmsGgHardware};
+use snes_config::SnesButton;
+use snes_core::api::{CoprocessorRomFn, CoprocessorRoms, SnesEmulator, SnesLoadError};
+use snes_core::input::SnesInputs;
+use std::error::Error;
+use std::ffi::{CStr, OsStr, c_uint};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use thiserror::Error;
+
+pub const SUPPORTED_EXTENSIONS: &CStr =
+    c"sg|sms|gg|gen|md|bin|smd|cue|chd|32x|nes|fds|qd|nsf|nsfe|sfc|smc|gb|gbc|gba";
+
+const FDS_BIOS_FILE_NAME: &str = "disksys.rom";
+const GBA_BIOS_FILE_NAME: &str = "gba_bios.bin";
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Unsupported file extension: '{0}'")]
+    UnsupportedExtension(String),
+    #[error("Error reading file '{path}': {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{system} emulation requires a BIOS ROM at '{path}'")]
+    MissingBios { system: &'static str, path: String },
+    #[error("Error loading Sega CD disc: {0}")]
+    SegaCd(#[from] SegaCdLoadError),
+    #[error("Error loading NES ROM: {0}")]
+    Nes(#[from] NesInitializationError),
+    #[error("Error loading SNES ROM: {0}")]
+    Snes(#[from] SnesLoadError),
+    #[error("Error loading Game Boy ROM: {0}")]
+    GameBoy(#[from] GameBoyLoadError),
+    #[error("Error loading GBA ROM: {0}")]
+    Gba(#[from] GbaLoadError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum System {
+    SmsGg(SmsGgHardware),
+    Genesis,
+    SegaCd,
+    Sega32X,
+    Nes,
+    FamicomDisk,
+    Snes,
+    GameBoy,
+    GameBoyAdvance,
+}
+
+impl System {
+    fn from_extension(extension: &str, rom: &[u8]) -> Option<Self> {
+        let system = match extension {
+            "sg" => Self::SmsGg(SmsGgHardware::Sg1000),
+            "sms" => Self::SmsGg(SmsGgHardware::MasterSystem),
+            "gg" => Self::SmsGg(SmsGgHardware::GameGear),
+            "gen" | "md" | "smd" => Self::Genesis,
+            // .bin could be either Genesis or 32X; check for the 32X security program
+            "bin" => {
+                let start = s32x_core::SECURITY_PROGRAM_CARTRIDGE_ADDR;
+                let end = start + s32x_core::SECURITY_PROGRAM_LEN;
+                if rom.get(start..end) == Some(s32x_core::security_program()) {
+                    Self::Sega32X
+                } else {
+                    Self::Genesis
+                }
+            }
+            "cue" | "chd" => Self::SegaCd,
+            "32x" => Self::Sega32X,
+            "nes" | "nsf" | "nsfe" => Self::Nes,
+            "fds" | "qd" => Self::FamicomDisk,
+            "sfc" | "smc" => Self::Snes,
+            "gb" | "gbc" => Self::GameBoy,
+            "gba" => Self::GameBoyAdvance,
+            _ => return None,
+        };
+
+        Some(system)
+    }
+}
+
+/// Everything needed to create (or re-create) an emulator instance for a loaded game.
+pub struct Game {
+    system: System,
+    path: PathBuf,
+    // Empty for Sega CD, which reads from the disc image on demand
+    rom: Vec<u8>,
+    system_dir: PathBuf,
+}
+
+impl Game {
+    /// # Errors
+    ///
+    /// Returns an error if the file extension is not supported or the file cannot be read.
+    pub fn open(path: &Path, system_dir: &Path) -> Result<Self, LoadError> {
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+
+        let rom = match extension.as_str() {
+            "cue" | "chd" => vec![],
+            _ => read_file(path)?,
+        };
+
+        let system = System::from_extension(&extension, &rom)
+            .ok_or(LoadError::UnsupportedExtension(extension))?;
+
+        Ok(Self { system, path: path.to_owned(), rom, system_dir: system_dir.to_owned() })
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if a required BIOS ROM is missing or if the backend fails to load the game.
+    pub fn create_emulator(
+        &self,
+        options: &mut impl OptionVisitor,
+        save_writer: &mut RetroSaveWriter,
+    ) -> Result<Emulator, LoadError> {
+        let rom = self.rom.clone();
+
+        let emulator = match self.system {
+            System::SmsGg(hardware) => {
+                let config = options::smsgg_config(options);
+                let emulator =
+                    SmsGgEmulator::create(Some(rom), None, hardware, config, save_writer);
+                Emulator::SmsGg(emulator, SmsGgInputs::default())
+            }
+            System::Genesis => {
+                let config = options::genesis_config(options);
+                let emulator = GenesisEmulator::create(rom, None, config, save_writer);
+                Emulator::Genesis(emulator, GenesisInputs::default())
+            }
+            System::SegaCd => {
+                let config = options::sega_cd_config(options);
+                let format = CdRomFileFormat::from_file_path(&self.path).unwrap_or_else(|| {
+                    log::warn!(
+                        "Unrecognized CD-ROM file extension, behaving as if this is a CUE file: {}",
+                        self.path.display()
+                    );
+                    CdRomFileFormat::CueBin
+                });
+
+                let region = config.genesis.forced_region.unwrap_or_else(|| {
+                    CdRom::open(&self.path, format)
+                        .ok()
+                        .and_then(|mut disc| segacd_core::parse_disc_region(&mut disc).ok())
+                        .unwrap_or_else(|| {
+                            log::error!(
+                                "Unable to determine disc region for purposes of selecting BIOS; defaulting to US"
+                            );
+                            GenesisRegion::Americas
+                        })
+                });
+                let bios = self.read_bios("Sega CD", sega_cd_bios_file_name(region))?;
+
+                let emulator =
+                    SegaCdEmulator::create(bios, &self.path, format, false, config, save_writer)?;
+                Emulator::SegaCd(emulator, GenesisInputs::default())
+            }
+            System::Sega32X => {
+                let config = options::sega_32x_config(options);
+                let emulator = Sega32XEmulator::create(rom, config, save_writer);
+                Emulator::Sega32X(emulator, GenesisInputs::default())
+            }
+            System::Nes => {
+                let config = options::nes_config(options);
+                let emulator = NesEmulator::create(rom, config, save_writer)?;
+                Emulator::Nes(emulator, NesInputs::default())
+            }
+            System::FamicomDisk => {
+                let config = options::nes_config(options);
+                let bios = self.read_bios("Famicom Disk System", FDS_BIOS_FILE_NAME)?;
+                let emulator = NesEmulator::create_fds(rom, bios, config, save_writer)?;
+                Emulator::Nes(emulator, NesInputs::default())
+            }
+            System::Snes => {
+                let config = options::snes_config(options);
+                let emulator =
+                    SnesEmulator::create(rom, config, self.coprocessor_roms(), save_writer)?;
+                Emulator::Snes(emulator, SnesInputs::default())
+            }
+            System::GameBoy => {
+                let config = options::gb_config(options);
+                let boot_roms = BootRoms { dmg: None, cgb: None };
+                let emulator = GameBoyEmulator::create(rom, boot_roms, config, save_writer)?;
+                Emulator::GameBoy(emulator, GameBoyInputs::default())
+            }
+            System::GameBoyAdvance => {
+                let config = options::gba_config(options);
+
+                // Uses the built-in BIOS if there is no BIOS ROM in the system directory
+                let bios_path = self.system_dir.join(GBA_BIOS_FILE_NAME);
+                let bios = bios_path.is_file().then(|| read_file(&bios_path)).transpose()?;
+
+                let emulator = GameBoyAdvanceEmulator::create(rom, bios, config, save_writer)?;
+                Emulator::Gba(emulator, GbaInputs::default())
+            }
+        };
+
+        Ok(emulator)
+    }
+
+    fn read_bios(&self, system: &'static str, file_name: &str) -> Result<Vec<u8>, LoadError> {
+        let path = self.system_dir.join(file_name);
+        if !path.is_file() {
+            return Err(LoadError::MissingBios { system, path: path.display().to_string() });
+        }
+
+        read_file(&path)
+    }
+
+    fn coprocessor_roms(&self) -> CoprocessorRoms {
+        let rom_fn = |file_name: &str| {
+            let path = self.system_dir.join(file_name);
+            let rom_fn: Box<CoprocessorRomFn> =
+                Box::new(move || fs::read(&path).map_err(|err| (err, path.display().to_string())));
+            Some(rom_fn)
+        };
+
+        // MSU-1 is enabled if there is a .msu data file next to the ROM file
+        let msu1_data_path = self.path.with_extension("msu");
+
+        CoprocessorRoms {
+            dsp1: rom_fn("dsp1.rom"),
+            dsp2: rom_fn("dsp2.rom"),
+            dsp3: rom_fn("dsp3.rom"),
+            dsp4: rom_fn("dsp4.rom"),
+            st010: rom_fn("st010.rom"),
+            st011: rom_fn("st011.rom"),
+            st018: rom_fn("st018.rom"),
+            sgb_cartridge: None,
+            msu1_data_path: msu1_data_path.is_file().then_some(msu1_data_path),
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, LoadError> {
+    fs::read(path).map_err(|source| LoadError::Read { path: path.display().to_string(), source })
+}
+
+fn sega_cd_bios_file_name(region: GenesisRegion) -> &'static str {
+    match region {
+        GenesisRegion::Americas => "bios_CD_U.bin",
+        GenesisRegion::Europe => "bios_CD_E.bin",
+        GenesisRegion::Japan => "bios_CD_J.bin",
+    }
+}
+
+pub struct ButtonMapping<Button> {
+    pub id: c_uint,
+    pub button: Button,
+    pub description: &'static CStr,
+}
+
+macro_rules! button_mappings {
+    ($button_type:ident { $($id:ident => $button:ident: $description:literal),* $(,)? }) => {
+        &[
+            $(
+                ButtonMapping {
+                    id: ffi::$id,
+                    button: $button_type::$button,
+                    description: $description,
+                },
+            )*
+        ]
+    };
+}
+
+const SMSGG_MAPPINGS: &[ButtonMapping<SmsGgButton>] = button_mappings!(SmsGgButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_B => Button1: c"Button 1",
+    DEVICE_ID_JOYPAD_A => Button2: c"Button 2",
+    DEVICE_ID_JOYPAD_START => Pause: c"Pause / Start",
+});
+
+const GENESIS_MAPPINGS: &[ButtonMapping<GenesisButton>] = button_mappings!(GenesisButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_Y => A: c"A",
+    DEVICE_ID_JOYPAD_B => B: c"B",
+    DEVICE_ID_JOYPAD_A => C: c"C",
+    DEVICE_ID_JOYPAD_L => X: c"X",
+    DEVICE_ID_JOYPAD_X => Y: c"Y",
+    DEVICE_ID_JOYPAD_R => Z: c"Z",
+    DEVICE_ID_JOYPAD_START => Start: c"Start",
+    DEVICE_ID_JOYPAD_SELECT => Mode: c"Mode",
+});
+
+const NES_MAPPINGS: &[ButtonMapping<NesButton>] = button_mappings!(NesButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_A => A: c"A",
+    DEVICE_ID_JOYPAD_B => B: c"B",
+    DEVICE_ID_JOYPAD_START => Start: c"Start",
+    DEVICE_ID_JOYPAD_SELECT => Select: c"Select",
+});
+
+const SNES_MAPPINGS: &[ButtonMapping<SnesButton>] = button_mappings!(SnesButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_A => A: c"A",
+    DEVICE_ID_JOYPAD_B => B: c"B",
+    DEVICE_ID_JOYPAD_X => X: c"X",
+    DEVICE_ID_JOYPAD_Y => Y: c"Y",
+    DEVICE_ID_JOYPAD_L => L: c"L",
+    DEVICE_ID_JOYPAD_R => R: c"R",
+    DEVICE_ID_JOYPAD_START => Start: c"Start",
+    DEVICE_ID_JOYPAD_SELECT => Select: c"Select",
+});
+
+const GAME_BOY_MAPPINGS: &[ButtonMapping<GameBoyButton>] = button_mappings!(GameBoyButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_A => A: c"A",
+    DEVICE_ID_JOYPAD_B => B: c"B",
+    DEVICE_ID_JOYPAD_START => Start: c"Start",
+    DEVICE_ID_JOYPAD_SELECT => Select: c"Select",
+});
+
+const GBA_MAPPINGS: &[ButtonMapping<GbaButton>] = button_mappings!(GbaButton {
+    DEVICE_ID_JOYPAD_UP => Up: c"Up",
+    DEVICE_ID_JOYPAD_LEFT => Left: c"Left",
+    DEVICE_ID_JOYPAD_RIGHT => Right: c"Right",
+    DEVICE_ID_JOYPAD_DOWN => Down: c"Down",
+    DEVICE_ID_JOYPAD_A => A: c"A",
+    DEVICE_ID_JOYPAD_B => B: c"B",
+    DEVICE_ID_JOYPAD_L => L: c"L",
+    DEVICE_ID_JOYPAD_R => R: c"R",
+    DEVICE_ID_JOYPAD_START => Start: c"Start",
+    DEVICE_ID_JOYPAD_SELECT => Select: c"Select",
+});
+
+const TWO_PLAYERS: &[Player] = &[Player::One, Player::Two];
+const ONE_PLAYER: &[Player] = &[Player::One];
+
+fn update_inputs<Button: Copy, Inputs: MappableInputs<Button>>(
+    inputs: &mut Inputs,
+    mappings: &[ButtonMapping<Button>],
+    players: &[Player],
+    is_pressed: &mut impl FnMut(c_uint, c_uint) -> bool,
+) {
+    for (port, &player) in players.iter().enumerate() {
+        for mapping in mappings {
+            inputs.set_field(mapping.button, player, is_pressed(port as c_uint, mapping.id));
+        }
+    }
+}
+
+fn input_descriptors<Button>(
+    mappings: &[ButtonMapping<Button>],
+    players: &[Player],
+) -> Vec<ffi::InputDescriptor> {
+    (0..players.len() as c_uint)
+        .flat_map(|port| {
+            mappings.iter().map(move |mapping| ffi::InputDescriptor {
+                port,
+                device: ffi::DEVICE_JOYPAD,
+                index: 0,
+                id: mapping.id,
+                description: mapping.description.as_ptr(),
+            })
+        })
+        .collect()
+}
+
+fn load_state<Emulator: EmulatorTrait>(
+    emulator: &mut Emulator,
+    bytes: &[u8],
+) -> Result<(), DecodeError> {
+    let (mut loaded_emulator, _): (Emulator, _) =
+        bincode::decode_from_slice(bytes, bincode_config!())?;
+    loaded_emulator.take_rom_from(emulator);
+    *emulator = loaded_emulator;
+
+    Ok(())
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Emulator {
+    SmsGg(SmsGgEmulator, SmsGgInputs),
+    Genesis(GenesisEmulator, GenesisInputs),
+    SegaCd(SegaCdEmulator, GenesisInputs),
+    Sega32X(Sega32XEmulator, GenesisInputs),
+    Nes(NesEmulator, NesInputs),
+    Snes(SnesEmulator, SnesInputs),
+    GameBoy(GameBoyEmulator, GameBoyInputs),
+    Gba(GameBoyAdvanceEmulator, GbaInputs),
+}
+
+// Expands the given expression once per backend, with `$emulator` bound to the emulator
+macro_rules! match_each_emulator {
+    ($value:expr, $emulator:ident => $body:expr) => {
+        match $value {
+            Emulator::SmsGg($emulator, ..) => $body,
+            Emulator::Genesis($emulator, ..) => $body,
+            Emulator::SegaCd($emulator, ..) => $body,
+            Emulator::Sega32X($emulator, ..) => $body,
+            Emulator::Nes($emulator, ..) => $body,
+            Emulator::Snes($emulator, ..) => $body,
+            Emulator::GameBoy($emulator, ..) => $body,
+            Emulator::Gba($emulator, ..) => $body,
+        }
+    };
+}
+
+impl Emulator {
+    /// Run the emulator until it renders the next frame.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any error returned by the emulator.
+    pub fn run_frame<R: Renderer, A: AudioOutput>(
+        &mut self,
+        renderer: &mut R,
+        audio_output: &mut A,
+        save_writer: &mut RetroSaveWriter,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        macro_rules! run_emulator {
+            ($emulator:expr, $inputs:expr) => {
+                while $emulator.tick(
+                    renderer,
+                    audio_output,
+                    &mut ConstantInputPoller($inputs),
+                    save_writer,
+                )? != TickEffect::FrameRendered
+                {}
+            };
+        }
+
+        match self {
+            Self::SmsGg(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::Genesis(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::SegaCd(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::Sega32X(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::Nes(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::Snes(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::GameBoy(emulator, inputs) => run_emulator!(emulator, inputs),
+            Self::Gba(emulator, inputs) => run_emulator!(emulator, inputs),
+        }
+
+        Ok(())
+    }
+
+    /// Update inputs from the `RetroPad` state. `is_pressed` is called with a port and a joypad
+    /// button ID.
+    pub fn update_inputs(&mut self, mut is_pressed: impl FnMut(c_uint, c_uint) -> bool) {
+        let is_pressed = &mut is_pressed;
+        match self {
+            Self::SmsGg(_, inputs) => {
+                update_inputs(inputs, SMSGG_MAPPINGS, TWO_PLAYERS, is_pressed);
+            }
+            Self::Genesis(_, inputs) | Self::SegaCd(_, inputs) | Self::Sega32X(_, inputs) => {
+                update_inputs(inputs, GENESIS_MAPPINGS, TWO_PLAYERS, is_pressed);
+            }
+            Self::Nes(_, inputs) => update_inputs(inputs, NES_MAPPINGS, TWO_PLAYERS, is_pressed),
+            Self::Snes(_, inputs) => update_inputs(inputs, SNES_MAPPINGS, TWO_PLAYERS, is_pressed),
+            Self::GameBoy(_, inputs) => {
+                update_inputs(inputs, GAME_BOY_MAPPINGS, ONE_PLAYER, is_pressed);
+            }
+            Self::Gba(_, inputs) => update_inputs(inputs, GBA_MAPPINGS, ONE_PLAYER, is_pressed),
+        }
+    }
+
+    pub fn input_descriptors(&self) -> Vec<ffi::InputDescriptor> {
+        match self {
+            Self::SmsGg(..) => input_descriptors(SMSGG_MAPPINGS, TWO_PLAYERS),
+            Self::Genesis(..) | Self::SegaCd(..) | Self::Sega32X(..) => {
+                input_descriptors(GENESIS_MAPPINGS, TWO_PLAYERS)
+            }
+            Self::Nes(..) => input_descriptors(NES_MAPPINGS, TWO_PLAYERS),
+            Self::Snes(..) => input_descriptors(SNES_MAPPINGS, TWO_PLAYERS),
+            Self::GameBoy(..) => input_descriptors(GAME_BOY_MAPPINGS, ONE_PLAYER),
+            Self::Gba(..) => input_descriptors(GBA_MAPPINGS, ONE_PLAYER),
+        }
+    }
+
+    pub fn reload_config(&mut self, options: &mut impl OptionVisitor) {
+        match self {
+            Self::SmsGg(emulator, ..) => emulator.reload_config(&options::smsgg_config(options)),
+            Self::Genesis(emulator, ..) => {
+                emulator.reload_config(&options::genesis_config(options));
+            }
+            Self::SegaCd(emulator, ..) => {
+                emulator.reload_config(&options::sega_cd_config(options));
+            }
+            Self::Sega32X(emulator, ..) => {
+                emulator.reload_config(&options::sega_32x_config(options));
+            }
+            Self::Nes(emulator, ..) => emulator.reload_config(&options::nes_config(options)),
+            Self::Snes(emulator, ..) => emulator.reload_config(&options::snes_config(options)),
+            Self::GameBoy(emulator, ..) => emulator.reload_config(&options::gb_config(options)),
+            Self::Gba(emulator, ..) => emulator.reload_config(&options::gba_config(options)),
+        }
+    }
+
+    pub fn soft_reset(&mut self) {
+        match_each_emulator!(self, emulator => emulator.soft_reset());
+    }
+
+    pub fn target_fps(&self) -> f64 {
+        match_each_emulator!(self, emulator => emulator.target_fps())
+    }
+
+    pub fn update_audio_output_frequency(&mut self, output_frequency: u64) {
+        match_each_emulator!(self, emulator => emulator.update_audio_output_frequency(output_frequency));
+    }
+
+    /// Contents of the game's primary save file (the one persisted under the "sav" extension), or
+    /// `None` if the game has no battery-backed memory.
+    pub fn save_ram(&self) -> Option<&[u8]> {
+        match self {
+            Self::SmsGg(emulator, _) => emulator.save_ram(),
+            Self::Genesis(emulator, _) => emulator.save_ram(),
+            Self::SegaCd(emulator, _) => Some(emulator.save_ram()),
+            Self::Sega32X(emulator, _) => emulator.save_ram(),
+            Self::Nes(emulator, _) => emulator.save_ram(),
+            Self::Snes(emulator, _) => emulator.save_ram(),
+            Self::GameBoy(emulator, _) => emulator.save_ram(),
+            Self::Gba(emulator, _) => emulator.save_ram(),
+        }
+    }
+
+    /// Initial contents of the save RAM region exposed to the frontend. Some backends only detect
+    /// their save memory type (or whether it is battery-backed) once the game accesses it, so in
+    /// those cases the region is sized for the largest save file that the game could produce.
+    pub fn initial_save_ram(&self) -> Vec<u8> {
+        match self {
+            Self::SmsGg(emulator, _) => emulator.cartridge_ram().to_vec(),
+            Self::Gba(emulator, _) if emulator.save_ram().is_none() => {
+                vec![0xFF; emulator.max_save_ram_len()]
+            }
+            _ => self.save_ram().unwrap_or_default().to_vec(),
+        }
+    }
+
+    pub fn set_cheats(&mut self, codes: &[String]) {
+        match_each_emulator!(self, emulator => {
+            emulator.set_cheats(CheatSet::from_codes(cheat_system(emulator), codes));
+        });
+    }
+
+    /// # Errors
+    ///
+    /// Propagates any error encountered while encoding the emulator state.
+    pub fn save_state(&self) -> Result<Vec<u8>, EncodeError> {
+        match_each_emulator!(self, emulator => bincode::encode_to_vec(emulator, bincode_config!()))
+    }
+
+    /// Load a state produced by [`Self::save_state`]. The caller should reload config afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any error encountered while decoding the emulator state.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), DecodeError> {
+        match_each_emulator!(self, emulator => load_state(emulator, bytes))
+    }
+}
+
+fn cheat_system<Emulator: EmulatorTrait>(
+    _emulator: &Emulator,
+) -> jgenesis_common::cheats::CheatSystem {
+    Emulator::CHEAT_SYSTEM
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bin_extension_detects_32x() {
+        let mut rom = vec![0; 0x1000];
+        assert_eq!(System::from_extension("bin", &rom), Some(System::Genesis));
+
+        let start = s32x_core::SECURITY_PROGRAM_CARTRIDGE_ADDR;
+        rom[start..start + s32x_core::SECURITY_PROGRAM_LEN]
+            .copy_from_slice(s32x_core::security_program());
+        assert_eq!(System::from_extension("bin", &rom), Some(System::Sega32X));
+    }
+
+    #[test]
+    fn every_supported_extension_has_a_system() {
+        for extension in SUPPORTED_EXTENSIONS.to_str().unwrap().split('|') {
+            assert!(System::from_extension(extension, &[]).is_some(), "no system for .{extension}");
+        }
+    }
+}
@@ -0,0 +1,789 @@
+//! libretro core that exposes every jgenesis backend through the libretro API.
+//!
+//! The frontend calls into the core from a single thread, but the exported functions still need
+//! global state; it is kept behind mutexes so that no `static mut` is required.
+
+mod audio;
+mod emulator;
+mod ffi;
+mod options;
+mod save;
+mod video;
+
+use crate::audio::RetroAudioOutput;
+use crate::emulator::{Emulator, Game};
+use crate::options::{OptionDefinitions, OptionValues, OptionVisitor};
+use crate::save::RetroSaveWriter;
+use crate::video::RetroRenderer;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString, c_char, c_uint, c_void};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::{iter, ptr, slice};
+
+const LIBRARY_NAME: &CStr = c"jgenesis";
+const LIBRARY_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("package version should not contain NUL bytes"),
+    };
+
+// Save states are padded up to a fixed size because frontends expect retro_serialize_size() to
+// remain constant after a game is loaded, and some backends' states vary slightly in size
+const SERIALIZE_SIZE_HEADROOM: usize = 64 * 1024;
+const SERIALIZE_LEN_PREFIX: usize = size_of::<u32>();
+
+#[derive(Default, Clone, Copy)]
+struct Callbacks {
+    environment: Option<ffi::EnvironmentFn>,
+    video_refresh: Option<ffi::VideoRefreshFn>,
+    audio_sample_batch: Option<ffi::AudioSampleBatchFn>,
+    input_poll: Option<ffi::InputPollFn>,
+    input_state: Option<ffi::InputStateFn>,
+    log_printf: Option<ffi::LogPrintfFn>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    log_printf: None,
+});
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap()
+}
+
+fn callbacks_mut() -> MutexGuard<'static, Callbacks> {
+    CALLBACKS.lock().unwrap()
+}
+
+/// # Safety
+///
+/// `data` must be valid for the given environment command.
+unsafe fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match callbacks().environment {
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+fn get_variable(key: &CStr) -> Option<String> {
+    let mut variable = ffi::Variable { key: key.as_ptr(), value: ptr::null() };
+    if !unsafe { environment(ffi::ENVIRONMENT_GET_VARIABLE, (&raw mut variable).cast()) }
+        || variable.value.is_null()
+    {
+        return None;
+    }
+
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+fn option_values() -> impl OptionVisitor {
+    OptionValues(get_variable)
+}
+
+fn variables_updated() -> bool {
+    let mut updated = false;
+    let supported =
+        unsafe { environment(ffi::ENVIRONMENT_GET_VARIABLE_UPDATE, (&raw mut updated).cast()) };
+    supported && updated
+}
+
+fn system_directory() -> Option<PathBuf> {
+    environment_directory(ffi::ENVIRONMENT_GET_SYSTEM_DIRECTORY)
+}
+
+fn save_directory() -> Option<PathBuf> {
+    environment_directory(ffi::ENVIRONMENT_GET_SAVE_DIRECTORY)
+}
+
+fn environment_directory(cmd: c_uint) -> Option<PathBuf> {
+    let mut dir: *const c_char = ptr::null();
+    if !unsafe { environment(cmd, (&raw mut dir).cast()) } || dir.is_null() {
+        return None;
+    }
+
+    let dir = unsafe { CStr::from_ptr(dir) };
+    Some(PathBuf::from(dir.to_string_lossy().into_owned()))
+}
+
+/// Forwards log messages to the frontend's log interface, if it provides one.
+struct RetroLogger;
+
+impl Log for RetroLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let Some(log_printf) = callbacks().log_printf else { return };
+
+        let level = match record.level() {
+            Level::Error => ffi::LOG_ERROR,
+            Level::Warn => ffi::LOG_WARN,
+            Level::Info => ffi::LOG_INFO,
+            Level::Debug | Level::Trace => ffi::LOG_DEBUG,
+        };
+
+        let message = format!("[jgenesis] {}\n", record.args()).replace('\0', "");
+        let message = CString::new(message).unwrap();
+        unsafe {
+            log_printf(level, c"%s".as_ptr(), message.as_ptr());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: RetroLogger = RetroLogger;
+
+struct Core {
+    game: Game,
+    emulator: Emulator,
+    renderer: RetroRenderer,
+    audio_output: RetroAudioOutput,
+    save_writer: RetroSaveWriter,
+    av_info: ffi::SystemAvInfo,
+    serialize_size: usize,
+    cheats: BTreeMap<c_uint, String>,
+    save_ram_applied: bool,
+}
+
+impl Core {
+    fn load(path: &Path) -> Result<Self, emulator::LoadError> {
+        let system_dir =
+            system_directory().or_else(|| path.parent().map(Path::to_path_buf)).unwrap_or_default();
+
+        let game = Game::open(path, &system_dir)?;
+
+        // Auxiliary save files are named after the ROM and live next to the frontend's .srm files
+        let save_dir = save_directory().unwrap_or_else(|| system_dir.clone());
+        let file_stem = path.file_stem().unwrap_or_default();
+        let mut save_writer = RetroSaveWriter::new(save_dir.join(file_stem));
+
+        let mut emulator = game.create_emulator(&mut option_values(), &mut save_writer)?;
+        emulator.update_audio_output_frequency(audio::SAMPLE_RATE);
+
+        // The frontend queries the save RAM size right after retro_load_game() and only loads its
+        // save file once, so the region needs to be large enough before the first frame runs
+        save_writer.init_save_ram(emulator.initial_save_ram());
+
+        let renderer = RetroRenderer::new();
+        let av_info = ffi::SystemAvInfo {
+            geometry: renderer.geometry(),
+            timing: ffi::SystemTiming {
+                fps: emulator.target_fps(),
+                sample_rate: audio::SAMPLE_RATE as f64,
+            },
+        };
+
+        let serialize_size = emulator.save_state().map_or(0, |state| serialize_size(state.len()));
+
+        Ok(Self {
+            game,
+            emulator,
+            renderer,
+            audio_output: RetroAudioOutput::default(),
+            save_writer,
+            av_info,
+            serialize_size,
+            cheats: BTreeMap::new(),
+            save_ram_applied: false,
+        })
+    }
+
+    // The frontend writes save RAM contents after retro_load_game() returns, so the emulator needs
+    // to be re-created with that save file before it runs the first frame. This also needs to
+    // happen before any state is saved or loaded, since frontends may load a state before the first
+    // frame (e.g. auto-load state or runahead).
+    fn apply_save_ram(&mut self) {
+        if self.save_ram_applied {
+            return;
+        }
+        self.save_ram_applied = true;
+
+        if !self.save_writer.save_ram_modified() {
+            return;
+        }
+
+        match self.game.create_emulator(&mut option_values(), &mut self.save_writer) {
+            Ok(emulator) => {
+                self.emulator = emulator;
+                self.emulator.update_audio_output_frequency(audio::SAMPLE_RATE);
+                self.apply_cheats();
+            }
+            Err(err) => log::error!("Error re-creating emulator with save files: {err}"),
+        }
+    }
+
+    fn apply_cheats(&mut self) {
+        self.emulator.set_cheats(&split_cheat_codes(&self.cheats));
+    }
+
+    fn run(&mut self, callbacks: Callbacks) {
+        self.apply_save_ram();
+
+        if variables_updated() {
+            self.emulator.reload_config(&mut option_values());
+        }
+
+        if let Some(input_poll) = callbacks.input_poll {
+            unsafe {
+                input_poll();
+            }
+        }
+        if let Some(input_state) = callbacks.input_state {
+            self.emulator.update_inputs(|port, id| unsafe {
+                input_state(port, ffi::DEVICE_JOYPAD, 0, id) != 0
+            });
+        }
+
+        if let Err(err) = self.emulator.run_frame(
+            &mut self.renderer,
+            &mut self.audio_output,
+            &mut self.save_writer,
+        ) {
+            log::error!("Emulator error: {err}");
+        }
+
+        self.update_av_info();
+
+        if let Some(video_refresh) = callbacks.video_refresh {
+            match self.renderer.take_frame() {
+                Some((frame, size)) => unsafe {
+                    video_refresh(
+                        frame.as_ptr().cast(),
+                        size.width,
+                        size.height,
+                        size.width as usize * size_of::<u32>(),
+                    );
+                },
+                // NULL tells the frontend to duplicate the previous frame
+                None => unsafe { video_refresh(ptr::null(), 0, 0, 0) },
+            }
+        }
+
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            let mut samples = self.audio_output.samples.as_slice();
+            while samples.len() >= 2 {
+                let written = unsafe { audio_sample_batch(samples.as_ptr(), samples.len() / 2) };
+                if written == 0 {
+                    break;
+                }
+                samples = &samples[(2 * written).min(samples.len())..];
+            }
+        }
+        self.audio_output.samples.clear();
+    }
+
+    fn update_av_info(&mut self) {
+        let geometry = self.renderer.geometry();
+        let fps = self.emulator.target_fps();
+
+        match update_av_info(&mut self.av_info, geometry, fps) {
+            Some(AvInfoChange::Timing) => unsafe {
+                environment(ffi::ENVIRONMENT_SET_SYSTEM_AV_INFO, (&raw mut self.av_info).cast());
+            },
+            Some(AvInfoChange::Geometry) => unsafe {
+                environment(ffi::ENVIRONMENT_SET_GEOMETRY, (&raw mut self.av_info.geometry).cast());
+            },
+            None => {}
+        }
+    }
+}
+
+fn serialize_size(state_len: usize) -> usize {
+    SERIALIZE_LEN_PREFIX + state_len + state_len / 8 + SERIALIZE_SIZE_HEADROOM
+}
+
+/// Write a length-prefixed state into the frontend's buffer, zero-filling the rest of it.
+///
+/// Returns `false` if the state does not fit.
+fn write_serialized_state(state: &[u8], buffer: &mut [u8]) -> bool {
+    let Ok(len) = u32::try_from(state.len()) else { return false };
+    if SERIALIZE_LEN_PREFIX + state.len() > buffer.len() {
+        log::error!(
+            "Save state is too large for buffer: {} bytes, buffer is {} bytes",
+            SERIALIZE_LEN_PREFIX + state.len(),
+            buffer.len()
+        );
+        return false;
+    }
+
+    let (prefix, rest) = buffer.split_at_mut(SERIALIZE_LEN_PREFIX);
+    prefix.copy_from_slice(&len.to_le_bytes());
+    rest[..state.len()].copy_from_slice(state);
+    rest[state.len()..].fill(0);
+
+    true
+}
+
+/// Read a state written by [`write_serialized_state`], ignoring the padding after it.
+fn read_serialized_state(buffer: &[u8]) -> Option<&[u8]> {
+    let (prefix, rest) = buffer.split_first_chunk::<SERIALIZE_LEN_PREFIX>()?;
+    rest.get(..u32::from_le_bytes(*prefix) as usize)
+}
+
+// Each frontend cheat entry can contain multiple codes separated by '+'
+fn split_cheat_codes(cheats: &BTreeMap<c_uint, String>) -> Vec<String> {
+    cheats
+        .values()
+        .flat_map(|code| code.split('+'))
+        .map(|code| code.trim().to_string())
+        .filter(|code| !code.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AvInfoChange {
+    Timing,
+    Geometry,
+}
+
+// Changing the frame rate requires SET_SYSTEM_AV_INFO, while geometry changes alone can use the
+// cheaper SET_GEOMETRY
+fn update_av_info(
+    av_info: &mut ffi::SystemAvInfo,
+    geometry: ffi::GameGeometry,
+    fps: f64,
+) -> Option<AvInfoChange> {
+    if (fps - av_info.timing.fps).abs() > f64::EPSILON {
+        av_info.geometry = geometry;
+        av_info.timing.fps = fps;
+        Some(AvInfoChange::Timing)
+    } else if geometry != av_info.geometry {
+        av_info.geometry = geometry;
+        Some(AvInfoChange::Geometry)
+    } else {
+        None
+    }
+}
+
+fn core() -> MutexGuard<'static, Option<Core>> {
+    CORE.lock().unwrap()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_api_version() -> c_uint {
+    ffi::API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_init() {
+    let mut log_callback = ffi::LogCallback { log: None };
+    if unsafe { environment(ffi::ENVIRONMENT_GET_LOG_INTERFACE, (&raw mut log_callback).cast()) } {
+        callbacks_mut().log_printf = log_callback.log;
+    }
+
+    // Fails if the core was previously initialized in this process, which is fine
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    *core() = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a valid `retro_system_info` struct.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut ffi::SystemInfo) {
+    unsafe {
+        info.write(ffi::SystemInfo {
+            library_name: LIBRARY_NAME.as_ptr(),
+            library_version: LIBRARY_VERSION.as_ptr(),
+            valid_extensions: emulator::SUPPORTED_EXTENSIONS.as_ptr(),
+            need_fullpath: true,
+            block_extract: false,
+        });
+    }
+}
+
+/// # Safety
+///
+/// `info` must point to a valid `retro_system_av_info` struct.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut ffi::SystemAvInfo) {
+    let av_info = core().as_ref().map_or(
+        ffi::SystemAvInfo {
+            geometry: RetroRenderer::new().geometry(),
+            timing: ffi::SystemTiming { fps: 60.0, sample_rate: audio::SAMPLE_RATE as f64 },
+        },
+        |core| core.av_info,
+    );
+
+    unsafe {
+        info.write(av_info);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_environment(environment: ffi::EnvironmentFn) {
+    callbacks_mut().environment = Some(environment);
+
+    let definitions = OptionDefinitions::all();
+    let mut variables: Vec<_> = definitions
+        .definitions
+        .iter()
+        .map(|(key, value)| ffi::Variable { key: key.as_ptr(), value: value.as_ptr() })
+        .chain(iter::once(ffi::Variable { key: ptr::null(), value: ptr::null() }))
+        .collect();
+
+    unsafe {
+        environment(ffi::ENVIRONMENT_SET_VARIABLES, variables.as_mut_ptr().cast());
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(video_refresh: ffi::VideoRefreshFn) {
+    callbacks_mut().video_refresh = Some(video_refresh);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: ffi::AudioSampleFn) {
+    // Audio is always sent through the batch callback
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: ffi::AudioSampleBatchFn) {
+    callbacks_mut().audio_sample_batch = Some(audio_sample_batch);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(input_poll: ffi::InputPollFn) {
+    callbacks_mut().input_poll = Some(input_poll);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(input_state: ffi::InputStateFn) {
+    callbacks_mut().input_state = Some(input_state);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {
+    // Only the RetroPad is supported
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = core().as_mut() {
+        core.apply_save_ram();
+        core.emulator.soft_reset();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    if let Some(core) = core().as_mut() {
+        core.run(callbacks);
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    core().as_ref().map_or(0, |core| core.serialize_size)
+}
+
+/// # Safety
+///
+/// `data` must be valid for writes of `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let mut guard = core();
+    let Some(core) = guard.as_mut() else { return false };
+    core.apply_save_ram();
+
+    let state = match core.emulator.save_state() {
+        Ok(state) => state,
+        Err(err) => {
+            log::error!("Error saving state: {err}");
+            return false;
+        }
+    };
+
+    let buffer = unsafe { slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+    write_serialized_state(&state, buffer)
+}
+
+/// # Safety
+///
+/// `data` must be valid for reads of `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut guard = core();
+    let Some(core) = guard.as_mut() else { return false };
+    core.apply_save_ram();
+
+    let buffer = unsafe { slice::from_raw_parts(data.cast::<u8>(), size) };
+    let Some(state) = read_serialized_state(buffer) else { return false };
+
+    if let Err(err) = core.emulator.load_state(state) {
+        log::error!("Error loading state: {err}");
+        return false;
+    }
+    core.emulator.reload_config(&mut option_values());
+
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {
+    if let Some(core) = core().as_mut() {
+        core.cheats.clear();
+        core.apply_cheats();
+    }
+}
+
+/// # Safety
+///
+/// `code` must be NULL or point to a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+    let mut guard = core();
+    let Some(core) = guard.as_mut() else { return };
+
+    if enabled && !code.is_null() {
+        let code = unsafe { CStr::from_ptr(code) };
+        core.cheats.insert(index, code.to_string_lossy().into_owned());
+    } else {
+        core.cheats.remove(&index);
+    }
+    core.apply_cheats();
+}
+
+/// # Safety
+///
+/// `game` must be NULL or point to a valid `retro_game_info` struct.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const ffi::GameInfo) -> bool {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        log::error!("No game provided; this core does not support running without content");
+        return false;
+    };
+
+    if game.path.is_null() {
+        log::error!("No content path provided");
+        return false;
+    }
+    let path = unsafe { CStr::from_ptr(game.path) };
+    let path = PathBuf::from(path.to_string_lossy().into_owned());
+
+    let mut pixel_format = ffi::PIXEL_FORMAT_XRGB8888;
+    if !unsafe { environment(ffi::ENVIRONMENT_SET_PIXEL_FORMAT, (&raw mut pixel_format).cast()) } {
+        log::error!("Frontend does not support XRGB8888 pixel format");
+        return false;
+    }
+
+    let core = match Core::load(&path) {
+        Ok(core) => core,
+        Err(err) => {
+            log::error!("Error loading '{}': {err}", path.display());
+            return false;
+        }
+    };
+
+    let mut descriptors = core.emulator.input_descriptors();
+    descriptors.push(ffi::InputDescriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: ptr::null(),
+    });
+    unsafe {
+        environment(ffi::ENVIRONMENT_SET_INPUT_DESCRIPTORS, descriptors.as_mut_ptr().cast());
+    }
+
+    *self::core() = Some(core);
+
+    true
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const ffi::GameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    *core() = None;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_region() -> c_uint {
+    // PAL consoles run at ~50Hz and NTSC consoles run at ~60Hz
+    match core().as_ref() {
+        Some(core) if core.emulator.target_fps() < 55.0 => ffi::REGION_PAL,
+        _ => ffi::REGION_NTSC,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match (id, core().as_mut()) {
+        (ffi::MEMORY_SAVE_RAM, Some(core)) => {
+            let save_ram = core.save_writer.save_ram_mut();
+            if save_ram.is_empty() { ptr::null_mut() } else { save_ram.as_mut_ptr().cast() }
+        }
+        _ => ptr::null_mut(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match (id, core().as_ref()) {
+        (ffi::MEMORY_SAVE_RAM, Some(core)) => core.save_writer.save_ram().len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    fn load_gba_core(name: &str) -> (Core, PathBuf) {
+        let dir = env::temp_dir().join(format!("jgenesis-libretro-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // The save memory type string makes the backend assume EEPROM of unknown size
+        let mut rom = vec![0; 0x1000];
+        rom[0x200..0x20B].copy_from_slice(b"EEPROM_V123");
+        let path = dir.join("game.gba");
+        fs::write(&path, rom).unwrap();
+
+        (Core::load(&path).unwrap(), dir)
+    }
+
+    #[test]
+    fn undetected_save_memory_is_sized_at_load() {
+        let (core, dir) = load_gba_core("undetected-save");
+
+        assert_eq!(core.emulator.save_ram(), None);
+        assert_eq!(core.save_writer.save_ram().len(), 8 * 1024);
+        assert!(core.save_writer.save_ram().iter().all(|&byte| byte == 0xFF));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_ram_applied_before_state_operations() {
+        let (mut core, dir) = load_gba_core("state-before-frame");
+
+        // State from a few frames in, so that it differs from a freshly created emulator
+        let (mut other_core, _) = load_gba_core("state-before-frame");
+        for _ in 0..3 {
+            other_core.run(Callbacks::default());
+        }
+        let state = other_core.emulator.save_state().unwrap();
+
+        // Frontend writes its save file into the region, then loads a state before the first frame
+        core.save_writer.save_ram_mut()[..4].copy_from_slice(&[1, 2, 3, 4]);
+        let mut buffer = vec![0; core.serialize_size];
+        assert!(write_serialized_state(&state, &mut buffer));
+
+        *self::core() = Some(core);
+        assert!(unsafe { retro_unserialize(buffer.as_ptr().cast(), buffer.len()) });
+
+        let mut guard = self::core();
+        let core = guard.as_mut().unwrap();
+        assert!(core.save_ram_applied);
+
+        // The first frame must not re-create the emulator and discard the loaded state
+        core.apply_save_ram();
+        assert_eq!(core.emulator.save_state().unwrap(), state);
+        *guard = None;
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn serialize_round_trip_with_padding() {
+        let state: Vec<u8> = (1..=100).collect();
+        let mut buffer = vec![0xAA; serialize_size(state.len())];
+        assert!(buffer.len() > SERIALIZE_LEN_PREFIX + state.len());
+
+        assert!(write_serialized_state(&state, &mut buffer));
+        assert_eq!(buffer[..SERIALIZE_LEN_PREFIX], 100_u32.to_le_bytes());
+        assert!(buffer[SERIALIZE_LEN_PREFIX + state.len()..].iter().all(|&byte| byte == 0));
+
+        assert_eq!(read_serialized_state(&buffer), Some(state.as_slice()));
+    }
+
+    #[test]
+    fn serialize_buffer_too_small() {
+        let state = [5; 16];
+
+        let mut buffer = [0; SERIALIZE_LEN_PREFIX + 15];
+        assert!(!write_serialized_state(&state, &mut buffer));
+
+        let mut buffer = [0; SERIALIZE_LEN_PREFIX + 16];
+        assert!(write_serialized_state(&state, &mut buffer));
+        assert_eq!(read_serialized_state(&buffer), Some(state.as_slice()));
+    }
+
+    #[test]
+    fn unserialize_truncated() {
+        assert_eq!(read_serialized_state(&[]), None);
+        assert_eq!(read_serialized_state(&[16, 0, 0]), None);
+
+        // Prefix claims more bytes than the buffer holds
+        let mut buffer = vec![0; SERIALIZE_LEN_PREFIX + 8];
+        buffer[..SERIALIZE_LEN_PREFIX].copy_from_slice(&9_u32.to_le_bytes());
+        assert_eq!(read_serialized_state(&buffer), None);
+
+        buffer[..SERIALIZE_LEN_PREFIX].copy_from_slice(&0_u32.to_le_bytes());
+        assert_eq!(read_serialized_state(&buffer), Some([].as_slice()));
+    }
+
+    #[test]
+    fn cheat_codes_split_on_plus() {
+        let cheats = BTreeMap::from([
+            (0, "ABCD-1234+ EFGH-5678".to_string()),
+            (1, "  ".to_string()),
+            (2, "7E0DBF63++".to_string()),
+        ]);
+
+        assert_eq!(split_cheat_codes(&cheats), vec!["ABCD-1234", "EFGH-5678", "7E0DBF63"]);
+    }
+
+    #[test]
+    fn av_info_changes() {
+        let geometry = RetroRenderer::new().geometry();
+        let mut av_info = ffi::SystemAvInfo {
+            geometry,
+            timing: ffi::SystemTiming { fps: 60.0, sample_rate: audio::SAMPLE_RATE as f64 },
+        };
+
+        assert_eq!(update_av_info(&mut av_info, geometry, 60.0), None);
+
+        let resized = ffi::GameGeometry { base_width: geometry.base_width / 2, ..geometry };
+        assert_eq!(update_av_info(&mut av_info, resized, 60.0), Some(AvInfoChange::Geometry));
+        assert_eq!(av_info.geometry, resized);
+
+        // Frame rate changes take precedence and also update geometry
+        assert_eq!(update_av_info(&mut av_info, geometry, 50.0), Some(AvInfoChange::Timing));
+        assert_eq!(av_info.geometry, geometry);
+        assert_eq!(av_info.timing.fps, 50.0);
+        assert_eq!(update_av_info(&mut av_info, geometry, 50.0), None);
+    }
+}
@@ -0,0 +1,585 @@
+//! Core options, built from the config enums that the backends already expose.
+//!
+//! Each `*_config` function both defines and reads the options for one backend through an
+//! [`OptionVisitor`], so that the option list registered with the frontend and the values read back
+//! can never drift apart. Defaults match the native frontend's defaults.
+
+use gb_config::{GbAspectRatio, GbAudioResampler, GbPalette, GbcColorCorrection};
+use gb_core::api::GameBoyEmulatorConfig;
+use gba_config::{GbaAspectRatio, GbaAudioInterpolation, GbaColorCorrection, GbaSaveMemory};
+use gba_core::api::{GbaAudioConfig, GbaEmulatorConfig};
+use genesis_config::{
+    GenesisAspectRatio, GenesisControllerType, GenesisRegion, Opn2BusyBehavior, PcmInterpolation,
+    S32XColorTint, S32XPwmResampling, S32XVideoOut,
+};
+use genesis_core::GenesisEmulatorConfig;
+use jgenesis_common::frontend::{ColorCorrection, FiniteF32, TimingMode};
+use nes_config::{NesAspectRatio, NesAudioResampler, NesPalette, Overscan};
+use nes_core::api::NesEmulatorConfig;
+use s32x_core::api::Sega32XEmulatorConfig;
+use segacd_core::api::SegaCdEmulatorConfig;
+use smsgg_config::{
+    GgAspectRatio, SmsAspectRatio, SmsGgControllerType, SmsGgRegion, SmsModel, Sn76489Version,
+};
+use smsgg_core::SmsGgEmulatorConfig;
+use snes_config::{AudioInterpolationMode, SnesAspectRatio};
+use snes_core::api::SnesEmulatorConfig;
+use std::ffi::{CStr, CString};
+use std::fmt::Display;
+use std::iter;
+use std::num::{NonZeroU16, NonZeroU32, NonZeroU64};
+
+const AUTO: &str = "Auto";
+const ENABLED: &str = "enabled";
+const DISABLED: &str = "disabled";
+
+const GBC_CORRECTION_GAMMA: f32 = 2.0;
+const GBA_CORRECTION_GAMMA: f32 = 3.2;
+
+pub trait OptionVisitor {
+    /// Visit a single option. `values` contains every possible value, with the default value first.
+    ///
+    /// Returns the currently selected value, if known.
+    fn visit(
+        &mut self,
+        key: &'static CStr,
+        description: &'static str,
+        values: &[String],
+    ) -> Option<String>;
+
+    fn enum_option<T: Copy + PartialEq + Display>(
+        &mut self,
+        key: &'static CStr,
+        description: &'static str,
+        all: &[T],
+        default: T,
+    ) -> T {
+        let values: Vec<_> = iter::once(default)
+            .chain(all.iter().copied().filter(|&value| value != default))
+            .map(|value| value.to_string())
+            .collect();
+
+        self.visit(key, description, &values)
+            .and_then(|selected| all.iter().copied().find(|value| value.to_string() == selected))
+            .unwrap_or(default)
+    }
+
+    fn optional_enum_option<T: Copy + Display>(
+        &mut self,
+        key: &'static CStr,
+        description: &'static str,
+        all: &[T],
+    ) -> Option<T> {
+        let values: Vec<_> =
+            iter::once(AUTO.into()).chain(all.iter().map(ToString::to_string)).collect();
+
+        let selected = self.visit(key, description, &values)?;
+        all.iter().copied().find(|value| value.to_string() == selected)
+    }
+
+    fn bool_option(
+        &mut self,
+        key: &'static CStr,
+        description: &'static str,
+        default: bool,
+    ) -> bool {
+        let values = if default { [ENABLED, DISABLED] } else { [DISABLED, ENABLED] };
+        let values = values.map(String::from);
+
+        self.visit(key, description, &values).map_or(default, |selected| selected == ENABLED)
+    }
+}
+
+/// Collects option definitions in the `"Description; default|other|..."` format expected by
+/// `RETRO_ENVIRONMENT_SET_VARIABLES`.
+#[derive(Debug, Default)]
+pub struct OptionDefinitions {
+    pub definitions: Vec<(&'static CStr, CString)>,
+}
+
+impl OptionDefinitions {
+    pub fn all() -> Self {
+        let mut definitions = Self::default();
+
+        smsgg_config(&mut definitions);
+        genesis_config(&mut definitions);
+        sega_cd_config(&mut definitions);
+        sega_32x_config(&mut definitions);
+        nes_config(&mut definitions);
+        snes_config(&mut definitions);
+        gb_config(&mut definitions);
+        gba_config(&mut definitions);
+
+        definitions
+    }
+}
+
+impl OptionVisitor for OptionDefinitions {
+    fn visit(
+        &mut self,
+        key: &'static CStr,
+        description: &'static str,
+        values: &[String],
+    ) -> Option<String> {
+        // Sega CD and 32X options include the Genesis options; only define those once
+        if self.definitions.iter().all(|&(existing, _)| existing != key) {
+            let definition = format!("{description}; {}", values.join("|"));
+            self.definitions.push((key, CString::new(definition).unwrap()));
+        }
+
+        None
+    }
+}
+
+/// Reads currently selected option values using the given lookup function.
+pub struct OptionValues<F>(pub F);
+
+impl<F: FnMut(&CStr) -> Option<String>> OptionVisitor for OptionValues<F> {
+    fn visit(
+        &mut self,
+        key: &'static CStr,
+        _description: &'static str,
+        _values: &[String],
+    ) -> Option<String> {
+        (self.0)(key)
+    }
+}
+
+pub fn smsgg_config(v: &mut impl OptionVisitor) -> SmsGgEmulatorConfig {
+    SmsGgEmulatorConfig {
+        sms_timing_mode: v.enum_option(
+            c"jgenesis_sms_timing_mode",
+            "SMS: Timing mode",
+            &TimingMode::ALL,
+            TimingMode::default(),
+        ),
+        sms_model: v.enum_option(
+            c"jgenesis_sms_model",
+            "SMS: Model",
+            &SmsModel::ALL,
+            SmsModel::default(),
+        ),
+        forced_psg_version: v.optional_enum_option(
+            c"jgenesis_sms_psg_version",
+            "SMS/GG: PSG version",
+            &Sn76489Version::ALL,
+        ),
+        sms_aspect_ratio: v.enum_option(
+            c"jgenesis_sms_aspect_ratio",
+            "SMS: Aspect ratio",
+            &SmsAspectRatio::ALL,
+            SmsAspectRatio::default(),
+        ),
+        gg_aspect_ratio: v.enum_option(
+            c"jgenesis_gg_aspect_ratio",
+            "GG: Aspect ratio",
+            &GgAspectRatio::ALL,
+            GgAspectRatio::default(),
+        ),
+        remove_sprite_limit: v.bool_option(
+            c"jgenesis_smsgg_remove_sprite_limit",
+            "SMS/GG: Remove sprite-per-scanline limit",
+            false,
+        ),
+        forced_region: v.optional_enum_option(
+            c"jgenesis_smsgg_region",
+            "SMS/GG: Region",
+            &SmsGgRegion::ALL,
+        ),
+        sms_crop_vertical_border: v.bool_option(
+            c"jgenesis_sms_crop_vertical_border",
+            "SMS: Crop vertical border",
+            true,
+        ),
+        sms_crop_left_border: v.bool_option(
+            c"jgenesis_sms_crop_left_border",
+            "SMS: Crop left border",
+            false,
+        ),
+        gg_frame_blending: v.bool_option(
+            c"jgenesis_gg_frame_blending",
+            "GG: Frame blending",
+            false,
+        ),
+        gg_use_sms_resolution: v.bool_option(
+            c"jgenesis_gg_use_sms_resolution",
+            "GG: Render at SMS resolution",
+            false,
+        ),
+        fm_sound_unit_enabled: v.bool_option(
+            c"jgenesis_sms_fm_sound_unit",
+            "SMS: FM sound unit",
+            true,
+        ),
+        z80_divider: NonZeroU32::new(smsgg_config::NATIVE_Z80_DIVIDER).unwrap(),
+        p1_controller_type: SmsGgControllerType::default(),
+        p2_controller_type: SmsGgControllerType::default(),
+    }
+}
+
+pub fn genesis_config(v: &mut impl OptionVisitor) -> GenesisEmulatorConfig {
+    const CONTROLLER_TYPES: [GenesisControllerType; 3] = [
+        GenesisControllerType::ThreeButton,
+        GenesisControllerType::SixButton,
+        GenesisControllerType::None,
+    ];
+
+    GenesisEmulatorConfig {
+        p1_controller_type: v.enum_option(
+            c"jgenesis_genesis_p1_controller_type",
+            "Genesis: Player 1 controller type",
+            &CONTROLLER_TYPES,
+            GenesisControllerType::default(),
+        ),
+        p2_controller_type: v.enum_option(
+            c"jgenesis_genesis_p2_controller_type",
+            "Genesis: Player 2 controller type",
+            &CONTROLLER_TYPES,
+            GenesisControllerType::default(),
+        ),
+        forced_timing_mode: v.optional_enum_option(
+            c"jgenesis_genesis_timing_mode",
+            "Genesis: Timing mode",
+            &TimingMode::ALL,
+        ),
+        forced_region: v.optional_enum_option(
+            c"jgenesis_genesis_region",
+            "Genesis: Region",
+            &GenesisRegion::ALL,
+        ),
+        aspect_ratio: v.enum_option(
+            c"jgenesis_genesis_aspect_ratio",
+            "Genesis: Aspect ratio",
+            &GenesisAspectRatio::ALL,
+            GenesisAspectRatio::default(),
+        ),
+        remove_sprite_limits: v.bool_option(
+            c"jgenesis_genesis_remove_sprite_limits",
+            "Genesis: Remove sprite limits",
+            false,
+        ),
+        non_linear_color_scale: v.bool_option(
+            c"jgenesis_genesis_non_linear_color_scale",
+            "Genesis: Non-linear color scale",
+            true,
+        ),
+        deinterlace: v.bool_option(c"jgenesis_genesis_deinterlace", "Genesis: Deinterlace", true),
+        render_vertical_border: v.bool_option(
+            c"jgenesis_genesis_render_vertical_border",
+            "Genesis: Render vertical border",
+            false,
+        ),
+        render_horizontal_border: v.bool_option(
+            c"jgenesis_genesis_render_horizontal_border",
+            "Genesis: Render horizontal border",
+            false,
+        ),
+        opn2_busy_behavior: v.enum_option(
+            c"jgenesis_genesis_opn2_busy_behavior",
+            "Genesis: YM2612 busy flag behavior",
+            &Opn2BusyBehavior::ALL,
+            Opn2BusyBehavior::default(),
+        ),
+        genesis_lpf_enabled: v.bool_option(
+            c"jgenesis_genesis_lpf",
+            "Genesis: Low-pass filter",
+            true,
+        ),
+        ym2612_2nd_lpf_enabled: v.bool_option(
+            c"jgenesis_genesis_2nd_lpf",
+            "Genesis: Model 2 second low-pass filter",
+            false,
+        ),
+        ..GenesisEmulatorConfig::default()
+    }
+}
+
+pub fn sega_cd_config(v: &mut impl OptionVisitor) -> SegaCdEmulatorConfig {
+    SegaCdEmulatorConfig {
+        genesis: genesis_config(v),
+        pcm_interpolation: v.enum_option(
+            c"jgenesis_scd_pcm_interpolation",
+            "Sega CD: PCM interpolation",
+            &PcmInterpolation::ALL,
+            PcmInterpolation::default(),
+        ),
+        enable_ram_cartridge: v.bool_option(
+            c"jgenesis_scd_ram_cartridge",
+            "Sega CD: RAM cartridge",
+            true,
+        ),
+        load_disc_into_ram: v.bool_option(
+            c"jgenesis_scd_load_disc_into_ram",
+            "Sega CD: Load disc into RAM",
+            false,
+        ),
+        disc_drive_speed: NonZeroU16::new(1).unwrap(),
+        sub_cpu_divider: NonZeroU64::new(genesis_config::NATIVE_SUB_CPU_DIVIDER).unwrap(),
+        pcm_lpf_enabled: v.bool_option(
+            c"jgenesis_scd_pcm_lpf",
+            "Sega CD: PCM low-pass filter",
+            true,
+        ),
+        pcm_lpf_cutoff: genesis_config::DEFAULT_PCM_LPF_CUTOFF,
+        apply_genesis_lpf_to_pcm: false,
+        apply_genesis_lpf_to_cd_da: false,
+        pcm_enabled: true,
+        cd_audio_enabled: true,
+        pcm_volume_adjustment_db: 0.0,
+        cd_volume_adjustment_db: 0.0,
+    }
+}
+
+pub fn sega_32x_config(v: &mut impl OptionVisitor) -> Sega32XEmulatorConfig {
+    Sega32XEmulatorConfig {
+        genesis: genesis_config(v),
+        video_out: v.enum_option(
+            c"jgenesis_32x_video_out",
+            "32X: Video output",
+            &S32XVideoOut::ALL,
+            S32XVideoOut::default(),
+        ),
+        darken_genesis_colors: v.bool_option(
+            c"jgenesis_32x_darken_genesis_colors",
+            "32X: Darken Genesis colors",
+            true,
+        ),
+        color_tint: v.enum_option(
+            c"jgenesis_32x_color_tint",
+            "32X: Color tint",
+            &S32XColorTint::ALL,
+            S32XColorTint::default(),
+        ),
+        pwm_resampling: v.enum_option(
+            c"jgenesis_32x_pwm_resampling",
+            "32X: PWM resampling",
+            &S32XPwmResampling::ALL,
+            S32XPwmResampling::default(),
+        ),
+        ..Sega32XEmulatorConfig::default()
+    }
+}
+
+pub fn nes_config(v: &mut impl OptionVisitor) -> NesEmulatorConfig {
+    NesEmulatorConfig {
+        forced_timing_mode: v.optional_enum_option(
+            c"jgenesis_nes_timing_mode",
+            "NES: Timing mode",
+            &TimingMode::ALL,
+        ),
+        aspect_ratio: v.enum_option(
+            c"jgenesis_nes_aspect_ratio",
+            "NES: Aspect ratio",
+            &NesAspectRatio::ALL,
+            NesAspectRatio::default(),
+        ),
+        palette: NesPalette::default(),
+        ntsc_crop_vertical_overscan: v.bool_option(
+            c"jgenesis_nes_ntsc_crop_vertical_overscan",
+            "NES: Crop NTSC vertical overscan",
+            true,
+        ),
+        overscan: Overscan::default(),
+        remove_sprite_limit: v.bool_option(
+            c"jgenesis_nes_remove_sprite_limit",
+            "NES: Remove sprite-per-scanline limit",
+            false,
+        ),
+        pal_black_border: v.bool_option(
+            c"jgenesis_nes_pal_black_border",
+            "NES: Emulate PAL black border",
+            false,
+        ),
+        silence_ultrasonic_triangle_output: v.bool_option(
+            c"jgenesis_nes_silence_ultrasonic_triangle",
+            "NES: Silence ultrasonic triangle output",
+            false,
+        ),
+        audio_resampler: v.enum_option(
+            c"jgenesis_nes_audio_resampler",
+            "NES: Audio resampler",
+            &NesAudioResampler::ALL,
+            NesAudioResampler::default(),
+        ),
+        audio_refresh_rate_adjustment: false,
+        allow_opposing_joypad_inputs: v.bool_option(
+            c"jgenesis_nes_allow_opposing_inputs",
+            "NES: Allow opposing directional inputs",
+            false,
+        ),
+        dma_dummy_joy_reads: v.bool_option(
+            c"jgenesis_nes_dma_dummy_joy_reads",
+            "NES: DMA dummy controller reads",
+            true,
+        ),
+    }
+}
+
+pub fn snes_config(v: &mut impl OptionVisitor) -> SnesEmulatorConfig {
+    SnesEmulatorConfig {
+        forced_timing_mode: v.optional_enum_option(
+            c"jgenesis_snes_timing_mode",
+            "SNES: Timing mode",
+            &TimingMode::ALL,
+        ),
+        aspect_ratio: v.enum_option(
+            c"jgenesis_snes_aspect_ratio",
+            "SNES: Aspect ratio",
+            &SnesAspectRatio::ALL,
+            SnesAspectRatio::default(),
+        ),
+        deinterlace: v.bool_option(c"jgenesis_snes_deinterlace", "SNES: Deinterlace", true),
+        audio_interpolation: v.enum_option(
+            c"jgenesis_snes_audio_interpolation",
+            "SNES: Audio interpolation",
+            &AudioInterpolationMode::ALL,
+            AudioInterpolationMode::default(),
+        ),
+        ..SnesEmulatorConfig::default()
+    }
+}
+
+pub fn gb_config(v: &mut impl OptionVisitor) -> GameBoyEmulatorConfig {
+    let gbc_color_correction = v.enum_option(
+        c"jgenesis_gb_gbc_color_correction",
+        "GB: GBC color correction",
+        &GbcColorCorrection::ALL,
+        GbcColorCorrection::default(),
+    );
+
+    GameBoyEmulatorConfig {
+        force_dmg_mode: v.bool_option(c"jgenesis_gb_force_dmg_mode", "GB: Force DMG mode", false),
+        force_cgb_mode: v.bool_option(c"jgenesis_gb_force_cgb_mode", "GB: Force CGB mode", false),
+        pretend_to_be_gba: false,
+        printer_connected: false,
+        aspect_ratio: v.enum_option(
+            c"jgenesis_gb_aspect_ratio",
+            "GB: Aspect ratio",
+            &GbAspectRatio::ALL,
+            GbAspectRatio::default(),
+        ),
+        gb_palette: v.enum_option(
+            c"jgenesis_gb_palette",
+            "GB: DMG palette",
+            &GbPalette::ALL,
+            GbPalette::default(),
+        ),
+        // Black and white
+        gb_custom_palette: [
+            (0xFF, 0xFF, 0xFF),
+            (0xAA, 0xAA, 0xAA),
+            (0x55, 0x55, 0x55),
+            (0x00, 0x00, 0x00),
+        ],
+        gbc_color_correction: match gbc_color_correction {
+            GbcColorCorrection::None => ColorCorrection::None,
+            GbcColorCorrection::GbcLcd => ColorCorrection::GbcLcd {
+                screen_gamma: FiniteF32::try_from(GBC_CORRECTION_GAMMA).unwrap(),
+            },
+            GbcColorCorrection::GbaLcd => ColorCorrection::GbaLcd {
+                screen_gamma: FiniteF32::try_from(GBA_CORRECTION_GAMMA).unwrap(),
+            },
+        },
+        frame_blending: v.bool_option(c"jgenesis_gb_frame_blending", "GB: Frame blending", true),
+        audio_resampler: v.enum_option(
+            c"jgenesis_gb_audio_resampler",
+            "GB: Audio resampler",
+            &GbAudioResampler::ALL,
+            GbAudioResampler::default(),
+        ),
+        audio_60hz_hack: false,
+    }
+}
+
+pub fn gba_config(v: &mut impl OptionVisitor) -> GbaEmulatorConfig {
+    let color_correction = v.enum_option(
+        c"jgenesis_gba_color_correction",
+        "GBA: Color correction",
+        &GbaColorCorrection::ALL,
+        GbaColorCorrection::default(),
+    );
+
+    GbaEmulatorConfig {
+        skip_bios_animation: v.bool_option(
+            c"jgenesis_gba_skip_bios_animation",
+            "GBA: Skip BIOS intro animation",
+            false,
+        ),
+        aspect_ratio: v.enum_option(
+            c"jgenesis_gba_aspect_ratio",
+            "GBA: Aspect ratio",
+            &GbaAspectRatio::ALL,
+            GbaAspectRatio::default(),
+        ),
+        color_correction: match color_correction {
+            GbaColorCorrection::None => ColorCorrection::None,
+            GbaColorCorrection::GbaLcd => ColorCorrection::GbaLcd {
+                screen_gamma: FiniteF32::try_from(GBA_CORRECTION_GAMMA).unwrap(),
+            },
+        },
+        frame_blending: v.bool_option(c"jgenesis_gba_frame_blending", "GBA: Frame blending", true),
+        forced_save_memory_type: v.optional_enum_option(
+            c"jgenesis_gba_save_memory_type",
+            "GBA: Save memory type",
+            &GbaSaveMemory::ALL,
+        ),
+        audio: GbaAudioConfig {
+            audio_interpolation: v.enum_option(
+                c"jgenesis_gba_audio_interpolation",
+                "GBA: Audio interpolation",
+                &GbaAudioInterpolation::ALL,
+                GbaAudioInterpolation::default(),
+            ),
+            ..GbaAudioConfig::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn option_keys_are_unique() {
+        let definitions = OptionDefinitions::all();
+
+        let mut keys: Vec<_> = definitions.definitions.iter().map(|&(key, _)| key).collect();
+        let len = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), len);
+    }
+
+    #[test]
+    fn default_value_listed_first() {
+        let definitions = OptionDefinitions::all();
+        let (_, definition) = definitions
+            .definitions
+            .iter()
+            .find(|&&(key, _)| key == c"jgenesis_sms_crop_vertical_border")
+            .unwrap();
+
+        assert_eq!(definition.to_str().unwrap(), "SMS: Crop vertical border; enabled|disabled");
+    }
+
+    #[test]
+    fn reads_selected_values() {
+        let selected: HashMap<&CStr, String> = [
+            (c"jgenesis_genesis_aspect_ratio", GenesisAspectRatio::SquarePixels.to_string()),
+            (c"jgenesis_genesis_region", GenesisRegion::Japan.to_string()),
+            (c"jgenesis_genesis_deinterlace", DISABLED.into()),
+            (c"jgenesis_scd_pcm_interpolation", "not a valid value".into()),
+        ]
+        .into_iter()
+        .collect();
+
+        let config = sega_cd_config(&mut OptionValues(|key: &CStr| selected.get(key).cloned()));
+
+        assert_eq!(config.genesis.aspect_ratio, GenesisAspectRatio::SquarePixels);
+        assert_eq!(config.genesis.forced_region, Some(GenesisRegion::Japan));
+        assert!(!config.genesis.deinterlace);
+        assert_eq!(config.genesis.forced_timing_mode, None);
+        assert_eq!(config.pcm_interpolation, PcmInterpolation::default());
+    }
+}
@@ -0,0 +1,219 @@
+//! [`SaveWriter`] implementation for libretro frontends.
+//!
+//! The game's primary save file (persisted by every backend under the "sav" extension) is exposed
+//! raw through `RETRO_MEMORY_SAVE_RAM`, sized to the cartridge's actual save memory, so that the
+//! frontend's `.srm` files are interchangeable with other emulators. The region is allocated once
+//! at load time with the largest size the save file can have and only ever shrinks afterwards, so
+//! pointers returned to the frontend stay valid. libretro only exposes one
+//! save RAM region per game, so any auxiliary save files (RTC state, Sega CD RAM cartridge, FDS
+//! disk changes, etc.) are read from and written to the frontend's save directory instead.
+
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use jgenesis_common::frontend::SaveWriter;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::{fs, io};
+use thiserror::Error;
+
+macro_rules! bincode_config {
+    () => {
+        bincode::config::standard()
+            .with_little_endian()
+            .with_fixed_int_encoding()
+            .with_limit::<{ 10 * 1024 * 1024 }>()
+    };
+}
+
+pub(crate) use bincode_config;
+
+const SAVE_RAM_EXTENSION: &str = "sav";
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("No save file found for extension '{0}'")]
+    NotFound(String),
+    #[error("Save file is {len} bytes, save RAM region is only {capacity} bytes")]
+    SaveRamTooLarge { len: usize, capacity: usize },
+    #[error("I/O error accessing save file '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error encoding save file: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("Error decoding save file: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+pub struct RetroSaveWriter {
+    save_ram: Box<[u8]>,
+    save_ram_len: usize,
+    initial_save_ram: Box<[u8]>,
+    base_path: PathBuf,
+}
+
+impl RetroSaveWriter {
+    /// Create a save writer with empty save RAM. Auxiliary save files are stored at `base_path`
+    /// with the file's extension appended, e.g. `<save dir>/<ROM file stem>.rtc`.
+    pub fn new(base_path: PathBuf) -> Self {
+        Self { save_ram: Box::new([]), save_ram_len: 0, initial_save_ram: Box::new([]), base_path }
+    }
+
+    pub fn save_ram(&self) -> &[u8] {
+        &self.save_ram[..self.save_ram_len]
+    }
+
+    /// The save RAM region exposed to the frontend through `retro_get_memory_data`.
+    pub fn save_ram_mut(&mut self) -> &mut [u8] {
+        &mut self.save_ram[..self.save_ram_len]
+    }
+
+    /// Allocate the save RAM region with the given initial contents, which should be sized for the
+    /// largest save file the game can produce. The frontend writes its save file over the start of
+    /// the region after the game is loaded.
+    pub fn init_save_ram(&mut self, initial: Vec<u8>) {
+        self.save_ram = initial.into_boxed_slice();
+        self.save_ram_len = self.save_ram.len();
+        self.initial_save_ram = self.save_ram.clone();
+    }
+
+    /// Returns whether the save RAM region no longer contains the contents it was initialized
+    /// with, i.e. the frontend has written a save file into it.
+    pub fn save_ram_modified(&self) -> bool {
+        self.save_ram() != &*self.initial_save_ram
+    }
+
+    fn auxiliary_path(&self, extension: &str) -> PathBuf {
+        let mut path = OsString::from(self.base_path.as_os_str());
+        path.push(".");
+        path.push(extension);
+        path.into()
+    }
+}
+
+impl SaveWriter for RetroSaveWriter {
+    type Err = SaveError;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        if extension == SAVE_RAM_EXTENSION {
+            if self.save_ram_len == 0 {
+                return Err(SaveError::NotFound(extension.into()));
+            }
+            return Ok(self.save_ram().to_vec());
+        }
+
+        let path = self.auxiliary_path(extension);
+        fs::read(&path).map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound => SaveError::NotFound(extension.into()),
+            _ => SaveError::Io { path: path.display().to_string(), source },
+        })
+    }
+
+    fn persist_bytes(&mut self, extension: &str, bytes: &[u8]) -> Result<(), Self::Err> {
+        if extension == SAVE_RAM_EXTENSION {
+            // Never reallocate; the frontend may hold a pointer to the region
+            let Some(region) = self.save_ram.get_mut(..bytes.len()) else {
+                return Err(SaveError::SaveRamTooLarge {
+                    len: bytes.len(),
+                    capacity: self.save_ram.len(),
+                });
+            };
+            region.copy_from_slice(bytes);
+            self.save_ram_len = bytes.len();
+            return Ok(());
+        }
+
+        let path = self.auxiliary_path(extension);
+        fs::write(&path, bytes)
+            .map_err(|source| SaveError::Io { path: path.display().to_string(), source })
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        let bytes = self.load_bytes(extension)?;
+        let (value, _) = bincode::decode_from_slice(&bytes, bincode_config!())?;
+        Ok(value)
+    }
+
+    fn persist_serialized<E: Encode>(&mut self, extension: &str, data: E) -> Result<(), Self::Err> {
+        let bytes = bincode::encode_to_vec(data, bincode_config!())?;
+        self.persist_bytes(extension, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jgenesis-libretro-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_ram_is_raw() {
+        let dir = test_dir("save-ram");
+        let mut writer = RetroSaveWriter::new(dir.join("game"));
+        assert!(matches!(writer.load_bytes("sav"), Err(SaveError::NotFound(_))));
+
+        writer.init_save_ram(vec![0xFF; 8192]);
+        assert_eq!(writer.save_ram().len(), 8192);
+        assert!(!writer.save_ram_modified());
+
+        // Frontend writes the .srm contents into the region before the first frame
+        writer.save_ram_mut()[..4].copy_from_slice(&[1, 2, 3, 4]);
+        assert!(writer.save_ram_modified());
+        assert_eq!(&writer.load_bytes("sav").unwrap()[..5], &[1, 2, 3, 4, 0xFF]);
+
+        writer.persist_bytes("sav", &[5; 8192]).unwrap();
+        assert_eq!(writer.save_ram(), &[5; 8192]);
+        assert!(!dir.join("game.sav").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_ram_truncates_without_moving() {
+        let mut writer = RetroSaveWriter::new(PathBuf::new());
+
+        // e.g. GBA cartridge where the save memory type has not been detected yet
+        writer.init_save_ram(vec![0xFF; 128 * 1024]);
+        let ptr = writer.save_ram_mut().as_mut_ptr();
+
+        writer.persist_bytes("sav", &[7; 512]).unwrap();
+        assert_eq!(writer.save_ram(), &[7; 512]);
+        assert_eq!(writer.save_ram_mut().as_mut_ptr(), ptr);
+
+        writer.persist_bytes("sav", &[8; 32 * 1024]).unwrap();
+        assert_eq!(writer.save_ram(), &[8; 32 * 1024]);
+        assert_eq!(writer.save_ram_mut().as_mut_ptr(), ptr);
+
+        assert!(matches!(
+            writer.persist_bytes("sav", &[0; 256 * 1024]),
+            Err(SaveError::SaveRamTooLarge { len: 262144, capacity: 131072 })
+        ));
+    }
+
+    #[test]
+    fn auxiliary_files_on_disk() {
+        let dir = test_dir("auxiliary");
+        let base_path = dir.join("Game (v1.1)");
+        let mut writer = RetroSaveWriter::new(base_path.clone());
+        assert!(matches!(writer.load_serialized::<u32>("rtc"), Err(SaveError::NotFound(_))));
+
+        writer.persist_serialized("rtc", 0x1234_5678_u32).unwrap();
+        writer.persist_bytes("ramc", &[1, 2, 3]).unwrap();
+        assert!(dir.join("Game (v1.1).rtc").exists());
+        assert_eq!(fs::read(dir.join("Game (v1.1).ramc")).unwrap(), vec![1, 2, 3]);
+        assert!(writer.save_ram().is_empty());
+
+        let mut loaded = RetroSaveWriter::new(base_path);
+        assert_eq!(loaded.load_serialized::<u32>("rtc").unwrap(), 0x1234_5678);
+        assert_eq!(loaded.load_bytes("ramc").unwrap(), vec![1, 2, 3]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
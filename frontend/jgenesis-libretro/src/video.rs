@@ -0,0 +1,158 @@
+//! [`Renderer`] implementation that converts frames to the XRGB8888 format expected by
+//! `retro_video_refresh`.
+//!
+//! Color correction and frame blending are applied in software here because the libretro frontend
+//! owns the GPU, unlike the native and web frontends where these run as shader passes.
+
+use crate::ffi::GameGeometry;
+use jgenesis_common::frontend::{
+    Color, ColorCorrection, FiniteF32, FrameSize, RenderFrameOptions, Renderer,
+};
+use std::convert::Infallible;
+
+// Large enough for every backend's largest frame (deinterlaced Genesis with borders, SNES hi-res)
+const MAX_WIDTH: u32 = 1024;
+const MAX_HEIGHT: u32 = 1024;
+
+// Same matrices as the GB/GBA color correction shader in jgenesis-renderer
+const GBC_CORRECTION: [[f32; 3]; 3] =
+    [[0.78824, 0.12157, 0.0], [0.025, 0.72941, 0.275], [0.12039, 0.12157, 0.82]];
+const GBA_CORRECTION: [[f32; 3]; 3] =
+    [[0.845, 0.17, 0.015], [0.09, 0.68, 0.23], [0.16, 0.085, 0.755]];
+
+struct GammaTable {
+    screen_gamma: FiniteF32,
+    table: Box<[f32; 256]>,
+}
+
+impl GammaTable {
+    fn new(screen_gamma: FiniteF32) -> Self {
+        let exponent = f32::from(screen_gamma) / 2.2;
+        let table = Box::new(std::array::from_fn(|i| (i as f32 / 255.0).powf(exponent)));
+        Self { screen_gamma, table }
+    }
+}
+
+pub struct RetroRenderer {
+    frame: Vec<u32>,
+    prev_frame: Vec<u32>,
+    frame_size: FrameSize,
+    prev_frame_size: FrameSize,
+    pixel_aspect_ratio: Option<f64>,
+    gamma_table: Option<GammaTable>,
+    frame_rendered: bool,
+}
+
+impl RetroRenderer {
+    pub fn new() -> Self {
+        Self {
+            frame: Vec::with_capacity((MAX_WIDTH * MAX_HEIGHT) as usize),
+            prev_frame: Vec::with_capacity((MAX_WIDTH * MAX_HEIGHT) as usize),
+            frame_size: FrameSize { width: 320, height: 224 },
+            prev_frame_size: FrameSize { width: 0, height: 0 },
+            pixel_aspect_ratio: None,
+            gamma_table: None,
+            frame_rendered: false,
+        }
+    }
+
+    pub fn geometry(&self) -> GameGeometry {
+        let FrameSize { width, height } = self.frame_size;
+
+        // Aspect ratios <= 0 tell the frontend to use width / height
+        let aspect_ratio = self
+            .pixel_aspect_ratio
+            .map_or(0.0, |par| (f64::from(width) * par / f64::from(height)) as f32);
+
+        GameGeometry {
+            base_width: width,
+            base_height: height,
+            max_width: MAX_WIDTH,
+            max_height: MAX_HEIGHT,
+            aspect_ratio,
+        }
+    }
+
+    /// Returns the most recently rendered frame, if a frame has been rendered since the last call.
+    pub fn take_frame(&mut self) -> Option<(&[u32], FrameSize)> {
+        if !self.frame_rendered {
+            return None;
+        }
+
+        self.frame_rendered = false;
+        Some((&self.frame, self.frame_size))
+    }
+
+    fn apply_color_correction(&mut self, color_correction: ColorCorrection) {
+        let (matrix, screen_gamma) = match color_correction {
+            ColorCorrection::None => return,
+            ColorCorrection::GbcLcd { screen_gamma } => (GBC_CORRECTION, screen_gamma),
+            ColorCorrection::GbaLcd { screen_gamma } => (GBA_CORRECTION, screen_gamma),
+        };
+
+        if self.gamma_table.as_ref().is_none_or(|table| table.screen_gamma != screen_gamma) {
+            self.gamma_table = Some(GammaTable::new(screen_gamma));
+        }
+        let table = &self.gamma_table.as_ref().unwrap().table;
+
+        for pixel in &mut self.frame {
+            let [b, g, r, _] = pixel.to_le_bytes();
+            let rgb = [r, g, b].map(|component| table[component as usize]);
+
+            let [r, g, b] = matrix.map(|row| {
+                let value = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+                (value.clamp(0.0, 1.0) * 255.0).round() as u8
+            });
+
+            *pixel = u32::from_le_bytes([b, g, r, 0]);
+        }
+    }
+
+    // Blends with the previous frame as output by the emulator, not with the previous blended frame
+    fn apply_frame_blending(&mut self) {
+        if self.prev_frame_size != self.frame_size {
+            self.prev_frame.clone_from(&self.frame);
+            self.prev_frame_size = self.frame_size;
+            return;
+        }
+
+        for (pixel, prev) in self.frame.iter_mut().zip(&mut self.prev_frame) {
+            let current = *pixel;
+            // Average each 8-bit component without carrying between components
+            *pixel = (current & *prev) + (((current ^ *prev) & 0xFEFEFE) >> 1);
+            *prev = current;
+        }
+    }
+}
+
+impl Renderer for RetroRenderer {
+    type Err = Infallible;
+
+    fn render_frame(
+        &mut self,
+        frame_buffer: &[Color],
+        frame_size: FrameSize,
+        _target_fps: f64,
+        options: RenderFrameOptions,
+    ) -> Result<(), Self::Err> {
+        let len = (frame_size.width * frame_size.height) as usize;
+        self.frame.clear();
+        self.frame.extend(frame_buffer[..len].iter().map(|color| {
+            (u32::from(color.r) << 16) | (u32::from(color.g) << 8) | u32::from(color.b)
+        }));
+        self.frame_size = frame_size;
+        self.pixel_aspect_ratio = options.pixel_aspect_ratio.map(f64::from);
+
+        self.apply_color_correction(options.color_correction);
+
+        if options.frame_blending {
+            self.apply_frame_blending();
+        } else {
+            self.prev_frame_size = FrameSize { width: 0, height: 0 };
+        }
+
+        self.frame_rendered = true;
+
+        Ok(())
+    }
+}
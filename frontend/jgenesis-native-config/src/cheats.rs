@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheatConfig {
+    pub code: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "true_fn")]
+    pub enabled: bool,
+}
+
+fn true_fn() -> bool {
+    true
+}
+
+/// Cheat codes persisted per game, keyed by ROM file name without extension.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CheatsAppConfig {
+    #[serde(default)]
+    pub games: BTreeMap<String, Vec<CheatConfig>>,
+}
+
+impl CheatsAppConfig {
+    #[must_use]
+    pub fn game_key(rom_path: &Path) -> String {
+        rom_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn game_cheats(&self, rom_path: &Path) -> &[CheatConfig] {
+        self.games.get(&Self::game_key(rom_path)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn game_cheats_mut(&mut self, rom_path: &Path) -> &mut Vec<CheatConfig> {
+        self.games.entry(Self::game_key(rom_path)).or_default()
+    }
+
+    /// Remove entries for games that no longer have any cheats.
+    pub fn remove_empty(&mut self) {
+        self.games.retain(|_, cheats| !cheats.is_empty());
+    }
+
+    #[must_use]
+    pub fn enabled_codes(&self, rom_path: &Path) -> Vec<String> {
+        self.game_cheats(rom_path)
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .map(|cheat| cheat.code.clone())
+            .collect()
+    }
+}
@@ -304,8 +304,10 @@ pub struct CommonAppConfig {
     pub load_recent_state_at_launch: bool,
     #[serde(default = "default_fast_forward_multiplier")]
     pub fast_forward_multiplier: u64,
-    #[serde(default = "default_rewind_buffer_length")]
-    pub rewind_buffer_length_seconds: u64,
+    #[serde(default = "default_rewind_buffer_size_mb")]
+    pub rewind_buffer_size_mb: u64,
+    #[serde(default = "default_rewind_frame_interval")]
+    pub rewind_frame_interval: u64,
     #[serde(default)]
     pub pause_emulator: PauseEmulator,
     #[serde(default)]
@@ -374,6 +376,10 @@ fn default_fast_forward_multiplier() -> u64 {
     2
 }
 
-fn default_rewind_buffer_length() -> u64 {
-    10
+pub(crate) fn default_rewind_buffer_size_mb() -> u64 {
+    256
+}
+
+fn default_rewind_frame_interval() -> u64 {
+    1
 }
@@ -11,6 +11,8 @@ pub struct GameBoyAppConfig {
     #[serde(default)]
     pub pretend_to_be_gba: bool,
     #[serde(default)]
+    pub printer_connected: bool,
+    #[serde(default)]
     pub dmg_boot_rom: bool,
     #[serde(default)]
     pub cgb_boot_rom: bool,
@@ -19,6 +21,8 @@ pub struct GameBoyAppConfig {
     #[serde(default)]
     pub cgb_boot_rom_path: Option<PathBuf>,
     #[serde(default)]
+    pub camera_image_path: Option<PathBuf>,
+    #[serde(default)]
     pub aspect_ratio: GbAspectRatio,
     #[serde(default)]
     pub gb_palette: GbPalette,
@@ -67,6 +67,8 @@ pub struct GenesisAppConfig {
     pub ym2612_volume_adjustment_db: f64,
     #[serde(default)]
     pub psg_volume_adjustment_db: f64,
+    #[serde(default)]
+    pub lock_on_rom_path: Option<PathBuf>,
 }
 
 const fn true_fn() -> bool {
@@ -185,6 +185,8 @@ pub enum Hotkey {
     Rewind,
     ToggleOverclocking,
     OpenDebugger,
+    ChangeDiskSide,
+    ToggleMovieReadOnly,
     SaveState,
     LoadState,
     NextSaveStateSlot,
@@ -230,6 +232,8 @@ pub enum CompactHotkey {
     Rewind,
     ToggleOverclocking,
     OpenDebugger,
+    ChangeDiskSide,
+    ToggleMovieReadOnly,
 }
 
 impl Hotkey {
@@ -252,6 +256,8 @@ impl Hotkey {
             Self::Rewind => CompactHotkey::Rewind,
             Self::ToggleOverclocking => CompactHotkey::ToggleOverclocking,
             Self::OpenDebugger => CompactHotkey::OpenDebugger,
+            Self::ChangeDiskSide => CompactHotkey::ChangeDiskSide,
+            Self::ToggleMovieReadOnly => CompactHotkey::ToggleMovieReadOnly,
             Self::SaveStateSlot0 => CompactHotkey::SaveStateSlot(0),
             Self::SaveStateSlot1 => CompactHotkey::SaveStateSlot(1),
             Self::SaveStateSlot2 => CompactHotkey::SaveStateSlot(2),
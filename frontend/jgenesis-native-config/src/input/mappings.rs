@@ -8,7 +8,7 @@ use nes_config::NesButton;
 use sdl3::keyboard::Keycode;
 use sdl3::mouse::MouseButton;
 use serde::{Deserialize, Serialize};
-use smsgg_config::SmsGgButton;
+use smsgg_config::{SmsGgButton, SmsGgControllerType};
 use snes_config::SnesButton;
 use std::fmt::Formatter;
 
@@ -118,13 +118,17 @@ macro_rules! impl_to_mapping_vec {
 
 macro_rules! impl_to_turbo_mapping_vec {
     ($button:ty) => {
+        impl_to_turbo_mapping_vec!($button, [p1_turbo: One, p2_turbo: Two]);
+    };
+    ($button:ty, [$($field:ident: $player:ident),* $(,)?]) => {
         #[must_use]
         pub fn to_turbo_mapping_vec(&self) -> ButtonMappingVec<'_, $button> {
             let mut out = Vec::new();
 
             for mapping in [&self.mapping_1, &self.mapping_2] {
-                mapping.p1_turbo.to_mapping_vec(Player::One, &mut out);
-                mapping.p2_turbo.to_mapping_vec(Player::Two, &mut out);
+                $(
+                    mapping.$field.to_mapping_vec(Player::$player, &mut out);
+                )*
             }
 
             out
@@ -172,6 +176,43 @@ impl SmsGgControllerMapping {
     }
 }
 
+define_controller_mapping!(SmsGgLightPhaserMapping, SmsGgButton, [
+    trigger: LightPhaserTrigger,
+]);
+
+impl SmsGgLightPhaserMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self { trigger: Some(vec![GenericInput::Mouse(MouseButton::Left)]) }
+    }
+}
+
+define_controller_mapping!(SmsGgPaddleMapping, SmsGgButton, [
+    button: PaddleButton,
+]);
+
+impl SmsGgPaddleMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self { button: Some(vec![GenericInput::Mouse(MouseButton::Left)]) }
+    }
+}
+
+define_controller_mapping!(SmsGgSportsPadMapping, SmsGgButton, [
+    button_1: SportsPadButton1,
+    button_2: SportsPadButton2,
+]);
+
+impl SmsGgSportsPadMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self {
+            button_1: Some(vec![GenericInput::Mouse(MouseButton::Left)]),
+            button_2: Some(vec![GenericInput::Mouse(MouseButton::Right)]),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ConfigDisplay)]
 pub struct SmsGgInputMapping {
     #[serde(default)]
@@ -184,12 +225,24 @@ pub struct SmsGgInputMapping {
     pub p2_turbo: SmsGgControllerMapping,
     #[cfg_display(debug_fmt)]
     pub pause: Option<Vec<GenericInput>>,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub light_phaser: SmsGgLightPhaserMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub paddle: SmsGgPaddleMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub sports_pad: SmsGgSportsPadMapping,
 }
 
 impl SmsGgInputMapping {
     pub fn to_mapping_vec<'a>(&'a self, out: &mut ButtonMappingVec<'a, SmsGgButton>) {
         self.p1.to_mapping_vec(Player::One, out);
         self.p2.to_mapping_vec(Player::Two, out);
+        self.light_phaser.to_mapping_vec(Player::One, out);
+        self.paddle.to_mapping_vec(Player::One, out);
+        self.sports_pad.to_mapping_vec(Player::One, out);
 
         if let Some(pause) = &self.pause {
             out.push(((SmsGgButton::Pause, Player::One), pause));
@@ -199,6 +252,10 @@ impl SmsGgInputMapping {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ConfigDisplay)]
 pub struct SmsGgInputConfig {
+    #[serde(default)]
+    pub p1_type: SmsGgControllerType,
+    #[serde(default)]
+    pub p2_type: SmsGgControllerType,
     #[serde(default = "default_smsgg_mapping_1")]
     #[cfg_display(indent_nested)]
     pub mapping_1: SmsGgInputMapping,
@@ -220,12 +277,20 @@ fn default_smsgg_mapping_1() -> SmsGgInputMapping {
         p1_turbo: SmsGgControllerMapping::default(),
         p2_turbo: SmsGgControllerMapping::default(),
         pause: key_input!(Return),
+        light_phaser: SmsGgLightPhaserMapping::mouse(),
+        paddle: SmsGgPaddleMapping::mouse(),
+        sports_pad: SmsGgSportsPadMapping::mouse(),
     }
 }
 
 impl Default for SmsGgInputConfig {
     fn default() -> Self {
-        Self { mapping_1: default_smsgg_mapping_1(), mapping_2: SmsGgInputMapping::default() }
+        Self {
+            p1_type: SmsGgControllerType::default(),
+            p2_type: SmsGgControllerType::default(),
+            mapping_1: default_smsgg_mapping_1(),
+            mapping_2: SmsGgInputMapping::default(),
+        }
     }
 }
 
@@ -282,6 +347,46 @@ impl GenesisControllerMapping {
     }
 }
 
+define_controller_mapping!(GenesisMouseMapping, GenesisButton, [
+    left: MouseLeft,
+    right: MouseRight,
+    middle: MouseMiddle,
+    start: MouseStart,
+]);
+
+impl GenesisMouseMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self {
+            left: Some(vec![GenericInput::Mouse(MouseButton::Left)]),
+            right: Some(vec![GenericInput::Mouse(MouseButton::Right)]),
+            middle: Some(vec![GenericInput::Mouse(MouseButton::Middle)]),
+            start: key_input!(Return),
+        }
+    }
+}
+
+define_controller_mapping!(GenesisLightGunMapping, GenesisButton, [
+    trigger: LightGunTrigger,
+    a: LightGunA,
+    b: LightGunB,
+    start: LightGunStart,
+    force_offscreen: LightGunForceOffscreen,
+]);
+
+impl GenesisLightGunMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self {
+            trigger: Some(vec![GenericInput::Mouse(MouseButton::Left)]),
+            a: Some(vec![GenericInput::Mouse(MouseButton::Middle)]),
+            b: Some(vec![GenericInput::Mouse(MouseButton::X1)]),
+            start: key_input!(Return),
+            force_offscreen: Some(vec![GenericInput::Mouse(MouseButton::Right)]),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ConfigDisplay)]
 pub struct GenesisInputMapping {
     #[serde(default)]
@@ -292,16 +397,38 @@ pub struct GenesisInputMapping {
     pub p2: GenesisControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
+    pub p3: GenesisControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4: GenesisControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
     pub p1_turbo: GenesisControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
     pub p2_turbo: GenesisControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p3_turbo: GenesisControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4_turbo: GenesisControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub mouse: GenesisMouseMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub light_gun: GenesisLightGunMapping,
 }
 
 impl GenesisInputMapping {
     pub fn to_mapping_vec<'a>(&'a self, out: &mut ButtonMappingVec<'a, GenesisButton>) {
         self.p1.to_mapping_vec(Player::One, out);
         self.p2.to_mapping_vec(Player::Two, out);
+        self.p3.to_mapping_vec(Player::Three, out);
+        self.p4.to_mapping_vec(Player::Four, out);
+        self.mouse.to_mapping_vec(Player::One, out);
+        self.light_gun.to_mapping_vec(Player::One, out);
     }
 }
 
@@ -322,15 +449,20 @@ pub struct GenesisInputConfig {
 impl GenesisInputConfig {
     impl_to_mapping_vec!(GenesisButton);
 
-    impl_to_turbo_mapping_vec!(GenesisButton);
+    impl_to_turbo_mapping_vec!(GenesisButton, [
+        p1_turbo: One,
+        p2_turbo: Two,
+        p3_turbo: Three,
+        p4_turbo: Four,
+    ]);
 }
 
 fn default_genesis_mapping_1() -> GenesisInputMapping {
     GenesisInputMapping {
         p1: GenesisControllerMapping::keyboard_arrows(),
-        p2: GenesisControllerMapping::default(),
-        p1_turbo: GenesisControllerMapping::default(),
-        p2_turbo: GenesisControllerMapping::default(),
+        mouse: GenesisMouseMapping::mouse(),
+        light_gun: GenesisLightGunMapping::mouse(),
+        ..GenesisInputMapping::default()
     }
 }
 
@@ -401,6 +533,52 @@ impl NesZapperMapping {
     }
 }
 
+define_controller_mapping!(NesVausMapping, NesButton, [
+    button: VausButton,
+]);
+
+impl NesVausMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self { button: Some(vec![GenericInput::Mouse(MouseButton::Left)]) }
+    }
+}
+
+define_controller_mapping!(NesPowerPadMapping, NesButton, [
+    button_1: PowerPad1,
+    button_2: PowerPad2,
+    button_3: PowerPad3,
+    button_4: PowerPad4,
+    button_5: PowerPad5,
+    button_6: PowerPad6,
+    button_7: PowerPad7,
+    button_8: PowerPad8,
+    button_9: PowerPad9,
+    button_10: PowerPad10,
+    button_11: PowerPad11,
+    button_12: PowerPad12,
+]);
+
+impl NesPowerPadMapping {
+    #[must_use]
+    pub fn keyboard() -> Self {
+        Self {
+            button_1: key_input!(_1),
+            button_2: key_input!(_2),
+            button_3: key_input!(_3),
+            button_4: key_input!(_4),
+            button_5: key_input!(Q),
+            button_6: key_input!(W),
+            button_7: key_input!(E),
+            button_8: key_input!(R),
+            button_9: key_input!(Z),
+            button_10: key_input!(X),
+            button_11: key_input!(C),
+            button_12: key_input!(V),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ConfigDisplay)]
 pub struct NesInputMapping {
     #[serde(default)]
@@ -411,20 +589,42 @@ pub struct NesInputMapping {
     pub p2: NesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
+    pub p3: NesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4: NesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
     pub p1_turbo: NesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
     pub p2_turbo: NesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
+    pub p3_turbo: NesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4_turbo: NesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
     pub zapper: NesZapperMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub vaus: NesVausMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub power_pad: NesPowerPadMapping,
 }
 
 impl NesInputMapping {
     pub fn to_mapping_vec<'a>(&'a self, out: &mut ButtonMappingVec<'a, NesButton>) {
         self.p1.to_mapping_vec(Player::One, out);
         self.p2.to_mapping_vec(Player::Two, out);
+        self.p3.to_mapping_vec(Player::Three, out);
+        self.p4.to_mapping_vec(Player::Four, out);
         self.zapper.to_mapping_vec(Player::One, out);
+        self.vaus.to_mapping_vec(Player::One, out);
+        self.power_pad.to_mapping_vec(Player::One, out);
     }
 }
 
@@ -436,12 +636,27 @@ pub enum NesControllerType {
     #[default]
     Gamepad,
     Zapper,
+    ArkanoidVaus,
+    PowerPad,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, EnumDisplay, EnumAll,
+)]
+#[cfg_attr(feature = "clap", derive(jgenesis_proc_macros::CustomValueEnum))]
+pub enum NesFourPlayerAdapterType {
+    #[default]
+    None,
+    FourScore,
+    Hori,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ConfigDisplay)]
 pub struct NesInputConfig {
     #[serde(default)]
     pub p2_type: NesControllerType,
+    #[serde(default)]
+    pub four_player_adapter: NesFourPlayerAdapterType,
     #[serde(default = "default_nes_mapping_1")]
     #[cfg_display(indent_nested)]
     pub mapping_1: NesInputMapping,
@@ -453,16 +668,21 @@ pub struct NesInputConfig {
 impl NesInputConfig {
     impl_to_mapping_vec!(NesButton);
 
-    impl_to_turbo_mapping_vec!(NesButton);
+    impl_to_turbo_mapping_vec!(NesButton, [
+        p1_turbo: One,
+        p2_turbo: Two,
+        p3_turbo: Three,
+        p4_turbo: Four,
+    ]);
 }
 
 fn default_nes_mapping_1() -> NesInputMapping {
     NesInputMapping {
         p1: NesControllerMapping::keyboard_arrows(),
-        p2: NesControllerMapping::default(),
-        p1_turbo: NesControllerMapping::default(),
-        p2_turbo: NesControllerMapping::default(),
         zapper: NesZapperMapping::mouse(),
+        vaus: NesVausMapping::mouse(),
+        power_pad: NesPowerPadMapping::keyboard(),
+        ..NesInputMapping::default()
     }
 }
 
@@ -470,6 +690,7 @@ impl Default for NesInputConfig {
     fn default() -> Self {
         Self {
             p2_type: NesControllerType::default(),
+            four_player_adapter: NesFourPlayerAdapterType::default(),
             mapping_1: default_nes_mapping_1(),
             mapping_2: NesInputMapping::default(),
         }
@@ -548,6 +769,36 @@ impl SnesSuperScopeMapping {
     }
 }
 
+define_controller_mapping!(SnesMouseMapping, SnesButton, [
+    left: MouseLeft,
+    right: MouseRight,
+]);
+
+impl SnesMouseMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self {
+            left: Some(vec![GenericInput::Mouse(MouseButton::Left)]),
+            right: Some(vec![GenericInput::Mouse(MouseButton::Right)]),
+        }
+    }
+}
+
+define_controller_mapping!(SnesJustifierMapping, SnesButton, [
+    trigger: JustifierTrigger,
+    start: JustifierStart,
+]);
+
+impl SnesJustifierMapping {
+    #[must_use]
+    pub fn mouse() -> Self {
+        Self {
+            trigger: Some(vec![GenericInput::Mouse(MouseButton::Left)]),
+            start: Some(vec![GenericInput::Mouse(MouseButton::Right)]),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, ConfigDisplay)]
 pub struct SnesInputMapping {
     #[serde(default)]
@@ -558,20 +809,49 @@ pub struct SnesInputMapping {
     pub p2: SnesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
+    pub p3: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p5: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
     pub p1_turbo: SnesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
     pub p2_turbo: SnesControllerMapping,
     #[serde(default)]
     #[cfg_display(indent_nested)]
+    pub p3_turbo: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p4_turbo: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub p5_turbo: SnesControllerMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
     pub super_scope: SnesSuperScopeMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub mouse: SnesMouseMapping,
+    #[serde(default)]
+    #[cfg_display(indent_nested)]
+    pub justifier: SnesJustifierMapping,
 }
 
 impl SnesInputMapping {
     pub fn to_mapping_vec<'a>(&'a self, out: &mut ButtonMappingVec<'a, SnesButton>) {
         self.p1.to_mapping_vec(Player::One, out);
         self.p2.to_mapping_vec(Player::Two, out);
+        self.p3.to_mapping_vec(Player::Three, out);
+        self.p4.to_mapping_vec(Player::Four, out);
+        self.p5.to_mapping_vec(Player::Five, out);
         self.super_scope.to_mapping_vec(Player::One, out);
+        self.mouse.to_mapping_vec(Player::One, out);
+        self.justifier.to_mapping_vec(Player::One, out);
     }
 }
 
@@ -583,10 +863,15 @@ pub enum SnesControllerType {
     #[default]
     Gamepad,
     SuperScope,
+    Mouse,
+    Justifier,
+    Multitap,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ConfigDisplay)]
 pub struct SnesInputConfig {
+    #[serde(default)]
+    pub p1_type: SnesControllerType,
     #[serde(default)]
     pub p2_type: SnesControllerType,
     #[serde(default = "default_snes_mapping_1")]
@@ -600,22 +885,29 @@ pub struct SnesInputConfig {
 impl SnesInputConfig {
     impl_to_mapping_vec!(SnesButton);
 
-    impl_to_turbo_mapping_vec!(SnesButton);
+    impl_to_turbo_mapping_vec!(SnesButton, [
+        p1_turbo: One,
+        p2_turbo: Two,
+        p3_turbo: Three,
+        p4_turbo: Four,
+        p5_turbo: Five,
+    ]);
 }
 
 fn default_snes_mapping_1() -> SnesInputMapping {
     SnesInputMapping {
         p1: SnesControllerMapping::keyboard_arrows(),
-        p2: SnesControllerMapping::default(),
-        p1_turbo: SnesControllerMapping::default(),
-        p2_turbo: SnesControllerMapping::default(),
         super_scope: SnesSuperScopeMapping::mouse(),
+        mouse: SnesMouseMapping::mouse(),
+        justifier: SnesJustifierMapping::mouse(),
+        ..SnesInputMapping::default()
     }
 }
 
 impl Default for SnesInputConfig {
     fn default() -> Self {
         Self {
+            p1_type: SnesControllerType::default(),
             p2_type: SnesControllerType::default(),
             mapping_1: default_snes_mapping_1(),
             mapping_2: SnesInputMapping::default(),
@@ -632,6 +924,10 @@ define_controller_mapping!(GameBoyInputMapping, GameBoyButton, [
     b: B,
     start: Start,
     select: Select,
+    tilt_up: TiltUp,
+    tilt_left: TiltLeft,
+    tilt_right: TiltRight,
+    tilt_down: TiltDown,
 ]);
 
 impl GameBoyInputMapping {
@@ -646,6 +942,10 @@ impl GameBoyInputMapping {
             b: key_input!(S),
             start: key_input!(Return),
             select: key_input!(RShift),
+            tilt_up: key_input!(I),
+            tilt_left: key_input!(J),
+            tilt_right: key_input!(L),
+            tilt_down: key_input!(K),
         }
     }
 
@@ -660,6 +960,10 @@ impl GameBoyInputMapping {
             b: key_input!(K),
             start: key_input!(Return),
             select: key_input!(RShift),
+            tilt_up: key_input!(Up),
+            tilt_left: key_input!(Left),
+            tilt_right: key_input!(Right),
+            tilt_down: key_input!(Down),
         }
     }
 }
@@ -913,6 +1217,8 @@ define_hotkey_mapping!(
     rewind: Rewind default Grave,
     toggle_overclocking: ToggleOverclocking default Semicolon,
     open_debugger: OpenDebugger default Apostrophe,
+    change_disk_side: ChangeDiskSide default none,
+    toggle_movie_read_only: ToggleMovieReadOnly default none,
     save_state_slot_0: SaveStateSlot0 default none,
     save_state_slot_1: SaveStateSlot1 default none,
     save_state_slot_2: SaveStateSlot2 default none,
@@ -1,3 +1,4 @@
+pub mod cheats;
 pub mod common;
 pub mod gb;
 pub mod gba;
@@ -10,6 +11,7 @@ pub mod snes;
 
 pub use migration::{current_config_version, migrate_config, migrate_config_str};
 
+use crate::cheats::CheatsAppConfig;
 use crate::common::CommonAppConfig;
 use crate::gb::GameBoyAppConfig;
 use crate::gba::GameBoyAdvanceAppConfig;
@@ -101,6 +103,8 @@ pub struct AppConfig {
     pub game_boy_advance: GameBoyAdvanceAppConfig,
     #[serde(default)]
     pub input: InputAppConfig,
+    #[serde(default)]
+    pub cheats: CheatsAppConfig,
     // TODO move GUI-specific config/state somewhere else - separate file?
     #[serde(default)]
     pub list_filters: ListFilters,
@@ -1,6 +1,7 @@
 mod old_default_nes_palette;
 
 use crate::AppConfig;
+use crate::common::default_rewind_buffer_size_mb;
 use crate::input::GenericInput;
 use crate::input::mappings::HotkeyConfig;
 use nes_config::NesPalette;
@@ -9,7 +10,9 @@ use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 use std::str::FromStr;
-use toml_edit::DocumentMut;
+use toml_edit::{DocumentMut, Item};
+
+const OLD_DEFAULT_REWIND_BUFFER_LENGTH_SECONDS: i64 = 10;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct SemVer {
@@ -72,13 +75,41 @@ pub fn migrate_config_str(config_str: &mut String) {
 
     let mut changed = false;
 
-    if document["common"]["wgpu_backend"].as_str() == Some("OpenGl") {
+    // Index with get() rather than [] because indexing panics on missing keys
+    if document.get("common").and_then(|common| common.get("wgpu_backend")).and_then(Item::as_str)
+        == Some("OpenGl")
+    {
         log::info!("OpenGL wgpu backend option no longer exists; changing to Auto");
 
         document["common"]["wgpu_backend"] = toml_edit::value("Auto");
         changed = true;
     }
 
+    // common.rewind_buffer_length_seconds -> common.rewind_buffer_size_mb; scale so that the old
+    // default length maps to the new default size, and keep rewind disabled if it was disabled
+    if let Some(common) = document.get_mut("common").and_then(Item::as_table_like_mut)
+        && let Some(old_item) = common.remove("rewind_buffer_length_seconds")
+    {
+        if let Some(seconds) = old_item.as_integer()
+            && !common.contains_key("rewind_buffer_size_mb")
+        {
+            let default_size_mb =
+                i64::try_from(default_rewind_buffer_size_mb()).unwrap_or(i64::MAX);
+            let size_mb = if seconds <= 0 {
+                0
+            } else {
+                (seconds.saturating_mul(default_size_mb) / OLD_DEFAULT_REWIND_BUFFER_LENGTH_SECONDS)
+                    .max(1)
+            };
+            log::info!(
+                "Migrating rewind buffer length of {seconds} seconds to rewind buffer size of {size_mb} MB"
+            );
+            common.insert("rewind_buffer_size_mb", toml_edit::value(size_mb));
+        }
+
+        changed = true;
+    }
+
     if changed {
         *config_str = document.to_string();
     }
@@ -254,4 +285,32 @@ bios_path = \"/path/to/bios.sms\"
         assert!(config.smsgg.sms_boot_from_bios);
         assert_eq!(config.smsgg.sms_bios_path, Some("/path/to/bios.sms".into()));
     }
+
+    #[test]
+    fn rewind_buffer_length_to_size() {
+        fn migrated_size_mb(config_str: &str) -> Option<i64> {
+            let mut config_str = config_str.to_string();
+            migrate_config_str(&mut config_str);
+
+            let document = config_str.parse::<DocumentMut>().unwrap();
+            assert!(document["common"].get("rewind_buffer_length_seconds").is_none());
+            document["common"]["rewind_buffer_size_mb"].as_integer()
+        }
+
+        let default_size_mb = i64::try_from(default_rewind_buffer_size_mb()).unwrap();
+        assert_eq!(
+            migrated_size_mb("[common]\nrewind_buffer_length_seconds = 20\n"),
+            Some(2 * default_size_mb)
+        );
+        assert_eq!(
+            migrated_size_mb(
+                "[common]\nrewind_buffer_length_seconds = 20\nrewind_buffer_size_mb = 64\n"
+            ),
+            Some(64)
+        );
+
+        // Rewind disabled stays disabled
+        assert_eq!(migrated_size_mb("[common]\nrewind_buffer_length_seconds = 0\n"), Some(0));
+        assert_eq!(migrated_size_mb("[common]\nrewind_buffer_length_seconds = -5\n"), Some(0));
+    }
 }
@@ -1,6 +1,7 @@
 use jgenesis_common::frontend::TimingMode;
 use nes_config::{NesAspectRatio, NesAudioResampler, NesPalette, Overscan};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NesAppConfig {
@@ -27,6 +28,8 @@ pub struct NesAppConfig {
     pub dma_dummy_joy_reads: bool,
     #[serde(default)]
     pub palette: NesPalette,
+    #[serde(default)]
+    pub fds_bios_path: Option<PathBuf>,
 }
 
 const fn true_fn() -> bool {
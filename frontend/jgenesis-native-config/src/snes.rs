@@ -24,6 +24,7 @@ pub struct SnesAppConfig {
     pub st010_rom_path: Option<PathBuf>,
     pub st011_rom_path: Option<PathBuf>,
     pub st018_rom_path: Option<PathBuf>,
+    pub sgb_cartridge_path: Option<PathBuf>,
 }
 
 const fn true_fn() -> bool {
@@ -1,6 +1,6 @@
 use crate::archive::{ArchiveEntry, ArchiveError};
 use crate::mainloop::NativeEmulatorError;
-use crate::{NativeEmulatorResult, archive, extensions};
+use crate::{NativeEmulatorResult, archive, extensions, patch};
 use gb_config::GbcColorCorrection;
 use gb_core::api::GameBoyEmulatorConfig;
 use gba_config::GbaColorCorrection;
@@ -58,7 +58,8 @@ pub struct CommonConfig {
     #[cfg_display(indent_nested)]
     pub renderer_config: RendererConfig,
     pub fast_forward_multiplier: u64,
-    pub rewind_buffer_length_seconds: u64,
+    pub rewind_buffer_size_mb: u64,
+    pub rewind_frame_interval: u64,
     pub load_recent_state_at_launch: bool,
     pub launch_in_fullscreen: bool,
     pub initial_window_size: NonZeroU8,
@@ -68,12 +69,45 @@ pub struct CommonConfig {
     pub pause_emulator: PauseEmulator,
     pub hide_mouse_cursor: HideMouseCursor,
     pub egui_theme: EguiTheme,
+    /// Patch file to apply to the ROM; if not set, an IPS/BPS/UPS file next to the ROM with the
+    /// same file name will be applied if present
+    #[cfg_display(path)]
+    pub patch_path: Option<PathBuf>,
+    /// Enabled cheat codes for the current game
+    #[cfg_display(skip)]
+    pub cheat_codes: Vec<String>,
 }
 
 impl CommonConfig {
+    pub(crate) fn rewind_buffer_size_bytes(&self) -> usize {
+        usize::try_from(self.rewind_buffer_size_mb.saturating_mul(1024 * 1024))
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Read the ROM file, extracting it from an archive if necessary, and then apply the
+    /// configured or auto-detected patch file if there is one.
     pub(crate) fn read_rom_file(
         &self,
         supported_extensions: &[&str],
+    ) -> NativeEmulatorResult<RomReadResult> {
+        let mut result = self.read_unpatched_rom_file(supported_extensions)?;
+
+        let patch_path =
+            self.patch_path.clone().or_else(|| patch::find_patch_file(&self.rom_file_path));
+        if let Some(patch_path) = patch_path {
+            log::info!("Applying patch file '{}'", patch_path.display());
+
+            result.rom = patch::read_and_apply(&result.rom, &patch_path).map_err(|source| {
+                NativeEmulatorError::Patch { path: patch_path.display().to_string(), source }
+            })?;
+        }
+
+        Ok(result)
+    }
+
+    fn read_unpatched_rom_file(
+        &self,
+        supported_extensions: &[&str],
     ) -> NativeEmulatorResult<RomReadResult> {
         #[derive(Default)]
         struct ArchiveListCallback {
@@ -171,6 +205,8 @@ pub struct GenesisConfig {
     pub inputs: GenesisInputConfig,
     #[cfg_display(indent_nested)]
     pub emulator_config: GenesisEmulatorConfig,
+    #[cfg_display(path)]
+    pub lock_on_rom_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, ConfigDisplay)]
@@ -205,6 +241,8 @@ pub struct NesConfig {
     pub inputs: NesInputConfig,
     #[cfg_display(indent_nested)]
     pub emulator_config: NesEmulatorConfig,
+    #[cfg_display(path)]
+    pub fds_bios_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, ConfigDisplay)]
@@ -229,6 +267,8 @@ pub struct SnesConfig {
     pub st011_rom_path: Option<PathBuf>,
     #[cfg_display(path)]
     pub st018_rom_path: Option<PathBuf>,
+    #[cfg_display(path)]
+    pub sgb_cartridge_path: Option<PathBuf>,
 }
 
 impl SnesConfig {
@@ -240,8 +280,19 @@ impl SnesConfig {
         let st010 = self.st010_rom_path.clone().map(coprocessor_read_fn);
         let st011 = self.st011_rom_path.clone().map(coprocessor_read_fn);
         let st018 = self.st018_rom_path.clone().map(coprocessor_read_fn);
-
-        CoprocessorRoms { dsp1, dsp2, dsp3, dsp4, st010, st011, st018 }
+        let sgb_cartridge = self.sgb_cartridge_path.clone().map(coprocessor_read_fn);
+
+        CoprocessorRoms {
+            dsp1,
+            dsp2,
+            dsp3,
+            dsp4,
+            st010,
+            st011,
+            st018,
+            sgb_cartridge,
+            msu1_data_path: None,
+        }
     }
 }
 
@@ -263,6 +314,8 @@ pub struct GameBoyConfig {
     pub dmg_boot_rom_path: Option<PathBuf>,
     #[cfg_display(path)]
     pub cgb_boot_rom_path: Option<PathBuf>,
+    #[cfg_display(path)]
+    pub camera_image_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, ConfigDisplay)]
@@ -319,6 +372,8 @@ impl AppConfigExt for AppConfig {
             }
         }
 
+        let cheat_codes = self.cheats.enabled_codes(&path);
+
         CommonConfig {
             rom_file_path: path,
             mute_audio: self.common.mute_audio,
@@ -348,7 +403,8 @@ impl AppConfigExt for AppConfig {
                 preprocess_shader: self.common.preprocess_shader,
             },
             fast_forward_multiplier: self.common.fast_forward_multiplier,
-            rewind_buffer_length_seconds: self.common.rewind_buffer_length_seconds,
+            rewind_buffer_size_mb: self.common.rewind_buffer_size_mb,
+            rewind_frame_interval: self.common.rewind_frame_interval,
             load_recent_state_at_launch: self.common.load_recent_state_at_launch,
             launch_in_fullscreen: self.common.launch_in_fullscreen,
             initial_window_size: self.common.initial_window_size,
@@ -357,6 +413,8 @@ impl AppConfigExt for AppConfig {
             pause_emulator: self.common.pause_emulator,
             hide_mouse_cursor: self.common.hide_mouse_cursor,
             egui_theme: self.egui_theme,
+            patch_path: None,
+            cheat_codes,
         }
     }
 
@@ -399,6 +457,7 @@ impl AppConfigExt for AppConfig {
                 ym2612_volume_adjustment_db: self.genesis.ym2612_volume_adjustment_db,
                 psg_volume_adjustment_db: self.genesis.psg_volume_adjustment_db,
             },
+            lock_on_rom_path: self.genesis.lock_on_rom_path.clone(),
         })
     }
 
@@ -481,6 +540,8 @@ impl AppConfigExt for AppConfig {
                 gg_use_sms_resolution: self.smsgg.gg_use_sms_resolution,
                 fm_sound_unit_enabled: self.smsgg.fm_sound_unit_enabled,
                 z80_divider: self.smsgg.z80_divider,
+                p1_controller_type: self.input.smsgg.p1_type,
+                p2_controller_type: self.input.smsgg.p2_type,
             },
             sms_boot_from_bios: self.smsgg.sms_boot_from_bios,
             gg_boot_from_bios: self.smsgg.gg_boot_from_bios,
@@ -508,6 +569,7 @@ impl AppConfigExt for AppConfig {
                 allow_opposing_joypad_inputs: self.nes.allow_opposing_joypad_inputs,
                 dma_dummy_joy_reads: self.nes.dma_dummy_joy_reads,
             },
+            fds_bios_path: self.nes.fds_bios_path.clone(),
         })
     }
 
@@ -530,6 +592,7 @@ impl AppConfigExt for AppConfig {
             st010_rom_path: self.snes.st010_rom_path.clone(),
             st011_rom_path: self.snes.st011_rom_path.clone(),
             st018_rom_path: self.snes.st018_rom_path.clone(),
+            sgb_cartridge_path: self.snes.sgb_cartridge_path.clone(),
         })
     }
 
@@ -551,6 +614,7 @@ impl AppConfigExt for AppConfig {
                 force_dmg_mode: self.game_boy.force_dmg_mode,
                 force_cgb_mode: self.game_boy.force_cgb_mode,
                 pretend_to_be_gba: self.game_boy.pretend_to_be_gba,
+                printer_connected: self.game_boy.printer_connected,
                 aspect_ratio: self.game_boy.aspect_ratio,
                 gb_palette: self.game_boy.gb_palette,
                 gb_custom_palette: self.game_boy.gb_custom_palette,
@@ -563,6 +627,7 @@ impl AppConfigExt for AppConfig {
             cgb_boot_rom: self.game_boy.cgb_boot_rom,
             dmg_boot_rom_path: self.game_boy.dmg_boot_rom_path.clone(),
             cgb_boot_rom_path: self.game_boy.cgb_boot_rom_path.clone(),
+            camera_image_path: self.game_boy.camera_image_path.clone(),
         })
     }
 
@@ -15,7 +15,7 @@ pub const GAME_GEAR: &[&str] = &["gg"];
 pub const GENESIS: &[&str] = &["gen", "md", "bin", "smd"];
 pub const SEGA_CD: &[&str] = &["cue", "chd"];
 pub const SEGA_32X: &[&str] = &["32x", "bin"];
-pub const NES: &[&str] = &["nes"];
+pub const NES: &[&str] = &["nes", "fds", "qd", "nsf", "nsfe"];
 pub const SNES: &[&str] = &["sfc", "smc"];
 pub const GAME_BOY: &[&str] = &["gb"];
 pub const GAME_BOY_COLOR: &[&str] = &["gbc"];
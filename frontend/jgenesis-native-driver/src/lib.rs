@@ -4,13 +4,18 @@ pub mod extensions;
 mod fpstracker;
 pub mod input;
 mod mainloop;
+pub mod patch;
 
 pub use mainloop::{
-    AudioError, Native32XEmulator, NativeEmulator, NativeEmulatorError, NativeEmulatorResult,
-    NativeGameBoyEmulator, NativeGbaEmulator, NativeGenesisEmulator, NativeNesEmulator,
-    NativeSegaCdEmulator, NativeSmsGgEmulator, NativeSnesEmulator, NativeTickEffect,
+    AudioError, CameraError, GdbError, HeadlessConfig, HeadlessError, HeadlessReport,
+    LinkCableConfig, LinkCableError, MovieError, MovieStartType, Native32XEmulator, NativeEmulator,
+    NativeEmulatorError, NativeEmulatorResult, NativeGameBoyEmulator, NativeGbaEmulator,
+    NativeGenesisEmulator, NativeNesEmulator, NativeSegaCdEmulator, NativeSmsGgEmulator,
+    NativeSnesEmulator, NativeTickEffect, NetplayConfig, NetplayError, PrinterError,
     SAVE_STATE_SLOTS, SaveStateMetadata, SaveWriteError, create_32x, create_gb, create_gba,
-    create_genesis, create_nes, create_sega_cd, create_smsgg, create_snes,
+    create_genesis, create_nes, create_sega_cd, create_smsgg, create_snes, run_headless_32x,
+    run_headless_gb, run_headless_gba, run_headless_genesis, run_headless_nes,
+    run_headless_sega_cd, run_headless_smsgg, run_headless_snes,
 };
 use sdl3::VideoSubsystem;
 
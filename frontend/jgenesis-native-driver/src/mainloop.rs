@@ -1,9 +1,16 @@
 mod audio;
+mod camera;
 mod gb;
 mod gba;
+mod gdb;
 mod genesis;
+mod headless;
 mod input;
+mod link;
+mod movie;
 mod nes;
+mod netplay;
+mod printer;
 mod render;
 mod rewind;
 mod runner;
@@ -12,15 +19,22 @@ mod smsgg;
 mod snes;
 mod state;
 
-pub use gb::{NativeGameBoyEmulator, create_gb};
-pub use gba::{NativeGbaEmulator, create_gba};
+pub use camera::CameraError;
+pub use gb::{NativeGameBoyEmulator, create_gb, run_headless_gb};
+pub use gba::{NativeGbaEmulator, create_gba, run_headless_gba};
+pub use gdb::GdbError;
 pub use genesis::{
     Native32XEmulator, NativeGenesisEmulator, NativeSegaCdEmulator, create_32x, create_genesis,
-    create_sega_cd,
+    create_sega_cd, run_headless_32x, run_headless_genesis, run_headless_sega_cd,
 };
-pub use nes::{NativeNesEmulator, create_nes};
-pub use smsgg::{NativeSmsGgEmulator, create_smsgg};
-pub use snes::{NativeSnesEmulator, create_snes};
+pub use headless::{HeadlessConfig, HeadlessError, HeadlessReport};
+pub use link::{LinkCableConfig, LinkCableError};
+pub use movie::{MovieError, MovieStartType};
+pub use nes::{NativeNesEmulator, create_nes, run_headless_nes};
+pub use netplay::{NetplayConfig, NetplayError};
+pub use printer::PrinterError;
+pub use smsgg::{NativeSmsGgEmulator, create_smsgg, run_headless_smsgg};
+pub use snes::{NativeSnesEmulator, create_snes, run_headless_snes};
 pub use state::{SAVE_STATE_SLOTS, SaveStateMetadata};
 
 use crate::archive::ArchiveError;
@@ -28,12 +42,19 @@ use crate::config::CommonConfig;
 use crate::fpstracker::FpsTracker;
 use crate::input::{InputEvent, InputMapper, Joysticks};
 use crate::mainloop::audio::{SdlAudioOutput, SdlAudioOutputHandle};
+use crate::mainloop::camera::{ImageCameraSensor, UpdateCameraFn};
+use crate::mainloop::gdb::{GdbFn, GdbServerHandle};
+use crate::mainloop::link::LinkCableFn;
+use crate::mainloop::movie::ExternalMovieFormat;
+use crate::mainloop::netplay::MergeNetplayInputsFn;
+use crate::mainloop::printer::TakePrintedImageFn;
 use crate::mainloop::render::{RecvFrameError, ThreadedRenderer};
 use crate::mainloop::runner::{
-    ChangeDiscFn, RemoveDiscFn, RunnerCommand, RunnerCommandResponse, RunnerSpawnArgs,
-    RunnerThreadHandle,
+    ChangeDiscFn, ChangeDiskSideFn, RemoveDiscFn, RunnerCommand, RunnerCommandResponse,
+    RunnerSpawnArgs, RunnerThreadHandle,
 };
-use crate::mainloop::save::FsSaveWriter;
+use crate::mainloop::save::{CreateSaveWriter, FsSaveWriter};
+use crate::patch::PatchError;
 pub use audio::AudioError;
 use bincode::error::{DecodeError, EncodeError};
 use gb_core::api::GameBoyLoadError;
@@ -193,6 +214,8 @@ pub struct NativeEmulator<Emulator: EmulatorTrait> {
     window_state: WindowState,
     fps_tracker: FpsTracker,
     rom_path: PathBuf,
+    gdb_fn: Option<GdbFn<Emulator>>,
+    gdb_server: Option<GdbServerHandle>,
     // Put SDL handle last so that it is dropped last when the emulator is dropped
     sdl: Sdl,
 }
@@ -289,6 +312,12 @@ pub enum NativeEmulatorError {
     },
     #[error("{0}")]
     Archive(#[from] ArchiveError),
+    #[error("Error applying patch file '{path}': {source}")]
+    Patch {
+        path: String,
+        #[source]
+        source: PatchError,
+    },
     #[error("No SMS BIOS provided")]
     SmsNoBios,
     #[error("No Game Gear BIOS provided")]
@@ -299,6 +328,12 @@ pub enum NativeEmulatorError {
         #[source]
         source: io::Error,
     },
+    #[error("Error opening lock-on ROM file at '{path}': {source}")]
+    GenesisLockOnRomRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
     #[error("{0} BIOS is required for Sega CD emulation")]
     SegaCdNoBios(GenesisRegion),
     #[error("Error opening BIOS file at '{path}': {source}")]
@@ -311,6 +346,10 @@ pub enum NativeEmulatorError {
     SegaCdDisc(#[from] SegaCdLoadError),
     #[error("{0}")]
     NesLoad(#[from] NesInitializationError),
+    #[error("No Famicom Disk System BIOS provided")]
+    NesNoFdsBios,
+    #[error("Failed to load FDS BIOS: {0}")]
+    NesFdsBiosLoad(io::Error),
     #[error("{0}")]
     SnesLoad(#[from] SnesLoadError),
     #[error("No Game Boy boot ROM provided")]
@@ -321,8 +360,6 @@ pub enum NativeEmulatorError {
     GbBootRomLoad(io::Error),
     #[error("{0}")]
     GameBoyLoad(#[from] GameBoyLoadError),
-    #[error("No Game Boy Advance BIOS provided")]
-    GbaNoBios,
     #[error("Failed to load GBA BIOS: {0}")]
     GbaBiosLoad(io::Error),
     #[error("Failed to initialize GBA emulator: {0}")]
@@ -345,6 +382,20 @@ pub enum NativeEmulatorError {
     LoadStatePrefixMismatch,
     #[error("Save state version mismatch; expected '{expected}', got '{actual}'")]
     LoadStateVersionMismatch { expected: String, actual: String },
+    #[error("{0}")]
+    Movie(#[from] MovieError),
+    #[error("{0}")]
+    Netplay(#[from] NetplayError),
+    #[error("{0}")]
+    LinkCable(#[from] LinkCableError),
+    #[error("{0}")]
+    Printer(#[from] PrinterError),
+    #[error("{0}")]
+    Camera(#[from] CameraError),
+    #[error("{0}")]
+    Gdb(#[from] GdbError),
+    #[error("{0}")]
+    Headless(#[from] HeadlessError),
     #[error("Lost connection to runner thread")]
     LostRunnerConnection,
     #[error("Error changing/removing disc: {0}")]
@@ -361,7 +412,7 @@ pub(crate) struct CreatedEmulator<Emulator: EmulatorTrait> {
     pub default_window_size: WindowSize,
 }
 
-pub(crate) type CreateEmulatorFn<Emulator> = dyn FnOnce(&mut FsSaveWriter) -> Result<CreatedEmulator<Emulator>, NativeEmulatorError>
+pub(crate) type CreateEmulatorFn<Emulator> = dyn FnOnce(&mut CreateSaveWriter<'_>) -> Result<CreatedEmulator<Emulator>, NativeEmulatorError>
     + Send
     + Sync
     + 'static;
@@ -370,6 +421,14 @@ pub(crate) struct NativeEmulatorArgs<'input, 'turbo, Emulator: EmulatorTrait> {
     pub create_emulator_fn: Box<CreateEmulatorFn<Emulator>>,
     pub change_disc_fn: ChangeDiscFn<Emulator>,
     pub remove_disc_fn: RemoveDiscFn<Emulator>,
+    pub change_disk_side_fn: ChangeDiskSideFn<Emulator>,
+    pub movie_format: Option<ExternalMovieFormat<Emulator::Inputs>>,
+    pub netplay_merge_fn: Option<MergeNetplayInputsFn<Emulator::Inputs>>,
+    pub link_cable_fn: Option<LinkCableFn<Emulator>>,
+    pub take_printed_image_fn: Option<TakePrintedImageFn<Emulator>>,
+    pub camera_sensor: Option<ImageCameraSensor>,
+    pub update_camera_fn: Option<UpdateCameraFn<Emulator>>,
+    pub gdb_fn: Option<GdbFn<Emulator>>,
     pub emulator_config: Emulator::Config,
     pub common_config: CommonConfig,
     pub rom_extension: String,
@@ -399,6 +458,14 @@ where
             create_emulator_fn,
             change_disc_fn: |_emulator, _path| Ok(String::new()),
             remove_disc_fn: |_emulator| {},
+            change_disk_side_fn: |_emulator| {},
+            movie_format: None,
+            netplay_merge_fn: None,
+            link_cable_fn: None,
+            take_printed_image_fn: None,
+            camera_sensor: None,
+            update_camera_fn: None,
+            gdb_fn: None,
             emulator_config,
             common_config,
             rom_extension,
@@ -438,6 +505,58 @@ where
         self.remove_disc_fn = remove_disc_fn;
         self
     }
+
+    pub fn with_disk_side_change_fn(
+        mut self,
+        change_disk_side_fn: ChangeDiskSideFn<Emulator>,
+    ) -> Self {
+        self.change_disk_side_fn = change_disk_side_fn;
+        self
+    }
+
+    pub fn with_movie_format(
+        mut self,
+        movie_format: ExternalMovieFormat<Emulator::Inputs>,
+    ) -> Self {
+        self.movie_format = Some(movie_format);
+        self
+    }
+
+    pub fn with_netplay_merge_fn(
+        mut self,
+        netplay_merge_fn: MergeNetplayInputsFn<Emulator::Inputs>,
+    ) -> Self {
+        self.netplay_merge_fn = Some(netplay_merge_fn);
+        self
+    }
+
+    pub fn with_link_cable_fn(mut self, link_cable_fn: LinkCableFn<Emulator>) -> Self {
+        self.link_cable_fn = Some(link_cable_fn);
+        self
+    }
+
+    pub fn with_take_printed_image_fn(
+        mut self,
+        take_printed_image_fn: TakePrintedImageFn<Emulator>,
+    ) -> Self {
+        self.take_printed_image_fn = Some(take_printed_image_fn);
+        self
+    }
+
+    pub fn with_camera(
+        mut self,
+        camera_sensor: Option<ImageCameraSensor>,
+        update_camera_fn: UpdateCameraFn<Emulator>,
+    ) -> Self {
+        self.camera_sensor = camera_sensor;
+        self.update_camera_fn = Some(update_camera_fn);
+        self
+    }
+
+    pub fn with_gdb_fn(mut self, gdb_fn: GdbFn<Emulator>) -> Self {
+        self.gdb_fn = Some(gdb_fn);
+        self
+    }
 }
 
 impl<Emulator> NativeEmulator<Emulator>
@@ -449,6 +568,14 @@ where
             create_emulator_fn,
             change_disc_fn,
             remove_disc_fn,
+            change_disk_side_fn,
+            movie_format,
+            netplay_merge_fn,
+            link_cable_fn,
+            take_printed_image_fn,
+            camera_sensor,
+            update_camera_fn,
+            gdb_fn,
             emulator_config,
             common_config,
             rom_extension,
@@ -481,6 +608,13 @@ where
             create_emulator_fn,
             change_disc_fn,
             remove_disc_fn,
+            change_disk_side_fn,
+            movie_format,
+            netplay_merge_fn,
+            link_cable_fn,
+            take_printed_image_fn,
+            camera_sensor,
+            update_camera_fn,
             common_config: common_config.clone(),
             emulator_config: emulator_config.clone(),
             rom_extension: rom_extension.clone(),
@@ -529,6 +663,8 @@ where
             window_state: WindowState::new(),
             fps_tracker: FpsTracker::new(),
             rom_path: common_config.rom_file_path,
+            gdb_fn,
+            gdb_server: None,
         };
 
         if common_config.load_recent_state_at_launch {
@@ -740,6 +876,77 @@ where
             RunnerCommandResponse::ChangeDiscFailed(err) => {
                 log::error!("Failed to change disc: {err}");
             }
+            RunnerCommandResponse::MovieRecordingStarted => {
+                self.renderer.add_modal("Started recording movie".into(), MODAL_DURATION);
+            }
+            RunnerCommandResponse::MoviePlaybackStarted { read_only } => {
+                let mode = if read_only { "read-only" } else { "read-write" };
+                self.renderer.add_modal(format!("Started movie playback ({mode})"), MODAL_DURATION);
+            }
+            RunnerCommandResponse::MoviePlaybackFinished => {
+                self.renderer.add_modal("Movie playback finished".into(), MODAL_DURATION);
+            }
+            RunnerCommandResponse::MovieStopped => {
+                self.renderer.add_modal("Movie stopped".into(), MODAL_DURATION);
+            }
+            RunnerCommandResponse::MovieReadOnlyChanged { read_only } => {
+                let modal_text = if read_only { "Movie read-only" } else { "Movie read-write" };
+                self.renderer.add_or_update_modal(
+                    Some("movie_read_only".into()),
+                    modal_text.into(),
+                    MODAL_DURATION,
+                );
+            }
+            RunnerCommandResponse::MovieFailed(err) => {
+                self.renderer.add_modal("Failed to start movie".into(), MODAL_DURATION);
+                log::error!("Failed to start movie: {err}");
+            }
+            RunnerCommandResponse::NetplayWaitingForPeer => {
+                self.renderer.add_modal("Waiting for netplay peer".into(), MODAL_DURATION);
+            }
+            RunnerCommandResponse::NetplayConnected { peer } => {
+                self.renderer.add_modal(format!("Netplay connected to {peer}"), MODAL_DURATION);
+            }
+            RunnerCommandResponse::NetplayDesync { frame } => {
+                self.renderer
+                    .add_modal(format!("Netplay desync detected at frame {frame}"), MODAL_DURATION);
+            }
+            RunnerCommandResponse::NetplayStopped { reason } => {
+                let modal_text = match reason {
+                    Some(reason) => format!("Netplay ended: {reason}"),
+                    None => "Netplay stopped".into(),
+                };
+                self.renderer.add_modal(modal_text, MODAL_DURATION);
+            }
+            RunnerCommandResponse::NetplayFailed(err) => {
+                self.renderer.add_modal("Failed to start netplay".into(), MODAL_DURATION);
+                log::error!("Failed to start netplay: {err}");
+            }
+            RunnerCommandResponse::LinkCableWaitingForPeer => {
+                self.renderer.add_modal("Waiting for link cable peer".into(), MODAL_DURATION);
+            }
+            RunnerCommandResponse::LinkCableConnected { peer } => {
+                self.renderer.add_modal(format!("Link cable connected to {peer}"), MODAL_DURATION);
+            }
+            RunnerCommandResponse::LinkCableStopped { reason } => {
+                let modal_text = match reason {
+                    Some(reason) => format!("Link cable disconnected: {reason}"),
+                    None => "Link cable disconnected".into(),
+                };
+                self.renderer.add_modal(modal_text, MODAL_DURATION);
+            }
+            RunnerCommandResponse::LinkCableFailed(err) => {
+                self.renderer.add_modal("Failed to start link cable".into(), MODAL_DURATION);
+                log::error!("Failed to start link cable: {err}");
+            }
+            RunnerCommandResponse::PrintedImageSaved { path } => {
+                let file_name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+                self.renderer.add_modal(format!("Printed to {file_name}"), MODAL_DURATION);
+            }
+            RunnerCommandResponse::PrintedImageFailed(err) => {
+                self.renderer.add_modal("Failed to save printed image".into(), MODAL_DURATION);
+                log::error!("Failed to save printed image: {err}");
+            }
         }
     }
 
@@ -800,6 +1007,98 @@ where
         self.runner.send_command(RunnerCommand::LoadState { slot })
     }
 
+    /// Start recording an input movie to the given path. Recording from power-on hard resets the
+    /// emulator.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn record_movie(
+        &mut self,
+        path: PathBuf,
+        start: MovieStartType,
+    ) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::RecordMovie { path, start })
+    }
+
+    /// Start playing back the input movie at the given path.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn play_movie(&mut self, path: PathBuf, read_only: bool) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::PlayMovie { path, read_only })
+    }
+
+    /// Stop recording or playing back the current movie, saving it if it was modified.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn stop_movie(&mut self) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::StopMovie)
+    }
+
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn toggle_movie_read_only(&mut self) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::ToggleMovieReadOnly)
+    }
+
+    /// Start a two-player netplay session, either hosting or connecting to a host depending on
+    /// the config. The emulator hard resets once the peer connects.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn start_netplay(&mut self, config: NetplayConfig) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::StartNetplay(config))
+    }
+
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn stop_netplay(&mut self) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::StopNetplay)
+    }
+
+    /// Connect a link cable to another instance of the emulator, either listening for a
+    /// connection or connecting to a listening instance depending on the config.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn start_link_cable(&mut self, config: LinkCableConfig) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::StartLinkCable(config))
+    }
+
+    /// # Errors
+    ///
+    /// This method will return an error if unable to send the command to the emulator runner thread.
+    pub fn stop_link_cable(&mut self) -> NativeEmulatorResult<()> {
+        self.runner.send_command(RunnerCommand::StopLinkCable)
+    }
+
+    /// Start a GDB remote serial protocol server listening on the given localhost port. The
+    /// emulator stops when GDB connects and runs normally again after GDB disconnects.
+    ///
+    /// The server takes over the emulator's debugger hook while GDB is connected, so opening the
+    /// debugger window ends the GDB session.
+    ///
+    /// # Errors
+    ///
+    /// This method will return an error if the GDB server is not supported for this system or if
+    /// unable to listen on the port.
+    pub fn start_gdb_server(&mut self, port: u16) -> NativeEmulatorResult<()> {
+        let Some(gdb_fn) = self.gdb_fn else { return Err(GdbError::Unsupported.into()) };
+
+        self.gdb_server =
+            Some(gdb::spawn_server(port, gdb_fn, self.runner.clone_command_sender())?);
+
+        Ok(())
+    }
+
     /// Try to load the most recent save state.
     ///
     /// If there are no save states or the most recent save state is invalid, this method will log
@@ -896,6 +1195,10 @@ where
             }
             CompactHotkey::ToggleOverclocking => self.toggle_overclocking()?,
             CompactHotkey::OpenDebugger => self.open_memory_viewer()?,
+            CompactHotkey::ChangeDiskSide => {
+                self.runner.send_command(RunnerCommand::ChangeDiskSide)?;
+            }
+            CompactHotkey::ToggleMovieReadOnly => self.toggle_movie_read_only()?,
         }
 
         Ok(None)
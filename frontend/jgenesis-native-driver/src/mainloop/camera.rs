@@ -0,0 +1,105 @@
+//! Image file input for an emulated camera sensor (currently only the Game Boy Camera).
+//!
+//! The sensor image can come from a single still image or from a directory of images. Images in a
+//! directory are shown in file name order, each for a fixed number of frames, looping back to the
+//! first image after the last.
+
+use gb_core::camera::{CAMERA_IMAGE_HEIGHT, CAMERA_IMAGE_WIDTH, CameraImage, CameraSensor};
+use image::imageops::FilterType;
+use std::path::Path;
+use std::{fs, io};
+use thiserror::Error;
+
+// Roughly 1 second per image at 60fps
+const FRAMES_PER_IMAGE: u32 = 60;
+
+/// Update the emulated camera sensor from the loaded images. Called once per emulated frame.
+pub type UpdateCameraFn<Emulator> = fn(&mut Emulator, &mut ImageCameraSensor);
+
+#[derive(Debug, Error)]
+pub enum CameraError {
+    #[error("Error reading camera image directory '{path}': {source}")]
+    ReadDir {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error loading camera image '{path}': {source}")]
+    Load {
+        path: String,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("No camera images found in directory '{path}'")]
+    EmptyDir { path: String },
+}
+
+/// Camera sensor that shows images loaded from files.
+#[derive(Debug)]
+pub struct ImageCameraSensor {
+    images: Vec<Box<CameraImage>>,
+    image_idx: usize,
+    frames_shown: u32,
+}
+
+impl ImageCameraSensor {
+    /// Load sensor images from `path`, which can be either an image file or a directory of image
+    /// files. Images are converted to grayscale and resized to the sensor resolution.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any image cannot be loaded, or if `path` is a directory that contains
+    /// no image files.
+    pub fn load(path: &Path) -> Result<Self, CameraError> {
+        let images = if path.is_dir() { load_image_dir(path)? } else { vec![load_image(path)?] };
+
+        log::info!("Loaded {} camera image(s) from '{}'", images.len(), path.display());
+
+        Ok(Self { images, image_idx: 0, frames_shown: 0 })
+    }
+}
+
+impl CameraSensor for ImageCameraSensor {
+    fn capture(&mut self, image: &mut CameraImage) {
+        image.copy_from_slice(self.images[self.image_idx].as_ref());
+
+        self.frames_shown += 1;
+        if self.frames_shown == FRAMES_PER_IMAGE {
+            self.frames_shown = 0;
+            self.image_idx = (self.image_idx + 1) % self.images.len();
+        }
+    }
+}
+
+fn load_image_dir(dir: &Path) -> Result<Vec<Box<CameraImage>>, CameraError> {
+    let read_dir_err = |source| CameraError::ReadDir { path: dir.display().to_string(), source };
+
+    let mut paths = fs::read_dir(dir)
+        .map_err(read_dir_err)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_dir_err)?;
+    paths.retain(|path| path.is_file() && image::ImageFormat::from_path(path).is_ok());
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(CameraError::EmptyDir { path: dir.display().to_string() });
+    }
+
+    paths.iter().map(|path| load_image(path)).collect()
+}
+
+fn load_image(path: &Path) -> Result<Box<CameraImage>, CameraError> {
+    let image = image::open(path)
+        .map_err(|source| CameraError::Load { path: path.display().to_string(), source })?;
+
+    let image = image
+        .resize_exact(CAMERA_IMAGE_WIDTH as u32, CAMERA_IMAGE_HEIGHT as u32, FilterType::Triangle)
+        .into_luma8();
+
+    Ok(image
+        .into_raw()
+        .into_boxed_slice()
+        .try_into()
+        .expect("Resized image should always have the camera sensor's dimensions"))
+}
@@ -1,6 +1,9 @@
 use crate::config::GameBoyConfig;
 use crate::config::RomReadResult;
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
+use crate::mainloop::camera::ImageCameraSensor;
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
+use crate::mainloop::link::{LinkCableMessage, LinkCableSocket};
+use crate::mainloop::save::{CreateSaveWriter, DeterminedPaths};
 use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, save};
 use crate::{NativeEmulator, NativeEmulatorError, NativeEmulatorResult, extensions};
 use gb_core::api::{BootRoms, GameBoyEmulator};
@@ -38,6 +41,27 @@ impl NativeGameBoyEmulator {
 ///
 /// This function will return an error if unable to initialize the emulator.
 pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameBoyEmulator> {
+    launch_gb(config, NativeGameBoyEmulator::new)
+}
+
+/// Run the Game Boy core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_gb(
+    config: Box<GameBoyConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_gb(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_gb<T>(
+    config: Box<GameBoyConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, GameBoyEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.common.rom_file_path);
@@ -55,6 +79,9 @@ pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameB
     )?;
     let boot_roms = BootRoms { dmg: dmg_boot_rom, cgb: cgb_boot_rom };
 
+    let camera_sensor =
+        config.camera_image_path.as_deref().map(ImageCameraSensor::load).transpose()?;
+
     let DeterminedPaths { save_path, save_state_path } = save::determine_save_paths(
         &config.common.save_path,
         &config.common.state_path,
@@ -66,7 +93,7 @@ pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameB
     let initial_window_size = config.common.initial_window_size;
     let rom_file_path = config.common.rom_file_path.clone();
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let emulator = GameBoyEmulator::create(rom, boot_roms, emulator_config, save_writer)?;
 
         let rom_title = file_name_no_ext(rom_file_path)?;
@@ -77,7 +104,7 @@ pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameB
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    NativeGameBoyEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -88,6 +115,9 @@ pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameB
             config.inputs.to_mapping_vec(),
         )
         .with_turbo_mappings(config.inputs.to_turbo_mapping_vec())
+        .with_link_cable_fn(update_link_cable)
+        .with_take_printed_image_fn(GameBoyEmulator::take_printed_image)
+        .with_camera(camera_sensor, GameBoyEmulator::update_camera_sensor)
         .with_debug_fn(|| {
             jgenesis_debugger_frontend::partial_clone_debug_fn(
                 jgenesis_debugger_frontend::gb::render_fn(),
@@ -96,6 +126,25 @@ pub fn create_gb(config: Box<GameBoyConfig>) -> NativeEmulatorResult<NativeGameB
     )
 }
 
+fn update_link_cable(emulator: &mut GameBoyEmulator, socket: &mut LinkCableSocket) {
+    // Always set this because loading a save state or rewinding replaces the serial port state
+    emulator.set_link_cable_connected(socket.is_connected());
+
+    while let Some(message) = socket.receive() {
+        match message {
+            LinkCableMessage::Pulse(bit) => {
+                let response = emulator.receive_link_cable_pulse(bit);
+                socket.send(LinkCableMessage::Response(response));
+            }
+            LinkCableMessage::Response(bit) => emulator.receive_link_cable_response(bit),
+        }
+    }
+
+    while let Some(bit) = emulator.pop_link_cable_pulse() {
+        socket.send(LinkCableMessage::Pulse(bit));
+    }
+}
+
 fn load_boot_rom(
     load: bool,
     path: Option<&PathBuf>,
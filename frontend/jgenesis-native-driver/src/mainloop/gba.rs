@@ -1,6 +1,7 @@
 use crate::config::{GameBoyAdvanceConfig, RomReadResult};
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
-use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, save};
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
+use crate::mainloop::save::{CreateSaveWriter, DeterminedPaths};
+use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, gdb, save};
 use crate::{NativeEmulator, NativeEmulatorError, NativeEmulatorResult, extensions};
 use gba_config::{GbaInputs, SolarSensorState};
 use gba_core::api::GameBoyAdvanceEmulator;
@@ -53,18 +54,43 @@ fn new_solar_state(config: &GameBoyAdvanceConfig) -> SolarSensorState {
 ///
 /// Propagates any errors encountered while initializing the emulator.
 pub fn create_gba(config: Box<GameBoyAdvanceConfig>) -> NativeEmulatorResult<NativeGbaEmulator> {
+    launch_gba(config, NativeGbaEmulator::new)
+}
+
+/// Run the GBA core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_gba(
+    config: Box<GameBoyAdvanceConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_gba(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_gba<T>(
+    config: Box<GameBoyAdvanceConfig>,
+    launch_fn: impl FnOnce(
+        NativeEmulatorArgs<'_, '_, GameBoyAdvanceEmulator>,
+    ) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.common.rom_file_path);
     let RomReadResult { rom, extension } =
         config.common.read_rom_file(extensions::GAME_BOY_ADVANCE)?;
 
-    let Some(bios_path) = &config.bios_path else {
-        return Err(NativeEmulatorError::GbaNoBios);
+    let bios_rom = match &config.bios_path {
+        Some(bios_path) => Some(fs::read(bios_path).map_err(NativeEmulatorError::GbaBiosLoad)?),
+        None => {
+            log::info!("No GBA BIOS path configured; using built-in BIOS");
+            None
+        }
     };
 
-    let bios_rom = fs::read(bios_path).map_err(NativeEmulatorError::GbaBiosLoad)?;
-
     let DeterminedPaths { save_path, save_state_path } = save::determine_save_paths(
         &config.common.save_path,
         &config.common.state_path,
@@ -76,7 +102,7 @@ pub fn create_gba(config: Box<GameBoyAdvanceConfig>) -> NativeEmulatorResult<Nat
     let initial_window_size = config.common.initial_window_size;
     let rom_path = rom_path.to_owned();
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let emulator = GameBoyAdvanceEmulator::create(rom, bios_rom, emulator_config, save_writer)?;
 
         let rom_title = file_name_no_ext(rom_path)?;
@@ -89,7 +115,7 @@ pub fn create_gba(config: Box<GameBoyAdvanceConfig>) -> NativeEmulatorResult<Nat
 
     let initial_inputs = GbaInputs { solar: new_solar_state(&config), ..GbaInputs::default() };
 
-    NativeGbaEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -105,6 +131,7 @@ pub fn create_gba(config: Box<GameBoyAdvanceConfig>) -> NativeEmulatorResult<Nat
             jgenesis_debugger_frontend::partial_clone_debug_fn(
                 jgenesis_debugger_frontend::gba::render_fn(),
             )
-        }),
+        })
+        .with_gdb_fn(gdb::gba_gdb_fn),
     )
 }
@@ -0,0 +1,818 @@
+//! GDB remote serial protocol server for debugging emulated CPUs with GDB or any other client that
+//! speaks the protocol.
+//!
+//! The server runs on its own thread and controls the emulator through the same backend debugger
+//! that the debugger window uses, so it only has access to what the debugger can see: each CPU is
+//! exposed as a GDB thread, registers are read-only, and memory reads/writes only reach RAM and
+//! ROM areas that the debugger can view and edit. Only one client can be connected at a time.
+
+mod gba;
+mod genesis;
+mod protocol;
+
+pub use gba::gba_gdb_fn;
+pub use genesis::{genesis_gdb_fn, sega_32x_gdb_fn, sega_cd_gdb_fn};
+
+use crate::mainloop::gdb::protocol::{
+    Breakpoint, BreakpointKind, Command, Event, PacketDecoder, ThreadId, encode_hex, encode_packet,
+};
+use crate::mainloop::runner::{NativeDebuggerRunnerProcess, RunnerCommand};
+use jgenesis_common::debug::WatchpointHit;
+use jgenesis_common::frontend::EmulatorTrait;
+use jgenesis_common::sync::SharedVarReceiver;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+use std::{mem, thread};
+use thiserror::Error;
+
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const READ_TIMEOUT: Duration = Duration::from_millis(10);
+
+const MAX_PACKET_SIZE: usize = 0x4000;
+// Each byte is sent as 2 hex digits, plus room for packet framing
+const MAX_MEMORY_READ_LEN: usize = (MAX_PACKET_SIZE - 4) / 2;
+
+const ERROR_REPLY: &str = "E01";
+
+/// Create a runner-side debugger process along with the GDB target that controls it. Called once
+/// per GDB connection.
+pub type GdbFn<Emulator> = fn() -> (Box<NativeDebuggerRunnerProcess<Emulator>>, Box<dyn GdbTarget>);
+
+#[derive(Debug, Error)]
+pub enum GdbError {
+    #[error("GDB server is not supported for this system")]
+    Unsupported,
+    #[error("Error listening on TCP port {port}: {source}")]
+    Bind {
+        port: u16,
+        #[source]
+        source: io::Error,
+    },
+    #[error("GDB socket error: {0}")]
+    Socket(#[source] io::Error),
+    #[error("Emulator debugger was stopped or replaced")]
+    EmulatorDetached,
+}
+
+/// Why and where the emulator stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopEvent {
+    pub thread: u32,
+    /// Set if the stop was caused by a read/write breakpoint
+    pub watchpoint: Option<WatchpointHit>,
+}
+
+impl StopEvent {
+    fn new(thread: u32, watchpoint: Option<WatchpointHit>) -> Self {
+        Self { thread, watchpoint }
+    }
+}
+
+/// System-specific half of the GDB server, which translates between GDB's view of the target and
+/// the backend debugger's commands and state.
+///
+/// Thread IDs are 1-based indices into [`Self::thread_names`].
+pub trait GdbTarget: Send {
+    /// Target description XML, which tells GDB the CPU architecture and register layout
+    fn target_xml(&self) -> &'static str;
+
+    fn thread_names(&self) -> &'static [&'static str];
+
+    /// Replace the full set of breakpoints and watchpoints. Each breakpoint is paired with the
+    /// thread that was selected when GDB inserted it; targets with multiple CPUs in separate
+    /// address spaces should only install a breakpoint on that thread's CPU.
+    fn update_breakpoints(&mut self, breakpoints: &[(u32, Breakpoint)]) -> Result<(), GdbError>;
+
+    fn pause(&mut self) -> Result<(), GdbError>;
+
+    fn resume(&mut self) -> Result<(), GdbError>;
+
+    fn step(&mut self, thread: u32) -> Result<(), GdbError>;
+
+    /// Returns the thread that stopped and the reason once the emulator has stopped after a pause,
+    /// a step, or a breakpoint hit
+    fn poll_stopped(&mut self) -> Option<StopEvent>;
+
+    /// Registers in the order described by the target XML, or `None` if the emulator has not
+    /// stopped yet
+    fn read_registers(&mut self, thread: u32) -> Option<Vec<u8>>;
+
+    /// Read a byte as seen by the given thread's CPU, or `None` if the address is not mapped to
+    /// a memory area that the debugger can view
+    fn read_memory(&mut self, thread: u32, address: u32) -> Option<u8>;
+
+    /// Write a byte as seen by the given thread's CPU. Returns `false` if the address is not mapped
+    /// to a memory area that the debugger can edit.
+    fn write_memory(&mut self, thread: u32, address: u32, value: u8) -> Result<bool, GdbError>;
+}
+
+/// Latest debug state sent by a backend debugger, along with tracking for whether the emulator has
+/// stopped since it was last resumed.
+///
+/// Backend debuggers send a fresh state each time the emulator stops, right before marking the CPU
+/// as breaking, so a stop is only reported once a new state has arrived _and_ the CPU is breaking.
+/// Checking the break status alone could report a stale stop if the emulator has not yet processed
+/// a resume command.
+struct BreakSnapshot<State> {
+    receiver: SharedVarReceiver<State>,
+    stop_pending: bool,
+}
+
+impl<State> BreakSnapshot<State> {
+    fn new(receiver: SharedVarReceiver<State>) -> Self {
+        Self { receiver, stop_pending: false }
+    }
+
+    fn poll_stopped(&mut self, breaking: bool) -> bool {
+        if self.receiver.get_if_updated().is_some() {
+            self.stop_pending = true;
+        }
+
+        if self.stop_pending && breaking {
+            self.stop_pending = false;
+            return true;
+        }
+
+        false
+    }
+
+    fn state(&mut self) -> Option<&mut State> {
+        self.receiver.get()
+    }
+}
+
+// Sending a command to the backend debugger only fails if the runner thread has dropped it
+fn send_error<T>(_err: T) -> GdbError {
+    GdbError::EmulatorDetached
+}
+
+/// Stops the GDB server thread when dropped.
+pub struct GdbServerHandle {
+    shutdown: Arc<AtomicBool>,
+}
+
+impl Drop for GdbServerHandle {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Start listening for GDB connections on the given localhost port.
+///
+/// # Errors
+///
+/// Returns an error if unable to bind the TCP listener.
+pub fn spawn_server<Emulator: EmulatorTrait>(
+    port: u16,
+    gdb_fn: GdbFn<Emulator>,
+    runner_sender: Sender<RunnerCommand<Emulator>>,
+) -> Result<GdbServerHandle, GdbError> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|source| GdbError::Bind { port, source })?;
+    listener.set_nonblocking(true).map_err(GdbError::Socket)?;
+
+    log::info!("GDB server listening on port {port}");
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let server = GdbServer { listener, gdb_fn, runner_sender, shutdown: Arc::clone(&shutdown) };
+    thread::spawn(move || server.run());
+
+    Ok(GdbServerHandle { shutdown })
+}
+
+struct GdbServer<Emulator: EmulatorTrait> {
+    listener: TcpListener,
+    gdb_fn: GdbFn<Emulator>,
+    runner_sender: Sender<RunnerCommand<Emulator>>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl<Emulator: EmulatorTrait> GdbServer<Emulator> {
+    fn run(self) {
+        while !self.shutdown.load(Ordering::Relaxed) {
+            let (stream, peer) = match self.listener.accept() {
+                Ok(connection) => connection,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(err) => {
+                    log::error!("Error accepting GDB connection: {err}");
+                    return;
+                }
+            };
+
+            log::info!("GDB connected from {peer}");
+
+            let (runner_process, mut target) = (self.gdb_fn)();
+            if self.runner_sender.send(RunnerCommand::StartDebugger(runner_process)).is_err() {
+                // Runner thread has terminated
+                return;
+            }
+
+            let result =
+                Session::new(stream, target.as_mut(), &self.shutdown).and_then(Session::run);
+
+            match result {
+                Ok(()) => log::info!("GDB disconnected"),
+                Err(err) => log::error!("GDB session ended: {err}"),
+            }
+
+            // Sending a command only succeeds if the runner thread still has this session's
+            // debugger process, which it will not if e.g. the debugger window has replaced it
+            let attached = target.resume().is_ok();
+
+            // Dropping the target disconnects the backend debugger, which clears all breakpoints
+            // and resumes the emulator if it is stopped
+            drop(target);
+
+            if attached {
+                let _ = self.runner_sender.send(RunnerCommand::StopDebugger);
+            }
+        }
+    }
+}
+
+enum Action {
+    Reply(String),
+    Resume,
+    Close { reply: Option<&'static str> },
+}
+
+struct Session<'a> {
+    stream: TcpStream,
+    decoder: PacketDecoder,
+    target: &'a mut dyn GdbTarget,
+    shutdown: &'a AtomicBool,
+    breakpoints: Vec<(u32, Breakpoint)>,
+    general_thread: u32,
+    continue_thread: Option<u32>,
+    last_stop: StopEvent,
+    running: bool,
+    awaiting_stop_reply: bool,
+    // Packets received while the emulator is running, other than interrupts, are handled once it
+    // stops; this is all-stop mode, so GDB should not send anything else while waiting for a stop
+    deferred_packets: VecDeque<Vec<u8>>,
+}
+
+impl<'a> Session<'a> {
+    fn new(
+        stream: TcpStream,
+        target: &'a mut dyn GdbTarget,
+        shutdown: &'a AtomicBool,
+    ) -> Result<Self, GdbError> {
+        // Accepted sockets may inherit non-blocking mode from the listener on some platforms
+        stream.set_nonblocking(false).map_err(GdbError::Socket)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT)).map_err(GdbError::Socket)?;
+        stream.set_nodelay(true).map_err(GdbError::Socket)?;
+
+        Ok(Self {
+            stream,
+            decoder: PacketDecoder::new(),
+            target,
+            shutdown,
+            breakpoints: Vec::new(),
+            general_thread: 1,
+            continue_thread: None,
+            last_stop: StopEvent::new(1, None),
+            running: false,
+            awaiting_stop_reply: false,
+            deferred_packets: VecDeque::new(),
+        })
+    }
+
+    fn run(mut self) -> Result<(), GdbError> {
+        // GDB expects the target to be stopped when it attaches
+        self.target.pause()?;
+        self.running = true;
+
+        let mut buffer = [0; 4096];
+        loop {
+            if self.shutdown.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(len) => {
+                    for &byte in &buffer[..len] {
+                        let Some(event) = self.decoder.push(byte) else { continue };
+                        if self.handle_event(event)?.is_break() {
+                            return Ok(());
+                        }
+                    }
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                    ) => {}
+                Err(err) => return Err(GdbError::Socket(err)),
+            }
+
+            if self.running
+                && let Some(stop) = self.target.poll_stopped()
+            {
+                self.running = false;
+                self.last_stop = stop;
+                self.general_thread = stop.thread;
+
+                if mem::take(&mut self.awaiting_stop_reply) {
+                    self.send(&self.stop_reply())?;
+                }
+
+                while !self.running
+                    && let Some(packet) = self.deferred_packets.pop_front()
+                {
+                    if self.handle_packet(&packet)?.is_break() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<ControlFlow<()>, GdbError> {
+        match event {
+            Event::Interrupt => {
+                if self.running {
+                    self.target.pause()?;
+                    self.awaiting_stop_reply = true;
+                }
+            }
+            Event::ChecksumMismatch => {
+                self.write(b"-")?;
+            }
+            Event::Packet(packet) => {
+                self.write(b"+")?;
+
+                if self.running {
+                    self.deferred_packets.push_back(packet);
+                } else {
+                    return self.handle_packet(&packet);
+                }
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn handle_packet(&mut self, packet: &[u8]) -> Result<ControlFlow<()>, GdbError> {
+        match self.handle_command(Command::parse(packet))? {
+            Action::Reply(reply) => self.send(&reply)?,
+            Action::Resume => {
+                self.running = true;
+                self.awaiting_stop_reply = true;
+            }
+            Action::Close { reply } => {
+                if let Some(reply) = reply {
+                    self.send(reply)?;
+                }
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn handle_command(&mut self, command: Command) -> Result<Action, GdbError> {
+        let reply = match command {
+            Command::QuerySupported => {
+                format!("PacketSize={MAX_PACKET_SIZE:x};qXfer:features:read+")
+            }
+            Command::QueryFeatures { annex, offset, length } => {
+                if annex == "target.xml" {
+                    let xml = self.target.target_xml();
+                    let start = offset.min(xml.len());
+                    let end = start.saturating_add(length).min(xml.len());
+                    let prefix = if end == xml.len() { 'l' } else { 'm' };
+                    format!("{prefix}{}", &xml[start..end])
+                } else {
+                    ERROR_REPLY.into()
+                }
+            }
+            Command::QueryFirstThreadInfo => {
+                let ids: Vec<_> = (1..=self.thread_count()).map(|id| format!("{id:x}")).collect();
+                format!("m{}", ids.join(","))
+            }
+            Command::QueryNextThreadInfo => "l".into(),
+            Command::QueryCurrentThread => format!("QC{:x}", self.general_thread),
+            Command::QueryThreadExtraInfo(thread) => match self.resolve_thread(thread) {
+                Some(id) => encode_hex(self.target.thread_names()[(id - 1) as usize].as_bytes()),
+                None => ERROR_REPLY.into(),
+            },
+            Command::QueryAttached => "1".into(),
+            Command::SetGeneralThread(thread) => match self.resolve_thread(thread) {
+                Some(id) => {
+                    self.general_thread = id;
+                    "OK".into()
+                }
+                None => ERROR_REPLY.into(),
+            },
+            Command::SetContinueThread(thread) => match thread {
+                ThreadId::Any | ThreadId::All => {
+                    self.continue_thread = None;
+                    "OK".into()
+                }
+                ThreadId::Id(_) => match self.resolve_thread(thread) {
+                    Some(id) => {
+                        self.continue_thread = Some(id);
+                        "OK".into()
+                    }
+                    None => ERROR_REPLY.into(),
+                },
+            },
+            Command::IsThreadAlive(thread) => {
+                if self.resolve_thread(thread).is_some() {
+                    "OK".into()
+                } else {
+                    ERROR_REPLY.into()
+                }
+            }
+            Command::HaltReason => self.stop_reply(),
+            Command::ReadRegisters => match self.target.read_registers(self.general_thread) {
+                Some(registers) => encode_hex(&registers),
+                None => ERROR_REPLY.into(),
+            },
+            Command::ReadRegister(n) => {
+                let registers = self.target.read_registers(self.general_thread);
+                match registers.as_ref().and_then(|registers| registers.get(4 * n..4 * (n + 1))) {
+                    Some(register) => encode_hex(register),
+                    None => "xxxxxxxx".into(),
+                }
+            }
+            // Registers are read-only; the backend debuggers have no way to edit them
+            Command::WriteRegisters | Command::WriteRegister => ERROR_REPLY.into(),
+            Command::ReadMemory { address, length } => {
+                let length = (length as usize).min(MAX_MEMORY_READ_LEN);
+                // Partial reads are allowed, and GDB will retry from the first unmapped address
+                let bytes: Vec<u8> = (0..length as u32)
+                    .map_while(|i| {
+                        self.target.read_memory(self.general_thread, address.wrapping_add(i))
+                    })
+                    .collect();
+
+                if bytes.is_empty() && length != 0 {
+                    ERROR_REPLY.into()
+                } else {
+                    encode_hex(&bytes)
+                }
+            }
+            Command::WriteMemory { address, data } => {
+                let mut reply = "OK";
+                for (i, value) in (0..).zip(data) {
+                    if !self.target.write_memory(
+                        self.general_thread,
+                        address.wrapping_add(i),
+                        value,
+                    )? {
+                        reply = ERROR_REPLY;
+                        break;
+                    }
+                }
+                reply.into()
+            }
+            Command::Continue => {
+                self.target.resume()?;
+                return Ok(Action::Resume);
+            }
+            Command::Step => {
+                // GDB sets the continue thread to the thread being stepped, but it may leave it
+                // unset and select the thread through the general thread instead
+                let thread = self.continue_thread.unwrap_or(self.general_thread);
+                self.target.step(thread)?;
+                return Ok(Action::Resume);
+            }
+            Command::InsertBreakpoint(breakpoint) => {
+                let entry = (self.general_thread, breakpoint);
+                if !self.breakpoints.contains(&entry) {
+                    self.breakpoints.push(entry);
+                }
+                self.target.update_breakpoints(&self.breakpoints)?;
+                "OK".into()
+            }
+            Command::RemoveBreakpoint(breakpoint) => {
+                // The selected thread may have changed since the breakpoint was inserted, e.g.
+                // after a stop on another CPU
+                self.breakpoints.retain(|&(_, existing)| existing != breakpoint);
+                self.target.update_breakpoints(&self.breakpoints)?;
+                "OK".into()
+            }
+            Command::Detach => return Ok(Action::Close { reply: Some("OK") }),
+            Command::Kill => return Ok(Action::Close { reply: None }),
+            Command::Unsupported => String::new(),
+        };
+
+        Ok(Action::Reply(reply))
+    }
+
+    fn thread_count(&self) -> u32 {
+        self.target.thread_names().len() as u32
+    }
+
+    fn resolve_thread(&self, thread: ThreadId) -> Option<u32> {
+        match thread {
+            ThreadId::Any => Some(self.general_thread),
+            ThreadId::All => None,
+            ThreadId::Id(id) => (1..=self.thread_count()).contains(&id).then_some(id),
+        }
+    }
+
+    fn stop_reply(&self) -> String {
+        // Signal 5 is SIGTRAP
+        let mut reply = String::from("T05");
+
+        if let Some(hit) = self.last_stop.watchpoint {
+            // GDB matches the reason against its own watchpoint types, so an access watchpoint
+            // must be reported as such even though the backend only knows read vs. write
+            let access_watch = self.breakpoints.iter().any(|&(_, breakpoint)| {
+                let (start, end) = breakpoint.address_range();
+                breakpoint.kind == BreakpointKind::AccessWatch
+                    && (start..=end).contains(&hit.address)
+            });
+            let reason = match (access_watch, hit.write) {
+                (true, _) => "awatch",
+                (false, true) => "watch",
+                (false, false) => "rwatch",
+            };
+            write!(reply, "{reason}:{:x};", hit.address).unwrap();
+        }
+
+        write!(reply, "thread:{:x};", self.last_stop.thread).unwrap();
+        reply
+    }
+
+    fn send(&mut self, reply: &str) -> Result<(), GdbError> {
+        let packet = encode_packet(reply.as_bytes());
+        self.write(&packet)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), GdbError> {
+        self.stream.write_all(bytes).map_err(GdbError::Socket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const MEMORY_START: u32 = 0x1000;
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[derive(Debug, Default)]
+    struct MockState {
+        pending_stop: Option<StopEvent>,
+        breakpoints: Vec<(u32, Breakpoint)>,
+        memory: Vec<u8>,
+    }
+
+    struct MockTarget(Arc<Mutex<MockState>>);
+
+    impl GdbTarget for MockTarget {
+        fn target_xml(&self) -> &'static str {
+            "<target/>"
+        }
+
+        fn thread_names(&self) -> &'static [&'static str] {
+            &["CPU 1", "CPU 2"]
+        }
+
+        fn update_breakpoints(
+            &mut self,
+            breakpoints: &[(u32, Breakpoint)],
+        ) -> Result<(), GdbError> {
+            self.0.lock().unwrap().breakpoints = breakpoints.to_vec();
+            Ok(())
+        }
+
+        fn pause(&mut self) -> Result<(), GdbError> {
+            self.0.lock().unwrap().pending_stop = Some(StopEvent::new(1, None));
+            Ok(())
+        }
+
+        fn resume(&mut self) -> Result<(), GdbError> {
+            Ok(())
+        }
+
+        fn step(&mut self, thread: u32) -> Result<(), GdbError> {
+            self.0.lock().unwrap().pending_stop = Some(StopEvent::new(thread, None));
+            Ok(())
+        }
+
+        fn poll_stopped(&mut self) -> Option<StopEvent> {
+            self.0.lock().unwrap().pending_stop.take()
+        }
+
+        fn read_registers(&mut self, thread: u32) -> Option<Vec<u8>> {
+            Some([thread, 0x12345678].into_iter().flat_map(u32::to_le_bytes).collect())
+        }
+
+        fn read_memory(&mut self, _thread: u32, address: u32) -> Option<u8> {
+            let offset = address.checked_sub(MEMORY_START)?;
+            self.0.lock().unwrap().memory.get(offset as usize).copied()
+        }
+
+        fn write_memory(
+            &mut self,
+            _thread: u32,
+            address: u32,
+            value: u8,
+        ) -> Result<bool, GdbError> {
+            let Some(offset) = address.checked_sub(MEMORY_START) else { return Ok(false) };
+            let mut state = self.0.lock().unwrap();
+            let Some(byte) = state.memory.get_mut(offset as usize) else { return Ok(false) };
+            *byte = value;
+            Ok(true)
+        }
+    }
+
+    struct Client(TcpStream);
+
+    impl Client {
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.0.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+
+        fn send(&mut self, payload: &str) {
+            self.0.write_all(&encode_packet(payload.as_bytes())).unwrap();
+            assert_eq!(self.read_byte(), b'+', "packet {payload:?} was not acked");
+        }
+
+        fn recv(&mut self) -> String {
+            while self.read_byte() != b'$' {}
+
+            let mut body = Vec::new();
+            loop {
+                match self.read_byte() {
+                    b'#' => break,
+                    byte => body.push(byte),
+                }
+            }
+
+            // Checksums are covered by the protocol tests
+            self.read_byte();
+            self.read_byte();
+
+            String::from_utf8(body).unwrap()
+        }
+
+        fn request(&mut self, payload: &str) -> String {
+            self.send(payload);
+            self.recv()
+        }
+
+        fn is_closed(&mut self) -> bool {
+            let mut buffer = [0; 16];
+            matches!(self.0.read(&mut buffer), Ok(0))
+        }
+    }
+
+    /// Run a session against a mock target with 256 bytes of memory at `MEMORY_START`. The session
+    /// is shut down if the client has not ended it by the time `test` returns.
+    fn run_session(test: impl FnOnce(&mut Client, &Mutex<MockState>)) -> Result<(), GdbError> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let client_stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client_stream.set_read_timeout(Some(CLIENT_TIMEOUT)).unwrap();
+        let (server_stream, _) = listener.accept().unwrap();
+
+        let state =
+            Arc::new(Mutex::new(MockState { memory: vec![0; 0x100], ..MockState::default() }));
+        let mut target = MockTarget(Arc::clone(&state));
+        let shutdown = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            let session = scope.spawn(|| {
+                Session::new(server_stream, &mut target, &shutdown).and_then(Session::run)
+            });
+
+            test(&mut Client(client_stream), &state);
+
+            shutdown.store(true, Ordering::Relaxed);
+            session.join().unwrap()
+        })
+    }
+
+    fn stop_with(state: &Mutex<MockState>, stop: StopEvent) {
+        state.lock().unwrap().pending_stop = Some(stop);
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        let result = run_session(|client, _| {
+            // Target is stopped on attach
+            assert_eq!(client.request("?"), "T05thread:1;");
+
+            assert_eq!(client.request("g"), "0100000078563412");
+            assert_eq!(client.request("Hg2"), "OK");
+            assert_eq!(client.request("g"), "0200000078563412");
+            assert_eq!(client.request("p1"), "78563412");
+            assert_eq!(client.request("p2"), "xxxxxxxx");
+            assert_eq!(client.request("P0=00000000"), ERROR_REPLY);
+
+            assert_eq!(client.request("m1000,4"), "00000000");
+            assert_eq!(client.request("M1002,2:abcd"), "OK");
+            assert_eq!(client.request("m1000,4"), "0000abcd");
+
+            // Reads stop at the first unmapped address
+            assert_eq!(client.request("m10fe,4"), "0000");
+            assert_eq!(client.request("m2000,1"), ERROR_REPLY);
+            assert_eq!(client.request("M10ff,2:0102"), ERROR_REPLY);
+            assert_eq!(client.request("m10ff,1"), "01");
+
+            assert_eq!(client.request("D"), "OK");
+            assert!(client.is_closed());
+        });
+
+        result.unwrap();
+    }
+
+    #[test]
+    fn breakpoints_track_inserting_thread() {
+        let result = run_session(|client, state| {
+            let breakpoint = |kind, address, length| Breakpoint { kind, address, length };
+            let execute = breakpoint(BreakpointKind::Software, 0x1000, 2);
+            let watch = breakpoint(BreakpointKind::WriteWatch, 0x1010, 4);
+
+            assert_eq!(client.request("Z0,1000,2"), "OK");
+            assert_eq!(client.request("Z0,1000,2"), "OK");
+            assert_eq!(client.request("Hg2"), "OK");
+            assert_eq!(client.request("Z2,1010,4"), "OK");
+            assert_eq!(state.lock().unwrap().breakpoints, vec![(1, execute), (2, watch)]);
+
+            // Removal does not depend on the selected thread
+            assert_eq!(client.request("z0,1000,2"), "OK");
+            assert_eq!(state.lock().unwrap().breakpoints, vec![(2, watch)]);
+            assert_eq!(client.request("z2,1010,4"), "OK");
+            assert!(state.lock().unwrap().breakpoints.is_empty());
+
+            // Kill has no reply
+            client.send("k");
+            assert!(client.is_closed());
+        });
+
+        result.unwrap();
+    }
+
+    #[test]
+    fn packets_deferred_until_stop() {
+        let result = run_session(|client, state| {
+            assert_eq!(client.request("?"), "T05thread:1;");
+            assert_eq!(client.request("Z2,1004,4"), "OK");
+            assert_eq!(client.request("Z4,1008,4"), "OK");
+            assert_eq!(client.request("Z3,100c,1"), "OK");
+
+            client.send("c");
+            client.send("m1004,4");
+            let hit = WatchpointHit { address: 0x1006, write: true };
+            stop_with(state, StopEvent::new(1, Some(hit)));
+            assert_eq!(client.recv(), "T05watch:1006;thread:1;");
+            assert_eq!(client.recv(), "00000000");
+
+            client.send("c");
+            let hit = WatchpointHit { address: 0x100a, write: false };
+            stop_with(state, StopEvent::new(2, Some(hit)));
+            assert_eq!(client.recv(), "T05awatch:100a;thread:2;");
+            assert_eq!(client.request("qC"), "QC2");
+
+            client.send("c");
+            let hit = WatchpointHit { address: 0x100c, write: false };
+            stop_with(state, StopEvent::new(1, Some(hit)));
+            assert_eq!(client.recv(), "T05rwatch:100c;thread:1;");
+
+            // Step the continue thread rather than the general thread
+            assert_eq!(client.request("Hc2"), "OK");
+            assert_eq!(client.request("Hg1"), "OK");
+            assert_eq!(client.request("s"), "T05thread:2;");
+            assert_eq!(client.request("?"), "T05thread:2;");
+        });
+
+        result.unwrap();
+    }
+
+    #[test]
+    fn interrupt_stops_target() {
+        let result = run_session(|client, _| {
+            assert_eq!(client.request("?"), "T05thread:1;");
+
+            client.send("c");
+            client.0.write_all(&[protocol::INTERRUPT]).unwrap();
+            assert_eq!(client.recv(), "T05thread:1;");
+
+            // Interrupts are ignored while stopped
+            client.0.write_all(&[protocol::INTERRUPT]).unwrap();
+            assert_eq!(client.request("qC"), "QC1");
+        });
+
+        result.unwrap();
+    }
+}
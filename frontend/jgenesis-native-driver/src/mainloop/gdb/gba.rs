@@ -0,0 +1,181 @@
+//! GDB target for the GBA's ARM7TDMI
+
+use crate::mainloop::gdb::protocol::Breakpoint;
+use crate::mainloop::gdb::{BreakSnapshot, GdbError, GdbTarget, StopEvent, send_error};
+use crate::mainloop::runner::NativeDebuggerRunnerProcess;
+use gba_config::GbaInputs;
+use gba_core::api::GameBoyAdvanceEmulator;
+use gba_core::api::debug::{
+    GbaBreakpoint, GbaDebugCommand, GbaDebugState, GbaDebugger, GbaDebuggerHandle, GbaMemoryArea,
+};
+use jgenesis_common::frontend::{AudioOutput, InputPoller, Renderer, SaveWriter, TickEffect};
+use jgenesis_common::sync;
+use jgenesis_debugger_frontend::{DebuggerRunnerProcess, RunTillNextResult};
+use std::error::Error;
+
+// GDB's ARM core feature expects CPSR immediately after PC
+const ARM_TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>armv4t</architecture>
+  <feature name="org.gnu.gdb.arm.core">
+    <reg name="r0" bitsize="32"/>
+    <reg name="r1" bitsize="32"/>
+    <reg name="r2" bitsize="32"/>
+    <reg name="r3" bitsize="32"/>
+    <reg name="r4" bitsize="32"/>
+    <reg name="r5" bitsize="32"/>
+    <reg name="r6" bitsize="32"/>
+    <reg name="r7" bitsize="32"/>
+    <reg name="r8" bitsize="32"/>
+    <reg name="r9" bitsize="32"/>
+    <reg name="r10" bitsize="32"/>
+    <reg name="r11" bitsize="32"/>
+    <reg name="r12" bitsize="32"/>
+    <reg name="sp" bitsize="32" type="data_ptr"/>
+    <reg name="lr" bitsize="32"/>
+    <reg name="pc" bitsize="32" type="code_ptr"/>
+    <reg name="cpsr" bitsize="32"/>
+  </feature>
+</target>
+"#;
+
+struct GbaGdbRunnerProcess(GbaDebugger);
+
+impl<R, A, I, S> DebuggerRunnerProcess<GameBoyAdvanceEmulator, R, A, I, S> for GbaGdbRunnerProcess
+where
+    R: Renderer,
+    A: AudioOutput,
+    I: InputPoller<GbaInputs>,
+    S: SaveWriter,
+{
+    fn run(
+        &mut self,
+        emulator: &mut GameBoyAdvanceEmulator,
+    ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+        self.0.process_commands(emulator);
+
+        Ok(())
+    }
+
+    fn run_emulator_till_next_frame(
+        &mut self,
+        emulator: &mut GameBoyAdvanceEmulator,
+        renderer: &mut R,
+        audio_output: &mut A,
+        input_poller: &mut I,
+        save_writer: &mut S,
+    ) -> RunTillNextResult<GameBoyAdvanceEmulator, R::Err, A::Err, S::Err> {
+        while emulator.debug_tick(renderer, audio_output, input_poller, save_writer, &mut self.0)?
+            != TickEffect::FrameRendered
+        {}
+
+        Ok(())
+    }
+}
+
+/// Map an ARM7TDMI address to the memory areas visible in the debugger
+fn gba_memory_area(address: u32) -> Option<(GbaMemoryArea, usize)> {
+    let (area, offset) = match address {
+        0x00000000..=0x00003FFF => (GbaMemoryArea::BiosRom, address),
+        0x02000000..=0x02FFFFFF => (GbaMemoryArea::Ewram, address & 0x3FFFF),
+        0x03000000..=0x03FFFFFF => (GbaMemoryArea::Iwram, address & 0x7FFF),
+        0x05000000..=0x05FFFFFF => (GbaMemoryArea::PaletteRam, address & 0x3FF),
+        0x06000000..=0x06FFFFFF => {
+            // VRAM is 96KB mirrored in 128KB blocks, with the last 32KB mirroring the previous 32KB
+            let offset = address & 0x1FFFF;
+            (GbaMemoryArea::Vram, if offset >= 0x18000 { offset - 0x8000 } else { offset })
+        }
+        0x07000000..=0x07FFFFFF => (GbaMemoryArea::Oam, address & 0x3FF),
+        0x08000000..=0x0DFFFFFF => (GbaMemoryArea::CartridgeRom, address & 0x1FFFFFF),
+        _ => return None,
+    };
+
+    Some((area, offset as usize))
+}
+
+struct GbaGdbTarget {
+    handle: GbaDebuggerHandle,
+    snapshot: BreakSnapshot<GbaDebugState>,
+}
+
+#[must_use]
+pub fn gba_gdb_fn() -> (Box<NativeDebuggerRunnerProcess<GameBoyAdvanceEmulator>>, Box<dyn GdbTarget>)
+{
+    let (state_sender, state_receiver) = sync::new_shared_var();
+    let (debugger, handle) = GbaDebugger::new(state_sender);
+
+    let target = GbaGdbTarget { handle, snapshot: BreakSnapshot::new(state_receiver) };
+    (Box::new(GbaGdbRunnerProcess(debugger)), Box::new(target))
+}
+
+impl GdbTarget for GbaGdbTarget {
+    fn target_xml(&self) -> &'static str {
+        ARM_TARGET_XML
+    }
+
+    fn thread_names(&self) -> &'static [&'static str] {
+        &["ARM7TDMI"]
+    }
+
+    fn update_breakpoints(&mut self, breakpoints: &[(u32, Breakpoint)]) -> Result<(), GdbError> {
+        let breakpoints = breakpoints
+            .iter()
+            .map(|&(_, breakpoint)| {
+                let (start_address, end_address) = breakpoint.address_range();
+                GbaBreakpoint {
+                    start_address,
+                    end_address,
+                    read: breakpoint.read(),
+                    write: breakpoint.write(),
+                    execute: breakpoint.execute(),
+                }
+            })
+            .collect();
+
+        self.handle
+            .send_command(GbaDebugCommand::UpdateBreakpoints(breakpoints))
+            .map_err(send_error)
+    }
+
+    fn pause(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(GbaDebugCommand::BreakPause).map_err(send_error)
+    }
+
+    fn resume(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(GbaDebugCommand::BreakResume).map_err(send_error)
+    }
+
+    fn step(&mut self, _thread: u32) -> Result<(), GdbError> {
+        self.handle.send_command(GbaDebugCommand::BreakStep).map_err(send_error)
+    }
+
+    fn poll_stopped(&mut self) -> Option<StopEvent> {
+        let status = self.handle.break_status();
+        self.snapshot.poll_stopped(status.breaking).then(|| StopEvent::new(1, status.watchpoint))
+    }
+
+    fn read_registers(&mut self, _thread: u32) -> Option<Vec<u8>> {
+        let state = self.snapshot.state()?;
+        Some(state.registers.into_iter().chain([state.cpsr]).flat_map(u32::to_le_bytes).collect())
+    }
+
+    fn read_memory(&mut self, _thread: u32, address: u32) -> Option<u8> {
+        let (area, offset) = gba_memory_area(address)?;
+        self.snapshot.state()?.memory(area).get(offset).copied()
+    }
+
+    fn write_memory(&mut self, _thread: u32, address: u32, value: u8) -> Result<bool, GdbError> {
+        let Some((area, offset)) = gba_memory_area(address) else { return Ok(false) };
+        let Some(state) = self.snapshot.state() else { return Ok(false) };
+        if offset >= state.memory(area).len() {
+            return Ok(false);
+        }
+
+        state.apply_memory_edit(area, offset, value);
+        self.handle
+            .send_command(GbaDebugCommand::EditMemory(area, offset, value))
+            .map_err(send_error)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,603 @@
+//! GDB targets for the 68000 in Genesis and Sega CD, and for the two SH-2s in the 32X
+
+use crate::mainloop::gdb::protocol::Breakpoint;
+use crate::mainloop::gdb::{BreakSnapshot, GdbError, GdbTarget, StopEvent, send_error};
+use crate::mainloop::runner::NativeDebuggerRunnerProcess;
+use genesis_config::GenesisInputs;
+use genesis_core::GenesisEmulator;
+use genesis_core::api::debug::{
+    GenesisDebugCommand, GenesisDebugState, GenesisDebugger, GenesisDebuggerHandle,
+    GenesisMemoryArea, M68000Breakpoint,
+};
+use jgenesis_common::frontend::{AudioOutput, InputPoller, Renderer, SaveWriter, TickEffect};
+use jgenesis_common::sync;
+use jgenesis_debugger_frontend::{DebuggerRunnerProcess, RunTillNextResult};
+use m68000_emu::M68000;
+use s32x_core::WhichCpu;
+use s32x_core::api::Sega32XEmulator;
+use s32x_core::api::debug::{
+    S32XMemoryArea, Sega32XDebugCommand, Sega32XDebugState, Sega32XDebugger, Sega32XDebuggerHandle,
+    Sh2Breakpoint,
+};
+use segacd_core::api::SegaCdEmulator;
+use segacd_core::api::debug::{
+    SegaCdDebugCommand, SegaCdDebugState, SegaCdDebugger, SegaCdDebuggerHandle, SegaCdMemoryArea,
+};
+use sh2_emu::Sh2;
+use std::error::Error;
+
+// The 68000 has a 24-bit address bus
+const M68K_ADDRESS_MASK: u32 = 0xFFFFFF;
+
+// The top 3 bits of SH-2 addresses select the cache behavior and do not affect the physical address
+const SH2_ADDRESS_MASK: u32 = 0x1FFFFFFF;
+
+const M68K_TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>m68k:68000</architecture>
+  <feature name="org.gnu.gdb.m68k.core">
+    <reg name="d0" bitsize="32"/>
+    <reg name="d1" bitsize="32"/>
+    <reg name="d2" bitsize="32"/>
+    <reg name="d3" bitsize="32"/>
+    <reg name="d4" bitsize="32"/>
+    <reg name="d5" bitsize="32"/>
+    <reg name="d6" bitsize="32"/>
+    <reg name="d7" bitsize="32"/>
+    <reg name="a0" bitsize="32" type="data_ptr"/>
+    <reg name="a1" bitsize="32" type="data_ptr"/>
+    <reg name="a2" bitsize="32" type="data_ptr"/>
+    <reg name="a3" bitsize="32" type="data_ptr"/>
+    <reg name="a4" bitsize="32" type="data_ptr"/>
+    <reg name="a5" bitsize="32" type="data_ptr"/>
+    <reg name="fp" bitsize="32" type="data_ptr"/>
+    <reg name="sp" bitsize="32" type="data_ptr"/>
+    <reg name="ps" bitsize="32"/>
+    <reg name="pc" bitsize="32" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+// GDB has a built-in register layout for SH-2: r0-r15, pc, pr, gbr, vbr, mach, macl, sr
+const SH2_TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>sh2</architecture>
+</target>
+"#;
+
+macro_rules! impl_gdb_runner_process {
+    ($process:ident, $emulator:ty, $debugger:ty) => {
+        struct $process($debugger);
+
+        impl<R, A, I, S> DebuggerRunnerProcess<$emulator, R, A, I, S> for $process
+        where
+            R: Renderer,
+            A: AudioOutput,
+            I: InputPoller<GenesisInputs>,
+            S: SaveWriter,
+        {
+            fn run(
+                &mut self,
+                emulator: &mut $emulator,
+            ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+                let mut debug_view = emulator.as_debug_view();
+                self.0.process_commands(&mut debug_view);
+
+                Ok(())
+            }
+
+            fn run_emulator_till_next_frame(
+                &mut self,
+                emulator: &mut $emulator,
+                renderer: &mut R,
+                audio_output: &mut A,
+                input_poller: &mut I,
+                save_writer: &mut S,
+            ) -> RunTillNextResult<$emulator, R::Err, A::Err, S::Err> {
+                while emulator.debug_tick(
+                    renderer,
+                    audio_output,
+                    input_poller,
+                    save_writer,
+                    &mut self.0,
+                )? != TickEffect::FrameRendered
+                {}
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_gdb_runner_process!(GenesisGdbRunnerProcess, GenesisEmulator, GenesisDebugger);
+impl_gdb_runner_process!(SegaCdGdbRunnerProcess, SegaCdEmulator, SegaCdDebugger);
+impl_gdb_runner_process!(Sega32XGdbRunnerProcess, Sega32XEmulator, Sega32XDebugger);
+
+fn m68k_registers(m68k: &M68000) -> Vec<u8> {
+    m68k.data_registers()
+        .into_iter()
+        .chain(m68k.address_registers())
+        .chain([m68k.stack_pointer(), m68k.status_register().into(), m68k.pc()])
+        .flat_map(u32::to_be_bytes)
+        .collect()
+}
+
+fn sh2_registers(sh2: &Sh2) -> Vec<u8> {
+    let registers = sh2.registers();
+    registers
+        .gpr
+        .into_iter()
+        .chain([
+            registers.pc,
+            registers.pr,
+            registers.gbr,
+            registers.vbr,
+            registers.mach,
+            registers.macl,
+            registers.sr.into(),
+        ])
+        .flat_map(u32::to_be_bytes)
+        .collect()
+}
+
+fn m68k_breakpoints(
+    breakpoints: &[(u32, Breakpoint)],
+    thread_filter: impl Fn(u32) -> bool,
+) -> Vec<M68000Breakpoint> {
+    breakpoints
+        .iter()
+        .filter(|&&(thread, _)| thread_filter(thread))
+        .map(|&(_, breakpoint)| {
+            let (start_address, end_address) = breakpoint.address_range();
+            M68000Breakpoint {
+                start_address: start_address & M68K_ADDRESS_MASK,
+                end_address: end_address & M68K_ADDRESS_MASK,
+                read: breakpoint.read(),
+                write: breakpoint.write(),
+                execute: breakpoint.execute(),
+            }
+        })
+        .collect()
+}
+
+fn sh2_breakpoints(breakpoints: &[(u32, Breakpoint)]) -> Vec<Sh2Breakpoint> {
+    breakpoints
+        .iter()
+        .map(|&(_, breakpoint)| {
+            let (start_address, end_address) = breakpoint.address_range();
+            Sh2Breakpoint {
+                start_address: start_address & SH2_ADDRESS_MASK,
+                end_address: end_address & SH2_ADDRESS_MASK,
+                read: breakpoint.read(),
+                write: breakpoint.write(),
+                execute: breakpoint.execute(),
+            }
+        })
+        .collect()
+}
+
+/// Map a Genesis 68000 address to the memory areas visible in the debugger
+fn genesis_memory_area(address: u32) -> Option<(GenesisMemoryArea, usize)> {
+    let address = address & M68K_ADDRESS_MASK;
+    match address {
+        0x000000..=0x3FFFFF => Some((GenesisMemoryArea::CartridgeRom, address as usize)),
+        0xA00000..=0xA03FFF => Some((GenesisMemoryArea::AudioRam, (address & 0x1FFF) as usize)),
+        0xE00000..=0xFFFFFF => Some((GenesisMemoryArea::WorkingRam, (address & 0xFFFF) as usize)),
+        _ => None,
+    }
+}
+
+fn read_genesis_memory(state: &mut GenesisDebugState, address: u32) -> Option<u8> {
+    let (area, offset) = genesis_memory_area(address)?;
+    let view = state.memory_view(area);
+    (offset < view.len()).then(|| view.read(offset))
+}
+
+fn write_genesis_memory(
+    state: &mut GenesisDebugState,
+    area: GenesisMemoryArea,
+    offset: usize,
+    value: u8,
+) -> bool {
+    let mut view = state.memory_view(area);
+    if offset >= view.len() {
+        return false;
+    }
+
+    view.write(offset, value);
+    true
+}
+
+struct GenesisGdbTarget {
+    handle: GenesisDebuggerHandle,
+    snapshot: BreakSnapshot<GenesisDebugState>,
+}
+
+#[must_use]
+pub fn genesis_gdb_fn() -> (Box<NativeDebuggerRunnerProcess<GenesisEmulator>>, Box<dyn GdbTarget>) {
+    let (state_sender, state_receiver) = sync::new_shared_var();
+    let (debugger, handle) = GenesisDebugger::new(state_sender);
+
+    let target = GenesisGdbTarget { handle, snapshot: BreakSnapshot::new(state_receiver) };
+    (Box::new(GenesisGdbRunnerProcess(debugger)), Box::new(target))
+}
+
+impl GdbTarget for GenesisGdbTarget {
+    fn target_xml(&self) -> &'static str {
+        M68K_TARGET_XML
+    }
+
+    fn thread_names(&self) -> &'static [&'static str] {
+        &["68000"]
+    }
+
+    fn update_breakpoints(&mut self, breakpoints: &[(u32, Breakpoint)]) -> Result<(), GdbError> {
+        let breakpoints = m68k_breakpoints(breakpoints, |_| true);
+        self.handle
+            .send_command(GenesisDebugCommand::Update68kBreakpoints(breakpoints))
+            .map_err(send_error)
+    }
+
+    fn pause(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(GenesisDebugCommand::BreakPause68k).map_err(send_error)
+    }
+
+    fn resume(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(GenesisDebugCommand::BreakResume).map_err(send_error)
+    }
+
+    fn step(&mut self, _thread: u32) -> Result<(), GdbError> {
+        self.handle.send_command(GenesisDebugCommand::BreakStep68k).map_err(send_error)
+    }
+
+    fn poll_stopped(&mut self) -> Option<StopEvent> {
+        let status = self.handle.m68k_break_status();
+        self.snapshot.poll_stopped(status.breaking).then(|| StopEvent::new(1, status.watchpoint))
+    }
+
+    fn read_registers(&mut self, _thread: u32) -> Option<Vec<u8>> {
+        self.snapshot.state().map(|state| m68k_registers(state.m68k()))
+    }
+
+    fn read_memory(&mut self, _thread: u32, address: u32) -> Option<u8> {
+        read_genesis_memory(self.snapshot.state()?, address)
+    }
+
+    fn write_memory(&mut self, _thread: u32, address: u32, value: u8) -> Result<bool, GdbError> {
+        let Some((area, offset)) = genesis_memory_area(address) else { return Ok(false) };
+        let Some(state) = self.snapshot.state() else { return Ok(false) };
+        if !write_genesis_memory(state, area, offset, value) {
+            return Ok(false);
+        }
+
+        self.handle
+            .send_command(GenesisDebugCommand::EditMemory(area, offset, value))
+            .map_err(send_error)?;
+        Ok(true)
+    }
+}
+
+const SEGA_CD_MAIN_THREAD: u32 = 1;
+const SEGA_CD_SUB_THREAD: u32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+enum SegaCdMemory {
+    Genesis(GenesisMemoryArea, usize),
+    SegaCd(SegaCdMemoryArea, usize),
+}
+
+impl SegaCdMemory {
+    fn map(state: &SegaCdDebugState, thread: u32, address: u32) -> Option<Self> {
+        let address = address & M68K_ADDRESS_MASK;
+
+        if thread == SEGA_CD_SUB_THREAD {
+            return match address {
+                0x000000..=0x07FFFF => {
+                    Some(Self::SegaCd(SegaCdMemoryArea::PrgRam, address as usize))
+                }
+                _ => None,
+            };
+        }
+
+        match address {
+            0x000000..=0x01FFFF => Some(Self::SegaCd(SegaCdMemoryArea::BiosRom, address as usize)),
+            0x020000..=0x03FFFF => {
+                let bank_offset = usize::from(state.main_cpu_prg_ram_bank()) << 17;
+                let offset = bank_offset | (address & 0x1FFFF) as usize;
+                Some(Self::SegaCd(SegaCdMemoryArea::PrgRam, offset))
+            }
+            0xE00000..=0xFFFFFF => {
+                Some(Self::Genesis(GenesisMemoryArea::WorkingRam, (address & 0xFFFF) as usize))
+            }
+            _ => None,
+        }
+    }
+
+    fn read(self, state: &mut SegaCdDebugState) -> Option<u8> {
+        let view = match self {
+            Self::Genesis(area, _) => state.genesis.memory_view(area),
+            Self::SegaCd(area, _) => state.scd_memory_view(area),
+        };
+        let offset = self.offset();
+        (offset < view.len()).then(|| view.read(offset))
+    }
+
+    fn write(self, state: &mut SegaCdDebugState, value: u8) -> bool {
+        let mut view = match self {
+            Self::Genesis(area, _) => state.genesis.memory_view(area),
+            Self::SegaCd(area, _) => state.scd_memory_view(area),
+        };
+        let offset = self.offset();
+        if offset >= view.len() {
+            return false;
+        }
+
+        view.write(offset, value);
+        true
+    }
+
+    fn offset(self) -> usize {
+        match self {
+            Self::Genesis(_, offset) | Self::SegaCd(_, offset) => offset,
+        }
+    }
+
+    fn edit_command(self, value: u8) -> SegaCdDebugCommand {
+        match self {
+            Self::Genesis(area, offset) => {
+                SegaCdDebugCommand::EditGenesisMemory(area, offset, value)
+            }
+            Self::SegaCd(area, offset) => SegaCdDebugCommand::EditSegaCdMemory(area, offset, value),
+        }
+    }
+}
+
+struct SegaCdGdbTarget {
+    handle: SegaCdDebuggerHandle,
+    snapshot: BreakSnapshot<SegaCdDebugState>,
+}
+
+#[must_use]
+pub fn sega_cd_gdb_fn() -> (Box<NativeDebuggerRunnerProcess<SegaCdEmulator>>, Box<dyn GdbTarget>) {
+    let (state_sender, state_receiver) = sync::new_shared_var();
+    let (debugger, handle) = SegaCdDebugger::new(state_sender);
+
+    let target = SegaCdGdbTarget { handle, snapshot: BreakSnapshot::new(state_receiver) };
+    (Box::new(SegaCdGdbRunnerProcess(debugger)), Box::new(target))
+}
+
+impl GdbTarget for SegaCdGdbTarget {
+    fn target_xml(&self) -> &'static str {
+        M68K_TARGET_XML
+    }
+
+    fn thread_names(&self) -> &'static [&'static str] {
+        &["Main 68000", "Sub 68000"]
+    }
+
+    // The two 68000s have different memory maps, so a breakpoint address is only meaningful for
+    // the CPU that was selected when the breakpoint was inserted
+    fn update_breakpoints(&mut self, breakpoints: &[(u32, Breakpoint)]) -> Result<(), GdbError> {
+        let main_breakpoints = m68k_breakpoints(breakpoints, |thread| thread != SEGA_CD_SUB_THREAD);
+        let sub_breakpoints = m68k_breakpoints(breakpoints, |thread| thread == SEGA_CD_SUB_THREAD);
+        self.handle
+            .send_command(SegaCdDebugCommand::UpdateMain68kBreakpoints(main_breakpoints))
+            .map_err(send_error)?;
+        self.handle
+            .send_command(SegaCdDebugCommand::UpdateSub68kBreakpoints(sub_breakpoints))
+            .map_err(send_error)
+    }
+
+    fn pause(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(SegaCdDebugCommand::BreakPauseMain68k).map_err(send_error)
+    }
+
+    fn resume(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(SegaCdDebugCommand::BreakResume).map_err(send_error)
+    }
+
+    fn step(&mut self, thread: u32) -> Result<(), GdbError> {
+        let command = if thread == SEGA_CD_SUB_THREAD {
+            SegaCdDebugCommand::BreakStepSub68k
+        } else {
+            SegaCdDebugCommand::BreakStepMain68k
+        };
+        self.handle.send_command(command).map_err(send_error)
+    }
+
+    fn poll_stopped(&mut self) -> Option<StopEvent> {
+        let main_status = self.handle.main_cpu_break_status();
+        let sub_status = self.handle.sub_cpu_break_status();
+        if !self.snapshot.poll_stopped(main_status.breaking || sub_status.breaking) {
+            return None;
+        }
+
+        Some(if main_status.breaking {
+            StopEvent::new(SEGA_CD_MAIN_THREAD, main_status.watchpoint)
+        } else {
+            StopEvent::new(SEGA_CD_SUB_THREAD, sub_status.watchpoint)
+        })
+    }
+
+    fn read_registers(&mut self, thread: u32) -> Option<Vec<u8>> {
+        let state = self.snapshot.state()?;
+        let m68k =
+            if thread == SEGA_CD_SUB_THREAD { state.sub_cpu() } else { state.genesis.m68k() };
+        Some(m68k_registers(m68k))
+    }
+
+    fn read_memory(&mut self, thread: u32, address: u32) -> Option<u8> {
+        let state = self.snapshot.state()?;
+        SegaCdMemory::map(state, thread, address)?.read(state)
+    }
+
+    fn write_memory(&mut self, thread: u32, address: u32, value: u8) -> Result<bool, GdbError> {
+        let Some(state) = self.snapshot.state() else { return Ok(false) };
+        let Some(memory) = SegaCdMemory::map(state, thread, address) else { return Ok(false) };
+        if !memory.write(state, value) {
+            return Ok(false);
+        }
+
+        self.handle.send_command(memory.edit_command(value)).map_err(send_error)?;
+        Ok(true)
+    }
+}
+
+const SH2_THREADS: [WhichCpu; 2] = [WhichCpu::Master, WhichCpu::Slave];
+
+fn sh2_thread_cpu(thread: u32) -> WhichCpu {
+    if thread == 2 { WhichCpu::Slave } else { WhichCpu::Master }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Sega32XMemory {
+    CartridgeRom(usize),
+    Sdram(usize),
+}
+
+impl Sega32XMemory {
+    fn map(address: u32) -> Option<Self> {
+        let address = address & SH2_ADDRESS_MASK;
+        match address {
+            0x02000000..=0x023FFFFF => Some(Self::CartridgeRom((address & 0x3FFFFF) as usize)),
+            0x06000000..=0x06FFFFFF => Some(Self::Sdram((address & 0x3FFFF) as usize)),
+            _ => None,
+        }
+    }
+
+    fn read(self, state: &mut Sega32XDebugState) -> Option<u8> {
+        match self {
+            Self::CartridgeRom(offset) => {
+                let view = state.genesis.memory_view(GenesisMemoryArea::CartridgeRom);
+                (offset < view.len()).then(|| view.read(offset))
+            }
+            Self::Sdram(offset) => {
+                let view = state.s32x_memory_view(S32XMemoryArea::Sdram);
+                (offset < view.len()).then(|| view.read(offset))
+            }
+        }
+    }
+
+    fn write(self, state: &mut Sega32XDebugState, value: u8) -> bool {
+        match self {
+            Self::CartridgeRom(offset) => write_genesis_memory(
+                &mut state.genesis,
+                GenesisMemoryArea::CartridgeRom,
+                offset,
+                value,
+            ),
+            Self::Sdram(offset) => {
+                let mut view = state.s32x_memory_view(S32XMemoryArea::Sdram);
+                if offset >= view.len() {
+                    return false;
+                }
+
+                view.write(offset, value);
+                true
+            }
+        }
+    }
+
+    fn edit_command(self, value: u8) -> Sega32XDebugCommand {
+        match self {
+            Self::CartridgeRom(offset) => Sega32XDebugCommand::EditGenesisMemory(
+                GenesisMemoryArea::CartridgeRom,
+                offset,
+                value,
+            ),
+            Self::Sdram(offset) => {
+                Sega32XDebugCommand::Edit32XMemory(S32XMemoryArea::Sdram, offset, value)
+            }
+        }
+    }
+}
+
+struct Sega32XGdbTarget {
+    handle: Sega32XDebuggerHandle,
+    snapshot: BreakSnapshot<Sega32XDebugState>,
+}
+
+#[must_use]
+pub fn sega_32x_gdb_fn() -> (Box<NativeDebuggerRunnerProcess<Sega32XEmulator>>, Box<dyn GdbTarget>)
+{
+    let (state_sender, state_receiver) = sync::new_shared_var();
+    let (debugger, handle) = Sega32XDebugger::new(state_sender);
+
+    let target = Sega32XGdbTarget { handle, snapshot: BreakSnapshot::new(state_receiver) };
+    (Box::new(Sega32XGdbRunnerProcess(debugger)), Box::new(target))
+}
+
+impl GdbTarget for Sega32XGdbTarget {
+    fn target_xml(&self) -> &'static str {
+        SH2_TARGET_XML
+    }
+
+    fn thread_names(&self) -> &'static [&'static str] {
+        &["Master SH-2", "Slave SH-2"]
+    }
+
+    // Both SH-2s share the same memory map, so apply breakpoints to both CPUs regardless of which
+    // thread was selected
+    fn update_breakpoints(&mut self, breakpoints: &[(u32, Breakpoint)]) -> Result<(), GdbError> {
+        let breakpoints = sh2_breakpoints(breakpoints);
+        for which in SH2_THREADS {
+            self.handle
+                .send_command(Sega32XDebugCommand::UpdateSh2Breakpoints(which, breakpoints.clone()))
+                .map_err(send_error)?;
+        }
+
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<(), GdbError> {
+        self.handle
+            .send_command(Sega32XDebugCommand::BreakPauseSh2(WhichCpu::Master))
+            .map_err(send_error)
+    }
+
+    fn resume(&mut self) -> Result<(), GdbError> {
+        self.handle.send_command(Sega32XDebugCommand::BreakResume).map_err(send_error)
+    }
+
+    fn step(&mut self, thread: u32) -> Result<(), GdbError> {
+        self.handle
+            .send_command(Sega32XDebugCommand::BreakStepSh2(sh2_thread_cpu(thread)))
+            .map_err(send_error)
+    }
+
+    fn poll_stopped(&mut self) -> Option<StopEvent> {
+        let status = self.handle.sh2_break_status();
+        if !self.snapshot.poll_stopped(status.master.breaking || status.slave.breaking) {
+            return None;
+        }
+
+        Some(if status.master.breaking {
+            StopEvent::new(1, status.master.watchpoint)
+        } else {
+            StopEvent::new(2, status.slave.watchpoint)
+        })
+    }
+
+    fn read_registers(&mut self, thread: u32) -> Option<Vec<u8>> {
+        let state = self.snapshot.state()?;
+        Some(sh2_registers(state.sh2(sh2_thread_cpu(thread))))
+    }
+
+    // Both SH-2s share the same memory map, so memory accesses do not depend on the thread
+    fn read_memory(&mut self, _thread: u32, address: u32) -> Option<u8> {
+        Sega32XMemory::map(address)?.read(self.snapshot.state()?)
+    }
+
+    fn write_memory(&mut self, _thread: u32, address: u32, value: u8) -> Result<bool, GdbError> {
+        let Some(memory) = Sega32XMemory::map(address) else { return Ok(false) };
+        let Some(state) = self.snapshot.state() else { return Ok(false) };
+        if !memory.write(state, value) {
+            return Ok(false);
+        }
+
+        self.handle.send_command(memory.edit_command(value)).map_err(send_error)?;
+        Ok(true)
+    }
+}
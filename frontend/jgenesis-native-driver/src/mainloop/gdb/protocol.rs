@@ -0,0 +1,425 @@
+//! GDB remote serial protocol packet framing and command parsing.
+//!
+//! Only the subset of the protocol needed for all-stop debugging is supported. Packets that are
+//! not recognized parse to [`Command::Unsupported`], which should be answered with an empty reply.
+
+use std::fmt::Write;
+
+pub const INTERRUPT: u8 = 0x03;
+
+const ESCAPE: u8 = b'}';
+const ESCAPE_XOR: u8 = 0x20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Packet(Vec<u8>),
+    /// Packet with a bad checksum; should be answered with a NAK so that GDB retransmits
+    ChecksumMismatch,
+    /// Ctrl-C sent while the target is running
+    Interrupt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DecoderState {
+    #[default]
+    Idle,
+    Body,
+    Escape,
+    Checksum0,
+    Checksum1(u8),
+}
+
+#[derive(Debug, Default)]
+pub struct PacketDecoder {
+    state: DecoderState,
+    body: Vec<u8>,
+    checksum: u8,
+}
+
+impl PacketDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process one byte received from GDB. Acks (`+`) and NAKs (`-`) sent by GDB are ignored
+    /// because this implementation never retransmits.
+    pub fn push(&mut self, byte: u8) -> Option<Event> {
+        match self.state {
+            DecoderState::Idle => match byte {
+                b'$' => {
+                    self.state = DecoderState::Body;
+                    self.body.clear();
+                    self.checksum = 0;
+                }
+                INTERRUPT => return Some(Event::Interrupt),
+                _ => {}
+            },
+            DecoderState::Body => {
+                match byte {
+                    b'#' => {
+                        self.state = DecoderState::Checksum0;
+                        return None;
+                    }
+                    ESCAPE => self.state = DecoderState::Escape,
+                    _ => self.body.push(byte),
+                }
+                self.checksum = self.checksum.wrapping_add(byte);
+            }
+            DecoderState::Escape => {
+                self.body.push(byte ^ ESCAPE_XOR);
+                self.checksum = self.checksum.wrapping_add(byte);
+                self.state = DecoderState::Body;
+            }
+            DecoderState::Checksum0 => {
+                self.state = DecoderState::Checksum1(byte);
+            }
+            DecoderState::Checksum1(high) => {
+                self.state = DecoderState::Idle;
+
+                let expected =
+                    parse_hex(&[high, byte]).and_then(|checksum| u8::try_from(checksum).ok());
+                return Some(if expected == Some(self.checksum) {
+                    Event::Packet(std::mem::take(&mut self.body))
+                } else {
+                    Event::ChecksumMismatch
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Frame a reply payload as `$<payload>#<checksum>`, escaping bytes that have special meaning.
+pub fn encode_packet(payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(payload.len() + 4);
+    packet.push(b'$');
+
+    for &byte in payload {
+        if matches!(byte, b'$' | b'#' | b'*' | ESCAPE) {
+            packet.extend([ESCAPE, byte ^ ESCAPE_XOR]);
+        } else {
+            packet.push(byte);
+        }
+    }
+
+    let checksum = packet[1..].iter().fold(0_u8, |sum, &byte| sum.wrapping_add(byte));
+    packet.push(b'#');
+    packet.extend(format!("{checksum:02x}").bytes());
+
+    packet
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::with_capacity(2 * bytes.len()), |mut s, byte| {
+        write!(s, "{byte:02x}").unwrap();
+        s
+    })
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    hex.chunks_exact(2).map(|chunk| parse_hex(chunk).map(|byte| byte as u8)).collect()
+}
+
+fn parse_hex(hex: &[u8]) -> Option<u32> {
+    if hex.is_empty() || hex.len() > 8 {
+        return None;
+    }
+
+    let s = std::str::from_utf8(hex).ok()?;
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn parse_address_length(args: &[u8]) -> Option<(u32, u32)> {
+    let (address, length) = split_once(args, b',')?;
+    Some((parse_hex(address)?, parse_hex(length)?))
+}
+
+fn split_once(bytes: &[u8], delimiter: u8) -> Option<(&[u8], &[u8])> {
+    let i = bytes.iter().position(|&byte| byte == delimiter)?;
+    Some((&bytes[..i], &bytes[i + 1..]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadId {
+    Any,
+    All,
+    Id(u32),
+}
+
+impl ThreadId {
+    fn parse(s: &[u8]) -> Option<Self> {
+        match s {
+            b"0" => Some(Self::Any),
+            b"-1" => Some(Self::All),
+            _ => parse_hex(s).map(Self::Id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BreakpointKind {
+    Software,
+    Hardware,
+    WriteWatch,
+    ReadWatch,
+    AccessWatch,
+}
+
+impl BreakpointKind {
+    fn parse(byte: u8) -> Option<Self> {
+        match byte {
+            b'0' => Some(Self::Software),
+            b'1' => Some(Self::Hardware),
+            b'2' => Some(Self::WriteWatch),
+            b'3' => Some(Self::ReadWatch),
+            b'4' => Some(Self::AccessWatch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+    pub address: u32,
+    /// Instruction length for execute breakpoints, number of bytes watched for watchpoints
+    pub length: u32,
+}
+
+impl Breakpoint {
+    pub fn execute(self) -> bool {
+        matches!(self.kind, BreakpointKind::Software | BreakpointKind::Hardware)
+    }
+
+    pub fn read(self) -> bool {
+        matches!(self.kind, BreakpointKind::ReadWatch | BreakpointKind::AccessWatch)
+    }
+
+    pub fn write(self) -> bool {
+        matches!(self.kind, BreakpointKind::WriteWatch | BreakpointKind::AccessWatch)
+    }
+
+    /// Inclusive address range that triggers this breakpoint
+    pub fn address_range(self) -> (u32, u32) {
+        if self.execute() {
+            (self.address, self.address)
+        } else {
+            (self.address, self.address.wrapping_add(self.length.max(1) - 1))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    QuerySupported,
+    QueryFeatures { annex: String, offset: usize, length: usize },
+    QueryFirstThreadInfo,
+    QueryNextThreadInfo,
+    QueryCurrentThread,
+    QueryThreadExtraInfo(ThreadId),
+    QueryAttached,
+    SetGeneralThread(ThreadId),
+    SetContinueThread(ThreadId),
+    IsThreadAlive(ThreadId),
+    HaltReason,
+    ReadRegisters,
+    ReadRegister(usize),
+    WriteRegisters,
+    WriteRegister,
+    ReadMemory { address: u32, length: u32 },
+    WriteMemory { address: u32, data: Vec<u8> },
+    Continue,
+    Step,
+    InsertBreakpoint(Breakpoint),
+    RemoveBreakpoint(Breakpoint),
+    Detach,
+    Kill,
+    Unsupported,
+}
+
+impl Command {
+    pub fn parse(packet: &[u8]) -> Self {
+        Self::try_parse(packet).unwrap_or(Self::Unsupported)
+    }
+
+    fn try_parse(packet: &[u8]) -> Option<Self> {
+        let (&first, args) = packet.split_first()?;
+
+        let command = match first {
+            b'q' => return Self::parse_query(args),
+            b'H' => {
+                let (&op, thread) = args.split_first()?;
+                let thread = ThreadId::parse(thread)?;
+                match op {
+                    b'g' => Self::SetGeneralThread(thread),
+                    b'c' => Self::SetContinueThread(thread),
+                    _ => return None,
+                }
+            }
+            b'T' => Self::IsThreadAlive(ThreadId::parse(args)?),
+            b'?' => Self::HaltReason,
+            b'g' => Self::ReadRegisters,
+            b'G' => Self::WriteRegisters,
+            b'p' => Self::ReadRegister(parse_hex(args)? as usize),
+            b'P' => Self::WriteRegister,
+            b'm' => {
+                let (address, length) = parse_address_length(args)?;
+                Self::ReadMemory { address, length }
+            }
+            b'M' => {
+                let (address_length, data) = split_once(args, b':')?;
+                let (address, length) = parse_address_length(address_length)?;
+                let data = decode_hex(data)?;
+                if data.len() != length as usize {
+                    return None;
+                }
+                Self::WriteMemory { address, data }
+            }
+            // Resuming at a different address is not supported
+            b'c' if args.is_empty() => Self::Continue,
+            b's' if args.is_empty() => Self::Step,
+            b'Z' | b'z' => {
+                let mut fields = args.split(|&byte| byte == b',');
+                let kind = match fields.next()? {
+                    [kind] => BreakpointKind::parse(*kind)?,
+                    _ => return None,
+                };
+                let address = parse_hex(fields.next()?)?;
+                let length = parse_hex(fields.next()?)?;
+
+                let breakpoint = Breakpoint { kind, address, length };
+                if first == b'Z' {
+                    Self::InsertBreakpoint(breakpoint)
+                } else {
+                    Self::RemoveBreakpoint(breakpoint)
+                }
+            }
+            b'D' => Self::Detach,
+            b'k' => Self::Kill,
+            _ => return None,
+        };
+
+        Some(command)
+    }
+
+    fn parse_query(args: &[u8]) -> Option<Self> {
+        if args.starts_with(b"Supported") {
+            return Some(Self::QuerySupported);
+        }
+
+        if let Some(rest) = args.strip_prefix(b"Xfer:features:read:") {
+            let (annex, offset_length) = split_once(rest, b':')?;
+            let (offset, length) = parse_address_length(offset_length)?;
+            return Some(Self::QueryFeatures {
+                annex: String::from_utf8(annex.to_vec()).ok()?,
+                offset: offset as usize,
+                length: length as usize,
+            });
+        }
+
+        if let Some(thread) = args.strip_prefix(b"ThreadExtraInfo,") {
+            return Some(Self::QueryThreadExtraInfo(ThreadId::parse(thread)?));
+        }
+
+        let command = match args {
+            b"fThreadInfo" => Self::QueryFirstThreadInfo,
+            b"sThreadInfo" => Self::QueryNextThreadInfo,
+            b"C" => Self::QueryCurrentThread,
+            _ if args.starts_with(b"Attached") => Self::QueryAttached,
+            _ => return None,
+        };
+
+        Some(command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(bytes: &[u8]) -> Vec<Event> {
+        let mut decoder = PacketDecoder::new();
+        bytes.iter().filter_map(|&byte| decoder.push(byte)).collect()
+    }
+
+    #[test]
+    fn decodes_packets() {
+        assert_eq!(
+            decode_all(b"+$g#67$?#3f"),
+            vec![Event::Packet(b"g".to_vec()), Event::Packet(b"?".to_vec())]
+        );
+        assert_eq!(decode_all(b"$g#00"), vec![Event::ChecksumMismatch]);
+        assert_eq!(decode_all(&[INTERRUPT]), vec![Event::Interrupt]);
+
+        // Escaped '#' (0x23) inside a binary payload
+        assert_eq!(decode_all(b"$X}\x03#d8"), vec![Event::Packet(b"X#".to_vec())]);
+    }
+
+    #[test]
+    fn encodes_packets() {
+        assert_eq!(encode_packet(b"OK"), b"$OK#9a".to_vec());
+        assert_eq!(encode_packet(b""), b"$#00".to_vec());
+        assert_eq!(encode_packet(b"a#b"), b"$a}\x03b#43".to_vec());
+
+        let mut decoder = PacketDecoder::new();
+        let events: Vec<_> =
+            encode_packet(b"$}*").into_iter().filter_map(|byte| decoder.push(byte)).collect();
+        assert_eq!(events, vec![Event::Packet(b"$}*".to_vec())]);
+    }
+
+    #[test]
+    fn parses_memory_commands() {
+        assert_eq!(
+            Command::parse(b"mff0000,10"),
+            Command::ReadMemory { address: 0xFF0000, length: 0x10 }
+        );
+        assert_eq!(
+            Command::parse(b"M6000000,2:4e71"),
+            Command::WriteMemory { address: 0x6000000, data: vec![0x4E, 0x71] }
+        );
+        assert_eq!(Command::parse(b"M6000000,3:4e71"), Command::Unsupported);
+    }
+
+    #[test]
+    fn parses_breakpoint_commands() {
+        assert_eq!(
+            Command::parse(b"Z0,200,2"),
+            Command::InsertBreakpoint(Breakpoint {
+                kind: BreakpointKind::Software,
+                address: 0x200,
+                length: 2
+            })
+        );
+
+        let Command::RemoveBreakpoint(watchpoint) = Command::parse(b"z4,3000010,4") else {
+            panic!("expected remove breakpoint command");
+        };
+        assert!(watchpoint.read() && watchpoint.write() && !watchpoint.execute());
+        assert_eq!(watchpoint.address_range(), (0x3000010, 0x3000013));
+
+        assert_eq!(Command::parse(b"Z5,0,1"), Command::Unsupported);
+    }
+
+    #[test]
+    fn parses_thread_and_query_commands() {
+        assert_eq!(Command::parse(b"Hg2"), Command::SetGeneralThread(ThreadId::Id(2)));
+        assert_eq!(Command::parse(b"Hc-1"), Command::SetContinueThread(ThreadId::All));
+        assert_eq!(Command::parse(b"Hg0"), Command::SetGeneralThread(ThreadId::Any));
+        assert_eq!(
+            Command::parse(b"qSupported:multiprocess+;xmlRegisters=i386"),
+            Command::QuerySupported
+        );
+        assert_eq!(
+            Command::parse(b"qXfer:features:read:target.xml:0,ffb"),
+            Command::QueryFeatures { annex: "target.xml".into(), offset: 0, length: 0xFFB }
+        );
+        assert_eq!(Command::parse(b"c"), Command::Continue);
+        assert_eq!(Command::parse(b"c200"), Command::Unsupported);
+        assert_eq!(Command::parse(b"vMustReplyEmpty"), Command::Unsupported);
+    }
+}
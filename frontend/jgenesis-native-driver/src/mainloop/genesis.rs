@@ -1,11 +1,12 @@
 use crate::config::RomReadResult;
 use crate::config::{GenesisConfig, Sega32XConfig, SegaCdConfig};
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
 use crate::mainloop::runner::RunnerCommand;
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
-use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, NativeEmulatorError, save};
+use crate::mainloop::save::{CreateSaveWriter, DeterminedPaths};
+use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, NativeEmulatorError, gdb, movie, save};
 use crate::{NativeEmulator, NativeEmulatorResult, extensions};
 use cdrom::reader::CdRom;
-use genesis_config::GenesisRegion;
+use genesis_config::{GenesisInputs, GenesisRegion};
 use genesis_core::GenesisEmulator;
 use jgenesis_native_config::common::WindowSize;
 use s32x_core::api::Sega32XEmulator;
@@ -16,6 +17,18 @@ use std::path::{Path, PathBuf};
 
 pub type NativeGenesisEmulator = NativeEmulator<GenesisEmulator>;
 
+fn merge_netplay_inputs(p1_inputs: &GenesisInputs, p2_inputs: &GenesisInputs) -> GenesisInputs {
+    GenesisInputs {
+        p1: p1_inputs.p1,
+        p2: p2_inputs.p1,
+        // Peripherals always follow the host player's mouse
+        mouse: p1_inputs.mouse,
+        light_gun: p1_inputs.light_gun,
+        pointer_position: p1_inputs.pointer_position,
+        ..GenesisInputs::default()
+    }
+}
+
 impl NativeGenesisEmulator {
     /// # Errors
     ///
@@ -123,11 +136,43 @@ impl Native32XEmulator {
 ///
 /// This function will return an error upon encountering any video, audio, or I/O error.
 pub fn create_genesis(config: Box<GenesisConfig>) -> NativeEmulatorResult<NativeGenesisEmulator> {
+    launch_genesis(config, NativeGenesisEmulator::new)
+}
+
+/// Run the Genesis core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_genesis(
+    config: Box<GenesisConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_genesis(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_genesis<T>(
+    config: Box<GenesisConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, GenesisEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.common.rom_file_path);
     let RomReadResult { rom, extension } = config.common.read_rom_file(extensions::GENESIS)?;
 
+    let lock_on_rom = config
+        .lock_on_rom_path
+        .as_ref()
+        .map(|path| {
+            fs::read(path).map_err(|source| NativeEmulatorError::GenesisLockOnRomRead {
+                path: path.clone(),
+                source,
+            })
+        })
+        .transpose()?;
+
     let DeterminedPaths { save_path, save_state_path } = save::determine_save_paths(
         &config.common.save_path,
         &config.common.state_path,
@@ -138,8 +183,8 @@ pub fn create_genesis(config: Box<GenesisConfig>) -> NativeEmulatorResult<Native
     let emulator_config = config.emulator_config;
     let initial_window_size = config.common.initial_window_size;
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
-        let emulator = GenesisEmulator::create(rom, emulator_config, save_writer);
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
+        let emulator = GenesisEmulator::create(rom, lock_on_rom, emulator_config, save_writer);
 
         let mut cartridge_title = emulator.cartridge_title();
         // Remove non-printable characters
@@ -158,7 +203,7 @@ pub fn create_genesis(config: Box<GenesisConfig>) -> NativeEmulatorResult<Native
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    NativeGenesisEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -169,7 +214,10 @@ pub fn create_genesis(config: Box<GenesisConfig>) -> NativeEmulatorResult<Native
             config.inputs.to_mapping_vec(),
         )
         .with_turbo_mappings(config.inputs.to_turbo_mapping_vec())
-        .with_debug_fn(|| jgenesis_debugger_frontend::genesis::genesis_debug_fn()),
+        .with_debug_fn(|| jgenesis_debugger_frontend::genesis::genesis_debug_fn())
+        .with_gdb_fn(gdb::genesis_gdb_fn)
+        .with_movie_format(movie::gmv::FORMAT)
+        .with_netplay_merge_fn(merge_netplay_inputs),
     )
 }
 
@@ -180,6 +228,27 @@ pub fn create_genesis(config: Box<GenesisConfig>) -> NativeEmulatorResult<Native
 /// This function will return an error upon encountering any video, audio, or I/O error, including
 /// any error encountered loading the Sega CD game disc.
 pub fn create_sega_cd(config: Box<SegaCdConfig>) -> NativeEmulatorResult<NativeSegaCdEmulator> {
+    launch_sega_cd(config, NativeSegaCdEmulator::new)
+}
+
+/// Run the Sega CD core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_sega_cd(
+    config: Box<SegaCdConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_sega_cd(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_sega_cd<T>(
+    config: Box<SegaCdConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, SegaCdEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     const SCD_SAVE_EXTENSION: &str = "scd";
 
     log::info!("Running with config: {config}");
@@ -238,7 +307,7 @@ pub fn create_sega_cd(config: Box<SegaCdConfig>) -> NativeEmulatorResult<NativeS
     let run_without_disc = config.run_without_disc;
     let rom_path = rom_path.to_owned();
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let emulator = SegaCdEmulator::create(
             bios,
             rom_path,
@@ -274,7 +343,7 @@ pub fn create_sega_cd(config: Box<SegaCdConfig>) -> NativeEmulatorResult<NativeS
 
     let remove_disc_fn = SegaCdEmulator::remove_disc;
 
-    NativeSegaCdEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -286,7 +355,10 @@ pub fn create_sega_cd(config: Box<SegaCdConfig>) -> NativeEmulatorResult<NativeS
         )
         .with_turbo_mappings(config.genesis.inputs.to_turbo_mapping_vec())
         .with_debug_fn(|| jgenesis_debugger_frontend::genesis::sega_cd_debug_fn())
-        .with_disc_change_fns(change_disc_fn, remove_disc_fn),
+        .with_gdb_fn(gdb::sega_cd_gdb_fn)
+        .with_disc_change_fns(change_disc_fn, remove_disc_fn)
+        .with_movie_format(movie::gmv::FORMAT)
+        .with_netplay_merge_fn(merge_netplay_inputs),
     )
 }
 
@@ -327,6 +399,27 @@ fn bios_path_for_region(config: &SegaCdConfig, region: GenesisRegion) -> Option<
 ///
 /// Propagates any errors encountered while initializing the emulator.
 pub fn create_32x(config: Box<Sega32XConfig>) -> NativeEmulatorResult<Native32XEmulator> {
+    launch_32x(config, Native32XEmulator::new)
+}
+
+/// Run the 32X core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_32x(
+    config: Box<Sega32XConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_32x(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_32x<T>(
+    config: Box<Sega32XConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, Sega32XEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.genesis.common.rom_file_path);
@@ -343,7 +436,7 @@ pub fn create_32x(config: Box<Sega32XConfig>) -> NativeEmulatorResult<Native32XE
     let emulator_config = config.emulator_config;
     let initial_window_size = config.genesis.common.initial_window_size;
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let emulator = Sega32XEmulator::create(rom, emulator_config, save_writer);
 
         let cartridge_title = emulator.cartridge_title();
@@ -359,7 +452,7 @@ pub fn create_32x(config: Box<Sega32XConfig>) -> NativeEmulatorResult<Native32XE
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    Native32XEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -370,6 +463,9 @@ pub fn create_32x(config: Box<Sega32XConfig>) -> NativeEmulatorResult<Native32XE
             config.genesis.inputs.to_mapping_vec(),
         )
         .with_turbo_mappings(config.genesis.inputs.to_turbo_mapping_vec())
-        .with_debug_fn(|| jgenesis_debugger_frontend::genesis::sega_32x_debug_fn()),
+        .with_debug_fn(|| jgenesis_debugger_frontend::genesis::sega_32x_debug_fn())
+        .with_gdb_fn(gdb::sega_32x_gdb_fn)
+        .with_movie_format(movie::gmv::FORMAT)
+        .with_netplay_merge_fn(merge_netplay_inputs),
     )
 }
@@ -0,0 +1,625 @@
+//! Headless mode, which runs an emulator for a fixed number of frames without a window or audio
+//! device and captures its output, e.g. for regression tests on a machine without a GPU.
+//!
+//! Inputs come from either a movie file (`.jmv` or the system's external movie format) or a plain
+//! text input script. Each script line starts with a frame number or an inclusive frame range,
+//! followed by the buttons to hold on those frames or a reset command. Frames are numbered from 0,
+//! and buttons are for player 1 unless prefixed with a player number:
+//!
+//! ```text
+//! # Press Start for a few frames, then hold right and jump with player 2
+//! 120-125 start
+//! 300-400 right p2:a
+//! 500 softreset
+//! ```
+//!
+//! After the last frame, the most recent frame is written to `frame.png` and all audio samples are
+//! written to `audio.wav` in the output directory. The emulator always starts with no save files,
+//! and save writes are only kept in memory; save files on disk are never read or written.
+
+use crate::mainloop::movie::{ExternalMovieFormat, FrameCommand, Movie, MovieFrame, MovieStart};
+use crate::mainloop::save::{CreateSaveWriter, MemorySaveWriter};
+use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, movie, netplay, state};
+use crate::{MovieError, NativeEmulatorError, NativeEmulatorResult};
+use image::{ImageFormat, RgbaImage};
+use jgenesis_common::cheats::CheatSet;
+use jgenesis_common::frontend::{
+    AudioOutput, Color, ConstantInputPoller, EmulatorTrait, FrameSize, MappableInputs,
+    RenderFrameOptions, Renderer, TickEffect,
+};
+use jgenesis_common::input::Player;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{fmt, fs, io};
+use thiserror::Error;
+
+const FRAME_FILE_NAME: &str = "frame.png";
+const AUDIO_FILE_NAME: &str = "audio.wav";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadlessConfig {
+    /// Number of frames to run
+    pub frames: NonZeroU64,
+    /// Movie file or input script; no buttons are pressed if not set
+    pub input_path: Option<PathBuf>,
+    /// Directory to write the captured frame and audio to; created if it does not exist
+    pub output_dir: PathBuf,
+}
+
+#[derive(Debug, Error)]
+pub enum HeadlessError {
+    #[error("I/O error reading input script '{path}': {source}")]
+    ReadScript {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Input script line {line}: {message}")]
+    ParseScript { line: usize, message: String },
+    #[error("Error creating output directory '{path}': {source}")]
+    CreateOutputDir {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Error writing frame to '{path}': {source}")]
+    WriteFrame {
+        path: String,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("Error writing audio to '{path}': {source}")]
+    WriteAudio {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Emulator did not render any frames")]
+    NoFrameRendered,
+}
+
+/// Hashes of the emulator's output at the end of a headless run. All hashes are 64-bit FNV-1a.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadlessReport {
+    pub frames: u64,
+    /// Hash of the final frame's size followed by its pixels in RGBA order
+    pub frame_hash: u64,
+    /// Hash of the 16-bit little-endian stereo samples written to the WAV file
+    pub audio_hash: u64,
+    /// Hash of the bincode-serialized emulator state after the last frame
+    pub state_hash: u64,
+}
+
+impl Display for HeadlessReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "frames: {}", self.frames)?;
+        writeln!(f, "frame hash: {:016x}", self.frame_hash)?;
+        writeln!(f, "audio hash: {:016x}", self.audio_hash)?;
+        write!(f, "state hash: {:016x}", self.state_hash)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScriptAction<Button> {
+    Press(Button, Player),
+    Command(FrameCommand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScriptEntry<Button> {
+    first_frame: u64,
+    last_frame: u64,
+    action: ScriptAction<Button>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InputScript<Button> {
+    entries: Vec<ScriptEntry<Button>>,
+}
+
+impl<Button> InputScript<Button>
+where
+    Button: Copy + FromStr<Err = String>,
+{
+    fn parse(script: &str) -> Result<Self, HeadlessError> {
+        let mut entries = Vec::new();
+
+        for (i, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+            let Some(frames) = tokens.next() else { continue };
+
+            let parse_err = |message: String| HeadlessError::ParseScript { line: i + 1, message };
+
+            let (first_frame, last_frame) = parse_frame_range(frames).map_err(parse_err)?;
+
+            let mut any_actions = false;
+            for token in tokens {
+                let action = parse_action(token).map_err(parse_err)?;
+                entries.push(ScriptEntry { first_frame, last_frame, action });
+                any_actions = true;
+            }
+
+            if !any_actions {
+                return Err(parse_err(format!("No buttons or commands for frames '{frames}'")));
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    fn frame<Inputs>(&self, frame: u64, initial_inputs: &Inputs) -> MovieFrame<Inputs>
+    where
+        Inputs: Clone + MappableInputs<Button>,
+    {
+        let mut command = FrameCommand::None;
+        let mut inputs = initial_inputs.clone();
+
+        for entry in &self.entries {
+            if !(entry.first_frame..=entry.last_frame).contains(&frame) {
+                continue;
+            }
+
+            match entry.action {
+                ScriptAction::Press(button, player) => inputs.set_field(button, player, true),
+                // Commands only apply once, at the start of their range
+                ScriptAction::Command(entry_command) => {
+                    if frame == entry.first_frame {
+                        command = entry_command;
+                    }
+                }
+            }
+        }
+
+        MovieFrame { command, inputs }
+    }
+}
+
+fn parse_frame_range(s: &str) -> Result<(u64, u64), String> {
+    let parse_frame =
+        |frame: &str| frame.parse::<u64>().map_err(|_| format!("Invalid frame number '{frame}'"));
+
+    let Some((first, last)) = s.split_once('-') else {
+        let frame = parse_frame(s)?;
+        return Ok((frame, frame));
+    };
+
+    let (first, last) = (parse_frame(first)?, parse_frame(last)?);
+    if last < first {
+        return Err(format!("Frame range '{s}' ends before it starts"));
+    }
+
+    Ok((first, last))
+}
+
+fn parse_action<Button: FromStr<Err = String>>(s: &str) -> Result<ScriptAction<Button>, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "softreset" => return Ok(ScriptAction::Command(FrameCommand::SoftReset)),
+        "hardreset" => return Ok(ScriptAction::Command(FrameCommand::HardReset)),
+        _ => {}
+    }
+
+    let (player, button) = match s.split_once(':') {
+        Some((player, button)) => (parse_player(player)?, button),
+        None => (Player::One, s),
+    };
+
+    Ok(ScriptAction::Press(button.parse()?, player))
+}
+
+fn parse_player(s: &str) -> Result<Player, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "p1" => Ok(Player::One),
+        "p2" => Ok(Player::Two),
+        "p3" => Ok(Player::Three),
+        "p4" => Ok(Player::Four),
+        "p5" => Ok(Player::Five),
+        _ => Err(format!("Invalid player '{s}', expected p1-p5")),
+    }
+}
+
+enum InputSource<Emulator: EmulatorTrait> {
+    None,
+    Script(InputScript<Emulator::Button>),
+    Movie(Box<Movie<Emulator::Inputs>>),
+}
+
+impl<Emulator> InputSource<Emulator>
+where
+    Emulator: EmulatorTrait,
+    Emulator::Button: FromStr<Err = String>,
+{
+    fn open(
+        path: Option<&Path>,
+        movie_format: Option<ExternalMovieFormat<Emulator::Inputs>>,
+        emulator: &mut Emulator,
+        emulator_config: &Emulator::Config,
+    ) -> NativeEmulatorResult<Self> {
+        let Some(path) = path else { return Ok(Self::None) };
+
+        let format = match movie::determine_format(path, movie_format) {
+            Ok(format) => format,
+            Err(MovieError::UnsupportedExtension(_)) => {
+                let script = fs::read_to_string(path).map_err(|source| {
+                    HeadlessError::ReadScript { path: path.display().to_string(), source }
+                })?;
+                log::info!("Loaded input script from '{}'", path.display());
+
+                return Ok(Self::Script(InputScript::parse(&script)?));
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let movie = Movie::load(path.into(), format, Emulator::save_state_version(), true)?;
+        if let MovieStart::SaveState(bytes) = movie.start() {
+            state::load_from_bytes(emulator, emulator_config, bytes)?;
+        }
+        log::info!("Playing movie from '{}'", path.display());
+
+        Ok(Self::Movie(Box::new(movie)))
+    }
+
+    fn next_frame(
+        &mut self,
+        frame: u64,
+        initial_inputs: &Emulator::Inputs,
+    ) -> MovieFrame<Emulator::Inputs> {
+        if let Self::Movie(movie) = self {
+            if let Some(movie_frame) = movie.next_frame(initial_inputs) {
+                return movie_frame;
+            }
+
+            log::info!("Movie playback finished on frame {frame}");
+            *self = Self::None;
+        }
+
+        match self {
+            Self::Script(script) => script.frame(frame, initial_inputs),
+            Self::None | Self::Movie(_) => {
+                MovieFrame { command: FrameCommand::None, inputs: initial_inputs.clone() }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct CaptureRenderer {
+    frame_buffer: Vec<Color>,
+    frame_size: Option<FrameSize>,
+}
+
+impl Renderer for CaptureRenderer {
+    type Err = Infallible;
+
+    fn render_frame(
+        &mut self,
+        frame_buffer: &[Color],
+        frame_size: FrameSize,
+        _target_fps: f64,
+        _options: RenderFrameOptions,
+    ) -> Result<(), Self::Err> {
+        let len = (frame_size.width * frame_size.height) as usize;
+        self.frame_buffer.clear();
+        self.frame_buffer.extend_from_slice(&frame_buffer[..len]);
+        self.frame_size = Some(frame_size);
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct CaptureAudioOutput {
+    samples: Vec<i16>,
+}
+
+impl AudioOutput for CaptureAudioOutput {
+    type Err = Infallible;
+
+    fn push_sample(&mut self, sample_l: f64, sample_r: f64) -> Result<(), Self::Err> {
+        for sample in [sample_l, sample_r] {
+            self.samples.push((sample.clamp(-1.0, 1.0) * f64::from(i16::MAX)).round() as i16);
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn run<Emulator>(
+    NativeEmulatorArgs {
+        create_emulator_fn,
+        movie_format,
+        emulator_config,
+        common_config,
+        initial_inputs,
+        ..
+    }: NativeEmulatorArgs<'_, '_, Emulator>,
+    config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport>
+where
+    Emulator: EmulatorTrait,
+    Emulator::Button: FromStr<Err = String>,
+{
+    // Always start from empty saves so that runs are reproducible regardless of what saves exist on
+    // disk
+    let mut save_writer = MemorySaveWriter::default();
+
+    let CreatedEmulator { mut emulator, .. } =
+        create_emulator_fn(&mut CreateSaveWriter::Memory(&mut save_writer))?;
+
+    let audio_output_frequency = common_config.audio_output_frequency;
+    emulator.update_audio_output_frequency(audio_output_frequency);
+
+    let cheats = CheatSet::from_codes(Emulator::CHEAT_SYSTEM, &common_config.cheat_codes);
+    emulator.set_cheats(cheats.clone());
+
+    let input_source = InputSource::open(
+        config.input_path.as_deref(),
+        movie_format,
+        &mut emulator,
+        &emulator_config,
+    )?;
+
+    run_frames(
+        emulator,
+        save_writer,
+        input_source,
+        &initial_inputs,
+        &cheats,
+        audio_output_frequency,
+        config,
+    )
+}
+
+fn run_frames<Emulator>(
+    mut emulator: Emulator,
+    mut save_writer: MemorySaveWriter,
+    mut input_source: InputSource<Emulator>,
+    initial_inputs: &Emulator::Inputs,
+    cheats: &CheatSet,
+    audio_output_frequency: u64,
+    config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport>
+where
+    Emulator: EmulatorTrait,
+    Emulator::Button: FromStr<Err = String>,
+{
+    let mut renderer = CaptureRenderer::default();
+    let mut audio_output = CaptureAudioOutput::default();
+
+    for frame in 0..config.frames.get() {
+        let MovieFrame { command, inputs } = input_source.next_frame(frame, initial_inputs);
+        match command {
+            FrameCommand::None => {}
+            FrameCommand::SoftReset => emulator.soft_reset(),
+            FrameCommand::HardReset => {
+                emulator.hard_reset(&mut save_writer);
+                // Hard reset recreates the emulator, which clears cheats
+                emulator.set_cheats(cheats.clone());
+            }
+        }
+
+        let mut input_poller = ConstantInputPoller(&inputs);
+        while emulator
+            .tick(&mut renderer, &mut audio_output, &mut input_poller, &mut save_writer)
+            .map_err(|err| NativeEmulatorError::Emulator(Box::new(err)))?
+            != TickEffect::FrameRendered
+        {}
+    }
+
+    let frame_size = renderer.frame_size.ok_or(HeadlessError::NoFrameRendered)?;
+
+    fs::create_dir_all(&config.output_dir).map_err(|source| HeadlessError::CreateOutputDir {
+        path: config.output_dir.display().to_string(),
+        source,
+    })?;
+
+    let frame_path = config.output_dir.join(FRAME_FILE_NAME);
+    write_frame(&frame_path, &renderer.frame_buffer, frame_size)?;
+
+    let audio_path = config.output_dir.join(AUDIO_FILE_NAME);
+    write_wav(&audio_path, &audio_output.samples, audio_output_frequency as u32).map_err(
+        |source| HeadlessError::WriteAudio { path: audio_path.display().to_string(), source },
+    )?;
+
+    let frame_bytes: Vec<u8> = [frame_size.width, frame_size.height]
+        .into_iter()
+        .flat_map(u32::to_le_bytes)
+        .chain(renderer.frame_buffer.iter().flat_map(|color| [color.r, color.g, color.b, color.a]))
+        .collect();
+    let audio_bytes: Vec<u8> =
+        audio_output.samples.iter().copied().flat_map(i16::to_le_bytes).collect();
+
+    Ok(HeadlessReport {
+        frames: config.frames.get(),
+        frame_hash: netplay::fnv1a_hash(&frame_bytes),
+        audio_hash: netplay::fnv1a_hash(&audio_bytes),
+        state_hash: netplay::fnv1a_hash(&state::save_to_bytes(&emulator)?),
+    })
+}
+
+fn write_frame(
+    path: &Path,
+    frame_buffer: &[Color],
+    frame_size: FrameSize,
+) -> Result<(), HeadlessError> {
+    let pixels =
+        frame_buffer.iter().flat_map(|color| [color.r, color.g, color.b, color.a]).collect();
+    let image = RgbaImage::from_raw(frame_size.width, frame_size.height, pixels)
+        .expect("Frame buffer length should match frame size");
+
+    image
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|source| HeadlessError::WriteFrame { path: path.display().to_string(), source })?;
+
+    log::info!("Wrote {}x{} frame to '{}'", frame_size.width, frame_size.height, path.display());
+
+    Ok(())
+}
+
+// 16-bit stereo PCM
+fn write_wav(path: &Path, samples: &[i16], sample_rate: u32) -> io::Result<()> {
+    const CHANNELS: u16 = 2;
+    const BYTES_PER_SAMPLE: u16 = 2;
+
+    let data_len = (samples.len() * usize::from(BYTES_PER_SAMPLE)) as u32;
+    let block_align = CHANNELS * BYTES_PER_SAMPLE;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16_u32.to_le_bytes())?;
+    // Format 1 = PCM
+    writer.write_all(&1_u16.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&(8 * BYTES_PER_SAMPLE).to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    writer.flush()?;
+
+    log::info!("Wrote {} audio samples to '{}'", samples.len() / 2, path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genesis_config::{GenesisButton, GenesisInputs};
+    use genesis_core::{GenesisEmulator, GenesisEmulatorConfig};
+    use std::env;
+
+    const AUDIO_OUTPUT_FREQUENCY: u64 = 48000;
+    const WAV_HEADER_LEN: usize = 44;
+
+    // Copies the player 1 controller port to RAM in a loop; the VDP still renders a frame every
+    // 1/60 seconds with the display disabled
+    fn test_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x400];
+        rom[0..4].copy_from_slice(&0x00FF_FE00_u32.to_be_bytes());
+        rom[4..8].copy_from_slice(&0x0000_0200_u32.to_be_bytes());
+        rom[0x100..0x110].copy_from_slice(b"SEGA GENESIS    ");
+        // MOVE.B ($A10003).L, ($FF0000).L
+        // BRA.S $200
+        rom[0x200..0x20C].copy_from_slice(&[
+            0x13, 0xF9, 0x00, 0xA1, 0x00, 0x03, 0x00, 0xFF, 0x00, 0x00, 0x60, 0xF4,
+        ]);
+        rom
+    }
+
+    fn run_test_rom(name: &str, script: &str, frames: u64) -> (HeadlessReport, PathBuf) {
+        let output_dir =
+            env::temp_dir().join(format!("jgenesis-headless-{name}-{}", std::process::id()));
+
+        let mut save_writer = MemorySaveWriter::default();
+        let mut emulator = GenesisEmulator::create(
+            test_rom(),
+            None,
+            GenesisEmulatorConfig::default(),
+            &mut save_writer,
+        );
+        emulator.update_audio_output_frequency(AUDIO_OUTPUT_FREQUENCY);
+
+        let report = run_frames(
+            emulator,
+            save_writer,
+            InputSource::Script(InputScript::parse(script).unwrap()),
+            &GenesisInputs::default(),
+            &CheatSet::default(),
+            AUDIO_OUTPUT_FREQUENCY,
+            &HeadlessConfig {
+                frames: NonZeroU64::new(frames).unwrap(),
+                input_path: None,
+                output_dir: output_dir.clone(),
+            },
+        )
+        .unwrap();
+
+        (report, output_dir)
+    }
+
+    #[test]
+    fn run_and_hash_output() {
+        let (report, output_dir) = run_test_rom("run", "", 30);
+        assert_eq!(report.frames, 30);
+        assert!(output_dir.join(FRAME_FILE_NAME).exists());
+
+        // 30 frames at ~60Hz is half a second of stereo audio
+        let wav = fs::read(output_dir.join(AUDIO_FILE_NAME)).unwrap();
+        let sample_frames = (wav.len() - WAV_HEADER_LEN) / 4;
+        let expected_sample_frames = AUDIO_OUTPUT_FREQUENCY as usize / 2;
+        assert!(
+            sample_frames.abs_diff(expected_sample_frames) < expected_sample_frames / 100,
+            "{sample_frames} samples"
+        );
+        assert_eq!(report.audio_hash, netplay::fnv1a_hash(&wav[WAV_HEADER_LEN..]));
+
+        // Runs are deterministic
+        let (rerun_report, rerun_output_dir) = run_test_rom("rerun", "", 30);
+        assert_eq!(rerun_report, report);
+
+        // The test ROM copies the controller state to RAM, so inputs held on the last frame change
+        // the emulator state
+        let (input_report, input_output_dir) = run_test_rom("input", "20-29 right", 30);
+        assert_eq!(input_report.frames, 30);
+        assert_ne!(input_report.state_hash, report.state_hash);
+
+        for dir in [output_dir, rerun_output_dir, input_output_dir] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn input_script() {
+        let script = InputScript::<GenesisButton>::parse(
+            "# comment\n\
+             2-4 start # trailing comment\n\
+             \n\
+             3 p2:A right\n\
+             5 SoftReset",
+        )
+        .unwrap();
+
+        let initial_inputs = GenesisInputs::default();
+
+        let frame = script.frame(1, &initial_inputs);
+        assert_eq!(frame.inputs, initial_inputs);
+        assert_eq!(frame.command, FrameCommand::None);
+
+        let frame = script.frame(3, &initial_inputs);
+        assert!(frame.inputs.p1.start && frame.inputs.p1.right && frame.inputs.p2.a);
+        assert!(!frame.inputs.p1.a);
+
+        let frame = script.frame(4, &initial_inputs);
+        assert!(frame.inputs.p1.start && !frame.inputs.p1.right);
+
+        assert_eq!(script.frame(5, &initial_inputs).command, FrameCommand::SoftReset);
+    }
+
+    #[test]
+    fn input_script_errors() {
+        for script in ["10-5 start", "abc start", "10", "10 jump", "10 p9:start"] {
+            let result = InputScript::<GenesisButton>::parse(script);
+            assert!(
+                matches!(result, Err(HeadlessError::ParseScript { line: 1, .. })),
+                "{script}: {result:?}"
+            );
+        }
+    }
+}
@@ -7,6 +7,9 @@ use std::sync::{Arc, Mutex};
 #[derive(Debug)]
 pub struct ThreadedInputPoller<Inputs> {
     cached: Inputs,
+    // Inputs for the current frame when a movie is recording or playing back; these take priority
+    // over inputs received from the main thread so that inputs cannot change mid-frame
+    frame_inputs: Option<Inputs>,
     locked: Arc<Mutex<Inputs>>,
     updated: Arc<AtomicBool>,
 }
@@ -22,6 +25,7 @@ impl<Inputs: Clone + Eq> ThreadedInputPoller<Inputs> {
     pub fn new(initial_inputs: Inputs) -> Self {
         Self {
             cached: initial_inputs.clone(),
+            frame_inputs: None,
             locked: Arc::new(Mutex::new(initial_inputs)),
             updated: Arc::new(AtomicBool::new(false)),
         }
@@ -34,6 +38,26 @@ impl<Inputs: Clone + Eq> ThreadedInputPoller<Inputs> {
             updated: Arc::clone(&self.updated),
         }
     }
+
+    /// Return the most recent inputs received from the main thread, ignoring any frame inputs.
+    pub fn poll_live(&mut self) -> &Inputs {
+        self.update_cached();
+        &self.cached
+    }
+
+    /// Set inputs that will be returned from every poll until this is called again with `None`.
+    pub fn set_frame_inputs(&mut self, frame_inputs: Option<Inputs>) {
+        self.frame_inputs = frame_inputs;
+    }
+
+    fn update_cached(&mut self) {
+        if self.updated.load(Ordering::Relaxed)
+            && self.updated.compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
+                == Ok(true)
+        {
+            self.cached = self.locked.lock().unwrap().clone();
+        }
+    }
 }
 
 impl<Inputs: Clone + Eq> ThreadedInputPollerHandle<Inputs> {
@@ -50,13 +74,7 @@ impl<Inputs: Clone + Eq> ThreadedInputPollerHandle<Inputs> {
 
 impl<Inputs: Clone + Eq> InputPoller<Inputs> for ThreadedInputPoller<Inputs> {
     fn poll(&mut self) -> &Inputs {
-        if self.updated.load(Ordering::Relaxed)
-            && self.updated.compare_exchange(true, false, Ordering::AcqRel, Ordering::Relaxed)
-                == Ok(true)
-        {
-            self.cached = self.locked.lock().unwrap().clone();
-        }
-
-        &self.cached
+        self.update_cached();
+        self.frame_inputs.as_ref().unwrap_or(&self.cached)
     }
 }
@@ -0,0 +1,245 @@
+//! Link cable emulation between two instances of the emulator over a TCP socket.
+//!
+//! One instance listens for a connection and the other connects to it. Each message is a single
+//! byte carrying either a serial clock pulse with its data bit, or the data bit sent back in
+//! response to a clock pulse.
+//!
+//! The two instances do not run in lockstep. Messages are exchanged once per frame, after the
+//! emulator has run the frame, so a byte sent on the internal clock has latency of one frame
+//! before the other Game Boy receives its 8 clock pulses, and up to one more frame before the
+//! responses arrive and the sending Game Boy's transfer completes. A byte that would take about
+//! 1ms on hardware thus takes 17-33ms. Games that handshake before every byte (e.g. trading and
+//! versus modes) tolerate this, but games that send bytes back-to-back on a timer or that time out
+//! waiting for a response may not.
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+const PULSE_MESSAGE: u8 = 0x00;
+const RESPONSE_MESSAGE: u8 = 0x02;
+
+/// Exchange pending link cable messages between the emulator and the socket. Called once per
+/// runner loop iteration, and once more after the link is stopped so the emulator can disconnect.
+pub type LinkCableFn<Emulator> = fn(&mut Emulator, &mut LinkCableSocket);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkCableConfig {
+    /// Local TCP port to listen on, if `remote_addr` is `None`
+    pub local_port: u16,
+    /// Address of the other instance to connect to, or `None` to listen for a connection
+    pub remote_addr: Option<SocketAddr>,
+}
+
+#[derive(Debug, Error)]
+pub enum LinkCableError {
+    #[error("Link cable is not supported for this system")]
+    Unsupported,
+    #[error("Error listening on TCP port {port}: {source}")]
+    Bind {
+        port: u16,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Link cable socket error: {0}")]
+    Socket(#[source] io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkCableMessage {
+    /// Clock pulse with the data bit sent by the Game Boy providing the clock
+    Pulse(bool),
+    /// Data bit sent back in response to a clock pulse
+    Response(bool),
+}
+
+impl LinkCableMessage {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Pulse(bit) => PULSE_MESSAGE | u8::from(bit),
+            Self::Response(bit) => RESPONSE_MESSAGE | u8::from(bit),
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        let bit = byte & 1 != 0;
+        match byte & !1 {
+            PULSE_MESSAGE => Some(Self::Pulse(bit)),
+            RESPONSE_MESSAGE => Some(Self::Response(bit)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkCableEvent {
+    Connected { peer: SocketAddr },
+    Disconnected { reason: String },
+}
+
+enum Connection {
+    Listening(TcpListener),
+    Connecting { remote_addr: SocketAddr, last_attempt_time: Option<Instant> },
+    Connected(TcpStream),
+    Closed,
+}
+
+pub struct LinkCableSocket {
+    connection: Connection,
+    received: VecDeque<LinkCableMessage>,
+    send_buffer: Vec<u8>,
+    events: Vec<LinkCableEvent>,
+}
+
+impl LinkCableSocket {
+    /// # Errors
+    ///
+    /// Returns an error if unable to listen on the configured port.
+    pub fn new(config: &LinkCableConfig) -> Result<Self, LinkCableError> {
+        let connection = match config.remote_addr {
+            Some(remote_addr) => Connection::Connecting { remote_addr, last_attempt_time: None },
+            None => {
+                let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, config.local_port))
+                    .map_err(|source| LinkCableError::Bind { port: config.local_port, source })?;
+                listener.set_nonblocking(true).map_err(LinkCableError::Socket)?;
+
+                log::info!("Listening for link cable connection on port {}", config.local_port);
+
+                Connection::Listening(listener)
+            }
+        };
+
+        Ok(Self { connection, received: VecDeque::new(), send_buffer: vec![], events: vec![] })
+    }
+
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        matches!(self.connection, Connection::Connected(_))
+    }
+
+    pub fn take_events(&mut self) -> Vec<LinkCableEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn receive(&mut self) -> Option<LinkCableMessage> {
+        self.received.pop_front()
+    }
+
+    pub fn send(&mut self, message: LinkCableMessage) {
+        if self.is_connected() {
+            self.send_buffer.push(message.to_byte());
+        }
+    }
+
+    /// Accept or attempt a connection if not yet connected, and read any messages that have
+    /// arrived.
+    pub fn poll(&mut self) {
+        if let Err(err) = self.try_poll() {
+            self.disconnect(err.to_string());
+        }
+    }
+
+    fn try_poll(&mut self) -> io::Result<()> {
+        match &mut self.connection {
+            Connection::Listening(listener) => match listener.accept() {
+                Ok((stream, peer)) => self.on_connected(stream, peer)?,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err),
+            },
+            Connection::Connecting { remote_addr, last_attempt_time } => {
+                let now = Instant::now();
+                if last_attempt_time.is_none_or(|time| now - time >= CONNECT_RETRY_INTERVAL) {
+                    *last_attempt_time = Some(now);
+
+                    let remote_addr = *remote_addr;
+                    match TcpStream::connect_timeout(&remote_addr, CONNECT_TIMEOUT) {
+                        Ok(stream) => self.on_connected(stream, remote_addr)?,
+                        Err(err) => {
+                            log::debug!("Link cable connection to {remote_addr} failed: {err}");
+                        }
+                    }
+                }
+            }
+            Connection::Connected(stream) => {
+                let mut buffer = [0; 1024];
+                loop {
+                    match stream.read(&mut buffer) {
+                        Ok(0) => {
+                            return Err(io::Error::new(
+                                ErrorKind::ConnectionAborted,
+                                "connection closed by peer",
+                            ));
+                        }
+                        Ok(len) => {
+                            for &byte in &buffer[..len] {
+                                match LinkCableMessage::from_byte(byte) {
+                                    Some(message) => self.received.push_back(message),
+                                    None => log::warn!("Invalid link cable message: {byte:02X}"),
+                                }
+                            }
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            Connection::Closed => {}
+        }
+
+        Ok(())
+    }
+
+    fn on_connected(&mut self, stream: TcpStream, peer: SocketAddr) -> io::Result<()> {
+        stream.set_nonblocking(true)?;
+        // Messages are tiny and latency-sensitive
+        stream.set_nodelay(true)?;
+
+        log::info!("Link cable connected to {peer}");
+
+        self.connection = Connection::Connected(stream);
+        self.events.push(LinkCableEvent::Connected { peer });
+
+        Ok(())
+    }
+
+    /// Send all messages queued since the last flush.
+    pub fn flush(&mut self) {
+        if let Err(err) = self.try_flush() {
+            self.disconnect(err.to_string());
+        }
+    }
+
+    fn try_flush(&mut self) -> io::Result<()> {
+        let Connection::Connected(stream) = &mut self.connection else { return Ok(()) };
+
+        while !self.send_buffer.is_empty() {
+            match stream.write(&self.send_buffer) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.send_buffer.drain(..len);
+                }
+                // Anything left over will be sent on the next flush
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        self.connection = Connection::Closed;
+        self.received.clear();
+        self.send_buffer.clear();
+    }
+
+    fn disconnect(&mut self, reason: String) {
+        self.close();
+        self.events.push(LinkCableEvent::Disconnected { reason });
+    }
+}
@@ -0,0 +1,587 @@
+//! Deterministic input movie recording and playback.
+//!
+//! Movies record one set of inputs per frame, starting either from power-on or from an embedded
+//! save state. Inputs are latched at the start of each frame so that playback is bit-exact.
+
+pub mod fm2;
+pub mod gmv;
+
+use crate::mainloop::bincode_config;
+use bincode::error::{DecodeError, EncodeError};
+use bincode::{Decode, Encode};
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, io, mem};
+use thiserror::Error;
+
+pub const EXTENSION: &str = "jmv";
+
+// Input log snapshots are stored next to the save state file, e.g. `<game>_3.jmvs`
+const SNAPSHOT_EXTENSION: &str = "jmvs";
+
+const FILE_PREFIX: &[u8] = b"jgenmovie";
+
+#[derive(Debug, Error)]
+pub enum MovieError {
+    #[error("I/O error opening movie file '{path}': {source}")]
+    OpenFile {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("I/O error reading movie file: {0}")]
+    Read(#[source] io::Error),
+    #[error("I/O error writing movie file: {0}")]
+    Write(#[source] io::Error),
+    #[error("Error serializing movie: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("Error deserializing movie: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("Movie file begins with invalid prefix")]
+    PrefixMismatch,
+    #[error(
+        "Movie starts from a save state with version '{actual}', but the current version is '{expected}'"
+    )]
+    StateVersionMismatch { expected: String, actual: String },
+    #[error("Unsupported movie file extension: '{0}'")]
+    UnsupportedExtension(String),
+    #[error("Invalid {format} movie: {message}")]
+    InvalidExternal { format: &'static str, message: String },
+    #[error("{0} movies can only start from power-on")]
+    ExportFromSaveState(&'static str),
+}
+
+/// Where a new movie recording should start from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieStartType {
+    PowerOn,
+    CurrentState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum MovieStart {
+    PowerOn,
+    SaveState(Vec<u8>),
+}
+
+/// Reset that is applied immediately before a frame's inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+pub enum FrameCommand {
+    #[default]
+    None,
+    SoftReset,
+    HardReset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct MovieFrame<Inputs> {
+    pub command: FrameCommand,
+    pub inputs: Inputs,
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+struct MovieFile<Inputs> {
+    save_state_version: String,
+    rerecord_count: u32,
+    start: MovieStart,
+    frames: Vec<MovieFrame<Inputs>>,
+}
+
+/// A movie in a format from another emulator. These always start from power-on.
+#[derive(Debug, Clone)]
+pub struct ExternalMovie<Inputs> {
+    pub rerecord_count: u32,
+    pub frames: Vec<MovieFrame<Inputs>>,
+}
+
+/// Import/export functions for a system-specific movie format, e.g. FM2 for the NES.
+pub struct ExternalMovieFormat<Inputs> {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub import: fn(&[u8]) -> Result<ExternalMovie<Inputs>, MovieError>,
+    pub export: fn(&ExternalMovie<Inputs>) -> Vec<u8>,
+}
+
+impl<Inputs> Clone for ExternalMovieFormat<Inputs> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Inputs> Copy for ExternalMovieFormat<Inputs> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieMode {
+    Recording,
+    Playback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateLoadEffect {
+    None,
+    Rerecord,
+    // The loaded state was not saved while this movie was active, so the movie can't continue
+    Desync,
+    // Read-only mode only; the loaded state's input log is not a prefix of the movie
+    TimelineMismatch,
+}
+
+pub struct Movie<Inputs> {
+    path: PathBuf,
+    format: Option<ExternalMovieFormat<Inputs>>,
+    mode: MovieMode,
+    read_only: bool,
+    save_state_version: String,
+    rerecord_count: u32,
+    start: MovieStart,
+    frames: Vec<MovieFrame<Inputs>>,
+    position: usize,
+    pending_command: FrameCommand,
+    dirty: bool,
+}
+
+impl<Inputs> Movie<Inputs>
+where
+    Inputs: Clone + Eq + Encode + Decode<()>,
+{
+    pub fn new_recording(
+        path: PathBuf,
+        format: Option<ExternalMovieFormat<Inputs>>,
+        save_state_version: &str,
+        start: MovieStart,
+    ) -> Result<Self, MovieError> {
+        if let (Some(format), MovieStart::SaveState(_)) = (format, &start) {
+            return Err(MovieError::ExportFromSaveState(format.name));
+        }
+
+        Ok(Self {
+            path,
+            format,
+            mode: MovieMode::Recording,
+            read_only: false,
+            save_state_version: save_state_version.into(),
+            rerecord_count: 0,
+            start,
+            frames: vec![],
+            position: 0,
+            pending_command: FrameCommand::None,
+            dirty: true,
+        })
+    }
+
+    pub fn load(
+        path: PathBuf,
+        format: Option<ExternalMovieFormat<Inputs>>,
+        save_state_version: &str,
+        read_only: bool,
+    ) -> Result<Self, MovieError> {
+        let movie_file = match format {
+            Some(format) => {
+                let bytes = fs::read(&path).map_err(|source| MovieError::OpenFile {
+                    path: path.display().to_string(),
+                    source,
+                })?;
+                let ExternalMovie { rerecord_count, frames } = (format.import)(&bytes)?;
+                MovieFile {
+                    save_state_version: save_state_version.into(),
+                    rerecord_count,
+                    start: MovieStart::PowerOn,
+                    frames,
+                }
+            }
+            None => read_movie_file(&path)?,
+        };
+
+        if matches!(movie_file.start, MovieStart::SaveState(_))
+            && movie_file.save_state_version != save_state_version
+        {
+            return Err(MovieError::StateVersionMismatch {
+                expected: save_state_version.into(),
+                actual: movie_file.save_state_version,
+            });
+        }
+
+        Ok(Self {
+            path,
+            format,
+            mode: MovieMode::Playback,
+            read_only,
+            save_state_version: movie_file.save_state_version,
+            rerecord_count: movie_file.rerecord_count,
+            start: movie_file.start,
+            frames: movie_file.frames,
+            position: 0,
+            pending_command: FrameCommand::None,
+            dirty: false,
+        })
+    }
+
+    pub fn start(&self) -> &MovieStart {
+        &self.start
+    }
+
+    pub fn toggle_read_only(&mut self) -> bool {
+        self.read_only = !self.read_only;
+        self.read_only
+    }
+
+    /// Queue a reset to be recorded and applied at the start of the next frame. Resets are
+    /// ignored during playback because the movie controls when they happen.
+    pub fn queue_command(&mut self, command: FrameCommand) {
+        if self.mode != MovieMode::Recording {
+            log::info!("Ignoring {command:?} during movie playback");
+            return;
+        }
+
+        self.pending_command = command;
+    }
+
+    /// Return the command and inputs for the next frame, or `None` if playback has finished.
+    ///
+    /// While recording, `live_inputs` are appended to the input log.
+    pub fn next_frame(&mut self, live_inputs: &Inputs) -> Option<MovieFrame<Inputs>> {
+        match self.mode {
+            MovieMode::Recording => {
+                let frame = MovieFrame {
+                    command: mem::take(&mut self.pending_command),
+                    inputs: live_inputs.clone(),
+                };
+                self.frames.push(frame.clone());
+                self.position += 1;
+                self.dirty = true;
+
+                Some(frame)
+            }
+            MovieMode::Playback => {
+                let frame = self.frames.get(self.position)?.clone();
+                self.position += 1;
+
+                Some(frame)
+            }
+        }
+    }
+
+    /// Write the input log up to the current frame next to the save state file at `state_path`,
+    /// so that the state can be used to rerecord this movie later, including after the movie is
+    /// reopened.
+    ///
+    /// # Errors
+    ///
+    /// Propagates any errors encountered while writing the snapshot file.
+    pub fn on_state_saved(&self, state_path: &Path) -> Result<(), MovieError> {
+        write_movie_file(
+            &state_path.with_extension(SNAPSHOT_EXTENSION),
+            &MovieFile {
+                save_state_version: self.save_state_version.clone(),
+                rerecord_count: self.rerecord_count,
+                start: self.start.clone(),
+                frames: self.frames[..self.position].to_vec(),
+            },
+        )
+    }
+
+    pub fn on_state_loaded(&mut self, state_path: &Path) -> StateLoadEffect {
+        let snapshot_path = state_path.with_extension(SNAPSHOT_EXTENSION);
+        let snapshot = match read_movie_file::<Inputs>(&snapshot_path) {
+            Ok(snapshot) if snapshot.start == self.start => snapshot.frames,
+            Ok(_) => return StateLoadEffect::Desync,
+            Err(MovieError::OpenFile { source, .. })
+                if source.kind() == io::ErrorKind::NotFound =>
+            {
+                return StateLoadEffect::Desync;
+            }
+            Err(err) => {
+                log::error!("Error reading movie snapshot '{}': {err}", snapshot_path.display());
+                return StateLoadEffect::Desync;
+            }
+        };
+
+        if self.read_only {
+            // Loading a state in read-only mode seeks within the existing movie and plays back
+            // from there instead of modifying it, which is only possible if the state is on the
+            // same timeline as the movie
+            if !self.frames.starts_with(&snapshot) {
+                return StateLoadEffect::TimelineMismatch;
+            }
+
+            self.position = snapshot.len();
+            self.mode = MovieMode::Playback;
+            return StateLoadEffect::None;
+        }
+
+        // Loading a state in read-write mode resumes recording from that point
+        self.frames = snapshot;
+        self.position = self.frames.len();
+        self.mode = MovieMode::Recording;
+        self.pending_command = FrameCommand::None;
+        self.rerecord_count += 1;
+        self.dirty = true;
+
+        StateLoadEffect::Rerecord
+    }
+
+    pub fn rerecord_count(&self) -> u32 {
+        self.rerecord_count
+    }
+
+    /// Write the movie to disk if it has changed since it was loaded.
+    pub fn save(&mut self) -> Result<(), MovieError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        match self.format {
+            Some(format) => {
+                let bytes = (format.export)(&ExternalMovie {
+                    rerecord_count: self.rerecord_count,
+                    frames: self.frames.clone(),
+                });
+                fs::write(&self.path, bytes).map_err(MovieError::Write)?;
+            }
+            None => write_movie_file(
+                &self.path,
+                &MovieFile {
+                    save_state_version: self.save_state_version.clone(),
+                    rerecord_count: self.rerecord_count,
+                    start: self.start.clone(),
+                    frames: self.frames.clone(),
+                },
+            )?,
+        }
+
+        log::info!(
+            "Saved movie with {} frames and {} rerecords to '{}'",
+            self.frames.len(),
+            self.rerecord_count,
+            self.path.display()
+        );
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+/// Remove the input log snapshot stored next to the save state file at `state_path`, if any. Used
+/// when a state is saved while no movie is active so that a stale snapshot is not attached to it.
+pub fn remove_state_snapshot(state_path: &Path) {
+    let snapshot_path = state_path.with_extension(SNAPSHOT_EXTENSION);
+    if let Err(err) = fs::remove_file(&snapshot_path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        log::error!("Error removing movie snapshot '{}': {err}", snapshot_path.display());
+    }
+}
+
+/// Determine which format a movie file is in based on its extension. Returns `Ok(None)` for
+/// jgenesis movies.
+///
+/// # Errors
+///
+/// Returns an error if the extension does not match jgenesis movies or the given external format.
+pub fn determine_format<Inputs>(
+    path: &Path,
+    external_format: Option<ExternalMovieFormat<Inputs>>,
+) -> Result<Option<ExternalMovieFormat<Inputs>>, MovieError> {
+    let extension = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
+    if extension == EXTENSION {
+        return Ok(None);
+    }
+
+    match external_format {
+        Some(format) if extension == format.extension => Ok(Some(format)),
+        _ => Err(MovieError::UnsupportedExtension(extension)),
+    }
+}
+
+fn read_movie_file<Inputs: Decode<()>>(path: &Path) -> Result<MovieFile<Inputs>, MovieError> {
+    let file = File::open(path)
+        .map_err(|source| MovieError::OpenFile { path: path.display().to_string(), source })?;
+    let mut reader = BufReader::new(file);
+
+    let mut prefix = [0; FILE_PREFIX.len()];
+    reader.read_exact(&mut prefix).map_err(MovieError::Read)?;
+    if prefix != FILE_PREFIX {
+        return Err(MovieError::PrefixMismatch);
+    }
+
+    let mut decoder = zstd::stream::Decoder::new(reader).map_err(MovieError::Read)?;
+    Ok(bincode::decode_from_std_read(&mut decoder, bincode_config!())?)
+}
+
+fn write_movie_file<Inputs: Encode>(
+    path: &Path,
+    movie_file: &MovieFile<Inputs>,
+) -> Result<(), MovieError> {
+    let file = File::create(path)
+        .map_err(|source| MovieError::OpenFile { path: path.display().to_string(), source })?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(FILE_PREFIX).map_err(MovieError::Write)?;
+
+    let mut encoder = zstd::stream::Encoder::new(writer, 0).map_err(MovieError::Write)?;
+    bincode::encode_into_std_write(movie_file, &mut encoder, bincode_config!())?;
+    encoder.finish().map_err(MovieError::Write)?.flush().map_err(MovieError::Write)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const VERSION: &str = "0.1.0";
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jgenesis-movie-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn record(movie: &mut Movie<u8>, inputs: impl IntoIterator<Item = u8>) {
+        for input in inputs {
+            movie.next_frame(&input).unwrap();
+        }
+    }
+
+    fn play_back(movie: &mut Movie<u8>) -> Vec<u8> {
+        // Live inputs are ignored during playback
+        std::iter::from_fn(|| movie.next_frame(&0xFF)).map(|frame| frame.inputs).collect()
+    }
+
+    #[test]
+    fn record_and_play_back() {
+        let dir = test_dir("round-trip");
+        let path = dir.join("movie.jmv");
+
+        let start = MovieStart::SaveState(vec![1, 2, 3]);
+        let mut movie = Movie::new_recording(path.clone(), None, VERSION, start.clone()).unwrap();
+        record(&mut movie, [1, 2]);
+        movie.queue_command(FrameCommand::SoftReset);
+        record(&mut movie, [3, 4]);
+        movie.save().unwrap();
+
+        let mut movie = Movie::<u8>::load(path.clone(), None, VERSION, false).unwrap();
+        assert_eq!(movie.start(), &start);
+        assert_eq!(
+            movie.next_frame(&0),
+            Some(MovieFrame { command: FrameCommand::None, inputs: 1 })
+        );
+        assert_eq!(
+            movie.next_frame(&0),
+            Some(MovieFrame { command: FrameCommand::None, inputs: 2 })
+        );
+        assert_eq!(
+            movie.next_frame(&0),
+            Some(MovieFrame { command: FrameCommand::SoftReset, inputs: 3 })
+        );
+
+        // Resets cannot be added during playback
+        movie.queue_command(FrameCommand::HardReset);
+        assert_eq!(
+            movie.next_frame(&0),
+            Some(MovieFrame { command: FrameCommand::None, inputs: 4 })
+        );
+        assert_eq!(movie.next_frame(&0), None);
+
+        assert!(matches!(
+            Movie::<u8>::load(path, None, "0.2.0", true),
+            Err(MovieError::StateVersionMismatch { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rerecord() {
+        let dir = test_dir("rerecord");
+        let path = dir.join("movie.jmv");
+        let state_path = dir.join("game_0.jst");
+
+        let mut movie =
+            Movie::new_recording(path.clone(), None, VERSION, MovieStart::PowerOn).unwrap();
+        record(&mut movie, [1, 2, 3]);
+        movie.on_state_saved(&state_path).unwrap();
+        record(&mut movie, [4, 5]);
+
+        assert_eq!(movie.on_state_loaded(&state_path), StateLoadEffect::Rerecord);
+        assert_eq!(movie.rerecord_count(), 1);
+        record(&mut movie, [6]);
+        movie.save().unwrap();
+
+        let mut movie = Movie::<u8>::load(path, None, VERSION, true).unwrap();
+        assert_eq!(movie.rerecord_count(), 1);
+        assert_eq!(play_back(&mut movie), [1, 2, 3, 6]);
+
+        // States not saved during the movie can't be used to rerecord it
+        assert_eq!(movie.on_state_loaded(&dir.join("game_1.jst")), StateLoadEffect::Desync);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn snapshots_persist_after_reopening() {
+        let dir = test_dir("reopen");
+        let path = dir.join("movie.jmv");
+        let state_path = dir.join("game_0.jst");
+
+        let mut movie =
+            Movie::new_recording(path.clone(), None, VERSION, MovieStart::PowerOn).unwrap();
+        record(&mut movie, [1, 2]);
+        movie.on_state_saved(&state_path).unwrap();
+        record(&mut movie, [3]);
+        movie.save().unwrap();
+
+        let mut movie = Movie::<u8>::load(path.clone(), None, VERSION, false).unwrap();
+        assert_eq!(movie.on_state_loaded(&state_path), StateLoadEffect::Rerecord);
+        record(&mut movie, [4, 5]);
+        movie.save().unwrap();
+
+        let mut movie = Movie::<u8>::load(path, None, VERSION, true).unwrap();
+        assert_eq!(movie.rerecord_count(), 1);
+        assert_eq!(play_back(&mut movie), [1, 2, 4, 5]);
+
+        // Snapshots are only attached to states saved during a movie with the same start
+        let other_path = dir.join("other.jmv");
+        let mut other =
+            Movie::<u8>::new_recording(other_path, None, VERSION, MovieStart::SaveState(vec![0]))
+                .unwrap();
+        assert_eq!(other.on_state_loaded(&state_path), StateLoadEffect::Desync);
+
+        remove_state_snapshot(&state_path);
+        let mut movie = Movie::<u8>::load(dir.join("movie.jmv"), None, VERSION, false).unwrap();
+        assert_eq!(movie.on_state_loaded(&state_path), StateLoadEffect::Desync);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_only_load_checks_timeline() {
+        let dir = test_dir("read-only");
+        let path = dir.join("movie.jmv");
+        let state_path = dir.join("game_0.jst");
+        let branch_state_path = dir.join("game_1.jst");
+
+        let mut movie =
+            Movie::new_recording(path.clone(), None, VERSION, MovieStart::PowerOn).unwrap();
+        record(&mut movie, [1, 2, 3]);
+        movie.on_state_saved(&state_path).unwrap();
+        record(&mut movie, [4]);
+        movie.save().unwrap();
+
+        // Branch off from the state without saving the movie, then save a state on the branch
+        let mut branch = Movie::<u8>::load(path.clone(), None, VERSION, false).unwrap();
+        assert_eq!(branch.on_state_loaded(&state_path), StateLoadEffect::Rerecord);
+        record(&mut branch, [7]);
+        branch.on_state_saved(&branch_state_path).unwrap();
+
+        let mut movie = Movie::<u8>::load(path, None, VERSION, true).unwrap();
+        assert_eq!(movie.on_state_loaded(&branch_state_path), StateLoadEffect::TimelineMismatch);
+        assert_eq!(movie.on_state_loaded(&state_path), StateLoadEffect::None);
+        assert_eq!(movie.rerecord_count(), 0);
+        assert_eq!(play_back(&mut movie), [4]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+//! FCEUX FM2 movie import/export (text format only).
+//!
+//! Each input record is a line of the form `|commands|port0|port1|port2|`, where each gamepad
+//! port is 8 characters in the order `RLDUTSBA`, with `.` or space meaning not pressed. Four Score
+//! movies instead have four gamepad fields before the `port2` field.
+
+use crate::mainloop::movie::{
+    ExternalMovie, ExternalMovieFormat, FrameCommand, MovieError, MovieFrame,
+};
+use nes_config::NesJoypadState;
+use nes_core::input::{NesFourPlayerAdapter, NesInputDevice, NesInputs};
+use std::fmt::Write;
+
+const FORMAT_NAME: &str = "FM2";
+
+const BUTTON_CHARS: [u8; 8] = *b"RLDUTSBA";
+
+const SOFT_RESET_BIT: u32 = 1 << 0;
+const HARD_RESET_BIT: u32 = 1 << 1;
+
+pub const FORMAT: ExternalMovieFormat<NesInputs> =
+    ExternalMovieFormat { name: FORMAT_NAME, extension: "fm2", import, export };
+
+fn invalid(message: impl Into<String>) -> MovieError {
+    MovieError::InvalidExternal { format: FORMAT_NAME, message: message.into() }
+}
+
+fn import(bytes: &[u8]) -> Result<ExternalMovie<NesInputs>, MovieError> {
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("file is not valid UTF-8"))?;
+
+    let mut rerecord_count = 0;
+    let mut port1_gamepad = true;
+    let mut four_score = false;
+    let mut frames = Vec::new();
+
+    for line in text.lines() {
+        if let Some(record) = line.strip_prefix('|') {
+            frames.push(parse_input_record(record, port1_gamepad, four_score)?);
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(' ') else { continue };
+        match key {
+            "binary" if value.trim() != "0" => {
+                return Err(invalid("binary input logs are not supported"));
+            }
+            "savestate" => {
+                return Err(invalid("movies that start from a savestate are not supported"));
+            }
+            "fourscore" => four_score = value.trim() != "0",
+            "rerecordCount" => {
+                rerecord_count =
+                    value.trim().parse().map_err(|_| invalid("invalid rerecordCount"))?;
+            }
+            "port1" => port1_gamepad = value.trim() == "1",
+            _ => {}
+        }
+    }
+
+    Ok(ExternalMovie { rerecord_count, frames })
+}
+
+fn parse_input_record(
+    record: &str,
+    port1_gamepad: bool,
+    four_score: bool,
+) -> Result<MovieFrame<NesInputs>, MovieError> {
+    let mut fields = record.split('|');
+
+    let commands: u32 = fields
+        .next()
+        .and_then(|field| field.trim().parse().ok())
+        .ok_or_else(|| invalid(format!("invalid input record: '|{record}'")))?;
+    let command = if commands & HARD_RESET_BIT != 0 {
+        FrameCommand::HardReset
+    } else if commands & SOFT_RESET_BIT != 0 {
+        FrameCommand::SoftReset
+    } else {
+        FrameCommand::None
+    };
+
+    if four_score {
+        let mut next_gamepad = || parse_gamepad(fields.next().unwrap_or(""));
+        let inputs = NesInputs {
+            p1: next_gamepad(),
+            p2: NesInputDevice::Controller(next_gamepad()),
+            p3: next_gamepad(),
+            p4: next_gamepad(),
+            four_player_adapter: NesFourPlayerAdapter::FourScore,
+        };
+        return Ok(MovieFrame { command, inputs });
+    }
+
+    let p1 = parse_gamepad(fields.next().unwrap_or(""));
+    let p2 = if port1_gamepad {
+        NesInputDevice::Controller(parse_gamepad(fields.next().unwrap_or("")))
+    } else {
+        NesInputDevice::default()
+    };
+
+    Ok(MovieFrame { command, inputs: NesInputs { p1, p2, ..NesInputs::default() } })
+}
+
+fn parse_gamepad(field: &str) -> NesJoypadState {
+    let pressed: Vec<bool> = field.bytes().map(|c| c != b'.' && c != b' ').collect();
+    let pressed = |i: usize| pressed.get(i).copied().unwrap_or(false);
+
+    NesJoypadState {
+        right: pressed(0),
+        left: pressed(1),
+        down: pressed(2),
+        up: pressed(3),
+        start: pressed(4),
+        select: pressed(5),
+        b: pressed(6),
+        a: pressed(7),
+    }
+}
+
+fn export(movie: &ExternalMovie<NesInputs>) -> Vec<u8> {
+    let four_score = movie
+        .frames
+        .iter()
+        .any(|frame| frame.inputs.four_player_adapter == NesFourPlayerAdapter::FourScore);
+
+    let mut out = String::new();
+
+    for line in [
+        "version 3",
+        "emuVersion 0",
+        &format!("rerecordCount {}", movie.rerecord_count),
+        "palFlag 0",
+        "romFilename jgenesis",
+        "romChecksum base64:AAAAAAAAAAAAAAAAAAAAAA==",
+        "guid 00000000-0000-0000-0000-000000000000",
+        if four_score { "fourscore 1" } else { "fourscore 0" },
+        "microphone 0",
+        "port0 1",
+        "port1 1",
+        "port2 0",
+        "FDS 0",
+        "NewPPU 0",
+    ] {
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    for frame in &movie.frames {
+        let commands = match frame.command {
+            FrameCommand::None => 0,
+            FrameCommand::SoftReset => SOFT_RESET_BIT,
+            FrameCommand::HardReset => HARD_RESET_BIT,
+        };
+
+        let p2 = match frame.inputs.p2 {
+            NesInputDevice::Controller(joypad_state) => joypad_state,
+            NesInputDevice::Zapper(_)
+            | NesInputDevice::ArkanoidVaus(_)
+            | NesInputDevice::PowerPad(_) => NesJoypadState::default(),
+        };
+
+        write!(out, "|{commands}|{}|{}|", format_gamepad(frame.inputs.p1), format_gamepad(p2))
+            .unwrap();
+        if four_score {
+            write!(out, "{}|{}|", format_gamepad(frame.inputs.p3), format_gamepad(frame.inputs.p4))
+                .unwrap();
+        }
+        out.push_str("|\n");
+    }
+
+    out.into_bytes()
+}
+
+fn format_gamepad(joypad_state: NesJoypadState) -> String {
+    let NesJoypadState { right, left, down, up, start, select, b, a } = joypad_state;
+
+    [right, left, down, up, start, select, b, a]
+        .into_iter()
+        .zip(BUTTON_CHARS)
+        .map(|(pressed, c)| if pressed { c as char } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_export_round_trip() {
+        let fm2 = "version 3\nrerecordCount 42\nport0 1\nport1 1\n|0|R......A|........||\n|1|...U....|.L......||\n";
+
+        let movie = import(fm2.as_bytes()).unwrap();
+        assert_eq!(movie.rerecord_count, 42);
+        assert_eq!(movie.frames.len(), 2);
+        assert!(movie.frames[0].inputs.p1.right && movie.frames[0].inputs.p1.a);
+        assert!(!movie.frames[0].inputs.p1.b);
+        assert_eq!(movie.frames[1].command, FrameCommand::SoftReset);
+        assert_eq!(
+            movie.frames[1].inputs.p2,
+            NesInputDevice::Controller(NesJoypadState { left: true, ..NesJoypadState::default() })
+        );
+
+        let exported = export(&movie);
+        assert_eq!(import(&exported).unwrap().frames, movie.frames);
+    }
+
+    #[test]
+    fn four_score_round_trip() {
+        let fm2 = "version 3\nfourscore 1\n|0|R.......|........|.......A|...U....||\n";
+
+        let movie = import(fm2.as_bytes()).unwrap();
+        assert_eq!(movie.frames.len(), 1);
+        let inputs = movie.frames[0].inputs;
+        assert_eq!(inputs.four_player_adapter, NesFourPlayerAdapter::FourScore);
+        assert!(inputs.p1.right && inputs.p3.a && inputs.p4.up);
+
+        let exported = export(&movie);
+        assert_eq!(import(&exported).unwrap().frames, movie.frames);
+    }
+}
@@ -0,0 +1,139 @@
+//! Gens GMV movie import/export.
+//!
+//! GMV files have a 64-byte header followed by 3 bytes per frame: one byte each for the player 1
+//! and 2 D-pad/ABC/Start buttons, then one byte containing both players' XYZ/Mode buttons. Buttons
+//! are active low.
+
+use crate::mainloop::movie::{
+    ExternalMovie, ExternalMovieFormat, FrameCommand, MovieError, MovieFrame,
+};
+use genesis_config::{GenesisInputs, GenesisJoypadState};
+
+const FORMAT_NAME: &str = "GMV";
+
+const SIGNATURE: &[u8] = b"Gens Movie TEST";
+const VERSION: u8 = b'A';
+const HEADER_LEN: usize = 0x40;
+const FRAME_LEN: usize = 3;
+
+const FLAG_FROM_SAVESTATE: u8 = 1 << 7;
+const FLAG_THREE_PLAYERS: u8 = 1 << 5;
+
+pub const FORMAT: ExternalMovieFormat<GenesisInputs> =
+    ExternalMovieFormat { name: FORMAT_NAME, extension: "gmv", import, export };
+
+fn invalid(message: &str) -> MovieError {
+    MovieError::InvalidExternal { format: FORMAT_NAME, message: message.into() }
+}
+
+fn import(bytes: &[u8]) -> Result<ExternalMovie<GenesisInputs>, MovieError> {
+    if bytes.len() < HEADER_LEN || !bytes.starts_with(SIGNATURE) {
+        return Err(invalid("missing GMV signature"));
+    }
+
+    // Flags are only present in version A and later
+    let flags = if bytes[15] >= b'A' { bytes[0x16] } else { 0 };
+    if flags & FLAG_FROM_SAVESTATE != 0 {
+        return Err(invalid("movies that start from a savestate are not supported"));
+    }
+    if flags & FLAG_THREE_PLAYERS != 0 {
+        return Err(invalid("3-player movies are not supported"));
+    }
+
+    let rerecord_count = u32::from_le_bytes(bytes[0x10..0x14].try_into().unwrap());
+
+    let frames = bytes[HEADER_LEN..]
+        .chunks_exact(FRAME_LEN)
+        .map(|frame| {
+            let p1 = decode_joypad(frame[0], frame[2]);
+            let p2 = decode_joypad(frame[1], frame[2] >> 4);
+            MovieFrame {
+                command: FrameCommand::None,
+                inputs: GenesisInputs { p1, p2, ..GenesisInputs::default() },
+            }
+        })
+        .collect();
+
+    Ok(ExternalMovie { rerecord_count, frames })
+}
+
+fn decode_joypad(main: u8, extra: u8) -> GenesisJoypadState {
+    let pressed = |byte: u8, bit: u8| byte & (1 << bit) == 0;
+
+    GenesisJoypadState {
+        up: pressed(main, 0),
+        down: pressed(main, 1),
+        left: pressed(main, 2),
+        right: pressed(main, 3),
+        a: pressed(main, 4),
+        b: pressed(main, 5),
+        c: pressed(main, 6),
+        start: pressed(main, 7),
+        x: pressed(extra, 0),
+        y: pressed(extra, 1),
+        z: pressed(extra, 2),
+        mode: pressed(extra, 3),
+    }
+}
+
+fn export(movie: &ExternalMovie<GenesisInputs>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + FRAME_LEN * movie.frames.len());
+
+    out.extend(SIGNATURE);
+    out.push(VERSION);
+    out.extend(movie.rerecord_count.to_le_bytes());
+    // Both players use 6-button controllers; no flags
+    out.extend([b'6', b'6', 0, 0]);
+    out.resize(HEADER_LEN, 0);
+
+    for frame in &movie.frames {
+        let (p1_main, p1_extra) = encode_joypad(frame.inputs.p1);
+        let (p2_main, p2_extra) = encode_joypad(frame.inputs.p2);
+        out.extend([p1_main, p2_main, p1_extra | (p2_extra << 4)]);
+    }
+
+    out
+}
+
+fn encode_joypad(joypad_state: GenesisJoypadState) -> (u8, u8) {
+    let GenesisJoypadState { up, left, right, down, a, b, c, x, y, z, start, mode } = joypad_state;
+
+    let to_byte = |buttons: &[bool]| {
+        buttons
+            .iter()
+            .enumerate()
+            .fold(0_u8, |byte, (bit, &pressed)| byte | (u8::from(!pressed) << bit))
+    };
+
+    let main = to_byte(&[up, down, left, right, a, b, c, start]);
+    let extra = to_byte(&[x, y, z, mode]);
+    (main, extra)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_import_round_trip() {
+        let movie = ExternalMovie {
+            rerecord_count: 7,
+            frames: vec![MovieFrame {
+                command: FrameCommand::None,
+                inputs: GenesisInputs {
+                    p1: GenesisJoypadState { up: true, start: true, z: true, ..Default::default() },
+                    p2: GenesisJoypadState { c: true, mode: true, ..Default::default() },
+                    ..GenesisInputs::default()
+                },
+            }],
+        };
+
+        let exported = export(&movie);
+        assert_eq!(exported.len(), HEADER_LEN + FRAME_LEN);
+        assert_eq!(exported[HEADER_LEN..], [0x7E, 0xBF, 0x7B]);
+
+        let imported = import(&exported).unwrap();
+        assert_eq!(imported.rerecord_count, 7);
+        assert_eq!(imported.frames, movie.frames);
+    }
+}
@@ -1,16 +1,20 @@
 use crate::config::NesConfig;
 
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
-use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, save};
-use crate::{NativeEmulator, NativeEmulatorResult, extensions};
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
+use crate::mainloop::save::{CreateSaveWriter, DeterminedPaths};
+use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, movie, save};
+use crate::{NativeEmulator, NativeEmulatorError, NativeEmulatorResult, extensions};
 
 use nes_core::api::NesEmulator;
-use nes_core::input::{NesInputDevice, NesInputs, ZapperState};
+use nes_core::input::{
+    ArkanoidVausState, NesFourPlayerAdapter, NesInputDevice, NesInputs, PowerPadState, ZapperState,
+};
 
 use crate::config::RomReadResult;
 use jgenesis_native_config::common::WindowSize;
-use jgenesis_native_config::input::mappings::NesControllerType;
+use jgenesis_native_config::input::mappings::{NesControllerType, NesFourPlayerAdapterType};
 use nes_config::NesJoypadState;
+use std::fs;
 use std::path::Path;
 
 trait NesControllerTypeExt {
@@ -22,12 +26,45 @@ impl NesControllerTypeExt for NesControllerType {
         match self {
             Self::Gamepad => NesInputDevice::Controller(NesJoypadState::default()),
             Self::Zapper => NesInputDevice::Zapper(ZapperState::default()),
+            Self::ArkanoidVaus => NesInputDevice::ArkanoidVaus(ArkanoidVausState::default()),
+            Self::PowerPad => NesInputDevice::PowerPad(PowerPadState::default()),
+        }
+    }
+}
+
+trait NesFourPlayerAdapterTypeExt {
+    fn to_adapter(self) -> NesFourPlayerAdapter;
+}
+
+impl NesFourPlayerAdapterTypeExt for NesFourPlayerAdapterType {
+    fn to_adapter(self) -> NesFourPlayerAdapter {
+        match self {
+            Self::None => NesFourPlayerAdapter::None,
+            Self::FourScore => NesFourPlayerAdapter::FourScore,
+            Self::Hori => NesFourPlayerAdapter::Hori,
         }
     }
 }
 
 pub type NativeNesEmulator = NativeEmulator<NesEmulator>;
 
+fn merge_netplay_inputs(p1_inputs: &NesInputs, p2_inputs: &NesInputs) -> NesInputs {
+    // The peer's controller drives player 2 unless the peer has a Zapper, Vaus, or Power Pad
+    // connected to port 2. Players 3/4 and the four player adapter are always local to the host
+    let p2 = match p2_inputs.p2 {
+        NesInputDevice::Controller(_) => NesInputDevice::Controller(p2_inputs.p1),
+        device => device,
+    };
+
+    NesInputs {
+        p1: p1_inputs.p1,
+        p2,
+        p3: p1_inputs.p3,
+        p4: p1_inputs.p4,
+        four_player_adapter: p1_inputs.four_player_adapter,
+    }
+}
+
 impl NativeNesEmulator {
     /// # Errors
     ///
@@ -46,22 +83,59 @@ impl NativeNesEmulator {
             &config.common.hotkey_config.to_mapping_vec(),
         );
         self.inputs.p2 = config.inputs.p2_type.to_input_device();
+        self.inputs.four_player_adapter = config.inputs.four_player_adapter.to_adapter();
 
         Ok(())
     }
 }
 
+fn is_fds_extension(extension: &str) -> bool {
+    extension.eq_ignore_ascii_case("fds") || extension.eq_ignore_ascii_case("qd")
+}
+
 /// Create an emulator with the NES core with the given config.
 ///
 /// # Errors
 ///
 /// Propagates any errors encountered during initialization.
 pub fn create_nes(config: Box<NesConfig>) -> NativeEmulatorResult<NativeNesEmulator> {
+    launch_nes(config, NativeNesEmulator::new)
+}
+
+/// Run the NES core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_nes(
+    config: Box<NesConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_nes(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_nes<T>(
+    config: Box<NesConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, NesEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.common.rom_file_path);
     let RomReadResult { rom, extension } = config.common.read_rom_file(extensions::NES)?;
 
+    // FDS disk images require the FDS BIOS; all other files are treated as iNES/NES 2.0 ROMs
+    let fds_bios_rom = if is_fds_extension(&extension) {
+        let Some(bios_path) = &config.fds_bios_path else {
+            return Err(NativeEmulatorError::NesNoFdsBios);
+        };
+
+        Some(fs::read(bios_path).map_err(NativeEmulatorError::NesFdsBiosLoad)?)
+    } else {
+        None
+    };
+
     let DeterminedPaths { save_path, save_state_path } = save::determine_save_paths(
         &config.common.save_path,
         &config.common.state_path,
@@ -73,8 +147,11 @@ pub fn create_nes(config: Box<NesConfig>) -> NativeEmulatorResult<NativeNesEmula
     let initial_window_size = config.common.initial_window_size;
     let rom_file_path = config.common.rom_file_path.clone();
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
-        let emulator = NesEmulator::create(rom, emulator_config, save_writer)?;
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
+        let emulator = match fds_bios_rom {
+            Some(bios_rom) => NesEmulator::create_fds(rom, bios_rom, emulator_config, save_writer)?,
+            None => NesEmulator::create(rom, emulator_config, save_writer)?,
+        };
 
         let rom_title = file_name_no_ext(rom_file_path)?;
         let window_title = format!("nes - {rom_title}");
@@ -89,10 +166,13 @@ pub fn create_nes(config: Box<NesConfig>) -> NativeEmulatorResult<NativeNesEmula
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    let initial_inputs =
-        NesInputs { p1: NesJoypadState::default(), p2: config.inputs.p2_type.to_input_device() };
+    let initial_inputs = NesInputs {
+        p2: config.inputs.p2_type.to_input_device(),
+        four_player_adapter: config.inputs.four_player_adapter.to_adapter(),
+        ..NesInputs::default()
+    };
 
-    NativeNesEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -104,6 +184,9 @@ pub fn create_nes(config: Box<NesConfig>) -> NativeEmulatorResult<NativeNesEmula
         )
         .with_turbo_mappings(config.inputs.to_turbo_mapping_vec())
         .with_initial_inputs(initial_inputs)
+        .with_disk_side_change_fn(NesEmulator::change_fds_disk_side)
+        .with_movie_format(movie::fm2::FORMAT)
+        .with_netplay_merge_fn(merge_netplay_inputs)
         .with_debug_fn(|| {
             jgenesis_debugger_frontend::partial_clone_debug_fn(
                 jgenesis_debugger_frontend::nes::render_fn(),
@@ -0,0 +1,1190 @@
+//! Two-player rollback netplay over UDP.
+//!
+//! Each peer sends its local player's inputs to the other peer every frame. Remote inputs that
+//! have not arrived yet are predicted by repeating the most recent remote inputs; when a
+//! prediction turns out to be wrong, the emulator is restored from a snapshot taken before the
+//! mispredicted frame and the intervening frames are re-simulated without video or audio output.
+//!
+//! When the session connects, the host hard resets and sends its emulator state to the client so
+//! that both peers start from the same state, even if some of it was randomized at power-on. Peers
+//! must still use the same ROM and emulator settings; the session ends immediately if the peers'
+//! ROM files or versions differ, and peers periodically exchange hashes of confirmed emulator state
+//! to detect desyncs.
+
+use crate::mainloop::audio::SdlAudioOutput;
+use crate::mainloop::bincode_config;
+use crate::mainloop::input::ThreadedInputPoller;
+use crate::mainloop::render::ThreadedRenderer;
+use crate::mainloop::runner::RunTillNextErr;
+use crate::mainloop::save::FsSaveWriter;
+use crate::mainloop::state;
+use bincode::{Decode, Encode};
+use jgenesis_common::frontend::{
+    AudioOutput, Color, EmulatorTrait, FrameSize, PartialClone, RenderFrameOptions, Renderer,
+    SaveWriter, TickEffect,
+};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+// Maximum number of frames that the local peer can run ahead of the last received remote inputs
+const MAX_PREDICTION_FRAMES: u64 = 8;
+
+// Maximum number of local inputs to include in a single packet
+const MAX_INPUTS_PER_PACKET: usize = 64;
+
+const CHECKSUM_INTERVAL: u64 = 60;
+const TIME_SYNC_INTERVAL: u64 = 60;
+const MAX_TIME_SYNC_STALL_FRAMES: u64 = 4;
+
+// Initial state is sent in chunks small enough to avoid IP fragmentation
+const STATE_CHUNK_LEN: usize = 1200;
+const STATE_CHUNK_WINDOW: usize = 32;
+const STATE_RESEND_INTERVAL: Duration = Duration::from_millis(50);
+
+const HELLO_INTERVAL: Duration = Duration::from_millis(200);
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAX_PACKET_LEN: usize = 8192;
+
+// Only the start of large ROM files and disc images is hashed to check that peers match
+const MAX_ROM_HASH_LEN: u64 = 16 * 1024 * 1024;
+
+/// Combine player 1's local inputs and player 2's local inputs into the inputs for a single frame.
+///
+/// Both peers map their controls to player 1, so this is responsible for moving player 2's
+/// controls into the second controller port.
+pub type MergeNetplayInputsFn<Inputs> = fn(&Inputs, &Inputs) -> Inputs;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetplayConfig {
+    /// Local UDP port to bind. The host must bind the port that the other peer connects to.
+    pub local_port: u16,
+    /// Address of the host to connect to as player 2, or `None` to host the session as player 1
+    pub remote_addr: Option<SocketAddr>,
+    /// Number of frames to delay local inputs by; higher values reduce rollbacks on slow
+    /// connections at the cost of input latency
+    pub input_delay: u8,
+}
+
+#[derive(Debug, Error)]
+pub enum NetplayError {
+    #[error("Netplay is not supported for this system")]
+    Unsupported,
+    #[error("Not available while netplay is active")]
+    Active,
+    #[error("Error binding UDP port {port}: {source}")]
+    Bind {
+        port: u16,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Netplay socket error: {0}")]
+    Socket(#[source] io::Error),
+}
+
+enum Phase {
+    Connecting,
+    // Host only; chunks are empty until the caller has reset the emulator
+    SendingState { chunks: Vec<Vec<u8>>, acked: usize, last_send_time: Option<Instant> },
+    // Client only
+    ReceivingState { chunks: Vec<Vec<u8>>, count: Option<usize> },
+    Running,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetplayEvent {
+    Connected { peer: SocketAddr },
+    Desync { frame: u64 },
+    Disconnected { reason: String },
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+enum Message<Inputs> {
+    Hello {
+        save_state_version: String,
+        audio_output_frequency: u64,
+        rom_hash: u64,
+    },
+    // Sent by the host when the client's hello does not match; the session ends on both peers
+    Reject {
+        reason: String,
+    },
+    StateChunk {
+        index: u32,
+        count: u32,
+        data: Vec<u8>,
+    },
+    // Number of state chunks that the client has received
+    StateAck {
+        received: u32,
+    },
+    Inputs {
+        // Frame of the first entry in `inputs`
+        start_frame: u64,
+        inputs: Vec<Inputs>,
+        // Number of the receiver's inputs that the sender has received, used to trim resends
+        received_count: u64,
+        // Sender's current frame and frame advantage, used for time synchronization
+        frame: u64,
+        advantage: i64,
+    },
+    Checksum {
+        frame: u64,
+        hash: u64,
+    },
+}
+
+/// Per-frame values starting from some base frame, with older frames discarded once they are no
+/// longer needed.
+#[derive(Debug, Clone)]
+struct FrameBuffer<T> {
+    base: u64,
+    values: VecDeque<T>,
+}
+
+impl<T> FrameBuffer<T> {
+    fn new() -> Self {
+        Self { base: 0, values: VecDeque::new() }
+    }
+
+    // Frame after the last value in the buffer
+    fn end(&self) -> u64 {
+        self.base + self.values.len() as u64
+    }
+
+    fn get(&self, frame: u64) -> Option<&T> {
+        let index = frame.checked_sub(self.base)?;
+        self.values.get(index as usize)
+    }
+
+    fn last(&self) -> Option<&T> {
+        self.values.back()
+    }
+
+    fn push(&mut self, value: T) {
+        self.values.push_back(value);
+    }
+
+    // Set the value for the given frame, which must be at most `end()`, and discard all values
+    // after it
+    fn set(&mut self, frame: u64, value: T) {
+        let index = (frame - self.base) as usize;
+        self.values.truncate(index);
+        self.values.push_back(value);
+    }
+
+    fn discard_before(&mut self, frame: u64) {
+        while self.base < frame && !self.values.is_empty() {
+            self.values.pop_front();
+            self.base += 1;
+        }
+    }
+}
+
+pub struct NetplaySession<Emulator: EmulatorTrait> {
+    socket: UdpSocket,
+    peer: Option<SocketAddr>,
+    is_host: bool,
+    merge_fn: MergeNetplayInputsFn<Emulator::Inputs>,
+    save_state_version: String,
+    audio_output_frequency: u64,
+    rom_hash: u64,
+    phase: Phase,
+    last_hello_time: Option<Instant>,
+    last_receive_time: Instant,
+    // Next frame to run
+    frame: u64,
+    local_inputs: FrameBuffer<Emulator::Inputs>,
+    remote_inputs: FrameBuffer<Emulator::Inputs>,
+    // Remote inputs that were actually used for each frame that has run, possibly predicted
+    used_remote_inputs: FrameBuffer<Emulator::Inputs>,
+    // Emulator state at the start of each frame that could still be rolled back to
+    snapshots: FrameBuffer<Emulator>,
+    rollback_frame: Option<u64>,
+    remote_received_count: u64,
+    remote_frame: u64,
+    remote_advantage: i64,
+    stall_frames: u64,
+    last_time_sync_frame: u64,
+    next_checksum_frame: u64,
+    local_checksums: VecDeque<(u64, u64)>,
+    remote_checksums: VecDeque<(u64, u64)>,
+    desynced: bool,
+    events: Vec<NetplayEvent>,
+}
+
+impl<Emulator: EmulatorTrait> NetplaySession<Emulator> {
+    /// Bind the local socket. Hosts wait for a peer to connect; clients repeatedly send a hello
+    /// message to the host until it responds.
+    pub fn new(
+        config: &NetplayConfig,
+        merge_fn: MergeNetplayInputsFn<Emulator::Inputs>,
+        save_state_version: &str,
+        audio_output_frequency: u64,
+        rom_hash: u64,
+    ) -> Result<Self, NetplayError> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, config.local_port))
+            .map_err(|source| NetplayError::Bind { port: config.local_port, source })?;
+        socket.set_nonblocking(true).map_err(NetplayError::Socket)?;
+
+        match config.remote_addr {
+            Some(remote_addr) => log::info!(
+                "Connecting to netplay host at {remote_addr} from port {}",
+                config.local_port
+            ),
+            None => log::info!("Hosting netplay session on port {}", config.local_port),
+        }
+
+        let mut local_inputs = FrameBuffer::new();
+        for _ in 0..config.input_delay {
+            local_inputs.push(Emulator::Inputs::default());
+        }
+
+        Ok(Self {
+            socket,
+            peer: config.remote_addr,
+            is_host: config.remote_addr.is_none(),
+            merge_fn,
+            save_state_version: save_state_version.into(),
+            audio_output_frequency,
+            rom_hash,
+            phase: Phase::Connecting,
+            last_hello_time: None,
+            last_receive_time: Instant::now(),
+            frame: 0,
+            local_inputs,
+            remote_inputs: FrameBuffer::new(),
+            used_remote_inputs: FrameBuffer::new(),
+            snapshots: FrameBuffer::new(),
+            rollback_frame: None,
+            remote_received_count: 0,
+            remote_frame: 0,
+            remote_advantage: 0,
+            stall_frames: 0,
+            last_time_sync_frame: 0,
+            next_checksum_frame: CHECKSUM_INTERVAL,
+            local_checksums: VecDeque::new(),
+            remote_checksums: VecDeque::new(),
+            desynced: false,
+            events: vec![],
+        })
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.is_host
+    }
+
+    pub fn take_events(&mut self) -> Vec<NetplayEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Exchange messages with the peer, roll back if any remote inputs were mispredicted, and
+    /// latch the inputs for the next frame.
+    ///
+    /// Returns whether the emulator should run a frame. Returns `false` while connecting and
+    /// transferring the initial state, or when the local peer is too far ahead of the remote
+    /// peer.
+    ///
+    /// If this is the host, the caller should hard reset the emulator when a
+    /// [`NetplayEvent::Connected`] event is emitted; the reset state is sent to the client before
+    /// the first frame.
+    pub fn prepare_frame(
+        &mut self,
+        emulator: &mut Emulator,
+        config: &Emulator::Config,
+        input_poller: &mut ThreadedInputPoller<Emulator::Inputs>,
+        save_writer: &mut FsSaveWriter,
+    ) -> Result<bool, RunTillNextErr<Emulator>> {
+        if matches!(self.phase, Phase::Disconnected) {
+            return Ok(false);
+        }
+
+        let was_connecting = matches!(self.phase, Phase::Connecting);
+        self.receive_messages(emulator, config);
+
+        if was_connecting && self.is_host && !matches!(self.phase, Phase::Connecting) {
+            // Give the caller a chance to reset the emulator before sending the initial state
+            return Ok(false);
+        }
+
+        match self.phase {
+            Phase::Connecting => {
+                self.send_hello_if_needed();
+                return Ok(false);
+            }
+            Phase::SendingState { .. } => {
+                self.send_state_chunks(emulator);
+                self.check_timeout();
+                return Ok(false);
+            }
+            Phase::ReceivingState { .. } => {
+                self.check_timeout();
+                return Ok(false);
+            }
+            Phase::Disconnected => return Ok(false),
+            Phase::Running => {}
+        }
+
+        if self.check_timeout() {
+            return Ok(false);
+        }
+
+        if let Some(rollback_frame) = self.rollback_frame.take() {
+            self.roll_back(rollback_frame, emulator, config, input_poller, save_writer)?;
+        }
+
+        self.update_checksums();
+
+        if self.frame >= self.remote_inputs.end() + MAX_PREDICTION_FRAMES {
+            // Too far ahead of the remote peer to keep predicting; wait for its inputs
+            self.send_inputs();
+            return Ok(false);
+        }
+
+        if self.frame.is_multiple_of(TIME_SYNC_INTERVAL) && self.frame != self.last_time_sync_frame
+        {
+            self.last_time_sync_frame = self.frame;
+
+            // Both peers measure their advantage with the same latency, so half of the difference
+            // is how far ahead this peer is running
+            let local_advantage = self.frame as i64 - self.remote_frame as i64;
+            let frames_ahead = (local_advantage - self.remote_advantage) / 2;
+            if frames_ahead > 0 {
+                self.stall_frames = (frames_ahead as u64).min(MAX_TIME_SYNC_STALL_FRAMES);
+            }
+        }
+
+        if self.stall_frames != 0 {
+            self.stall_frames -= 1;
+            self.send_inputs();
+            return Ok(false);
+        }
+
+        self.local_inputs.push(input_poller.poll_live().clone());
+        self.send_inputs();
+
+        self.snapshots.set(self.frame, emulator.partial_clone());
+        let inputs = self.frame_inputs(self.frame);
+        input_poller.set_frame_inputs(Some(inputs));
+        self.frame += 1;
+
+        self.discard_old_frames();
+
+        Ok(true)
+    }
+
+    fn frame_inputs(&mut self, frame: u64) -> Emulator::Inputs {
+        let local = self.local_inputs.get(frame).cloned().unwrap_or_default();
+        let remote = self
+            .remote_inputs
+            .get(frame)
+            .or_else(|| self.remote_inputs.last())
+            .cloned()
+            .unwrap_or_default();
+
+        let inputs = if self.is_host {
+            (self.merge_fn)(&local, &remote)
+        } else {
+            (self.merge_fn)(&remote, &local)
+        };
+        self.used_remote_inputs.set(frame, remote);
+
+        inputs
+    }
+
+    fn roll_back(
+        &mut self,
+        rollback_frame: u64,
+        emulator: &mut Emulator,
+        config: &Emulator::Config,
+        input_poller: &mut ThreadedInputPoller<Emulator::Inputs>,
+        save_writer: &mut FsSaveWriter,
+    ) -> Result<(), RunTillNextErr<Emulator>> {
+        let Some(snapshot) = self.snapshots.get(rollback_frame) else {
+            log::error!("Netplay rollback to frame {rollback_frame} failed; no snapshot available");
+            return Ok(());
+        };
+
+        log::debug!("Rolling back from frame {} to frame {rollback_frame}", self.frame);
+
+        state::replace_emulator(emulator, snapshot.partial_clone(), config);
+
+        let mut save_writer = ResimulationSaveWriter(save_writer);
+        for frame in rollback_frame..self.frame {
+            if frame != rollback_frame {
+                self.snapshots.set(frame, emulator.partial_clone());
+            }
+
+            let inputs = self.frame_inputs(frame);
+            input_poller.set_frame_inputs(Some(inputs));
+
+            while emulator.tick(
+                &mut NullRenderer,
+                &mut NullAudioOutput,
+                input_poller,
+                &mut save_writer,
+            )? != TickEffect::FrameRendered
+            {}
+        }
+
+        Ok(())
+    }
+
+    fn update_checksums(&mut self) {
+        // A frame's snapshot can no longer change once remote inputs are confirmed up to it
+        while self.next_checksum_frame < self.frame
+            && self.next_checksum_frame <= self.remote_inputs.end()
+        {
+            let frame = self.next_checksum_frame;
+            self.next_checksum_frame += CHECKSUM_INTERVAL;
+
+            let Some(snapshot) = self.snapshots.get(frame) else { continue };
+            let hash = match bincode::encode_to_vec(snapshot, bincode_config!()) {
+                Ok(bytes) => fnv1a_hash(&bytes),
+                Err(err) => {
+                    log::error!("Error serializing emulator state for netplay checksum: {err}");
+                    continue;
+                }
+            };
+
+            self.send(&Message::Checksum { frame, hash });
+            push_checksum(&mut self.local_checksums, frame, hash);
+        }
+
+        self.compare_checksums();
+    }
+
+    fn compare_checksums(&mut self) {
+        if self.desynced {
+            return;
+        }
+
+        for &(frame, local_hash) in &self.local_checksums {
+            let Some(&(_, remote_hash)) = self.remote_checksums.iter().find(|&&(f, _)| f == frame)
+            else {
+                continue;
+            };
+
+            if local_hash != remote_hash {
+                log::error!(
+                    "Netplay desync detected at frame {frame}: local hash {local_hash:016X}, remote hash {remote_hash:016X}"
+                );
+                self.desynced = true;
+                self.events.push(NetplayEvent::Desync { frame });
+                return;
+            }
+        }
+    }
+
+    fn discard_old_frames(&mut self) {
+        let confirmed_frame = self.remote_inputs.end().min(self.frame);
+
+        self.snapshots.discard_before(confirmed_frame.min(self.next_checksum_frame));
+        self.used_remote_inputs.discard_before(confirmed_frame);
+        self.remote_inputs.discard_before(confirmed_frame.saturating_sub(1));
+        self.local_inputs.discard_before(confirmed_frame.min(self.remote_received_count));
+    }
+
+    fn check_timeout(&mut self) -> bool {
+        let timed_out = self.last_receive_time.elapsed() >= DISCONNECT_TIMEOUT;
+        if timed_out {
+            self.disconnect("Timed out waiting for peer".into());
+        }
+
+        timed_out
+    }
+
+    // End the session; the disconnected event is only emitted once
+    fn disconnect(&mut self, reason: String) {
+        if matches!(self.phase, Phase::Disconnected) {
+            return;
+        }
+
+        self.phase = Phase::Disconnected;
+        self.events.push(NetplayEvent::Disconnected { reason });
+    }
+
+    fn receive_messages(&mut self, emulator: &mut Emulator, config: &Emulator::Config) {
+        let mut buf = [0; MAX_PACKET_LEN];
+
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return,
+                Err(err) => {
+                    // On some platforms, ICMP port unreachable errors show up here if the peer
+                    // is not running yet
+                    log::debug!("Error receiving netplay packet: {err}");
+                    continue;
+                }
+            };
+
+            if self.peer.is_some_and(|peer| peer != addr) {
+                log::warn!("Ignoring netplay packet from unknown address {addr}");
+                continue;
+            }
+
+            let message: Message<Emulator::Inputs> =
+                match bincode::decode_from_slice(&buf[..len], bincode_config!()) {
+                    Ok((message, _)) => message,
+                    Err(err) => {
+                        log::warn!("Ignoring invalid netplay packet from {addr}: {err}");
+                        continue;
+                    }
+                };
+
+            self.last_receive_time = Instant::now();
+            self.handle_message(message, addr, emulator, config);
+
+            if matches!(self.phase, Phase::Disconnected) {
+                return;
+            }
+        }
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Message<Emulator::Inputs>,
+        addr: SocketAddr,
+        emulator: &mut Emulator,
+        config: &Emulator::Config,
+    ) {
+        match message {
+            Message::Hello { save_state_version, audio_output_frequency, rom_hash } => {
+                let mismatch = if rom_hash != self.rom_hash {
+                    Some("Peer is running a different ROM".into())
+                } else if save_state_version != self.save_state_version
+                    || audio_output_frequency != self.audio_output_frequency
+                {
+                    Some(format!(
+                        "Peer version or audio settings do not match (peer version '{save_state_version}', audio frequency {audio_output_frequency})"
+                    ))
+                } else {
+                    None
+                };
+
+                if let Some(reason) = mismatch {
+                    if self.is_host {
+                        // Let the client know so that it does not keep waiting for the host
+                        self.peer = Some(addr);
+                        self.send(&Message::Reject { reason: reason.clone() });
+                    }
+                    self.disconnect(reason);
+                    return;
+                }
+
+                if self.is_host {
+                    // Reply so that the client knows the session has started; the client may
+                    // resend its hello if this reply is lost
+                    self.peer = Some(addr);
+                    self.send(&self.hello_message());
+                }
+
+                if matches!(self.phase, Phase::Connecting) {
+                    self.on_connected(addr);
+                }
+            }
+            Message::StateChunk { index, count, data } => {
+                if matches!(self.phase, Phase::Connecting) && !self.is_host {
+                    // The host's reply to the hello message was lost
+                    self.on_connected(addr);
+                }
+
+                self.receive_state_chunk(index as usize, count as usize, data, emulator, config);
+            }
+            Message::StateAck { received } => {
+                if let Phase::SendingState { chunks, acked, last_send_time } = &mut self.phase
+                    && !chunks.is_empty()
+                {
+                    let received = received as usize;
+                    if received >= chunks.len() {
+                        log::info!("Netplay peer received initial state");
+                        self.phase = Phase::Running;
+                    } else if received > *acked {
+                        *acked = received;
+                        // Send the next window immediately
+                        *last_send_time = None;
+                    }
+                }
+            }
+            Message::Inputs { start_frame, inputs, received_count, frame, advantage } => {
+                if matches!(self.phase, Phase::SendingState { .. }) {
+                    // The client only sends inputs after it has loaded the initial state, so
+                    // the final acknowledgement must have been lost
+                    self.phase = Phase::Running;
+                }
+
+                self.remote_received_count = self.remote_received_count.max(received_count);
+                if frame >= self.remote_frame {
+                    self.remote_frame = frame;
+                    self.remote_advantage = advantage;
+                }
+
+                for (input_frame, inputs) in (start_frame..).zip(inputs) {
+                    if input_frame != self.remote_inputs.end() {
+                        continue;
+                    }
+
+                    if input_frame < self.frame
+                        && self.used_remote_inputs.get(input_frame) != Some(&inputs)
+                    {
+                        self.rollback_frame =
+                            Some(self.rollback_frame.map_or(input_frame, |f| f.min(input_frame)));
+                    }
+
+                    self.remote_inputs.push(inputs);
+                }
+            }
+            Message::Checksum { frame, hash } => {
+                push_checksum(&mut self.remote_checksums, frame, hash);
+            }
+            Message::Reject { reason } => {
+                self.disconnect(format!("Host rejected connection: {reason}"));
+            }
+        }
+    }
+
+    fn on_connected(&mut self, addr: SocketAddr) {
+        log::info!("Netplay peer connected at {addr}");
+
+        self.phase = if self.is_host {
+            Phase::SendingState { chunks: vec![], acked: 0, last_send_time: None }
+        } else {
+            Phase::ReceivingState { chunks: vec![], count: None }
+        };
+        self.events.push(NetplayEvent::Connected { peer: addr });
+    }
+
+    fn send_state_chunks(&mut self, emulator: &Emulator) {
+        let Phase::SendingState { chunks, acked, last_send_time } = &mut self.phase else { return };
+
+        if chunks.is_empty() {
+            // The caller has reset the emulator by the time this is first called after connecting
+            let compressed =
+                state::save_to_bytes(emulator).map_err(|err| err.to_string()).and_then(|bytes| {
+                    zstd::stream::encode_all(bytes.as_slice(), 0).map_err(|err| err.to_string())
+                });
+            match compressed {
+                Ok(bytes) => {
+                    log::info!("Sending {} bytes of initial state to netplay peer", bytes.len());
+                    *chunks = bytes.chunks(STATE_CHUNK_LEN).map(<[u8]>::to_vec).collect();
+                }
+                Err(err) => {
+                    self.disconnect(format!("Error serializing initial state: {err}"));
+                    return;
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if last_send_time.is_some_and(|time| now.duration_since(time) < STATE_RESEND_INTERVAL) {
+            return;
+        }
+        *last_send_time = Some(now);
+
+        let count = chunks.len() as u32;
+        let messages: Vec<_> = chunks
+            .iter()
+            .enumerate()
+            .skip(*acked)
+            .take(STATE_CHUNK_WINDOW)
+            .map(|(index, data)| Message::StateChunk {
+                index: index as u32,
+                count,
+                data: data.clone(),
+            })
+            .collect();
+        for message in &messages {
+            self.send(message);
+        }
+    }
+
+    fn receive_state_chunk(
+        &mut self,
+        index: usize,
+        count: usize,
+        data: Vec<u8>,
+        emulator: &mut Emulator,
+        config: &Emulator::Config,
+    ) {
+        let Phase::ReceivingState { chunks, count: expected_count } = &mut self.phase else {
+            if !self.is_host && matches!(self.phase, Phase::Running) {
+                // The host did not receive the final acknowledgement
+                self.send(&Message::StateAck { received: count as u32 });
+            }
+            return;
+        };
+
+        *expected_count = Some(count);
+        if index == chunks.len() {
+            chunks.push(data);
+        }
+
+        let received = chunks.len();
+        if received < count {
+            self.send(&Message::StateAck { received: received as u32 });
+            return;
+        }
+
+        let compressed = chunks.concat();
+        let result = zstd::stream::decode_all(compressed.as_slice())
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                state::load_from_bytes(emulator, config, &bytes).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            self.disconnect(format!("Error loading initial state from host: {err}"));
+            return;
+        }
+
+        log::info!("Loaded initial state from netplay host");
+        self.phase = Phase::Running;
+        self.send(&Message::StateAck { received: received as u32 });
+    }
+
+    fn hello_message(&self) -> Message<Emulator::Inputs> {
+        Message::Hello {
+            save_state_version: self.save_state_version.clone(),
+            audio_output_frequency: self.audio_output_frequency,
+            rom_hash: self.rom_hash,
+        }
+    }
+
+    fn send_hello_if_needed(&mut self) {
+        if self.is_host {
+            return;
+        }
+
+        let now = Instant::now();
+        if self.last_hello_time.is_none_or(|time| now.duration_since(time) >= HELLO_INTERVAL) {
+            self.send(&self.hello_message());
+            self.last_hello_time = Some(now);
+        }
+    }
+
+    fn send_inputs(&self) {
+        let start_frame = self.remote_received_count.max(self.local_inputs.base);
+        let inputs: Vec<_> = (start_frame..self.local_inputs.end())
+            .take(MAX_INPUTS_PER_PACKET)
+            .filter_map(|frame| self.local_inputs.get(frame).cloned())
+            .collect();
+
+        self.send(&Message::Inputs {
+            start_frame,
+            inputs,
+            received_count: self.remote_inputs.end(),
+            frame: self.frame,
+            advantage: self.frame as i64 - self.remote_frame as i64,
+        });
+    }
+
+    fn send(&self, message: &Message<Emulator::Inputs>) {
+        let Some(peer) = self.peer else { return };
+
+        let bytes = match bincode::encode_to_vec(message, bincode_config!()) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                log::error!("Error serializing netplay message: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = self.socket.send_to(&bytes, peer) {
+            log::debug!("Error sending netplay packet to {peer}: {err}");
+        }
+    }
+}
+
+fn push_checksum(checksums: &mut VecDeque<(u64, u64)>, frame: u64, hash: u64) {
+    const MAX_CHECKSUMS: usize = 16;
+
+    checksums.push_back((frame, hash));
+    while checksums.len() > MAX_CHECKSUMS {
+        checksums.pop_front();
+    }
+}
+
+/// Hash the ROM file at the given path, or at least the start of it, so that peers can check that
+/// they are running the same game.
+pub fn rom_hash(path: &Path) -> u64 {
+    let mut bytes = Vec::new();
+    let result =
+        File::open(path).and_then(|file| file.take(MAX_ROM_HASH_LEN).read_to_end(&mut bytes));
+    if let Err(err) = result {
+        log::error!("Error reading ROM file '{}' for netplay: {err}", path.display());
+    }
+
+    fnv1a_hash(&bytes)
+}
+
+pub(crate) fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(PRIME))
+}
+
+// Used when re-simulating frames after a rollback; the error types match the real renderer and
+// audio output so that the emulator's error type is unchanged
+struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    type Err = <ThreadedRenderer as Renderer>::Err;
+
+    fn render_frame(
+        &mut self,
+        _frame_buffer: &[Color],
+        _frame_size: FrameSize,
+        _target_fps: f64,
+        _options: RenderFrameOptions,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+struct NullAudioOutput;
+
+impl AudioOutput for NullAudioOutput {
+    type Err = <SdlAudioOutput as AudioOutput>::Err;
+
+    fn push_sample(&mut self, _sample_l: f64, _sample_r: f64) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+// Save files are never written while re-simulating frames; every re-simulated frame already ran
+// once with its save writes persisted, and re-simulating up to 8 frames per rollback would
+// otherwise rewrite the same files many times per second. Loads still read from disk
+struct ResimulationSaveWriter<'a>(&'a mut FsSaveWriter);
+
+impl SaveWriter for ResimulationSaveWriter<'_> {
+    type Err = <FsSaveWriter as SaveWriter>::Err;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        self.0.load_bytes(extension)
+    }
+
+    fn persist_bytes(&mut self, _extension: &str, _bytes: &[u8]) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        self.0.load_serialized(extension)
+    }
+
+    fn persist_serialized<E: Encode>(
+        &mut self,
+        _extension: &str,
+        _data: E,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jgenesis_common::cheats::{CheatSet, CheatSystem};
+    use jgenesis_common::frontend::{EmulatorConfigTrait, InputPoller, MappableInputs, TickResult};
+    use jgenesis_common::input::Player;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::{env, fs, thread};
+
+    const ROM_HASH: u64 = 0x1234_5678;
+
+    // Total ticks across all test emulators, used to check that frames were re-simulated
+    static TICKS: AtomicU64 = AtomicU64::new(0);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Encode, Decode)]
+    struct TestInputs {
+        p1: u8,
+        p2: u8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct TestButton;
+
+    impl MappableInputs<TestButton> for TestInputs {
+        fn set_field(&mut self, _button: TestButton, _player: Player, _pressed: bool) {}
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestConfig;
+
+    impl EmulatorConfigTrait for TestConfig {}
+
+    #[derive(Debug, Error)]
+    #[error("{0}")]
+    struct TestError<SErr>(SErr);
+
+    // Hashes every frame's inputs, so emulators end in the same state only if they ran the same
+    // inputs on every frame
+    #[derive(Debug, Clone, PartialEq, Eq, Default, Encode, Decode)]
+    struct TestEmulator {
+        frame: u64,
+        hash: u64,
+    }
+
+    impl TestEmulator {
+        fn run_frame(&mut self, inputs: TestInputs) {
+            self.frame += 1;
+            self.hash =
+                fnv1a_hash(&[self.hash.to_le_bytes().as_slice(), &[inputs.p1, inputs.p2]].concat());
+        }
+    }
+
+    impl PartialClone for TestEmulator {
+        fn partial_clone(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    impl EmulatorTrait for TestEmulator {
+        type Button = TestButton;
+        type Inputs = TestInputs;
+        type Config = TestConfig;
+
+        const CHEAT_SYSTEM: CheatSystem = CheatSystem::Nes;
+
+        type Err<
+            RErr: std::fmt::Debug + std::fmt::Display + Send + Sync + 'static,
+            AErr: std::fmt::Debug + std::fmt::Display + Send + Sync + 'static,
+            SErr: std::fmt::Debug + std::fmt::Display + Send + Sync + 'static,
+        > = TestError<SErr>;
+
+        fn tick<R, A, I, S>(
+            &mut self,
+            _renderer: &mut R,
+            _audio_output: &mut A,
+            input_poller: &mut I,
+            save_writer: &mut S,
+        ) -> TickResult<Self::Err<R::Err, A::Err, S::Err>>
+        where
+            R: Renderer,
+            A: AudioOutput,
+            I: InputPoller<Self::Inputs>,
+            S: SaveWriter,
+        {
+            TICKS.fetch_add(1, Ordering::Relaxed);
+
+            self.run_frame(*input_poller.poll());
+            save_writer.persist_bytes("sav", &self.hash.to_le_bytes()).map_err(TestError)?;
+
+            Ok(TickEffect::FrameRendered)
+        }
+
+        fn force_render<R>(&mut self, _renderer: &mut R) -> Result<(), R::Err>
+        where
+            R: Renderer,
+        {
+            Ok(())
+        }
+
+        fn reload_config(&mut self, _config: &Self::Config) {}
+
+        fn take_rom_from(&mut self, _other: &mut Self) {}
+
+        fn set_cheats(&mut self, _cheats: CheatSet) {}
+
+        fn soft_reset(&mut self) {}
+
+        fn hard_reset<S: SaveWriter>(&mut self, _save_writer: &mut S) {}
+
+        fn target_fps(&self) -> f64 {
+            60.0
+        }
+
+        fn update_audio_output_frequency(&mut self, _output_frequency: u64) {}
+    }
+
+    fn merge_inputs(p1: &TestInputs, p2: &TestInputs) -> TestInputs {
+        TestInputs { p1: p1.p1, p2: p2.p1 }
+    }
+
+    struct TestPeer {
+        session: NetplaySession<TestEmulator>,
+        emulator: TestEmulator,
+        input_poller: ThreadedInputPoller<TestInputs>,
+        save_writer: FsSaveWriter,
+        events: Vec<NetplayEvent>,
+    }
+
+    impl TestPeer {
+        fn new(remote_addr: Option<SocketAddr>, rom_hash: u64, save_dir: &Path) -> Self {
+            let config = NetplayConfig { local_port: 0, remote_addr, input_delay: 0 };
+            let session =
+                NetplaySession::new(&config, merge_inputs, "test", 48000, rom_hash).unwrap();
+
+            Self {
+                session,
+                emulator: TestEmulator::default(),
+                input_poller: ThreadedInputPoller::new(TestInputs::default()),
+                save_writer: FsSaveWriter::new(save_dir.join("game")),
+                events: vec![],
+            }
+        }
+
+        fn addr(&self) -> SocketAddr {
+            let port = self.session.socket.local_addr().unwrap().port();
+            (Ipv4Addr::LOCALHOST, port).into()
+        }
+
+        // Returns whether a frame ran
+        fn step(&mut self, local_input: u8) -> bool {
+            self.input_poller.handle().update_inputs(&TestInputs { p1: local_input, p2: 0 });
+
+            let run_frame = self
+                .session
+                .prepare_frame(
+                    &mut self.emulator,
+                    &TestConfig,
+                    &mut self.input_poller,
+                    &mut self.save_writer,
+                )
+                .unwrap();
+            self.events.extend(self.session.take_events());
+
+            if run_frame {
+                // Only rollbacks should be able to write save files
+                self.emulator
+                    .tick(
+                        &mut NullRenderer,
+                        &mut NullAudioOutput,
+                        &mut self.input_poller,
+                        &mut ResimulationSaveWriter(&mut self.save_writer),
+                    )
+                    .unwrap();
+            }
+
+            run_frame
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jgenesis-netplay-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn host_input(frame: u64) -> u8 {
+        if frame < 90 { (frame * 3) as u8 } else { 0 }
+    }
+
+    fn client_input(frame: u64) -> u8 {
+        if frame < 90 { (frame * 7 + 1) as u8 } else { 0 }
+    }
+
+    #[test]
+    fn loopback_session_converges() {
+        const FRAMES: u64 = 150;
+
+        let dir = test_dir("loopback");
+        let mut host = TestPeer::new(None, ROM_HASH, &dir);
+        let mut client = TestPeer::new(Some(host.addr()), ROM_HASH, &dir);
+
+        let initial_ticks = TICKS.load(Ordering::Relaxed);
+        for _ in 0..10_000 {
+            if host.emulator.frame == FRAMES && client.emulator.frame == FRAMES {
+                break;
+            }
+
+            if host.emulator.frame < FRAMES {
+                host.step(host_input(host.session.frame));
+            }
+            if client.emulator.frame < FRAMES {
+                client.step(client_input(client.session.frame));
+            }
+
+            thread::sleep(Duration::from_micros(200));
+        }
+
+        assert!(matches!(host.events.first(), Some(NetplayEvent::Connected { .. })));
+        assert!(matches!(client.events.first(), Some(NetplayEvent::Connected { .. })));
+        assert!(
+            host.events
+                .iter()
+                .chain(&client.events)
+                .all(|event| matches!(event, NetplayEvent::Connected { .. })),
+            "unexpected events: {:?} {:?}",
+            host.events,
+            client.events
+        );
+
+        let mut expected = TestEmulator::default();
+        for frame in 0..FRAMES {
+            expected.run_frame(TestInputs { p1: host_input(frame), p2: client_input(frame) });
+        }
+        assert_eq!(host.emulator, expected);
+        assert_eq!(client.emulator, expected);
+
+        // Remote inputs change every frame, so predictions must have been rolled back
+        assert!(TICKS.load(Ordering::Relaxed) - initial_ticks > 2 * FRAMES);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rom_mismatch_ends_session() {
+        let dir = test_dir("mismatch");
+        let mut host = TestPeer::new(None, ROM_HASH, &dir);
+        let mut client = TestPeer::new(Some(host.addr()), ROM_HASH + 1, &dir);
+
+        for _ in 0..100 {
+            assert!(!client.step(0));
+            assert!(!host.step(0));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        for peer in [&host, &client] {
+            assert_eq!(peer.events.len(), 1, "{:?}", peer.events);
+            assert!(matches!(peer.events[0], NetplayEvent::Disconnected { .. }));
+            assert!(matches!(peer.session.phase, Phase::Disconnected));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timeout_is_reported_once() {
+        let dir = test_dir("timeout");
+        let mut peer = TestPeer::new(None, ROM_HASH, &dir);
+        peer.session.phase = Phase::Running;
+        peer.session.last_receive_time = Instant::now() - DISCONNECT_TIMEOUT;
+
+        for _ in 0..3 {
+            assert!(!peer.step(0));
+        }
+        assert_eq!(peer.events.len(), 1);
+        assert!(matches!(peer.events[0], NetplayEvent::Disconnected { .. }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn frame_buffer_set_and_discard() {
+        let mut buffer = FrameBuffer::new();
+        for value in 0..5 {
+            buffer.push(value);
+        }
+
+        buffer.set(3, 10);
+        assert_eq!(buffer.end(), 4);
+        assert_eq!(buffer.get(3), Some(&10));
+
+        buffer.discard_before(2);
+        assert_eq!(buffer.get(1), None);
+        assert_eq!(buffer.get(2), Some(&2));
+        assert_eq!(buffer.end(), 4);
+
+        buffer.set(4, 11);
+        assert_eq!(buffer.last(), Some(&11));
+    }
+}
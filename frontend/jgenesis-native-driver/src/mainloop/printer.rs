@@ -0,0 +1,56 @@
+//! Output for pages printed by an emulated printer peripheral (currently only the Game Boy Printer).
+//!
+//! Each printed page is written as a grayscale PNG next to the save file, named after the save
+//! file with an increasing number appended, e.g. `Pokemon Yellow-print-001.png`.
+
+use gb_core::printer::PrintedImage;
+use image::{GrayImage, ImageFormat};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Take the oldest page that the emulated printer has finished printing, if any. Called once per
+/// runner loop iteration.
+pub type TakePrintedImageFn<Emulator> = fn(&mut Emulator) -> Option<PrintedImage>;
+
+#[derive(Debug, Error)]
+pub enum PrinterError {
+    #[error("Printed image has invalid dimensions {width}x{height} for {len} pixels")]
+    InvalidDimensions { width: u32, height: u32, len: usize },
+    #[error("Error writing printed image to '{path}': {source}")]
+    Write {
+        path: String,
+        #[source]
+        source: image::ImageError,
+    },
+}
+
+/// Write a printed page to the first unused numbered PNG path based on `save_path`. Returns the
+/// path that was written.
+///
+/// # Errors
+///
+/// Returns an error if the image is malformed or the PNG file cannot be written.
+pub fn write_printed_image(save_path: &Path, image: PrintedImage) -> Result<PathBuf, PrinterError> {
+    let PrintedImage { width, height, pixels } = image;
+    let len = pixels.len();
+    let image = GrayImage::from_raw(width, height, pixels)
+        .ok_or(PrinterError::InvalidDimensions { width, height, len })?;
+
+    let path = next_image_path(save_path);
+    image
+        .save_with_format(&path, ImageFormat::Png)
+        .map_err(|source| PrinterError::Write { path: path.display().to_string(), source })?;
+
+    log::info!("Wrote printed image to '{}'", path.display());
+
+    Ok(path)
+}
+
+fn next_image_path(save_path: &Path) -> PathBuf {
+    let file_stem = save_path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+
+    (1..)
+        .map(|i| save_path.with_file_name(format!("{file_stem}-print-{i:03}.png")))
+        .find(|path| !path.exists())
+        .expect("Infinite iterator should always find an unused path")
+}
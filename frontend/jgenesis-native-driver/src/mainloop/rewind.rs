@@ -1,42 +1,131 @@
-use jgenesis_common::frontend::{EmulatorTrait, PartialClone, Renderer};
+//! Rewind buffer that stores serialized emulator states within a fixed memory budget.
+//!
+//! States are grouped behind periodic keyframes. Each keyframe is stored as a compressed full state,
+//! and every other state in the group is stored as a compressed XOR delta against the group's
+//! keyframe. Most of the emulator state does not change between nearby frames, so the deltas are
+//! mostly zeroes and compress extremely well even with a fast compression level.
+
+use crate::mainloop::{bincode_config, state};
+use bincode::error::{DecodeError, EncodeError};
+use jgenesis_common::frontend::{EmulatorTrait, Renderer};
 use std::collections::VecDeque;
+use std::io;
 use std::time::{Duration, Instant};
+use thiserror::Error;
 
-const FRAME_DIVIDER: u64 = 10;
+const KEYFRAME_INTERVAL: usize = 60;
 const REWIND_SPEED: u64 = 2;
+const COMPRESSION_LEVEL: i32 = 1;
+
+#[derive(Debug, Error)]
+enum RewindError {
+    #[error("Error serializing state: {0}")]
+    Encode(#[from] EncodeError),
+    #[error("Error deserializing state: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("Error compressing or decompressing state: {0}")]
+    Compression(#[from] io::Error),
+}
+
+struct KeyframeGroup {
+    keyframe: Vec<u8>,
+    deltas: Vec<Vec<u8>>,
+}
+
+impl KeyframeGroup {
+    fn stored_bytes(&self) -> usize {
+        self.keyframe.len() + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+}
 
-pub struct Rewinder<Emulator> {
-    previous_states: VecDeque<Emulator>,
-    buffer_len: usize,
+pub struct Rewinder {
+    groups: VecDeque<KeyframeGroup>,
+    // Uncompressed copy of the newest group's keyframe; empty if there are no groups
+    keyframe: Vec<u8>,
+    // Total compressed size of all groups, not including the uncompressed keyframe
+    stored_bytes: usize,
+    buffer_size: usize,
+    frame_interval: u64,
     frame_count: u64,
     last_rewind_time: Option<Instant>,
 }
 
-impl<Emulator: PartialClone> Rewinder<Emulator> {
-    pub fn new(buffer_duration: Duration) -> Self {
-        let buffer_len = duration_to_buffer_len(buffer_duration);
+impl Rewinder {
+    pub fn new(buffer_size: usize, frame_interval: u64) -> Self {
         Self {
-            previous_states: VecDeque::with_capacity(buffer_len + 1),
-            buffer_len,
+            groups: VecDeque::new(),
+            keyframe: Vec::new(),
+            stored_bytes: 0,
+            buffer_size,
+            frame_interval: frame_interval.max(1),
             frame_count: 0,
             last_rewind_time: None,
         }
     }
 
-    pub fn record_frame(&mut self, emulator: &Emulator) {
-        if self.buffer_len == 0 {
+    pub fn record_frame<Emulator: EmulatorTrait>(&mut self, emulator: &Emulator) {
+        if self.buffer_size == 0 {
             return;
         }
 
         self.frame_count += 1;
 
-        if self.frame_count.is_multiple_of(FRAME_DIVIDER) {
-            self.previous_states.push_back(emulator.partial_clone());
+        if self.frame_count.is_multiple_of(self.frame_interval)
+            && let Err(err) = self.push_state(emulator)
+        {
+            log::error!("Error recording rewind state, clearing rewind buffer: {err}");
+            self.clear();
+        }
+    }
+
+    fn push_state<Emulator: EmulatorTrait>(
+        &mut self,
+        emulator: &Emulator,
+    ) -> Result<(), RewindError> {
+        let state = bincode::encode_to_vec(emulator, bincode_config!())?;
+        self.push_state_bytes(state)?;
 
-            while self.previous_states.len() > self.buffer_len {
-                self.previous_states.pop_front();
+        Ok(())
+    }
+
+    fn push_state_bytes(&mut self, mut state: Vec<u8>) -> Result<(), RewindError> {
+        match self.groups.back_mut() {
+            Some(group) if group.deltas.len() + 1 < KEYFRAME_INTERVAL => {
+                xor_common_prefix(&mut state, &self.keyframe);
+                let delta = compress(&state)?;
+
+                self.stored_bytes += delta.len();
+                group.deltas.push(delta);
+            }
+            _ => {
+                let keyframe = compress(&state)?;
+
+                self.stored_bytes += keyframe.len();
+                self.groups.push_back(KeyframeGroup { keyframe, deltas: vec![] });
+                self.keyframe = state;
             }
         }
+
+        self.evict_old_states();
+
+        Ok(())
+    }
+
+    fn evict_old_states(&mut self) {
+        while self.stored_bytes + self.keyframe.len() > self.buffer_size {
+            let Some(group) = self.groups.pop_front() else { break };
+            self.stored_bytes -= group.stored_bytes();
+
+            if self.groups.is_empty() {
+                self.keyframe = Vec::new();
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.groups.clear();
+        self.keyframe = Vec::new();
+        self.stored_bytes = 0;
     }
 
     pub fn start_rewinding(&mut self) {
@@ -53,7 +142,7 @@ impl<Emulator: PartialClone> Rewinder<Emulator> {
         self.last_rewind_time.is_some()
     }
 
-    pub fn tick<R>(
+    pub fn tick<Emulator, R>(
         &mut self,
         emulator: &mut Emulator,
         renderer: &mut R,
@@ -65,45 +154,214 @@ impl<Emulator: PartialClone> Rewinder<Emulator> {
     {
         let Some(last_rewind_time) = self.last_rewind_time else { return Ok(()) };
 
-        let rewind_interval_secs = 1.0 / 60.0 * (FRAME_DIVIDER as f64) / (REWIND_SPEED as f64);
+        let rewind_interval_secs =
+            1.0 / 60.0 * (self.frame_interval as f64) / (REWIND_SPEED as f64);
 
+        // Rewind through multiple states at once if the runner thread fell behind, e.g. because
+        // rendering is blocked on VSync
         let now = Instant::now();
-        if now.duration_since(last_rewind_time) >= Duration::from_secs_f64(rewind_interval_secs) {
-            let Some(mut clone) = self.previous_states.pop_back() else { return Ok(()) };
-            clone.take_rom_from(emulator);
-            *emulator = clone;
+        let elapsed_secs = now.duration_since(last_rewind_time).as_secs_f64();
+        let state_count = (elapsed_secs / rewind_interval_secs) as usize;
+        if state_count == 0 {
+            return Ok(());
+        }
 
-            emulator.reload_config(config);
-            emulator.force_render(renderer)?;
+        let loaded_emulator = match self.pop_states(state_count) {
+            Ok(Some(loaded_emulator)) => loaded_emulator,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                log::error!("Error loading rewind state, clearing rewind buffer: {err}");
+                self.clear();
+                return Ok(());
+            }
+        };
 
-            self.last_rewind_time = Some(now);
-        }
+        state::replace_emulator(emulator, loaded_emulator, config);
+        emulator.force_render(renderer)?;
+
+        self.last_rewind_time = Some(
+            last_rewind_time + Duration::from_secs_f64(state_count as f64 * rewind_interval_secs),
+        );
 
         Ok(())
     }
 
-    pub fn set_buffer_duration(&mut self, duration: Duration) {
-        self.set_buffer_len(duration_to_buffer_len(duration));
+    /// Remove the newest `count` states from the buffer and return the last one removed.
+    fn pop_states<Emulator: EmulatorTrait>(
+        &mut self,
+        count: usize,
+    ) -> Result<Option<Emulator>, RewindError> {
+        let Some(state) = self.pop_state_bytes(count)? else { return Ok(None) };
+
+        let (loaded_emulator, _) = bincode::decode_from_slice(&state, bincode_config!())?;
+        Ok(Some(loaded_emulator))
     }
 
-    fn set_buffer_len(&mut self, buffer_len: usize) {
-        self.buffer_len = buffer_len;
+    fn pop_state_bytes(&mut self, count: usize) -> Result<Option<Vec<u8>>, RewindError> {
+        for _ in 1..count {
+            // Always stop at the oldest state rather than emptying the buffer
+            if self.groups.len() == 1 && self.groups[0].deltas.is_empty() {
+                break;
+            }
 
-        // If size increased, immediately resize deque to avoid incremental allocations later
-        if buffer_len + 1 > self.previous_states.capacity() {
-            self.previous_states.reserve(buffer_len + 1 - self.previous_states.capacity());
+            self.discard_newest_state()?;
         }
 
-        // If size decreased, immediately drop unused states
-        while self.previous_states.len() > buffer_len {
-            self.previous_states.pop_front();
+        let Some(state) = self.newest_state()? else { return Ok(None) };
+        self.discard_newest_state()?;
+
+        Ok(Some(state))
+    }
+
+    fn newest_state(&self) -> Result<Option<Vec<u8>>, RewindError> {
+        let Some(group) = self.groups.back() else { return Ok(None) };
+
+        let Some(delta) = group.deltas.last() else { return Ok(Some(self.keyframe.clone())) };
+
+        let mut state = decompress(delta)?;
+        xor_common_prefix(&mut state, &self.keyframe);
+
+        Ok(Some(state))
+    }
+
+    fn discard_newest_state(&mut self) -> Result<(), RewindError> {
+        let Some(group) = self.groups.back_mut() else { return Ok(()) };
+
+        if let Some(delta) = group.deltas.pop() {
+            self.stored_bytes -= delta.len();
+            return Ok(());
         }
+
+        // Group is now empty; drop it and decompress the previous group's keyframe
+        self.stored_bytes -= group.keyframe.len();
+        self.groups.pop_back();
+
+        self.keyframe = match self.groups.back() {
+            Some(group) => decompress(&group.keyframe)?,
+            None => Vec::new(),
+        };
+
+        Ok(())
+    }
+
+    pub fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+
+        // If size decreased, immediately drop states that no longer fit
+        self.evict_old_states();
+    }
+
+    pub fn set_frame_interval(&mut self, frame_interval: u64) {
+        let frame_interval = frame_interval.max(1);
+        if frame_interval != self.frame_interval {
+            // Existing states would rewind at the wrong speed with the new interval
+            self.frame_interval = frame_interval;
+            self.clear();
+        }
+    }
+}
+
+fn xor_common_prefix(state: &mut [u8], keyframe: &[u8]) {
+    for (byte, keyframe_byte) in state.iter_mut().zip(keyframe) {
+        *byte ^= keyframe_byte;
     }
 }
 
-fn duration_to_buffer_len(duration: Duration) -> usize {
-    // Not really a better place for this, and this should get optimized out anyway
-    assert_eq!(FRAME_DIVIDER % REWIND_SPEED, 0);
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::stream::encode_all(bytes, COMPRESSION_LEVEL)
+}
+
+fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::stream::decode_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic pseudo-random state that changes slightly from frame to frame
+    fn test_state(frame: usize, len: usize) -> Vec<u8> {
+        let mut state: Vec<u8> = (0..len).map(|i| (i * 31 + i / 7) as u8).collect();
+        for i in 0..8 {
+            let index = (frame * 97 + i * 13) % len;
+            state[index] = state[index].wrapping_add(frame as u8);
+        }
+        state.extend_from_slice(&(frame as u32).to_le_bytes());
+        state
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let mut rewinder = Rewinder::new(usize::MAX, 1);
+
+        let frames = 2 * KEYFRAME_INTERVAL + 10;
+        for frame in 0..frames {
+            rewinder.push_state_bytes(test_state(frame, 4096)).unwrap();
+        }
+        assert_eq!(rewinder.groups.len(), 3);
+
+        for frame in (0..frames).rev() {
+            assert_eq!(rewinder.pop_state_bytes(1).unwrap(), Some(test_state(frame, 4096)));
+        }
+        assert_eq!(rewinder.pop_state_bytes(1).unwrap(), None);
+        assert_eq!(rewinder.stored_bytes, 0);
+    }
+
+    #[test]
+    fn delta_round_trip_different_lengths() {
+        let mut rewinder = Rewinder::new(usize::MAX, 1);
+
+        // States can change size, e.g. if a vector in the emulator state grows
+        let states = [test_state(0, 1000), test_state(1, 1200), test_state(2, 800)];
+        for state in &states {
+            rewinder.push_state_bytes(state.clone()).unwrap();
+        }
+
+        for state in states.iter().rev() {
+            assert_eq!(rewinder.pop_state_bytes(1).unwrap().as_ref(), Some(state));
+        }
+    }
+
+    #[test]
+    fn pop_multiple_states() {
+        let mut rewinder = Rewinder::new(usize::MAX, 1);
+
+        for frame in 0..KEYFRAME_INTERVAL + 5 {
+            rewinder.push_state_bytes(test_state(frame, 4096)).unwrap();
+        }
+
+        // Crosses a keyframe boundary
+        let frame = KEYFRAME_INTERVAL + 4 - 9;
+        assert_eq!(rewinder.pop_state_bytes(10).unwrap(), Some(test_state(frame, 4096)));
+
+        // Stops at the oldest state instead of emptying the buffer
+        assert_eq!(rewinder.pop_state_bytes(1000).unwrap(), Some(test_state(0, 4096)));
+        assert_eq!(rewinder.pop_state_bytes(1).unwrap(), None);
+    }
+
+    #[test]
+    fn evicts_oldest_groups_within_budget() {
+        const BUFFER_SIZE: usize = 64 * 1024;
+
+        let mut rewinder = Rewinder::new(BUFFER_SIZE, 1);
 
-    (duration.as_secs() * 60 / (FRAME_DIVIDER / REWIND_SPEED)) as usize
+        let frames = 20 * KEYFRAME_INTERVAL;
+        for frame in 0..frames {
+            rewinder.push_state_bytes(test_state(frame, 16 * 1024)).unwrap();
+            assert!(rewinder.stored_bytes + rewinder.keyframe.len() <= BUFFER_SIZE);
+        }
+
+        let expected_stored_bytes: usize =
+            rewinder.groups.iter().map(KeyframeGroup::stored_bytes).sum();
+        assert_eq!(rewinder.stored_bytes, expected_stored_bytes);
+
+        // Oldest groups were evicted, and the newest states are still intact
+        assert!(rewinder.groups.len() < frames / KEYFRAME_INTERVAL);
+        assert_eq!(rewinder.pop_state_bytes(1).unwrap(), Some(test_state(frames - 1, 16 * 1024)));
+
+        // Shrinking the buffer evicts immediately
+        rewinder.set_buffer_size(1);
+        assert!(rewinder.groups.is_empty());
+        assert_eq!(rewinder.stored_bytes, 0);
+    }
 }
@@ -1,16 +1,31 @@
 use crate::config::CommonConfig;
 use crate::mainloop::audio::{SdlAudioOutput, SdlAudioOutputHandle};
+use crate::mainloop::camera::{ImageCameraSensor, UpdateCameraFn};
 use crate::mainloop::input::{ThreadedInputPoller, ThreadedInputPollerHandle};
+use crate::mainloop::link::{
+    LinkCableConfig, LinkCableError, LinkCableEvent, LinkCableFn, LinkCableSocket,
+};
+use crate::mainloop::movie::{
+    ExternalMovieFormat, FrameCommand, Movie, MovieStart, MovieStartType, StateLoadEffect,
+};
+use crate::mainloop::netplay::{
+    self, MergeNetplayInputsFn, NetplayConfig, NetplayError, NetplayEvent, NetplaySession,
+};
+use crate::mainloop::printer::TakePrintedImageFn;
 use crate::mainloop::render::{RecvFrameError, ThreadedRenderer, ThreadedRendererHandle};
 use crate::mainloop::rewind::Rewinder;
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
+use crate::mainloop::save::{
+    CreateSaveWriter, DeterminedPaths, EmptySaveWriter, FsSaveWriter, SaveWriteError,
+};
 use crate::mainloop::state::SaveStatePaths;
-use crate::mainloop::{CreateEmulatorFn, CreatedEmulator, save, state};
+use crate::mainloop::{CreateEmulatorFn, CreatedEmulator, movie, printer, save, state};
 use crate::{NativeEmulatorError, NativeEmulatorResult, SaveStateMetadata};
+use jgenesis_common::cheats::CheatSet;
 use jgenesis_common::frontend::{AudioOutput, EmulatorTrait, Renderer, SaveWriter, TickEffect};
 use jgenesis_debugger_frontend::DebuggerRunnerProcess;
 use jgenesis_native_config::common::WindowSize;
 use std::error::Error;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
@@ -25,17 +40,28 @@ pub type ChangeDiscFn<Emulator> =
 
 pub type RemoveDiscFn<Emulator> = fn(&mut Emulator);
 
+pub type ChangeDiskSideFn<Emulator> = fn(&mut Emulator);
+
 pub enum RunnerCommand<Emulator: EmulatorTrait> {
     Terminate,
     SoftReset,
     HardReset,
     ChangeDisc(PathBuf),
     RemoveDisc,
+    ChangeDiskSide,
     StepFrame,
     FastForward { enabled: bool },
     Rewind { enabled: bool },
     SaveState { slot: usize },
     LoadState { slot: usize },
+    RecordMovie { path: PathBuf, start: MovieStartType },
+    PlayMovie { path: PathBuf, read_only: bool },
+    StopMovie,
+    ToggleMovieReadOnly,
+    StartNetplay(NetplayConfig),
+    StopNetplay,
+    StartLinkCable(LinkCableConfig),
+    StopLinkCable,
     ReloadConfig(Box<(CommonConfig, Emulator::Config)>),
     StartDebugger(Box<NativeDebuggerRunnerProcess<Emulator>>),
     StopDebugger,
@@ -49,6 +75,23 @@ pub enum RunnerCommandResponse {
     LoadStateFailed { slot: usize, err: NativeEmulatorError },
     ChangeDiscSucceeded { window_title: String },
     ChangeDiscFailed(Box<dyn Error + Send + Sync + 'static>),
+    MovieRecordingStarted,
+    MoviePlaybackStarted { read_only: bool },
+    MoviePlaybackFinished,
+    MovieStopped,
+    MovieReadOnlyChanged { read_only: bool },
+    MovieFailed(NativeEmulatorError),
+    NetplayWaitingForPeer,
+    NetplayConnected { peer: SocketAddr },
+    NetplayDesync { frame: u64 },
+    NetplayStopped { reason: Option<String> },
+    NetplayFailed(NativeEmulatorError),
+    LinkCableWaitingForPeer,
+    LinkCableConnected { peer: SocketAddr },
+    LinkCableStopped { reason: Option<String> },
+    LinkCableFailed(NativeEmulatorError),
+    PrintedImageSaved { path: PathBuf },
+    PrintedImageFailed(NativeEmulatorError),
 }
 
 pub type NativeDebuggerRunnerProcess<Emulator> = dyn DebuggerRunnerProcess<
@@ -77,10 +120,20 @@ struct RunnerThreadState<Emulator: EmulatorTrait> {
     save_state_metadata: Arc<Mutex<SaveStateMetadata>>,
     paused: Arc<AtomicBool>,
     step_frame: bool,
-    rewinder: Rewinder<Emulator>,
+    rewinder: Rewinder,
     change_disc_fn: ChangeDiscFn<Emulator>,
     remove_disc_fn: RemoveDiscFn<Emulator>,
+    change_disk_side_fn: ChangeDiskSideFn<Emulator>,
     debugger_process: Option<Box<NativeDebuggerRunnerProcess<Emulator>>>,
+    movie: Option<Movie<Emulator::Inputs>>,
+    movie_format: Option<ExternalMovieFormat<Emulator::Inputs>>,
+    netplay: Option<NetplaySession<Emulator>>,
+    netplay_merge_fn: Option<MergeNetplayInputsFn<Emulator::Inputs>>,
+    link_cable: Option<LinkCableSocket>,
+    link_cable_fn: Option<LinkCableFn<Emulator>>,
+    take_printed_image_fn: Option<TakePrintedImageFn<Emulator>>,
+    camera_sensor: Option<ImageCameraSensor>,
+    update_camera_fn: Option<UpdateCameraFn<Emulator>>,
 }
 
 impl<Emulator: EmulatorTrait> RunnerThreadState<Emulator> {
@@ -121,18 +174,256 @@ impl<Emulator: EmulatorTrait> RunnerThreadState<Emulator> {
 
         self.update_save_paths()?;
 
-        self.rewinder.set_buffer_duration(Duration::from_secs(
-            self.common_config.rewind_buffer_length_seconds,
-        ));
+        self.rewinder.set_buffer_size(self.common_config.rewind_buffer_size_bytes());
+        self.rewinder.set_frame_interval(self.common_config.rewind_frame_interval);
+
+        self.apply_cheats();
+
+        Ok(())
+    }
+
+    fn apply_cheats(&mut self) {
+        let cheats = CheatSet::from_codes(Emulator::CHEAT_SYSTEM, &self.common_config.cheat_codes);
+        self.emulator.set_cheats(cheats);
+    }
+
+    fn hard_reset(&mut self) {
+        self.emulator.hard_reset(&mut self.save_writer);
+        // Hard reset recreates the emulator, which clears cheats
+        self.apply_cheats();
+    }
+
+    // Movies must replay identically regardless of what saves exist on disk, so power-on starts and
+    // hard resets within movies never load save files
+    fn movie_hard_reset(&mut self) {
+        self.emulator.hard_reset(&mut EmptySaveWriter);
+        self.apply_cheats();
+    }
+
+    // Latch the inputs for the next frame and apply any reset recorded in the movie
+    fn prepare_movie_frame(&mut self) {
+        let Some(movie) = &mut self.movie else { return };
+
+        let Some(frame) = movie.next_frame(self.input_poller.poll_live()) else {
+            log::info!("Movie playback finished");
+            self.movie = None;
+            self.input_poller.set_frame_inputs(None);
+            let _ = self.response_sender.send(RunnerCommandResponse::MoviePlaybackFinished);
+            return;
+        };
+
+        match frame.command {
+            FrameCommand::None => {}
+            FrameCommand::SoftReset => self.emulator.soft_reset(),
+            FrameCommand::HardReset => self.movie_hard_reset(),
+        }
+
+        self.input_poller.set_frame_inputs(Some(frame.inputs));
+    }
+
+    fn start_movie_recording(
+        &mut self,
+        path: PathBuf,
+        start_type: MovieStartType,
+    ) -> NativeEmulatorResult<()> {
+        self.stop_movie();
+
+        let format = movie::determine_format(&path, self.movie_format)?;
+        let start = match start_type {
+            MovieStartType::PowerOn => MovieStart::PowerOn,
+            MovieStartType::CurrentState => {
+                MovieStart::SaveState(state::save_to_bytes(&self.emulator)?)
+            }
+        };
+        let movie = Movie::new_recording(path, format, Emulator::save_state_version(), start)?;
+
+        if start_type == MovieStartType::PowerOn {
+            self.movie_hard_reset();
+        }
+
+        self.movie = Some(movie);
+
+        Ok(())
+    }
+
+    fn start_movie_playback(&mut self, path: PathBuf, read_only: bool) -> NativeEmulatorResult<()> {
+        self.stop_movie();
+
+        let format = movie::determine_format(&path, self.movie_format)?;
+        let movie = Movie::load(path, format, Emulator::save_state_version(), read_only)?;
+
+        match movie.start() {
+            MovieStart::PowerOn => self.movie_hard_reset(),
+            MovieStart::SaveState(bytes) => {
+                state::load_from_bytes(&mut self.emulator, &self.emulator_config, bytes)?;
+            }
+        }
+
+        self.movie = Some(movie);
+
+        Ok(())
+    }
+
+    fn stop_movie(&mut self) {
+        let Some(mut movie) = self.movie.take() else { return };
+
+        self.input_poller.set_frame_inputs(None);
+
+        if let Err(err) = movie.save() {
+            log::error!("Error saving movie: {err}");
+        }
+    }
+
+    fn start_netplay(&mut self, config: &NetplayConfig) -> NativeEmulatorResult<()> {
+        let merge_fn = self.netplay_merge_fn.ok_or(NetplayError::Unsupported)?;
+
+        self.stop_movie();
+        self.stop_netplay();
+
+        self.netplay = Some(NetplaySession::new(
+            config,
+            merge_fn,
+            Emulator::save_state_version(),
+            self.common_config.audio_output_frequency,
+            netplay::rom_hash(&self.rom_path),
+        )?);
+
+        Ok(())
+    }
+
+    fn stop_netplay(&mut self) {
+        if self.netplay.take().is_some() {
+            self.input_poller.set_frame_inputs(None);
+        }
+    }
+
+    // Returns whether the emulator should run a frame
+    fn prepare_netplay_frame(&mut self) -> Result<bool, RunTillNextErr<Emulator>> {
+        let Some(netplay) = &mut self.netplay else { return Ok(true) };
+
+        let run_frame = netplay.prepare_frame(
+            &mut self.emulator,
+            &self.emulator_config,
+            &mut self.input_poller,
+            &mut self.save_writer,
+        )?;
+
+        let is_host = netplay.is_host();
+        for event in netplay.take_events() {
+            let response = match event {
+                NetplayEvent::Connected { peer } => {
+                    // The host's state after the reset is sent to the client. Dynamic resampling
+                    // would make audio resampler state differ between peers, so use the
+                    // configured frequency
+                    if is_host {
+                        self.hard_reset();
+                        self.emulator.update_audio_output_frequency(
+                            self.common_config.audio_output_frequency,
+                        );
+                    }
+                    RunnerCommandResponse::NetplayConnected { peer }
+                }
+                NetplayEvent::Desync { frame } => RunnerCommandResponse::NetplayDesync { frame },
+                NetplayEvent::Disconnected { reason } => {
+                    log::error!("Netplay session ended: {reason}");
+                    self.stop_netplay();
+                    let _ = self
+                        .response_sender
+                        .send(RunnerCommandResponse::NetplayStopped { reason: Some(reason) });
+                    return Ok(false);
+                }
+            };
+
+            let _ = self.response_sender.send(response);
+        }
+
+        Ok(run_frame)
+    }
+
+    fn start_link_cable(&mut self, config: &LinkCableConfig) -> NativeEmulatorResult<()> {
+        if self.link_cable_fn.is_none() {
+            return Err(LinkCableError::Unsupported.into());
+        }
+
+        self.stop_link_cable();
+        self.link_cable = Some(LinkCableSocket::new(config)?);
 
         Ok(())
     }
+
+    fn stop_link_cable(&mut self) {
+        let (Some(mut socket), Some(link_cable_fn)) = (self.link_cable.take(), self.link_cable_fn)
+        else {
+            return;
+        };
+
+        // Give the emulator a chance to see that the cable was disconnected
+        socket.close();
+        link_cable_fn(&mut self.emulator, &mut socket);
+    }
+
+    fn update_link_cable(&mut self) {
+        let (Some(socket), Some(link_cable_fn)) = (&mut self.link_cable, self.link_cable_fn) else {
+            return;
+        };
+
+        socket.poll();
+        link_cable_fn(&mut self.emulator, socket);
+        socket.flush();
+
+        for event in socket.take_events() {
+            let response = match event {
+                LinkCableEvent::Connected { peer } => {
+                    RunnerCommandResponse::LinkCableConnected { peer }
+                }
+                LinkCableEvent::Disconnected { reason } => {
+                    log::error!("Link cable disconnected: {reason}");
+                    self.stop_link_cable();
+                    let _ = self
+                        .response_sender
+                        .send(RunnerCommandResponse::LinkCableStopped { reason: Some(reason) });
+                    return;
+                }
+            };
+
+            let _ = self.response_sender.send(response);
+        }
+    }
+
+    fn save_printed_images(&mut self) {
+        let Some(take_printed_image_fn) = self.take_printed_image_fn else { return };
+
+        while let Some(image) = take_printed_image_fn(&mut self.emulator) {
+            let response = match printer::write_printed_image(self.save_writer.base_path(), image) {
+                Ok(path) => RunnerCommandResponse::PrintedImageSaved { path },
+                Err(err) => RunnerCommandResponse::PrintedImageFailed(err.into()),
+            };
+            let _ = self.response_sender.send(response);
+        }
+    }
+
+    fn update_camera(&mut self) {
+        let (Some(camera_sensor), Some(update_camera_fn)) =
+            (&mut self.camera_sensor, self.update_camera_fn)
+        else {
+            return;
+        };
+
+        update_camera_fn(&mut self.emulator, camera_sensor);
+    }
 }
 
 pub struct RunnerSpawnArgs<'a, Emulator: EmulatorTrait> {
     pub create_emulator_fn: Box<CreateEmulatorFn<Emulator>>,
     pub change_disc_fn: ChangeDiscFn<Emulator>,
     pub remove_disc_fn: RemoveDiscFn<Emulator>,
+    pub change_disk_side_fn: ChangeDiskSideFn<Emulator>,
+    pub movie_format: Option<ExternalMovieFormat<Emulator::Inputs>>,
+    pub netplay_merge_fn: Option<MergeNetplayInputsFn<Emulator::Inputs>>,
+    pub link_cable_fn: Option<LinkCableFn<Emulator>>,
+    pub take_printed_image_fn: Option<TakePrintedImageFn<Emulator>>,
+    pub camera_sensor: Option<ImageCameraSensor>,
+    pub update_camera_fn: Option<UpdateCameraFn<Emulator>>,
     pub common_config: CommonConfig,
     pub emulator_config: Emulator::Config,
     pub rom_extension: String,
@@ -167,6 +458,13 @@ pub fn spawn<Emulator: EmulatorTrait>(
         create_emulator_fn,
         change_disc_fn,
         remove_disc_fn,
+        change_disk_side_fn,
+        movie_format,
+        netplay_merge_fn,
+        link_cable_fn,
+        take_printed_image_fn,
+        camera_sensor,
+        update_camera_fn,
         common_config,
         emulator_config,
         rom_extension,
@@ -200,42 +498,59 @@ pub fn spawn<Emulator: EmulatorTrait>(
 
         let common_config = common_config.clone();
 
-        thread::spawn(move || match create_emulator_fn(&mut save_writer) {
-            Ok(CreatedEmulator { emulator, window_title, default_window_size }) => {
-                init_sender.send(Ok((window_title, default_window_size))).unwrap();
-
-                let rewinder =
-                    Rewinder::new(Duration::from_secs(common_config.rewind_buffer_length_seconds));
-
-                let rom_path = common_config.rom_file_path.clone();
-                run_thread(RunnerThreadState {
-                    emulator,
-                    renderer,
-                    audio_output,
-                    input_poller,
-                    save_writer,
-                    common_config,
-                    emulator_config,
-                    command_receiver,
-                    response_sender,
-                    error_sender,
-                    rom_path,
-                    rom_extension,
-                    base_save_state_path: save_state_path,
-                    save_state_paths,
-                    save_state_metadata,
-                    paused,
-                    step_frame: false,
-                    rewinder,
-                    change_disc_fn,
-                    remove_disc_fn,
-                    debugger_process: None,
-                });
-
-                log::info!("Runner thread has terminated");
-            }
-            Err(err) => {
-                init_sender.send(Err(err)).unwrap();
+        thread::spawn(move || {
+            match create_emulator_fn(&mut CreateSaveWriter::Fs(&mut save_writer)) {
+                Ok(CreatedEmulator { emulator, window_title, default_window_size }) => {
+                    init_sender.send(Ok((window_title, default_window_size))).unwrap();
+
+                    let rewinder = Rewinder::new(
+                        common_config.rewind_buffer_size_bytes(),
+                        common_config.rewind_frame_interval,
+                    );
+
+                    let rom_path = common_config.rom_file_path.clone();
+                    let mut state = RunnerThreadState {
+                        emulator,
+                        renderer,
+                        audio_output,
+                        input_poller,
+                        save_writer,
+                        common_config,
+                        emulator_config,
+                        command_receiver,
+                        response_sender,
+                        error_sender,
+                        rom_path,
+                        rom_extension,
+                        base_save_state_path: save_state_path,
+                        save_state_paths,
+                        save_state_metadata,
+                        paused,
+                        step_frame: false,
+                        rewinder,
+                        change_disc_fn,
+                        remove_disc_fn,
+                        change_disk_side_fn,
+                        debugger_process: None,
+                        movie: None,
+                        movie_format,
+                        netplay: None,
+                        netplay_merge_fn,
+                        link_cable: None,
+                        link_cable_fn,
+                        take_printed_image_fn,
+                        camera_sensor,
+                        update_camera_fn,
+                    };
+                    state.apply_cheats();
+
+                    run_thread(state);
+
+                    log::info!("Runner thread has terminated");
+                }
+                Err(err) => {
+                    init_sender.send(Err(err)).unwrap();
+                }
             }
         })
     };
@@ -313,6 +628,11 @@ impl<Emulator: EmulatorTrait> RunnerThreadHandle<Emulator> {
         self.command_sender.send(command).map_err(|_| NativeEmulatorError::LostRunnerConnection)
     }
 
+    /// Clone the command sender, for sending commands to the runner thread from other threads.
+    pub fn clone_command_sender(&self) -> Sender<RunnerCommand<Emulator>> {
+        self.command_sender.clone()
+    }
+
     pub fn save_state_metadata(&self) -> &Arc<Mutex<SaveStateMetadata>> {
         &self.save_state_metadata
     }
@@ -330,7 +650,12 @@ fn run_thread<Emulator: EmulatorTrait>(mut state: RunnerThreadState<Emulator>) {
     loop {
         match handle_commands(&mut state) {
             Ok(CommandEffect::None) => {}
-            Ok(CommandEffect::Terminate) => return,
+            Ok(CommandEffect::Terminate) => {
+                state.stop_movie();
+                state.stop_netplay();
+                state.stop_link_cable();
+                return;
+            }
             Err(CommandError::ReloadConfig(err)) => {
                 log::error!("{}", CommandError::ReloadConfig(err));
             }
@@ -345,20 +670,45 @@ fn run_thread<Emulator: EmulatorTrait>(mut state: RunnerThreadState<Emulator>) {
 
         let should_run_emulator = !rewinding && (!paused || state.step_frame);
 
-        if should_run_emulator {
-            if let Err(err) = run_till_next_frame(&mut state) {
-                let _ = state.error_sender.send(err.into());
-                return;
+        let ran_frame = should_run_emulator && {
+            let run_frame = match state.prepare_netplay_frame() {
+                Ok(run_frame) => run_frame,
+                Err(err) => {
+                    let _ = state.error_sender.send(err.into());
+                    return;
+                }
+            };
+
+            if run_frame {
+                state.prepare_movie_frame();
+
+                if let Err(err) = run_till_next_frame(&mut state) {
+                    let _ = state.error_sender.send(err.into());
+                    return;
+                }
+
+                if state.netplay.is_none() {
+                    state.rewinder.record_frame(&state.emulator);
+
+                    state.audio_output.adjust_dynamic_resampling_ratio();
+                    state
+                        .emulator
+                        .update_audio_output_frequency(state.audio_output.output_frequency());
+                }
             }
 
-            state.rewinder.record_frame(&state.emulator);
-
-            state.audio_output.adjust_dynamic_resampling_ratio();
-            state.emulator.update_audio_output_frequency(state.audio_output.output_frequency());
-        }
+            run_frame
+        };
 
         state.step_frame = false;
 
+        state.update_link_cable();
+        state.save_printed_images();
+
+        if ran_frame {
+            state.update_camera();
+        }
+
         if rewinding
             && let Err(err) = state.rewinder.tick(
                 &mut state.emulator,
@@ -376,8 +726,8 @@ fn run_thread<Emulator: EmulatorTrait>(mut state: RunnerThreadState<Emulator>) {
             log::error!("Error updating debugger in runner thread: {err}");
         }
 
-        if !should_run_emulator {
-            // Don't spin loop when the emulator is paused or rewinding
+        if !ran_frame {
+            // Don't spin loop when the emulator is paused, rewinding, or waiting on a netplay peer
             thread::sleep(Duration::from_millis(1));
         }
     }
@@ -420,18 +770,29 @@ fn handle_command<Emulator: EmulatorTrait>(
         RunnerCommand::Terminate => {
             return Ok(CommandEffect::Terminate);
         }
-        RunnerCommand::SoftReset => {
-            state.emulator.soft_reset();
+        RunnerCommand::SoftReset | RunnerCommand::HardReset if state.netplay.is_some() => {
+            log::warn!("Ignoring reset during netplay");
         }
-        RunnerCommand::HardReset => {
-            state.emulator.hard_reset(&mut state.save_writer);
+        RunnerCommand::SoftReset => {
+            // Resets are applied through the movie so that they are recorded
+            match &mut state.movie {
+                Some(movie) => movie.queue_command(FrameCommand::SoftReset),
+                None => state.emulator.soft_reset(),
+            }
         }
+        RunnerCommand::HardReset => match &mut state.movie {
+            Some(movie) => movie.queue_command(FrameCommand::HardReset),
+            None => state.hard_reset(),
+        },
         RunnerCommand::ChangeDisc(path) => {
             change_disc(state, path)?;
         }
         RunnerCommand::RemoveDisc => {
             (state.remove_disc_fn)(&mut state.emulator);
         }
+        RunnerCommand::ChangeDiskSide => {
+            (state.change_disk_side_fn)(&mut state.emulator);
+        }
         RunnerCommand::StepFrame => {
             state.step_frame = true;
         }
@@ -442,7 +803,11 @@ fn handle_command<Emulator: EmulatorTrait>(
             state.audio_output.set_speed_multiplier(1);
         }
         RunnerCommand::Rewind { enabled: true } => {
-            state.rewinder.start_rewinding();
+            // Rewinding would desync a movie or netplay session; use save states to rerecord
+            // movies instead
+            if state.movie.is_none() && state.netplay.is_none() {
+                state.rewinder.start_rewinding();
+            }
         }
         RunnerCommand::Rewind { enabled: false } => {
             state.rewinder.stop_rewinding();
@@ -450,9 +815,64 @@ fn handle_command<Emulator: EmulatorTrait>(
         RunnerCommand::SaveState { slot } => {
             save_state(state, slot)?;
         }
+        RunnerCommand::LoadState { .. }
+        | RunnerCommand::RecordMovie { .. }
+        | RunnerCommand::PlayMovie { .. }
+            if state.netplay.is_some() =>
+        {
+            log::warn!("Ignoring state load or movie command during netplay");
+        }
         RunnerCommand::LoadState { slot } => {
             load_state(state, slot)?;
         }
+        RunnerCommand::RecordMovie { path, start } => {
+            record_movie(state, path, start)?;
+        }
+        RunnerCommand::PlayMovie { path, read_only } => {
+            play_movie(state, path, read_only)?;
+        }
+        RunnerCommand::StopMovie => {
+            if state.movie.is_some() {
+                state.stop_movie();
+                state
+                    .response_sender
+                    .send(RunnerCommandResponse::MovieStopped)
+                    .map_err(|_| CommandError::LostConnection)?;
+            }
+        }
+        RunnerCommand::ToggleMovieReadOnly => {
+            if let Some(movie) = &mut state.movie {
+                let read_only = movie.toggle_read_only();
+                state
+                    .response_sender
+                    .send(RunnerCommandResponse::MovieReadOnlyChanged { read_only })
+                    .map_err(|_| CommandError::LostConnection)?;
+            }
+        }
+        RunnerCommand::StartNetplay(config) => {
+            start_netplay(state, &config)?;
+        }
+        RunnerCommand::StopNetplay => {
+            if state.netplay.is_some() {
+                state.stop_netplay();
+                state
+                    .response_sender
+                    .send(RunnerCommandResponse::NetplayStopped { reason: None })
+                    .map_err(|_| CommandError::LostConnection)?;
+            }
+        }
+        RunnerCommand::StartLinkCable(config) => {
+            start_link_cable(state, &config)?;
+        }
+        RunnerCommand::StopLinkCable => {
+            if state.link_cable.is_some() {
+                state.stop_link_cable();
+                state
+                    .response_sender
+                    .send(RunnerCommandResponse::LinkCableStopped { reason: None })
+                    .map_err(|_| CommandError::LostConnection)?;
+            }
+        }
         RunnerCommand::ReloadConfig(configs) => {
             state.reload_configs(configs.0, configs.1).map_err(CommandError::ReloadConfig)?;
         }
@@ -484,20 +904,43 @@ fn run_till_next_frame<Emulator: EmulatorTrait>(
             &mut state.input_poller,
             &mut state.save_writer,
         ),
-        None => {
-            while state.emulator.tick(
-                &mut state.renderer,
-                &mut state.audio_output,
-                &mut state.input_poller,
-                &mut state.save_writer,
-            )? != TickEffect::FrameRendered
-            {}
-
-            Ok(())
-        }
+        // Movies start from empty saves, so saves written during a movie are discarded rather
+        // than overwriting the save files on disk
+        None if state.movie.is_some() => tick_till_next_frame(
+            &mut state.emulator,
+            &mut state.renderer,
+            &mut state.audio_output,
+            &mut state.input_poller,
+            &mut EmptySaveWriter,
+        ),
+        None => tick_till_next_frame(
+            &mut state.emulator,
+            &mut state.renderer,
+            &mut state.audio_output,
+            &mut state.input_poller,
+            &mut state.save_writer,
+        ),
     }
 }
 
+fn tick_till_next_frame<Emulator, S>(
+    emulator: &mut Emulator,
+    renderer: &mut ThreadedRenderer,
+    audio_output: &mut SdlAudioOutput,
+    input_poller: &mut ThreadedInputPoller<Emulator::Inputs>,
+    save_writer: &mut S,
+) -> Result<(), RunTillNextErr<Emulator>>
+where
+    Emulator: EmulatorTrait,
+    S: SaveWriter<Err = SaveWriteError>,
+{
+    while emulator.tick(renderer, audio_output, input_poller, save_writer)?
+        != TickEffect::FrameRendered
+    {}
+
+    Ok(())
+}
+
 fn save_state<Emulator: EmulatorTrait>(
     state: &mut RunnerThreadState<Emulator>,
     slot: usize,
@@ -507,6 +950,18 @@ fn save_state<Emulator: EmulatorTrait>(
         state::save(&state.emulator, &state.save_state_paths, slot, &mut save_state_metadata)
     };
 
+    if result.is_ok() {
+        let state_path = &state.save_state_paths[slot];
+        match &state.movie {
+            Some(movie) => {
+                if let Err(err) = movie.on_state_saved(state_path) {
+                    log::error!("Error saving movie snapshot for slot {slot}: {err}");
+                }
+            }
+            None => movie::remove_state_snapshot(state_path),
+        }
+    }
+
     let message = match result {
         Ok(()) => RunnerCommandResponse::SaveStateSucceeded { slot },
         Err(err) => RunnerCommandResponse::SaveStateFailed { slot, err },
@@ -522,6 +977,10 @@ fn load_state<Emulator: EmulatorTrait>(
     let result =
         state::load(&mut state.emulator, &state.emulator_config, &state.save_state_paths, slot);
 
+    if result.is_ok() {
+        update_movie_after_load(state, slot)?;
+    }
+
     let message = match result {
         Ok(()) => RunnerCommandResponse::LoadStateSucceeded { slot },
         Err(err) => RunnerCommandResponse::LoadStateFailed { slot, err },
@@ -530,6 +989,82 @@ fn load_state<Emulator: EmulatorTrait>(
     state.response_sender.send(message).map_err(|_| CommandError::LostConnection)
 }
 
+fn update_movie_after_load<Emulator: EmulatorTrait>(
+    state: &mut RunnerThreadState<Emulator>,
+    slot: usize,
+) -> Result<(), CommandError> {
+    let Some(movie) = &mut state.movie else { return Ok(()) };
+
+    let reason = match movie.on_state_loaded(&state.save_state_paths[slot]) {
+        StateLoadEffect::None => return Ok(()),
+        StateLoadEffect::Rerecord => {
+            log::info!("Movie rerecord count: {}", movie.rerecord_count());
+            return Ok(());
+        }
+        StateLoadEffect::Desync => "was not saved during the current movie",
+        StateLoadEffect::TimelineMismatch => "is not on the same timeline as the read-only movie",
+    };
+
+    log::warn!("State in slot {slot} {reason}; stopping movie");
+    state.stop_movie();
+    state
+        .response_sender
+        .send(RunnerCommandResponse::MovieStopped)
+        .map_err(|_| CommandError::LostConnection)?;
+
+    Ok(())
+}
+
+fn record_movie<Emulator: EmulatorTrait>(
+    state: &mut RunnerThreadState<Emulator>,
+    path: PathBuf,
+    start: MovieStartType,
+) -> Result<(), CommandError> {
+    let message = match state.start_movie_recording(path, start) {
+        Ok(()) => RunnerCommandResponse::MovieRecordingStarted,
+        Err(err) => RunnerCommandResponse::MovieFailed(err),
+    };
+
+    state.response_sender.send(message).map_err(|_| CommandError::LostConnection)
+}
+
+fn play_movie<Emulator: EmulatorTrait>(
+    state: &mut RunnerThreadState<Emulator>,
+    path: PathBuf,
+    read_only: bool,
+) -> Result<(), CommandError> {
+    let message = match state.start_movie_playback(path, read_only) {
+        Ok(()) => RunnerCommandResponse::MoviePlaybackStarted { read_only },
+        Err(err) => RunnerCommandResponse::MovieFailed(err),
+    };
+
+    state.response_sender.send(message).map_err(|_| CommandError::LostConnection)
+}
+
+fn start_netplay<Emulator: EmulatorTrait>(
+    state: &mut RunnerThreadState<Emulator>,
+    config: &NetplayConfig,
+) -> Result<(), CommandError> {
+    let message = match state.start_netplay(config) {
+        Ok(()) => RunnerCommandResponse::NetplayWaitingForPeer,
+        Err(err) => RunnerCommandResponse::NetplayFailed(err),
+    };
+
+    state.response_sender.send(message).map_err(|_| CommandError::LostConnection)
+}
+
+fn start_link_cable<Emulator: EmulatorTrait>(
+    state: &mut RunnerThreadState<Emulator>,
+    config: &LinkCableConfig,
+) -> Result<(), CommandError> {
+    let message = match state.start_link_cable(config) {
+        Ok(()) => RunnerCommandResponse::LinkCableWaitingForPeer,
+        Err(err) => RunnerCommandResponse::LinkCableFailed(err),
+    };
+
+    state.response_sender.send(message).map_err(|_| CommandError::LostConnection)
+}
+
 fn change_disc<Emulator: EmulatorTrait>(
     state: &mut RunnerThreadState<Emulator>,
     path: PathBuf,
@@ -80,6 +80,10 @@ impl FsSaveWriter {
         self.extension_to_paths.clear();
     }
 
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
     fn get_or_insert_paths(&mut self, extension: &str) -> &SavePaths {
         // Double get necessary to avoid borrow checker issues related to returning a reference
         if !self.extension_to_paths.contains_key(extension) {
@@ -175,6 +179,130 @@ impl SaveWriter for FsSaveWriter {
     }
 }
 
+/// Save writer that has no save files and discards all writes. Used when the emulator must start
+/// from a clean power-on state regardless of what saves exist on disk, e.g. for movies.
+pub struct EmptySaveWriter;
+
+impl EmptySaveWriter {
+    fn not_found(extension: &str) -> SaveWriteError {
+        SaveWriteError::ReadFile {
+            path: format!("(empty).{extension}"),
+            source: io::ErrorKind::NotFound.into(),
+        }
+    }
+}
+
+impl SaveWriter for EmptySaveWriter {
+    type Err = SaveWriteError;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        Err(Self::not_found(extension))
+    }
+
+    fn persist_bytes(&mut self, _extension: &str, _bytes: &[u8]) -> Result<(), Self::Err> {
+        Ok(())
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        Err(Self::not_found(extension))
+    }
+
+    fn persist_serialized<E: Encode>(
+        &mut self,
+        _extension: &str,
+        _data: E,
+    ) -> Result<(), Self::Err> {
+        Ok(())
+    }
+}
+
+/// Save writer that keeps all save files in memory and never reads or writes the filesystem. Starts
+/// with no save files. Used when runs must be reproducible regardless of what saves exist on disk,
+/// e.g. headless mode.
+#[derive(Debug, Default)]
+pub struct MemorySaveWriter {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySaveWriter {
+    fn path(extension: &str) -> String {
+        format!("(memory).{extension}")
+    }
+
+    fn get(&self, extension: &str) -> Result<&[u8], SaveWriteError> {
+        self.files.get(extension).map(Vec::as_slice).ok_or_else(|| SaveWriteError::ReadFile {
+            path: Self::path(extension),
+            source: io::ErrorKind::NotFound.into(),
+        })
+    }
+}
+
+impl SaveWriter for MemorySaveWriter {
+    type Err = SaveWriteError;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        self.get(extension).map(<[u8]>::to_vec)
+    }
+
+    fn persist_bytes(&mut self, extension: &str, bytes: &[u8]) -> Result<(), Self::Err> {
+        self.files.insert(extension.into(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        bincode::decode_from_slice(self.get(extension)?, bincode_config!())
+            .map(|(data, _)| data)
+            .map_err(|source| SaveWriteError::Decode { path: Self::path(extension), source })
+    }
+
+    fn persist_serialized<E: Encode>(&mut self, extension: &str, data: E) -> Result<(), Self::Err> {
+        let bytes = bincode::encode_to_vec(data, bincode_config!())
+            .map_err(|source| SaveWriteError::Encode { path: Self::path(extension), source })?;
+        self.files.insert(extension.into(), bytes);
+
+        Ok(())
+    }
+}
+
+/// Save writer that emulators are created with. Emulators normally load their saves from disk, but
+/// headless mode creates them from in-memory saves instead.
+pub enum CreateSaveWriter<'a> {
+    Fs(&'a mut FsSaveWriter),
+    Memory(&'a mut MemorySaveWriter),
+}
+
+impl SaveWriter for CreateSaveWriter<'_> {
+    type Err = SaveWriteError;
+
+    fn load_bytes(&mut self, extension: &str) -> Result<Vec<u8>, Self::Err> {
+        match self {
+            Self::Fs(save_writer) => save_writer.load_bytes(extension),
+            Self::Memory(save_writer) => save_writer.load_bytes(extension),
+        }
+    }
+
+    fn persist_bytes(&mut self, extension: &str, bytes: &[u8]) -> Result<(), Self::Err> {
+        match self {
+            Self::Fs(save_writer) => save_writer.persist_bytes(extension, bytes),
+            Self::Memory(save_writer) => save_writer.persist_bytes(extension, bytes),
+        }
+    }
+
+    fn load_serialized<D: Decode<()>>(&mut self, extension: &str) -> Result<D, Self::Err> {
+        match self {
+            Self::Fs(save_writer) => save_writer.load_serialized(extension),
+            Self::Memory(save_writer) => save_writer.load_serialized(extension),
+        }
+    }
+
+    fn persist_serialized<E: Encode>(&mut self, extension: &str, data: E) -> Result<(), Self::Err> {
+        match self {
+            Self::Fs(save_writer) => save_writer.persist_serialized(extension, data),
+            Self::Memory(save_writer) => save_writer.persist_serialized(extension, data),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RemoveDiscFromPath {
     No,
@@ -1,16 +1,33 @@
 use crate::config::SmsGgConfig;
 use std::fs;
 
-use crate::mainloop::save::FsSaveWriter;
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
+use crate::mainloop::save::CreateSaveWriter;
 use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, file_name_no_ext, save};
 use crate::{NativeEmulator, NativeEmulatorError, NativeEmulatorResult, extensions};
 
 use jgenesis_native_config::common::WindowSize;
+use smsgg_config::SmsGgInputs;
 use smsgg_core::{SmsGgEmulator, SmsGgHardware};
 use std::path::{Path, PathBuf};
 
 pub type NativeSmsGgEmulator = NativeEmulator<SmsGgEmulator>;
 
+// Peripheral inputs (Light Phaser, Paddle Control, Sports Pad) are not per-player and share a single
+// pointer position, so during netplay they are always taken from player 1's side
+fn merge_netplay_inputs(p1_inputs: &SmsGgInputs, p2_inputs: &SmsGgInputs) -> SmsGgInputs {
+    SmsGgInputs {
+        p1: p1_inputs.p1,
+        p2: p2_inputs.p1,
+        pause: p1_inputs.pause || p2_inputs.pause,
+        light_phaser_trigger: p1_inputs.light_phaser_trigger,
+        paddle_button: p1_inputs.paddle_button,
+        sports_pad_button_1: p1_inputs.sports_pad_button_1,
+        sports_pad_button_2: p1_inputs.sports_pad_button_2,
+        pointer_position: p1_inputs.pointer_position,
+    }
+}
+
 trait SmsGgHardwareExt: Sized + Copy {
     fn bios_path(self, config: &SmsGgConfig) -> Option<&PathBuf>;
 
@@ -82,6 +99,27 @@ impl NativeSmsGgEmulator {
 ///
 /// This function will propagate any video, audio, or disk errors encountered.
 pub fn create_smsgg(config: Box<SmsGgConfig>) -> NativeEmulatorResult<NativeSmsGgEmulator> {
+    launch_smsgg(config, NativeSmsGgEmulator::new)
+}
+
+/// Run the SMS/GG core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_smsgg(
+    config: Box<SmsGgConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_smsgg(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_smsgg<T>(
+    config: Box<SmsGgConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, SmsGgEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom: Option<Vec<u8>>;
@@ -151,7 +189,7 @@ pub fn create_smsgg(config: Box<SmsGgConfig>) -> NativeEmulatorResult<NativeSmsG
     let emulator_config = config.emulator_config;
     let initial_window_size = config.common.initial_window_size;
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let emulator = SmsGgEmulator::create(rom, bios_rom, hardware, emulator_config, save_writer);
 
         let window_title = match hardware {
@@ -172,7 +210,7 @@ pub fn create_smsgg(config: Box<SmsGgConfig>) -> NativeEmulatorResult<NativeSmsG
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    NativeSmsGgEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -183,6 +221,7 @@ pub fn create_smsgg(config: Box<SmsGgConfig>) -> NativeEmulatorResult<NativeSmsG
             config.inputs.to_mapping_vec(),
         )
         .with_turbo_mappings(config.inputs.to_turbo_mapping_vec())
+        .with_netplay_merge_fn(merge_netplay_inputs)
         .with_debug_fn(|| {
             jgenesis_debugger_frontend::partial_clone_debug_fn(
                 jgenesis_debugger_frontend::smsgg::render_fn(),
@@ -1,6 +1,7 @@
 use crate::config::SnesConfig;
 
-use crate::mainloop::save::{DeterminedPaths, FsSaveWriter};
+use crate::mainloop::headless::{self, HeadlessConfig, HeadlessReport};
+use crate::mainloop::save::{CreateSaveWriter, DeterminedPaths};
 use crate::mainloop::{CreatedEmulator, NativeEmulatorArgs, save};
 use crate::{NativeEmulator, NativeEmulatorResult, extensions};
 
@@ -9,7 +10,9 @@ use jgenesis_native_config::common::WindowSize;
 use jgenesis_native_config::input::mappings::SnesControllerType;
 use snes_config::SnesJoypadState;
 use snes_core::api::SnesEmulator;
-use snes_core::input::{SnesInputDevice, SnesInputs, SuperScopeState};
+use snes_core::input::{
+    JustifierState, SnesInputDevice, SnesInputs, SnesMouseState, SuperScopeState,
+};
 use std::path::Path;
 
 trait SnesControllerTypeExt {
@@ -21,12 +24,19 @@ impl SnesControllerTypeExt for SnesControllerType {
         match self {
             Self::Gamepad => SnesInputDevice::Controller(SnesJoypadState::default()),
             Self::SuperScope => SnesInputDevice::SuperScope(SuperScopeState::default()),
+            Self::Mouse => SnesInputDevice::Mouse(SnesMouseState::default()),
+            Self::Justifier => SnesInputDevice::Justifier(JustifierState::default()),
+            Self::Multitap => SnesInputDevice::Multitap([SnesJoypadState::default(); 4]),
         }
     }
 }
 
 pub type NativeSnesEmulator = NativeEmulator<SnesEmulator>;
 
+fn merge_netplay_inputs(p1_inputs: &SnesInputs, p2_inputs: &SnesInputs) -> SnesInputs {
+    SnesInputs { p1: p1_inputs.p1, p2: p2_inputs.p1 }
+}
+
 impl NativeSnesEmulator {
     /// # Errors
     ///
@@ -44,6 +54,7 @@ impl NativeSnesEmulator {
             &config.inputs.to_turbo_mapping_vec(),
             &config.common.hotkey_config.to_mapping_vec(),
         );
+        self.inputs.p1 = config.inputs.p1_type.to_input_device();
         self.inputs.p2 = config.inputs.p2_type.to_input_device();
 
         Ok(())
@@ -56,6 +67,27 @@ impl NativeSnesEmulator {
 ///
 /// This function will return an error if unable to initialize the emulator.
 pub fn create_snes(config: Box<SnesConfig>) -> NativeEmulatorResult<NativeSnesEmulator> {
+    launch_snes(config, NativeSnesEmulator::new)
+}
+
+/// Run the SNES core headless with the given config for a fixed number of frames, capturing its
+/// final frame, audio, and state.
+///
+/// # Errors
+///
+/// Propagates any errors encountered while initializing or running the emulator, or while writing
+/// output files.
+pub fn run_headless_snes(
+    config: Box<SnesConfig>,
+    headless_config: &HeadlessConfig,
+) -> NativeEmulatorResult<HeadlessReport> {
+    launch_snes(config, |args| headless::run(args, headless_config))
+}
+
+fn launch_snes<T>(
+    config: Box<SnesConfig>,
+    launch_fn: impl FnOnce(NativeEmulatorArgs<'_, '_, SnesEmulator>) -> NativeEmulatorResult<T>,
+) -> NativeEmulatorResult<T> {
     log::info!("Running with config: {config}");
 
     let rom_path = Path::new(&config.common.rom_file_path);
@@ -70,9 +102,15 @@ pub fn create_snes(config: Box<SnesConfig>) -> NativeEmulatorResult<NativeSnesEm
 
     let emulator_config = config.emulator_config;
     let initial_window_size = config.common.initial_window_size;
-    let coprocessor_roms = config.to_coprocessor_roms();
+    let mut coprocessor_roms = config.to_coprocessor_roms();
 
-    let create_emulator_fn = move |save_writer: &mut FsSaveWriter| {
+    // MSU-1 is enabled if there is a .msu data file next to the ROM file
+    let msu1_data_path = rom_path.with_extension("msu");
+    if msu1_data_path.is_file() {
+        coprocessor_roms.msu1_data_path = Some(msu1_data_path);
+    }
+
+    let create_emulator_fn = move |save_writer: &mut CreateSaveWriter<'_>| {
         let mut emulator =
             SnesEmulator::create(rom, emulator_config, coprocessor_roms, save_writer)?;
 
@@ -85,10 +123,12 @@ pub fn create_snes(config: Box<SnesConfig>) -> NativeEmulatorResult<NativeSnesEm
         Ok(CreatedEmulator { emulator, window_title, default_window_size })
     };
 
-    let initial_inputs =
-        SnesInputs { p1: SnesJoypadState::default(), p2: config.inputs.p2_type.to_input_device() };
+    let initial_inputs = SnesInputs {
+        p1: config.inputs.p1_type.to_input_device(),
+        p2: config.inputs.p2_type.to_input_device(),
+    };
 
-    NativeSnesEmulator::new(
+    launch_fn(
         NativeEmulatorArgs::new(
             Box::new(create_emulator_fn),
             emulator_config,
@@ -100,6 +140,7 @@ pub fn create_snes(config: Box<SnesConfig>) -> NativeEmulatorResult<NativeSnesEm
         )
         .with_turbo_mappings(config.inputs.to_turbo_mapping_vec())
         .with_initial_inputs(initial_inputs)
+        .with_netplay_merge_fn(merge_netplay_inputs)
         .with_debug_fn(|| {
             jgenesis_debugger_frontend::partial_clone_debug_fn(
                 jgenesis_debugger_frontend::snes::render_fn(),
@@ -151,12 +151,38 @@ pub fn load<Emulator: EmulatorTrait>(
     reader.seek(SeekFrom::Start(total_header_len)).map_err(NativeEmulatorError::SaveStateIo)?;
     let mut decoder =
         zstd::stream::Decoder::new(reader).map_err(NativeEmulatorError::LoadStateIo)?;
-    let mut loaded_emulator: Emulator =
-        bincode::decode_from_std_read(&mut decoder, bincode_config!())?;
+    let loaded_emulator: Emulator = bincode::decode_from_std_read(&mut decoder, bincode_config!())?;
 
+    replace_emulator(emulator, loaded_emulator, config);
+
+    Ok(())
+}
+
+/// Serialize emulator state to an in-memory buffer, e.g. for the start of an input movie.
+pub fn save_to_bytes<Emulator: EmulatorTrait>(
+    emulator: &Emulator,
+) -> NativeEmulatorResult<Vec<u8>> {
+    Ok(bincode::encode_to_vec(emulator, bincode_config!())?)
+}
+
+/// Load emulator state from a buffer produced by [`save_to_bytes`].
+pub fn load_from_bytes<Emulator: EmulatorTrait>(
+    emulator: &mut Emulator,
+    config: &Emulator::Config,
+    bytes: &[u8],
+) -> NativeEmulatorResult<()> {
+    let (loaded_emulator, _) = bincode::decode_from_slice(bytes, bincode_config!())?;
+    replace_emulator(emulator, loaded_emulator, config);
+
+    Ok(())
+}
+
+pub fn replace_emulator<Emulator: EmulatorTrait>(
+    emulator: &mut Emulator,
+    mut loaded_emulator: Emulator,
+    config: &Emulator::Config,
+) {
     loaded_emulator.take_rom_from(emulator);
     *emulator = loaded_emulator;
     emulator.reload_config(config);
-
-    Ok(())
 }
@@ -0,0 +1,643 @@
+//! ROM soft-patching support for IPS, BPS, and UPS patch files
+//!
+//! Patches are applied in memory at ROM load time; the ROM file on disk is never modified.
+
+use crate::extensions;
+use crc::Crc;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+use thiserror::Error;
+
+const CRC: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: u32 = u32::from_be_bytes([0, b'E', b'O', b'F']);
+
+const BPS_MAGIC: &[u8] = b"BPS1";
+const UPS_MAGIC: &[u8] = b"UPS1";
+
+// BPS action commands
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+// Source CRC32 + target CRC32 + patch CRC32
+const BPS_UPS_FOOTER_LEN: usize = 12;
+
+// Larger than any supported ROM (GBA ROMs are at most 32MB); guards against malformed or malicious
+// patches requesting huge allocations
+const MAX_TARGET_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+    Ups,
+}
+
+impl PatchFormat {
+    pub const ALL: [Self; 3] = [Self::Ips, Self::Bps, Self::Ups];
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ips => "ips",
+            Self::Bps => "bps",
+            Self::Ups => "ups",
+        }
+    }
+
+    #[must_use]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = extensions::from_path(path)?;
+        Self::ALL.into_iter().find(|format| format.extension() == extension)
+    }
+}
+
+impl Display for PatchFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ips => write!(f, "IPS"),
+            Self::Bps => write!(f, "BPS"),
+            Self::Ups => write!(f, "UPS"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumKind {
+    Source,
+    Target,
+    Patch,
+}
+
+impl Display for ChecksumKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source => write!(f, "source ROM"),
+            Self::Target => write!(f, "patched ROM"),
+            Self::Patch => write!(f, "patch file"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("Failed to read patch file: {0}")]
+    Read(#[source] io::Error),
+    #[error("Unsupported patch file extension for path '{0}'; expected .ips, .bps, or .ups")]
+    UnsupportedExtension(String),
+    #[error("{format} patch does not start with the expected header")]
+    InvalidHeader { format: PatchFormat },
+    #[error("{format} patch ended unexpectedly")]
+    UnexpectedEof { format: PatchFormat },
+    #[error("{format} patch contains an invalid variable-length integer")]
+    InvalidNumber { format: PatchFormat },
+    #[error(
+        "{format} patch expects a source ROM of {expected} bytes, but the ROM is {actual} bytes"
+    )]
+    SourceSizeMismatch { format: PatchFormat, expected: usize, actual: usize },
+    #[error(
+        "{format} patch produces a ROM of {size} bytes, which is larger than the maximum of {} bytes",
+        MAX_TARGET_SIZE
+    )]
+    TargetTooLarge { format: PatchFormat, size: usize },
+    #[error(
+        "{format} patch {kind} CRC32 mismatch: expected {expected:08X}, calculated {actual:08X}"
+    )]
+    ChecksumMismatch { format: PatchFormat, kind: ChecksumKind, expected: u32, actual: u32 },
+    #[error("BPS patch contains a copy command that reads out of bounds")]
+    BpsCopyOutOfBounds,
+    #[error("BPS patch writes past the end of the target ROM")]
+    BpsTargetOverflow,
+}
+
+/// Look for a patch file next to the given ROM file that has the same file name and an `.ips`,
+/// `.bps`, or `.ups` extension, e.g. `game.ips` for `game.sfc` or `game.zip`.
+#[must_use]
+pub fn find_patch_file(rom_path: &Path) -> Option<PathBuf> {
+    PatchFormat::ALL
+        .into_iter()
+        .map(|format| rom_path.with_extension(format.extension()))
+        .find(|patch_path| patch_path != rom_path && patch_path.is_file())
+}
+
+/// Read the patch file at the given path and apply it to the given ROM, with the format determined
+/// by the patch file's extension.
+///
+/// # Errors
+///
+/// Returns an error if the patch file cannot be read or is malformed, or if any of the patch's
+/// checksums do not match.
+pub fn read_and_apply(rom: &[u8], patch_path: &Path) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::from_path(patch_path)
+        .ok_or_else(|| PatchError::UnsupportedExtension(patch_path.display().to_string()))?;
+
+    let patch = fs::read(patch_path).map_err(PatchError::Read)?;
+
+    apply(rom, &patch, format)
+}
+
+/// Apply a patch in the given format to the given ROM, returning the patched ROM.
+///
+/// # Errors
+///
+/// Returns an error if the patch is malformed or if any of the patch's checksums do not match.
+pub fn apply(rom: &[u8], patch: &[u8], format: PatchFormat) -> Result<Vec<u8>, PatchError> {
+    match format {
+        PatchFormat::Ips => apply_ips(rom, patch),
+        PatchFormat::Bps => apply_bps(rom, patch),
+        PatchFormat::Ups => apply_ups(rom, patch),
+    }
+}
+
+struct PatchReader<'a> {
+    format: PatchFormat,
+    patch: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(format: PatchFormat, patch: &'a [u8], magic: &[u8]) -> Result<Self, PatchError> {
+        if !patch.starts_with(magic) {
+            return Err(PatchError::InvalidHeader { format });
+        }
+
+        Ok(Self { format, patch, position: magic.len() })
+    }
+
+    fn eof(&self) -> PatchError {
+        PatchError::UnexpectedEof { format: self.format }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.position.checked_add(len).ok_or_else(|| self.eof())?;
+        let bytes = self.patch.get(self.position..end).ok_or_else(|| self.eof())?;
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u24_be(&mut self) -> Result<u32, PatchError> {
+        let bytes = self.read_bytes(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    // BPS and UPS variable-length integer encoding: 7 bits per byte, least significant first, with
+    // the highest bit set on the final byte. Each continuation byte implicitly adds 1 to the next
+    // group so that every number has exactly one encoding
+    fn read_number(&mut self) -> Result<usize, PatchError> {
+        let format = self.format;
+        let invalid = || PatchError::InvalidNumber { format };
+
+        let mut value: u64 = 0;
+        let mut shift: u64 = 1;
+        loop {
+            let byte = self.read_u8()?;
+            value = u64::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|group| value.checked_add(group))
+                .ok_or_else(invalid)?;
+            if byte & 0x80 != 0 {
+                break;
+            }
+
+            shift = shift.checked_mul(1 << 7).ok_or_else(invalid)?;
+            value = value.checked_add(shift).ok_or_else(invalid)?;
+        }
+
+        usize::try_from(value).map_err(|_| invalid())
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut reader = PatchReader::new(PatchFormat::Ips, patch, IPS_MAGIC)?;
+    let mut output = rom.to_vec();
+
+    loop {
+        let offset = reader.read_u24_be()?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = offset as usize;
+
+        let len = reader.read_u16_be()?;
+        if len != 0 {
+            let data = reader.read_bytes(len.into())?;
+            write_ips_record(&mut output, offset, data.len(), |dest| dest.copy_from_slice(data));
+        } else {
+            // RLE record
+            let rle_len = reader.read_u16_be()?;
+            let value = reader.read_u8()?;
+            write_ips_record(&mut output, offset, rle_len.into(), |dest| dest.fill(value));
+        }
+    }
+
+    // Some IPS patches include a 24-bit truncation length after the EOF marker
+    if let Ok(truncate_len) = reader.read_u24_be() {
+        output.truncate(truncate_len as usize);
+    }
+
+    Ok(output)
+}
+
+fn write_ips_record(
+    output: &mut Vec<u8>,
+    offset: usize,
+    len: usize,
+    write_fn: impl FnOnce(&mut [u8]),
+) {
+    let end = offset + len;
+    if end > output.len() {
+        output.resize(end, 0);
+    }
+
+    write_fn(&mut output[offset..end]);
+}
+
+// Verifies the patch and source ROM checksums, returning the expected target ROM checksum
+fn verify_footer(format: PatchFormat, rom: &[u8], patch: &[u8]) -> Result<u32, PatchError> {
+    if patch.len() < BPS_UPS_FOOTER_LEN {
+        return Err(PatchError::UnexpectedEof { format });
+    }
+
+    let footer = &patch[patch.len() - BPS_UPS_FOOTER_LEN..];
+    let read_crc = |i: usize| u32::from_le_bytes(footer[i..i + 4].try_into().unwrap());
+    let source_crc = read_crc(0);
+    let target_crc = read_crc(4);
+    let patch_crc = read_crc(8);
+
+    verify_checksum(
+        format,
+        ChecksumKind::Patch,
+        patch_crc,
+        CRC.checksum(&patch[..patch.len() - 4]),
+    )?;
+    verify_checksum(format, ChecksumKind::Source, source_crc, CRC.checksum(rom))?;
+
+    Ok(target_crc)
+}
+
+fn verify_checksum(
+    format: PatchFormat,
+    kind: ChecksumKind,
+    expected: u32,
+    actual: u32,
+) -> Result<(), PatchError> {
+    if expected != actual {
+        return Err(PatchError::ChecksumMismatch { format, kind, expected, actual });
+    }
+
+    Ok(())
+}
+
+fn verify_source_size(format: PatchFormat, rom: &[u8], expected: usize) -> Result<(), PatchError> {
+    if rom.len() != expected {
+        return Err(PatchError::SourceSizeMismatch { format, expected, actual: rom.len() });
+    }
+
+    Ok(())
+}
+
+fn verify_target_size(format: PatchFormat, size: usize) -> Result<(), PatchError> {
+    if size > MAX_TARGET_SIZE {
+        return Err(PatchError::TargetTooLarge { format, size });
+    }
+
+    Ok(())
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::Bps;
+    let target_crc = verify_footer(format, rom, patch)?;
+    let actions = &patch[..patch.len() - BPS_UPS_FOOTER_LEN];
+
+    let mut reader = PatchReader::new(format, actions, BPS_MAGIC)?;
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+    let metadata_size = reader.read_number()?;
+    reader.read_bytes(metadata_size)?;
+
+    verify_source_size(format, rom, source_size)?;
+    verify_target_size(format, target_size)?;
+
+    let mut output: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+
+    while reader.position < actions.len() {
+        let data = reader.read_number()?;
+        let command = data & 3;
+        let len = (data >> 2) + 1;
+
+        if output.len() + len > target_size {
+            return Err(PatchError::BpsTargetOverflow);
+        }
+
+        match command {
+            SOURCE_READ => {
+                let start = output.len();
+                let bytes = rom.get(start..start + len).ok_or(PatchError::BpsCopyOutOfBounds)?;
+                output.extend_from_slice(bytes);
+            }
+            TARGET_READ => {
+                output.extend_from_slice(reader.read_bytes(len)?);
+            }
+            SOURCE_COPY => {
+                source_offset = apply_relative_offset(source_offset, reader.read_number()?)?;
+                let end = source_offset.checked_add(len).ok_or(PatchError::BpsCopyOutOfBounds)?;
+                let bytes = rom.get(source_offset..end).ok_or(PatchError::BpsCopyOutOfBounds)?;
+                output.extend_from_slice(bytes);
+                source_offset = end;
+            }
+            TARGET_COPY => {
+                target_offset = apply_relative_offset(target_offset, reader.read_number()?)?;
+                if target_offset >= output.len() {
+                    return Err(PatchError::BpsCopyOutOfBounds);
+                }
+
+                // Copies can overlap the bytes being written, so this must be done byte-by-byte
+                for _ in 0..len {
+                    output.push(output[target_offset]);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!("value & 3 is always <= 3"),
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(PatchError::UnexpectedEof { format });
+    }
+
+    verify_checksum(format, ChecksumKind::Target, target_crc, CRC.checksum(&output))?;
+
+    Ok(output)
+}
+
+// BPS relative offsets store the sign in the lowest bit
+fn apply_relative_offset(offset: usize, encoded: usize) -> Result<usize, PatchError> {
+    let magnitude = encoded >> 1;
+    let new_offset = if encoded & 1 != 0 {
+        offset.checked_sub(magnitude)
+    } else {
+        offset.checked_add(magnitude)
+    };
+
+    new_offset.ok_or(PatchError::BpsCopyOutOfBounds)
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let format = PatchFormat::Ups;
+    let target_crc = verify_footer(format, rom, patch)?;
+    let hunks = &patch[..patch.len() - BPS_UPS_FOOTER_LEN];
+
+    let mut reader = PatchReader::new(format, hunks, UPS_MAGIC)?;
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+
+    verify_source_size(format, rom, source_size)?;
+    verify_target_size(format, target_size)?;
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+
+    // Each hunk is a relative offset followed by bytes to XOR against the source, terminated by a
+    // zero byte; the terminator also skips over one unchanged byte
+    let mut position: usize = 0;
+    while reader.position < hunks.len() {
+        position = position.saturating_add(reader.read_number()?);
+
+        loop {
+            let xor = reader.read_u8()?;
+            if xor == 0 {
+                position = position.saturating_add(1);
+                break;
+            }
+
+            if let Some(byte) = output.get_mut(position) {
+                *byte ^= xor;
+            }
+            position = position.saturating_add(1);
+        }
+    }
+
+    verify_checksum(format, ChecksumKind::Target, target_crc, CRC.checksum(&output))?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_number(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte | 0x80);
+                return;
+            }
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn bps_action(command: usize, len: usize) -> usize {
+        ((len - 1) << 2) | command
+    }
+
+    fn append_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+        patch.extend(CRC.checksum(source).to_le_bytes());
+        patch.extend(CRC.checksum(target).to_le_bytes());
+        patch.extend(CRC.checksum(patch).to_le_bytes());
+    }
+
+    #[test]
+    fn number_round_trip() {
+        for value in [0, 1, 127, 128, 129, 16511, 16512, 0x12345678] {
+            let mut encoded = BPS_MAGIC.to_vec();
+            encode_number(value, &mut encoded);
+
+            let mut reader = PatchReader::new(PatchFormat::Bps, &encoded, BPS_MAGIC).unwrap();
+            assert_eq!(reader.read_number().unwrap(), value);
+            assert_eq!(reader.position, encoded.len());
+        }
+    }
+
+    #[test]
+    fn ips() {
+        let rom = [0, 1, 2, 3, 4, 5, 6, 7];
+
+        let mut patch = IPS_MAGIC.to_vec();
+        // Normal record at $000002
+        patch.extend([0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);
+        // RLE record at $000006 that extends past the end of the ROM
+        patch.extend([0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0xCC]);
+        patch.extend(b"EOF");
+
+        assert_eq!(
+            apply(&rom, &patch, PatchFormat::Ips).unwrap(),
+            vec![0, 1, 0xAA, 0xBB, 4, 5, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+
+        // Truncation extension
+        patch.extend([0x00, 0x00, 0x03]);
+        assert_eq!(apply(&rom, &patch, PatchFormat::Ips).unwrap(), vec![0, 1, 0xAA]);
+
+        assert!(matches!(
+            apply(&rom, &patch[..patch.len() - 6], PatchFormat::Ips),
+            Err(PatchError::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            apply(&rom, b"NOTAPATCH", PatchFormat::Ips),
+            Err(PatchError::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn bps() {
+        let source = b"ABCDEFGH";
+        let target = b"ABXYABXYABFGH!";
+
+        let mut patch = BPS_MAGIC.to_vec();
+        encode_number(source.len(), &mut patch);
+        encode_number(target.len(), &mut patch);
+        encode_number(0, &mut patch);
+        // SourceRead "AB"
+        encode_number(bps_action(SOURCE_READ, 2), &mut patch);
+        // TargetRead "XY"
+        encode_number(bps_action(TARGET_READ, 2), &mut patch);
+        patch.extend(b"XY");
+        // TargetCopy "ABXYAB" from target offset 0, overlapping the bytes being written
+        encode_number(bps_action(TARGET_COPY, 6), &mut patch);
+        encode_number(0, &mut patch);
+        // SourceCopy "FGH" from source offset 5
+        encode_number(bps_action(SOURCE_COPY, 3), &mut patch);
+        encode_number(5 << 1, &mut patch);
+        // TargetRead "!"
+        encode_number(bps_action(TARGET_READ, 1), &mut patch);
+        patch.push(b'!');
+        append_footer(&mut patch, source, target);
+
+        assert_eq!(apply(source, &patch, PatchFormat::Bps).unwrap(), target.to_vec());
+
+        assert!(matches!(
+            apply(b"ABCDEFGI", &patch, PatchFormat::Bps),
+            Err(PatchError::ChecksumMismatch { kind: ChecksumKind::Source, .. })
+        ));
+
+        let mut corrupted = patch.clone();
+        corrupted[7] ^= 0xFF;
+        assert!(matches!(
+            apply(source, &corrupted, PatchFormat::Bps),
+            Err(PatchError::ChecksumMismatch { kind: ChecksumKind::Patch, .. })
+        ));
+    }
+
+    #[test]
+    fn ups() {
+        let source = b"ABCDEFGH";
+        let target = b"AbCDEfGH??";
+
+        let mut patch = UPS_MAGIC.to_vec();
+        encode_number(source.len(), &mut patch);
+        encode_number(target.len(), &mut patch);
+        // Hunk at offset 1
+        encode_number(1, &mut patch);
+        patch.extend([b'B' ^ b'b', 0]);
+        // Hunk at offset 5 (terminator of previous hunk skipped offset 2)
+        encode_number(2, &mut patch);
+        patch.extend([b'F' ^ b'f', 0]);
+        // Hunk at offset 8, past the end of the source ROM
+        encode_number(1, &mut patch);
+        patch.extend([b'?', b'?', 0]);
+        append_footer(&mut patch, source, target);
+
+        assert_eq!(apply(source, &patch, PatchFormat::Ups).unwrap(), target.to_vec());
+
+        assert!(matches!(
+            apply(b"ABCDEFG", &patch, PatchFormat::Ups),
+            Err(PatchError::ChecksumMismatch { kind: ChecksumKind::Source, .. })
+        ));
+    }
+
+    fn patch_with_sizes(magic: &[u8], source: &[u8], target_size: usize) -> Vec<u8> {
+        let mut patch = magic.to_vec();
+        encode_number(source.len(), &mut patch);
+        encode_number(target_size, &mut patch);
+        if magic == BPS_MAGIC {
+            // Metadata size
+            encode_number(0, &mut patch);
+        }
+        patch
+    }
+
+    #[test]
+    fn target_size_limit() {
+        let source = b"ABCDEFGH";
+
+        for (magic, format) in [(BPS_MAGIC, PatchFormat::Bps), (UPS_MAGIC, PatchFormat::Ups)] {
+            for target_size in [MAX_TARGET_SIZE + 1, usize::MAX >> 1] {
+                let mut patch = patch_with_sizes(magic, source, target_size);
+                append_footer(&mut patch, source, source);
+
+                assert!(
+                    matches!(
+                        apply(source, &patch, format),
+                        Err(PatchError::TargetTooLarge { size, .. }) if size == target_size
+                    ),
+                    "{format} {target_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bps_out_of_bounds_copies() {
+        let source = b"ABCDEFGH";
+
+        let apply_action = |command: usize, len: usize, encoded_offset: usize| {
+            let mut patch = patch_with_sizes(BPS_MAGIC, source, 8);
+            encode_number(bps_action(command, len), &mut patch);
+            encode_number(encoded_offset, &mut patch);
+            append_footer(&mut patch, source, source);
+            apply(source, &patch, PatchFormat::Bps)
+        };
+
+        // Source copy past the end of the source ROM
+        assert!(matches!(
+            apply_action(SOURCE_COPY, 4, 6 << 1),
+            Err(PatchError::BpsCopyOutOfBounds)
+        ));
+
+        // Source copy with a huge offset
+        assert!(matches!(
+            apply_action(SOURCE_COPY, 8, usize::MAX & !1),
+            Err(PatchError::BpsCopyOutOfBounds)
+        ));
+
+        // Negative offset before the start of the ROM
+        assert!(matches!(
+            apply_action(SOURCE_COPY, 1, (1 << 1) | 1),
+            Err(PatchError::BpsCopyOutOfBounds)
+        ));
+
+        // Target copy before any bytes have been written
+        assert!(matches!(apply_action(TARGET_COPY, 1, 0), Err(PatchError::BpsCopyOutOfBounds)));
+
+        // Action longer than the target ROM
+        assert!(matches!(apply_action(SOURCE_COPY, 9, 0), Err(PatchError::BpsTargetOverflow)));
+    }
+}
@@ -15,7 +15,9 @@ use jgenesis_renderer::config::{
 use s32x_core::api::Sega32XEmulatorConfig;
 use segacd_core::api::SegaCdEmulatorConfig;
 use serde::{Deserialize, Serialize};
-use smsgg_config::{GgAspectRatio, SmsAspectRatio, SmsGgButton, SmsGgInputs, SmsModel};
+use smsgg_config::{
+    GgAspectRatio, SmsAspectRatio, SmsGgButton, SmsGgControllerType, SmsGgInputs, SmsModel,
+};
 use smsgg_core::SmsGgEmulatorConfig;
 use snes_config::{AudioInterpolationMode, SnesAspectRatio, SnesButton};
 use snes_core::api::SnesEmulatorConfig;
@@ -103,6 +105,8 @@ impl SmsGgWebConfig {
             gg_use_sms_resolution: false,
             fm_sound_unit_enabled: self.fm_unit_enabled,
             z80_divider: NonZeroU32::new(smsgg_core::NATIVE_Z80_DIVIDER).unwrap(),
+            p1_controller_type: SmsGgControllerType::default(),
+            p2_controller_type: SmsGgControllerType::default(),
         }
     }
 }
@@ -834,13 +834,8 @@ async fn open_file(event_loop_proxy: EventLoopProxy<JgenesisUserEvent>) {
             };
             Some(bios_rom)
         }
-        Some("gba") => {
-            let Some(bios_rom) = read_bios_from_idb(GBA_BIOS_KEY).await else {
-                js::alert("GBA emulation requires a GBA BIOS ROM to be configured");
-                return;
-            };
-            Some(bios_rom)
-        }
+        // Uses the built-in BIOS if no GBA BIOS is configured
+        Some("gba") => read_bios_from_idb(GBA_BIOS_KEY).await,
         _ => None,
     };
 
@@ -883,7 +878,6 @@ async fn upload_save_file(event_loop_proxy: EventLoopProxy<JgenesisUserEvent>) {
 
 enum OpenEmulatorError {
     NoSegaCdBios,
-    NoGbaBios,
     Other(Box<dyn Error>),
 }
 
@@ -893,7 +887,6 @@ impl Display for OpenEmulatorError {
             Self::NoSegaCdBios => {
                 write!(f, "No Sega CD BIOS is configured; required for Sega CD emulation")
             }
-            Self::NoGbaBios => write!(f, "No GBA BIOS is configured; required for GBA emulation"),
             Self::Other(err) => write!(f, "{err}"),
         }
     }
@@ -967,6 +960,7 @@ fn open_emulator(
 
             let emulator = GenesisEmulator::create(
                 rom,
+                None,
                 config_ref.borrow().genesis.to_emulator_config(),
                 save_writer,
             );
@@ -1014,13 +1008,9 @@ fn open_emulator(
             Ok(Emulator::Snes(emulator, SnesInputs::default()))
         }
         "gba" => {
-            let Some(bios) = bios_rom else {
-                return Err(OpenEmulatorError::NoGbaBios);
-            };
-
             let emulator = GameBoyAdvanceEmulator::create(
                 rom,
-                bios,
+                bios_rom,
                 config_ref.borrow().gba.to_emulator_config(),
                 save_writer,
             )